@@ -1,6 +1,8 @@
 //! This module contains a basic axis-aligned bounding box (AABB) implementation.
 //! It is mostly used in [`bvh`](crate::bvh) for constructing and using [`BVHTree`](crate::bvh::BVHTree).
 
+use multiversion::multiversion;
+
 use crate::{
     interval::Interval,
     ray::Ray,
@@ -288,6 +290,50 @@ impl Aabb {
         intersection_t.filter(|&t| t > 0.)
     }
 
+    /// Get the range of `t` over which `ray` is inside `self`, using the slab
+    /// method. Unlike [`Aabb::ray_hit()`], the range may start behind the
+    /// origin of the [ray](Ray).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, interval, interval::Interval, ray, ray::Ray, vec3, vec3::Vec3};
+    ///
+    /// let aabb = Aabb {
+    ///     x: interval![0.0, 1.0],
+    ///     y: interval![0.0, 1.0],
+    ///     z: interval![0.0, 1.0],
+    /// };
+    ///
+    /// let through = ray!(vec3![-1.0, 0.5, 0.5], vec3![1.0, 0.0, 0.0]);
+    /// assert_eq!(aabb.ray_interval(&through), Some(interval![1.0, 2.0]));
+    ///
+    /// let miss = ray!(vec3![-1.0, 2.0, 0.5], vec3![1.0, 0.0, 0.0]);
+    /// assert_eq!(aabb.ray_interval(&miss), None);
+    /// ```
+    #[must_use]
+    pub fn ray_interval(&self, ray: &Ray) -> Option<Interval> {
+        let mut span = Interval::universe();
+        for (axis, slab) in [self.x, self.y, self.z].iter().enumerate() {
+            let inv_d = 1.0 / ray.direction[axis];
+            let t0 = (slab.min - ray.origin[axis]) * inv_d;
+            let t1 = (slab.max - ray.origin[axis]) * inv_d;
+            let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            // parallel rays outside the slab give NaN or an empty range
+            if near.is_nan() || far.is_nan() {
+                if slab.contains(ray.origin[axis]) {
+                    continue;
+                }
+                return None;
+            }
+            span.min = span.min.max(near);
+            span.max = span.max.min(far);
+        }
+
+        (span.min <= span.max).then_some(span)
+    }
+
     /// Calculate the centroid of `self`.
     ///
     /// # Example
@@ -331,6 +377,121 @@ impl Aabb {
         let z = self.z.size();
         2. * (x * y + y * z + z * x)
     }
+
+    /// Get a copy of `self` moved by `offset`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, interval, interval::Interval, vec3, vec3::Vec3};
+    ///
+    /// let aabb = Aabb {
+    ///     x: interval![0.0, 1.0],
+    ///     y: interval![0.0, 1.0],
+    ///     z: interval![0.0, 1.0],
+    /// };
+    ///
+    /// let moved = aabb.translated(vec3![1.0, 0.0, -1.0]);
+    ///
+    /// assert_eq!(moved.x, interval![1.0, 2.0]);
+    /// assert_eq!(moved.z, interval![-1.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn translated(&self, offset: Vec3) -> Aabb {
+        let shift = |axis: Interval, by: f64| Interval::new(axis.min + by, axis.max + by);
+        Aabb {
+            x: shift(self.x, offset[0]),
+            y: shift(self.y, offset[1]),
+            z: shift(self.z, offset[2]),
+        }
+    }
+
+    /// Check if `self` extends infinitely along any axis, such as the bounds
+    /// of an infinite plane. Empty bounds aren't unbounded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, interval, interval::Interval};
+    ///
+    /// let floor = Aabb {
+    ///     x: Interval::universe(),
+    ///     y: interval![0.0, 0.0],
+    ///     z: Interval::universe(),
+    /// };
+    ///
+    /// assert!(floor.is_unbounded());
+    /// assert!(!Aabb::new().is_unbounded());
+    /// ```
+    #[must_use]
+    pub fn is_unbounded(&self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .any(|axis| axis.size() == f64::INFINITY)
+    }
+
+    /// Get a copy of `self` with any axis thinner than `min_size` grown to
+    /// `min_size` about its middle, so flat primitives such as quads still
+    /// have some volume for the slab tests and splitting in a
+    /// [`BVHTree`](crate::bvh::BVHTree).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, interval, interval::Interval};
+    ///
+    /// let flat = Aabb {
+    ///     x: interval![0.0, 1.0],
+    ///     y: interval![2.0, 2.0],
+    ///     z: interval![0.0, 1.0],
+    /// };
+    ///
+    /// let padded = flat.padded(1e-4);
+    /// assert_eq!(padded.x, interval![0.0, 1.0]);
+    /// assert_eq!(padded.y, interval![2.0 - 5e-5, 2.0 + 5e-5]);
+    /// ```
+    #[must_use]
+    pub fn padded(&self, min_size: f64) -> Aabb {
+        let pad = |axis: Interval| {
+            if axis.size() < min_size {
+                let half = 0.5 * min_size;
+                Interval::new(axis.mid() - half, axis.mid() + half)
+            } else {
+                axis
+            }
+        };
+        Aabb {
+            x: pad(self.x),
+            y: pad(self.y),
+            z: pad(self.z),
+        }
+    }
+
+    /// Calculate the squared distance from `point` to the closest point in
+    /// `self`, which is `0.0` if `self` contains `point`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, interval, interval::Interval, vec3, vec3::Vec3};
+    ///
+    /// let aabb = Aabb {
+    ///     x: interval![0.0, 1.0],
+    ///     y: interval![0.0, 1.0],
+    ///     z: interval![0.0, 1.0],
+    /// };
+    ///
+    /// assert_eq!(aabb.distance_squared(vec3![0.5, 0.5, 0.5]), 0.0);
+    /// assert_eq!(aabb.distance_squared(vec3![3.0, 0.5, -1.0]), 5.0);
+    /// ```
+    #[must_use]
+    pub fn distance_squared(&self, point: Vec3) -> f64 {
+        [self.x, self.y, self.z]
+            .iter()
+            .zip(point.e)
+            .map(|(axis, p)| (p - axis.clamp(p)).powi(2))
+            .sum()
+    }
 }
 
 impl PartialEq for Aabb {
@@ -338,3 +499,73 @@ impl PartialEq for Aabb {
         self.x == other.x && self.y == other.y && self.z == other.z
     }
 }
+
+/// Test a [ray](Ray) against a batch of [`Aabb`]s with the slab method,
+/// writing the distance to each box into `hits` like [`Aabb::ray_hit()`]:
+/// `Some(0.0)` if the origin is inside, the entry `t` if the box is in front,
+/// otherwise [`None`].
+///
+/// Compiled for AVX-512, AVX2 and NEON as well as the baseline target, with
+/// the best one the CPU supports picked the first time it's called (see
+/// [`cpu`](crate::cpu)).
+///
+/// # Panics
+///
+/// If `hits` isn't the same length as `aabbs`.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{aabb::{ray_hit_batch, Aabb}, interval, interval::Interval, ray, ray::Ray, vec3, vec3::Vec3};
+///
+/// let unit = |x: f64| Aabb {
+///     x: interval![x, x + 1.0],
+///     y: interval![0.0, 1.0],
+///     z: interval![0.0, 1.0],
+/// };
+/// let boxes = [unit(-3.0), unit(-0.5), unit(2.0), Aabb::new()];
+///
+/// let r = ray!(vec3![0.0, 0.5, 0.5], vec3![1.0, 0.0, 0.0]);
+/// let mut hits = [None; 4];
+/// ray_hit_batch(&boxes, &r, &mut hits);
+///
+/// // behind, around the origin, in front, and empty
+/// assert_eq!(hits, [None, Some(0.0), Some(2.0), None]);
+/// ```
+#[multiversion(targets("x86_64+avx512f+avx512vl", "x86_64+avx2+fma", "aarch64+neon"))]
+pub fn ray_hit_batch(aabbs: &[Aabb], ray: &Ray, hits: &mut [Option<f64>]) {
+    assert_eq!(aabbs.len(), hits.len(), "Need one hit per Aabb");
+
+    let origin = ray.origin.e;
+    let inv_d = ray.direction.e.map(|d| 1.0 / d);
+    let parallel = ray.direction.e.map(|d| d == 0.0);
+
+    for (aabb, hit) in aabbs.iter().zip(hits.iter_mut()) {
+        let mut near = f64::NEG_INFINITY;
+        let mut far = f64::INFINITY;
+        for (axis, slab) in [aabb.x, aabb.y, aabb.z].iter().enumerate() {
+            // parallel rays are either always or never within the slab, and
+            // would give NaN on its faces
+            let (enter, exit) = if parallel[axis] {
+                if slab.contains(origin[axis]) {
+                    (f64::NEG_INFINITY, f64::INFINITY)
+                } else {
+                    (f64::INFINITY, f64::NEG_INFINITY)
+                }
+            } else {
+                let t0 = (slab.min - origin[axis]) * inv_d[axis];
+                let t1 = (slab.max - origin[axis]) * inv_d[axis];
+                // ordered by direction rather than value, so empty boxes
+                // (`min > max`) stay empty
+                if inv_d[axis] > 0.0 {
+                    (t0, t1)
+                } else {
+                    (t1, t0)
+                }
+            };
+            near = near.max(enter);
+            far = far.min(exit);
+        }
+        *hit = (near <= far && far >= 0.0).then_some(near.max(0.0));
+    }
+}
@@ -0,0 +1,481 @@
+//! This module contains texture baking, which traces [ray](Ray)s from points on
+//! the surface of a [`Mesh`] (found through its UV layout) rather than from the
+//! [`Camera`], and writes the results into a [`FilmBuffer`] texture. Data maps
+//! (ambient occlusion, curvature, normals, thickness) should be saved with
+//! [`FilmBuffer::save_linear()`].
+
+use anyhow::{anyhow, Result};
+use rand::{
+    rngs::{SmallRng, SysRng},
+    SeedableRng,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    camera::{Camera, PathState, Trace},
+    film::FilmBuffer,
+    hit::Hittable,
+    interval,
+    interval::Interval,
+    mesh::{FaceVertex, Mesh, MeshData},
+    ray,
+    ray::Ray,
+    triangle::uv_derivatives,
+    vec3,
+    vec3::{cross, dot, Vec3},
+};
+
+/// A point on the surface of a [`Mesh`] covered by a texel.
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSample {
+    /// The world-space position of the texel centre.
+    pub p: Vec3,
+    /// The (interpolated) unit surface normal.
+    pub norm: Vec3,
+    /// The index of the face in [`MeshData::faces`] the texel landed on.
+    pub face: usize,
+    /// The barycentric coordinates of the texel centre within the face.
+    pub bary: [f64; 3],
+}
+
+/// Find the [`SurfaceSample`] under the centre of every texel of a square
+/// `resolution` sized texture laid out by the UVs of `data`. Texels not covered
+/// by any face are [`None`].
+///
+/// # Errors
+///
+/// If `resolution` is `0`, or the mesh doesn't have texture coordinates on
+/// every face.
+pub fn texel_samples(data: &MeshData, resolution: u32) -> Result<Vec<Option<SurfaceSample>>> {
+    if resolution == 0 {
+        return Err(anyhow!("Can't bake a texture with a resolution of 0."));
+    }
+    if !data.has_uvs() {
+        return Err(anyhow!("Mesh needs texture coordinates to be baked."));
+    }
+
+    let res = f64::from(resolution);
+    let mut samples = vec![None; resolution as usize * resolution as usize];
+
+    for (face_idx, face) in data.faces.iter().enumerate() {
+        let Some(uvs) = data.face_uvs(face) else {
+            continue;
+        };
+        // texel space, with y down
+        let corners = uvs.map(|[u, v]| [u * res, (1.0 - v) * res]);
+
+        let area = edge(corners[0], corners[1], corners[2]);
+        if area.abs() < f64::EPSILON {
+            continue;
+        }
+
+        let min_x = corners.iter().map(|c| c[0]).fold(f64::INFINITY, f64::min);
+        let max_x = corners
+            .iter()
+            .map(|c| c[0])
+            .fold(f64::NEG_INFINITY, f64::max);
+        let min_y = corners.iter().map(|c| c[1]).fold(f64::INFINITY, f64::min);
+        let max_y = corners
+            .iter()
+            .map(|c| c[1])
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        #[expect(clippy::cast_possible_truncation)]
+        #[expect(clippy::cast_sign_loss)]
+        let (x0, x1, y0, y1) = (
+            min_x.floor().clamp(0.0, res - 1.0) as u32,
+            max_x.ceil().clamp(0.0, res - 1.0) as u32,
+            min_y.floor().clamp(0.0, res - 1.0) as u32,
+            max_y.ceil().clamp(0.0, res - 1.0) as u32,
+        );
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let centre = [f64::from(x) + 0.5, f64::from(y) + 0.5];
+                let bary = [
+                    edge(corners[1], corners[2], centre) / area,
+                    edge(corners[2], corners[0], centre) / area,
+                    edge(corners[0], corners[1], centre) / area,
+                ];
+
+                if bary.iter().all(|&b| b >= 0.0) {
+                    samples[y as usize * resolution as usize + x as usize] =
+                        Some(surface_sample(data, face, face_idx, bary));
+                }
+            }
+        }
+    }
+
+    Ok(samples)
+}
+
+/// Twice the signed area of the 2D triangle `(a, b, c)`.
+fn edge(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Interpolate the position and normal of `face` at barycentric coordinates
+/// `bary`.
+fn surface_sample(
+    data: &MeshData,
+    face: &[FaceVertex; 3],
+    face_idx: usize,
+    bary: [f64; 3],
+) -> SurfaceSample {
+    let [p0, p1, p2] = data.face_positions(face);
+    let p = p0 * bary[0] + p1 * bary[1] + p2 * bary[2];
+    let norm = match data.face_normals(face) {
+        Some([n0, n1, n2]) => (n0 * bary[0] + n1 * bary[1] + n2 * bary[2]).unit(),
+        None => cross(&(p1 - p0), &(p2 - p1)).unit(),
+    };
+
+    SurfaceSample {
+        p,
+        norm,
+        face: face_idx,
+        bary,
+    }
+}
+
+/// Grow the filled area of a baked texture by one texel, copying the average of
+/// the filled neighbours into each empty texel. This stops texture filtering
+/// from bleeding background into the edges of UV islands.
+fn dilate(pixels: &mut [Option<Vec3>], resolution: u32) {
+    let res = resolution as usize;
+    let source = pixels.to_vec();
+
+    for y in 0..res {
+        for x in 0..res {
+            if source[y * res + x].is_some() {
+                continue;
+            }
+
+            let mut total = vec3![0.0, 0.0, 0.0];
+            let mut count = 0_u32;
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                if nx < res && ny < res {
+                    if let Some(c) = source[ny * res + nx] {
+                        total += c;
+                        count += 1;
+                    }
+                }
+            }
+
+            if count > 0 {
+                pixels[y * res + x] = Some(total / count);
+            }
+        }
+    }
+}
+
+/// Run `texel` in parallel for every covered texel of a `resolution` sized
+/// texture over the UVs of `mesh`, then dilate and collect the results into a
+/// [`FilmBuffer`]. Uncovered texels are black.
+fn bake_texels<F>(mesh: &Mesh, resolution: u32, texel: F) -> Result<FilmBuffer>
+where
+    F: Fn(&SurfaceSample, &mut SmallRng) -> Vec3 + Sync,
+{
+    let samples = texel_samples(mesh.data(), resolution)?;
+    let res = resolution as usize;
+
+    let mut pixels: Vec<Option<Vec3>> = (0..res)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
+            samples[y * res..(y + 1) * res]
+                .iter()
+                .map(|sample| sample.map(|s| texel(&s, &mut rng)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    dilate(&mut pixels, resolution);
+
+    Ok(FilmBuffer::from_rows(
+        pixels
+            .chunks(res)
+            .map(|row| row.iter().map(|c| c.unwrap_or_default()).collect())
+            .collect(),
+    ))
+}
+
+/// Estimate the mean curvature at each vertex position of `data`, from how much
+/// the normals turn along the edges leaving it. Positive values are convex.
+fn vertex_curvatures(data: &MeshData) -> Vec<f64> {
+    // average the normals of every corner sharing a position
+    let mut normals = vec![vec3![0.0, 0.0, 0.0]; data.positions.len()];
+    for face in &data.faces {
+        let corner_normals = data.face_normals(face).unwrap_or_else(|| {
+            let [p0, p1, p2] = data.face_positions(face);
+            let n = cross(&(p1 - p0), &(p2 - p1));
+            [n, n, n]
+        });
+        for (fv, n) in face.iter().zip(corner_normals) {
+            normals[fv.position] += n.unit();
+        }
+    }
+
+    let mut totals = vec![0.0; data.positions.len()];
+    let mut counts = vec![0_u32; data.positions.len()];
+    for face in &data.faces {
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            let (i, j) = (face[a].position, face[b].position);
+            let edge = data.positions[j] - data.positions[i];
+            let len_sq = edge.length_squared();
+            if len_sq <= 0.0 || normals[i].near_zero() || normals[j].near_zero() {
+                continue;
+            }
+
+            let k = dot(&(normals[j].unit() - normals[i].unit()), &edge) / len_sq;
+            for v in [i, j] {
+                totals[v] += k;
+                counts[v] += 1;
+            }
+        }
+    }
+
+    totals
+        .iter()
+        .zip(counts)
+        .map(|(&total, count)| {
+            if count > 0 {
+                total / f64::from(count)
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// Bake a `resolution` sized square curvature map of `mesh`, laid out by its
+/// UVs. Flat areas are mid grey (`0.5`), convex areas brighter and concave
+/// areas darker, with the curvature (in inverse scene units) multiplied by
+/// `scale` before being clamped into range.
+///
+/// # Panics
+///
+/// May panic if unable to get or use the system random.
+///
+/// # Errors
+///
+/// If `resolution` is `0`, or the mesh doesn't have texture coordinates on
+/// every face.
+pub fn bake_curvature(mesh: &Mesh, resolution: u32, scale: f64) -> Result<FilmBuffer> {
+    let data = mesh.data();
+    let curvatures = vertex_curvatures(data);
+
+    bake_texels(mesh, resolution, |s, _| {
+        let face = &data.faces[s.face];
+        let k: f64 = face
+            .iter()
+            .zip(s.bary)
+            .map(|(fv, b)| curvatures[fv.position] * b)
+            .sum();
+        let value = (0.5 + 0.5 * k * scale).clamp(0.0, 1.0);
+        vec3![value, value, value]
+    })
+}
+
+/// The tangent frame `(tangent, bitangent)` of `face` from its positions and
+/// UVs, made orthonormal to `norm`. Falls back to an arbitrary frame around
+/// `norm` if the UVs are degenerate.
+fn tangent_frame(data: &MeshData, face: &[FaceVertex; 3], norm: Vec3) -> (Vec3, Vec3) {
+    let Some((tangent, bitangent)) = data
+        .face_uvs(face)
+        .and_then(|uvs| uv_derivatives(data.face_positions(face), uvs))
+    else {
+        return norm.orthonormal_basis();
+    };
+
+    // Gram-Schmidt against the normal, keeping the handedness of the UVs
+    let tangent = (tangent - norm * dot(&norm, &tangent)).unit();
+    let handed = cross(&norm, &tangent);
+    if dot(&handed, &bitangent) < 0.0 {
+        (tangent, -handed)
+    } else {
+        (tangent, handed)
+    }
+}
+
+/// Bake a `resolution` sized square tangent-space normal map for the low-poly
+/// `low` mesh, laid out by its UVs, capturing the detail of the `high`
+/// (high-poly) surface. For each texel a [ray](Ray) is cast back along the
+/// low-poly normal from `cage_distance` above the surface to `cage_distance`
+/// below it, and the closest high-poly normal found is written in the low-poly
+/// tangent frame, encoded as `0.5 * n + 0.5`. Texels that miss get the flat
+/// normal `(0.5, 0.5, 1.0)`.
+///
+/// # Panics
+///
+/// May panic if unable to get or use the system random.
+///
+/// # Errors
+///
+/// If `resolution` is `0`, or the low-poly mesh doesn't have texture
+/// coordinates on every face.
+pub fn bake_normal_map(
+    low: &Mesh,
+    high: &dyn Hittable,
+    resolution: u32,
+    cage_distance: f64,
+) -> Result<FilmBuffer> {
+    let data = low.data();
+
+    bake_texels(low, resolution, |s, _| {
+        let r = ray!(s.p + s.norm * cage_distance, -s.norm);
+        let Some(rec) = high.hit(&r, interval![0.0, 2.0 * cage_distance]) else {
+            return vec3![0.5, 0.5, 1.0];
+        };
+
+        let (tangent, bitangent) = tangent_frame(data, &data.faces[s.face], s.norm);
+        // hit normals face the incoming ray, which came from outside
+        let n = vec3![
+            dot(&rec.norm, &tangent),
+            dot(&rec.norm, &bitangent),
+            dot(&rec.norm, &s.norm)
+        ]
+        .unit();
+
+        n * 0.5 + vec3![0.5, 0.5, 0.5]
+    })
+}
+
+impl Camera {
+    /// Bake a `resolution` sized square ambient occlusion map of `mesh` (which
+    /// should also be part of `world`), laid out by the mesh's UVs. Each texel
+    /// traces the [`Camera`]'s samples per pixel worth of cosine-weighted
+    /// [ray](Ray)s and is the fraction of them that travel `distance` without
+    /// hitting anything, so `1.0` is fully open and `0.0` fully occluded.
+    ///
+    /// # Panics
+    ///
+    /// May panic if unable to get or use the system random.
+    ///
+    /// # Errors
+    ///
+    /// If `resolution` is `0`, or the mesh doesn't have texture coordinates on
+    /// every face.
+    pub fn bake_ambient_occlusion(
+        &self,
+        mesh: &Mesh,
+        resolution: u32,
+        distance: f64,
+        world: &dyn Hittable,
+    ) -> Result<FilmBuffer> {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let samples = self.samples_per_pixel();
+
+        bake_texels(mesh, resolution, |s, rng| {
+            let mut open = 0_u32;
+            for _ in 0..samples {
+                let mut dir = s.norm + Vec3::random_unit(rng);
+                if dir.near_zero() {
+                    dir = s.norm;
+                }
+                let r = ray!(s.p, dir.unit(), self.sample_time(rng));
+                if world.hit(&r, interval![min_t, distance]).is_none() {
+                    open += 1;
+                }
+            }
+
+            let value = f64::from(open) / f64::from(samples);
+            vec3![value, value, value]
+        })
+    }
+
+    /// Bake a `resolution` sized square thickness map of `mesh` (which should
+    /// also be part of `world`), laid out by the mesh's UVs, as used to fake
+    /// light scattering through skin, wax or leaves. Each texel traces the
+    /// [`Camera`]'s samples per pixel worth of cosine-weighted [ray](Ray)s
+    /// into the surface around the inverted normal, and is the mean distance
+    /// they travel before leaving it through the back, as a fraction of
+    /// `distance`, so `1.0` is at least `distance` thick. [`Ray`]s that
+    /// don't find a back count as `distance`.
+    ///
+    /// # Panics
+    ///
+    /// May panic if unable to get or use the system random.
+    ///
+    /// # Errors
+    ///
+    /// If `resolution` is `0`, or the mesh doesn't have texture coordinates on
+    /// every face.
+    pub fn bake_thickness(
+        &self,
+        mesh: &Mesh,
+        resolution: u32,
+        distance: f64,
+        world: &dyn Hittable,
+    ) -> Result<FilmBuffer> {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let samples = self.samples_per_pixel();
+
+        bake_texels(mesh, resolution, |s, rng| {
+            let mut total = 0.0;
+            for _ in 0..samples {
+                let mut dir = -s.norm + Vec3::random_unit(rng);
+                if dir.near_zero() {
+                    dir = -s.norm;
+                }
+                let r = ray!(s.p, dir.unit(), self.sample_time(rng));
+                total += world
+                    .hit(&r, interval![min_t, distance])
+                    .map_or(distance, |rec| rec.t);
+            }
+
+            let value = (total / f64::from(samples) / distance).min(1.0);
+            vec3![value, value, value]
+        })
+    }
+
+    /// Bake a `resolution` sized square lightmap of the irradiance arriving at
+    /// the surface of `mesh` (which should also be part of `world` so it can
+    /// shadow itself), laid out by the mesh's UVs. Each texel traces the
+    /// [`Camera`]'s samples per pixel worth of cosine-weighted [ray](Ray)s into
+    /// `world` with the [`Camera`]'s max depth and [`RayBias`](crate::camera::RayBias).
+    /// The result is linear HDR, so should be saved as `.hdr` or `.exr`.
+    ///
+    /// # Panics
+    ///
+    /// May panic if unable to get or use the system random.
+    ///
+    /// # Errors
+    ///
+    /// If `resolution` is `0`, or the mesh doesn't have texture coordinates on
+    /// every face.
+    pub fn bake_lightmap(
+        &self,
+        mesh: &Mesh,
+        resolution: u32,
+        world: &dyn Hittable,
+    ) -> Result<FilmBuffer> {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let samples = self.samples_per_pixel();
+
+        bake_texels(mesh, resolution, |s, rng| {
+            let mut radiance = vec3![0.0, 0.0, 0.0];
+            for _ in 0..samples {
+                let mut dir = s.norm + Vec3::random_unit(rng);
+                if dir.near_zero() {
+                    dir = s.norm;
+                }
+                let r = ray!(s.p, dir, self.sample_time(rng));
+                radiance += self.ray_colour(
+                    &r,
+                    PathState::DIFFUSE,
+                    self.max_depth,
+                    &mut Trace::new(world, min_t),
+                    rng,
+                );
+            }
+
+            // cosine-weighted sampling gives an irradiance estimate of `PI`
+            // times the mean radiance
+            radiance * std::f64::consts::PI / samples
+        })
+    }
+}
@@ -0,0 +1,114 @@
+//! This module contains [`instanced_scene()`], a scalable stress scene for
+//! reproducible performance measurements, such as comparing BVH builds at a
+//! controlled number of objects and triangles.
+
+use std::sync::Arc;
+
+use rand::{rngs::SmallRng, RngExt as _, SeedableRng};
+
+use crate::{
+    hit::Hittable,
+    hit_list::HittableList,
+    mesh::Mesh,
+    transform::{Animated, Keyframe},
+    vec3,
+    vec3::Vec3,
+};
+
+/// How big to make an [`instanced_scene()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneSize {
+    /// Exactly this many instances of the mesh.
+    Instances(usize),
+    /// Enough instances to reach at least this many triangles in total.
+    Triangles(usize),
+}
+
+/// A generated stress scene and its size.
+#[derive(Debug)]
+pub struct StressScene {
+    /// The instances, ready to build into a [`BVHTree`](crate::bvh::BVHTree)
+    /// or any other acceleration structure.
+    pub objects: HittableList,
+    /// The number of instances in the scene.
+    pub instances: usize,
+    /// The total number of triangles over all the instances.
+    pub triangles: usize,
+}
+
+/// Scatter instances of `mesh` over a square grid on the `y = 0` plane, each
+/// with a random rotation about `Y`, scale and offset within its cell. The
+/// geometry is shared rather than copied, so scenes with millions of triangles
+/// stay small in memory. The same `seed` always gives the same scene.
+///
+/// # Example
+///
+/// ```rust
+/// use std::{path::PathBuf, sync::Arc};
+/// use rustrace::{
+///     bench::{instanced_scene, SceneSize},
+///     bvh::BVHTree,
+///     material::Lambertian,
+///     mesh::Mesh,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let teapot = Mesh::from_obj(
+///     &PathBuf::from("objs/teapot.obj"),
+///     &Lambertian::new(vec3![0.5, 0.5, 0.5]),
+/// )
+/// .unwrap();
+///
+/// let scene = instanced_scene(&Arc::new(teapot), SceneSize::Triangles(1_000_000), 42);
+///
+/// assert!(scene.triangles >= 1_000_000);
+/// assert_eq!(scene.objects.objects.len(), scene.instances);
+///
+/// let world = BVHTree::from_hit_list(scene.objects);
+/// ```
+#[must_use]
+pub fn instanced_scene(mesh: &Arc<Mesh>, size: SceneSize, seed: u64) -> StressScene {
+    let mesh_triangles = mesh.data().faces.len();
+    let instances = match size {
+        SceneSize::Instances(n) => n,
+        SceneSize::Triangles(n) => n.div_ceil(mesh_triangles.max(1)),
+    };
+
+    // cells are big enough to fit the mesh at its largest scale
+    let bound = mesh.bound();
+    let extent = bound.x.size().max(bound.z.size());
+    let cell = extent * 1.5;
+
+    #[expect(clippy::cast_possible_truncation)]
+    #[expect(clippy::cast_sign_loss)]
+    #[expect(clippy::cast_precision_loss)]
+    let per_row = (instances as f64).sqrt().ceil() as usize;
+    #[expect(clippy::cast_precision_loss)]
+    let half_width = per_row as f64 * cell * 0.5;
+
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut objects = HittableList::new();
+    for idx in 0..instances {
+        #[expect(clippy::cast_precision_loss)]
+        let (x, z) = ((idx % per_row) as f64, (idx / per_row) as f64);
+        let scale = rng.random_range(0.5..=1.0);
+        let jitter = (cell - extent * scale) * 0.5;
+        let translation = vec3![
+            (x + 0.5) * cell - half_width + rng.random_range(-jitter..=jitter),
+            -bound.y.min * scale,
+            (z + 0.5) * cell - half_width + rng.random_range(-jitter..=jitter)
+        ];
+
+        let pose = Keyframe::at(0.0, translation)
+            .with_rotation(vec3![0.0, rng.random_range(0.0..360.0), 0.0])
+            .with_scale(vec3![scale, scale, scale]);
+        objects.add(Box::new(Animated::new(Arc::clone(mesh), vec![pose])));
+    }
+
+    StressScene {
+        objects,
+        instances,
+        triangles: instances * mesh_triangles,
+    }
+}
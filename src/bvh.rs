@@ -6,11 +6,13 @@
 //! for more information.
 
 use crate::{
-    aabb::{Aabb, SplitAxis},
+    aabb::{ray_hit_batch, Aabb, SplitAxis},
     hit::{HitRecord, Hittable},
     hit_list::HittableList,
     interval::Interval,
     ray::Ray,
+    tolerance::Tolerances,
+    vec3::Vec3,
 };
 
 /// Used in [`BVHTree::sah_split`] to bucket objects.
@@ -43,54 +45,161 @@ pub struct BVHTree {
     pub aabb: Aabb,
     /// The [objects](HittableList) contained within the current node.
     pub objects: HittableList,
+    /// Objects with infinite bounds, such as [planes](crate::plane::Plane),
+    /// kept out of the tree so they don't stretch every node's bounds, and
+    /// tested for every [ray](Ray). Only used at the root.
+    pub unbounded: HittableList,
 }
 
 impl BVHTree {
     /// Create a [`BVHTree`] from a [`HittableList`] using surface area heuristics to
     /// split effectively. [Read more](https://www.pbr-book.org/3ed-2018/Primitives_and_Intersection_Acceleration/Bounding_Volume_Hierarchies).
+    ///
+    /// Objects with [unbounded](Aabb::is_unbounded()) bounds are kept aside in
+    /// [`unbounded`](field@BVHTree::unbounded) rather than split.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     bvh::BVHTree, hit::Hittable, hit_list::HittableList, material::Lambertian,
+    ///     plane::Plane, ray, ray::Ray, sphere::Sphere, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let mut hit_list = HittableList::new();
+    /// hit_list.add(Box::new(Plane::new(
+    ///     vec3![0.0, -1.0, 0.0],
+    ///     vec3![0.0, 1.0, 0.0],
+    ///     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// )));
+    /// hit_list.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, 0.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let bvh = BVHTree::from_hit_list(hit_list);
+    /// assert_eq!(bvh.unbounded.objects.len(), 1);
+    /// // the tree itself only bounds the sphere
+    /// assert_eq!(bvh.aabb.x.max, 1.0);
+    ///
+    /// // both are still hit
+    /// let down = ray!(vec3![0.0, 5.0, 0.0], vec3![0.0, -1.0, 0.0]);
+    /// assert_eq!(bvh.nearest_hit_along(&down).unwrap().p[1], 1.0);
+    /// let beside = ray!(vec3![3.0, 5.0, 0.0], vec3![0.0, -1.0, 0.0]);
+    /// assert_eq!(bvh.nearest_hit_along(&beside).unwrap().p[1], -1.0);
+    /// ```
     #[must_use]
     pub fn from_hit_list(hit_list: HittableList) -> Self {
-        let aabb = hit_list.bound();
+        Self::from_items(
+            hit_list.objects,
+            Tolerances::DEFAULT.bound_padding,
+            &|object| object.bound(),
+            &|objects| HittableList { objects },
+        )
+    }
+
+    /// Like [`BVHTree::from_hit_list()`], but for any kind of item with bounds
+    /// given by `bound`. The tree is split over the individual items, then the
+    /// items kept at each node are turned into its [`objects`](field@BVHTree::objects)
+    /// by `pack`, for example to store several primitives together.
+    ///
+    /// Bounds flat along an axis, such as those of axis-aligned triangles, are
+    /// [padded](Aabb::padded()) to at least `padding`, usually the
+    /// [bound padding](Tolerances::bound_padding) of the items, so they still
+    /// split sensibly and aren't missed by the slab test. Trees built by
+    /// [`BVHTree::from_hit_list()`] use the default padding until given
+    /// others by [`Hittable::set_tolerances()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use rustrace::{
+    ///     bvh::BVHTree, hit::Hittable, hit_list::HittableList, material::{Lambertian, Material},
+    ///     tolerance::Tolerances, triangle::Triangle, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let mat: Arc<dyn Material> = Arc::new(Lambertian::new(vec3![0.5, 0.5, 0.5]));
+    /// let triangles: Vec<Triangle> = (0..100)
+    ///     .map(|i| {
+    ///         let x = f64::from(i);
+    ///         Triangle::with_shared_material(
+    ///             [vec3![x, 0.0, 0.0], vec3![x + 1.0, 0.0, 0.0], vec3![x, 1.0, 0.0]],
+    ///             None,
+    ///             Arc::clone(&mat),
+    ///         )
+    ///     })
+    ///     .collect();
+    ///
+    /// let padding = Tolerances::DEFAULT.bound_padding;
+    /// let bvh = BVHTree::from_items(triangles, padding, &Triangle::bound, &|node_triangles| {
+    ///     let mut objects = HittableList::new();
+    ///     for triangle in node_triangles {
+    ///         objects.add(Box::new(triangle));
+    ///     }
+    ///     objects
+    /// });
+    ///
+    /// assert_eq!(bvh.bound().x.max, 100.0);
+    /// ```
+    #[must_use]
+    pub fn from_items<T, B, P>(items: Vec<T>, padding: f64, bound: &B, pack: &P) -> Self
+    where
+        B: Fn(&T) -> Aabb,
+        P: Fn(Vec<T>) -> HittableList,
+    {
+        let (unbounded, items): (Vec<T>, Vec<T>) = items
+            .into_iter()
+            .partition(|item| bound(item).is_unbounded());
+
+        // flat bounds have no surface area for the SAH and can be missed by
+        // the slab test, so give everything some thickness
+        let bound = &|item: &T| bound(item).padded(padding);
+
+        let mut tree = Self::build(items, bound, pack);
+        if !unbounded.is_empty() {
+            tree.unbounded = pack(unbounded);
+        }
+        tree
+    }
+
+    /// Recursively build the tree over `items`, which are all bounded.
+    fn build<T, B, P>(items: Vec<T>, bound: &B, pack: &P) -> Self
+    where
+        B: Fn(&T) -> Aabb,
+        P: Fn(Vec<T>) -> HittableList,
+    {
+        let mut aabb = Aabb::new();
+        for item in &items {
+            aabb.union(&bound(item));
+        }
 
         let split_axis = SplitAxis::choose_from_aabb(aabb);
-        let (left, right, both) = Self::sah_split(hit_list, &aabb, split_axis);
-
-        match (left.objects.is_empty(), right.objects.is_empty()) {
-            (true, true) => Self {
-                aabb,
-                objects: both,
-                left: None,
-                right: None,
-            },
-            (true, false) => Self {
-                aabb,
-                objects: both,
-                left: None,
-                right: Some(Box::new(Self::from_hit_list(right))),
-            },
-            (false, true) => Self {
-                aabb,
-                objects: both,
-                left: Some(Box::new(Self::from_hit_list(left))),
-                right: None,
-            },
-            (false, false) => Self {
-                aabb,
-                objects: both,
-                left: Some(Box::new(Self::from_hit_list(left))),
-                right: Some(Box::new(Self::from_hit_list(right))),
-            },
+        let (left, right, both) = Self::sah_split(items, &aabb, split_axis, bound);
+
+        let child =
+            |items: Vec<T>| (!items.is_empty()).then(|| Box::new(Self::build(items, bound, pack)));
+
+        Self {
+            aabb,
+            objects: pack(both),
+            unbounded: HittableList::new(),
+            left: child(left),
+            right: child(right),
         }
     }
 
-    /// Splits a [`HittableList`] into three parts `(left, right, both)`
+    /// Splits `items` into three parts `(left, right, both)`
     /// according to a surface area heuristic cost. Uses [`BVHTree::partition_objects()`] to
     /// partition. [Read more](https://www.pbr-book.org/3ed-2018/Primitives_and_Intersection_Acceleration/Bounding_Volume_Hierarchies).
-    fn sah_split(
-        hit_list: HittableList,
+    fn sah_split<T, B: Fn(&T) -> Aabb>(
+        items: Vec<T>,
         aabb: &Aabb,
         split_axis: SplitAxis,
-    ) -> (HittableList, HittableList, HittableList) {
+        bound: &B,
+    ) -> (Vec<T>, Vec<T>, Vec<T>) {
         const NUM_BUCKETS: usize = 12;
 
         let axis_interval = match split_axis {
@@ -101,8 +210,8 @@ impl BVHTree {
 
         let mut buckets = vec![Bucket::new(); NUM_BUCKETS];
 
-        for object in &hit_list.objects {
-            let object_aabb = object.bound();
+        for item in &items {
+            let object_aabb = bound(item);
             let centroid = object_aabb.centroid();
             let centroid_value = match split_axis {
                 SplitAxis::X => centroid.e[0],
@@ -156,25 +265,26 @@ impl BVHTree {
         let split_pos = axis_interval.min
             + (axis_interval.size() * (min_cost_idx + 1) as f64 / NUM_BUCKETS as f64);
 
-        Self::partition_objects(hit_list, split_axis, split_pos, aabb)
+        Self::partition_objects(items, split_axis, split_pos, aabb, bound)
     }
 
-    /// Partitions [`hit_list`](HittableList) into three new [`HittableList`]s along the
-    /// given [`split_axis`](SplitAxis) and `split_pos`.
-    fn partition_objects(
-        hit_list: HittableList,
+    /// Partitions `items` into three new lists along the given
+    /// [`split_axis`](SplitAxis) and `split_pos`.
+    fn partition_objects<T, B: Fn(&T) -> Aabb>(
+        items: Vec<T>,
         split_axis: SplitAxis,
         split_pos: f64,
         parent_aabb: &Aabb,
-    ) -> (HittableList, HittableList, HittableList) {
-        let mut left = HittableList::new();
-        let mut right = HittableList::new();
-        let mut both = HittableList::new();
+        bound: &B,
+    ) -> (Vec<T>, Vec<T>, Vec<T>) {
+        let mut left = vec![];
+        let mut right = vec![];
+        let mut both = vec![];
 
         let (left_aabb, right_aabb) = parent_aabb.split_at(split_axis, split_pos);
 
-        for object in hit_list.objects {
-            let object_aabb = object.bound();
+        for item in items {
+            let object_aabb = bound(&item);
             let centroid = object_aabb.centroid();
             let centroid_value = match split_axis {
                 SplitAxis::X => centroid.e[0],
@@ -186,15 +296,46 @@ impl BVHTree {
                 left_aabb.overlaps(&object_aabb),
                 right_aabb.overlaps(&object_aabb),
             ) {
-                (true, false) if centroid_value < split_pos => left.add(object),
-                (false, true) if centroid_value >= split_pos => right.add(object),
-                _ => both.add(object),
+                (true, false) if centroid_value < split_pos => left.push(item),
+                (false, true) if centroid_value >= split_pos => right.push(item),
+                _ => both.push(item),
             }
         }
 
         (left, right, both)
     }
 
+    /// Recursively search for the closest point to `point`, skipping nodes
+    /// whose [`Aabb`] is further away than the best point found so far.
+    fn closest_point_within(&self, point: Vec3, best: &mut Option<(f64, Vec3)>) {
+        if best.is_some_and(|(dist_sq, _)| self.aabb.distance_squared(point) >= dist_sq) {
+            return;
+        }
+
+        for object in &self.objects.objects {
+            if let Some(c) = object.closest_point(point) {
+                let dist_sq = (c - point).length_squared();
+                if best.is_none_or(|(best_sq, _)| dist_sq < best_sq) {
+                    *best = Some((dist_sq, c));
+                }
+            }
+        }
+
+        // visit the nearer child first so the further one is more likely pruned
+        let mut children: Vec<&BVHTree> = [self.left.as_deref(), self.right.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        children.sort_by(|a, b| {
+            a.aabb
+                .distance_squared(point)
+                .total_cmp(&b.aabb.distance_squared(point))
+        });
+        for child in children {
+            child.closest_point_within(point, best);
+        }
+    }
+
     // DEBUG: verify that AABBs surround their object's AABBs
     // pub fn verify(&self) -> bool {
     //     for object in &self.objects.objects {
@@ -222,20 +363,21 @@ impl Hittable for BVHTree {
     /// Recursively check if the ray [`r`](Ray) hits any object in the current
     /// [`BVHTree`] node or any sub-trees and returns a [`Some(HitRecord)`](Option<HitRecord>)
     /// of the closest intersection if found. Otherwise, returns [`None`].
-    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
-        let left = self.left.as_ref();
-        let left_t = if let Some(node) = left {
-            node.aabb.ray_hit(r)
-        } else {
-            None
-        };
+    fn hit(&self, r: &Ray, mut ray_t: Interval) -> Option<HitRecord<'_>> {
+        // anything in the tree has to be nearer than the unbounded objects
+        let unbounded_hit = self.unbounded.hit(r, ray_t);
+        if let Some(rec) = &unbounded_hit {
+            ray_t.max = rec.t;
+        }
 
+        let left = self.left.as_ref();
         let right = self.right.as_ref();
-        let right_t = if let Some(node) = right {
-            node.aabb.ray_hit(r)
-        } else {
-            None
-        };
+
+        // both children in one batch, missing ones are empty and never hit
+        let child_aabb = |node: Option<&Box<BVHTree>>| node.map_or_else(Aabb::new, |n| n.aabb);
+        let mut child_t = [None; 2];
+        ray_hit_batch(&[child_aabb(left), child_aabb(right)], r, &mut child_t);
+        let [left_t, right_t] = child_t;
 
         let self_hit = self.objects.hit(r, ray_t);
         let subtree_hit = match (left_t, right_t) {
@@ -252,7 +394,7 @@ impl Hittable for BVHTree {
             }
         };
 
-        match (self_hit, subtree_hit) {
+        let tree_hit = match (self_hit, subtree_hit) {
             (None, None) => None,
             (None, Some(r)) => Some(r),
             (Some(l), None) => Some(l),
@@ -263,11 +405,83 @@ impl Hittable for BVHTree {
                     Some(r)
                 }
             }
-        }
+        };
+        tree_hit.or(unbounded_hit)
     }
 
-    /// Returns [`self.aabb`](field@BVHTree::aabb).
+    /// Returns [`self.aabb`](field@BVHTree::aabb), grown by any
+    /// [`unbounded`](field@BVHTree::unbounded) objects.
     fn bound(&self) -> Aabb {
-        self.aabb
+        let mut aabb = self.aabb;
+        aabb.union(&self.unbounded.bound());
+        aabb
+    }
+
+    /// Moves every object and bounding box in the [`BVHTree`], returning
+    /// whether all the objects could be.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.aabb = self.aabb.translated(offset);
+        let mut all = self.objects.translate(offset);
+        all &= self.unbounded.translate(offset);
+        for child in [self.left.as_mut(), self.right.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            all &= child.translate(offset);
+        }
+        all
+    }
+
+    /// Passes the tolerances on to every object, then pads the bounding
+    /// boxes again with the new [bound padding](Tolerances::bound_padding),
+    /// keeping the shape of the tree.
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.unbounded.set_tolerances(tolerances);
+        let mut aabb = Aabb::new();
+        for object in &mut self.objects.objects {
+            object.set_tolerances(tolerances);
+            aabb.union(&object.bound().padded(tolerances.bound_padding));
+        }
+        for child in [self.left.as_mut(), self.right.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            child.set_tolerances(tolerances);
+            aabb.union(&child.aabb);
+        }
+        self.aabb = aabb;
+    }
+
+    /// Finds the closest point on any object in the [`BVHTree`], pruning
+    /// sub-trees by the distance to their [`Aabb`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     bvh::BVHTree, hit::Hittable, hit_list::HittableList, material::Lambertian,
+    ///     sphere::Sphere, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let mut hit_list = HittableList::new();
+    /// for x in 0..10 {
+    ///     hit_list.add(Box::new(Sphere {
+    ///         centre: vec3![f64::from(x) * 3.0, 0.0, 0.0],
+    ///         radius: 1.0,
+    ///         mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    ///     }));
+    /// }
+    /// let bvh = BVHTree::from_hit_list(hit_list);
+    ///
+    /// assert_eq!(bvh.closest_point(vec3![6.0, 5.0, 0.0]), Some(vec3![6.0, 1.0, 0.0]));
+    /// assert_eq!(bvh.distance(vec3![-3.0, 0.0, 0.0]), Some(2.0));
+    /// ```
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        let mut best = self
+            .unbounded
+            .closest_point(point)
+            .map(|c| ((c - point).length_squared(), c));
+        self.closest_point_within(point, &mut best);
+        best.map(|(_, c)| c)
     }
 }
@@ -2,25 +2,41 @@
 //! configurability. This includes supersampling configuration for
 //! [anti-aliasing](AntiAliasing) and defocus blur.
 
+use std::fmt;
+use std::ops::{ControlFlow, Range};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use anyhow::{anyhow, Result};
-use image::RgbImage;
+use anyhow::{anyhow, bail, Result};
 use rand::rngs::SysRng;
 use rand::SeedableRng;
 use rand::{rngs::SmallRng, RngExt as _};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Deserialize;
 
-use crate::hit::Hittable;
+use crate::aabb::Aabb;
+use crate::dof::CircleOfConfusion;
+use crate::environment::EnvironmentMap;
+use crate::film::{FilmBuffer, Output, VarianceFilm, Welford, CONFIDENCE_95};
+use crate::guide::{GuideSample, PathGuide};
+use crate::hit::{HitRecord, Hittable};
 use crate::interval::Interval;
-use crate::ray::Ray;
-use crate::utils::deg_to_rad;
-use crate::vec3::{cross, Vec3};
+use crate::irradiance::IrradianceCache;
+use crate::layers::LayerFilm;
+use crate::material::LightEffect;
+use crate::noise::Noise;
+use crate::pyramid::DeepZoomWriter;
+use crate::ray::{Ray, RayBatch};
+use crate::stats::{RenderStats, StatsTally};
+use crate::transform::{Keyframe, Transform};
+use crate::utils::{deg_to_rad, equirect_to_direction};
+use crate::vec3::{cross, dot, Vec3};
 use crate::{interval, ray, vec3};
 
 /// Different supersampling modes for anti-aliasing.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum AntiAliasing {
     /// Use a set grid supersampling mode with a specific square grid size.
     /// <div class="warning">This doesn't remove anti-aliasing, just upsamples
@@ -28,18 +44,634 @@ pub enum AntiAliasing {
     Grid(u16),
     /// Sample using a random offset with this many samples per pixel.
     Random(u16),
+    /// Sample like [`Random`](AntiAliasing::Random) with `base` samples per
+    /// pixel, then find the pixels on edges (see [`Camera::edge_pixels()`])
+    /// and take `edge` samples in total in those. Most of the quality of
+    /// `Random(edge)` for a fraction of the cost in clean scenes, where
+    /// aliasing only shows along silhouettes and hard shading boundaries.
+    /// Noisy lighting reads as contrast too, so suits it less. Tiled renders
+    /// only take the `base` samples.
+    Edges {
+        /// The samples every pixel takes.
+        base: u16,
+        /// The samples pixels on an edge take in total.
+        edge: u16,
+    },
+}
+
+/// How defocus blur is made when the [`Camera`] has a defocus angle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum DepthOfField {
+    /// Sample [ray](Ray)s across the lens. Physically correct, but the blurred
+    /// areas need many samples per pixel to converge.
+    #[default]
+    Lens,
+    /// Render through a pinhole along with the depth of each pixel, then blur
+    /// each pixel by its [`CircleOfConfusion`] as a post process. Far cheaper
+    /// for previews, but only approximate at the edges of blurred objects,
+    /// and reflections and refractions are blurred by the depth of the
+    /// surface rather than of what they show.
+    Post,
+}
+
+/// A function from a direction to the radiance arriving from it, for a
+/// [`Background::Callback`].
+pub type BackgroundFn = dyn Fn(Vec3) -> Vec3 + Send + Sync;
+
+/// What [ray](Ray)s that miss everything see, and are lit by.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use rustrace::{camera::Background, vec3, vec3::Vec3};
+///
+/// let up = vec3![0.0, 1.0, 0.0];
+/// let down = vec3![0.0, -1.0, 0.0];
+///
+/// let black = Background::Solid(vec3![0.0, 0.0, 0.0]);
+/// assert_eq!(black.radiance(up), vec3![0.0, 0.0, 0.0]);
+///
+/// let dusk = Background::Gradient {
+///     bottom: vec3![1.0, 0.5, 0.2],
+///     top: vec3![0.1, 0.1, 0.4],
+/// };
+/// assert_eq!(dusk.radiance(up), vec3![0.1, 0.1, 0.4]);
+/// assert_eq!(dusk.radiance(down), vec3![1.0, 0.5, 0.2]);
+///
+/// // a bright patch of sky towards +X
+/// let sun = Background::Callback(Arc::new(|dir: Vec3| {
+///     if dir.unit()[0] > 0.99 {
+///         vec3![50.0, 50.0, 50.0]
+///     } else {
+///         vec3![0.2, 0.3, 0.5]
+///     }
+/// }));
+/// assert_eq!(sun.radiance(vec3![2.0, 0.0, 0.0]), vec3![50.0, 50.0, 50.0]);
+/// assert_eq!(sun.radiance(up), vec3![0.2, 0.3, 0.5]);
+/// ```
+#[derive(Clone)]
+pub enum Background {
+    /// The same colour in every direction, such as black for studio lighting
+    /// where only the lights in the scene should light it.
+    Solid(Vec3),
+    /// A vertical blend from `bottom` straight down to `top` straight up. The
+    /// default is a white to light blue sky.
+    Gradient {
+        /// The colour looking straight down.
+        bottom: Vec3,
+        /// The colour looking straight up.
+        top: Vec3,
+    },
+    /// An [`EnvironmentMap`] image, shared so many cameras can use one large
+    /// image.
+    Environment(Arc<EnvironmentMap>),
+    /// Any function of the (not necessarily unit) direction, for procedural
+    /// skies.
+    Callback(Arc<BackgroundFn>),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Gradient {
+            bottom: vec3![1.0, 1.0, 1.0],
+            top: vec3![0.5, 0.7, 1.0],
+        }
+    }
+}
+
+impl fmt::Debug for Background {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Solid(colour) => f.debug_tuple("Solid").field(colour).finish(),
+            Self::Gradient { bottom, top } => f
+                .debug_struct("Gradient")
+                .field("bottom", bottom)
+                .field("top", top)
+                .finish(),
+            Self::Environment(environment) => {
+                f.debug_tuple("Environment").field(environment).finish()
+            }
+            Self::Callback(_) => f.debug_tuple("Callback").field(&"..").finish(),
+        }
+    }
+}
+
+impl Background {
+    /// The radiance arriving from far away in direction `dir`.
+    #[must_use]
+    pub fn radiance(&self, dir: Vec3) -> Vec3 {
+        match self {
+            Self::Solid(colour) => *colour,
+            Self::Gradient { bottom, top } => {
+                let a = (dir.unit()[1] + 1.0) * 0.5;
+                *bottom * (1.0 - a) + *top * a
+            }
+            Self::Environment(environment) => environment.radiance(dir),
+            Self::Callback(callback) => callback(dir),
+        }
+    }
+}
+
+/// How much of the sphere each eye covers in [`Camera::render_stereo()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StereoCoverage {
+    /// 360 by 180 degrees, for 360 video. Each eye is twice as wide as it is
+    /// tall.
+    #[default]
+    Full,
+    /// The 180 by 180 degrees in front of `-Z`, for VR180. Each eye is square.
+    Front,
+}
+
+/// How each pixel's samples are combined, optionally suppressing fireflies:
+/// rare, very bright samples from hard to find light paths (such as caustics)
+/// that take a huge number of samples to average out.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FireflyFilter {
+    /// Take the plain mean of the samples. Unbiased.
+    #[default]
+    None,
+    /// Scale down any sample whose brightest channel is over this value
+    /// before taking the mean. Cheap, but darkens legitimately bright
+    /// highlights as well.
+    Clamp(f64),
+    /// Split the samples into `batches` equal passes and drop any pass whose
+    /// mean luminance is more than `threshold` robust standard deviations
+    /// (from the median absolute deviation, MAD) above the median pass,
+    /// before taking the mean of the rest. Only rejects statistical outliers,
+    /// so bright but consistent pixels are kept.
+    RejectOutliers {
+        /// The number of passes to split the samples into, at least `3`.
+        batches: u16,
+        /// How many robust standard deviations above the median a pass can
+        /// be, typically `3.0` to `5.0`.
+        threshold: f64,
+    },
+}
+
+impl FireflyFilter {
+    /// Combine a pixel's `samples` into its final colour, which is never
+    /// darker than black, however much light
+    /// [negative lights](crate::material::LightEffect::Negative) take away.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{camera::FireflyFilter, vec3, vec3::Vec3};
+    ///
+    /// let mut samples = vec![vec3![0.5, 0.5, 0.5]; 15];
+    /// samples.push(vec3![500.0, 500.0, 500.0]);
+    ///
+    /// let mean = FireflyFilter::None.resolve(&samples);
+    /// let clamped = FireflyFilter::Clamp(1.0).resolve(&samples);
+    /// let rejected = FireflyFilter::RejectOutliers { batches: 8, threshold: 3.0 }.resolve(&samples);
+    ///
+    /// assert!(mean[0] > 30.0);
+    /// assert_eq!(clamped[0], (0.5 * 15.0 + 1.0) / 16.0);
+    /// assert_eq!(rejected, vec3![0.5, 0.5, 0.5]);
+    /// ```
+    #[must_use]
+    pub fn resolve(self, samples: &[Vec3]) -> Vec3 {
+        let colour = self.combine(samples);
+        vec3![colour[0].max(0.0), colour[1].max(0.0), colour[2].max(0.0)]
+    }
+
+    /// Combine a pixel's `samples` as [`FireflyFilter::resolve()`] does, but
+    /// without clamping to black.
+    fn combine(self, samples: &[Vec3]) -> Vec3 {
+        fn mean(samples: &[Vec3]) -> Vec3 {
+            if samples.is_empty() {
+                return Vec3::default();
+            }
+            #[expect(clippy::cast_precision_loss)]
+            let scale = 1.0 / samples.len() as f64;
+            vec3::sum(samples) * scale
+        }
+
+        fn median(values: &mut [f64]) -> f64 {
+            values.sort_by(f64::total_cmp);
+            let mid = values.len() / 2;
+            if values.len().is_multiple_of(2) {
+                0.5 * (values[mid - 1] + values[mid])
+            } else {
+                values[mid]
+            }
+        }
+
+        match self {
+            FireflyFilter::None => mean(samples),
+            FireflyFilter::Clamp(max) => {
+                let clamped: Vec<Vec3> = samples
+                    .iter()
+                    .map(|&s| {
+                        let brightest = s[0].max(s[1]).max(s[2]);
+                        if brightest > max {
+                            s * (max / brightest)
+                        } else {
+                            s
+                        }
+                    })
+                    .collect();
+                mean(&clamped)
+            }
+            FireflyFilter::RejectOutliers { batches, threshold } => {
+                let batch_size = samples.len() / usize::from(batches.max(3));
+                if batch_size == 0 {
+                    return mean(samples);
+                }
+
+                let means: Vec<Vec3> = samples.chunks(batch_size).map(mean).collect();
+                let mut lums: Vec<f64> = means.iter().map(Vec3::luminance).collect();
+                let med = median(&mut lums);
+                let mut deviations: Vec<f64> = lums.iter().map(|l| (l - med).abs()).collect();
+                // scaled so it estimates the standard deviation of a normal
+                let sigma = 1.4826 * median(&mut deviations);
+
+                let kept: Vec<Vec3> = means
+                    .into_iter()
+                    .filter(|m| m.luminance() <= med + threshold * sigma)
+                    .collect();
+                mean(&kept)
+            }
+        }
+    }
+}
+
+/// How the minimum `t` accepted for a [ray](Ray) hit is chosen. Rays leaving a
+/// surface start exactly on it, so a small bias is needed to stop them hitting
+/// the surface they came from (shadow acne). Too large a bias instead makes
+/// contact shadows detach from objects (peter-panning).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum RayBias {
+    /// Use a fixed minimum `t`.
+    Fixed(f64),
+    /// Derive the minimum `t` from the size of the scene, as a fraction of the
+    /// length of the diagonal of the scene's [`Aabb`].
+    Auto,
+}
+
+impl Default for RayBias {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl RayBias {
+    /// The default bias, also the fallback of [`RayBias::Auto`].
+    pub const DEFAULT: Self = RayBias::Fixed(0.001);
+
+    /// The fraction of the scene diagonal used by [`RayBias::Auto`].
+    pub const AUTO_SCALE: f64 = 1e-6;
+
+    /// Resolve the bias into a minimum `t` for a scene bounded by `bounds`.
+    /// [`RayBias::Auto`] falls back to the default fixed bias if the bounds are
+    /// empty or unbounded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, camera::RayBias, interval, interval::Interval};
+    ///
+    /// let bounds = Aabb {
+    ///     x: interval![0.0, 3000.0],
+    ///     y: interval![0.0, 4000.0],
+    ///     z: interval![0.0, 0.0],
+    /// };
+    ///
+    /// assert_eq!(RayBias::Fixed(0.1).resolve(&bounds), 0.1);
+    /// assert!((RayBias::Auto.resolve(&bounds) - 0.005).abs() < 1e-12);
+    /// assert_eq!(RayBias::Auto.resolve(&Aabb::new()), 0.001);
+    /// ```
+    #[must_use]
+    pub fn resolve(self, bounds: &Aabb) -> f64 {
+        match self {
+            RayBias::Fixed(bias) => bias,
+            RayBias::Auto => {
+                let diagonal = vec3![bounds.x.size(), bounds.y.size(), bounds.z.size()].length();
+                if diagonal.is_finite() && diagonal > 0.0 {
+                    diagonal * Self::AUTO_SCALE
+                } else {
+                    Self::DEFAULT.resolve(bounds)
+                }
+            }
+        }
+    }
+}
+
+/// A part of the image to spend more samples on, see [`SamplePriority`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionOfInterest {
+    /// The pixels from `(x, y)` across `width` and down `height`.
+    Pixels {
+        /// The leftmost column.
+        x: u32,
+        /// The top row.
+        y: u32,
+        /// The number of columns.
+        width: u32,
+        /// The number of rows.
+        height: u32,
+    },
+    /// The pixels whose centre ray passes through a box, such as the
+    /// [`bound()`](Hittable::bound()) of a picked object.
+    Bound(Aabb),
+}
+
+impl RegionOfInterest {
+    /// Whether pixel `(i, j)` of `camera` is in the region.
+    fn contains(&self, camera: &Camera, i: u32, j: u32) -> bool {
+        match *self {
+            RegionOfInterest::Pixels {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                (x..x.saturating_add(width)).contains(&i)
+                    && (y..y.saturating_add(height)).contains(&j)
+            }
+            RegionOfInterest::Bound(bound) => {
+                let pixel = camera.pixel00_loc
+                    + camera.pixel_delta_u * f64::from(i)
+                    + camera.pixel_delta_v * f64::from(j);
+                let r = ray!(camera.centre, pixel - camera.centre);
+                bound.ray_interval(&r).is_some_and(|span| span.max > 0.0)
+            }
+        }
+    }
+}
+
+/// Regions of the image to spend more of the samples on, such as the hero
+/// object, leaving the rest noisier. Set it on a render with
+/// [`RenderOptions::set_priority()`].
+///
+/// The total number of samples stays the same as without it, the
+/// [`Camera`]'s samples per pixel times the number of pixels, but pixels in
+/// any of the regions take `weight` times as many as those outside. Every
+/// pixel gets at least one sample.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder, RegionOfInterest, SamplePriority},
+///     hit::Hittable,
+///     material::Lambertian,
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let camera = CameraBuilder::default()
+///     .set_image_width(8)
+///     .set_aspect_ratio(1.0)
+///     .set_anti_aliasing(AntiAliasing::Random(4))
+///     .build().unwrap();
+///
+/// // the left half of the image gets three times the samples
+/// let priority = SamplePriority::new(3.0).with_region(RegionOfInterest::Pixels {
+///     x: 0,
+///     y: 0,
+///     width: 4,
+///     height: 8,
+/// });
+/// let counts = camera.sample_counts(Some(&priority));
+/// assert_eq!(counts[0], 6);
+/// assert_eq!(counts[7], 2);
+/// assert_eq!(counts.iter().sum::<u32>(), 4 * 64);
+///
+/// // or wherever a picked object is on screen
+/// let hero = Sphere {
+///     centre: vec3![0.0, 0.0, -5.0],
+///     radius: 1.0,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// };
+/// let priority = SamplePriority::new(3.0).with_region(RegionOfInterest::Bound(hero.bound()));
+/// let counts = camera.sample_counts(Some(&priority));
+/// assert!(counts[4 * 8 + 4] > counts[0]);
+///
+/// assert_eq!(camera.sample_counts(None), vec![4; 64]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplePriority {
+    regions: Vec<RegionOfInterest>,
+    weight: f64,
+}
+
+impl SamplePriority {
+    /// Create a [`SamplePriority`] giving pixels in its regions `weight`
+    /// times the samples of the rest, without any regions yet.
+    ///
+    /// # Panics
+    ///
+    /// If `weight` isn't positive and finite.
+    #[must_use]
+    pub fn new(weight: f64) -> Self {
+        assert!(
+            weight > 0.0 && weight.is_finite(),
+            "Priority weight must be positive and finite"
+        );
+        Self {
+            regions: vec![],
+            weight,
+        }
+    }
+
+    /// Add `region` to the regions given priority.
+    #[must_use]
+    pub fn with_region(mut self, region: RegionOfInterest) -> Self {
+        self.regions.push(region);
+        self
+    }
+}
+
+/// A rectangle of pixels, `width` by `height` from `(x, y)` at its top left,
+/// for splitting a render between workers or backends. See
+/// [`Camera::tiles()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// The column of the left edge.
+    pub x: u32,
+    /// The row of the top edge.
+    pub y: u32,
+    /// The number of columns.
+    pub width: u32,
+    /// The number of rows.
+    pub height: u32,
+}
+
+impl Tile {
+    /// Create a new [`Tile`] of `width` by `height` pixels from `(x, y)`.
+    #[must_use]
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The pixels `(i, j)` of the tile, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |j| (self.x..self.x + self.width).map(move |i| (i, j)))
+    }
+}
+
+/// What a [ray](Ray)'s path has been through so far, for checking
+/// [`Visibility`](crate::material::Visibility).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PathState {
+    /// Whether the ray came straight from the camera.
+    pub(crate) primary: bool,
+    /// Whether the path has scattered off a diffuse surface.
+    pub(crate) after_diffuse: bool,
+}
+
+impl PathState {
+    /// A ray straight from the camera.
+    pub(crate) const CAMERA: Self = Self {
+        primary: true,
+        after_diffuse: false,
+    };
+
+    /// A ray leaving a diffuse surface, such as when baking.
+    pub(crate) const DIFFUSE: Self = Self {
+        primary: false,
+        after_diffuse: true,
+    };
+}
+
+/// The world a path is traced through and the extras the render collects or
+/// uses along the way, shared by every bounce, see [`Camera::ray_colour()`].
+pub(crate) struct Trace<'a> {
+    /// The world to trace.
+    pub(crate) world: &'a dyn Hittable,
+    /// Hits closer than this are ignored.
+    pub(crate) min_t: f64,
+    /// Where to record stats, if anywhere.
+    pub(crate) stats: Option<&'a mut StatsTally>,
+    /// The cache to take diffuse indirect lighting from, if any.
+    pub(crate) cache: Option<&'a IrradianceCache>,
+    /// The guide to steer diffuse bounces with, if any.
+    pub(crate) guide: Option<&'a PathGuide>,
+    /// Where to record bounces for the guide to learn from, if anywhere.
+    pub(crate) guide_samples: Option<&'a mut Vec<GuideSample>>,
+    /// The render layer of the first surface the camera ray saw, if it saw
+    /// one on a layer.
+    pub(crate) layer: Option<usize>,
+    /// The lights that only cast shadows, kept out of the world, if any.
+    pub(crate) shadow_lights: Option<&'a dyn Hittable>,
+}
+
+impl<'a> Trace<'a> {
+    /// Trace through `world`, ignoring hits closer than `min_t`.
+    pub(crate) fn new(world: &'a dyn Hittable, min_t: f64) -> Self {
+        Self {
+            world,
+            min_t,
+            stats: None,
+            cache: None,
+            guide: None,
+            guide_samples: None,
+            layer: None,
+            shadow_lights: None,
+        }
+    }
+
+    /// The light the shadow-only lights would bring along `r` but for
+    /// something in the way, for paths after a diffuse bounce.
+    fn blocked_shadow_light(&self, r: &Ray, path: PathState) -> Vec3 {
+        let Some(lights) = self.shadow_lights.filter(|_| path.after_diffuse) else {
+            return Vec3::default();
+        };
+        let Some(light) = lights.hit(r, interval![self.min_t, f64::INFINITY]) else {
+            return Vec3::default();
+        };
+        if self.world.hit(r, interval![self.min_t, light.t]).is_some() {
+            light.mat.emitted(r, &light)
+        } else {
+            Vec3::default()
+        }
+    }
+}
+
+/// The parts of [`RenderOptions`] every row of a render shares.
+#[derive(Clone, Copy, Default)]
+struct RenderExtras<'a> {
+    stats: Option<&'a RenderStats>,
+    cache: Option<&'a IrradianceCache>,
+    guide: Option<&'a PathGuide>,
+    layers: Option<&'a LayerFilm>,
+    shadow_lights: Option<&'a dyn Hittable>,
+}
+
+impl<'a> RenderExtras<'a> {
+    fn of(options: &RenderOptions<'a>) -> Self {
+        Self {
+            stats: options.stats,
+            cache: options.irradiance_cache,
+            guide: options.path_guide,
+            layers: options.layers,
+            shadow_lights: options.shadow_lights,
+        }
+    }
+}
+
+/// The tile size [`Camera::render()`] uses for Deep Zoom output, the usual
+/// size for viewers.
+const DEEP_ZOOM_TILE_SIZE: u32 = 256;
+
+/// The difference in tone mapped luminance between neighbouring pixels that
+/// makes them an edge for [`AntiAliasing::Edges`].
+const EDGE_CONTRAST: f64 = 0.1;
+
+/// The cosine of the angle between neighbouring pixels' surface normals
+/// below which they're an edge for [`AntiAliasing::Edges`].
+const EDGE_NORMAL_COS: f64 = 0.9;
+
+/// The difference in depth between neighbouring pixels, relative to the
+/// nearer, that makes them an edge for [`AntiAliasing::Edges`].
+const EDGE_DEPTH_RATIO: f64 = 0.05;
+
+/// What the centre [ray](Ray) of a pixel hit, for finding geometry edges.
+#[derive(Debug, Clone, Copy)]
+struct EdgeSurface {
+    t: f64,
+    norm: Vec3,
+    /// The address of the [`Material`](crate::material::Material), which
+    /// tells objects apart.
+    material: usize,
+}
+
+impl EdgeSurface {
+    /// Whether two pixels seeing `a` and `b` are either side of an edge.
+    fn differ(a: Option<Self>, b: Option<Self>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                a.material != b.material
+                    || dot(&a.norm, &b.norm) < EDGE_NORMAL_COS
+                    || (a.t - b.t).abs() > EDGE_DEPTH_RATIO * a.t.min(b.t)
+            }
+            (None, None) => false,
+            _ => true,
+        }
+    }
 }
 
 /// Trait to support [`AntiAliasing::Grid`].
 trait AntiAliasingGrid {
-    fn sample_grid(self, sample: u16) -> Result<Vec3>;
-    fn get_ray_grid(self, i: u32, j: u32, sample: u16, rng: &mut SmallRng) -> Result<Ray>;
+    fn sample_grid(&self, sample: u16) -> Result<Vec3>;
+    fn get_ray_grid(&self, i: u32, j: u32, sample: u16, rng: &mut SmallRng) -> Result<Ray>;
 }
 
 /// Trait to support [`AntiAliasing::Random`].
 trait AntiAliasingRandom {
-    fn sample_random(self, rng: &mut SmallRng) -> Result<Vec3>;
-    fn get_ray_random(self, i: u32, j: u32, rng: &mut SmallRng) -> Result<Ray>;
+    fn sample_random(&self, rng: &mut SmallRng) -> Result<Vec3>;
+    fn get_ray_random(&self, i: u32, j: u32, rng: &mut SmallRng) -> Result<Ray>;
 }
 
 /// Trait to support sampling a [`Ray`]'s starting point using a defocus disc.
@@ -47,19 +679,392 @@ trait Defocus {
     fn defocus_disc_sample(&self, rng: &mut SmallRng) -> Vec3;
 }
 
+/// A hook run after each pass of a progressive render, given the image so far
+/// and the index of the pass, see [`RenderOptions::after_pass()`].
+type PassHook<'a> = Box<dyn FnMut(&FilmBuffer, u32) -> ControlFlow<()> + 'a>;
+
+/// Options for [`Camera::render_with()`] and [`Camera::render_film_with()`].
+/// The samples per pixel can be split over several passes, each refining the
+/// image, with a hook after each one to run a denoiser, save or upload the
+/// image so far, or stop early.
+///
+/// # Example
+///
+/// ```rust
+/// use std::ops::ControlFlow;
+///
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder, RenderOptions},
+///     hit_list::HittableList,
+/// };
+///
+/// let camera = CameraBuilder::default()
+///     .set_image_width(8)
+///     .set_anti_aliasing(AntiAliasing::Random(16))
+///     .build().unwrap();
+///
+/// let mut passes_seen = vec![];
+/// let options = RenderOptions::default()
+///     .set_passes(4)
+///     .after_pass(|_film, pass| {
+///         passes_seen.push(pass);
+///         // stop once the image is good enough
+///         if pass == 1 {
+///             ControlFlow::Break(())
+///         } else {
+///             ControlFlow::Continue(())
+///         }
+///     });
+///
+/// let film = camera.render_film_with(&HittableList::new(), options);
+///
+/// assert_eq!(film.width(), 8);
+/// assert_eq!(passes_seen, [0, 1]);
+/// ```
+pub struct RenderOptions<'a> {
+    passes: u32,
+    after_pass: Option<PassHook<'a>>,
+    preview_levels: u32,
+    after_preview: Option<PassHook<'a>>,
+    priority: Option<SamplePriority>,
+    stats: Option<&'a RenderStats>,
+    irradiance_cache: Option<&'a IrradianceCache>,
+    path_guide: Option<&'a PathGuide>,
+    variance: Option<&'a VarianceFilm>,
+    adaptive: Option<f64>,
+    layers: Option<&'a LayerFilm>,
+    shadow_lights: Option<&'a dyn Hittable>,
+}
+
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        Self {
+            passes: 1,
+            after_pass: None,
+            preview_levels: 0,
+            after_preview: None,
+            priority: None,
+            stats: None,
+            irradiance_cache: None,
+            path_guide: None,
+            variance: None,
+            adaptive: None,
+            layers: None,
+            shadow_lights: None,
+        }
+    }
+}
+
+impl fmt::Debug for RenderOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderOptions")
+            .field("passes", &self.passes)
+            .field("after_pass", &self.after_pass.as_ref().map(|_| ".."))
+            .field("preview_levels", &self.preview_levels)
+            .field("after_preview", &self.after_preview.as_ref().map(|_| ".."))
+            .field("priority", &self.priority)
+            .field("stats", &self.stats.is_some())
+            .field("irradiance_cache", &self.irradiance_cache.is_some())
+            .field("path_guide", &self.path_guide.is_some())
+            .field("variance", &self.variance.is_some())
+            .field("adaptive", &self.adaptive)
+            .field("layers", &self.layers.is_some())
+            .field("shadow_lights", &self.shadow_lights.is_some())
+            .finish()
+    }
+}
+
+impl<'a> RenderOptions<'a> {
+    /// Set the number of passes to split the samples per pixel over. Capped at
+    /// the number of samples per pixel when rendering.
+    ///
+    /// # Panics
+    ///
+    /// If `passes` is `0`.
+    #[must_use]
+    pub fn set_passes(mut self, passes: u32) -> Self {
+        assert!(passes > 0, "Need at least one pass");
+        self.passes = passes;
+        self
+    }
+
+    /// Set a `hook` to run after each pass with the image so far and the index
+    /// of the pass. Returning [`ControlFlow::Break`] stops the render there.
+    #[must_use]
+    pub fn after_pass<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&FilmBuffer, u32) -> ControlFlow<()> + 'a,
+    {
+        self.after_pass = Some(Box::new(hook));
+        self
+    }
+
+    /// Start with `levels` coarse previews at one sample per pixel before the
+    /// first pass, so the whole composition shows almost at once rather than
+    /// line by line. The first preview traces one pixel in every block of
+    /// `2^levels` by `2^levels`, and each level after halves the blocks,
+    /// tracing only the pixels the levels before didn't (interleaved like an
+    /// Adam7 PNG). Each pixel fills its block until a finer level replaces
+    /// it. The previews cost under a third of a sample per pixel, and aren't
+    /// kept in the final image.
+    ///
+    /// # Panics
+    ///
+    /// If `levels` is more than [`MAX_PREVIEW_LEVELS`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::ops::ControlFlow;
+    ///
+    /// use rustrace::{
+    ///     camera::{AntiAliasing, CameraBuilder, RenderOptions},
+    ///     hit_list::HittableList,
+    /// };
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(16)
+    ///     .set_anti_aliasing(AntiAliasing::Random(4))
+    ///     .build().unwrap();
+    ///
+    /// let mut previews = vec![];
+    /// let options = RenderOptions::default()
+    ///     .set_preview_levels(3)
+    ///     .after_preview(|film, level| {
+    ///         previews.push((level, film.width()));
+    ///         ControlFlow::Continue(())
+    ///     });
+    ///
+    /// let film = camera.render_film_with(&HittableList::new(), options);
+    ///
+    /// // blocks of 8, 4 then 2 pixels, then the full render
+    /// assert_eq!(previews, [(0, 16), (1, 16), (2, 16)]);
+    /// assert_eq!(film.width(), 16);
+    /// ```
+    #[must_use]
+    pub fn set_preview_levels(mut self, levels: u32) -> Self {
+        assert!(
+            levels <= MAX_PREVIEW_LEVELS,
+            "Can't have more than {MAX_PREVIEW_LEVELS} preview levels"
+        );
+        self.preview_levels = levels;
+        self
+    }
+
+    /// Set a `hook` to run after each coarse preview with the preview and its
+    /// level, see [`RenderOptions::set_preview_levels()`]. Returning
+    /// [`ControlFlow::Break`] stops the render there.
+    #[must_use]
+    pub fn after_preview<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&FilmBuffer, u32) -> ControlFlow<()> + 'a,
+    {
+        self.after_preview = Some(Box::new(hook));
+        self
+    }
+
+    /// Spend more of the samples on the regions of `priority`. The passes
+    /// split each pixel's own samples, so pixels with fewer samples than
+    /// passes skip some of them.
+    #[must_use]
+    pub fn set_priority(mut self, priority: SamplePriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Collect where the passes spend their time into `stats`, by object and
+    /// by material. The previews aren't counted.
+    #[must_use]
+    pub fn set_stats(mut self, stats: &'a RenderStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Take diffuse indirect lighting from `cache`, filling it as the render
+    /// goes, see the [module docs](crate::irradiance). The previews don't use
+    /// it.
+    #[must_use]
+    pub fn set_irradiance_cache(mut self, cache: &'a IrradianceCache) -> Self {
+        self.irradiance_cache = Some(cache);
+        self
+    }
+
+    /// Steer diffuse bounces with `guide`, learning from each pass for the
+    /// next, see the [module docs](crate::guide). The previews don't use it.
+    #[must_use]
+    pub fn set_path_guide(mut self, guide: &'a PathGuide) -> Self {
+        self.path_guide = Some(guide);
+        self
+    }
+
+    /// Collect the sample statistics of every pixel into `variance`,
+    /// updated after each pass, see [`VarianceFilm`].
+    #[must_use]
+    pub fn set_variance(mut self, variance: &'a VarianceFilm) -> Self {
+        self.variance = Some(variance);
+        self
+    }
+
+    /// Split the render into an image per render layer in `layers`, see
+    /// [`LayerFilm`]. The previews don't add to it.
+    #[must_use]
+    pub fn set_layers(mut self, layers: &'a LayerFilm) -> Self {
+        self.layers = Some(layers);
+        self
+    }
+
+    /// Cast shadows from `lights` without lighting anything with them, see
+    /// [`LightEffect::ShadowOnly`].
+    /// Where a path leaving a diffuse surface would have reached one of the
+    /// lights but for something in the way, the light it would have brought
+    /// is taken away. The lights aren't part of the world, so can't be seen
+    /// or hit. The previews don't cast their shadows.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::{AntiAliasing, CameraBuilder, RenderOptions},
+    ///     hit_list::HittableList,
+    ///     material::{DiffuseLight, Lambertian, LightEffect},
+    ///     sphere::Sphere,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let grey = || Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5]));
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere { centre: vec3![0.0, -100.5, -1.0], radius: 100.0, mat: grey() }));
+    /// world.add(Box::new(Sphere { centre: vec3![0.0, 0.5, -1.5], radius: 0.3, mat: grey() }));
+    ///
+    /// let mut lights = HittableList::new();
+    /// lights.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 4.0, -1.5],
+    ///     radius: 1.0,
+    ///     mat: Box::new(DiffuseLight::new(vec3![4.0, 4.0, 4.0]).with_effect(LightEffect::ShadowOnly)),
+    /// }));
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(16)
+    ///     .set_anti_aliasing(AntiAliasing::Random(4))
+    ///     .set_sample_seed(1)
+    ///     .build().unwrap();
+    /// let lit = camera.render_film(&world);
+    /// let shadowed = camera.render_film_with(&world, RenderOptions::default().set_shadow_lights(&lights));
+    ///
+    /// // the same paths, only ever darkened
+    /// let pairs = || lit.pixels().iter().zip(shadowed.pixels());
+    /// assert!(pairs().all(|(a, b)| (0..3).all(|c| b[c] <= a[c])));
+    /// assert!(pairs().any(|(a, b)| b[0] < a[0]));
+    /// ```
+    #[must_use]
+    pub fn set_shadow_lights(mut self, lights: &'a dyn Hittable) -> Self {
+        self.shadow_lights = Some(lights);
+        self
+    }
+
+    /// Stop sampling pixels once they've converged: after each pass, pixels
+    /// with at least [`MIN_ADAPTIVE_SAMPLES`] whose 95% confidence interval
+    /// is within `tolerance` of their mean (see [`Welford::relative_error()`])
+    /// take no more samples, leaving the rest of the budget for the noisy
+    /// ones. Only has an effect with several
+    /// [passes](RenderOptions::set_passes()). Pixels that miss a small or
+    /// rarely found light in all of their first samples look converged and
+    /// stop early, so it suits scenes lit by large lights or the sky.
+    ///
+    /// # Panics
+    ///
+    /// If `tolerance` isn't positive and finite.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::{AntiAliasing, Background, CameraBuilder, RenderOptions},
+    ///     film::VarianceFilm,
+    ///     hit_list::HittableList,
+    ///     material::{DiffuseLight, Lambertian},
+    ///     sphere::Sphere,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// // a ground lit by a light overhead, under a black sky
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, -100.5, -1.0],
+    ///     radius: 100.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 4.0, -1.0],
+    ///     radius: 2.0,
+    ///     mat: Box::new(DiffuseLight::new(vec3![4.0, 4.0, 4.0])),
+    /// }));
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(8)
+    ///     .set_anti_aliasing(AntiAliasing::Random(64))
+    ///     .set_background(Background::Solid(vec3![0.0, 0.0, 0.0]))
+    ///     .build().unwrap();
+    /// let variance = VarianceFilm::new();
+    /// let options = RenderOptions::default()
+    ///     .set_passes(8)
+    ///     .set_adaptive(0.01)
+    ///     .set_variance(&variance);
+    /// let _film = camera.render_film_with(&world, options);
+    ///
+    /// // the black sky stops after two passes, the noisy ground keeps going
+    /// assert_eq!(variance.get(4, 0).count(), 16);
+    /// let noisy = (0..8).filter(|&x| variance.get(x, 7).count() == 64).count();
+    /// assert!(noisy >= 4);
+    /// ```
+    #[must_use]
+    pub fn set_adaptive(mut self, tolerance: f64) -> Self {
+        assert!(
+            tolerance > 0.0 && tolerance.is_finite(),
+            "Adaptive tolerance must be positive and finite"
+        );
+        self.adaptive = Some(tolerance);
+        self
+    }
+}
+
+/// The most [preview levels](RenderOptions::set_preview_levels()) a render
+/// can start with, whose first blocks are already wider than any image.
+pub const MAX_PREVIEW_LEVELS: u32 = 16;
+
+/// The fewest samples a pixel takes before
+/// [adaptive sampling](RenderOptions::set_adaptive()) can stop it, so its
+/// variance is a fair estimate.
+pub const MIN_ADAPTIVE_SAMPLES: u32 = 16;
+
+/// The most [overscan](CameraBuilder::set_overscan()) a camera can have, as a
+/// percentage: a whole frame more beyond each edge.
+pub const MAX_OVERSCAN: f64 = 100.0;
+
 /// Helper struct to build a [`Camera`] using the builder pattern.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct CameraBuilder {
     aspect_ratio: f64,
     image_width: u32,
     anti_aliasing: AntiAliasing,
     max_depth: u32,
-    vfov: u16,
+    vfov: f64,
     look_from: Vec3,
     look_at: Vec3,
     v_up: Vec3,
     defocus_angle: f64,
     focus_dist: f64,
+    ray_bias: RayBias,
+    shutter: Interval,
+    motion: Vec<Keyframe>,
+    firefly_filter: FireflyFilter,
+    depth_of_field: DepthOfField,
+    background: Background,
+    sample_seed: Option<u64>,
+    dither: bool,
+    overscan: f64,
 }
 
 impl Default for CameraBuilder {
@@ -69,12 +1074,21 @@ impl Default for CameraBuilder {
             image_width: 100,
             anti_aliasing: AntiAliasing::Grid(4),
             max_depth: 10,
-            vfov: 90,
+            vfov: 90.0,
             look_from: vec3![0.0, 0.0, 0.0],
             look_at: vec3![0.0, 0.0, -1.0],
             v_up: vec3![0.0, 1.0, 0.0],
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            ray_bias: RayBias::default(),
+            shutter: interval![0.0, 0.0],
+            motion: vec![],
+            firefly_filter: FireflyFilter::default(),
+            depth_of_field: DepthOfField::default(),
+            background: Background::default(),
+            sample_seed: None,
+            dither: true,
+            overscan: 0.0,
         }
     }
 }
@@ -117,6 +1131,13 @@ impl CameraBuilder {
     /// Set the vertical field of view of the [`CameraBuilder`].
     #[must_use]
     pub fn set_vfov(self, vfov: u16) -> CameraBuilder {
+        self.set_vfov_degrees(f64::from(vfov))
+    }
+
+    /// Set the vertical field of view of the [`CameraBuilder`] in fractional
+    /// degrees, such as when converted from a focal length.
+    #[must_use]
+    pub fn set_vfov_degrees(self, vfov: f64) -> CameraBuilder {
         CameraBuilder { vfov, ..self }
     }
 
@@ -153,53 +1174,360 @@ impl CameraBuilder {
         CameraBuilder { focus_dist, ..self }
     }
 
-    /// Build a [`Camera`] from the [`CameraBuilder`].
+    /// Set the [`RayBias`] used to offset secondary [ray](Ray)s from surfaces
+    /// for the [`CameraBuilder`].
     #[must_use]
-    pub fn build(self) -> Camera {
-        #[expect(clippy::cast_possible_truncation)]
-        #[expect(clippy::cast_sign_loss)]
-        let mut image_height = (f64::from(self.image_width) / self.aspect_ratio) as u32;
-        image_height = if image_height < 1 { 1 } else { image_height };
+    pub fn set_ray_bias(self, ray_bias: RayBias) -> CameraBuilder {
+        CameraBuilder { ray_bias, ..self }
+    }
 
-        let samples_scale = match self.anti_aliasing {
-            AntiAliasing::Grid(size) => 1.0 / f64::from(size.pow(2)),
-            AntiAliasing::Random(number) => 1.0 / f64::from(number),
-        };
+    /// Set the times the shutter opens and closes for the [`CameraBuilder`]. Each
+    /// [ray](Ray) is sent at a random time in this interval, blurring anything
+    /// that moves over it. Defaults to `0.0` to `0.0` (no motion blur).
+    #[must_use]
+    pub fn set_shutter(self, open: f64, close: f64) -> CameraBuilder {
+        CameraBuilder {
+            shutter: interval![open, close],
+            ..self
+        }
+    }
 
-        let centre = self.look_from;
+    /// Set the [`Keyframe`]s the [`CameraBuilder`] moves through while the
+    /// [shutter](CameraBuilder::set_shutter()) is open, relative to where it
+    /// looks from. Each [ray](Ray) leaves from the pose at its time, so camera
+    /// and object motion blur together. Defaults to no keyframes (a still
+    /// camera).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{camera::CameraBuilder, transform::Keyframe, vec3, vec3::Vec3};
+    ///
+    /// // Pan a metre to the right over the shutter.
+    /// let camera = CameraBuilder::default()
+    ///     .set_shutter(0.0, 1.0)
+    ///     .set_motion(vec![
+    ///         Keyframe::at(0.0, vec3![0.0, 0.0, 0.0]),
+    ///         Keyframe::at(1.0, vec3![1.0, 0.0, 0.0]),
+    ///     ])
+    ///     .build().unwrap();
+    ///
+    /// assert!(camera.is_moving());
+    /// ```
+    #[must_use]
+    pub fn set_motion(self, mut keyframes: Vec<Keyframe>) -> CameraBuilder {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        CameraBuilder {
+            motion: keyframes,
+            ..self
+        }
+    }
 
-        let theta = deg_to_rad(f64::from(self.vfov));
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * self.focus_dist;
-        let viewport_width =
-            viewport_height * (f64::from(self.image_width) / f64::from(image_height));
+    /// Set the [`FireflyFilter`] used to combine each pixel's samples for the
+    /// [`CameraBuilder`]. Defaults to [`FireflyFilter::None`].
+    #[must_use]
+    pub fn set_firefly_filter(self, firefly_filter: FireflyFilter) -> CameraBuilder {
+        CameraBuilder {
+            firefly_filter,
+            ..self
+        }
+    }
 
-        let w = (self.look_from - self.look_at).unit();
-        let u = (cross(&self.v_up, &w)).unit();
-        let v = cross(&w, &u);
+    /// Set how the [`DepthOfField`] is made for the [`CameraBuilder`].
+    /// Defaults to [`DepthOfField::Lens`].
+    #[must_use]
+    pub fn set_depth_of_field(self, depth_of_field: DepthOfField) -> CameraBuilder {
+        CameraBuilder {
+            depth_of_field,
+            ..self
+        }
+    }
 
-        let viewport_u = u * viewport_width;
-        let viewport_v = -v * viewport_height;
+    /// Set the [`Background`] of the [`CameraBuilder`], seen and lit by in
+    /// place of the default sky gradient.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::{Background, CameraBuilder},
+    ///     hit_list::HittableList,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(4)
+    ///     .set_background(Background::Solid(vec3![0.0, 0.0, 0.0]))
+    ///     .build().unwrap();
+    ///
+    /// let film = camera.render_film(&HittableList::new());
+    /// assert!(film.pixels().iter().all(|p| *p == vec3![0.0, 0.0, 0.0]));
+    /// ```
+    #[must_use]
+    pub fn set_background(self, background: Background) -> CameraBuilder {
+        CameraBuilder { background, ..self }
+    }
+
+    /// Seed every pixel's samples from `seed`, rather than from the system
+    /// random, so rendering the same scene again gives exactly the same
+    /// noise. For an animation, keep the seed the same for every frame, so
+    /// the noise stays put in the image rather than sparkling from frame to
+    /// frame, which is far less distracting at low samples per pixel.
+    ///
+    /// Each pixel's random numbers come from a hash of the seed and the
+    /// pixel, and with [`AntiAliasing::Random`] its sample positions follow
+    /// a low discrepancy sequence offset by a hashed Cranley-Patterson
+    /// rotation, so neighbouring pixels don't correlate. The lens position
+    /// and time of each sample are hashed from the sample alone, so the
+    /// camera [rays](Ray) are the same however the render is split up, see
+    /// [`Camera::generate_rays()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::{AntiAliasing, CameraBuilder},
+    ///     hit_list::HittableList,
+    ///     material::{DiffuseLight, Lambertian},
+    ///     sphere::Sphere,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, -2.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(8)
+    ///     .set_anti_aliasing(AntiAliasing::Random(2))
+    ///     .set_sample_seed(42)
+    ///     .build().unwrap();
+    ///
+    /// let film = camera.render_film(&world);
+    /// assert_eq!(film.pixels(), camera.render_film(&world).pixels());
+    /// ```
+    #[must_use]
+    pub fn set_sample_seed(self, seed: u64) -> CameraBuilder {
+        CameraBuilder {
+            sample_seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// Set whether the 8-bit images of the [`CameraBuilder`]'s renders are
+    /// dithered, which they are by default, to avoid banding in smooth
+    /// gradients, see [`FilmBuffer::with_dither()`]. Turning it off makes
+    /// the images exact quantisations of the films, for regression
+    /// comparisons.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{camera::CameraBuilder, hit_list::HittableList};
+    ///
+    /// let world = HittableList::new();
+    /// let camera = CameraBuilder::default().set_image_width(4).build().unwrap();
+    /// assert!(camera.render_film(&world).is_dithered());
+    ///
+    /// let exact = CameraBuilder::default().set_image_width(4).set_dither(false).build().unwrap();
+    /// assert!(!exact.render_film(&world).is_dithered());
+    /// ```
+    #[must_use]
+    pub fn set_dither(self, dither: bool) -> CameraBuilder {
+        CameraBuilder { dither, ..self }
+    }
+
+    /// Set the overscan of the [`CameraBuilder`]: how far to render beyond
+    /// each edge of the frame, as a percentage of its width and height. The
+    /// frame itself and its field of view are unchanged, so a compositor can
+    /// shake, stabilise or distort the image without revealing empty edges.
+    /// [`Camera::render_film()`] returns the whole image, and it's cropped
+    /// back to the frame when saved, see [`Camera::crop_overscan()`].
+    ///
+    /// # Panics
+    ///
+    /// If `percent` is negative, not finite or more than [`MAX_OVERSCAN`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::{AntiAliasing, CameraBuilder, Tile},
+    ///     hit_list::HittableList,
+    ///     material::Lambertian,
+    ///     sphere::Sphere,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, -2.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let builder = CameraBuilder::default()
+    ///     .set_image_width(20)
+    ///     .set_aspect_ratio(2.0)
+    ///     .set_anti_aliasing(AntiAliasing::Random(2))
+    ///     .set_sample_seed(3);
+    /// let camera = builder.clone().build().unwrap();
+    /// let overscanned = builder.set_overscan(10.0).build().unwrap();
+    ///
+    /// // two pixels more on each side and one more above and below
+    /// assert_eq!((overscanned.image_width, overscanned.image_height()), (24, 12));
+    /// assert_eq!(overscanned.frame(), Tile::new(2, 1, 20, 10));
+    ///
+    /// // the frame looks through the same pixels
+    /// let film = overscanned.render_film(&world);
+    /// let frame = overscanned.crop_overscan(&film);
+    /// assert_eq!((frame.width(), frame.height()), (20, 10));
+    /// let plain = camera.render_film(&world);
+    /// let matches = (0..10)
+    ///     .flat_map(|j| (0..20).map(move |i| (i, j)))
+    ///     .filter(|&(i, j)| (frame.get(i, j) - plain.get(i, j)).length() < 0.2)
+    ///     .count();
+    /// assert!(matches > 180);
+    /// ```
+    #[must_use]
+    pub fn set_overscan(self, percent: f64) -> CameraBuilder {
+        assert!(
+            (0.0..=MAX_OVERSCAN).contains(&percent),
+            "Overscan must be a percentage between 0 and {MAX_OVERSCAN}"
+        );
+        CameraBuilder {
+            overscan: percent,
+            ..self
+        }
+    }
+
+    /// Set an [`EnvironmentMap`] as the [`Background`] of the
+    /// [`CameraBuilder`], see [`Background::Environment`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use rustrace::{
+    ///     camera::CameraBuilder, environment::EnvironmentMap, film::FilmBuffer,
+    ///     hit_list::HittableList, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let red = EnvironmentMap::new(FilmBuffer::from_rows(vec![vec![vec3![1.0, 0.0, 0.0]; 2]]));
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(4)
+    ///     .set_environment(Arc::new(red))
+    ///     .build().unwrap();
+    ///
+    /// let film = camera.render_film(&HittableList::new());
+    /// assert_eq!(film.get(1, 2), vec3![1.0, 0.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn set_environment(self, environment: Arc<EnvironmentMap>) -> CameraBuilder {
+        self.set_background(Background::Environment(environment))
+    }
+
+    /// Build a [`Camera`] from the [`CameraBuilder`].
+    ///
+    /// # Errors
+    ///
+    /// If the image width is zero, the aspect ratio isn't positive and
+    /// finite, or the image would be too large to count its rows or columns,
+    /// including any overscan.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::camera::CameraBuilder;
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(8)
+    ///     .set_aspect_ratio(2.0)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(camera.image_height(), 4);
+    ///
+    /// assert!(CameraBuilder::default().set_image_width(0).build().is_err());
+    /// assert!(CameraBuilder::default().set_aspect_ratio(0.0).build().is_err());
+    /// assert!(CameraBuilder::default().set_aspect_ratio(1e-12).build().is_err());
+    ///
+    /// // too wide to count its columns with a whole frame more on each side
+    /// let wide = CameraBuilder::default().set_image_width(u32::MAX / 2).set_aspect_ratio(1e9);
+    /// assert!(wide.set_overscan(100.0).build().is_err());
+    /// ```
+    pub fn build(self) -> Result<Camera> {
+        if self.image_width == 0 {
+            bail!("The image width must be at least one pixel");
+        }
+        if !(self.aspect_ratio > 0.0 && self.aspect_ratio.is_finite()) {
+            bail!("The aspect ratio must be positive and finite");
+        }
+        let image_height = (f64::from(self.image_width) / self.aspect_ratio).max(1.0);
+        if image_height > f64::from(u32::MAX) {
+            bail!("The image is too tall for its width and aspect ratio");
+        }
+        #[expect(clippy::cast_possible_truncation)]
+        #[expect(clippy::cast_sign_loss)]
+        let image_height = image_height as u32;
+
+        let centre = self.look_from;
+
+        let theta = deg_to_rad(self.vfov);
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h * self.focus_dist;
+        let viewport_width =
+            viewport_height * (f64::from(self.image_width) / f64::from(image_height));
+
+        let w = (self.look_from - self.look_at).unit();
+        let u = (cross(&self.v_up, &w)).unit();
+        let v = cross(&w, &u);
+
+        let viewport_u = u * viewport_width;
+        let viewport_v = -v * viewport_height;
 
         let pixel_delta_u = viewport_u / self.image_width;
         let pixel_delta_v = viewport_v / image_height;
 
         let viewport_upper_left = centre - (w * self.focus_dist) - viewport_u / 2 - viewport_v / 2;
 
-        let pixel00_loc = viewport_upper_left + (pixel_delta_u + pixel_delta_v) * 0.5;
+        // the overscan extends the grid of pixels past the frame's viewport
+        let pad = |length: u32| {
+            #[expect(clippy::cast_possible_truncation)]
+            #[expect(clippy::cast_sign_loss)]
+            let pad = (f64::from(length) * self.overscan / 100.0).round() as u32;
+            pad
+        };
+        let overscan = [pad(self.image_width), pad(image_height)];
+        let overscanned = |length: u32, pad: u32| {
+            pad.checked_mul(2)
+                .and_then(|pad| length.checked_add(pad))
+                .ok_or_else(|| anyhow!("The image is too large with its overscan"))
+        };
+        let overscanned_width = overscanned(self.image_width, overscan[0])?;
+        let overscanned_height = overscanned(image_height, overscan[1])?;
+        let pixel00_loc = viewport_upper_left + (pixel_delta_u + pixel_delta_v) * 0.5
+            - pixel_delta_u * overscan[0]
+            - pixel_delta_v * overscan[1];
 
         let defocus_rad = self.focus_dist * deg_to_rad(self.defocus_angle / 2.0).tan();
         let defocus_disc_u = u * defocus_rad;
         let defocus_disc_v = v * defocus_rad;
 
-        Camera {
+        Ok(Camera {
             // aspect_ratio: self.aspect_ratio,
-            image_width: self.image_width,
+            image_width: overscanned_width,
             anti_aliasing: self.anti_aliasing,
             max_depth: self.max_depth,
             defocus_angle: self.defocus_angle,
-            image_height,
-            samples_scale,
+            image_height: overscanned_height,
+            overscan,
             centre,
             pixel00_loc,
             pixel_delta_u,
@@ -209,21 +1537,33 @@ impl CameraBuilder {
             // w,
             defocus_disc_u,
             defocus_disc_v,
-        }
+            ray_bias: self.ray_bias,
+            shutter: self.shutter,
+            motion: self.motion,
+            firefly_filter: self.firefly_filter,
+            depth_of_field: self.depth_of_field,
+            focus_dist: self.focus_dist,
+            background: self.background,
+            sample_seed: self.sample_seed,
+            dither: self.dither,
+            render_origin: vec3![0.0, 0.0, 0.0],
+        })
     }
 }
 
 /// The main structure that holds camera information and implements [rendering](Camera::render()).
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Camera {
     // pub aspect_ratio: f64,
     /// The supersampling anti-aliasing mode and configuration.
     pub anti_aliasing: AntiAliasing,
-    /// The width of the resulting image for a [render](Camera::render()).
+    /// The width of the image rendered, including any
+    /// [overscan](CameraBuilder::set_overscan()).
     pub image_width: u32,
     image_height: u32,
-    samples_scale: f64,
-    max_depth: u32,
+    /// The pixels of overscan on the left and right, and above and below.
+    overscan: [u32; 2],
+    pub(crate) max_depth: u32,
     centre: Vec3,
     pixel00_loc: Vec3,
     pixel_delta_u: Vec3,
@@ -234,12 +1574,27 @@ pub struct Camera {
     defocus_angle: f64,
     defocus_disc_u: Vec3,
     defocus_disc_v: Vec3,
+    pub(crate) ray_bias: RayBias,
+    shutter: Interval,
+    motion: Vec<Keyframe>,
+    firefly_filter: FireflyFilter,
+    /// How defocus blur is made.
+    pub depth_of_field: DepthOfField,
+    focus_dist: f64,
+    pub(crate) background: Background,
+    /// Seeds every pixel's samples when set, see
+    /// [`CameraBuilder::set_sample_seed()`].
+    pub sample_seed: Option<u64>,
+    /// Whether the 8-bit images of renders are dithered, see
+    /// [`CameraBuilder::set_dither()`].
+    pub dither: bool,
+    render_origin: Vec3,
 }
 
 // TODO: fix
 impl AntiAliasingGrid for Camera {
     /// Sample on a unit grid with sample number `sample`.
-    fn sample_grid(self, sample: u16) -> Result<Vec3> {
+    fn sample_grid(&self, sample: u16) -> Result<Vec3> {
         if let AntiAliasing::Grid(size) = self.anti_aliasing {
             let grid_size = f64::from(size);
             Ok(vec3![
@@ -255,7 +1610,7 @@ impl AntiAliasingGrid for Camera {
     }
 
     /// Get a [ray](Ray) with grid sampling.
-    fn get_ray_grid(self, i: u32, j: u32, sample: u16, rng: &mut SmallRng) -> Result<Ray> {
+    fn get_ray_grid(&self, i: u32, j: u32, sample: u16, rng: &mut SmallRng) -> Result<Ray> {
         let offset = self.sample_grid(sample)?;
         let pixel_sample = self.pixel00_loc
             + (self.pixel_delta_u * (f64::from(i) + offset[0]))
@@ -269,16 +1624,16 @@ impl AntiAliasingGrid for Camera {
 
         let ray_direction = pixel_sample - ray_origin;
 
-        Ok(ray!(ray_origin, ray_direction))
+        Ok(self.in_motion(ray!(ray_origin, ray_direction, self.sample_time(rng))))
     }
 }
 
 impl AntiAliasingRandom for Camera {
     /// Randomly sample a [ray](Ray) offset in a unit square.
-    fn sample_random(self, rng: &mut SmallRng) -> Result<Vec3> {
+    fn sample_random(&self, rng: &mut SmallRng) -> Result<Vec3> {
         #[allow(clippy::match_wildcard_for_single_variants)]
         match self.anti_aliasing {
-            AntiAliasing::Random(_) => Ok(vec3![
+            AntiAliasing::Random(_) | AntiAliasing::Edges { .. } => Ok(vec3![
                 rng.random_range(-0.5..=0.5),
                 rng.random_range(-0.5..=0.5),
                 0.0
@@ -290,21 +1645,9 @@ impl AntiAliasingRandom for Camera {
     }
 
     /// Randomly sample a [ray](Ray).
-    fn get_ray_random(self, i: u32, j: u32, rng: &mut SmallRng) -> Result<Ray> {
+    fn get_ray_random(&self, i: u32, j: u32, rng: &mut SmallRng) -> Result<Ray> {
         let offset = self.sample_random(rng)?;
-        let pixel_sample = self.pixel00_loc
-            + (self.pixel_delta_u * (f64::from(i) + offset[0]))
-            + (self.pixel_delta_v * (f64::from(j) + offset[1]));
-
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.centre
-        } else {
-            self.defocus_disc_sample(rng)
-        };
-
-        let ray_direction = pixel_sample - ray_origin;
-
-        Ok(ray!(ray_origin, ray_direction))
+        Ok(self.ray_through(i, j, offset, rng))
     }
 }
 
@@ -317,25 +1660,433 @@ impl Defocus for Camera {
 }
 
 impl Camera {
+    /// Re-centre `world` on the [`Camera`] to keep precision when the scene is
+    /// far from the origin (say at coordinates around `10^6` or more), where
+    /// floating point intersections start to jitter and cause acne. Everything
+    /// is moved by minus the camera's position (a render-space translation),
+    /// so intersections happen near zero where floats are most precise, and
+    /// the resulting image is the same.
+    ///
+    /// Positions later given to the camera, such as for
+    /// [`Camera::render_probe()`], are still in the original world space.
+    /// Anything added to `world` afterwards must be moved by
+    /// [`Camera::render_origin()`] too.
+    ///
+    /// Returns whether every object could be moved (see
+    /// [`Hittable::translate()`]). If not, those objects are left where they
+    /// were and the scene is out of place.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::CameraBuilder, hit::Hittable, hit_list::HittableList,
+    ///     material::Lambertian, sphere::Sphere, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let far = vec3![1.0e9, 0.0, 1.0e9];
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: far + vec3![0.0, 0.0, -5.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let mut camera = CameraBuilder::default()
+    ///     .set_look_from(far)
+    ///     .set_look_at(far + vec3![0.0, 0.0, -1.0])
+    ///     .build().unwrap();
+    ///
+    /// assert!(camera.recentre(&mut world));
+    /// assert_eq!(camera.render_origin(), far);
+    /// assert_eq!(world.bound().centroid(), vec3![0.0, 0.0, -5.0]);
+    /// ```
+    #[must_use]
+    pub fn recentre(&mut self, world: &mut dyn Hittable) -> bool {
+        let offset = self.centre;
+        let moved = world.translate(-offset);
+
+        self.centre -= offset;
+        self.pixel00_loc -= offset;
+        self.render_origin += offset;
+        moved
+    }
+
+    /// Where the world's origin has been moved to by [`Camera::recentre()`].
+    #[must_use]
+    pub fn render_origin(&self) -> Vec3 {
+        self.render_origin
+    }
+
+    /// The height of the image rendered, from the width and aspect ratio,
+    /// including any [overscan](CameraBuilder::set_overscan()).
+    #[must_use]
+    pub fn image_height(&self) -> u32 {
+        self.image_height
+    }
+
+    /// The frame within the image rendered, which is all of it unless there
+    /// is [overscan](CameraBuilder::set_overscan()).
+    #[must_use]
+    pub fn frame(&self) -> Tile {
+        let [x, y] = self.overscan;
+        Tile::new(x, y, self.image_width - 2 * x, self.image_height - 2 * y)
+    }
+
+    /// Crop a `film` rendered by the [`Camera`] to its [frame](Camera::frame()),
+    /// as it's saved.
+    ///
+    /// # Panics
+    ///
+    /// If `film` is smaller than the image rendered.
+    #[must_use]
+    pub fn crop_overscan(&self, film: &FilmBuffer) -> FilmBuffer {
+        let frame = self.frame();
+        film.cropped(frame.x, frame.y, frame.width, frame.height)
+    }
+
+    /// The same [`Camera`] without its overscan, for renders that would
+    /// only crop it away.
+    fn without_overscan(&self) -> Camera {
+        let frame = self.frame();
+        Camera {
+            image_width: frame.width,
+            image_height: frame.height,
+            overscan: [0, 0],
+            pixel00_loc: self.pixel00_loc
+                + self.pixel_delta_u * frame.x
+                + self.pixel_delta_v * frame.y,
+            ..self.clone()
+        }
+    }
+
+    /// Split the image into [`Tile`]s of `size` by `size` pixels, row by row,
+    /// with smaller tiles along the right and bottom edges if the image
+    /// doesn't divide evenly.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is `0`.
+    #[must_use]
+    pub fn tiles(&self, size: u32) -> Vec<Tile> {
+        assert!(size > 0, "Tiles need at least one pixel");
+        let starts = |length: u32| (0..length).step_by(size as usize);
+        starts(self.image_height)
+            .flat_map(|y| {
+                starts(self.image_width).map(move |x| {
+                    Tile::new(
+                        x,
+                        y,
+                        size.min(self.image_width - x),
+                        size.min(self.image_height - y),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Generate every camera [ray](Ray) a render takes through the pixels of
+    /// `tile`, pixel by pixel and sample by sample, for alternative backends
+    /// (GPU, packet tracers and the like) to trace the same primary rays as
+    /// [`Camera::render()`]. With a
+    /// [sample seed](CameraBuilder::set_sample_seed()) they're exactly the
+    /// rays a render traces, so images from different backends can be
+    /// compared bit for bit. Without one they're random, from the same
+    /// distribution.
+    ///
+    /// # Panics
+    ///
+    /// If `tile` isn't within the image, or if unable to get or use the
+    /// system random.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::camera::{AntiAliasing, CameraBuilder, Tile};
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(20)
+    ///     .set_aspect_ratio(2.0)
+    ///     .set_anti_aliasing(AntiAliasing::Random(4))
+    ///     .set_sample_seed(7)
+    ///     .build().unwrap();
+    ///
+    /// let tiles = camera.tiles(8);
+    /// assert_eq!(tiles.len(), 6);
+    /// assert_eq!(tiles[5], Tile::new(16, 8, 4, 2));
+    ///
+    /// // the same rays whichever way the image is split
+    /// let whole = camera.generate_rays(Tile::new(0, 0, 20, 10));
+    /// assert_eq!(whole.len(), 20 * 10 * 4);
+    /// let corner = camera.generate_rays(tiles[5]);
+    /// let start = whole.pixels.iter().position(|&p| p == (16, 8)).unwrap();
+    /// assert_eq!(corner.ray(0).direction, whole.ray(start).direction);
+    /// assert_eq!(corner.samples[..4], [0, 1, 2, 3]);
+    /// ```
+    #[must_use]
+    pub fn generate_rays(&self, tile: Tile) -> RayBatch {
+        assert!(
+            tile.x.saturating_add(tile.width) <= self.image_width
+                && tile.y.saturating_add(tile.height) <= self.image_height,
+            "Tile must be within the image"
+        );
+        let count = self.samples_per_pixel();
+        let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
+        let mut batch = RayBatch::with_capacity((tile.width * tile.height * count) as usize);
+        for (i, j) in tile.pixels() {
+            for sample in 0..count {
+                batch.push(&self.get_ray(i, j, sample, count, &mut rng), (i, j), sample);
+            }
+        }
+        batch
+    }
+
+    /// The number of samples taken per pixel for the current [`AntiAliasing`]
+    /// mode, or with [`AntiAliasing::Edges`] the samples every pixel takes
+    /// before edges are refined.
+    #[must_use]
+    pub fn samples_per_pixel(&self) -> u32 {
+        match self.anti_aliasing {
+            AntiAliasing::Grid(size) => u32::from(size).pow(2),
+            AntiAliasing::Random(number) | AntiAliasing::Edges { base: number, .. } => {
+                u32::from(number)
+            }
+        }
+    }
+
+    /// The number of samples for each pixel, row by row, with any samples
+    /// moved towards the regions of `priority`. See [`SamplePriority`].
+    #[must_use]
+    pub fn sample_counts(&self, priority: Option<&SamplePriority>) -> Vec<u32> {
+        let samples = self.samples_per_pixel();
+        let pixels =
+            (0..self.image_height).flat_map(|j| (0..self.image_width).map(move |i| (i, j)));
+        let Some(priority) = priority else {
+            return pixels.map(|_| samples).collect();
+        };
+
+        let inside: Vec<bool> = pixels
+            .map(|(i, j)| priority.regions.iter().any(|r| r.contains(self, i, j)))
+            .collect();
+        #[expect(clippy::cast_precision_loss)]
+        let (total, prioritised) = (
+            inside.len() as f64,
+            inside.iter().filter(|&&p| p).count() as f64,
+        );
+
+        // keep the total, with prioritised pixels weighted
+        let base =
+            f64::from(samples) * total / (priority.weight * prioritised + (total - prioritised));
+        #[expect(clippy::cast_possible_truncation)]
+        #[expect(clippy::cast_sign_loss)]
+        let count = |weight: f64| (base * weight).round().clamp(1.0, f64::from(u32::MAX)) as u32;
+        let (inside_count, outside_count) = (count(priority.weight), count(1.0));
+        inside
+            .into_iter()
+            .map(|p| if p { inside_count } else { outside_count })
+            .collect()
+    }
+
+    /// Whether the camera has [motion](CameraBuilder::set_motion()).
+    #[must_use]
+    pub fn is_moving(&self) -> bool {
+        !self.motion.is_empty()
+    }
+
+    /// Move a [ray](Ray) leaving the camera by its
+    /// [motion](CameraBuilder::set_motion()) at the ray's time, about the
+    /// camera's centre.
+    fn in_motion(&self, r: Ray) -> Ray {
+        if self.motion.is_empty() {
+            return r;
+        }
+
+        let pose = Keyframe::interpolate(&self.motion, r.time).transform();
+        Transform::translate(-self.centre)
+            .then(&pose)
+            .then(&Transform::translate(self.centre))
+            .ray(&r)
+    }
+
+    /// Sample a random time within the shutter interval.
+    pub(crate) fn sample_time(&self, rng: &mut SmallRng) -> f64 {
+        if self.shutter.size() > 0.0 {
+            rng.random_range(self.shutter.min..=self.shutter.max)
+        } else {
+            self.shutter.min
+        }
+    }
+
     /// Colour a [ray](Ray) recursively with a max further `depth`. Uses [`crate::material::Material::scatter()`]
-    /// to decide attenuation and the next ray direction.
-    fn ray_colour(r: &Ray, depth: u32, world: &dyn Hittable, rng: &mut SmallRng) -> Vec3 {
+    /// to decide attenuation and the next ray direction, adding any light the surface
+    /// [emits](crate::material::Material::emitted()) on top. Paths end early on
+    /// surfaces that absorb everything, such as lights. Hits closer than the
+    /// [`Trace`]'s `min_t` are ignored, as are surfaces whose [`Visibility`](crate::material::Visibility) hides them from a ray
+    /// with the given `path` so far. Paths after a diffuse bounce go straight
+    /// through materials that [fake their caustics](crate::material::Material::fake_caustic()),
+    /// and lose the light of any shadow-only lights they're blocked from.
+    pub(crate) fn ray_colour(
+        &self,
+        r: &Ray,
+        path: PathState,
+        depth: u32,
+        trace: &mut Trace<'_>,
+        rng: &mut SmallRng,
+    ) -> Vec3 {
         if depth == 0 {
             return vec3![0.0, 0.0, 0.0];
         }
+        self.path_colour(r, path, depth, trace, rng) - trace.blocked_shadow_light(r, path)
+    }
 
-        if let Some(rec) = world.hit(r, interval![0.001, f64::INFINITY]) {
+    /// The light along `r` from the path traced on from it, without the
+    /// shadows of shadow-only lights, which passing straight through a
+    /// surface mustn't take away twice. See [`Camera::ray_colour()`].
+    fn path_colour(
+        &self,
+        r: &Ray,
+        path: PathState,
+        depth: u32,
+        trace: &mut Trace<'_>,
+        rng: &mut SmallRng,
+    ) -> Vec3 {
+        if depth == 0 {
+            return vec3![0.0, 0.0, 0.0];
+        }
+
+        let started = trace.stats.is_some().then(Instant::now);
+        if let Some(rec) = trace.world.hit(r, interval![trace.min_t, f64::INFINITY]) {
+            let visibility = rec.mat.visibility();
+            let effect = rec.mat.light_effect();
+            if (!path.primary && !visibility.secondary)
+                || (path.after_diffuse && !visibility.caustics)
+                || effect == LightEffect::ShadowOnly
+                || (path.primary && effect == LightEffect::Negative)
+            {
+                if let (Some(tally), Some(started)) = (trace.stats.as_deref_mut(), started) {
+                    tally.record_hit(&rec, started.elapsed());
+                }
+                // carry straight on as if the surface wasn't there
+                let through = ray![rec.p, r.direction, r.time];
+                return self.path_colour(&through, path, depth - 1, trace, rng);
+            }
+
+            if let Some(through) = path
+                .after_diffuse
+                .then(|| rec.mat.fake_caustic(r, &rec))
+                .flatten()
+            {
+                if let (Some(tally), Some(started)) = (trace.stats.as_deref_mut(), started) {
+                    tally.record_hit(&rec, started.elapsed());
+                }
+                // light through glass faking its caustics goes straight on
+                let straight = ray![rec.p, r.direction, r.time];
+                return through * self.path_colour(&straight, path, depth - 1, trace, rng);
+            }
+
+            if path.primary {
+                trace.layer = rec.layer;
+            }
+            let next = PathState {
+                primary: false,
+                after_diffuse: path.after_diffuse || rec.mat.is_diffuse(),
+            };
+            let emitted = rec.mat.emitted(r, &rec);
             let (scattered, attenuation) = rec.mat.scatter(r, &rec, Some(rng));
-            return attenuation * Camera::ray_colour(&scattered, depth - 1, world, rng);
+            if let (Some(tally), Some(started)) = (trace.stats.as_deref_mut(), started) {
+                tally.record_hit(&rec, started.elapsed());
+            }
+            if attenuation.near_zero() {
+                return emitted;
+            }
+            if let Some(cache) = trace.cache {
+                if path.after_diffuse && rec.mat.is_diffuse() {
+                    let incoming = cache.lookup(rec.p, rec.norm).unwrap_or_else(|| {
+                        self.gather_irradiance(cache, &rec, r.time, depth, trace, rng)
+                    });
+                    return emitted + attenuation * incoming;
+                }
+            }
+            if let (Some(guide), Some(albedo)) = (trace.guide, rec.mat.lambertian_albedo(r, &rec)) {
+                let Some(bounce) = guide.bounce(rec.p, rec.norm, rng) else {
+                    return emitted;
+                };
+                let guided = ray![rec.p, bounce.direction, r.time];
+                let incoming = self.ray_colour(&guided, next, depth - 1, trace, rng);
+                if let Some(samples) = trace.guide_samples.as_deref_mut() {
+                    samples.push(bounce.sample(rec.p, incoming));
+                }
+                return emitted + albedo * incoming * bounce.weight;
+            }
+            return emitted
+                + attenuation * self.ray_colour(&scattered, next, depth - 1, trace, rng);
+        }
+
+        let colour = self.background.radiance(r.direction);
+        if let (Some(tally), Some(started)) = (trace.stats.as_deref_mut(), started) {
+            tally.record_miss(started.elapsed());
+        }
+        colour
+    }
+
+    /// Trace the [`IrradianceCache`]'s samples over the hemisphere above `rec`,
+    /// cosine-weighted and stratified, and add a record of the mean radiance
+    /// arriving, which is returned. The rays are path traced without the
+    /// cache or stats, with the `depth` left.
+    fn gather_irradiance(
+        &self,
+        cache: &IrradianceCache,
+        rec: &HitRecord,
+        time: f64,
+        depth: u32,
+        trace: &Trace<'_>,
+        rng: &mut SmallRng,
+    ) -> Vec3 {
+        let mut plain = Trace {
+            shadow_lights: trace.shadow_lights,
+            ..Trace::new(trace.world, trace.min_t)
+        };
+        let (tangent, bitangent) = rec.norm.orthonormal_basis();
+
+        // a square grid of strata over the unit square, mapped onto the
+        // hemisphere with Malley's method
+        #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let side = f64::from(cache.samples()).sqrt().ceil() as u32;
+        let mut radiance = vec3![0.0, 0.0, 0.0];
+        let mut inverse_distance = 0.0;
+        for a in 0..side {
+            for b in 0..side {
+                let across = (f64::from(a) + rng.random_range(0.0..1.0)) / f64::from(side);
+                let around = (f64::from(b) + rng.random_range(0.0..1.0)) / f64::from(side);
+                let (sin, cos) = (std::f64::consts::TAU * around).sin_cos();
+                let spread = across.sqrt();
+                let dir = tangent * (spread * cos)
+                    + bitangent * (spread * sin)
+                    + rec.norm * (1.0 - across).sqrt();
+
+                let r = ray!(rec.p, dir, time);
+                if let Some(hit) = plain.world.hit(&r, interval![plain.min_t, f64::INFINITY]) {
+                    inverse_distance += 1.0 / hit.t;
+                }
+                radiance += self.ray_colour(&r, PathState::DIFFUSE, depth - 1, &mut plain, rng);
+            }
         }
 
-        let unit_dir = r.direction.unit();
-        let a = (unit_dir[1] + 1.0) * 0.5;
-        vec3![1.0, 1.0, 1.0] * (1.0 - a) + vec3![0.5, 0.7, 1.0] * a
+        let count = f64::from(side * side);
+        let radiance = radiance / count;
+        let distance = if inverse_distance > 0.0 {
+            count / inverse_distance
+        } else {
+            f64::INFINITY
+        };
+        cache.insert(rec.p, rec.norm, radiance, distance);
+        radiance
     }
 
     /// Render the given `world`, outputting to the file at path `output`.
-    /// Uses [rayon] to paralellise rendering rows.
+    /// Uses [rayon] to paralellise rendering rows. Paths ending in `.dzi` are
+    /// rendered tile by tile into a Deep Zoom pyramid, see
+    /// [`Camera::render_deep_zoom()`].
     ///
     /// # Panics
     ///
@@ -346,59 +2097,1157 @@ impl Camera {
     /// # Errors
     ///
     /// May throw an error if image saving fails.
-    pub fn render(self, output: &str, world: &dyn Hittable) -> Result<()> {
-        let img = Mutex::new(RgbImage::new(self.image_width, self.image_height));
-        let lines_done = AtomicUsize::new(0);
+    pub fn render(&self, output: &str, world: &dyn Hittable) -> Result<()> {
+        let is_dzi = Path::new(output)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("dzi"));
+        if is_dzi {
+            return self.render_deep_zoom(output, world, DEEP_ZOOM_TILE_SIZE);
+        }
+        self.render_with(output, world, RenderOptions::default())
+    }
 
-        (0..self.image_height).into_par_iter().for_each(|j| {
-            let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
-            let mut row = vec![];
-            for i in 0..self.image_width {
-                let mut pixel_colour = vec3![0.0, 0.0, 0.0];
-
-                match self.anti_aliasing {
-                    AntiAliasing::Grid(size) => {
-                        for sample in 0..size.pow(2) {
-                            let r = self.get_ray_grid(i, j, sample, &mut rng).unwrap();
-                            pixel_colour += Camera::ray_colour(&r, self.max_depth, world, &mut rng);
-                        }
-                    }
-                    AntiAliasing::Random(number) => {
-                        for _ in 0..number {
-                            let r = self.get_ray_random(i, j, &mut rng).unwrap();
-                            pixel_colour += Camera::ray_colour(&r, self.max_depth, world, &mut rng);
-                        }
+    /// Render the given `world` one [`Tile`] of `tile_size` pixels at a
+    /// time, row by row, passing each to `on_tile` as soon as it's done, so
+    /// the whole image never has to be held at once. The rows of each tile
+    /// are rendered in parallel. Every pixel takes the full samples per pixel,
+    /// and [`DepthOfField::Post`] can't blur across tiles, so the lens is
+    /// sampled instead.
+    ///
+    /// # Panics
+    ///
+    /// See [`Camera::render()`], and if `tile_size` is `0`.
+    ///
+    /// # Errors
+    ///
+    /// The first error from `on_tile`, which stops the render.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{camera::CameraBuilder, hit_list::HittableList};
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(10)
+    ///     .set_aspect_ratio(2.0)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut pixels = 0;
+    /// camera
+    ///     .render_tiled(&HittableList::new(), 4, |tile, film| {
+    ///         assert_eq!((film.width(), film.height()), (tile.width, tile.height));
+    ///         pixels += film.pixels().len();
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(pixels, 10 * 5);
+    /// ```
+    pub fn render_tiled<F>(
+        &self,
+        world: &dyn Hittable,
+        tile_size: u32,
+        mut on_tile: F,
+    ) -> Result<()>
+    where
+        F: FnMut(Tile, FilmBuffer) -> Result<()>,
+    {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let count = self.samples_per_pixel();
+        let tiles = self.tiles(tile_size);
+
+        for (done, tile) in tiles.iter().enumerate() {
+            eprint!("\rTiles: {}/{}", done + 1, tiles.len());
+            let rows = (tile.y..tile.y + tile.height)
+                .into_par_iter()
+                .map(|j| {
+                    let pixels = (tile.x..tile.x + tile.width).map(|i| (i, count, 0..count));
+                    self.render_row(world, min_t, j, pixels, RenderExtras::default())
+                        .into_iter()
+                        .map(|(colour, _)| colour)
+                        .collect()
+                })
+                .collect();
+            on_tile(*tile, FilmBuffer::from_rows(rows).with_dither(self.dither))?;
+        }
+        eprintln!();
+
+        Ok(())
+    }
+
+    /// Render the given `world` into a Deep Zoom image pyramid described by
+    /// the `.dzi` file at `output`, in tiles of `tile_size` pixels, for
+    /// gigapixel renders too big to hold in memory. Full resolution tiles
+    /// are written as they finish, and the smaller levels built from them at
+    /// the end, see [`DeepZoomWriter`]. Any overscan is left out rather than
+    /// rendered and cropped.
+    ///
+    /// # Panics
+    ///
+    /// See [`Camera::render_tiled()`].
+    ///
+    /// # Errors
+    ///
+    /// If writing any of the pyramid fails.
+    pub fn render_deep_zoom<P: AsRef<Path>>(
+        &self,
+        output: P,
+        world: &dyn Hittable,
+        tile_size: u32,
+    ) -> Result<()> {
+        let camera = self.without_overscan();
+        let writer =
+            DeepZoomWriter::new(&output, camera.image_width, camera.image_height, tile_size);
+        camera.render_tiled(world, tile_size, |tile, film| {
+            writer.write_tile(tile, &film)
+        })?;
+
+        eprintln!("Building the smaller levels...");
+        writer.finish()?;
+        eprintln!("Saved to {}!", output.as_ref().display());
+
+        Ok(())
+    }
+
+    /// Like [`Camera::render()`], but rendering progressively as set by
+    /// `options`. The image saved is the one after the last pass run.
+    ///
+    /// # Panics
+    ///
+    /// See [`Camera::render()`].
+    ///
+    /// # Errors
+    ///
+    /// May throw an error if image saving fails.
+    pub fn render_with(
+        &self,
+        output: &str,
+        world: &dyn Hittable,
+        options: RenderOptions<'_>,
+    ) -> Result<()> {
+        self.render_outputs(&[Output::new(output)], world, options)
+    }
+
+    /// Like [`Camera::render_with()`], but saving the one render to every
+    /// one of `outputs`, such as a full size `.exr` and a small preview, see
+    /// [`FilmBuffer::save_outputs()`]. Any overscan is cropped away first.
+    ///
+    /// # Panics
+    ///
+    /// See [`Camera::render()`].
+    ///
+    /// # Errors
+    ///
+    /// May throw an error if image saving fails.
+    pub fn render_outputs(
+        &self,
+        outputs: &[Output],
+        world: &dyn Hittable,
+        options: RenderOptions<'_>,
+    ) -> Result<()> {
+        let film = self.crop_overscan(&self.render_film_with(world, options));
+
+        eprintln!("Saving...");
+        film.save_outputs(outputs)?;
+        for output in outputs {
+            eprintln!("Saved to {}!", output.path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Render the given `world` once and save an exposure bracket from it, see
+    /// [`FilmBuffer::save_bracketed()`], with any overscan cropped away.
+    /// Returns the paths written.
+    ///
+    /// # Panics
+    ///
+    /// See [`Camera::render()`].
+    ///
+    /// # Errors
+    ///
+    /// May throw an error if image saving fails.
+    pub fn render_bracketed(
+        &self,
+        output: &str,
+        world: &dyn Hittable,
+        stops: &[f64],
+    ) -> Result<Vec<PathBuf>> {
+        let film = self.crop_overscan(&self.render_film(world));
+
+        eprintln!("Saving...");
+        let written = film.save_bracketed(output, stops)?;
+        eprintln!("Saved {} images next to {output}!", written.len());
+
+        Ok(written)
+    }
+
+    /// Render the given `world` into a linear HDR [`FilmBuffer`] rather than
+    /// straight to a file, for further processing such as applying a
+    /// [LUT](crate::lut::Lut) on output. Uses [rayon] to paralellise rendering
+    /// rows. The film includes any [overscan](CameraBuilder::set_overscan()).
+    ///
+    /// # Panics
+    ///
+    /// May panic if unable to get or use the system random, if the wrong sampling function is
+    /// called for the current type of [`AntiAliasing`], or if conversion from [`usize`] to [`u32`]
+    /// via [`u32::try_from`] fails.
+    #[must_use]
+    pub fn render_film(&self, world: &dyn Hittable) -> FilmBuffer {
+        self.render_film_with(world, RenderOptions::default())
+    }
+
+    /// Like [`Camera::render_film()`], but splitting the samples per pixel
+    /// into passes and running the hook after each, as set by `options`. Each
+    /// pass's samples go through the [`FireflyFilter`] on their own, then the
+    /// passes are averaged. With [`AntiAliasing::Grid`] each pass takes a band
+    /// of the grid, so the images in between are slightly offset until the
+    /// last pass.
+    ///
+    /// With [`DepthOfField::Post`] the passes are rendered through a pinhole
+    /// and the hook sees them unblurred. The blur is applied once at the end.
+    ///
+    /// With [`AntiAliasing::Edges`] the passes take the base samples, and the
+    /// edges are refined once at the end, unless a hook stops the render.
+    ///
+    /// # Panics
+    ///
+    /// See [`Camera::render_film()`].
+    #[must_use]
+    pub fn render_film_with(
+        &self,
+        world: &dyn Hittable,
+        mut options: RenderOptions<'_>,
+    ) -> FilmBuffer {
+        if self.depth_of_field == DepthOfField::Post && self.defocus_angle > 0.0 {
+            let pinhole = Camera {
+                defocus_angle: 0.0,
+                depth_of_field: DepthOfField::Lens,
+                ..self.clone()
+            };
+            let film = pinhole.render_film_with(world, options);
+            return self
+                .circle_of_confusion()
+                .apply(&film, &pinhole.render_depth(world))
+                .with_dither(self.dither);
+        }
+
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let counts = self.sample_counts(options.priority.as_ref());
+        let most = counts.iter().copied().max().unwrap_or(1);
+        let passes = options.passes.min(most).max(1);
+
+        let mut film =
+            FilmBuffer::new(self.image_width, self.image_height).with_dither(self.dither);
+        for level in 0..options.preview_levels {
+            let step = 1 << (options.preview_levels - level);
+            self.render_preview(world, &mut film, step, level == 0, min_t);
+            if let Some(hook) = options.after_preview.as_mut() {
+                if hook(&film, level).is_break() {
+                    return film;
+                }
+            }
+        }
+
+        if let Some(guide) = options.path_guide {
+            guide.prepare(world.bound());
+        }
+        if let Some(layers) = options.layers {
+            layers.reset(self.image_width, self.image_height);
+        }
+        let mut pixel_stats = vec![Welford::default(); counts.len()];
+        let mut converged = vec![false; counts.len()];
+        for pass in 0..passes {
+            let (pass_film, pass_stats) =
+                self.render_pass(world, &counts, &converged, pass, passes, &options);
+            if let Some(guide) = options.path_guide {
+                guide.refine();
+            }
+
+            // running mean, weighted by the samples in each pass, which
+            // replaces any preview on the first
+            for ((acc, &new), (stats, new_stats)) in film
+                .pixels_mut()
+                .iter_mut()
+                .zip(pass_film.pixels())
+                .zip(pixel_stats.iter_mut().zip(&pass_stats))
+            {
+                if new_stats.count() == 0 {
+                    continue;
+                }
+                stats.merge(new_stats);
+                *acc += (new - *acc) * (f64::from(new_stats.count()) / f64::from(stats.count()));
+            }
+
+            if let Some(tolerance) = options.adaptive {
+                for (done, stats) in converged.iter_mut().zip(&pixel_stats) {
+                    *done |= stats.count() >= MIN_ADAPTIVE_SAMPLES
+                        && stats.relative_error(CONFIDENCE_95) <= tolerance;
+                }
+            }
+            if let Some(variance) = options.variance {
+                variance.set(self.image_width, self.image_height, &pixel_stats);
+            }
+            if let Some(hook) = options.after_pass.as_mut() {
+                if hook(&film, pass).is_break() {
+                    return film;
+                }
+            }
+        }
+
+        if let AntiAliasing::Edges { base, edge } = self.anti_aliasing {
+            let samples: Vec<Range<u32>> = counts
+                .iter()
+                .zip(&pixel_stats)
+                .zip(&converged)
+                .map(|((&count, stats), &done)| {
+                    let total = u64::from(count) * u64::from(edge) / u64::from(base.max(1));
+                    let total = u32::try_from(total).unwrap_or(u32::MAX);
+                    let taken = stats.count();
+                    taken..if done { taken } else { total.max(taken) }
+                })
+                .collect();
+            self.refine_edges(world, &mut film, &mut pixel_stats, &samples, &options);
+            if let Some(variance) = options.variance {
+                variance.set(self.image_width, self.image_height, &pixel_stats);
+            }
+        }
+
+        film
+    }
+
+    /// Find the pixels of `film`, a render of `world` by this [`Camera`], on
+    /// an edge, row by row, as used by [`AntiAliasing::Edges`]. A pixel is on
+    /// an edge if a neighbour's tone mapped brightness differs from it by
+    /// more than a tenth, or the ray through its centre hits a different
+    /// object, a surface facing a different way, or nothing, or hits at a
+    /// different depth.
+    ///
+    /// # Panics
+    ///
+    /// If `film` isn't the size of the image.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::{AntiAliasing, Background, CameraBuilder},
+    ///     hit_list::HittableList,
+    ///     material::{DiffuseLight, Lambertian},
+    ///     sphere::Sphere,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, -3.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(16)
+    ///     .set_anti_aliasing(AntiAliasing::Edges { base: 1, edge: 16 })
+    ///     .set_background(Background::Solid(vec3![1.0, 1.0, 1.0]))
+    ///     .build().unwrap();
+    /// let film = camera.render_film(&world);
+    /// let edges = camera.edge_pixels(&world, &film);
+    ///
+    /// // the silhouette, but not the middle of the sphere or the background
+    /// assert!(edges.iter().any(|&e| e));
+    /// assert!(!edges[8 * 16 + 8]);
+    /// assert!(!edges[0]);
+    /// ```
+    #[must_use]
+    pub fn edge_pixels(&self, world: &dyn Hittable, film: &FilmBuffer) -> Vec<bool> {
+        let (width, height) = (self.image_width, self.image_height);
+        assert!(
+            film.width() == width && film.height() == height,
+            "Film must be the size of the image"
+        );
+
+        let surfaces: Vec<Option<EdgeSurface>> = self
+            .first_hits(world, |_, rec| EdgeSurface {
+                t: rec.t,
+                norm: rec.norm,
+                material: std::ptr::from_ref(rec.mat).cast::<()>().addr(),
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let tone = |c: Vec3| {
+            let l = c.luminance().max(0.0);
+            l / (1.0 + l)
+        };
+        let differ = |a: usize, b: usize| {
+            EdgeSurface::differ(surfaces[a], surfaces[b])
+                || (tone(film.pixels()[a]) - tone(film.pixels()[b])).abs() > EDGE_CONTRAST
+        };
+
+        let mut edges = vec![false; surfaces.len()];
+        let (width, height) = (width as usize, height as usize);
+        for j in 0..height {
+            for i in 0..width {
+                let idx = j * width + i;
+                let right = (i + 1 < width).then_some(idx + 1);
+                let below = (j + 1 < height).then_some(idx + width);
+                for other in right.into_iter().chain(below) {
+                    if differ(idx, other) {
+                        edges[idx] = true;
+                        edges[other] = true;
                     }
                 }
+            }
+        }
+        edges
+    }
+
+    /// Take the `samples` of each pixel of `film` on an edge, starting from
+    /// the number already taken, averaging them in and adding them to
+    /// `pixel_stats`.
+    fn refine_edges(
+        &self,
+        world: &dyn Hittable,
+        film: &mut FilmBuffer,
+        pixel_stats: &mut [Welford],
+        samples: &[Range<u32>],
+        options: &RenderOptions<'_>,
+    ) {
+        let edges = self.edge_pixels(world, film);
+        eprintln!(
+            "Refining {} edge pixels",
+            edges.iter().filter(|&&e| e).count()
+        );
+
+        let extras = RenderExtras::of(options);
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let width = self.image_width;
+        let rows: Vec<_> = (0..self.image_height)
+            .into_par_iter()
+            .map(|j| {
+                let pixels: Vec<(u32, u32, Range<u32>)> = (0..width)
+                    .filter(|&i| edges[(j * width + i) as usize])
+                    .map(|i| {
+                        let range = samples[(j * width + i) as usize].clone();
+                        (i, range.end, range)
+                    })
+                    .collect();
+                let colours = self.render_row(world, min_t, j, pixels.iter().cloned(), extras);
+                (j, pixels, colours)
+            })
+            .collect();
+
+        for (j, pixels, colours) in rows {
+            for ((i, _, _), (colour, new_stats)) in pixels.into_iter().zip(colours) {
+                let stats = &mut pixel_stats[(j * width + i) as usize];
+                if new_stats.count() == 0 {
+                    continue;
+                }
+                stats.merge(&new_stats);
+                let acc = film.get(i, j);
+                let weight = f64::from(new_stats.count()) / f64::from(stats.count());
+                film.set(i, j, acc + (colour - acc) * weight);
+            }
+        }
+    }
+
+    /// Render the depth of `world` along the view direction through the
+    /// centre of each pixel, in every channel of a [`FilmBuffer`]. Pixels
+    /// where nothing is hit are infinite. Ignores defocus, so it matches a
+    /// pinhole render.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::CameraBuilder, hit_list::HittableList, material::Lambertian, sphere::Sphere,
+    ///     vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, -5.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(9)
+    ///     .set_focus_dist(2.0)
+    ///     .build()
+    ///     .unwrap();
+    /// let depth = camera.render_depth(&world);
+    ///
+    /// assert!((depth.get(4, 4)[0] - 4.0).abs() < 1e-9);
+    /// assert_eq!(depth.get(0, 0)[0], f64::INFINITY);
+    /// ```
+    #[must_use]
+    pub fn render_depth(&self, world: &dyn Hittable) -> FilmBuffer {
+        // the pixels lie on the focus plane, so `t = 1` is the focus distance
+        let depths = self.first_hits(world, |_, rec| rec.t * self.focus_dist);
+        FilmBuffer::from_rows(
+            depths
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|depth| {
+                            let depth = depth.unwrap_or(f64::INFINITY);
+                            vec3![depth, depth, depth]
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Whatever `f` makes of the first hit along the view direction through
+    /// the centre of each pixel, given the [ray](Ray) and the hit, by row, or
+    /// [`None`] where nothing is hit.
+    /// Ignores defocus, so the pixels are seen as by a pinhole.
+    pub(crate) fn first_hits<T: Send>(
+        &self,
+        world: &dyn Hittable,
+        f: impl Fn(&Ray, &HitRecord) -> T + Sync,
+    ) -> Vec<Vec<Option<T>>> {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        (0..self.image_height)
+            .into_par_iter()
+            .map(|j| {
+                (0..self.image_width)
+                    .map(|i| {
+                        let pixel = self.pixel00_loc
+                            + self.pixel_delta_u * f64::from(i)
+                            + self.pixel_delta_v * f64::from(j);
+                        let r = self.in_motion(ray!(
+                            self.centre,
+                            pixel - self.centre,
+                            self.shutter.min
+                        ));
+                        world
+                            .hit(&r, interval![min_t, f64::INFINITY])
+                            .map(|rec| f(&r, &rec))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 
-                row.push((pixel_colour * self.samples_scale).to_rgb());
+    /// Render the world-space position of the first thing seen through the
+    /// centre of each pixel, an arbitrary output (AOV) for checking where
+    /// geometry ended up, or making a [`PointCloud`](crate::point_cloud::PointCloud).
+    /// Positions are in the world's coordinates from before any
+    /// [recentring](Camera::recentre()), and pixels where nothing is hit are
+    /// infinite. Ignores defocus like [`Camera::render_depth()`]. Save it
+    /// as `.exr` to keep the values.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::CameraBuilder, hit_list::HittableList, material::Lambertian, sphere::Sphere,
+    ///     vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, -5.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let mut camera = CameraBuilder::default().set_image_width(9).build().unwrap();
+    /// let position = camera.render_position(&world);
+    /// assert!((position.get(4, 4) - vec3![0.0, 0.0, -4.0]).length() < 1e-9);
+    /// assert_eq!(position.get(0, 0)[0], f64::INFINITY);
+    ///
+    /// // still where they were after moving everything to the camera
+    /// camera.recentre(&mut world);
+    /// assert!((camera.render_position(&world).get(4, 4) - vec3![0.0, 0.0, -4.0]).length() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn render_position(&self, world: &dyn Hittable) -> FilmBuffer {
+        let positions = self.first_hits(world, |_, rec| rec.p + self.render_origin);
+        let missed = vec3![f64::INFINITY, f64::INFINITY, f64::INFINITY];
+        FilmBuffer::from_rows(
+            positions
+                .into_iter()
+                .map(|row| row.into_iter().map(|p| p.unwrap_or(missed)).collect())
+                .collect(),
+        )
+    }
+
+    /// Render how curved the first surface seen through the centre of each
+    /// pixel is, in every channel of a [`FilmBuffer`], from how its normal
+    /// turns between the neighbouring pixels. It's one over the radius of
+    /// the surface, positive where it bulges towards the camera and negative
+    /// where it's hollow, so sharp corners and creases stand out, for
+    /// cavity and edge wear masks or ink lines. Pixels where nothing is hit,
+    /// or with no neighbours on a surface, are flat.
+    ///
+    /// The values depend on the size of the scene, so save it as `.exr`, or
+    /// scale it before saving to an 8-bit image.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::CameraBuilder, hit_list::HittableList, material::Lambertian, sphere::Sphere,
+    ///     vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, -5.0],
+    ///     radius: 2.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    ///
+    /// let camera = CameraBuilder::default().set_image_width(32).build().unwrap();
+    /// let curvature = camera.render_curvature(&world);
+    /// // a ball of radius 2 bulges with a curvature of a half
+    /// assert!((curvature.get(16, 16)[0] - 0.5).abs() < 0.01);
+    /// assert_eq!(curvature.get(0, 0)[0], 0.0);
+    ///
+    /// // and from inside it's hollow
+    /// let inside = CameraBuilder::default()
+    ///     .set_image_width(32)
+    ///     .set_look_from(vec3![0.0, 0.0, -5.0])
+    ///     .set_look_at(vec3![0.0, 0.0, -6.0])
+    ///     .build().unwrap();
+    /// assert!((inside.render_curvature(&world).get(16, 16)[0] + 0.5).abs() < 0.01);
+    /// ```
+    #[must_use]
+    pub fn render_curvature(&self, world: &dyn Hittable) -> FilmBuffer {
+        let hits = self.first_hits(world, |_, rec| (rec.p, rec.norm));
+        let hit = |i: u32, j: u32, di: i32, dj: i32| {
+            let (i, j) = (i.checked_add_signed(di)?, j.checked_add_signed(dj)?);
+            *hits.get(j as usize)?.get(i as usize)?
+        };
+        // how fast the normal turns towards the direction between two hits,
+        // from the centre if a neighbour missed
+        let along = |a: Option<(Vec3, Vec3)>, b: Option<(Vec3, Vec3)>| {
+            let ((pa, na), (pb, nb)) = (a?, b?);
+            let dp = pb - pa;
+            let length_squared = dp.length_squared();
+            (length_squared > 0.0).then(|| dot(&(nb - na), &dp) / length_squared)
+        };
+        let rows = (0..self.image_height)
+            .into_par_iter()
+            .map(|j| {
+                (0..self.image_width)
+                    .map(|i| {
+                        let centre = hit(i, j, 0, 0);
+                        let curvatures: Vec<_> = [(1, 0), (0, 1)]
+                            .into_iter()
+                            .filter_map(|(di, dj)| {
+                                let behind = hit(i, j, -di, -dj).or(centre);
+                                let ahead = hit(i, j, di, dj).or(centre);
+                                centre.and(along(behind, ahead))
+                            })
+                            .collect();
+                        #[expect(clippy::cast_precision_loss)]
+                        let curvature = if curvatures.is_empty() {
+                            0.0
+                        } else {
+                            curvatures.iter().sum::<f64>() / curvatures.len() as f64
+                        };
+                        vec3![curvature, curvature, curvature]
+                    })
+                    .collect()
+            })
+            .collect();
+        FilmBuffer::from_rows(rows)
+    }
+
+    /// Render `world` with a custom shader: `shade` is given each camera
+    /// [ray](Ray) and its first hit, or [`None`] where nothing is hit, and
+    /// picks the sample's colour, which is averaged over the pixel's samples
+    /// as usual. Nothing bounces, so the max depth is ignored. An escape
+    /// hatch for ID passes, debug views and one-off looks that no
+    /// [material](crate::material::Material) or other render gives yet.
+    ///
+    /// Anti-aliasing blends the colours at edges, so for exact IDs, render
+    /// with one sample per pixel and no dither.
+    ///
+    /// # Panics
+    ///
+    /// If the camera takes no samples per pixel. May panic if unable to get or
+    /// use the system random.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     camera::{AntiAliasing, CameraBuilder},
+    ///     hit_list::HittableList,
+    ///     material::Lambertian,
+    ///     sphere::Sphere,
+    ///     stats::Named,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(Named::new(
+    ///     "ball",
+    ///     Sphere {
+    ///         centre: vec3![0.0, 0.0, -3.0],
+    ///         radius: 1.0,
+    ///         mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    ///     },
+    /// )));
+    ///
+    /// let camera = CameraBuilder::default()
+    ///     .set_image_width(9)
+    ///     .set_aspect_ratio(1.0)
+    ///     .set_anti_aliasing(AntiAliasing::Random(1))
+    ///     .set_dither(false)
+    ///     .build().unwrap();
+    ///
+    /// // a mask of the ball
+    /// let mask = camera.render_shaded(&world, |_, rec| match rec {
+    ///     Some(rec) if rec.object == Some("ball") => vec3![1.0, 1.0, 1.0],
+    ///     _ => Vec3::default(),
+    /// });
+    /// assert_eq!(mask.get(4, 4), vec3![1.0, 1.0, 1.0]);
+    /// assert_eq!(mask.get(0, 0), Vec3::default());
+    /// ```
+    #[must_use]
+    pub fn render_shaded(
+        &self,
+        world: &dyn Hittable,
+        shade: impl Fn(&Ray, Option<&HitRecord>) -> Vec3 + Sync,
+    ) -> FilmBuffer {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let rows = self
+            .shade_samples(|r, _| shade(r, world.hit(r, interval![min_t, f64::INFINITY]).as_ref()));
+        FilmBuffer::from_rows(rows).with_dither(self.dither)
+    }
+
+    /// The average of whatever `shade` makes of each of the camera's
+    /// samples of each pixel, by row, without any bounces.
+    ///
+    /// # Panics
+    ///
+    /// If the camera takes no samples per pixel.
+    pub(crate) fn shade_samples(
+        &self,
+        shade: impl Fn(&Ray, &mut SmallRng) -> Vec3 + Sync,
+    ) -> Vec<Vec<Vec3>> {
+        let samples = self.samples_per_pixel();
+        assert!(samples > 0, "Need at least one sample per pixel to shade");
+        (0..self.image_height)
+            .into_par_iter()
+            .map(|j| {
+                let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
+                (0..self.image_width)
+                    .map(|i| {
+                        let total = (0..samples)
+                            .map(|sample| {
+                                let r = self.get_ray(i, j, sample, samples, &mut rng);
+                                shade(&r, &mut rng)
+                            })
+                            .fold(Vec3::default(), |acc, c| acc + c);
+                        total / f64::from(samples)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The [`CircleOfConfusion`] of the [`Camera`]'s lens, used by
+    /// [`DepthOfField::Post`].
+    #[must_use]
+    pub fn circle_of_confusion(&self) -> CircleOfConfusion {
+        CircleOfConfusion {
+            lens_radius: self.defocus_disc_u.length(),
+            focus_dist: self.focus_dist,
+            pixel_size: self.pixel_delta_u.length(),
+        }
+    }
+
+    /// The [ray](Ray) for `sample` of the `count` taken for pixel `(i, j)`, by
+    /// the [`AntiAliasing`] mode. With [`AntiAliasing::Grid`], a pixel taking
+    /// fewer or more samples than the grid spreads them evenly over it.
+    pub(crate) fn get_ray(
+        &self,
+        i: u32,
+        j: u32,
+        sample: u32,
+        count: u32,
+        rng: &mut SmallRng,
+    ) -> Ray {
+        // seeded lens and time samples come from the sample alone, not
+        // whatever the paths before it used
+        let mut seeded = self.sample_seed.map(|seed| {
+            let cell = [i, j, sample].map(i64::from);
+            SmallRng::seed_from_u64(Noise::new(seed).stream("lens").hash(cell))
+        });
+        let rng = seeded.as_mut().unwrap_or(rng);
+        match self.anti_aliasing {
+            AntiAliasing::Grid(_) => {
+                let grid = u64::from(self.samples_per_pixel());
+                let sample = u64::from(sample) * grid / u64::from(count) % grid;
+                let sample = u16::try_from(sample).expect("couldn't convert u64 to u16");
+                self.get_ray_grid(i, j, sample, rng).unwrap()
             }
+            AntiAliasing::Random(_) | AntiAliasing::Edges { .. } => match self.sample_seed {
+                Some(seed) => self.ray_through(i, j, seeded_jitter(seed, i, j, sample), rng),
+                None => self.get_ray_random(i, j, rng).unwrap(),
+            },
+        }
+    }
+
+    /// The [ray](Ray) through pixel `(i, j)` at `offset` from its centre, from
+    /// the defocus disc if there is one.
+    fn ray_through(&self, i: u32, j: u32, offset: Vec3, rng: &mut SmallRng) -> Ray {
+        let pixel_sample = self.pixel00_loc
+            + (self.pixel_delta_u * (f64::from(i) + offset[0]))
+            + (self.pixel_delta_v * (f64::from(j) + offset[1]));
+
+        let ray_origin = if self.defocus_angle <= 0.0 {
+            self.centre
+        } else {
+            self.defocus_disc_sample(rng)
+        };
+
+        self.in_motion(ray!(
+            ray_origin,
+            pixel_sample - ray_origin,
+            self.sample_time(rng)
+        ))
+    }
+
+    /// Trace one sample for each pixel on a grid `step` apart into `film`,
+    /// filling the `step` by `step` block below and right of it. Unless
+    /// `first`, pixels on the grid twice as coarse were traced by the level
+    /// before and are skipped.
+    fn render_preview(
+        &self,
+        world: &dyn Hittable,
+        film: &mut FilmBuffer,
+        step: u32,
+        first: bool,
+        min_t: f64,
+    ) {
+        let traced =
+            |i: u32, j: u32| !first && i.is_multiple_of(2 * step) && j.is_multiple_of(2 * step);
+        let rows: Vec<(u32, Vec<(u32, Vec3)>)> = (0..self.image_height)
+            .step_by(step as usize)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|j| {
+                let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
+                let row = (0..self.image_width)
+                    .step_by(step as usize)
+                    .filter(|&i| !traced(i, j))
+                    .map(|i| {
+                        let r = self.get_ray(i, j, 0, 1, &mut rng);
+                        let colour = self.ray_colour(
+                            &r,
+                            PathState::CAMERA,
+                            self.max_depth,
+                            &mut Trace::new(world, min_t),
+                            &mut rng,
+                        );
+                        (i, colour)
+                    })
+                    .collect();
+                (j, row)
+            })
+            .collect();
+
+        for (j, row) in rows {
+            for (i, colour) in row {
+                for y in j..(j + step).min(self.image_height) {
+                    for x in i..(i + step).min(self.image_width) {
+                        film.set(x, y, colour);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Render `pass` of `passes`, taking that pass's share of each pixel's
+    /// samples in `counts`, except in `converged` pixels, with the stats,
+    /// cache and guide of `options`. Returns the image and each pixel's
+    /// sample statistics. Pixels without any samples in this pass are left
+    /// black.
+    fn render_pass(
+        &self,
+        world: &dyn Hittable,
+        counts: &[u32],
+        converged: &[bool],
+        pass: u32,
+        passes: u32,
+        options: &RenderOptions<'_>,
+    ) -> (FilmBuffer, Vec<Welford>) {
+        let extras = RenderExtras::of(options);
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let progress = if passes > 1 {
+            format!("Pass {}/{passes}, lines", pass + 1)
+        } else {
+            "Lines".to_string()
+        };
+        let film = Mutex::new(FilmBuffer::new(self.image_width, self.image_height));
+        let stats = Mutex::new(vec![Welford::default(); counts.len()]);
+        let lines_done = AtomicUsize::new(0);
+
+        (0..self.image_height).into_par_iter().for_each(|j| {
+            let pixels = (0..self.image_width).map(|i| {
+                let idx = (j * self.image_width + i) as usize;
+                let range = if converged[idx] {
+                    0..0
+                } else {
+                    pass_range(counts[idx], pass, passes)
+                };
+                (i, counts[idx], range)
+            });
+            let (row, row_stats): (Vec<Vec3>, Vec<Welford>) = self
+                .render_row(world, min_t, j, pixels, extras)
+                .into_iter()
+                .unzip();
 
             eprint!(
-                "\rLines: {}/{}",
+                "\r{progress}: {}/{}",
                 lines_done.load(Ordering::Relaxed) + 1,
                 self.image_height
             );
 
             lines_done.fetch_add(1, Ordering::Relaxed);
 
-            let mut img = img.lock().unwrap();
-            for (i, pixel) in row.into_iter().enumerate() {
-                img.put_pixel(
-                    u32::try_from(i).expect("couldn't convert usize to u32"),
-                    j,
-                    pixel,
-                );
-            }
+            film.lock().unwrap().set_row(j, &row);
+            let start = (j * self.image_width) as usize;
+            stats.lock().unwrap()[start..start + row_stats.len()].copy_from_slice(&row_stats);
         });
+        eprintln!();
+
+        (film.into_inner().unwrap(), stats.into_inner().unwrap())
+    }
+
+    /// Render the pixels of row `j`, each given as `(i, count, samples)` to
+    /// take the `samples` of its `count`, with `extras`. Returns each pixel's
+    /// colour, black without any samples, and the statistics of its samples.
+    fn render_row(
+        &self,
+        world: &dyn Hittable,
+        min_t: f64,
+        j: u32,
+        pixels: impl Iterator<Item = (u32, u32, Range<u32>)>,
+        extras: RenderExtras<'_>,
+    ) -> Vec<(Vec3, Welford)> {
+        let RenderExtras {
+            stats,
+            cache,
+            guide,
+            layers,
+            shadow_lights,
+        } = extras;
+        let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
+        let mut tally = stats.map(|_| StatsTally::default());
+        let mut guide_samples = guide.map(|_| vec![]);
+        let mut row = vec![];
+        let mut samples = vec![];
+        let mut layer_row = vec![];
+        for (i, count, range) in pixels {
+            samples.clear();
+            let mut stats = Welford::default();
+            let mut layer_sums = vec![Vec3::default(); layers.map_or(0, LayerFilm::slots)];
+
+            if let Some(seed) = self.sample_seed {
+                let cell = [i, j, range.start].map(i64::from);
+                rng = SmallRng::seed_from_u64(Noise::new(seed).stream("paths").hash(cell));
+            }
+            for sample in range {
+                let r = self.get_ray(i, j, sample, count, &mut rng);
+                let mut trace = Trace {
+                    stats: tally.as_mut(),
+                    cache,
+                    guide,
+                    guide_samples: guide_samples.as_mut(),
+                    shadow_lights,
+                    ..Trace::new(world, min_t)
+                };
+                let colour =
+                    self.ray_colour(&r, PathState::CAMERA, self.max_depth, &mut trace, &mut rng);
+                if let Some(layers) = layers {
+                    layer_sums[layers.slot(trace.layer)] += colour;
+                }
+                stats.push(colour);
+                samples.push(colour);
+                if let Some(tally) = tally.as_mut() {
+                    tally.end_sample();
+                }
+            }
 
-        eprintln!("\nSaving...");
-        {
-            let img = img.lock().unwrap();
-            img.save(output)?;
+            let colour = if samples.is_empty() {
+                Vec3::default()
+            } else {
+                self.firefly_filter.resolve(&samples)
+            };
+            if layers.is_some() {
+                layer_row.push((i, stats.count(), layer_sums));
+            }
+            row.push((colour, stats));
         }
-        eprintln!("Saved to {output}!");
 
-        Ok(())
+        if let (Some(stats), Some(tally)) = (stats, &tally) {
+            stats.merge(tally);
+        }
+        if let (Some(guide), Some(samples)) = (guide, &guide_samples) {
+            guide.record(samples);
+        }
+        if let Some(layers) = layers {
+            layers.add_row(j, &layer_row);
+        }
+        row
+    }
+
+    /// Render a 360 degree equirectangular HDR environment map of `world` as seen
+    /// from `position`, `resolution` pixels wide and half that high. The image
+    /// layout matches [`equirect_to_direction()`], so it can be used as an
+    /// environment map or for baking reflection probes. Uses the [`Camera`]'s
+    /// samples per pixel, max depth, shutter and [`RayBias`], but not its
+    /// position, orientation or defocus.
+    ///
+    /// # Panics
+    ///
+    /// May panic if unable to get or use the system random.
+    #[must_use]
+    pub fn render_probe(
+        &self,
+        position: Vec3,
+        resolution: u32,
+        world: &dyn Hittable,
+    ) -> FilmBuffer {
+        let width = resolution.max(2);
+        let height = width / 2;
+
+        let position = position - self.render_origin;
+        FilmBuffer::from_rows(self.render_rays(width, height, world, |u, v| {
+            (position, equirect_to_direction(u, v))
+        }))
+        .with_dither(self.dither)
+    }
+
+    /// Render an omni-directional stereo (ODS) pair of `world` from `position`,
+    /// laid out top-bottom with the left eye on top, for VR headsets and
+    /// players. Each eye is an equirectangular image `resolution` pixels wide,
+    /// covering the whole sphere or just the front half depending on
+    /// `coverage`.
+    ///
+    /// Every column has its own pair of eye positions, `ipd` (interpupillary
+    /// distance) apart and at right angles to the column's direction, as if
+    /// the viewer turned their head to look that way. The separation shrinks
+    /// towards the poles to avoid the eyes swapping over when looking straight
+    /// up or down. Uses the same [`Camera`] settings as
+    /// [`Camera::render_probe()`].
+    ///
+    /// # Panics
+    ///
+    /// May panic if unable to get or use the system random.
+    #[must_use]
+    pub fn render_stereo(
+        &self,
+        position: Vec3,
+        resolution: u32,
+        ipd: f64,
+        coverage: StereoCoverage,
+        world: &dyn Hittable,
+    ) -> FilmBuffer {
+        let width = resolution.max(2);
+        let (height, u_range) = match coverage {
+            StereoCoverage::Full => (width / 2, interval![0.0, 1.0]),
+            StereoCoverage::Front => (width, interval![0.25, 0.75]),
+        };
+
+        let position = position - self.render_origin;
+        let eye = |offset: f64| {
+            self.render_rays(width, height, world, move |u, v| {
+                let dir = equirect_to_direction(u_range.min + u * u_range.size(), v);
+                // horizontal, with a length of sin(theta) so the eyes meet at
+                // the poles
+                let right = vec3![-dir[2], 0.0, dir[0]];
+                (position + right * offset, dir)
+            })
+        };
+
+        let mut rows = eye(-0.5 * ipd);
+        rows.extend(eye(0.5 * ipd));
+        FilmBuffer::from_rows(rows).with_dither(self.dither)
+    }
+
+    /// Render `width` by `height` rows of pixels in parallel, where `ray_at`
+    /// gives the origin and direction of the [ray](Ray) through the image
+    /// coordinates `(u, v)`, both in `0.0..1.0` with `v = 0.0` at the top.
+    fn render_rays<F>(
+        &self,
+        width: u32,
+        height: u32,
+        world: &dyn Hittable,
+        ray_at: F,
+    ) -> Vec<Vec<Vec3>>
+    where
+        F: Fn(f64, f64) -> (Vec3, Vec3) + Sync,
+    {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let samples = self.samples_per_pixel();
+
+        (0..height)
+            .into_par_iter()
+            .map(|j| {
+                let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
+                let mut pixel_samples = vec![];
+                (0..width)
+                    .map(|i| {
+                        pixel_samples.clear();
+                        for _ in 0..samples {
+                            let u = (f64::from(i) + rng.random_range(0.0..1.0)) / f64::from(width);
+                            let v = (f64::from(j) + rng.random_range(0.0..1.0)) / f64::from(height);
+                            let (origin, dir) = ray_at(u, v);
+                            let r = ray!(origin, dir, self.sample_time(&mut rng));
+                            pixel_samples.push(self.ray_colour(
+                                &r,
+                                PathState::CAMERA,
+                                self.max_depth,
+                                &mut Trace::new(world, min_t),
+                                &mut rng,
+                            ));
+                        }
+                        self.firefly_filter.resolve(&pixel_samples)
+                    })
+                    .collect()
+            })
+            .collect()
     }
 }
+
+/// The samples taken in `pass` of `passes` by a pixel taking `count`, split as
+/// evenly as possible with any spare in the earliest passes.
+fn pass_range(count: u32, pass: u32, passes: u32) -> Range<u32> {
+    // in u64 as a large grid can fill a u32
+    let split = |p: u32| {
+        u32::try_from((u64::from(count) * u64::from(p)).div_ceil(u64::from(passes)))
+            .expect("couldn't convert u64 to u32")
+    };
+    split(pass)..split(pass + 1)
+}
+
+/// The offset from the centre of pixel `(i, j)` for `sample` with a
+/// [sample seed](CameraBuilder::set_sample_seed()). The samples follow the R2
+/// sequence, rotated (modulo 1) by a hash of the seed and the pixel.
+fn seeded_jitter(seed: u64, i: u32, j: u32, sample: u32) -> Vec3 {
+    // the plastic number, which gives the R2 sequence its even spread
+    const PLASTIC: f64 = 1.324_717_957_244_746;
+
+    let mut rotation = Noise::new(seed)
+        .stream("jitter")
+        .rng([i64::from(i), i64::from(j), 0]);
+    let sample = f64::from(sample);
+    let across = (rotation.next_unit() + sample / PLASTIC).fract();
+    let down = (rotation.next_unit() + sample / (PLASTIC * PLASTIC)).fract();
+    vec3![across - 0.5, down - 0.5, 0.0]
+}
@@ -0,0 +1,478 @@
+//! This module contains [`CameraPath`], a keyframed camera move that can be
+//! read from and written to other tools, so shots matched or blocked out in a
+//! DCC package can be rendered here and rustrace moves taken back for previz.
+//!
+//! Two formats are supported:
+//!
+//! - CSV, one key per line as
+//!   `frame,from_x,from_y,from_z,at_x,at_y,at_z,up_x,up_y,up_z,vfov` in
+//!   rustrace's own `Y` up coordinates, with the field of view in degrees.
+//!   Lines starting with `#` and a header line starting with `frame` are
+//!   skipped.
+//! - Blender JSON, with a camera's world location, `XYZ` Euler rotation in
+//!   radians and focal length in millimetres per frame, in Blender's `Z` up
+//!   coordinates:
+//!
+//! ```json
+//! {
+//!     "fps": 24.0,
+//!     "resolution_x": 1920,
+//!     "resolution_y": 1080,
+//!     "sensor_width": 36.0,
+//!     "frames": [
+//!         { "frame": 1, "location": [7.4, -6.5, 5.3], "rotation_euler": [1.1, 0.0, 0.8], "lens": 50.0 }
+//!     ]
+//! }
+//! ```
+//!
+//! which can be written from Blender's Python console with:
+//!
+//! ```python
+//! import bpy, json
+//! scene, cam = bpy.context.scene, bpy.context.scene.camera
+//! frames = []
+//! for frame in range(scene.frame_start, scene.frame_end + 1):
+//!     scene.frame_set(frame)
+//!     loc, rot, _ = cam.matrix_world.decompose()
+//!     frames.append({"frame": frame, "location": list(loc),
+//!                    "rotation_euler": list(rot.to_euler("XYZ")), "lens": cam.data.lens})
+//! json.dump({"fps": scene.render.fps / scene.render.fps_base,
+//!            "resolution_x": scene.render.resolution_x, "resolution_y": scene.render.resolution_y,
+//!            "sensor_width": cam.data.sensor_width, "frames": frames}, open("/tmp/shot.json", "w"))
+//! ```
+//!
+//! Blender's sensor fit is taken to be `Auto`, with the sensor across the
+//! longer side of the image.
+
+use std::{fmt::Write, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::CameraBuilder,
+    vec3,
+    vec3::{cross, Vec3},
+};
+
+/// The pose and field of view of a camera at one frame of a [`CameraPath`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraKey {
+    /// The frame the key is at, which can be fractional.
+    pub frame: f64,
+    /// Where the camera is.
+    pub look_from: Vec3,
+    /// A point the camera looks towards.
+    pub look_at: Vec3,
+    /// Which way is up for the camera.
+    pub v_up: Vec3,
+    /// The vertical field of view in degrees.
+    pub vfov: f64,
+}
+
+/// A camera move as [`CameraKey`]s in order of frame, linearly interpolated
+/// between them.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera_path::{CameraKey, CameraPath},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let csv = "\
+/// frame,from_x,from_y,from_z,at_x,at_y,at_z,up_x,up_y,up_z,vfov
+/// 0,0,1,5,0,0,0,0,1,0,40
+/// ## halfway round
+/// 10,5,1,0,0,0,0,0,1,0,60
+/// ";
+/// let path = CameraPath::from_csv(csv).unwrap();
+/// assert_eq!(path.keys.len(), 2);
+///
+/// let key = path.key_at(5.0).unwrap();
+/// assert_eq!(key.look_from, vec3![2.5, 1.0, 2.5]);
+/// assert_eq!(key.vfov, 50.0);
+///
+/// // held before the first and after the last key
+/// assert_eq!(path.key_at(-3.0).unwrap().vfov, 40.0);
+/// assert_eq!(path.key_at(99.0).unwrap().vfov, 60.0);
+///
+/// // and written back the same
+/// assert_eq!(CameraPath::from_csv(&path.to_csv()).unwrap(), path);
+///
+/// // a camera for a frame
+/// let camera = path.apply_at(5.0, rustrace::camera::CameraBuilder::default()).build().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraPath {
+    /// The frame rate, used when exchanging the path with other tools.
+    pub fps: f64,
+    /// The keys, in order of frame.
+    pub keys: Vec<CameraKey>,
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A camera move as exported from Blender, see the [module](self) docs.
+#[derive(Debug, Serialize, Deserialize)]
+struct BlenderPath {
+    fps: f64,
+    resolution_x: u32,
+    resolution_y: u32,
+    #[serde(default = "default_sensor_width")]
+    sensor_width: f64,
+    frames: Vec<BlenderFrame>,
+}
+
+/// One frame of a [`BlenderPath`].
+#[derive(Debug, Serialize, Deserialize)]
+struct BlenderFrame {
+    frame: f64,
+    location: [f64; 3],
+    rotation_euler: [f64; 3],
+    lens: f64,
+}
+
+/// Blender's default full frame sensor width in millimetres.
+fn default_sensor_width() -> f64 {
+    36.0
+}
+
+/// From Blender's `Z` up coordinates into rustrace's `Y` up ones.
+fn from_blender([x, y, z]: [f64; 3]) -> Vec3 {
+    vec3![x, z, -y]
+}
+
+/// From rustrace's `Y` up coordinates into Blender's `Z` up ones.
+fn to_blender(v: Vec3) -> [f64; 3] {
+    [v[0], -v[2], v[1]]
+}
+
+/// The rotation matrix of Blender's `XYZ` Euler angles, `Rz * Ry * Rx`.
+fn euler_matrix([rx, ry, rz]: [f64; 3]) -> [[f64; 3]; 3] {
+    let (sx, cx) = rx.sin_cos();
+    let (sy, cy) = ry.sin_cos();
+    let (sz, cz) = rz.sin_cos();
+    [
+        [cz * cy, cz * sy * sx - sz * cx, cz * sy * cx + sz * sx],
+        [sz * cy, sz * sy * sx + cz * cx, sz * sy * cx - cz * sx],
+        [-sy, cy * sx, cy * cx],
+    ]
+}
+
+/// The `XYZ` Euler angles of rotation matrix `m`, the inverse of
+/// [`euler_matrix()`].
+fn matrix_euler(m: [[f64; 3]; 3]) -> [f64; 3] {
+    let ry = (-m[2][0]).clamp(-1.0, 1.0).asin();
+    if m[2][0].abs() < 1.0 - 1e-9 {
+        [m[2][1].atan2(m[2][2]), ry, m[1][0].atan2(m[0][0])]
+    } else {
+        // looking straight up or down, so put all of the roll in X
+        [(-m[1][2]).atan2(m[1][1]), ry, 0.0]
+    }
+}
+
+impl CameraPath {
+    /// Create an empty [`CameraPath`] at 24 frames per second.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            fps: 24.0,
+            keys: vec![],
+        }
+    }
+
+    /// Add `key`, keeping the keys in order of frame.
+    #[must_use]
+    pub fn push(mut self, key: CameraKey) -> Self {
+        let index = self.keys.partition_point(|k| k.frame <= key.frame);
+        self.keys.insert(index, key);
+        self
+    }
+
+    /// Parse a [`CameraPath`] from CSV, see the [module](self) docs.
+    ///
+    /// # Errors
+    ///
+    /// If a line doesn't have 11 numbers.
+    pub fn from_csv(text: &str) -> Result<Self> {
+        let mut path = Self::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            // comments, empties and the header
+            if line.is_empty() || line.starts_with('#') || line.starts_with("frame") {
+                continue;
+            }
+
+            let values = line
+                .split(',')
+                .map(|value| value.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(anyhow::Error::from)
+                .and_then(|values| {
+                    if values.len() == 11 {
+                        Ok(values)
+                    } else {
+                        bail!("Expected 11 values, found {}", values.len())
+                    }
+                })
+                .with_context(|| format!("Line {}: {line}", line_no + 1))?;
+
+            path = path.push(CameraKey {
+                frame: values[0],
+                look_from: vec3![values[1], values[2], values[3]],
+                look_at: vec3![values[4], values[5], values[6]],
+                v_up: vec3![values[7], values[8], values[9]],
+                vfov: values[10],
+            });
+        }
+        Ok(path)
+    }
+
+    /// Write the [`CameraPath`] as CSV with a header line, see the
+    /// [module](self) docs.
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("frame,from_x,from_y,from_z,at_x,at_y,at_z,up_x,up_y,up_z,vfov\n");
+        for key in &self.keys {
+            let (from, at, up) = (key.look_from, key.look_at, key.v_up);
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                key.frame,
+                from[0],
+                from[1],
+                from[2],
+                at[0],
+                at[1],
+                at[2],
+                up[0],
+                up[1],
+                up[2],
+                key.vfov
+            )
+            .expect("Writing to a String can't fail");
+        }
+        csv
+    }
+
+    /// Parse a [`CameraPath`] from Blender JSON, see the [module](self) docs.
+    /// Each camera looks at a point a unit in front of it.
+    ///
+    /// # Errors
+    ///
+    /// If the JSON is malformed or missing fields, or the resolution or a
+    /// focal length isn't positive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::camera_path::CameraPath;
+    ///
+    /// // Blender's default camera, looking up the Y axis and tilted down
+    /// let json = r#"{
+    ///     "fps": 30.0,
+    ///     "resolution_x": 1920,
+    ///     "resolution_y": 1080,
+    ///     "frames": [
+    ///         { "frame": 1, "location": [0.0, -10.0, 2.0], "rotation_euler": [1.2, 0.0, 0.0], "lens": 50.0 }
+    ///     ]
+    /// }"#;
+    /// let path = CameraPath::from_blender_json(json).unwrap();
+    /// let key = path.keys[0];
+    ///
+    /// assert_eq!(path.fps, 30.0);
+    /// // Blender's -Y is rustrace's +Z
+    /// assert!((key.look_from[2] - 10.0).abs() < 1e-9);
+    /// assert!((key.look_from[1] - 2.0).abs() < 1e-9);
+    /// assert!(key.look_at[2] < key.look_from[2] && key.look_at[1] < key.look_from[1]);
+    /// // a 50mm lens on a full frame sensor at 16:9
+    /// assert!((key.vfov - 22.9).abs() < 0.1);
+    ///
+    /// // and back again
+    /// let back = CameraPath::from_blender_json(&path.to_blender_json(1920, 1080)).unwrap();
+    /// assert!((back.keys[0].look_at - key.look_at).length() < 1e-9);
+    /// assert!((back.keys[0].vfov - key.vfov).abs() < 1e-9);
+    /// ```
+    pub fn from_blender_json(text: &str) -> Result<Self> {
+        let blender: BlenderPath = serde_json::from_str(text)?;
+        if blender.resolution_x == 0 || blender.resolution_y == 0 {
+            bail!("Resolution must be positive");
+        }
+        let aspect = f64::from(blender.resolution_x) / f64::from(blender.resolution_y);
+
+        let mut path = Self {
+            fps: blender.fps,
+            keys: vec![],
+        };
+        for frame in blender.frames {
+            if !(frame.lens > 0.0 && frame.lens.is_finite()) {
+                bail!("Frame {}: lens must be positive", frame.frame);
+            }
+
+            // the camera looks down its local -Z with +Y up
+            let m = euler_matrix(frame.rotation_euler);
+            let forward = from_blender([-m[0][2], -m[1][2], -m[2][2]]);
+            let up = from_blender([m[0][1], m[1][1], m[2][1]]);
+            let look_from = from_blender(frame.location);
+
+            // the sensor spans the longer side
+            let half_fov = (0.5 * blender.sensor_width / frame.lens).atan();
+            let vfov = if aspect >= 1.0 {
+                2.0 * (half_fov.tan() / aspect).atan()
+            } else {
+                2.0 * half_fov
+            };
+
+            path = path.push(CameraKey {
+                frame: frame.frame,
+                look_from,
+                look_at: look_from + forward,
+                v_up: up,
+                vfov: vfov.to_degrees(),
+            });
+        }
+        Ok(path)
+    }
+
+    /// Write the [`CameraPath`] as Blender JSON for an image `width` by
+    /// `height` pixels, with a full frame sensor. See the [module](self)
+    /// docs.
+    ///
+    /// # Panics
+    ///
+    /// If `width` or `height` is `0`.
+    #[must_use]
+    pub fn to_blender_json(&self, width: u32, height: u32) -> String {
+        assert!(width > 0 && height > 0, "Resolution must be positive");
+        let aspect = f64::from(width) / f64::from(height);
+        let sensor_width = default_sensor_width();
+
+        let frames = self
+            .keys
+            .iter()
+            .map(|key| {
+                let forward = (key.look_at - key.look_from).unit();
+                let right = cross(&forward, &key.v_up).unit();
+                let up = cross(&right, &forward);
+                let [x, y, z] = [right, up, -forward].map(to_blender);
+                let m = [[x[0], y[0], z[0]], [x[1], y[1], z[1]], [x[2], y[2], z[2]]];
+
+                let half_height = key.vfov.to_radians() / 2.0;
+                let half_fov = if aspect >= 1.0 {
+                    (half_height.tan() * aspect).atan()
+                } else {
+                    half_height
+                };
+
+                BlenderFrame {
+                    frame: key.frame,
+                    location: to_blender(key.look_from),
+                    rotation_euler: matrix_euler(m),
+                    lens: 0.5 * sensor_width / half_fov.tan(),
+                }
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&BlenderPath {
+            fps: self.fps,
+            resolution_x: width,
+            resolution_y: height,
+            sensor_width,
+            frames,
+        })
+        .expect("Camera paths are always valid JSON")
+    }
+
+    /// Load a [`CameraPath`] from a `.csv` or Blender `.json` file.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or parsed, or has another extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read camera path {}", path.display()))?;
+        match extension(path).as_deref() {
+            Some("csv") => Self::from_csv(&text),
+            Some("json") => Self::from_blender_json(&text),
+            _ => bail!("Unknown camera path format {}", path.display()),
+        }
+        .with_context(|| format!("Couldn't parse camera path {}", path.display()))
+    }
+
+    /// Save the [`CameraPath`] to a `.csv` or Blender `.json` file, the
+    /// latter for an image `width` by `height` pixels.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be written or has another extension.
+    ///
+    /// # Panics
+    ///
+    /// If saving JSON and `width` or `height` is `0`.
+    pub fn save<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> Result<()> {
+        let path = path.as_ref();
+        let text = match extension(path).as_deref() {
+            Some("csv") => self.to_csv(),
+            Some("json") => self.to_blender_json(width, height),
+            _ => bail!("Unknown camera path format {}", path.display()),
+        };
+        fs::write(path, text)
+            .with_context(|| format!("Couldn't write camera path {}", path.display()))
+    }
+
+    /// The camera at `frame`, linearly interpolated between the keys either
+    /// side and held before the first and after the last, or [`None`] if
+    /// there are no keys.
+    #[must_use]
+    pub fn key_at(&self, frame: f64) -> Option<CameraKey> {
+        let first = self.keys.first()?;
+        let index = self.keys.partition_point(|k| k.frame <= frame);
+        if index == 0 {
+            return Some(CameraKey { frame, ..*first });
+        }
+        let before = self.keys[index - 1];
+        let Some(&after) = self.keys.get(index) else {
+            return Some(CameraKey { frame, ..before });
+        };
+
+        let t = (frame - before.frame) / (after.frame - before.frame);
+        let lerp = |a: Vec3, b: Vec3| a * (1.0 - t) + b * t;
+        Some(CameraKey {
+            frame,
+            look_from: lerp(before.look_from, after.look_from),
+            look_at: lerp(before.look_at, after.look_at),
+            v_up: lerp(before.v_up, after.v_up).unit(),
+            vfov: before.vfov * (1.0 - t) + after.vfov * t,
+        })
+    }
+
+    /// Set the pose and field of view of `builder` to the camera at `frame`,
+    /// see [`CameraPath::key_at()`]. An empty path leaves `builder` as it is.
+    #[must_use]
+    pub fn apply_at(&self, frame: f64, builder: CameraBuilder) -> CameraBuilder {
+        match self.key_at(frame) {
+            Some(key) => builder
+                .set_look_from(key.look_from)
+                .set_look_at(key.look_at)
+                .set_v_up(key.v_up)
+                .set_vfov_degrees(key.vfov),
+            None => builder,
+        }
+    }
+}
+
+/// The lowercase extension of `path`.
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+}
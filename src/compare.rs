@@ -0,0 +1,434 @@
+//! This module contains the [`Comparison`] of two images by error metrics,
+//! for checking renders against golden images and judging sampler or
+//! denoiser changes by more than eye.
+//!
+//! Three metrics are computed: the root mean squared error of the linear
+//! values, the structural similarity (SSIM) of their display luminance, and
+//! LDR [FLIP](https://research.nvidia.com/publication/2020-07_flip-difference-evaluator-alternating-images),
+//! which models how visible the differences are when flipping between the
+//! images on a monitor.
+
+use anyhow::{bail, Result};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    film::FilmBuffer,
+    vec3,
+    vec3::{linear_to_gamma, Vec3},
+};
+
+/// The pixels per degree of vision [`Comparison::new()`] assumes: a 0.7m
+/// viewing distance from a 24" 4K monitor, as in the FLIP paper.
+pub const DEFAULT_PIXELS_PER_DEGREE: f64 = 67.02;
+
+/// The error metrics between a reference image and a test image, with maps
+/// of where the errors are. See the [module docs](crate::compare).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{compare::Comparison, film::FilmBuffer, vec3, vec3::Vec3};
+///
+/// let grey = |value| {
+///     let mut film = FilmBuffer::new(16, 16);
+///     film.pixels_mut().fill(vec3![value, value, value]);
+///     film
+/// };
+/// let reference = grey(0.5);
+///
+/// let same = Comparison::new(&reference, &reference.clone()).unwrap();
+/// assert_eq!(same.rmse, 0.0);
+/// assert!((same.ssim - 1.0).abs() < 1e-9);
+/// assert_eq!(same.flip, 0.0);
+///
+/// // a speck of noise is a small error, a black image a large one
+/// let mut speck = reference.clone();
+/// speck.set(8, 8, vec3![1.0, 1.0, 1.0]);
+/// let speck = Comparison::new(&reference, &speck).unwrap();
+/// let black = Comparison::new(&reference, &grey(0.0)).unwrap();
+/// assert!(speck.rmse < black.rmse);
+/// assert!(speck.ssim > black.ssim);
+/// assert!(speck.flip > 0.0 && speck.flip < black.flip);
+/// assert!(speck.flip_map.get(8, 8)[0] > speck.flip_map.get(0, 0)[0]);
+///
+/// // which need the same size of image
+/// assert!(Comparison::new(&reference, &FilmBuffer::new(8, 8)).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// The root mean squared error over every channel of every pixel.
+    pub rmse: f64,
+    /// The mean structural similarity, `1.0` for identical images.
+    pub ssim: f64,
+    /// The mean FLIP error, `0.0` for identical images and up to `1.0`.
+    pub flip: f64,
+    /// One minus the structural similarity at each pixel, so brighter is
+    /// worse.
+    pub ssim_map: FilmBuffer,
+    /// The FLIP error at each pixel.
+    pub flip_map: FilmBuffer,
+}
+
+impl Comparison {
+    /// Compare `test` against `reference`, viewed at
+    /// [`DEFAULT_PIXELS_PER_DEGREE`].
+    ///
+    /// # Errors
+    ///
+    /// If the images aren't the same size.
+    pub fn new(reference: &FilmBuffer, test: &FilmBuffer) -> Result<Self> {
+        Self::with_pixels_per_degree(reference, test, DEFAULT_PIXELS_PER_DEGREE)
+    }
+
+    /// Compare `test` against `reference`, viewed with `pixels_per_degree`
+    /// of vision, which sets how far apart differences FLIP can tell apart.
+    ///
+    /// # Errors
+    ///
+    /// If the images aren't the same size, or `pixels_per_degree` isn't
+    /// positive and finite.
+    pub fn with_pixels_per_degree(
+        reference: &FilmBuffer,
+        test: &FilmBuffer,
+        pixels_per_degree: f64,
+    ) -> Result<Self> {
+        if (reference.width(), reference.height()) != (test.width(), test.height()) {
+            bail!(
+                "Can't compare a {}x{} image with a {}x{} one",
+                reference.width(),
+                reference.height(),
+                test.width(),
+                test.height()
+            );
+        }
+        if !(pixels_per_degree > 0.0 && pixels_per_degree.is_finite()) {
+            bail!("Pixels per degree must be positive and finite");
+        }
+
+        let image = Image {
+            width: reference.width() as usize,
+            height: reference.height() as usize,
+        };
+        let ssim_map = image.ssim(reference.pixels(), test.pixels());
+        let flip_map = image.flip(reference.pixels(), test.pixels(), pixels_per_degree);
+
+        #[expect(clippy::cast_precision_loss)]
+        let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+        let squared_errors: Vec<f64> = reference
+            .pixels()
+            .iter()
+            .zip(test.pixels())
+            .map(|(a, b)| (*a - *b).length_squared() / 3.0)
+            .collect();
+        let to_film = |values: &[f64]| {
+            FilmBuffer::from_rows(
+                values
+                    .chunks(image.width.max(1))
+                    .map(|row| row.iter().map(|&v| vec3![v, v, v]).collect())
+                    .collect(),
+            )
+        };
+
+        Ok(Self {
+            rmse: mean(&squared_errors).sqrt(),
+            ssim: 1.0 - mean(&ssim_map),
+            flip: mean(&flip_map),
+            ssim_map: to_film(&ssim_map),
+            flip_map: to_film(&flip_map),
+        })
+    }
+}
+
+/// The size of the images being compared, for filtering their channels.
+#[derive(Debug, Clone, Copy)]
+struct Image {
+    width: usize,
+    height: usize,
+}
+
+impl Image {
+    /// One minus the SSIM of the display luminance of `a` and `b` at each
+    /// pixel, over a Gaussian window.
+    fn ssim(self, a: &[Vec3], b: &[Vec3]) -> Vec<f64> {
+        const C1: f64 = 0.01 * 0.01;
+        const C2: f64 = 0.03 * 0.03;
+        let display = |pixels: &[Vec3]| -> Vec<f64> {
+            pixels
+                .iter()
+                .map(|c| linear_to_gamma(c.luminance()).clamp(0.0, 1.0))
+                .collect()
+        };
+        let (x, y) = (display(a), display(b));
+        let window = gaussian(1.5, 5);
+        let blur = |values: Vec<f64>| self.convolve(&values, &window, &window);
+
+        let mean_x = blur(x.clone());
+        let mean_y = blur(y.clone());
+        let xx = blur(x.iter().map(|v| v * v).collect());
+        let yy = blur(y.iter().map(|v| v * v).collect());
+        let xy = blur(x.iter().zip(&y).map(|(a, b)| a * b).collect());
+
+        (0..x.len())
+            .map(|i| {
+                let (mx, my) = (mean_x[i], mean_y[i]);
+                let var_x = xx[i] - mx * mx;
+                let var_y = yy[i] - my * my;
+                let cov = xy[i] - mx * my;
+                let ssim = ((2.0 * mx * my + C1) * (2.0 * cov + C2))
+                    / ((mx * mx + my * my + C1) * (var_x + var_y + C2));
+                1.0 - ssim
+            })
+            .collect()
+    }
+
+    /// The LDR FLIP error between `reference` and `test` at each pixel.
+    fn flip(self, reference: &[Vec3], test: &[Vec3], pixels_per_degree: f64) -> Vec<f64> {
+        const QC: f64 = 0.7;
+        const PC: f64 = 0.4;
+        const PT: f64 = 0.95;
+        const QF: f64 = 0.5;
+
+        let colour = self.flip_colours(reference, pixels_per_degree);
+        let test_colour = self.flip_colours(test, pixels_per_degree);
+        let features = self.flip_features(reference, pixels_per_degree);
+        let test_features = self.flip_features(test, pixels_per_degree);
+
+        // the largest colour difference, between green and blue
+        let lab = |rgb: Vec3| hunt(lab(rgb_to_xyz(rgb)));
+        let c_max = hyab(lab(vec3![0.0, 1.0, 0.0]), lab(vec3![0.0, 0.0, 1.0])).powf(QC);
+
+        (0..reference.len())
+            .map(|i| {
+                let delta = hyab(colour[i], test_colour[i]).powf(QC);
+                let delta_colour = if delta < PC * c_max {
+                    delta * PT / (PC * c_max)
+                } else {
+                    PT + (delta - PC * c_max) / (c_max - PC * c_max) * (1.0 - PT)
+                };
+                let (edge, point) = features[i];
+                let (test_edge, test_point) = test_features[i];
+                let delta_feature = (edge - test_edge).abs().max((point - test_point).abs());
+                let delta_feature = (std::f64::consts::FRAC_1_SQRT_2 * delta_feature).powf(QF);
+                delta_colour.clamp(0.0, 1.0).powf(1.0 - delta_feature)
+            })
+            .collect()
+    }
+
+    /// The Hunt-adjusted L\*a\*b\* colours of `pixels` after filtering by
+    /// the contrast sensitivity of the eye in an opponent colour space.
+    fn flip_colours(self, pixels: &[Vec3], pixels_per_degree: f64) -> Vec<Vec3> {
+        // (a1, b1, a2, b2) for the achromatic, red-green and blue-yellow
+        // channels
+        const CSF: [[f64; 4]; 3] = [
+            [1.0, 0.0047, 0.0, 1e-5],
+            [1.0, 0.0053, 0.0, 1e-5],
+            [34.1, 0.04, 13.5, 0.025],
+        ];
+        let b_max = CSF.iter().flat_map(|c| [c[1], c[3]]).fold(0.0, f64::max);
+        let pi = std::f64::consts::PI;
+        #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let radius = (3.0 * (b_max / (2.0 * pi * pi)).sqrt() * pixels_per_degree).ceil() as usize;
+
+        let opponent: Vec<Vec3> = pixels
+            .iter()
+            .map(|c| xyz_to_ycxcz(rgb_to_xyz(clamped(*c))))
+            .collect();
+        let mut filtered = vec![Vec3::default(); pixels.len()];
+        for (channel, [a1, b1, a2, b2]) in CSF.into_iter().enumerate() {
+            let values: Vec<f64> = opponent.iter().map(|c| c[channel]).collect();
+            // each Gaussian term is separable, split evenly between the axes
+            let term = |a: f64, b: f64| -> Vec<f64> {
+                let scale = (a * (pi / b).sqrt()).sqrt();
+                (0..=2 * radius)
+                    .map(|i| {
+                        #[expect(clippy::cast_precision_loss)]
+                        let x = (i as f64 - radius as f64) / pixels_per_degree;
+                        scale * (-pi * pi * x * x / b).exp()
+                    })
+                    .collect()
+            };
+            let terms = [term(a1, b1), term(a2, b2)];
+            let total: f64 = terms.iter().map(|k| k.iter().sum::<f64>().powi(2)).sum();
+            for kernel in &terms {
+                for (out, value) in filtered
+                    .iter_mut()
+                    .zip(self.convolve(&values, kernel, kernel))
+                {
+                    out[channel] += value / total;
+                }
+            }
+        }
+
+        filtered
+            .into_iter()
+            .map(|c| hunt(lab(rgb_to_xyz(clamped(xyz_to_rgb(ycxcz_to_xyz(c)))))))
+            .collect()
+    }
+
+    /// The strength of edges and points in the luminance of `pixels`.
+    fn flip_features(self, pixels: &[Vec3], pixels_per_degree: f64) -> Vec<(f64, f64)> {
+        let sigma = 0.5 * 0.082 * pixels_per_degree;
+        #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let radius = (3.0 * sigma).ceil() as usize;
+        let luminance: Vec<f64> = pixels.iter().map(|c| rgb_to_xyz(clamped(*c))[1]).collect();
+
+        let blur = gaussian(sigma, radius);
+        let offsets = || {
+            #[expect(clippy::cast_precision_loss)]
+            (0..=2 * radius).map(move |i| i as f64 - radius as f64)
+        };
+        let falloff = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+        let edge = balanced(offsets().map(|x| -x * falloff(x)).collect());
+        let point = balanced(
+            offsets()
+                .map(|x| (x * x / (sigma * sigma) - 1.0) * falloff(x))
+                .collect(),
+        );
+
+        let edge_x = self.convolve(&luminance, &edge, &blur);
+        let edge_y = self.convolve(&luminance, &blur, &edge);
+        let point_x = self.convolve(&luminance, &point, &blur);
+        let point_y = self.convolve(&luminance, &blur, &point);
+        (0..pixels.len())
+            .map(|i| (edge_x[i].hypot(edge_y[i]), point_x[i].hypot(point_y[i])))
+            .collect()
+    }
+
+    /// Convolve a channel of the image with `horizontal` then `vertical`,
+    /// both an odd length, clamping at the borders.
+    fn convolve(self, values: &[f64], horizontal: &[f64], vertical: &[f64]) -> Vec<f64> {
+        let (width, height) = (self.width, self.height);
+        let pass = |values: &[f64], kernel: &[f64], along_rows: bool| -> Vec<f64> {
+            let radius = kernel.len() / 2;
+            (0..height)
+                .into_par_iter()
+                .flat_map_iter(|y| {
+                    (0..width).map(move |x| {
+                        kernel
+                            .iter()
+                            .enumerate()
+                            .map(|(k, weight)| {
+                                let (sx, sy) = if along_rows {
+                                    ((x + k).saturating_sub(radius).min(width - 1), y)
+                                } else {
+                                    (x, (y + k).saturating_sub(radius).min(height - 1))
+                                };
+                                weight * values[sy * width + sx]
+                            })
+                            .sum::<f64>()
+                    })
+                })
+                .collect()
+        };
+        if values.is_empty() {
+            return vec![];
+        }
+        pass(&pass(values, horizontal, true), vertical, false)
+    }
+}
+
+/// A normalised Gaussian kernel with standard deviation `sigma` in pixels,
+/// `radius` pixels either side of the centre.
+fn gaussian(sigma: f64, radius: usize) -> Vec<f64> {
+    #[expect(clippy::cast_precision_loss)]
+    let kernel: Vec<f64> = (0..=2 * radius)
+        .map(|i| {
+            let x = i as f64 - radius as f64;
+            (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let total: f64 = kernel.iter().sum();
+    kernel.into_iter().map(|w| w / total).collect()
+}
+
+/// Scale the positive weights of `kernel` to sum to `1.0`, and the negative
+/// ones to `-1.0`.
+fn balanced(kernel: Vec<f64>) -> Vec<f64> {
+    let positive: f64 = kernel.iter().filter(|&&w| w > 0.0).sum();
+    let negative: f64 = -kernel.iter().filter(|&&w| w < 0.0).sum::<f64>();
+    kernel
+        .into_iter()
+        .map(|w| if w > 0.0 { w / positive } else { w / negative })
+        .collect()
+}
+
+/// Linear sRGB to CIE XYZ, under D65.
+fn rgb_to_xyz(c: Vec3) -> Vec3 {
+    vec3![
+        0.412_456_4 * c[0] + 0.357_576_1 * c[1] + 0.180_437_5 * c[2],
+        0.212_672_9 * c[0] + 0.715_152_2 * c[1] + 0.072_175 * c[2],
+        0.019_333_9 * c[0] + 0.119_192 * c[1] + 0.950_304_1 * c[2]
+    ]
+}
+
+/// CIE XYZ to linear sRGB, under D65.
+fn xyz_to_rgb(c: Vec3) -> Vec3 {
+    vec3![
+        3.240_454_2 * c[0] - 1.537_138_5 * c[1] - 0.498_531_4 * c[2],
+        -0.969_266 * c[0] + 1.876_010_8 * c[1] + 0.041_556 * c[2],
+        0.055_643_4 * c[0] - 0.204_025_9 * c[1] + 1.057_225_2 * c[2]
+    ]
+}
+
+/// Clamp each channel of `c` into `0.0..=1.0`, the range of a display.
+fn clamped(c: Vec3) -> Vec3 {
+    vec3![
+        c[0].clamp(0.0, 1.0),
+        c[1].clamp(0.0, 1.0),
+        c[2].clamp(0.0, 1.0)
+    ]
+}
+
+/// The XYZ of white, which colours are taken relative to.
+fn white() -> Vec3 {
+    rgb_to_xyz(vec3![1.0, 1.0, 1.0])
+}
+
+/// CIE XYZ relative to [`white()`].
+fn relative(c: Vec3) -> Vec3 {
+    let w = white();
+    vec3![c[0] / w[0], c[1] / w[1], c[2] / w[2]]
+}
+
+/// CIE XYZ to the linear opponent space YCxCz.
+fn xyz_to_ycxcz(c: Vec3) -> Vec3 {
+    let n = relative(c);
+    vec3![
+        116.0 * n[1] - 16.0,
+        500.0 * (n[0] - n[1]),
+        200.0 * (n[1] - n[2])
+    ]
+}
+
+/// YCxCz back to CIE XYZ.
+fn ycxcz_to_xyz(c: Vec3) -> Vec3 {
+    let y = (c[0] + 16.0) / 116.0;
+    vec3![c[1] / 500.0 + y, y, y - c[2] / 200.0] * white()
+}
+
+/// CIE XYZ to CIE L\*a\*b\*.
+fn lab(c: Vec3) -> Vec3 {
+    const DELTA: f64 = 6.0 / 29.0;
+    let f = |t: f64| {
+        if t > DELTA.powi(3) {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+    let n = relative(c);
+    let (fx, fy, fz) = (f(n[0]), f(n[1]), f(n[2]));
+    vec3![116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Scale the chroma of an L\*a\*b\* colour by its lightness, as colours
+/// look less saturated when darker (the Hunt effect).
+fn hunt(c: Vec3) -> Vec3 {
+    vec3![c[0], 0.01 * c[0] * c[1], 0.01 * c[0] * c[2]]
+}
+
+/// The HyAB distance between two L\*a\*b\* colours, which suits large
+/// differences better than the Euclidean distance.
+fn hyab(a: Vec3, b: Vec3) -> f64 {
+    (a[0] - b[0]).abs() + (a[1] - b[1]).hypot(a[2] - b[2])
+}
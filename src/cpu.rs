@@ -0,0 +1,72 @@
+//! This module contains runtime CPU feature detection. Hot kernels such as
+//! [`ray_hit_batch()`](crate::aabb::ray_hit_batch) and
+//! [`vec3::sum()`](crate::vec3::sum) are compiled once per SIMD instruction set
+//! with [`multiversion`], and the best version the CPU supports is picked the
+//! first time each is called. One portable binary therefore still uses AVX2,
+//! AVX-512 or NEON where they're available, without `-C target-cpu=native`.
+//!
+//! The kernels are plain loops over fixed-size lanes with no intrinsics, so
+//! the vector instructions come from the compiler auto-vectorising each
+//! version for its target. Keep their loops branch-light and their lane
+//! counts fixed, or they quietly fall back to scalar code.
+
+use std::fmt;
+
+/// The SIMD instruction set the multiversioned kernels will run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SimdLevel {
+    /// The baseline of the compilation target, e.g. SSE2 on `x86_64`.
+    Baseline,
+    /// NEON on `aarch64`.
+    Neon,
+    /// AVX2 and FMA on `x86_64`.
+    Avx2,
+    /// AVX-512 (F and VL) on `x86_64`.
+    Avx512,
+}
+
+impl fmt::Display for SimdLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SimdLevel::Baseline => "baseline",
+            SimdLevel::Neon => "NEON",
+            SimdLevel::Avx2 => "AVX2",
+            SimdLevel::Avx512 => "AVX-512",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Detect the [`SimdLevel`] of the running CPU, matching the targets the
+/// kernels are compiled for. Useful for logging alongside benchmarks.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::cpu::{simd_level, SimdLevel};
+///
+/// let level = simd_level();
+/// assert!(level >= SimdLevel::Baseline);
+/// println!("Rendering with {level} kernels");
+/// ```
+#[must_use]
+pub fn simd_level() -> SimdLevel {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512vl") {
+            return SimdLevel::Avx512;
+        }
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return SimdLevel::Avx2;
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return SimdLevel::Neon;
+        }
+    }
+
+    SimdLevel::Baseline
+}
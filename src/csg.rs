@@ -0,0 +1,256 @@
+//! This module contains [`Csg`], which combines two closed [`Hittable`]s into
+//! one solid by constructive solid geometry, such as a lens from two spheres
+//! or a box with a hole cut through it.
+//!
+//! Both children are followed along the [ray](Ray), tracking whether it is
+//! inside each, and the first crossing where the ray enters or leaves the
+//! combined solid is the hit. The children must be closed and not overlap
+//! themselves for inside and outside to make sense, so build boxes with
+//! [`cuboid()`](crate::quad::cuboid) rather than loose quads.
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    tolerance::Tolerances,
+    vec3::Vec3,
+};
+
+/// The most surface crossings followed along one [ray](Ray) before giving up,
+/// which only matters for children that aren't closed.
+const MAX_CROSSINGS: usize = 64;
+
+/// How the two children of a [`Csg`] are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CsgOp {
+    /// Inside either child.
+    #[default]
+    Union,
+    /// Inside both children.
+    Intersection,
+    /// Inside the first child but not the second.
+    Difference,
+}
+
+impl CsgOp {
+    /// Whether a point is inside the combination, given whether it is inside
+    /// each child.
+    #[must_use]
+    pub fn inside(self, a: bool, b: bool) -> bool {
+        match self {
+            Self::Union => a || b,
+            Self::Intersection => a && b,
+            Self::Difference => a && !b,
+        }
+    }
+}
+
+/// Two closed [`Hittable`]s combined by a [`CsgOp`], see the
+/// [module docs](crate::csg). The surfaces keep the materials of the child
+/// they came from, so the inside of a cut-out shows the material of the
+/// cutter.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     csg::{Csg, CsgOp},
+///     hit::Hittable,
+///     material::Dielectric,
+///     ray,
+///     ray::Ray,
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let glass = || Box::new(Dielectric::new(1.5));
+///
+/// // a lens where two large spheres overlap
+/// let lens = Csg::new(
+///     Sphere { centre: vec3![0.0, 0.0, -1.5], radius: 2.0, mat: glass() },
+///     Sphere { centre: vec3![0.0, 0.0, 1.5], radius: 2.0, mat: glass() },
+///     CsgOp::Intersection,
+/// );
+///
+/// // in through the front of the lens, and out through the back
+/// let along_axis = ray!(vec3![0.0, 0.0, 5.0], vec3![0.0, 0.0, -1.0]);
+/// let rec = lens.nearest_hit_along(&along_axis).unwrap();
+/// assert_eq!(rec.p, vec3![0.0, 0.0, 0.5]);
+/// assert!(rec.front_face);
+///
+/// let inside = ray!(vec3![0.0, 0.0, 0.0], vec3![0.0, 0.0, -1.0]);
+/// let rec = lens.nearest_hit_along(&inside).unwrap();
+/// assert_eq!(rec.p, vec3![0.0, 0.0, -0.5]);
+/// assert!(!rec.front_face);
+///
+/// // beside the lens, but through both spheres
+/// assert!(lens
+///     .nearest_hit_along(&ray!(vec3![1.8, 0.0, 5.0], vec3![0.0, 0.0, -1.0]))
+///     .is_none());
+///
+/// assert!(lens.bound().z.size() <= 1.0 + 1e-9);
+/// ```
+///
+/// Cutting a sphere out of the middle of a box:
+///
+/// ```rust
+/// use rustrace::{
+///     csg::{Csg, CsgOp},
+///     hit::Hittable,
+///     material::Lambertian,
+///     quad::cuboid,
+///     ray,
+///     ray::Ray,
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let grey = Lambertian::new(vec3![0.5, 0.5, 0.5]);
+/// let block = Csg::new(
+///     cuboid(vec3![-1.0, -1.0, -1.0], vec3![1.0, 1.0, 1.0], &grey),
+///     Sphere { centre: vec3![0.0, 0.0, 1.0], radius: 0.5, mat: Box::new(grey.clone()) },
+///     CsgOp::Difference,
+/// );
+///
+/// // into the bowl cut in the front face
+/// let rec = block
+///     .nearest_hit_along(&ray!(vec3![0.0, 0.0, 5.0], vec3![0.0, 0.0, -1.0]))
+///     .unwrap();
+/// assert_eq!(rec.p, vec3![0.0, 0.0, 0.5]);
+/// assert!(rec.front_face);
+/// assert_eq!(rec.norm, vec3![0.0, 0.0, 1.0]);
+///
+/// // the front face is still there away from the bowl
+/// let rec = block
+///     .nearest_hit_along(&ray!(vec3![0.8, 0.8, 5.0], vec3![0.0, 0.0, -1.0]))
+///     .unwrap();
+/// assert_eq!(rec.p, vec3![0.8, 0.8, 1.0]);
+/// ```
+#[derive(Debug)]
+pub struct Csg<A: Hittable, B: Hittable> {
+    a: A,
+    b: B,
+    op: CsgOp,
+    aabb: Aabb,
+}
+
+impl<A: Hittable, B: Hittable> Csg<A, B> {
+    /// Combine `a` and `b` by `op`.
+    #[must_use]
+    pub fn new(a: A, b: B, op: CsgOp) -> Self {
+        let aabb = Self::combined_bound(&a, &b, op);
+        Self { a, b, op, aabb }
+    }
+
+    /// The operation combining the children.
+    #[must_use]
+    pub fn op(&self) -> CsgOp {
+        self.op
+    }
+
+    /// Everything for a union, only the overlap for an intersection, and just
+    /// the first child for a difference.
+    fn combined_bound(a: &A, b: &B, op: CsgOp) -> Aabb {
+        let (first, second) = (a.bound(), b.bound());
+        match op {
+            CsgOp::Union => {
+                let mut aabb = first;
+                aabb.union(&second);
+                aabb
+            }
+            CsgOp::Intersection => {
+                let overlap = |x: Interval, y: Interval| {
+                    let overlap = Interval::new(x.min.max(y.min), x.max.min(y.max));
+                    if overlap.min > overlap.max {
+                        Interval::empty()
+                    } else {
+                        overlap
+                    }
+                };
+                Aabb {
+                    x: overlap(first.x, second.x),
+                    y: overlap(first.y, second.y),
+                    z: overlap(first.z, second.z),
+                }
+            }
+            CsgOp::Difference => first,
+        }
+    }
+}
+
+impl<A: Hittable, B: Hittable> Hittable for Csg<A, B> {
+    /// Step through the crossings of both children in order, returning the
+    /// first that changes whether the [ray](Ray) is inside the combination.
+    /// Whether the ray starts inside a child is taken from the way it first
+    /// crosses that child's surface.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.aabb.ray_interval(r)?;
+
+        let mut next_a = self.a.hit(r, ray_t);
+        let mut next_b = self.b.hit(r, ray_t);
+        let mut in_a = next_a.as_ref().is_some_and(|h| !h.front_face);
+        let mut in_b = next_b.as_ref().is_some_and(|h| !h.front_face);
+        let mut inside = self.op.inside(in_a, in_b);
+
+        for _ in 0..MAX_CROSSINGS {
+            let from_a = match (&next_a, &next_b) {
+                (None, None) => return None,
+                (Some(a), Some(b)) => a.t <= b.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+            };
+
+            let crossing = if from_a {
+                let crossing = next_a.take()?;
+                in_a = crossing.front_face;
+                next_a = self.a.hit(r, Interval::new(crossing.t, ray_t.max));
+                crossing
+            } else {
+                let crossing = next_b.take()?;
+                in_b = crossing.front_face;
+                next_b = self.b.hit(r, Interval::new(crossing.t, ray_t.max));
+                crossing
+            };
+
+            let now_inside = self.op.inside(in_a, in_b);
+            if now_inside != inside {
+                // the normal already faces the ray, so only which side of
+                // the combined surface it hit changes
+                return Some(HitRecord {
+                    front_face: now_inside,
+                    ..crossing
+                });
+            }
+            inside = now_inside;
+        }
+
+        None
+    }
+
+    fn bound(&self) -> Aabb {
+        self.aabb
+    }
+
+    /// Moves both children or neither.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        if !self.a.translate(offset) {
+            return false;
+        }
+        if !self.b.translate(offset) {
+            self.a.translate(-offset);
+            return false;
+        }
+        self.aabb = self.aabb.translated(offset);
+        true
+    }
+
+    /// Sets the tolerances of both children and bounds them again.
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.a.set_tolerances(tolerances);
+        self.b.set_tolerances(tolerances);
+        self.aabb = Self::combined_bound(&self.a, &self.b, self.op);
+    }
+}
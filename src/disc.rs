@@ -0,0 +1,165 @@
+//! Contains the [`Disc`] struct which models a flat circle, such as a round
+//! area light or a patch of ground under an object.
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    tolerance::Tolerances,
+    vec3::{dot, Vec3},
+};
+
+/// A flat circle of [`radius`](field@Disc::radius) about
+/// [`centre`](field@Disc::centre), facing along
+/// [`normal`](field@Disc::normal).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     disc::Disc,
+///     hit::Hittable,
+///     material::Lambertian,
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // a round patch of ground facing +Y
+/// let patch = Disc::new(
+///     vec3![0.0, 0.0, 0.0],
+///     vec3![0.0, 2.0, 0.0],
+///     1.0,
+///     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// );
+/// assert_eq!(patch.normal, vec3![0.0, 1.0, 0.0]);
+///
+/// let rec = patch
+///     .nearest_hit_along(&ray!(vec3![0.5, 2.0, 0.5], vec3![0.0, -1.0, 0.0]))
+///     .unwrap();
+/// assert_eq!(rec.p, vec3![0.5, 0.0, 0.5]);
+/// assert!(rec.front_face);
+///
+/// // inside the square around it, but outside the circle
+/// assert!(patch
+///     .nearest_hit_along(&ray!(vec3![0.8, 2.0, 0.8], vec3![0.0, -1.0, 0.0]))
+///     .is_none());
+///
+/// assert_eq!(patch.closest_point(vec3![3.0, 1.0, 0.0]), Some(vec3![1.0, 0.0, 0.0]));
+///
+/// // as wide as the circle, with some thickness
+/// let bound = patch.bound();
+/// assert_eq!(bound.x.size(), 2.0);
+/// assert!(bound.y.size() > 0.0);
+/// ```
+#[derive(Debug)]
+pub struct Disc {
+    /// The centre of the disc.
+    pub centre: Vec3,
+    /// The unit normal of the front face.
+    pub normal: Vec3,
+    /// The radius of the disc.
+    pub radius: f64,
+    /// The [`Material`] of the disc.
+    pub mat: Box<dyn Material>,
+    /// The tolerances the disc is bounded with, see
+    /// [`Hittable::set_tolerances()`].
+    pub tolerances: Tolerances,
+}
+
+impl Disc {
+    /// Create a new [`Disc`] of `radius` about `centre` facing along `normal`,
+    /// which is normalised.
+    ///
+    /// # Panics
+    ///
+    /// If `normal` is zero.
+    #[must_use]
+    pub fn new(centre: Vec3, normal: Vec3, radius: f64, mat: Box<dyn Material>) -> Self {
+        assert!(!normal.near_zero(), "Disc normal can't be zero");
+        Self {
+            centre,
+            normal: normal.unit(),
+            radius,
+            mat,
+            tolerances: Tolerances::DEFAULT,
+        }
+    }
+}
+
+impl Hittable for Disc {
+    /// Intersect the disc's plane, then check the hit is within the radius.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let denom = dot(&self.normal, &ray.direction);
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = dot(&self.normal, &(self.centre - ray.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let p = ray.at(t);
+        if (p - self.centre).length_squared() > self.radius * self.radius {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            t,
+            p,
+            norm: self.normal,
+            mat: &*self.mat,
+            front_face: false,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+            layer: None,
+            tangent: None,
+        };
+        rec.set_face_norm(ray, &self.normal);
+        Some(rec)
+    }
+
+    /// The circle reaches `radius * sqrt(1 - n²)` along each axis, where `n`
+    /// is that component of the normal, [padded](Aabb::padded()) by the
+    /// [bound padding](Tolerances::bound_padding) of its tolerances along any
+    /// axis it lies flat in.
+    fn bound(&self) -> Aabb {
+        let axis = |i: usize| {
+            let reach = self.radius * (1.0 - self.normal[i] * self.normal[i]).max(0.0).sqrt();
+            Interval::new(self.centre[i] - reach, self.centre[i] + reach)
+        };
+        Aabb {
+            x: axis(0),
+            y: axis(1),
+            z: axis(2),
+        }
+        .padded(self.tolerances.bound_padding)
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.centre += offset;
+        true
+    }
+
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.tolerances = *tolerances;
+    }
+
+    /// The projection of `point` onto the disc's plane, pulled in to the rim
+    /// if it lands outside.
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        let on_plane = point - self.normal * dot(&self.normal, &(point - self.centre));
+        let offset = on_plane - self.centre;
+        let distance = offset.length();
+        if distance <= self.radius {
+            Some(on_plane)
+        } else {
+            Some(self.centre + offset * (self.radius / distance))
+        }
+    }
+}
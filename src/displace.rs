@@ -0,0 +1,521 @@
+//! This module contains on-the-fly displacement of primitives by a
+//! [`Heightmap`], in the style of parallax occlusion mapping. Rather than
+//! subdividing the surface into triangles, each [ray](Ray) that reaches the
+//! surface is marched down into a thin shell beneath it until it passes below
+//! the height field, which is cheap in memory and works at any resolution.
+//!
+//! The relief is carved into the surface rather than raised from it, so it
+//! stays within the primitive's bounds. Grazing rays can pass through the
+//! carved-out parts, so unlike parallax mapping on a rasteriser the relief
+//! shows at the silhouette too, which suits bricks, tiles and the craters of a
+//! planet. [`DisplacedSphere`] and [`DisplacedQuad`] share the march and
+//! [`Displacement`], differing only in how they map the surface.
+
+use std::{f64::consts::PI, path::Path, sync::Arc};
+
+use anyhow::Result;
+
+use crate::{
+    aabb::Aabb,
+    film::{ColourSpace, FilmBuffer},
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    quad::Quad,
+    ray::Ray,
+    sphere::{sphere_uv, Sphere},
+    tolerance::Tolerances,
+    vec3::{cross, dot, Vec3},
+};
+
+/// A height field over `(u, v)` in `0.0..1.0`, read from the luminance of a
+/// [`FilmBuffer`] and wrapping at the edges so it can tile. `v = 0.0` is the
+/// bottom of the image.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{displace::Heightmap, film::FilmBuffer, vec3, vec3::Vec3};
+///
+/// // dark on the left, bright on the right
+/// let heightmap = Heightmap::new(FilmBuffer::from_rows(vec![vec![
+///     vec3![0.0, 0.0, 0.0],
+///     vec3![1.0, 1.0, 1.0],
+/// ]]));
+///
+/// assert!(heightmap.height_at(0.25, 0.5).abs() < 1e-9);
+/// assert!((heightmap.height_at(0.75, 0.5) - 1.0).abs() < 1e-9);
+/// // halfway between the two texel centres
+/// assert!((heightmap.height_at(0.5, 0.5) - 0.5).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Heightmap {
+    film: FilmBuffer,
+}
+
+impl Heightmap {
+    /// Create a [`Heightmap`] from the luminance of `film`.
+    ///
+    /// # Panics
+    ///
+    /// If `film` is empty.
+    #[must_use]
+    pub fn new(film: FilmBuffer) -> Self {
+        assert!(
+            film.width() > 0 && film.height() > 0,
+            "Heightmap can't be empty"
+        );
+        Self { film }
+    }
+
+    /// Load a [`Heightmap`] from an image file, read as linear data.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or decoded.
+    ///
+    /// # Panics
+    ///
+    /// If the image is empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(FilmBuffer::load(path, ColourSpace::Linear)?))
+    }
+
+    /// The width of the [`Heightmap`] in texels.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.film.width()
+    }
+
+    /// The height of the [`Heightmap`] in texels.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.film.height()
+    }
+
+    /// The height at `(u, v)`, bilinearly interpolated between texel centres.
+    #[must_use]
+    pub fn height_at(&self, u: f64, v: f64) -> f64 {
+        self.film.sample_uv(u, v).luminance()
+    }
+}
+
+/// How a surface is carved by a [`Heightmap`]: a height of `1.0` is on the
+/// surface and `0.0` is [`depth`](Displacement::depth()) below it.
+#[derive(Debug, Clone)]
+pub struct Displacement {
+    heightmap: Arc<Heightmap>,
+    depth: f64,
+    tiling: [f64; 2],
+    steps: u32,
+}
+
+impl Displacement {
+    /// Create a [`Displacement`] carving up to `depth` into the surface, with
+    /// the `heightmap` covering it once and `64` march steps.
+    ///
+    /// # Panics
+    ///
+    /// If `depth` is negative.
+    #[must_use]
+    pub fn new(heightmap: Arc<Heightmap>, depth: f64) -> Self {
+        assert!(depth >= 0.0, "Displacement depth can't be negative");
+        Self {
+            heightmap,
+            depth,
+            tiling: [1.0, 1.0],
+            steps: 64,
+        }
+    }
+
+    /// Repeat the [`Heightmap`] `u` times across and `v` times up the
+    /// surface.
+    #[must_use]
+    pub fn with_tiling(self, u: f64, v: f64) -> Self {
+        Self {
+            tiling: [u, v],
+            ..self
+        }
+    }
+
+    /// Set how many steps each [ray](Ray) takes through the displaced shell
+    /// before refining the hit. More steps find thinner features at grazing
+    /// angles.
+    ///
+    /// # Panics
+    ///
+    /// If `steps` is `0`.
+    #[must_use]
+    pub fn with_steps(self, steps: u32) -> Self {
+        assert!(steps > 0, "Need at least one march step");
+        Self { steps, ..self }
+    }
+
+    /// The largest depth carved into the surface.
+    #[must_use]
+    pub fn depth(&self) -> f64 {
+        self.depth
+    }
+
+    /// How far below the surface the relief is at `(u, v)`.
+    #[must_use]
+    pub fn depth_at(&self, u: f64, v: f64) -> f64 {
+        let height = self
+            .heightmap
+            .height_at(u * self.tiling[0], v * self.tiling[1]);
+        (1.0 - height.clamp(0.0, 1.0)) * self.depth
+    }
+
+    /// Find the first `t` in `start..end` where `below` (how far below the
+    /// surface a `t` is, minus the relief there) becomes non-negative, by
+    /// stepping then bisecting.
+    fn march<F: Fn(f64) -> f64>(&self, start: f64, end: f64, below: F) -> Option<f64> {
+        let dt = (end - start) / f64::from(self.steps);
+        let mut prev = start;
+        for step in 1..=self.steps {
+            let t = start + dt * f64::from(step);
+            if below(t) >= 0.0 {
+                let (mut air, mut solid) = (prev, t);
+                for _ in 0..16 {
+                    let mid = 0.5 * (air + solid);
+                    if below(mid) >= 0.0 {
+                        solid = mid;
+                    } else {
+                        air = mid;
+                    }
+                }
+                return Some(solid);
+            }
+            prev = t;
+        }
+        None
+    }
+}
+
+/// A [`Sphere`] carved by a [`Displacement`], with the [`Heightmap`] wrapped
+/// around it in latitude and longitude.
+///
+/// [`Ray`]s starting inside the carved shell, such as those bouncing off
+/// the relief, are marched from their origin, so the relief shadows itself.
+/// Distance queries use the undisplaced sphere.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use rustrace::{
+///     displace::{DisplacedSphere, Displacement, Heightmap},
+///     film::FilmBuffer,
+///     hit::Hittable,
+///     material::Lambertian,
+///     ray,
+///     ray::Ray,
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let half = Heightmap::new(FilmBuffer::from_rows(vec![vec![vec3![0.5, 0.5, 0.5]]]));
+/// let planet = DisplacedSphere {
+///     sphere: Sphere {
+///         centre: vec3![0.0, 0.0, 0.0],
+///         radius: 1.0,
+///         mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+///     },
+///     displacement: Displacement::new(Arc::new(half), 0.2),
+/// };
+///
+/// // half of the depth carved away everywhere
+/// let down = ray!(vec3![0.0, 5.0, 0.0], vec3![0.0, -1.0, 0.0]);
+/// let rec = planet.nearest_hit_along(&down).unwrap();
+/// assert!((rec.p[1] - 0.9).abs() < 1e-6);
+/// assert!((rec.norm[1] - 1.0).abs() < 1e-6);
+///
+/// // passes through the carved-out edge
+/// let past = ray!(vec3![0.95, 5.0, 0.0], vec3![0.0, -1.0, 0.0]);
+/// assert!(planet.nearest_hit_along(&past).is_none());
+/// ```
+#[derive(Debug)]
+pub struct DisplacedSphere {
+    /// The undisplaced sphere, whose surface is the top of the relief.
+    pub sphere: Sphere,
+    /// How the sphere is carved.
+    pub displacement: Displacement,
+}
+
+impl DisplacedSphere {
+    /// How far below the surface the relief is in direction `dir` from the
+    /// centre.
+    fn depth_towards(&self, dir: Vec3) -> f64 {
+        let (u, v) = sphere_uv(dir);
+        self.displacement.depth_at(u, v)
+    }
+
+    /// The point on the relief in direction `dir` from the centre.
+    fn relief_point(&self, dir: Vec3) -> Vec3 {
+        let dir = dir.unit();
+        self.sphere.centre + dir * (self.sphere.radius - self.depth_towards(dir))
+    }
+
+    /// The normal of the relief in direction `dir`, from central differences
+    /// about a texel apart.
+    fn relief_normal(&self, dir: Vec3) -> Vec3 {
+        let heightmap = &self.displacement.heightmap;
+        let texels = (f64::from(heightmap.width()) * self.displacement.tiling[0])
+            .max(f64::from(heightmap.height()) * self.displacement.tiling[1] * 2.0);
+        let eps = PI / texels.max(1.0);
+
+        let (t1, t2) = dir.orthonormal_basis();
+        let along = |t: Vec3| self.relief_point(dir + t * eps) - self.relief_point(dir - t * eps);
+        let norm = cross(&along(t1), &along(t2)).unit();
+        if dot(&norm, &dir) < 0.0 {
+            -norm
+        } else {
+            norm
+        }
+    }
+}
+
+impl Hittable for DisplacedSphere {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let Sphere { centre, radius, .. } = self.sphere;
+        let origin_to_centre = centre - ray.origin;
+        let a = ray.direction.length_squared();
+        let h = dot(&ray.direction, &origin_to_centre);
+        let chord = |radius: f64| {
+            let c = origin_to_centre.length_squared() - radius * radius;
+            let discriminant = h * h - a * c;
+            (discriminant >= 0.0).then(|| {
+                let sqrtd = discriminant.sqrt();
+                ((h - sqrtd) / a, (h + sqrtd) / a)
+            })
+        };
+
+        // march through the shell from where the ray enters it (or starts)
+        // to where it leaves, or reaches the solid core
+        let (enter, exit) = chord(radius)?;
+        let start = enter.max(ray_t.min);
+        let mut end = exit.min(ray_t.max);
+        let core = radius - self.displacement.depth;
+        if let Some((core_enter, _)) = chord(core).filter(|_| core > 0.0) {
+            if core_enter > start {
+                end = end.min(core_enter);
+            }
+        }
+        if start >= end {
+            return None;
+        }
+
+        let below = |t: f64| {
+            let offset = ray.at(t) - centre;
+            let dist = offset.length();
+            (radius - dist) - self.depth_towards(offset / dist)
+        };
+        let hit_t = self.displacement.march(start, end, below)?;
+
+        let hit_point = ray.at(hit_t);
+        let outward_norm = self.relief_normal((hit_point - centre).unit());
+        let mut rec = HitRecord {
+            t: hit_t,
+            p: hit_point,
+            norm: outward_norm,
+            mat: &*self.sphere.mat,
+            front_face: false,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+            layer: None,
+            tangent: None,
+        };
+        rec.set_face_norm(ray, &outward_norm);
+        Some(rec)
+    }
+
+    fn bound(&self) -> Aabb {
+        self.sphere.bound()
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.sphere.translate(offset)
+    }
+
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        self.sphere.closest_point(point)
+    }
+}
+
+/// A [`Quad`] carved by a [`Displacement`], with the [`Heightmap`] stretched
+/// across it from its [`origin`](field@Quad::origin) along
+/// [`u`](field@Quad::u) and [`v`](field@Quad::v). The relief is cut into the
+/// back of the quad, so its front face is the top of the relief, and the
+/// carved slab is closed by walls along its edges.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use rustrace::{
+///     displace::{DisplacedQuad, Displacement, Heightmap},
+///     film::FilmBuffer,
+///     hit::Hittable,
+///     material::Lambertian,
+///     quad::Quad,
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let half = Heightmap::new(FilmBuffer::from_rows(vec![vec![vec3![0.5, 0.5, 0.5]]]));
+/// let floor = DisplacedQuad {
+///     quad: Quad::new(
+///         vec3![0.0, 0.0, 0.0],
+///         vec3![0.0, 0.0, 1.0],
+///         vec3![1.0, 0.0, 0.0],
+///         Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+///     ),
+///     displacement: Displacement::new(Arc::new(half), 0.2),
+/// };
+///
+/// // half of the depth carved away everywhere
+/// let down = ray!(vec3![0.5, 2.0, 0.5], vec3![0.0, -1.0, 0.0]);
+/// let rec = floor.nearest_hit_along(&down).unwrap();
+/// assert!((rec.p[1] + 0.1).abs() < 1e-6);
+/// assert!((rec.norm[1] - 1.0).abs() < 1e-6);
+///
+/// // passes over the carved-out relief, but hits the wall below it
+/// let over = ray!(vec3![5.0, -0.05, 0.5], vec3![-1.0, 0.0, 0.0]);
+/// assert!(floor.nearest_hit_along(&over).is_none());
+/// let side = ray!(vec3![5.0, -0.15, 0.5], vec3![-1.0, 0.0, 0.0]);
+/// let rec = floor.nearest_hit_along(&side).unwrap();
+/// assert!((rec.p[0] - 1.0).abs() < 1e-6);
+/// assert!((rec.norm[0] - 1.0).abs() < 1e-6);
+/// ```
+#[derive(Debug)]
+pub struct DisplacedQuad {
+    /// The undisplaced quad, whose surface is the top of the relief.
+    pub quad: Quad,
+    /// How the quad is carved.
+    pub displacement: Displacement,
+}
+
+impl DisplacedQuad {
+    /// The point on the relief at `(alpha, beta)` across the quad.
+    fn relief_point(&self, alpha: f64, beta: f64) -> Vec3 {
+        let Quad { origin, u, v, .. } = self.quad;
+        origin + u * alpha + v * beta - self.quad.normal() * self.displacement.depth_at(alpha, beta)
+    }
+
+    /// The normal of the relief at `(alpha, beta)`, from central differences
+    /// about a texel apart.
+    fn relief_normal(&self, alpha: f64, beta: f64) -> Vec3 {
+        let heightmap = &self.displacement.heightmap;
+        let du = 1.0 / (f64::from(heightmap.width()) * self.displacement.tiling[0]).max(1.0);
+        let dv = 1.0 / (f64::from(heightmap.height()) * self.displacement.tiling[1]).max(1.0);
+
+        let along_u = self.relief_point(alpha + du, beta) - self.relief_point(alpha - du, beta);
+        let along_v = self.relief_point(alpha, beta + dv) - self.relief_point(alpha, beta - dv);
+        cross(&along_u, &along_v).unit()
+    }
+}
+
+impl Hittable for DisplacedQuad {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let Quad { origin, u, v, .. } = self.quad;
+        let norm = self.quad.normal();
+        let n = cross(&u, &v);
+        let w = n / n.length_squared();
+
+        // the carved slab is where the height along the normal and the
+        // coordinates along each edge are all in range, each linear along the
+        // ray, so clip the ray to each in turn and keep the wall it enters by
+        let walls = [
+            (norm, -self.displacement.depth, 0.0),
+            (cross(&v, &w), 0.0, 1.0),
+            (cross(&w, &u), 0.0, 1.0),
+        ];
+        let (mut start, mut end) = (ray_t.min, ray_t.max);
+        let mut entry_wall = None;
+        for (gradient, low, high) in walls {
+            let at_origin = dot(&gradient, &(ray.origin - origin));
+            let rate = dot(&gradient, &ray.direction);
+            if rate.abs() < 1e-12 {
+                if at_origin < low || at_origin > high {
+                    return None;
+                }
+                continue;
+            }
+
+            let (t_low, t_high) = ((low - at_origin) / rate, (high - at_origin) / rate);
+            let (near, far, outward) = if rate > 0.0 {
+                (t_low, t_high, -gradient)
+            } else {
+                (t_high, t_low, gradient)
+            };
+            if near > start {
+                start = near;
+                entry_wall = Some(outward);
+            }
+            end = end.min(far);
+        }
+        if start >= end {
+            return None;
+        }
+
+        let below = |t: f64| {
+            let offset = ray.at(t) - origin;
+            let alpha = dot(&w, &cross(&offset, &v));
+            let beta = dot(&w, &cross(&u, &offset));
+            -dot(&norm, &offset) - self.displacement.depth_at(alpha, beta)
+        };
+
+        // a ray entering the slab through a wall below the relief hits the
+        // wall, otherwise march down to the relief
+        let (hit_t, wall) = match entry_wall {
+            Some(wall) if below(start) >= 0.0 => (start, Some(wall.unit())),
+            _ => (self.displacement.march(start, end, below)?, None),
+        };
+
+        let hit_point = ray.at(hit_t);
+        let offset = hit_point - origin;
+        let alpha = dot(&w, &cross(&offset, &v));
+        let beta = dot(&w, &cross(&u, &offset));
+        let outward_norm = wall.unwrap_or_else(|| self.relief_normal(alpha, beta));
+        let mut rec = HitRecord {
+            t: hit_t,
+            p: hit_point,
+            norm: outward_norm,
+            mat: &*self.quad.mat,
+            front_face: false,
+            u: alpha.clamp(0.0, 1.0),
+            v: beta.clamp(0.0, 1.0),
+            object: None,
+            layer: None,
+            tangent: Some(u),
+        };
+        rec.set_face_norm(ray, &outward_norm);
+        Some(rec)
+    }
+
+    /// The bounds of the quad and its back face, the bottom of the carved
+    /// slab.
+    fn bound(&self) -> Aabb {
+        let top = self.quad.bound();
+        let mut bound = top.translated(-self.quad.normal() * self.displacement.depth);
+        bound.union(&top);
+        bound
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.quad.translate(offset)
+    }
+
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.quad.set_tolerances(tolerances);
+    }
+
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        self.quad.closest_point(point)
+    }
+}
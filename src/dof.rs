@@ -0,0 +1,145 @@
+//! This module contains [`CircleOfConfusion`], an approximate depth of field
+//! applied as a post process to a pinhole render and its depth. It's much
+//! cheaper than sampling the lens (which needs many more samples per pixel to
+//! converge in the blurred areas), so suits previews, but gets occlusion at
+//! the edges of blurred objects wrong. See
+//! [`DepthOfField`](crate::camera::DepthOfField).
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{film::FilmBuffer, vec3::Vec3};
+
+/// The blur of a thin lens, in pixels, at each depth.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{dof::CircleOfConfusion, film::FilmBuffer, vec3, vec3::Vec3};
+///
+/// let coc = CircleOfConfusion {
+///     lens_radius: 0.1,
+///     focus_dist: 5.0,
+///     pixel_size: 0.01,
+/// };
+///
+/// assert_eq!(coc.radius(5.0), 0.0);
+/// assert!((coc.radius(10.0) - 5.0).abs() < 1e-9);
+/// assert!((coc.radius(f64::INFINITY) - 10.0).abs() < 1e-9);
+///
+/// // a single bright pixel at 10 units spreads into a disc of radius 5
+/// let mut film = FilmBuffer::new(21, 21);
+/// film.set(10, 10, vec3![1.0, 1.0, 1.0]);
+/// let mut depth = FilmBuffer::new(21, 21);
+/// depth.pixels_mut().fill(vec3![10.0, 10.0, 10.0]);
+///
+/// let blurred = coc.apply(&film, &depth);
+/// assert!(blurred.get(10, 10)[0] < 0.1);
+/// assert!(blurred.get(13, 10)[0] > 0.0);
+/// assert_eq!(blurred.get(17, 10)[0], 0.0);
+///
+/// // and the light is kept
+/// let total: f64 = blurred.pixels().iter().map(|p| p[0]).sum();
+/// assert!((total - 1.0).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircleOfConfusion {
+    /// The radius of the lens aperture.
+    pub lens_radius: f64,
+    /// The distance in focus, along the view direction.
+    pub focus_dist: f64,
+    /// The world-space width of a pixel at the focus distance.
+    pub pixel_size: f64,
+}
+
+impl CircleOfConfusion {
+    /// The largest blur radius in pixels, to bound the cost of
+    /// [`CircleOfConfusion::apply()`].
+    pub const MAX_RADIUS: f64 = 32.0;
+
+    /// The radius in pixels of the blur of a point at `depth` along the view
+    /// direction, up to [`CircleOfConfusion::MAX_RADIUS`].
+    #[must_use]
+    pub fn radius(&self, depth: f64) -> f64 {
+        // the spread over the lens projected onto the focus plane, where the
+        // pixels are
+        let spread = if depth.is_finite() {
+            self.lens_radius * (depth - self.focus_dist).abs() / depth
+        } else {
+            self.lens_radius
+        };
+        (spread / self.pixel_size).min(Self::MAX_RADIUS)
+    }
+
+    /// Blur `film` by the depths in `depth` (of the same size, in the first
+    /// channel, infinite where nothing was hit).
+    ///
+    /// Each pixel is spread evenly over its circle of confusion, gathered from
+    /// the pixels around each output pixel and normalised by the total weight
+    /// gathered, so light is kept where the blur is even. Pixels behind the
+    /// one being gathered for are limited to its own blur, so out of focus
+    /// backgrounds don't bleed over sharp foregrounds while blurred
+    /// foregrounds still spill over what's behind them.
+    ///
+    /// # Panics
+    ///
+    /// If `film` and `depth` aren't the same size.
+    #[must_use]
+    pub fn apply(&self, film: &FilmBuffer, depth: &FilmBuffer) -> FilmBuffer {
+        assert!(
+            film.width() == depth.width() && film.height() == depth.height(),
+            "Film and depth must be the same size"
+        );
+
+        let (width, height) = (film.width(), film.height());
+        let radii: Vec<f64> = depth.pixels().iter().map(|d| self.radius(d[0])).collect();
+        let max_radius = radii.iter().copied().fold(0.0, f64::max);
+        #[expect(clippy::cast_possible_truncation)]
+        #[expect(clippy::cast_sign_loss)]
+        let reach = max_radius.ceil() as u32;
+
+        let index = |x: u32, y: u32| y as usize * width as usize + x as usize;
+        // spread over the area of the circle, and below half a pixel a point
+        // stays in its own pixel
+        let weight = |r: f64| 1.0 / r.max(0.5).powi(2);
+
+        let rows = (0..height)
+            .into_par_iter()
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        let here = index(x, y);
+                        let (depth_here, radius_here) = (depth.pixels()[here][0], radii[here]);
+
+                        let mut sum = Vec3::default();
+                        let mut total = 0.0;
+                        for sy in y.saturating_sub(reach)..=(y + reach).min(height - 1) {
+                            for sx in x.saturating_sub(reach)..=(x + reach).min(width - 1) {
+                                let there = index(sx, sy);
+                                let mut radius = radii[there];
+                                if depth.pixels()[there][0] > depth_here {
+                                    radius = radius.min(radius_here);
+                                }
+
+                                let dx = f64::from(sx) - f64::from(x);
+                                let dy = f64::from(sy) - f64::from(y);
+                                if dx * dx + dy * dy <= radius.max(0.5).powi(2) {
+                                    let w = weight(radius);
+                                    sum += film.pixels()[there] * w;
+                                    total += w;
+                                }
+                            }
+                        }
+
+                        if total > 0.0 {
+                            sum * (1.0 / total)
+                        } else {
+                            Vec3::default()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        FilmBuffer::from_rows(rows)
+    }
+}
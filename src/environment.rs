@@ -0,0 +1,127 @@
+//! This module contains [`EnvironmentMap`], an equirectangular (latitude and
+//! longitude) image surrounding the scene at infinity. [Ray](crate::ray::Ray)s
+//! that miss everything look up their radiance from it, so an HDR photograph
+//! of a real location lights the scene and shows in reflections.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{
+    film::{ColourSpace, FilmBuffer},
+    utils::{deg_to_rad, direction_to_equirect},
+    vec3,
+    vec3::Vec3,
+};
+
+/// An equirectangular environment image, laid out as by
+/// [`equirect_to_direction()`](crate::utils::equirect_to_direction) with `+Y`
+/// up and the centre of the image towards `-Z`. Set it on a camera with
+/// [`CameraBuilder::set_environment()`](crate::camera::CameraBuilder::set_environment()).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{environment::EnvironmentMap, film::FilmBuffer, vec3, vec3::Vec3};
+///
+/// // a bright sky over dark ground
+/// let env = EnvironmentMap::new(FilmBuffer::from_rows(vec![
+///     vec![vec3![4.0, 4.0, 4.0]; 4],
+///     vec![vec3![0.1, 0.1, 0.1]; 4],
+/// ]));
+///
+/// assert_eq!(env.radiance(vec3![0.0, 1.0, 0.0]), vec3![4.0, 4.0, 4.0]);
+/// assert_eq!(env.radiance(vec3![0.3, -1.0, 0.2]), vec3![0.1, 0.1, 0.1]);
+///
+/// let dimmed = env.with_intensity(0.5);
+/// assert_eq!(dimmed.radiance(vec3![0.0, 1.0, 0.0]), vec3![2.0, 2.0, 2.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    film: FilmBuffer,
+    rotation: f64,
+    intensity: f64,
+}
+
+impl EnvironmentMap {
+    /// Create an [`EnvironmentMap`] from a linear equirectangular `film`,
+    /// ideally twice as wide as it is tall.
+    ///
+    /// # Panics
+    ///
+    /// If `film` is empty.
+    #[must_use]
+    pub fn new(film: FilmBuffer) -> Self {
+        assert!(
+            film.width() > 0 && film.height() > 0,
+            "Environment map can't be empty"
+        );
+        Self {
+            film,
+            rotation: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    /// Load an [`EnvironmentMap`] from an image file. `.hdr` and `.exr` files
+    /// are read as linear radiance, and 8 or 16-bit images are decoded from
+    /// sRGB.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or decoded.
+    ///
+    /// # Panics
+    ///
+    /// If the image is empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(FilmBuffer::load(path, ColourSpace::Srgb)?))
+    }
+
+    /// Turn the environment by `degrees` about `+Y`, anticlockwise seen from
+    /// above, to line the sun or a feature up with the scene.
+    #[must_use]
+    pub fn with_rotation(self, degrees: f64) -> Self {
+        Self {
+            rotation: deg_to_rad(degrees),
+            ..self
+        }
+    }
+
+    /// Scale the radiance of the environment by `intensity`.
+    #[must_use]
+    pub fn with_intensity(self, intensity: f64) -> Self {
+        Self { intensity, ..self }
+    }
+
+    /// The radiance arriving from direction `dir`, bilinearly interpolated
+    /// between texel centres.
+    #[must_use]
+    pub fn radiance(&self, dir: Vec3) -> Vec3 {
+        // turn the direction back rather than the map forwards
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let dir = vec3![
+            dir[0] * cos + dir[2] * sin,
+            dir[1],
+            -dir[0] * sin + dir[2] * cos
+        ];
+        let (u, v) = direction_to_equirect(dir);
+
+        let (width, height) = (self.film.width(), self.film.height());
+        let x = u * f64::from(width) - 0.5;
+        let y = v * f64::from(height) - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        // wrapping around horizontally, but not over the poles
+        #[expect(clippy::cast_possible_truncation)]
+        let column = |i: f64| (i as i64).rem_euclid(i64::from(width)) as u32;
+        #[expect(clippy::cast_possible_truncation)]
+        #[expect(clippy::cast_sign_loss)]
+        let row = |i: f64| i.clamp(0.0, f64::from(height - 1)) as u32;
+        let texel = |dx: f64, dy: f64| self.film.get(column(x.floor() + dx), row(y.floor() + dy));
+
+        let top = texel(0.0, 0.0) * (1.0 - fx) + texel(1.0, 0.0) * fx;
+        let bottom = texel(0.0, 1.0) * (1.0 - fx) + texel(1.0, 1.0) * fx;
+        (top * (1.0 - fy) + bottom * fy) * self.intensity
+    }
+}
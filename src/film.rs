@@ -0,0 +1,1192 @@
+//! This module contains the [`FilmBuffer`] struct, a linear HDR image that
+//! renders are accumulated into before being saved, and that images such as
+//! textures are decoded into when loaded, along with the per-pixel sample
+//! statistics of a [`VarianceFilm`] and luminance [`Histogram`]s.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, Rgb, Rgb32FImage, RgbImage};
+use serde::Deserialize;
+
+use crate::{
+    interval,
+    interval::Interval,
+    lut::Lut,
+    noise::Noise,
+    vec3,
+    vec3::{linear_to_gamma, Vec3},
+};
+
+/// How the values in an image file are encoded. Colour maps such as albedo
+/// are usually stored [`Srgb`](ColourSpace::Srgb) and have to be decoded to
+/// linear before shading with them, while data maps such as roughness,
+/// height or normal maps are stored [`Linear`](ColourSpace::Linear) and must
+/// be read as-is. Getting this wrong makes colours look washed out, or data
+/// maps too dark.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum ColourSpace {
+    /// Colour data with the sRGB transfer curve applied.
+    #[default]
+    Srgb,
+    /// Linear values, such as non-colour data or scene-linear colour.
+    Linear,
+}
+
+impl ColourSpace {
+    /// Decode one channel stored in this colour space to linear.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::film::ColourSpace;
+    ///
+    /// assert_eq!(ColourSpace::Linear.decode(0.5), 0.5);
+    /// assert!((ColourSpace::Srgb.decode(0.5) - 0.214).abs() < 1e-3);
+    /// assert_eq!(ColourSpace::Srgb.decode(1.0), 1.0);
+    /// ```
+    #[must_use]
+    pub fn decode(self, value: f64) -> f64 {
+        match self {
+            ColourSpace::Linear => value,
+            ColourSpace::Srgb if value <= 0.040_45 => value / 12.92,
+            ColourSpace::Srgb => ((value + 0.055) / 1.055).powf(2.4),
+        }
+    }
+}
+
+/// A linear, high dynamic range image made of [`Vec3`] pixels stored row by row.
+#[derive(Debug, Clone)]
+pub struct FilmBuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Vec3>,
+    /// Whether 8-bit display images are dithered, see
+    /// [`FilmBuffer::with_dither()`].
+    dither: bool,
+}
+
+impl FilmBuffer {
+    /// Create a new black [`FilmBuffer`] of the given size.
+    #[must_use]
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Vec3::default(); width as usize * height as usize],
+            dither: false,
+        }
+    }
+
+    /// Create a [`FilmBuffer`] from rows of pixels, each of which must be the
+    /// same length.
+    ///
+    /// # Panics
+    ///
+    /// If the rows aren't all the same length, or the size doesn't fit in a
+    /// [`u32`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let film = FilmBuffer::from_rows(vec![
+    ///     vec![vec3![1.0, 0.0, 0.0], vec3![0.0, 1.0, 0.0]],
+    ///     vec![vec3![0.0, 0.0, 1.0], vec3![1.0, 1.0, 1.0]],
+    /// ]);
+    ///
+    /// assert_eq!(film.width(), 2);
+    /// assert_eq!(film.height(), 2);
+    /// assert_eq!(film.get(0, 1), vec3![0.0, 0.0, 1.0]);
+    /// ```
+    #[must_use]
+    pub fn from_rows(rows: Vec<Vec<Vec3>>) -> Self {
+        let height = u32::try_from(rows.len()).expect("couldn't convert usize to u32");
+        let width = rows.first().map_or(0, Vec::len);
+        assert!(
+            rows.iter().all(|row| row.len() == width),
+            "All rows must be the same length"
+        );
+
+        Self {
+            width: u32::try_from(width).expect("couldn't convert usize to u32"),
+            height,
+            pixels: rows.into_iter().flatten().collect(),
+            dither: false,
+        }
+    }
+
+    /// Load an image file into a linear [`FilmBuffer`], decoding 8 and 16-bit
+    /// images from `colour_space`. Floating point formats such as `.hdr` and
+    /// `.exr` always store linear values, so are read as-is. Alpha is dropped.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or decoded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image::{Rgb, RgbImage};
+    /// use rustrace::film::{ColourSpace, FilmBuffer};
+    ///
+    /// let path = std::env::temp_dir().join("grey_128.png");
+    /// RgbImage::from_pixel(2, 2, Rgb([128, 128, 128])).save(&path).unwrap();
+    ///
+    /// // an albedo map is decoded to linear, a roughness map is left alone
+    /// let albedo = FilmBuffer::load(&path, ColourSpace::Srgb).unwrap();
+    /// let roughness = FilmBuffer::load(&path, ColourSpace::Linear).unwrap();
+    ///
+    /// assert!((albedo.get(0, 0)[0] - 0.216).abs() < 1e-3);
+    /// assert!((roughness.get(0, 0)[0] - 0.502).abs() < 1e-3);
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P, colour_space: ColourSpace) -> Result<Self> {
+        let path = path.as_ref();
+        let image =
+            image::open(path).with_context(|| format!("Couldn't load image {}", path.display()))?;
+        Ok(Self::from_image(image, colour_space))
+    }
+
+    /// Decode an image file already read into `bytes`, such as one embedded
+    /// in another file, like [`FilmBuffer::load()`].
+    ///
+    /// # Errors
+    ///
+    /// If the format isn't recognised or the image can't be decoded.
+    pub fn load_from_memory(bytes: &[u8], colour_space: ColourSpace) -> Result<Self> {
+        let image = image::load_from_memory(bytes).context("Couldn't decode image")?;
+        Ok(Self::from_image(image, colour_space))
+    }
+
+    /// Convert `image` to linear, decoding it from `colour_space` unless it's
+    /// already floating point.
+    fn from_image(image: DynamicImage, colour_space: ColourSpace) -> Self {
+        let colour_space = match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => ColourSpace::Linear,
+            _ => colour_space,
+        };
+
+        let image = image.into_rgb32f();
+        let mut film = Self::new(image.width(), image.height());
+        for (x, y, &Rgb([r, g, b])) in image.enumerate_pixels() {
+            let decode = |c: f32| colour_space.decode(f64::from(c));
+            film.set(x, y, vec3![decode(r), decode(g), decode(b)]);
+        }
+        film
+    }
+
+    /// The width of the [`FilmBuffer`] in pixels.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the [`FilmBuffer`] in pixels.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// All of the pixels, row by row.
+    #[must_use]
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    /// All of the pixels, row by row, for editing in place.
+    #[must_use]
+    pub fn pixels_mut(&mut self) -> &mut [Vec3] {
+        &mut self.pixels
+    }
+
+    /// Set whether 8-bit display images made from the [`FilmBuffer`], by
+    /// [`FilmBuffer::to_rgb_image()`] and the saves using it, are dithered:
+    /// each channel is offset by triangular noise of up to a level either
+    /// way before quantising, trading the bands of smooth gradients such as
+    /// skies for fine grain. Black and white stay exact, and the noise is
+    /// fixed per pixel so the same film always gives the same image. Linear
+    /// data images are never dithered. Off for a new [`FilmBuffer`], and on
+    /// for renders unless the [`Camera`](crate::camera::Camera) turns it off
+    /// for exact comparisons.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// // a gentle gradient, spanning less than two 8-bit levels
+    /// let mut film = FilmBuffer::new(256, 64);
+    /// for x in 0..256 {
+    ///     for y in 0..64 {
+    ///         let level = 0.25 + f64::from(x) / 256.0 * 0.003;
+    ///         film.set(x, y, vec3![level, level, level]);
+    ///     }
+    /// }
+    ///
+    /// // the mean of each column, in levels, and its error from the truth
+    /// let error = |film: &FilmBuffer| {
+    ///     let img = film.to_rgb_image();
+    ///     (0..256)
+    ///         .map(|x| {
+    ///             let mean = (0..64).map(|y| f64::from(img.get_pixel(x, y).0[0])).sum::<f64>() / 64.0;
+    ///             let truth = (0.25 + f64::from(x) / 256.0 * 0.003).sqrt() * 256.0 - 0.5;
+    ///             (mean - truth).abs()
+    ///         })
+    ///         .sum::<f64>()
+    ///         / 256.0
+    /// };
+    ///
+    /// // banded, columns are off by a quarter of a level on average, but
+    /// // dithered they average out to the gradient
+    /// let dithered = film.clone().with_dither(true);
+    /// assert!(error(&film) > 0.2);
+    /// assert!(error(&dithered) < 0.1);
+    ///
+    /// // and black and white aren't speckled
+    /// let mut film = FilmBuffer::new(2, 1).with_dither(true);
+    /// film.set(1, 0, vec3![1.0, 1.0, 1.0]);
+    /// assert_eq!(film.to_rgb_image().get_pixel(0, 0).0, [0, 0, 0]);
+    /// assert_eq!(film.to_rgb_image().get_pixel(1, 0).0, [255, 255, 255]);
+    /// ```
+    #[must_use]
+    pub fn with_dither(self, dither: bool) -> Self {
+        Self { dither, ..self }
+    }
+
+    /// Whether 8-bit display images are dithered, see
+    /// [`FilmBuffer::with_dither()`].
+    #[must_use]
+    pub fn is_dithered(&self) -> bool {
+        self.dither
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height, "Pixel out of bounds");
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Get the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    #[must_use]
+    pub fn get(&self, x: u32, y: u32) -> Vec3 {
+        self.pixels[self.index(x, y)]
+    }
+
+    /// Set the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    pub fn set(&mut self, x: u32, y: u32, colour: Vec3) {
+        let idx = self.index(x, y);
+        self.pixels[idx] = colour;
+    }
+
+    /// Set a whole row of pixels starting at `(0, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `row` doesn't match the width or `y` is out of bounds.
+    pub fn set_row(&mut self, y: u32, row: &[Vec3]) {
+        assert_eq!(row.len(), self.width as usize, "Row is the wrong length");
+        let start = self.index(0, y);
+        self.pixels[start..start + row.len()].copy_from_slice(row);
+    }
+
+    /// The pixel containing texture coordinates `(u, v)`, wrapping at the
+    /// edges so the image tiles. `v = 0.0` is the bottom of the image.
+    ///
+    /// # Panics
+    ///
+    /// If the [`FilmBuffer`] is empty.
+    #[must_use]
+    pub fn texel_uv(&self, u: f64, v: f64) -> Vec3 {
+        let x = u * f64::from(self.width);
+        let y = (1.0 - v) * f64::from(self.height);
+        self.get(wrap(x, self.width), wrap(y, self.height))
+    }
+
+    /// The colour at texture coordinates `(u, v)`, bilinearly interpolated
+    /// between pixel centres and wrapping at the edges so the image tiles.
+    /// `v = 0.0` is the bottom of the image.
+    ///
+    /// # Panics
+    ///
+    /// If the [`FilmBuffer`] is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let film = FilmBuffer::from_rows(vec![vec![vec3![0.0, 0.0, 0.0], vec3![1.0, 1.0, 1.0]]]);
+    ///
+    /// assert_eq!(film.sample_uv(0.25, 0.5), vec3![0.0, 0.0, 0.0]);
+    /// assert_eq!(film.sample_uv(0.5, 0.5), vec3![0.5, 0.5, 0.5]);
+    /// // wrapping around from the right edge back to the left
+    /// assert_eq!(film.sample_uv(1.0, 0.5), vec3![0.5, 0.5, 0.5]);
+    /// assert_eq!(film.texel_uv(1.0, 0.5), vec3![0.0, 0.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn sample_uv(&self, u: f64, v: f64) -> Vec3 {
+        let x = u * f64::from(self.width) - 0.5;
+        let y = (1.0 - v) * f64::from(self.height) - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        let texel = |dx: f64, dy: f64| {
+            self.get(
+                wrap(x.floor() + dx, self.width),
+                wrap(y.floor() + dy, self.height),
+            )
+        };
+
+        let top = texel(0.0, 0.0) * (1.0 - fx) + texel(1.0, 0.0) * fx;
+        let bottom = texel(0.0, 1.0) * (1.0 - fx) + texel(1.0, 1.0) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Convert to an 8-bit [`RgbImage`] with linear-to-gamma conversion (see
+    /// [`Vec3::to_rgb()`]), [dithered](FilmBuffer::with_dither()) if set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let mut film = FilmBuffer::new(2, 1);
+    /// film.set(1, 0, vec3![1.0, 0.25, 0.0]);
+    ///
+    /// let img = film.to_rgb_image();
+    ///
+    /// assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0]);
+    /// assert_eq!(img.get_pixel(1, 0).0, [255, 128, 0]);
+    /// ```
+    #[must_use]
+    pub fn to_rgb_image(&self) -> RgbImage {
+        self.to_rgb_image_by(self.dither, |c| {
+            vec3![
+                linear_to_gamma(c[0]),
+                linear_to_gamma(c[1]),
+                linear_to_gamma(c[2])
+            ]
+        })
+    }
+
+    /// Convert to an 8-bit [`RgbImage`] without linear-to-gamma conversion, for
+    /// data such as normal or ambient occlusion maps.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let mut film = FilmBuffer::new(1, 1);
+    /// film.set(0, 0, vec3![0.5, 0.5, 1.0]);
+    ///
+    /// assert_eq!(film.to_rgb_image_linear().get_pixel(0, 0).0, [128, 128, 255]);
+    /// ```
+    #[must_use]
+    pub fn to_rgb_image_linear(&self) -> RgbImage {
+        self.to_rgb_image_by(false, |c| c)
+    }
+
+    /// Convert to an 8-bit [`RgbImage`], passing the gamma-corrected colours
+    /// through `lut` before quantising, like a show LUT or film emulation
+    /// curve applied to a display image. [Dithered](FilmBuffer::with_dither())
+    /// if set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, lut::Lut, vec3, vec3::Vec3};
+    ///
+    /// let mut film = FilmBuffer::new(1, 1);
+    /// film.set(0, 0, vec3![0.25, 0.25, 0.25]);
+    ///
+    /// let invert = Lut::from_curve(2, |x| 1.0 - x);
+    ///
+    /// // 0.25 is 0.5 after gamma correction
+    /// assert_eq!(film.to_rgb_image_with_lut(&invert).get_pixel(0, 0).0, [128, 128, 128]);
+    /// ```
+    #[must_use]
+    pub fn to_rgb_image_with_lut(&self, lut: &Lut) -> RgbImage {
+        self.to_rgb_image_by(self.dither, |c| {
+            lut.apply(vec3![
+                linear_to_gamma(c[0]),
+                linear_to_gamma(c[1]),
+                linear_to_gamma(c[2])
+            ])
+        })
+    }
+
+    /// Quantise to an 8-bit [`RgbImage`] after mapping each pixel with `f`,
+    /// with triangular dithering if `dither` is set.
+    fn to_rgb_image_by<F: Fn(Vec3) -> Vec3>(&self, dither: bool, f: F) -> RgbImage {
+        let intensity = interval![0.000, 0.999];
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let c = f(self.get(x, y));
+            if dither {
+                let mut rng = Noise::default().rng([i64::from(x), i64::from(y), 0]);
+                // the sum of two uniforms, in -1.0..1.0 levels; out of range
+                // values are clamped after, so black and white can't be
+                // pushed off
+                let mut level = |c: f64| {
+                    let offset = rng.next_unit() + rng.next_unit() - 1.0;
+                    #[expect(clippy::cast_possible_truncation)]
+                    #[expect(clippy::cast_sign_loss)]
+                    let level = (c * 256.0 + offset).floor().clamp(0.0, 255.0) as u8;
+                    level
+                };
+                return Rgb([level(c[0]), level(c[1]), level(c[2])]);
+            }
+            #[expect(clippy::cast_possible_truncation)]
+            #[expect(clippy::cast_sign_loss)]
+            Rgb([
+                (intensity.clamp(c[0]) * 256.0) as u8,
+                (intensity.clamp(c[1]) * 256.0) as u8,
+                (intensity.clamp(c[2]) * 256.0) as u8,
+            ])
+        })
+    }
+
+    /// Convert to a linear 32-bit float [`Rgb32FImage`], suitable for saving as
+    /// HDR or EXR.
+    #[must_use]
+    pub fn to_hdr_image(&self) -> Rgb32FImage {
+        #[expect(clippy::cast_possible_truncation)]
+        Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            let c = self.get(x, y);
+            Rgb([c[0] as f32, c[1] as f32, c[2] as f32])
+        })
+    }
+
+    /// Save to the file at `path`. Paths ending in `.hdr` or `.exr` are saved
+    /// as linear HDR images, anything else as 8-bit gamma-corrected images.
+    ///
+    /// # Errors
+    ///
+    /// If saving the image fails.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if is_hdr_path(path.as_ref()) {
+            self.to_hdr_image().save(path)?;
+        } else {
+            self.to_rgb_image().save(path)?;
+        }
+        Ok(())
+    }
+
+    /// Save to the file at `path` like [`FilmBuffer::save()`], but without
+    /// linear-to-gamma conversion for 8-bit images (see
+    /// [`FilmBuffer::to_rgb_image_linear()`]).
+    ///
+    /// # Errors
+    ///
+    /// If saving the image fails.
+    pub fn save_linear<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if is_hdr_path(path.as_ref()) {
+            self.to_hdr_image().save(path)?;
+        } else {
+            self.to_rgb_image_linear().save(path)?;
+        }
+        Ok(())
+    }
+
+    /// A copy of the [`FilmBuffer`] with its exposure changed by `stops`, so
+    /// each stop doubles (or halves, if negative) the brightness.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let mut film = FilmBuffer::new(1, 1);
+    /// film.set(0, 0, vec3![0.25, 0.5, 1.0]);
+    ///
+    /// assert_eq!(film.exposed(1.0).get(0, 0), vec3![0.5, 1.0, 2.0]);
+    /// assert_eq!(film.exposed(-2.0).get(0, 0), vec3![0.0625, 0.125, 0.25]);
+    /// ```
+    #[must_use]
+    pub fn exposed(&self, stops: f64) -> Self {
+        let scale = stops.exp2();
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|&p| p * scale).collect(),
+            dither: self.dither,
+        }
+    }
+
+    /// Save an exposure bracket from one render: the raw linear image as
+    /// `.exr` next to `path`, and an 8-bit image for each exposure in `stops`
+    /// (see [`FilmBuffer::exposed()`]) named like `render_ev+1.png`. The
+    /// brackets use the extension of `path`, or `.png` if it's an HDR path.
+    /// Returns the paths written, raw image first.
+    ///
+    /// # Errors
+    ///
+    /// If saving any of the images fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::film::FilmBuffer;
+    ///
+    /// let film = FilmBuffer::new(4, 4);
+    /// let path = std::env::temp_dir().join("bracket.png");
+    ///
+    /// let written = film.save_bracketed(&path, &[-1.0, 0.0, 1.5]).unwrap();
+    /// let names: Vec<_> = written.iter().map(|p| p.file_name().unwrap()).collect();
+    ///
+    /// assert_eq!(
+    ///     names,
+    ///     ["bracket.exr", "bracket_ev-1.png", "bracket_ev+0.png", "bracket_ev+1.5.png"]
+    /// );
+    /// assert!(written.iter().all(|p| p.exists()));
+    /// ```
+    pub fn save_bracketed<P: AsRef<Path>>(&self, path: P, stops: &[f64]) -> Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = match path.extension() {
+            Some(ext) if !is_hdr_path(path) => ext.to_string_lossy(),
+            _ => "png".into(),
+        };
+
+        let raw = path.with_extension("exr");
+        self.save(&raw)?;
+        let mut written = vec![raw];
+
+        for &ev in stops {
+            let bracket = path.with_file_name(format!("{stem}_ev{ev:+}.{ext}"));
+            self.exposed(ev).save(&bracket)?;
+            written.push(bracket);
+        }
+
+        Ok(written)
+    }
+
+    /// Save to the file at `path` like [`FilmBuffer::save()`], applying `lut`
+    /// to 8-bit images (see [`FilmBuffer::to_rgb_image_with_lut()`]). HDR
+    /// images are kept scene-linear, so are saved without it.
+    ///
+    /// # Errors
+    ///
+    /// If saving the image fails.
+    pub fn save_with_lut<P: AsRef<Path>>(&self, path: P, lut: &Lut) -> Result<()> {
+        if is_hdr_path(path.as_ref()) {
+            self.to_hdr_image().save(path)?;
+        } else {
+            self.to_rgb_image_with_lut(lut).save(path)?;
+        }
+        Ok(())
+    }
+
+    /// A copy of the [`FilmBuffer`] scaled down to `width` pixels wide,
+    /// keeping the aspect ratio, by averaging the linear pixels each new
+    /// pixel covers, parts of pixels included. Films no wider than `width`
+    /// are copied as they are.
+    ///
+    /// # Panics
+    ///
+    /// If `width` is `0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let mut film = FilmBuffer::new(4, 2);
+    /// film.set(0, 0, vec3![1.0, 1.0, 1.0]);
+    /// film.set(3, 1, vec3![0.0, 0.0, 4.0]);
+    ///
+    /// let half = film.resized(2);
+    /// assert_eq!((half.width(), half.height()), (2, 1));
+    /// assert_eq!(half.get(0, 0), vec3![0.25, 0.25, 0.25]);
+    /// assert_eq!(half.get(1, 0), vec3![0.0, 0.0, 1.0]);
+    ///
+    /// // by a third, so each new pixel covers a pixel and a half
+    /// let third = FilmBuffer::from_rows(vec![vec![vec3![3.0, 0.0, 0.0]; 3], vec![vec3![0.0, 0.0, 0.0]; 3]])
+    ///     .resized(2);
+    /// assert_eq!((third.width(), third.height()), (2, 1));
+    /// assert!((third.get(0, 0)[0] - 1.5).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn resized(&self, width: u32) -> Self {
+        assert!(width > 0, "Can't resize to nothing");
+        if width >= self.width {
+            return self.clone();
+        }
+        let height = ((u64::from(self.height) * u64::from(width) + u64::from(self.width) / 2)
+            / u64::from(self.width))
+        .max(1);
+        let height = u32::try_from(height).expect("couldn't convert u64 to u32");
+
+        let columns = box_weights(self.width, width);
+        let rows = box_weights(self.height, height);
+        let mut out = Self::new(width, height).with_dither(self.dither);
+        for (y, row_weights) in rows.iter().enumerate() {
+            for (x, column_weights) in columns.iter().enumerate() {
+                let mut total = Vec3::default();
+                for &(sy, wy) in row_weights {
+                    for &(sx, wx) in column_weights {
+                        total += self.get(sx, sy) * (wx * wy);
+                    }
+                }
+                #[expect(clippy::cast_possible_truncation)]
+                out.set(x as u32, y as u32, total);
+            }
+        }
+        out
+    }
+
+    /// The `width` by `height` pixels with their top left corner at
+    /// `(x, y)`, such as the frame within a render with
+    /// [overscan](crate::camera::CameraBuilder::set_overscan()).
+    ///
+    /// # Panics
+    ///
+    /// If the region isn't within the [`FilmBuffer`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let mut film = FilmBuffer::new(4, 3);
+    /// film.set(2, 1, vec3![1.0, 0.0, 0.0]);
+    ///
+    /// let middle = film.cropped(1, 1, 2, 1);
+    /// assert_eq!((middle.width(), middle.height()), (2, 1));
+    /// assert_eq!(middle.get(1, 0), vec3![1.0, 0.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn cropped(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        assert!(
+            x.saturating_add(width) <= self.width && y.saturating_add(height) <= self.height,
+            "Crop must be within the film"
+        );
+        let rows = (y..y + height)
+            .map(|row| {
+                let start = self.index(x, row);
+                self.pixels[start..start + width as usize].to_vec()
+            })
+            .collect();
+        let mut out = Self::from_rows(rows).with_dither(self.dither);
+        // from_rows can't tell the width of no rows
+        out.width = width;
+        out
+    }
+
+    /// Save to every one of `outputs`, scaled down to any width it has (see
+    /// [`FilmBuffer::resized()`]), each in the format of its extension as
+    /// with [`FilmBuffer::save()`]. A render can so be written as a full size
+    /// `.exr` for compositing and a small `.webp` or `.jpg` preview for a
+    /// dashboard at once.
+    ///
+    /// # Errors
+    ///
+    /// If saving any of the images fails, after saving those before it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::film::{FilmBuffer, Output};
+    ///
+    /// let film = FilmBuffer::new(64, 32);
+    /// let dir = std::env::temp_dir();
+    /// let outputs = [
+    ///     Output::new(dir.join("outputs_final.exr")),
+    ///     Output::new(dir.join("outputs_preview.webp")).with_width(16),
+    /// ];
+    /// film.save_outputs(&outputs).unwrap();
+    ///
+    /// let preview = FilmBuffer::load(dir.join("outputs_preview.webp"), Default::default()).unwrap();
+    /// assert_eq!((preview.width(), preview.height()), (16, 8));
+    /// let full = FilmBuffer::load(dir.join("outputs_final.exr"), Default::default()).unwrap();
+    /// assert_eq!((full.width(), full.height()), (64, 32));
+    /// ```
+    pub fn save_outputs(&self, outputs: &[Output]) -> Result<()> {
+        for output in outputs {
+            match output.width {
+                Some(width) => self.resized(width).save(&output.path),
+                None => self.save(&output.path),
+            }
+            .with_context(|| format!("Couldn't save {}", output.path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// A [`Histogram`] of the luminance of the pixels as they'd be displayed,
+    /// in `bins` even bins from black to white.
+    ///
+    /// # Panics
+    ///
+    /// If `bins` is `0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{film::FilmBuffer, vec3, vec3::Vec3};
+    ///
+    /// let mut film = FilmBuffer::new(4, 1);
+    /// film.set(1, 0, vec3![0.5, 0.5, 0.5]);
+    /// film.set(2, 0, vec3![1.0, 1.0, 1.0]);
+    /// film.set(3, 0, vec3![4.0, 0.0, 0.0]);
+    ///
+    /// let histogram = film.histogram(4);
+    /// // a black, a grey made lighter by gamma, and a white and bright red
+    /// assert_eq!(histogram.bins(), [1, 0, 1, 2]);
+    /// // the white and the red are past what an 8-bit image can show
+    /// assert_eq!(histogram.clipped(), 0.5);
+    /// println!("{histogram}");
+    /// ```
+    #[must_use]
+    pub fn histogram(&self, bins: usize) -> Histogram {
+        assert!(bins > 0, "Need at least one bin");
+        let mut counts = vec![0; bins];
+        let mut clipped = 0;
+        for pixel in &self.pixels {
+            let value = linear_to_gamma(pixel.luminance()).clamp(0.0, 1.0);
+            #[expect(clippy::cast_possible_truncation)]
+            #[expect(clippy::cast_sign_loss)]
+            #[expect(clippy::cast_precision_loss)]
+            let bin = ((value * bins as f64) as usize).min(bins - 1);
+            counts[bin] += 1;
+            if (0..3).any(|c| pixel[c] >= 1.0) {
+                clipped += 1;
+            }
+        }
+        Histogram {
+            bins: counts,
+            clipped,
+        }
+    }
+}
+
+/// A histogram of the luminance of a [`FilmBuffer`], see
+/// [`FilmBuffer::histogram()`], for checking the exposure of a render. Shown
+/// with [`Display`](fmt::Display) as a bar chart for the terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    bins: Vec<usize>,
+    clipped: usize,
+}
+
+impl Histogram {
+    /// How wide the bar of the fullest bin is when displayed.
+    const BAR_WIDTH: usize = 40;
+
+    /// The number of pixels in each bin, from black to white.
+    #[must_use]
+    pub fn bins(&self) -> &[usize] {
+        &self.bins
+    }
+
+    /// The fraction of pixels with any channel at or over `1.0`, which clip
+    /// to full brightness in 8-bit images.
+    #[must_use]
+    pub fn clipped(&self) -> f64 {
+        let total: usize = self.bins.iter().sum();
+        #[expect(clippy::cast_precision_loss)]
+        let fraction = self.clipped as f64 / total.max(1) as f64;
+        fraction
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total: usize = self.bins.iter().sum();
+        let fullest = self.bins.iter().copied().max().unwrap_or(0).max(1);
+        #[expect(clippy::cast_precision_loss)]
+        for (i, &count) in self.bins.iter().enumerate() {
+            let start = i as f64 / self.bins.len() as f64;
+            let bar = "#".repeat(count * Self::BAR_WIDTH / fullest);
+            let share = 100.0 * count as f64 / total.max(1) as f64;
+            writeln!(
+                f,
+                "{start:>5.3} |{bar:<width$}| {share:>5.1}%",
+                width = Self::BAR_WIDTH
+            )?;
+        }
+        write!(f, "Clipped: {:.1}%", 100.0 * self.clipped())
+    }
+}
+
+/// For each of `dst` pixels spanning the same length as `src` pixels, the
+/// source pixels it covers and the share of it each covers.
+fn box_weights(src: u32, dst: u32) -> Vec<Vec<(u32, f64)>> {
+    let scale = f64::from(src) / f64::from(dst);
+    (0..dst)
+        .map(|i| {
+            let (start, end) = (f64::from(i) * scale, f64::from(i + 1) * scale);
+            #[expect(clippy::cast_possible_truncation)]
+            #[expect(clippy::cast_sign_loss)]
+            let first = start.floor() as u32;
+            (first..src)
+                .map(|j| {
+                    let overlap = end.min(f64::from(j + 1)) - start.max(f64::from(j));
+                    (j, overlap / scale)
+                })
+                .take_while(|&(_, weight)| weight > 0.0)
+                .collect()
+        })
+        .collect()
+}
+
+/// An image file to save a render to, optionally scaled down to a width,
+/// such as a small preview next to the full size image, see
+/// [`FilmBuffer::save_outputs()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::film::Output;
+///
+/// let preview: Output = "preview.webp@320".parse().unwrap();
+/// assert_eq!(preview, Output::new("preview.webp").with_width(320));
+///
+/// let full: Output = "final.exr".parse().unwrap();
+/// assert_eq!(full.width, None);
+///
+/// assert!("preview.webp@0".parse::<Output>().is_err());
+/// // an @ not followed by a width is part of the path
+/// assert_eq!("renders@home/final.png".parse::<Output>().unwrap().width, None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// The file to write, in the format of its extension.
+    pub path: PathBuf,
+    /// The width to scale the image down to, if any.
+    pub width: Option<u32>,
+}
+
+impl Output {
+    /// Create a new full size [`Output`] to the file at `path`.
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            width: None,
+        }
+    }
+
+    /// Scale the image down to `width` pixels wide, keeping the aspect ratio.
+    ///
+    /// # Panics
+    ///
+    /// If `width` is `0`.
+    #[must_use]
+    pub fn with_width(self, width: u32) -> Self {
+        assert!(width > 0, "Output width must be positive");
+        Self {
+            width: Some(width),
+            ..self
+        }
+    }
+}
+
+impl FromStr for Output {
+    type Err = anyhow::Error;
+
+    /// Parse a path, optionally followed by `@` and the width to scale down
+    /// to, as in `preview.webp@320`. Anything but digits after the last `@`
+    /// is part of the path.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.rsplit_once('@') {
+            Some((path, width))
+                if !path.is_empty()
+                    && !width.is_empty()
+                    && width.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                let width: u32 = width
+                    .parse()
+                    .with_context(|| format!("Invalid output width in {s:?}"))?;
+                if width == 0 {
+                    bail!("Output width must be positive in {s:?}");
+                }
+                Ok(Self::new(path).with_width(width))
+            }
+            _ => Ok(Self::new(s)),
+        }
+    }
+}
+
+/// A running mean and variance of the samples of one pixel, updated one
+/// sample at a time with Welford's online algorithm, so it never has to keep
+/// the samples. Each channel is tracked on its own.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{film::Welford, vec3, vec3::Vec3};
+///
+/// let mut pixel = Welford::default();
+/// for x in [1.0, 2.0, 3.0, 4.0] {
+///     pixel.push(vec3![x, 0.0, 0.0]);
+/// }
+///
+/// assert_eq!(pixel.count(), 4);
+/// assert_eq!(pixel.mean(), vec3![2.5, 0.0, 0.0]);
+/// assert!((pixel.variance()[0] - 5.0 / 3.0).abs() < 1e-12);
+///
+/// // two halves merged give the same as all at once
+/// let (mut a, mut b) = (Welford::default(), Welford::default());
+/// a.push(vec3![1.0, 0.0, 0.0]);
+/// a.push(vec3![2.0, 0.0, 0.0]);
+/// b.push(vec3![3.0, 0.0, 0.0]);
+/// b.push(vec3![4.0, 0.0, 0.0]);
+/// a.merge(&b);
+/// assert!((a.variance()[0] - pixel.variance()[0]).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Welford {
+    count: u32,
+    mean: Vec3,
+    /// The sum of squared differences from the mean.
+    m2: Vec3,
+}
+
+impl Welford {
+    /// Add one sample.
+    pub fn push(&mut self, sample: Vec3) {
+        self.count += 1;
+        let delta = sample - self.mean;
+        self.mean += delta / f64::from(self.count);
+        self.m2 += delta * (sample - self.mean);
+    }
+
+    /// Add all the samples of `other`, as if they had been pushed one by one
+    /// (Chan et al.'s parallel combination).
+    pub fn merge(&mut self, other: &Self) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let (n_a, n_b) = (f64::from(self.count), f64::from(other.count));
+        self.mean += delta * (n_b / f64::from(count));
+        self.m2 += other.m2 + delta * delta * (n_a * n_b / f64::from(count));
+        self.count = count;
+    }
+
+    /// The number of samples.
+    #[must_use]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// The mean of the samples.
+    #[must_use]
+    pub fn mean(&self) -> Vec3 {
+        self.mean
+    }
+
+    /// The unbiased variance of the samples, or zero with fewer than two.
+    #[must_use]
+    pub fn variance(&self) -> Vec3 {
+        if self.count < 2 {
+            Vec3::default()
+        } else {
+            self.m2 / f64::from(self.count - 1)
+        }
+    }
+
+    /// The standard error of the mean, how far it's likely to be from the
+    /// converged value.
+    #[must_use]
+    pub fn standard_error(&self) -> Vec3 {
+        if self.count == 0 {
+            return Vec3::default();
+        }
+        let var = self.variance() / f64::from(self.count);
+        vec3![var[0].sqrt(), var[1].sqrt(), var[2].sqrt()]
+    }
+
+    /// The half width of the confidence interval around the mean, `z`
+    /// standard errors, such as `1.96` for 95% confidence.
+    #[must_use]
+    pub fn confidence_interval(&self, z: f64) -> Vec3 {
+        self.standard_error() * z
+    }
+
+    /// The [confidence interval](Welford::confidence_interval()) relative to
+    /// the mean, by luminance, so `0.01` means the pixel is likely within 1%
+    /// of converged. Zero if there's no spread at all.
+    #[must_use]
+    pub fn relative_error(&self, z: f64) -> f64 {
+        let half_width = self.confidence_interval(z).luminance();
+        if half_width <= 0.0 {
+            0.0
+        } else {
+            half_width / self.mean.luminance().abs().max(f64::EPSILON)
+        }
+    }
+}
+
+/// The [`Welford`] sample statistics of every pixel of a render, an extra
+/// output (AOV) that answers "is this converged?". Set one on a render with
+/// [`RenderOptions::set_variance()`](crate::camera::RenderOptions::set_variance()),
+/// then read it during the pass hooks or after. The statistics are of the raw
+/// samples, before any [`FireflyFilter`](crate::camera::FireflyFilter).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, Background, CameraBuilder, RenderOptions},
+///     film::VarianceFilm,
+///     hit_list::HittableList,
+///     material::{DiffuseLight, Lambertian},
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // a ground lit by a light overhead, under a black sky
+/// let mut world = HittableList::new();
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, -100.5, -1.0],
+///     radius: 100.0,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// }));
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, 4.0, -1.0],
+///     radius: 2.0,
+///     mat: Box::new(DiffuseLight::new(vec3![4.0, 4.0, 4.0])),
+/// }));
+///
+/// let camera = CameraBuilder::default()
+///     .set_image_width(8)
+///     .set_anti_aliasing(AntiAliasing::Random(16))
+///     .set_background(Background::Solid(vec3![0.0, 0.0, 0.0]))
+///     .build().unwrap();
+/// let variance = VarianceFilm::new();
+/// let film = camera.render_film_with(&world, RenderOptions::default().set_variance(&variance));
+///
+/// assert_eq!(variance.get(0, 0).count(), 16);
+/// // the sky is always black, the ground only sometimes sees the light
+/// assert_eq!(variance.get(4, 0).relative_error(1.96), 0.0);
+/// assert!((0..8).any(|x| variance.get(x, 7).relative_error(1.96) > 0.1));
+///
+/// let errors = variance.confidence_film(1.96);
+/// assert_eq!((errors.width(), errors.height()), (film.width(), film.height()));
+/// assert!(variance.converged(0.01) < 1.0);
+/// ```
+#[derive(Debug, Default)]
+pub struct VarianceFilm {
+    pixels: Mutex<(u32, u32, Vec<Welford>)>,
+}
+
+impl VarianceFilm {
+    /// Create an empty [`VarianceFilm`], sized by the render it's set on.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the statistics with those of a `width` by `height` render.
+    pub(crate) fn set(&self, width: u32, height: u32, pixels: &[Welford]) {
+        *self.pixels.lock().expect("variance film lock poisoned") =
+            (width, height, pixels.to_vec());
+    }
+
+    /// The statistics of the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds, such as before the render has started, or
+    /// a thread panicked while updating the statistics.
+    #[must_use]
+    pub fn get(&self, x: u32, y: u32) -> Welford {
+        let (width, height, pixels) = &*self.pixels.lock().expect("variance film lock poisoned");
+        assert!(x < *width && y < *height, "Pixel out of bounds");
+        pixels[y as usize * *width as usize + x as usize]
+    }
+
+    /// A [`FilmBuffer`] of each pixel's statistics mapped by `f`.
+    fn map<F: Fn(&Welford) -> Vec3>(&self, f: F) -> FilmBuffer {
+        let (width, height, pixels) = &*self.pixels.lock().expect("variance film lock poisoned");
+        FilmBuffer {
+            width: *width,
+            height: *height,
+            pixels: pixels.iter().map(f).collect(),
+            dither: false,
+        }
+    }
+
+    /// The variance of each pixel, see [`Welford::variance()`].
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while updating the statistics.
+    #[must_use]
+    pub fn variance_film(&self) -> FilmBuffer {
+        self.map(Welford::variance)
+    }
+
+    /// The half width of each pixel's confidence interval, `z` standard
+    /// errors, see [`Welford::confidence_interval()`]. Saved linear, it shows
+    /// where the render is still noisy.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while updating the statistics.
+    #[must_use]
+    pub fn confidence_film(&self, z: f64) -> FilmBuffer {
+        self.map(|pixel| pixel.confidence_interval(z))
+    }
+
+    /// The fraction of pixels whose 95% confidence interval is within
+    /// `tolerance` of their mean, see [`Welford::relative_error()`].
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while updating the statistics.
+    #[must_use]
+    pub fn converged(&self, tolerance: f64) -> f64 {
+        let (_, _, pixels) = &*self.pixels.lock().expect("variance film lock poisoned");
+        let within = pixels
+            .iter()
+            .filter(|pixel| pixel.relative_error(CONFIDENCE_95) <= tolerance)
+            .count();
+        #[expect(clippy::cast_precision_loss)]
+        let fraction = within as f64 / pixels.len().max(1) as f64;
+        fraction
+    }
+}
+
+/// The number of standard errors either side of the mean covering 95% of
+/// the distribution.
+pub const CONFIDENCE_95: f64 = 1.96;
+
+/// Check if a path has an HDR image extension (`.hdr` or `.exr`).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::film::is_hdr_path;
+///
+/// assert!(is_hdr_path("probe.EXR".as_ref()));
+/// assert!(!is_hdr_path("output.png".as_ref()));
+/// ```
+#[must_use]
+pub fn is_hdr_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("hdr") || ext.eq_ignore_ascii_case("exr"))
+}
+
+/// Wrap the pixel coordinate `i` into `0..size`.
+fn wrap(i: f64, size: u32) -> u32 {
+    #[expect(clippy::cast_possible_truncation)]
+    let wrapped = (i.floor() as i64).rem_euclid(i64::from(size)) as u32;
+    wrapped
+}
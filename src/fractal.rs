@@ -0,0 +1,252 @@
+//! This module contains generators for the standard recursive test scenes:
+//! the [sphere flake](sphere_flake()), the [Menger sponge](menger_sponge()),
+//! and the [Sierpinski tetrahedron](sierpinski_tetrahedron()). Each level of
+//! recursion is built once as a [`BVHTree`] and instanced by the level above,
+//! so memory grows with the depth while the number of primitives grows
+//! exponentially, which makes them good stress tests for BVH traversal
+//! through instance hierarchies.
+
+use std::sync::Arc;
+
+use crate::{
+    bvh::BVHTree,
+    hit_list::HittableList,
+    material::Material,
+    sphere::Sphere,
+    transform::{Animated, Keyframe},
+    triangle::Triangle,
+    vec3,
+    vec3::{cross, dot, Vec3},
+};
+
+/// A generated fractal and its size.
+#[derive(Debug)]
+pub struct Fractal {
+    /// The top level of the hierarchy.
+    pub root: Arc<BVHTree>,
+    /// The number of instances made, over all of the levels.
+    pub instances: usize,
+    /// The number of spheres or triangles the fractal is made of once every
+    /// instance is expanded.
+    pub primitives: u64,
+}
+
+/// Build a hierarchy of `depth` levels on top of `base`, where each level is
+/// copies of the one below, each placed by one of `poses`.
+fn instance_levels(base: HittableList, depth: u32, poses: &[Keyframe]) -> Fractal {
+    let base_primitives = base.objects.len() as u64;
+    let mut level = Arc::new(BVHTree::from_hit_list(base));
+    let mut primitives = base_primitives;
+    for _ in 0..depth {
+        let mut objects = HittableList::new();
+        for &pose in poses {
+            objects.add(Box::new(Animated::new(Arc::clone(&level), vec![pose])));
+        }
+        level = Arc::new(BVHTree::from_hit_list(objects));
+        primitives *= poses.len() as u64;
+    }
+
+    Fractal {
+        root: level,
+        instances: poses.len() * depth as usize,
+        primitives,
+    }
+}
+
+/// The Euler angles (see [`Keyframe`]) that rotate `+Y` onto `dir`.
+fn euler_from_up(dir: Vec3) -> Vec3 {
+    let dir = dir.unit();
+    vec3![
+        dir[1].clamp(-1.0, 1.0).acos().to_degrees(),
+        dir[0].atan2(dir[2]).to_degrees(),
+        0.0
+    ]
+}
+
+/// Generate Eric Haines' sphere flake: a unit sphere at the origin with nine
+/// spheres a third of its size around its upper half, each with nine more
+/// around it, `depth` levels deep. Every level but the innermost also has its
+/// own sphere, so a depth of `d` makes `(9^(d + 1) - 1) / 8` spheres, which
+/// fit in a sphere of radius `2`.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     fractal::sphere_flake, hit::Hittable, material::Lambertian, ray, ray::Ray, vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let flake = sphere_flake(3, &Lambertian::new(vec3![0.8, 0.8, 0.8]));
+///
+/// assert_eq!(flake.primitives, 1 + 9 + 81 + 729);
+/// assert_eq!(flake.instances, 27);
+/// assert!(flake.root.bound().x.max <= 2.0);
+///
+/// // straight down onto the top of the central sphere
+/// let hit = flake.root.nearest_hit_along(&ray!(vec3![0.0, 5.0, 0.0], vec3![0.0, -1.0, 0.0]));
+/// assert!((hit.unwrap().p[1] - 1.0).abs() < 1e-9);
+/// ```
+#[must_use]
+pub fn sphere_flake(depth: u32, mat: &dyn Material) -> Fractal {
+    // six children around the equator and three above, pointing outwards
+    let directions = (0..6)
+        .map(|i| (f64::from(i) * 60.0, 0.0))
+        .chain([30.0, 150.0, 270.0].map(|azimuth| (azimuth, 60.0)))
+        .map(|(azimuth, elevation): (f64, f64)| {
+            let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+            vec3![
+                elevation.cos() * azimuth.cos(),
+                elevation.sin(),
+                elevation.cos() * azimuth.sin()
+            ]
+        });
+
+    let mut root = Arc::new(BVHTree::from_hit_list(flake_sphere(mat)));
+    let (mut instances, mut spheres) = (0, 1);
+    for _ in 0..depth {
+        let mut objects = flake_sphere(mat);
+        for dir in directions.clone() {
+            let pose = Keyframe::at(0.0, dir * (4.0 / 3.0))
+                .with_rotation(euler_from_up(dir))
+                .with_scale(vec3![1.0, 1.0, 1.0] * (1.0 / 3.0));
+            objects.add(Box::new(Animated::new(Arc::clone(&root), vec![pose])));
+        }
+
+        root = Arc::new(BVHTree::from_hit_list(objects));
+        instances += 9;
+        spheres = spheres * 9 + 1;
+    }
+
+    Fractal {
+        root,
+        instances,
+        primitives: spheres,
+    }
+}
+
+fn flake_sphere(mat: &dyn Material) -> HittableList {
+    let mut objects = HittableList::new();
+    objects.add(Box::new(Sphere {
+        centre: vec3![0.0, 0.0, 0.0],
+        radius: 1.0,
+        mat: mat.clone_box(),
+    }));
+    objects
+}
+
+/// Generate a Menger sponge filling the cube from `-1` to `1` on each axis,
+/// `depth` levels deep, from `20^depth` cubes of 12 triangles each.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     fractal::menger_sponge, hit::Hittable, material::Lambertian, ray, ray::Ray, vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let sponge = menger_sponge(2, &Lambertian::new(vec3![0.8, 0.8, 0.8]));
+/// assert_eq!(sponge.primitives, 20 * 20 * 12);
+///
+/// // straight through the hole in the middle
+/// let through_hole = ray!(vec3![0.0, 0.0, 5.0], vec3![0.0, 0.0, -1.0]);
+/// assert!(sponge.root.nearest_hit_along(&through_hole).is_none());
+///
+/// let at_corner = ray!(vec3![0.9, 0.9, 5.0], vec3![0.0, 0.0, -1.0]);
+/// assert!((sponge.root.nearest_hit_along(&at_corner).unwrap().p[2] - 1.0).abs() < 1e-9);
+/// ```
+#[must_use]
+pub fn menger_sponge(depth: u32, mat: &dyn Material) -> Fractal {
+    let mat: Arc<dyn Material> = Arc::from(mat.clone_box());
+    let mut cube = HittableList::new();
+    for axis in 0..3 {
+        for sign in [1.0, -1.0] {
+            let mut n = vec3![0.0, 0.0, 0.0];
+            n[axis] = sign;
+            let mut u = vec3![0.0, 0.0, 0.0];
+            u[(axis + 1) % 3] = 1.0;
+            let mut v = vec3![0.0, 0.0, 0.0];
+            v[(axis + 2) % 3] = 1.0;
+            // wind anticlockwise seen from outside, so u x v = n
+            if sign < 0.0 {
+                (u, v) = (v, u);
+            }
+
+            let corners = [n - u - v, n + u - v, n + u + v, n - u + v];
+            for [a, b, c] in [[0, 1, 2], [0, 2, 3]] {
+                cube.add(Box::new(Triangle::with_shared_material(
+                    [corners[a], corners[b], corners[c]],
+                    None,
+                    Arc::clone(&mat),
+                )));
+            }
+        }
+    }
+
+    // the 20 sub-cubes that aren't on more than one of the centre lines
+    let mut poses = vec![];
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                if [x, y, z].iter().filter(|&&c| c == 0).count() > 1 {
+                    continue;
+                }
+                let offset = vec3![f64::from(x), f64::from(y), f64::from(z)] * (2.0 / 3.0);
+                poses
+                    .push(Keyframe::at(0.0, offset).with_scale(vec3![1.0, 1.0, 1.0] * (1.0 / 3.0)));
+            }
+        }
+    }
+
+    instance_levels(cube, depth, &poses)
+}
+
+/// Generate a Sierpinski tetrahedron inscribed in the cube from `-1` to `1`
+/// on each axis, `depth` levels deep, from `4^depth` tetrahedra of 4
+/// triangles each.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{fractal::sierpinski_tetrahedron, material::Lambertian, vec3, vec3::Vec3};
+///
+/// let tetra = sierpinski_tetrahedron(5, &Lambertian::new(vec3![0.8, 0.8, 0.8]));
+///
+/// assert_eq!(tetra.primitives, 4_u64.pow(5) * 4);
+/// assert_eq!(tetra.instances, 20);
+/// ```
+#[must_use]
+pub fn sierpinski_tetrahedron(depth: u32, mat: &dyn Material) -> Fractal {
+    let corners = [
+        vec3![1.0, 1.0, 1.0],
+        vec3![1.0, -1.0, -1.0],
+        vec3![-1.0, 1.0, -1.0],
+        vec3![-1.0, -1.0, 1.0],
+    ];
+
+    let mat: Arc<dyn Material> = Arc::from(mat.clone_box());
+    let mut tetrahedron = HittableList::new();
+    for skip in 0..4 {
+        let [a, b, c] = match skip {
+            0 => [1, 2, 3],
+            1 => [0, 2, 3],
+            2 => [0, 1, 3],
+            _ => [0, 1, 2],
+        }
+        .map(|i| corners[i]);
+        // face away from the corner left out
+        let outwards = dot(&cross(&(b - a), &(c - a)), &(a - corners[skip])) > 0.0;
+        let vertices = if outwards { [a, b, c] } else { [a, c, b] };
+        tetrahedron.add(Box::new(Triangle::with_shared_material(
+            vertices,
+            None,
+            Arc::clone(&mat),
+        )));
+    }
+
+    let poses =
+        corners.map(|corner| Keyframe::at(0.0, corner * 0.5).with_scale(vec3![0.5, 0.5, 0.5]));
+
+    instance_levels(tetrahedron, depth, &poses)
+}
@@ -0,0 +1,174 @@
+//! This module contains the white furnace test, [`furnace_test()`], which
+//! renders a material on a ball lit evenly from every direction by a uniform
+//! white background. A material that neither creates nor loses energy, such
+//! as a white [`Lambertian`](crate::material::Lambertian), can't be told
+//! apart from the background, so any difference in brightness is energy the
+//! material gains or loses, for checking new materials before they're used
+//! in scenes.
+
+use std::fmt;
+
+use crate::{
+    camera::{AntiAliasing, Background, Camera, CameraBuilder},
+    hit_list::HittableList,
+    material::Material,
+    sphere::Sphere,
+    vec3,
+    vec3::Vec3,
+};
+
+/// The default size of the square image a [`furnace_test()`] renders.
+pub const FURNACE_RESOLUTION: u32 = 48;
+
+/// The smallest image a [`furnace_test()`] can render, below which no pixel
+/// is far enough from the ball's edge to be measured.
+pub const MIN_FURNACE_RESOLUTION: u32 = 8;
+
+/// The default samples per pixel of a [`furnace_test()`].
+pub const FURNACE_SAMPLES: u16 = 64;
+
+/// Bounces before a path is cut short, high enough that light inside glass
+/// escapes rather than being lost to the limit.
+const FURNACE_MAX_DEPTH: u32 = 64;
+
+/// The outcome of a [`furnace_test()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FurnaceResult {
+    /// The mean of the pixels covered by the ball, exactly one in every
+    /// channel for a material that conserves energy.
+    pub mean: Vec3,
+    /// The brightest of those pixels in each channel, showing gains at some
+    /// angles hidden by losses at others, though noisy for materials that
+    /// scatter randomly.
+    pub max: Vec3,
+    /// How many pixels were averaged.
+    pub pixels: usize,
+}
+
+impl FurnaceResult {
+    /// The fraction of the light falling on the material that it gains, by
+    /// luminance, or loses if negative.
+    #[must_use]
+    pub fn gain(&self) -> f64 {
+        self.mean.luminance() - 1.0
+    }
+
+    /// Whether no channel gains more than `tolerance` on average. Losing
+    /// energy is fine, as any material that isn't white does.
+    #[must_use]
+    pub fn conserves(&self, tolerance: f64) -> bool {
+        (0..3).all(|i| self.mean[i] <= 1.0 + tolerance)
+    }
+}
+
+impl fmt::Display for FurnaceResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.mean.e;
+        let [max_r, max_g, max_b] = self.max.e;
+        write!(
+            f,
+            "{:+.2}% (mean {r:.4} {g:.4} {b:.4}, brightest {max_r:.4} {max_g:.4} {max_b:.4})",
+            self.gain() * 100.0
+        )
+    }
+}
+
+/// The camera of a [`furnace_test()`], looking at a unit ball at the origin
+/// which fills most of the frame, against a white background.
+fn furnace_camera(resolution: u32, samples: u16) -> Camera {
+    CameraBuilder::default()
+        .set_aspect_ratio(1.0)
+        .set_image_width(resolution)
+        .set_anti_aliasing(AntiAliasing::Random(samples))
+        .set_max_depth(FURNACE_MAX_DEPTH)
+        .set_vfov_degrees(32.0)
+        .set_look_from(vec3![0.0, 0.0, 4.0])
+        .set_look_at(vec3![0.0, 0.0, 0.0])
+        .set_background(Background::Solid(vec3![1.0, 1.0, 1.0]))
+        .set_dither(false)
+        .build()
+        .expect("a square camera with pixels is valid")
+}
+
+/// Render `material` on a ball under a uniform white background, square and
+/// `resolution` pixels across with `samples` per pixel, and measure how
+/// much brighter or darker than the background it is away from the ball's
+/// edge. The ball is seen from every angle between head on and grazing, so
+/// the whole of the material's response is tested, and the paths go through
+/// the renderer's own integrator. Emissive materials add their light on top
+/// and so always gain.
+///
+/// # Panics
+///
+/// If `resolution` is less than [`MIN_FURNACE_RESOLUTION`], or `samples` is
+/// zero.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     furnace::{furnace_test, FURNACE_RESOLUTION},
+///     material::{Dielectric, Lambertian, Metal},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // white materials conserve energy exactly
+/// let white = furnace_test(&Lambertian::new(vec3![1.0, 1.0, 1.0]), FURNACE_RESOLUTION, 4);
+/// assert!(white.gain().abs() < 1e-9);
+/// let mirror = furnace_test(&Metal::new(vec3![1.0, 1.0, 1.0], 0.0), FURNACE_RESOLUTION, 4);
+/// assert!(mirror.gain().abs() < 1e-9);
+/// let glass = furnace_test(&Dielectric::new(1.5), 16, 16);
+/// assert!(glass.conserves(0.02), "{glass}");
+///
+/// // and grey ones lose half of it
+/// let grey = furnace_test(&Lambertian::new(vec3![0.5, 0.5, 0.5]), 16, 4);
+/// assert!((grey.gain() + 0.5).abs() < 1e-9);
+/// ```
+#[must_use]
+pub fn furnace_test(material: &dyn Material, resolution: u32, samples: u16) -> FurnaceResult {
+    assert!(
+        resolution >= MIN_FURNACE_RESOLUTION,
+        "Furnace resolution must be at least {MIN_FURNACE_RESOLUTION}"
+    );
+    assert!(samples > 0, "Furnace tests need at least one sample");
+    let camera = furnace_camera(resolution, samples);
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere {
+        centre: vec3![0.0, 0.0, 0.0],
+        radius: 1.0,
+        mat: material.clone_box(),
+    }));
+
+    let film = camera.render_film(&world);
+    let coverage = camera.render_shaded(&world, |_, rec| {
+        let seen = if rec.is_some() { 1.0 } else { 0.0 };
+        vec3![seen, seen, seen]
+    });
+
+    // the render's samples land elsewhere in each pixel than the coverage's,
+    // so pixels next to the edge may still see some of the background
+    let inside = |i: u32, j: u32| i < resolution && j < resolution && coverage.get(i, j)[0] >= 1.0;
+    let covered: Vec<Vec3> = (0..resolution)
+        .flat_map(|j| (0..resolution).map(move |i| (i, j)))
+        .filter(|&(i, j)| {
+            inside(i, j)
+                && inside(i + 1, j)
+                && inside(i, j + 1)
+                && i.checked_sub(1).is_some_and(|i| inside(i, j))
+                && j.checked_sub(1).is_some_and(|j| inside(i, j))
+        })
+        .map(|(i, j)| film.get(i, j))
+        .collect();
+    let pixels = covered.len().max(1);
+    #[expect(clippy::cast_precision_loss)]
+    let mean = covered.iter().fold(Vec3::default(), |acc, &p| acc + p) / pixels as f64;
+    let max = covered.iter().fold(Vec3::default(), |acc, p| {
+        vec3![acc[0].max(p[0]), acc[1].max(p[1]), acc[2].max(p[2])]
+    });
+    FurnaceResult {
+        mean,
+        max,
+        pixels: covered.len(),
+    }
+}
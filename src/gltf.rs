@@ -0,0 +1,876 @@
+//! Contains the [`GltfAsset`], an importer for glTF 2.0 scenes in either the
+//! `.gltf` JSON format, with its buffers and images alongside or embedded as
+//! data URIs, or the single file binary `.glb` format.
+//!
+//! Supported are the node hierarchy and its transforms, triangle meshes,
+//! perspective cameras, and metallic-roughness materials with base colour
+//! textures and emission. Animations, skins, morph targets and sparse
+//! accessors aren't, nor are extensions beyond
+//! `KHR_materials_emissive_strength`.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    camera::{Camera, CameraBuilder},
+    film::{ColourSpace, FilmBuffer},
+    material::{DiffuseLight, Material, PbrMaterial},
+    mesh::{FaceVertex, Mesh, MeshData},
+    scene::{Scene, SceneSettings},
+    texture::{ImageTexture, Textured},
+    transform::Transform,
+    utils::percent_decode,
+    vec3,
+    vec3::Vec3,
+};
+
+/// The magic number at the start of a `.glb` file, `glTF` in ASCII.
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+
+/// The type of the JSON chunk of a `.glb` file, `JSON` in ASCII.
+const GLB_JSON: u32 = 0x4E4F_534A;
+
+/// The type of the binary buffer chunk of a `.glb` file, `BIN` in ASCII.
+const GLB_BIN: u32 = 0x004E_4942;
+
+/// A glTF 2.0 asset read from a `.gltf` or `.glb` file, from which a
+/// [`Scene`] can be built with [`GltfAsset::scene()`].
+///
+/// The nodes of the default scene (or every root node, if there isn't one)
+/// are placed by their `matrix`, or `translation`, `rotation` and `scale`.
+/// Mesh primitives drawn as triangles, strips or fans are imported with
+/// their normals and first texture coordinates; points and lines are
+/// skipped. Materials become a [`PbrMaterial`] of their base colour,
+/// metallic and roughness factors, tinted by their base colour texture if
+/// they have one, or a [`DiffuseLight`] over the base colour if they're
+/// emissive. Primitives without a material get glTF's default, a white,
+/// fully rough metal. Alpha and double sidedness are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::CameraBuilder, gltf::GltfAsset, hit::Hittable, interval, interval::Interval,
+/// };
+///
+/// // a triangle moved two units up, and a camera looking at it
+/// let asset = GltfAsset::parse(
+///     br#"{
+///         "asset": { "version": "2.0" },
+///         "scene": 0,
+///         "scenes": [{ "nodes": [0, 1] }],
+///         "nodes": [
+///             { "mesh": 0, "translation": [0, 2, 0] },
+///             { "name": "Shot", "camera": 0, "translation": [0, 2, 5] }
+///         ],
+///         "meshes": [{
+///             "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }]
+///         }],
+///         "materials": [{
+///             "pbrMetallicRoughness": { "baseColorFactor": [0.8, 0.1, 0.1, 1], "metallicFactor": 0 }
+///         }],
+///         "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } }],
+///         "accessors": [
+///             { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+///             { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+///         ],
+///         "bufferViews": [
+///             { "buffer": 0, "byteLength": 36 },
+///             { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+///         ],
+///         "buffers": [{
+///             "byteLength": 44,
+///             "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA="
+///         }]
+///     }"#,
+///     ".",
+/// )
+/// .unwrap();
+///
+/// let scene = asset.scene(&CameraBuilder::default()).unwrap();
+/// assert!(scene.camera("Shot").is_some());
+/// assert_eq!(scene.world().bound().y, interval![2.0, 3.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct GltfAsset {
+    document: Document,
+    buffers: Vec<Vec<u8>>,
+    base_dir: PathBuf,
+    settings: SceneSettings,
+}
+
+impl GltfAsset {
+    /// Load a [`GltfAsset`] from a `.gltf` or `.glb` file, see
+    /// [`GltfAsset::parse()`]. Buffers and images are relative to the file.
+    ///
+    /// # Errors
+    ///
+    /// If reading the file fails, or parsing it or loading its buffers does.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            fs::read(path).with_context(|| format!("Couldn't read asset {}", path.display()))?;
+        Self::parse(&bytes, path.parent().unwrap_or_else(|| Path::new(".")))
+            .with_context(|| format!("Invalid asset {}", path.display()))
+    }
+
+    /// Parse a [`GltfAsset`] from the bytes of a `.gltf` or `.glb` file,
+    /// loading its buffers. Buffers and images that aren't embedded are
+    /// relative to `base_dir`.
+    ///
+    /// # Errors
+    ///
+    /// If the bytes aren't a valid glTF 2.0 asset, it requires an
+    /// unsupported extension, or a buffer can't be loaded.
+    pub fn parse<P: AsRef<Path>>(bytes: &[u8], base_dir: P) -> Result<Self> {
+        let base_dir = base_dir.as_ref().to_path_buf();
+        let (json, bin) = if bytes.starts_with(GLB_MAGIC) {
+            split_glb(bytes)?
+        } else {
+            (bytes, None)
+        };
+        let document: Document = serde_json::from_slice(json).context("Invalid glTF JSON")?;
+        if let Some(version) = &document.asset.version {
+            if !version.starts_with("2.") {
+                bail!("Unsupported glTF version {version}");
+            }
+        }
+        if let Some(extension) = document
+            .extensions_required
+            .iter()
+            .find(|&extension| extension != "KHR_materials_emissive_strength")
+        {
+            bail!("Unsupported required extension {extension}");
+        }
+
+        let buffers = document
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| {
+                let data = match (&buffer.uri, i, bin) {
+                    (Some(uri), _, _) => read_uri(uri, &base_dir)?,
+                    (None, 0, Some(bin)) => bin.to_vec(),
+                    (None, _, _) => bail!("Buffer {i} has no data"),
+                };
+                if data.len() < buffer.byte_length {
+                    bail!(
+                        "Buffer {i} is {} bytes, shorter than its byteLength",
+                        data.len()
+                    );
+                }
+                Ok(data)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            document,
+            buffers,
+            base_dir,
+            settings: SceneSettings::default(),
+        })
+    }
+
+    /// The [`SceneSettings`] the asset is imported with.
+    #[must_use]
+    pub fn settings(&self) -> SceneSettings {
+        self.settings
+    }
+
+    /// Set the [`SceneSettings`] to import the asset with. glTF is always
+    /// `+Y` up in metres, so the defaults are usually right.
+    #[must_use]
+    pub fn set_settings(mut self, settings: SceneSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Build the [`Scene`], see [`GltfAsset`].
+    ///
+    /// Cameras are added named by their nodes' names, or `camera<node>` by
+    /// the node's index if unnamed, starting from `camera` with the view,
+    /// vertical field of view and any aspect ratio set from the asset.
+    ///
+    /// # Errors
+    ///
+    /// If a node, mesh, material or accessor is malformed, such as indexing
+    /// past the end of a buffer, or an image fails to load.
+    pub fn scene(&self, camera: &CameraBuilder) -> Result<Scene> {
+        let materials = (0..self.document.materials.len())
+            .map(|i| {
+                self.material(i)
+                    .with_context(|| format!("Invalid material {i}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut builder = SceneBuilder {
+            asset: self,
+            materials,
+            default_material: PbrMaterial::new(vec3![1.0, 1.0, 1.0], 1.0, 1.0),
+            camera,
+            scene: Scene::new(self.settings),
+        };
+
+        let roots = match self.document.scene {
+            Some(scene) => self
+                .document
+                .scenes
+                .get(scene)
+                .context("The default scene doesn't exist")?
+                .nodes
+                .clone(),
+            None => self.root_nodes(),
+        };
+        for node in roots {
+            builder.add(node, &Transform::identity(), 0)?;
+        }
+        Ok(builder.scene)
+    }
+
+    /// The nodes that aren't anyone's children.
+    fn root_nodes(&self) -> Vec<usize> {
+        let nodes = &self.document.nodes;
+        (0..nodes.len())
+            .filter(|i| !nodes.iter().any(|node| node.children.contains(i)))
+            .collect()
+    }
+
+    /// The material for material `index`.
+    fn material(&self, index: usize) -> Result<Box<dyn Material>> {
+        let def = &self.document.materials[index];
+        let pbr = &def.pbr_metallic_roughness;
+        let [r, g, b, _] = pbr.base_color_factor;
+        let base_colour = vec3![r, g, b];
+
+        let strength = def
+            .extensions
+            .khr_materials_emissive_strength
+            .map_or(1.0, |extension| extension.emissive_strength);
+        let [er, eg, eb] = def.emissive_factor;
+        let emission = vec3![er, eg, eb] * strength;
+        let mat: Box<dyn Material> = if emission.near_zero() {
+            Box::new(PbrMaterial::new(
+                base_colour,
+                pbr.metallic_factor.clamp(0.0, 1.0),
+                pbr.roughness_factor.clamp(0.0, 1.0),
+            ))
+        } else {
+            Box::new(DiffuseLight::new(emission).with_albedo(base_colour))
+        };
+
+        Ok(match &pbr.base_color_texture {
+            Some(texture) => {
+                let film = self.texture(texture.index)?;
+                Box::new(Textured::new(Box::new(ImageTexture::new(film)), mat))
+            }
+            None => mat,
+        })
+    }
+
+    /// The colours of texture `index`.
+    fn texture(&self, index: usize) -> Result<FilmBuffer> {
+        let source = self
+            .document
+            .textures
+            .get(index)
+            .with_context(|| format!("Texture {index} doesn't exist"))?
+            .source
+            .with_context(|| format!("Texture {index} has no image"))?;
+        let image = self
+            .document
+            .images
+            .get(source)
+            .with_context(|| format!("Image {source} doesn't exist"))?;
+        let bytes = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) => read_uri(uri, &self.base_dir)?,
+            (None, Some(view)) => self.view(view)?.to_vec(),
+            (None, None) => bail!("Image {source} has no data"),
+        };
+        let film = FilmBuffer::load_from_memory(&bytes, ColourSpace::Srgb)
+            .with_context(|| format!("Invalid image {source}"))?;
+        if film.width() == 0 || film.height() == 0 {
+            bail!("Image {source} is empty");
+        }
+        Ok(film)
+    }
+
+    /// The bytes of buffer view `index`.
+    fn view(&self, index: usize) -> Result<&[u8]> {
+        let view = self
+            .document
+            .buffer_views
+            .get(index)
+            .with_context(|| format!("Buffer view {index} doesn't exist"))?;
+        let buffer = self
+            .buffers
+            .get(view.buffer)
+            .with_context(|| format!("Buffer {} doesn't exist", view.buffer))?;
+        buffer
+            .get(view.byte_offset..view.byte_offset + view.byte_length)
+            .with_context(|| format!("Buffer view {index} is past the end of its buffer"))
+    }
+
+    /// The elements of accessor `index`, each of its components as a number,
+    /// with normalised integers mapped into `0.0..=1.0` or `-1.0..=1.0`.
+    fn accessor(&self, index: usize) -> Result<Vec<Vec<f64>>> {
+        let accessor = self
+            .document
+            .accessors
+            .get(index)
+            .with_context(|| format!("Accessor {index} doesn't exist"))?;
+        if accessor.sparse.is_some() {
+            bail!("Accessor {index} is sparse, which isn't supported");
+        }
+        let components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            other => bail!("Unsupported accessor type {other}"),
+        };
+        let size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => bail!("Unsupported component type {other}"),
+        };
+
+        // accessors without a view are all zeros
+        let Some(view_index) = accessor.buffer_view else {
+            return Ok(vec![vec![0.0; components]; accessor.count]);
+        };
+        let view = self.view(view_index)?;
+        let stride = self.document.buffer_views[view_index]
+            .byte_stride
+            .unwrap_or(components * size);
+
+        let component = |bytes: &[u8]| -> f64 {
+            let normalized = accessor.normalized;
+            match (accessor.component_type, bytes) {
+                (5120, &[b]) if normalized => (f64::from(b.cast_signed()) / 127.0).max(-1.0),
+                (5120, &[b]) => f64::from(b.cast_signed()),
+                (5121, &[b]) if normalized => f64::from(b) / 255.0,
+                (5121, &[b]) => f64::from(b),
+                (5122, &[a, b]) if normalized => {
+                    (f64::from(i16::from_le_bytes([a, b])) / 32767.0).max(-1.0)
+                }
+                (5122, &[a, b]) => f64::from(i16::from_le_bytes([a, b])),
+                (5123, &[a, b]) if normalized => f64::from(u16::from_le_bytes([a, b])) / 65535.0,
+                (5123, &[a, b]) => f64::from(u16::from_le_bytes([a, b])),
+                (5125, &[a, b, c, d]) => f64::from(u32::from_le_bytes([a, b, c, d])),
+                (5126, &[a, b, c, d]) => f64::from(f32::from_le_bytes([a, b, c, d])),
+                _ => unreachable!("component sizes match their types"),
+            }
+        };
+
+        (0..accessor.count)
+            .map(|i| {
+                let start = accessor.byte_offset + i * stride;
+                let element = view
+                    .get(start..start + components * size)
+                    .with_context(|| format!("Accessor {index} is past the end of its view"))?;
+                Ok(element.chunks_exact(size).map(component).collect())
+            })
+            .collect()
+    }
+
+    /// The elements of accessor `index` as vectors.
+    fn vec3s(&self, index: usize) -> Result<Vec<Vec3>> {
+        self.accessor(index)?
+            .into_iter()
+            .map(|element| match element[..] {
+                [x, y, z] => Ok(vec3![x, y, z]),
+                _ => bail!("Accessor {index} must be VEC3"),
+            })
+            .collect()
+    }
+
+    /// The elements of accessor `index` as indices.
+    fn indices(&self, index: usize) -> Result<Vec<usize>> {
+        self.accessor(index)?
+            .into_iter()
+            .map(|element| match element[..] {
+                #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                [i] if i >= 0.0 && i.fract() == 0.0 => Ok(i as usize),
+                _ => bail!("Accessor {index} must be unsigned integer scalars"),
+            })
+            .collect()
+    }
+}
+
+/// Builds a [`Scene`] from a [`GltfAsset`]'s nodes.
+struct SceneBuilder<'a> {
+    asset: &'a GltfAsset,
+    materials: Vec<Box<dyn Material>>,
+    default_material: PbrMaterial,
+    camera: &'a CameraBuilder,
+    scene: Scene,
+}
+
+impl SceneBuilder<'_> {
+    /// Add node `index` and its descendants, under parents with the world
+    /// transform `parent`, `depth` nodes down.
+    fn add(&mut self, index: usize, parent: &Transform, depth: usize) -> Result<()> {
+        let nodes = &self.asset.document.nodes;
+        // a well formed hierarchy can't be deeper than it has nodes
+        if depth > nodes.len() {
+            bail!("The node hierarchy has a cycle");
+        }
+        let node = nodes
+            .get(index)
+            .with_context(|| format!("Node {index} doesn't exist"))?;
+        // nodes scaled to nothing are hidden, along with their children
+        let Some(local) = node.transform() else {
+            return Ok(());
+        };
+        let world = local.then(parent);
+
+        if let Some(mesh) = node.mesh {
+            self.add_mesh(mesh, &world)
+                .with_context(|| format!("Invalid mesh {mesh}"))?;
+        }
+        if let Some(camera) = node.camera {
+            let name = node
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("camera{index}"));
+            let camera = self
+                .camera(camera, &world)
+                .with_context(|| format!("Invalid camera {camera}"))?;
+            self.scene.add_camera(name, camera);
+        }
+
+        for &child in &node.children {
+            self.add(child, &world, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Add each primitive of mesh `index`, placed in the scene by `world`.
+    fn add_mesh(&mut self, index: usize, world: &Transform) -> Result<()> {
+        let asset = self.asset;
+        let mesh = asset
+            .document
+            .meshes
+            .get(index)
+            .context("The mesh doesn't exist")?;
+        for primitive in &mesh.primitives {
+            let Some(data) = self.mesh_data(primitive, world)? else {
+                continue;
+            };
+            let mat: &dyn Material = match primitive.material {
+                Some(i) => &**self
+                    .materials
+                    .get(i)
+                    .with_context(|| format!("Material {i} doesn't exist"))?,
+                None => &self.default_material,
+            };
+            self.scene.add(Box::new(Mesh::from_data(data, mat)));
+        }
+        Ok(())
+    }
+
+    /// The triangulated geometry of `primitive` placed in the scene by
+    /// `world`, or [`None`] if it's points or lines.
+    fn mesh_data(&self, primitive: &Primitive, world: &Transform) -> Result<Option<MeshData>> {
+        let asset = self.asset;
+        let attribute = |name: &str| primitive.attributes.get(name).copied();
+
+        let positions = asset.vec3s(attribute("POSITION").context("Missing POSITION")?)?;
+        let normals = attribute("NORMAL")
+            .map(|normals| asset.vec3s(normals))
+            .transpose()?;
+        let uvs = attribute("TEXCOORD_0")
+            .map(|uvs| {
+                asset
+                    .accessor(uvs)?
+                    .into_iter()
+                    .map(|element| match element[..] {
+                        // glTF's texture coordinates start at the top
+                        [u, v] => Ok([u, 1.0 - v]),
+                        _ => bail!("TEXCOORD_0 must be VEC2"),
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?;
+        for (name, len) in [
+            ("NORMAL", normals.as_ref().map(Vec::len)),
+            ("TEXCOORD_0", uvs.as_ref().map(Vec::len)),
+        ] {
+            if len.is_some_and(|len| len != positions.len()) {
+                bail!("{name} has a different count to POSITION");
+            }
+        }
+
+        let indices = match primitive.indices {
+            Some(indices) => asset.indices(indices)?,
+            None => (0..positions.len()).collect(),
+        };
+        if let Some(index) = indices.iter().find(|&&index| index >= positions.len()) {
+            bail!(
+                "Index {index} is past the primitive's {} vertices",
+                positions.len()
+            );
+        }
+
+        let triangles: Vec<[usize; 3]> = match primitive.mode {
+            4 => indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            // strips alternate winding to keep facing the same way
+            5 => (0..indices.len().saturating_sub(2))
+                .map(|i| {
+                    let (a, b) = if i % 2 == 0 { (1, 2) } else { (2, 1) };
+                    [indices[i], indices[i + a], indices[i + b]]
+                })
+                .collect(),
+            6 => (1..indices.len().saturating_sub(1))
+                .map(|i| [indices[0], indices[i], indices[i + 1]])
+                .collect(),
+            0..=3 => return Ok(None),
+            other => bail!("Unsupported primitive mode {other}"),
+        };
+
+        let (has_normals, has_uvs) = (normals.is_some(), uvs.is_some());
+        let corner = |i: usize| FaceVertex {
+            position: i,
+            normal: has_normals.then_some(i),
+            uv: has_uvs.then_some(i),
+        };
+        let settings = asset.settings;
+        Ok(Some(MeshData {
+            positions: positions
+                .iter()
+                .map(|&p| settings.point(world.point(p)))
+                .collect(),
+            normals: normals.map_or_else(Vec::new, |normals| {
+                normals
+                    .iter()
+                    .map(|&n| settings.normal(world.normal(n).unit()))
+                    .collect()
+            }),
+            uvs: uvs.unwrap_or_default(),
+            colours: Vec::new(),
+            faces: triangles.into_iter().map(|t| t.map(corner)).collect(),
+        }))
+    }
+
+    /// The camera for camera `index`, placed in the scene by `world`.
+    fn camera(&self, index: usize, world: &Transform) -> Result<Camera> {
+        let def = self
+            .asset
+            .document
+            .cameras
+            .get(index)
+            .context("The camera doesn't exist")?;
+        let perspective = match (def.kind.as_str(), &def.perspective) {
+            ("perspective", Some(perspective)) => perspective,
+            ("perspective", None) => bail!("Missing the perspective projection"),
+            (other, _) => bail!("Unsupported camera type {other}"),
+        };
+
+        // glTF cameras look down -Z with +Y up
+        let settings = self.asset.settings;
+        let look_from = settings.point(world.point(vec3![0.0, 0.0, 0.0]));
+        let forward = settings.normal(world.vector(vec3![0.0, 0.0, -1.0]));
+        let up = settings.normal(world.vector(vec3![0.0, 1.0, 0.0]));
+
+        let mut camera = self
+            .camera
+            .clone()
+            .set_vfov_degrees(perspective.yfov.to_degrees())
+            .set_look_from(look_from)
+            .set_look_at(look_from + forward.unit())
+            .set_v_up(up);
+        if let Some(aspect_ratio) = perspective.aspect_ratio {
+            camera = camera.set_aspect_ratio(aspect_ratio);
+        }
+        camera.build()
+    }
+}
+
+/// Split a `.glb` file into its JSON chunk and its binary buffer chunk, if
+/// it has one.
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>)> {
+    let word = |at: usize| -> Result<u32> {
+        let word = bytes.get(at..at + 4).context("The file is truncated")?;
+        Ok(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+    };
+    let version = word(4)?;
+    if version != 2 {
+        bail!("Unsupported glTF version {version}");
+    }
+    let length = (word(8)? as usize).min(bytes.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut at = 12;
+    while at + 8 <= length {
+        let chunk_length = word(at)? as usize;
+        let kind = word(at + 4)?;
+        let data = bytes
+            .get(at + 8..at + 8 + chunk_length)
+            .context("A chunk is truncated")?;
+        match kind {
+            GLB_JSON if json.is_none() => json = Some(data),
+            GLB_BIN if bin.is_none() => bin = Some(data),
+            _ => {}
+        }
+        at += 8 + chunk_length;
+    }
+    Ok((json.context("Missing the JSON chunk")?, bin))
+}
+
+/// The bytes of a buffer or image `uri`, either a base64 data URI or a path
+/// relative to `base_dir`.
+fn read_uri(uri: &str, base_dir: &Path) -> Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .context("Data URIs must be base64")?;
+        return base64_decode(encoded);
+    }
+    let path = base_dir.join(percent_decode(uri));
+    fs::read(&path).with_context(|| format!("Couldn't read {}", path.display()))
+}
+
+/// Decode standard base64, ignoring padding.
+fn base64_decode(encoded: &str) -> Result<Vec<u8>> {
+    let value = |c: u8| -> Result<u32> {
+        Ok(u32::from(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(anyhow!("Invalid base64 character {:?}", char::from(c))),
+        }))
+    };
+    let digits = encoded.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            bail!("Truncated base64");
+        }
+        let mut bits = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            bits |= value(c)? << (18 - 6 * i);
+        }
+        let [_, a, b, c] = bits.to_be_bytes();
+        out.extend_from_slice(&[a, b, c][..chunk.len() - 1]);
+    }
+    Ok(out)
+}
+
+/// The top level of a glTF JSON document, with the parts that are imported.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Document {
+    asset: AssetInfo,
+    extensions_required: Vec<String>,
+    scene: Option<usize>,
+    scenes: Vec<SceneDef>,
+    nodes: Vec<Node>,
+    meshes: Vec<MeshDef>,
+    materials: Vec<MaterialDef>,
+    textures: Vec<TextureDef>,
+    images: Vec<ImageDef>,
+    cameras: Vec<CameraDef>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct AssetInfo {
+    version: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct SceneDef {
+    nodes: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    name: Option<String>,
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    camera: Option<usize>,
+    /// Column major.
+    matrix: Option<[f64; 16]>,
+    translation: Option<[f64; 3]>,
+    /// A unit quaternion, imaginary parts first.
+    rotation: Option<[f64; 4]>,
+    scale: Option<[f64; 3]>,
+}
+
+impl Node {
+    /// The node's transform relative to its parent, or [`None`] if it can't
+    /// be inverted.
+    fn transform(&self) -> Option<Transform> {
+        if let Some(m) = self.matrix {
+            let rows = [0, 1, 2].map(|row| [m[row], m[4 + row], m[8 + row]]);
+            return Transform::from_matrix(rows, vec3![m[12], m[13], m[14]]);
+        }
+
+        let [x, y, z, w] = self.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        let rotation = [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ];
+        let scale = self.scale.unwrap_or([1.0, 1.0, 1.0]);
+        let m = rotation.map(|row| [row[0] * scale[0], row[1] * scale[1], row[2] * scale[2]]);
+        let [tx, ty, tz] = self.translation.unwrap_or_default();
+        Transform::from_matrix(m, vec3![tx, ty, tz])
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct MeshDef {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "Primitive::triangles")]
+    mode: u32,
+}
+
+impl Primitive {
+    fn triangles() -> u32 {
+        4
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct MaterialDef {
+    pbr_metallic_roughness: MetallicRoughness,
+    emissive_factor: [f64; 3],
+    extensions: MaterialExtensions,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct MetallicRoughness {
+    base_color_factor: [f64; 4],
+    base_color_texture: Option<TextureInfo>,
+    metallic_factor: f64,
+    roughness_factor: f64,
+}
+
+impl Default for MetallicRoughness {
+    fn default() -> Self {
+        Self {
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TextureInfo {
+    index: usize,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    khr_materials_emissive_strength: Option<EmissiveStrength>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrength {
+    emissive_strength: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct TextureDef {
+    source: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ImageDef {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CameraDef {
+    #[serde(rename = "type")]
+    kind: String,
+    perspective: Option<Perspective>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Perspective {
+    aspect_ratio: Option<f64>,
+    /// In radians.
+    yfov: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: Option<String>,
+    byte_length: usize,
+}
@@ -0,0 +1,342 @@
+//! This module contains the [`GroundPlane`], an infinite horizontal plane for
+//! studio-style floors, and the [`Ground`] material that gives it a checker or
+//! grid pattern fading out to a horizon colour with distance. Unlike the usual
+//! trick of a huge sphere as the floor, the plane is exactly flat however far
+//! it's looked across and doesn't lose precision far from the origin.
+//!
+//! The plane is unbounded, so [`BVHTree`](crate::bvh::BVHTree)s keep it aside
+//! and test it for every [ray](Ray). See [`Plane`](crate::plane::Plane) for
+//! other orientations.
+
+use rand::rngs::SmallRng;
+use serde::Deserialize;
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval,
+    interval::Interval,
+    material::Material,
+    ray,
+    ray::Ray,
+    vec3,
+    vec3::Vec3,
+};
+
+/// An infinite plane at a [`height`](field@GroundPlane::height) along `Y`,
+/// facing `+Y`.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     ground::{Ground, GroundPlane},
+///     hit::Hittable,
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let floor = GroundPlane {
+///     height: -1.0,
+///     mat: Box::new(Ground::checker(vec3![0.8, 0.8, 0.8], vec3![0.2, 0.2, 0.2], 1.0)),
+/// };
+///
+/// // a long way off, at a grazing angle
+/// let r = ray!(vec3![0.0, 0.0, 0.0], vec3![1e6, -1.0, 0.0]);
+/// let rec = floor.nearest_hit_along(&r).unwrap();
+/// assert_eq!(rec.p[1], -1.0);
+/// assert_eq!(rec.norm[1], 1.0);
+///
+/// // looking up never hits it
+/// assert!(floor.nearest_hit_along(&ray!(vec3![0.0, 0.0, 0.0], vec3![0.0, 1.0, 0.0])).is_none());
+/// ```
+#[derive(Debug)]
+pub struct GroundPlane {
+    /// The `Y` coordinate of the plane.
+    pub height: f64,
+    /// The [`Material`] of the plane, usually a [`Ground`].
+    pub mat: Box<dyn Material>,
+}
+
+impl Hittable for GroundPlane {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let dy = r.direction[1];
+        if dy == 0.0 {
+            return None;
+        }
+
+        let t = (self.height - r.origin[1]) / dy;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        // set exactly rather than from `r.at(t)`, so it doesn't drift off
+        // the plane far from the origin
+        let mut p = r.at(t);
+        p[1] = self.height;
+
+        let mut rec = HitRecord {
+            t,
+            p,
+            norm: Vec3::default(),
+            mat: &*self.mat,
+            front_face: false,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+            layer: None,
+            tangent: None,
+        };
+        rec.set_face_norm(r, &vec3![0.0, 1.0, 0.0]);
+        Some(rec)
+    }
+
+    /// Infinite along `X` and `Z`, and flat along `Y`.
+    fn bound(&self) -> Aabb {
+        Aabb {
+            x: interval![f64::NEG_INFINITY, f64::INFINITY],
+            y: interval![self.height, self.height],
+            z: interval![f64::NEG_INFINITY, f64::INFINITY],
+        }
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.height += offset[1];
+        true
+    }
+
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        Some(vec3![point[0], self.height, point[2]])
+    }
+}
+
+/// The pattern of a [`Ground`], projected straight down onto the `XZ` plane.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum GroundPattern {
+    /// Squares alternating between the two colours.
+    #[default]
+    Checker,
+    /// Lines of the second colour `line_width` wide over the first, along the
+    /// edges of each tile.
+    Grid {
+        /// The world-space width of the lines.
+        line_width: f64,
+    },
+}
+
+/// How a [`Ground`] fades out into the distance, hiding the aliasing of the
+/// pattern and the hard edge where the plane meets the background.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HorizonFade {
+    /// The distance from the ray's origin where the fade starts.
+    pub start: f64,
+    /// The distance where the ground is entirely the horizon colour.
+    pub end: f64,
+    /// The colour faded to, which should match the background at the horizon.
+    pub colour: Vec3,
+}
+
+impl HorizonFade {
+    /// The colour of the default sky at the horizon.
+    #[must_use]
+    pub fn sky_horizon() -> Vec3 {
+        vec3![0.75, 0.85, 1.0]
+    }
+
+    /// Create a [`HorizonFade`] from `start` to `end` into the default sky's
+    /// horizon colour.
+    ///
+    /// # Panics
+    ///
+    /// If `start` is negative or `end` is before `start`.
+    #[must_use]
+    pub fn new(start: f64, end: f64) -> Self {
+        assert!(
+            0.0 <= start && start <= end,
+            "Fade must start at a positive distance and end after it starts"
+        );
+        Self {
+            start,
+            end,
+            colour: Self::sky_horizon(),
+        }
+    }
+
+    /// Set the colour faded to.
+    #[must_use]
+    pub fn with_colour(self, colour: Vec3) -> Self {
+        Self { colour, ..self }
+    }
+
+    /// How faded the ground is at `distance`, from `0.0` to `1.0`.
+    #[must_use]
+    pub fn amount(&self, distance: f64) -> f64 {
+        if distance >= self.end {
+            return 1.0;
+        }
+        let t = ((distance - self.start) / (self.end - self.start)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// A diffuse [material](Material) with a procedural [`GroundPattern`] in two
+/// colours, optionally [fading](HorizonFade) out with distance. The faded part
+/// of the ground emits the horizon colour instead of reflecting, so the floor
+/// blends seamlessly into the background.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     ground::{Ground, HorizonFade},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let white = vec3![0.9, 0.9, 0.9];
+/// let grey = vec3![0.3, 0.3, 0.3];
+///
+/// let checker = Ground::checker(white, grey, 2.0);
+/// assert_eq!(checker.albedo_at(vec3![0.5, 0.0, 0.5]), white);
+/// assert_eq!(checker.albedo_at(vec3![2.5, 0.0, 0.5]), grey);
+/// assert_eq!(checker.albedo_at(vec3![-0.5, 0.0, 0.5]), grey);
+///
+/// let grid = Ground::grid(white, grey, 1.0, 0.1).with_fade(HorizonFade::new(10.0, 20.0));
+/// assert_eq!(grid.albedo_at(vec3![0.5, 0.0, 0.5]), white);
+/// assert_eq!(grid.albedo_at(vec3![3.02, 0.0, 0.5]), grey);
+/// assert_eq!(grid.fade_at(5.0), 0.0);
+/// assert_eq!(grid.fade_at(15.0), 0.5);
+/// assert_eq!(grid.fade_at(25.0), 1.0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ground {
+    pattern: GroundPattern,
+    colour: Vec3,
+    alternate: Vec3,
+    tile_size: f64,
+    fade: Option<HorizonFade>,
+}
+
+impl Ground {
+    /// Create a [`Ground`] with `pattern` of `tile_size` tiles in `colour` and
+    /// `alternate`.
+    ///
+    /// # Panics
+    ///
+    /// If `tile_size` isn't positive, or the lines of a
+    /// [`GroundPattern::Grid`] are negative or wider than a tile.
+    #[must_use]
+    pub fn new(pattern: GroundPattern, colour: Vec3, alternate: Vec3, tile_size: f64) -> Self {
+        assert!(tile_size > 0.0, "Tile size must be positive");
+        if let GroundPattern::Grid { line_width } = pattern {
+            assert!(
+                (0.0..=tile_size).contains(&line_width),
+                "Grid lines must be between zero and a tile wide"
+            );
+        }
+        Self {
+            pattern,
+            colour,
+            alternate,
+            tile_size,
+            fade: None,
+        }
+    }
+
+    /// Create a checkerboard [`Ground`], see [`Ground::new()`].
+    ///
+    /// # Panics
+    ///
+    /// If `tile_size` isn't positive.
+    #[must_use]
+    pub fn checker(colour: Vec3, alternate: Vec3, tile_size: f64) -> Self {
+        Self::new(GroundPattern::Checker, colour, alternate, tile_size)
+    }
+
+    /// Create a [`Ground`] of `line` coloured grid lines over `colour`, see
+    /// [`Ground::new()`].
+    ///
+    /// # Panics
+    ///
+    /// If `tile_size` isn't positive, or `line_width` is negative or wider
+    /// than a tile.
+    #[must_use]
+    pub fn grid(colour: Vec3, line: Vec3, tile_size: f64, line_width: f64) -> Self {
+        Self::new(GroundPattern::Grid { line_width }, colour, line, tile_size)
+    }
+
+    /// Fade the [`Ground`] out with distance.
+    #[must_use]
+    pub fn with_fade(self, fade: HorizonFade) -> Self {
+        Self {
+            fade: Some(fade),
+            ..self
+        }
+    }
+
+    /// The colour of the pattern at `p`, ignoring any fade.
+    #[must_use]
+    pub fn albedo_at(&self, p: Vec3) -> Vec3 {
+        let (u, v) = (p[0] / self.tile_size, p[2] / self.tile_size);
+        let alternate = match self.pattern {
+            GroundPattern::Checker => (u.floor() + v.floor()).rem_euclid(2.0) >= 1.0,
+            GroundPattern::Grid { line_width } => {
+                let half = 0.5 * line_width / self.tile_size;
+                let on_line = |x: f64| {
+                    let f = x - x.floor();
+                    f < half || f > 1.0 - half
+                };
+                on_line(u) || on_line(v)
+            }
+        };
+        if alternate {
+            self.alternate
+        } else {
+            self.colour
+        }
+    }
+
+    /// How faded the [`Ground`] is at `distance`, see [`HorizonFade::amount()`].
+    #[must_use]
+    pub fn fade_at(&self, distance: f64) -> f64 {
+        self.fade.map_or(0.0, |fade| fade.amount(distance))
+    }
+
+    fn hit_fade(&self, r_in: &Ray, rec: &HitRecord) -> f64 {
+        self.fade_at((rec.p - r_in.origin).length())
+    }
+}
+
+impl Material for Ground {
+    /// Scatter light [ray](Ray)s like a [`Lambertian`](crate::material::Lambertian)
+    /// with the pattern's colour, dimmed by how faded the hit is.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let mut scatter_dir = rec.norm + Vec3::random_unit(rng.unwrap());
+
+        if scatter_dir.near_zero() {
+            scatter_dir = rec.norm;
+        }
+
+        let albedo = self.albedo_at(rec.p) * (1.0 - self.hit_fade(r_in, rec));
+        (ray![rec.p, scatter_dir, r_in.time], albedo)
+    }
+
+    /// The horizon colour, as much as the hit is faded.
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        match self.fade {
+            Some(fade) => fade.colour * self.hit_fade(r_in, rec),
+            None => Vec3::default(),
+        }
+    }
+
+    fn lambertian_albedo(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        Some(self.albedo_at(rec.p) * (1.0 - self.hit_fade(r_in, rec)))
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
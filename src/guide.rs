@@ -0,0 +1,550 @@
+//! This module contains [`PathGuide`], which learns where light arrives from
+//! across a scene as it renders and steers diffuse bounces towards it (after
+//! Müller et al., "Practical Path Guiding for Efficient Light-Transport
+//! Simulation", 2017). Set it with
+//! [`RenderOptions::set_path_guide()`](crate::camera::RenderOptions::set_path_guide()).
+//!
+//! Space is split by a binary tree, finer where more paths bounce, and each
+//! leaf keeps histograms of the light arriving over the sphere of directions,
+//! one for each way a surface can face. Every bounce off a surface that
+//! [reflects like a Lambertian](crate::material::Material::lambertian_albedo())
+//! adds to it, and after each pass the histograms become the distributions
+//! the next pass samples from. Bounces either follow the distribution or the
+//! surface's usual cosine lobe and are weighted by both, so the render stays
+//! unbiased however poorly the guide has learned. It helps most where light
+//! gets into a scene through a small opening, such as a keyhole or a gap in
+//! the curtains, which plain path tracing rarely finds.
+
+use std::{
+    f64::consts::{PI, TAU},
+    sync::{RwLock, RwLockReadGuard},
+};
+
+use rand::{rngs::SmallRng, RngExt as _};
+
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    vec3,
+    vec3::{dot, Vec3},
+};
+
+/// Rows of each histogram, evenly spaced in the height of the direction
+/// along Z, so every bin covers the same solid angle.
+const ROWS: u32 = 16;
+
+/// Columns of each histogram, evenly spaced around Z.
+const COLUMNS: u32 = 32;
+
+/// Bins in each histogram.
+const BINS: usize = (ROWS * COLUMNS) as usize;
+
+/// The most times space is halved, to keep the tree from growing without end
+/// where paths keep bouncing.
+const MAX_DEPTH: u32 = 24;
+
+/// The histogram bin holding the unit `direction`.
+fn bin(direction: Vec3) -> u32 {
+    let height = 0.5 * (direction[2] + 1.0);
+    let around = direction[1].atan2(direction[0]) / TAU + 0.5;
+    #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let [row, column] = [(height, ROWS), (around, COLUMNS)]
+        .map(|(x, count)| ((x * f64::from(count)) as u32).min(count - 1));
+    row * COLUMNS + column
+}
+
+/// A uniformly random unit direction within `bin`.
+fn direction_in(bin: u32, rng: &mut SmallRng) -> Vec3 {
+    let (row, column) = (bin / COLUMNS, bin % COLUMNS);
+    let height = (f64::from(row) + rng.random_range(0.0..1.0)) / f64::from(ROWS);
+    let around = (f64::from(column) + rng.random_range(0.0..1.0)) / f64::from(COLUMNS);
+
+    let z = 2.0 * height - 1.0;
+    let (sin, cos) = (TAU * (around - 0.5)).sin_cos();
+    let spread = (1.0 - z * z).max(0.0).sqrt();
+    vec3![spread * cos, spread * sin, z]
+}
+
+/// A random unit direction about `normal`, cosine-weighted like a
+/// Lambertian's bounce.
+fn cosine_direction(normal: Vec3, rng: &mut SmallRng) -> Vec3 {
+    let dir = normal + Vec3::random_unit(rng);
+    if dir.near_zero() {
+        normal
+    } else {
+        dir.unit()
+    }
+}
+
+/// Which of the six axis directions `normal` faces most, so the two sides
+/// of a thin wall, or the walls meeting in a corner, learn separately.
+fn facing(normal: Vec3) -> usize {
+    let axis = (0..3)
+        .max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))
+        .unwrap_or(0);
+    2 * axis + usize::from(normal[axis] < 0.0)
+}
+
+/// One bounce recorded to learn from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GuideSample {
+    position: Vec3,
+    facing: usize,
+    direction: Vec3,
+    /// The luminance arriving, cosine-weighted, over the probability of
+    /// choosing the direction.
+    weight: f64,
+}
+
+/// A direction chosen by [`PathGuide::bounce()`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GuidedBounce {
+    /// The unit direction to bounce in.
+    pub(crate) direction: Vec3,
+    /// What to scale the albedo by for the light arriving from `direction`,
+    /// the cosine lobe over the probability of choosing it.
+    pub(crate) weight: f64,
+    facing: usize,
+    cos: f64,
+    pdf: f64,
+}
+
+impl GuidedBounce {
+    /// A sample to learn from, of the `incoming` light along the bounce from
+    /// `position`.
+    pub(crate) fn sample(&self, position: Vec3, incoming: Vec3) -> GuideSample {
+        GuideSample {
+            position,
+            facing: self.facing,
+            direction: self.direction,
+            weight: incoming.luminance() * self.cos / self.pdf,
+        }
+    }
+}
+
+/// The light learned arriving over the sphere of directions.
+#[derive(Debug, Clone, Default)]
+struct Directions {
+    /// The weight of the samples recorded in each bin, empty until the
+    /// first.
+    histogram: Vec<f64>,
+    /// The cumulative distribution over the bins sampled from, learned from
+    /// the histogram at the end of the last pass. Empty until then.
+    cdf: Vec<f64>,
+}
+
+impl Directions {
+    fn is_learned(&self) -> bool {
+        !self.cdf.is_empty()
+    }
+
+    fn add(&mut self, direction: Vec3, weight: f64) {
+        if self.histogram.is_empty() {
+            self.histogram = vec![0.0; BINS];
+        }
+        self.histogram[bin(direction) as usize] += weight;
+    }
+
+    /// A direction from the learned distribution.
+    fn sample(&self, rng: &mut SmallRng) -> Vec3 {
+        let x = rng.random_range(0.0..1.0);
+        let bin = self.cdf.partition_point(|&c| c <= x).min(BINS - 1);
+        #[expect(clippy::cast_possible_truncation)]
+        direction_in(bin as u32, rng)
+    }
+
+    /// The learned probability density of `direction`, per steradian.
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let bin = bin(direction) as usize;
+        let below = if bin == 0 { 0.0 } else { self.cdf[bin - 1] };
+        #[expect(clippy::cast_precision_loss)]
+        let per_steradian = BINS as f64 / (4.0 * PI);
+        (self.cdf[bin] - below) * per_steradian
+    }
+
+    /// Learn the distribution from the histogram, if anything has been
+    /// recorded.
+    fn learn(&mut self) {
+        let total: f64 = self.histogram.iter().sum();
+        if total <= 0.0 {
+            return;
+        }
+        let mut sum = 0.0;
+        self.cdf = self
+            .histogram
+            .iter()
+            .map(|&weight| {
+                sum += weight;
+                sum / total
+            })
+            .collect();
+    }
+
+    /// A copy with half the recorded weight, for half of a split region.
+    fn halved(&self) -> Self {
+        Self {
+            histogram: self.histogram.iter().map(|weight| 0.5 * weight).collect(),
+            cdf: self.cdf.clone(),
+        }
+    }
+}
+
+/// A region of space and the light learned arriving in it, by which way the
+/// surfaces face.
+#[derive(Debug, Clone)]
+struct GuideLeaf {
+    bound: Aabb,
+    depth: u32,
+    /// The samples recorded this pass.
+    samples: u32,
+    facings: [Directions; 6],
+}
+
+impl GuideLeaf {
+    fn new(bound: Aabb, depth: u32) -> Self {
+        Self {
+            bound,
+            depth,
+            samples: 0,
+            facings: Default::default(),
+        }
+    }
+
+    /// Halve the leaf across its longest finite axis, each half starting
+    /// with half of what it learned and of its samples. [`None`] if it's too
+    /// small or unbounded to split.
+    fn split(&self) -> Option<(usize, f64, [Self; 2])> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        let sides = [self.bound.x, self.bound.y, self.bound.z];
+        let (axis, extent) = sides
+            .iter()
+            .enumerate()
+            .filter(|(_, extent)| extent.size().is_finite())
+            .max_by(|(_, a), (_, b)| a.size().total_cmp(&b.size()))?;
+        let at = extent.mid();
+
+        let halves = [Interval::new(extent.min, at), Interval::new(at, extent.max)].map(|half| {
+            let mut bound = self.bound;
+            match axis {
+                0 => bound.x = half,
+                1 => bound.y = half,
+                _ => bound.z = half,
+            }
+            Self {
+                bound,
+                depth: self.depth + 1,
+                samples: self.samples / 2,
+                facings: self.facings.each_ref().map(Directions::halved),
+            }
+        });
+        Some((axis, at, halves))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum GuideNode {
+    /// Children below and above `at` along `axis`.
+    Split {
+        axis: usize,
+        at: f64,
+        children: [usize; 2],
+    },
+    Leaf(Box<GuideLeaf>),
+}
+
+/// The tree of [`GuideNode`]s, the first being the root.
+#[derive(Debug, Default)]
+struct GuideTree {
+    nodes: Vec<GuideNode>,
+}
+
+impl GuideTree {
+    /// The index of the leaf holding `position`, or [`None`] if the tree
+    /// hasn't been started.
+    fn leaf_index(&self, position: Vec3) -> Option<usize> {
+        let mut index = 0;
+        loop {
+            match self.nodes.get(index)? {
+                GuideNode::Split { axis, at, children } => {
+                    index = children[usize::from(position[*axis] >= *at)];
+                }
+                GuideNode::Leaf(_) => return Some(index),
+            }
+        }
+    }
+
+    fn leaf(&self, position: Vec3) -> Option<&GuideLeaf> {
+        match &self.nodes[self.leaf_index(position)?] {
+            GuideNode::Leaf(leaf) => Some(leaf),
+            GuideNode::Split { .. } => None,
+        }
+    }
+}
+
+/// A path guide learning where light comes from, see the
+/// [module docs](crate::guide). It learns between passes, so render with
+/// [`RenderOptions::set_passes()`](crate::camera::RenderOptions::set_passes()),
+/// and keeps what it learned between renders, so one guide can be shared by
+/// every frame of a camera moving through a static scene.
+/// [`PathGuide::clear()`] it when the scene changes.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, Background, CameraBuilder, RenderOptions},
+///     guide::PathGuide,
+///     hit_list::HittableList,
+///     material::{DiffuseLight, Lambertian},
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let mut world = HittableList::new();
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, -100.5, -1.0],
+///     radius: 100.0,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// }));
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, 3.0, -1.0],
+///     radius: 1.0,
+///     mat: Box::new(DiffuseLight::new(vec3![10.0, 10.0, 10.0])),
+/// }));
+///
+/// let guide = PathGuide::new().set_split_samples(256);
+/// let camera = CameraBuilder::default()
+///     .set_image_width(16)
+///     .set_anti_aliasing(AntiAliasing::Random(64))
+///     .set_background(Background::Solid(vec3![0.0, 0.0, 0.0]))
+///     .build().unwrap();
+/// let _film = camera.render_film_with(
+///     &world,
+///     RenderOptions::default().set_passes(4).set_path_guide(&guide),
+/// );
+///
+/// // the ground is split up where the camera sees it
+/// assert!(guide.leaves() > 1);
+///
+/// // and learns the light is straight up
+/// let floor = vec3![0.0, -0.5, -1.0];
+/// let (up, across) = (vec3![0.0, 1.0, 0.0], vec3![1.0, 0.0, 0.0]);
+/// let towards_light = guide.pdf(floor, up, up).unwrap();
+/// let sideways = guide.pdf(floor, up, across).unwrap();
+/// assert!(towards_light > 1.0 / (4.0 * std::f64::consts::PI));
+/// assert!(towards_light > sideways);
+/// ```
+#[derive(Debug)]
+pub struct PathGuide {
+    fraction: f64,
+    split_samples: u32,
+    tree: RwLock<GuideTree>,
+}
+
+impl Default for PathGuide {
+    fn default() -> Self {
+        Self {
+            fraction: 0.5,
+            split_samples: 4000,
+            tree: RwLock::default(),
+        }
+    }
+}
+
+impl PathGuide {
+    /// Create a new [`PathGuide`] that has learned nothing, guiding half of
+    /// the bounces and halving a region once `4000` bounces have been
+    /// recorded in it.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the `fraction` of bounces that follow the learned distribution
+    /// rather than the surface's own, where one has been learned. Keeping
+    /// some unguided covers what the guide hasn't found.
+    ///
+    /// # Panics
+    ///
+    /// If `fraction` isn't within `0.0..=1.0`.
+    #[must_use]
+    pub fn set_guided_fraction(self, fraction: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "Guided fraction must be between 0 and 1"
+        );
+        Self { fraction, ..self }
+    }
+
+    /// Set how many bounces a region of space records in a pass before it's
+    /// halved, with each half learning separately from then on. Lower adapts
+    /// to the scene faster but learns each region from fewer, noisier
+    /// samples.
+    ///
+    /// # Panics
+    ///
+    /// If `samples` is `0`.
+    #[must_use]
+    pub fn set_split_samples(self, samples: u32) -> Self {
+        assert!(samples > 0, "Need at least one sample before splitting");
+        Self {
+            split_samples: samples,
+            ..self
+        }
+    }
+
+    /// The number of regions space is split into.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while recording into the guide.
+    #[must_use]
+    pub fn leaves(&self) -> usize {
+        self.read()
+            .nodes
+            .iter()
+            .filter(|node| matches!(node, GuideNode::Leaf(_)))
+            .count()
+    }
+
+    /// Forget everything learned.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while recording into the guide.
+    pub fn clear(&self) {
+        self.tree
+            .write()
+            .expect("path guide lock poisoned")
+            .nodes
+            .clear();
+    }
+
+    /// The learned probability density, per steradian over the whole sphere,
+    /// of bouncing off a surface at `position` facing `normal` towards the
+    /// unit `direction`. The light arriving is weighted by the cosine to the
+    /// surfaces it was recorded on, so light skimming past is learned less.
+    /// [`None`] until a pass has been learned there.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while recording into the guide.
+    #[must_use]
+    pub fn pdf(&self, position: Vec3, normal: Vec3, direction: Vec3) -> Option<f64> {
+        let tree = self.read();
+        let directions = &tree.leaf(position)?.facings[facing(normal)];
+        directions.is_learned().then(|| directions.pdf(direction))
+    }
+
+    /// Start learning over `bound` if nothing has been learned yet.
+    pub(crate) fn prepare(&self, bound: Aabb) {
+        let mut tree = self.tree.write().expect("path guide lock poisoned");
+        if tree.nodes.is_empty() {
+            tree.nodes
+                .push(GuideNode::Leaf(Box::new(GuideLeaf::new(bound, 0))));
+        }
+    }
+
+    /// Choose a direction to bounce off a Lambertian surface at `position`
+    /// facing `normal`, from the learned distribution or the cosine lobe.
+    /// [`None`] if the direction chosen is below the surface.
+    pub(crate) fn bounce(
+        &self,
+        position: Vec3,
+        normal: Vec3,
+        rng: &mut SmallRng,
+    ) -> Option<GuidedBounce> {
+        let tree = self.read();
+        let facing = facing(normal);
+        let learned = tree
+            .leaf(position)
+            .map(|leaf| &leaf.facings[facing])
+            .filter(|directions| directions.is_learned());
+        let fraction = if learned.is_some() {
+            self.fraction
+        } else {
+            0.0
+        };
+
+        let direction = match learned {
+            Some(directions) if rng.random_range(0.0..1.0) < fraction => directions.sample(rng),
+            _ => cosine_direction(normal, rng),
+        };
+        let cos = dot(&direction, &normal);
+        if cos <= 0.0 {
+            return None;
+        }
+
+        // one-sample multiple importance sampling over both strategies
+        let pdf = (1.0 - fraction) * cos / PI
+            + learned.map_or(0.0, |directions| fraction * directions.pdf(direction));
+        (pdf > 0.0).then_some(GuidedBounce {
+            direction,
+            weight: cos / PI / pdf,
+            facing,
+            cos,
+            pdf,
+        })
+    }
+
+    /// Add the `samples` from a thread to the histograms.
+    pub(crate) fn record(&self, samples: &[GuideSample]) {
+        if samples.is_empty() {
+            return;
+        }
+        let mut tree = self.tree.write().expect("path guide lock poisoned");
+        for sample in samples {
+            let Some(index) = tree.leaf_index(sample.position) else {
+                return;
+            };
+            if let GuideNode::Leaf(leaf) = &mut tree.nodes[index] {
+                leaf.samples += 1;
+                if sample.weight.is_finite() && sample.weight > 0.0 {
+                    leaf.facings[sample.facing].add(sample.direction, sample.weight);
+                }
+            }
+        }
+    }
+
+    /// Learn the distributions for the next pass from the histograms, and
+    /// halve the regions that recorded enough samples.
+    pub(crate) fn refine(&self) {
+        let mut tree = self.tree.write().expect("path guide lock poisoned");
+        let mut unchecked: Vec<usize> = (0..tree.nodes.len()).collect();
+        for &index in &unchecked {
+            if let GuideNode::Leaf(leaf) = &mut tree.nodes[index] {
+                leaf.facings.iter_mut().for_each(Directions::learn);
+            }
+        }
+
+        // keep halving, assuming the samples were spread evenly, until each
+        // region recorded too few to split
+        while let Some(index) = unchecked.pop() {
+            let GuideNode::Leaf(leaf) = &mut tree.nodes[index] else {
+                continue;
+            };
+            let halves = if leaf.samples < self.split_samples {
+                None
+            } else {
+                leaf.split()
+            };
+            let Some((axis, at, [below, above])) = halves else {
+                leaf.samples = 0;
+                continue;
+            };
+
+            let children = [tree.nodes.len(), tree.nodes.len() + 1];
+            tree.nodes[index] = GuideNode::Split { axis, at, children };
+            tree.nodes.push(GuideNode::Leaf(Box::new(below)));
+            tree.nodes.push(GuideNode::Leaf(Box::new(above)));
+            unchecked.extend(children);
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, GuideTree> {
+        self.tree.read().expect("path guide lock poisoned")
+    }
+}
@@ -2,12 +2,15 @@
 //! when a [ray](Ray) hits a surface.
 
 use core::fmt;
+use std::sync::Arc;
 
 use crate::{
     aabb::Aabb,
+    interval,
     interval::Interval,
     material::Material,
     ray::Ray,
+    tolerance::Tolerances,
     vec3::{dot, Vec3},
 };
 
@@ -24,6 +27,22 @@ pub struct HitRecord<'a> {
     pub t: f64,
     /// Whether the hit was on the front face or not.
     pub front_face: bool,
+    /// The horizontal texture coordinate of the hit, or `0.0` if the surface
+    /// doesn't have texture coordinates.
+    pub u: f64,
+    /// The vertical texture coordinate of the hit, or `0.0` if the surface
+    /// doesn't have texture coordinates.
+    pub v: f64,
+    /// The name of the object that was hit, if it was given one with
+    /// [`Named`](crate::stats::Named).
+    pub object: Option<&'a str>,
+    /// The index of the render layer the object that was hit is on, if it
+    /// was put on one with [`OnLayer`](crate::layers::OnLayer).
+    pub layer: Option<usize>,
+    /// The direction of increasing [`u`](field@HitRecord::u) along the
+    /// surface, for anisotropic materials, if the surface has one. It isn't
+    /// necessarily normalised or exactly perpendicular to the normal.
+    pub tangent: Option<Vec3>,
 }
 
 impl HitRecord<'_> {
@@ -42,6 +61,11 @@ impl HitRecord<'_> {
     ///     mat: &mat,
     ///     t: 1.0,
     ///     front_face: true,
+    ///     u: 0.0,
+    ///     v: 0.0,
+    ///     object: None,
+    ///     layer: None,
+    ///     tangent: None,
     /// };
     ///
     /// rec.set_face_norm(
@@ -76,4 +100,81 @@ pub trait Hittable: fmt::Debug + Sync + Send {
 
     /// Get the bounds of a [`Hittable`] object as an [`Aabb`].
     fn bound(&self) -> Aabb;
+
+    /// Get the point on the surface closest to `point`, or [`None`] if the
+    /// object doesn't support distance queries.
+    fn closest_point(&self, _point: Vec3) -> Option<Vec3> {
+        None
+    }
+
+    /// Get the distance from `point` to the closest point on the surface, see
+    /// [`Hittable::closest_point()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{hit::Hittable, material::Lambertian, sphere::Sphere, vec3, vec3::Vec3};
+    ///
+    /// let sphere = Sphere {
+    ///     centre: vec3![0.0, 0.0, 0.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// };
+    ///
+    /// assert_eq!(sphere.distance(vec3![0.0, 3.0, 0.0]), Some(2.0));
+    /// ```
+    fn distance(&self, point: Vec3) -> Option<f64> {
+        self.closest_point(point).map(|c| (c - point).length())
+    }
+
+    /// Move the object by `offset` in place, returning whether it could be
+    /// moved. Used to re-centre a scene on the camera, see
+    /// [`Camera::recentre()`](crate::camera::Camera::recentre()). Objects
+    /// can't be moved by default.
+    fn translate(&mut self, _offset: Vec3) -> bool {
+        false
+    }
+
+    /// Intersect and bound the object with `tolerances` from now on, in
+    /// place of the [defaults](Tolerances::DEFAULT) it was built with. A
+    /// [`Scene`](crate::scene::Scene) sets those of its settings on every
+    /// object added to it. Objects that don't use any tolerances ignore them.
+    fn set_tolerances(&mut self, _tolerances: &Tolerances) {}
+
+    /// Find the nearest surface hit anywhere along the [ray](Ray) in front of
+    /// its origin, without needing an [interval](Interval).
+    fn nearest_hit_along(&self, r: &Ray) -> Option<HitRecord<'_>> {
+        self.hit(r, interval![0.0, f64::INFINITY])
+    }
+}
+
+/// Shared objects are [hittable](Hittable) too, so one copy of a large [`Mesh`](crate::mesh::Mesh)
+/// can be placed many times, for example with [`Animated`](crate::transform::Animated).
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        (**self).hit(r, ray_t)
+    }
+
+    fn bound(&self) -> Aabb {
+        (**self).bound()
+    }
+
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        (**self).closest_point(point)
+    }
+
+    /// Only moves the object if this is its only reference, as otherwise it
+    /// would move for everyone sharing it.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        Arc::get_mut(self).is_some_and(|h| h.translate(offset))
+    }
+
+    /// Only sets the tolerances if this is the only reference, like
+    /// [`Hittable::translate()`], so shared objects keep the ones they were
+    /// given before being shared.
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        if let Some(h) = Arc::get_mut(self) {
+            h.set_tolerances(tolerances);
+        }
+    }
 }
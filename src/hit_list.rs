@@ -7,6 +7,8 @@ use crate::{
     interval,
     interval::Interval,
     ray::Ray,
+    tolerance::Tolerances,
+    vec3::Vec3,
 };
 
 /// The [`HittableList`] struct itself. A [`Hittable`] abstraction over a [`Vec<Box<dyn Hittable>>`].
@@ -138,4 +140,35 @@ impl Hittable for HittableList {
         }
         aabb
     }
+
+    /// Moves every object in the [`HittableList`], returning whether they all
+    /// could be.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        // no short-circuiting, every object should still be moved
+        let mut all = true;
+        for object in &mut self.objects {
+            all &= object.translate(offset);
+        }
+        all
+    }
+
+    /// Sets the tolerances of every object in the [`HittableList`].
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        for object in &mut self.objects {
+            object.set_tolerances(tolerances);
+        }
+    }
+
+    /// Returns the closest point over all of the objects in the
+    /// [`HittableList`] that support distance queries.
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        self.objects
+            .iter()
+            .filter_map(|object| object.closest_point(point))
+            .min_by(|a, b| {
+                (*a - point)
+                    .length_squared()
+                    .total_cmp(&(*b - point).length_squared())
+            })
+    }
 }
@@ -0,0 +1,267 @@
+//! This module contains [`IrradianceCache`], which speeds up diffuse indirect
+//! lighting by computing it properly at a sparse set of surface points and
+//! interpolating in between (Ward et al., "A Ray Tracing Solution for Diffuse
+//! Interreflection", 1988). Set it with
+//! [`RenderOptions::set_irradiance_cache()`](crate::camera::RenderOptions::set_irradiance_cache()).
+//!
+//! The cache is used where a path reaches a diffuse surface after already
+//! bouncing off one, so the first bounce off each surface seen is still path
+//! traced and keeps sharp shadows and direct lighting. Everything after that
+//! comes from the cache, which fills up as the render goes. It's biased, and
+//! too low an accuracy shows as blotches, but it converges far faster in
+//! interiors lit mostly by bounced light.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{vec3, vec3::dot, vec3::Vec3};
+
+/// One place the incoming light was computed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IrradianceRecord {
+    position: Vec3,
+    normal: Vec3,
+    /// The mean incoming radiance, cosine-weighted about the normal.
+    radiance: Vec3,
+    /// The harmonic mean distance to the surfaces around, which sets how far
+    /// the record can be reused.
+    radius: f64,
+}
+
+impl IrradianceRecord {
+    /// How well the record predicts the light at `position` facing `normal`,
+    /// falling off with distance relative to the record's radius and with
+    /// the angle between the normals.
+    fn weight(&self, position: Vec3, normal: Vec3) -> f64 {
+        let apart = (position - self.position).length() / self.radius;
+        let turned = (1.0 - dot(&normal, &self.normal)).max(0.0).sqrt();
+        1.0 / (apart + turned).max(1e-12)
+    }
+}
+
+/// A cache of diffuse indirect lighting, see the
+/// [module docs](crate::irradiance). Records are kept between renders, so
+/// one cache can be reused by every pass, or every frame of a camera moving
+/// through a static scene. [`IrradianceCache::clear()`] it when the scene
+/// changes.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder, RenderOptions},
+///     hit_list::HittableList,
+///     irradiance::IrradianceCache,
+///     material::Lambertian,
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let mut world = HittableList::new();
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, -100.5, -1.0],
+///     radius: 100.0,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// }));
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, 0.0, -1.0],
+///     radius: 0.5,
+///     mat: Box::new(Lambertian::new(vec3![0.7, 0.3, 0.3])),
+/// }));
+///
+/// let cache = IrradianceCache::new().set_samples(16).set_accuracy(0.5);
+/// let camera = CameraBuilder::default()
+///     .set_image_width(16)
+///     .set_anti_aliasing(AntiAliasing::Random(4))
+///     .build().unwrap();
+/// let _film = camera.render_film_with(
+///     &world,
+///     RenderOptions::default().set_irradiance_cache(&cache),
+/// );
+///
+/// // far fewer records than bounces
+/// assert!(!cache.is_empty() && cache.len() < 16 * 16 * 4);
+///
+/// // the sky lights the open ground from above
+/// let light = cache.lookup(vec3![2.0, -0.5, -1.0], vec3![0.0, 1.0, 0.0]);
+/// assert!(light.map_or(true, |light| light[2] > 0.0));
+/// ```
+#[derive(Debug)]
+pub struct IrradianceCache {
+    accuracy: f64,
+    samples: u32,
+    min_spacing: f64,
+    max_spacing: f64,
+    /// The records by the cell of a grid as wide as the furthest any record
+    /// reaches, so lookups only need the cells around them.
+    cells: RwLock<HashMap<[i64; 3], Vec<IrradianceRecord>>>,
+}
+
+impl Default for IrradianceCache {
+    fn default() -> Self {
+        Self {
+            accuracy: 0.25,
+            samples: 64,
+            min_spacing: 0.01,
+            max_spacing: 1.0,
+            cells: RwLock::default(),
+        }
+    }
+}
+
+impl IrradianceCache {
+    /// Create a new, empty [`IrradianceCache`] with an accuracy of `0.25`,
+    /// gathering `64` samples per record, and records `0.01` to `1.0` apart.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how far records are reused, as the largest error allowed. Lower is
+    /// more accurate, with more records. Ward suggests `0.1` to `0.3`.
+    ///
+    /// # Panics
+    ///
+    /// If `accuracy` isn't positive and finite.
+    #[must_use]
+    pub fn set_accuracy(self, accuracy: f64) -> Self {
+        assert!(
+            accuracy > 0.0 && accuracy.is_finite(),
+            "Accuracy must be positive and finite"
+        );
+        Self { accuracy, ..self }
+    }
+
+    /// Set the number of [rays](crate::ray::Ray) traced to compute each
+    /// record.
+    ///
+    /// # Panics
+    ///
+    /// If `samples` is `0`.
+    #[must_use]
+    pub fn set_samples(self, samples: u32) -> Self {
+        assert!(samples > 0, "Need at least one sample per record");
+        Self { samples, ..self }
+    }
+
+    /// Set the range of distances to the surfaces around a record that set
+    /// how far it's reused, which keeps records from bunching up in corners
+    /// or spreading too thinly in open space. In scene units.
+    ///
+    /// # Panics
+    ///
+    /// If `min` isn't positive or `max` is less than `min`.
+    #[must_use]
+    pub fn set_spacing(self, min: f64, max: f64) -> Self {
+        assert!(min > 0.0 && max >= min, "Need 0 < min <= max spacing");
+        Self {
+            min_spacing: min,
+            max_spacing: max,
+            ..self
+        }
+    }
+
+    /// The number of [rays](crate::ray::Ray) traced to compute each record.
+    #[must_use]
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// The number of records in the cache.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while adding to the cache.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.read().values().map(Vec::len).sum()
+    }
+
+    /// Whether the cache has no records.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every record.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while adding to the cache.
+    pub fn clear(&self) {
+        self.cells
+            .write()
+            .expect("irradiance cache lock poisoned")
+            .clear();
+    }
+
+    /// The mean incoming radiance, cosine-weighted about `normal`, at
+    /// `position` interpolated from the records nearby. [`None`] if there
+    /// aren't any close enough to meet the accuracy.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while adding to the cache.
+    #[must_use]
+    pub fn lookup(&self, position: Vec3, normal: Vec3) -> Option<Vec3> {
+        let cells = self.read();
+        let [x, y, z] = self.cell(position);
+
+        let mut total = vec3![0.0, 0.0, 0.0];
+        let mut weights = 0.0;
+        for cell in
+            (-1..=1).flat_map(|i| (-1..=1).flat_map(move |j| (-1..=1).map(move |k| [i, j, k])))
+        {
+            let Some(records) = cells.get(&[x + cell[0], y + cell[1], z + cell[2]]) else {
+                continue;
+            };
+            for record in records {
+                // skip records in front of the point, which can see light it
+                // can't
+                let ahead = dot(&(position - record.position), &(normal + record.normal));
+                if ahead < -0.1 * record.radius {
+                    continue;
+                }
+                let weight = record.weight(position, normal);
+                if weight > 1.0 / self.accuracy {
+                    total += record.radiance * weight;
+                    weights += weight;
+                }
+            }
+        }
+
+        (weights > 0.0).then(|| total / weights)
+    }
+
+    /// Add a record of the `radiance` at `position` facing `normal`, with
+    /// surfaces an average (harmonic mean) `distance` away.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while adding to the cache.
+    pub fn insert(&self, position: Vec3, normal: Vec3, radiance: Vec3, distance: f64) {
+        let record = IrradianceRecord {
+            position,
+            normal,
+            radiance,
+            radius: distance.clamp(self.min_spacing, self.max_spacing),
+        };
+        self.cells
+            .write()
+            .expect("irradiance cache lock poisoned")
+            .entry(self.cell(position))
+            .or_default()
+            .push(record);
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<[i64; 3], Vec<IrradianceRecord>>> {
+        self.cells.read().expect("irradiance cache lock poisoned")
+    }
+
+    /// The grid cell holding `position`.
+    fn cell(&self, position: Vec3) -> [i64; 3] {
+        let size = self.max_spacing * self.accuracy;
+        #[expect(clippy::cast_possible_truncation)]
+        [0, 1, 2].map(|i| (position[i] / size).floor() as i64)
+    }
+}
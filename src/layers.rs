@@ -0,0 +1,366 @@
+//! This module contains [`RenderLayers`], for putting the objects of a scene
+//! on named layers (such as `"foreground"`, `"background"` and `"fx"`) that
+//! can be switched on and off, and the [`LayerFilm`], which splits a render
+//! into an image per layer in the same pass, for compositing.
+//!
+//! Each camera sample goes to the layer of the first thing it sees, so the
+//! images have the full lighting of the whole scene, shadows and reflections
+//! of other layers included, and add up to the render. What's on no layer and
+//! the background go to the [rest](LayerFilm::rest()).
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use crate::{
+    aabb::Aabb,
+    film::FilmBuffer,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    tolerance::Tolerances,
+    vec3::Vec3,
+};
+
+/// A named layer and whether it's enabled, shared with the objects on it.
+#[derive(Debug, Clone)]
+struct Layer {
+    name: String,
+    enabled: Arc<AtomicBool>,
+}
+
+/// A set of named render layers. Objects are put on a layer with
+/// [`RenderLayers::assign()`], and disabling the layer hides them from every
+/// [ray](Ray), without rebuilding anything. Clones share the enable flags.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable, layers::RenderLayers, material::Lambertian, ray, ray::Ray, sphere::Sphere,
+///     vec3, vec3::Vec3,
+/// };
+///
+/// let mut layers = RenderLayers::new();
+/// let ball = layers.assign(
+///     "fx",
+///     Sphere {
+///         centre: vec3![0.0, 0.0, -2.0],
+///         radius: 0.5,
+///         mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+///     },
+/// );
+///
+/// let r = ray!(vec3![0.0, 0.0, 0.0], vec3![0.0, 0.0, -1.0]);
+/// assert_eq!(ball.nearest_hit_along(&r).unwrap().layer, layers.index_of("fx"));
+///
+/// assert!(layers.set_enabled("fx", false));
+/// assert!(ball.nearest_hit_along(&r).is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RenderLayers {
+    layers: Vec<Layer>,
+}
+
+impl RenderLayers {
+    /// Create a new [`RenderLayers`] without any layers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The index of the layer called `name`, adding it, enabled, if there
+    /// isn't one.
+    pub fn layer(&mut self, name: &str) -> usize {
+        self.index_of(name).unwrap_or_else(|| {
+            self.layers.push(Layer {
+                name: name.to_string(),
+                enabled: Arc::new(AtomicBool::new(true)),
+            });
+            self.layers.len() - 1
+        })
+    }
+
+    /// Put `child` on the layer called `name`, adding the layer if there
+    /// isn't one.
+    pub fn assign<H: Hittable>(&mut self, name: &str, child: H) -> OnLayer<H> {
+        let index = self.layer(name);
+        OnLayer {
+            index,
+            enabled: Arc::clone(&self.layers[index].enabled),
+            child,
+        }
+    }
+
+    /// The index of the layer called `name`, if there is one.
+    #[must_use]
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    /// Enable or disable the layer called `name`, for everything sharing it.
+    /// Returns whether there's a layer called `name`.
+    #[must_use]
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        self.layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .map(|layer| layer.enabled.store(enabled, Ordering::Relaxed))
+            .is_some()
+    }
+
+    /// Whether the layer called `name` is enabled, or [`None`] if there's no
+    /// such layer.
+    #[must_use]
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .map(|layer| layer.enabled.load(Ordering::Relaxed))
+    }
+
+    /// The names of the layers, in the order of their indices.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|layer| layer.name.as_str())
+    }
+
+    /// The number of layers.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Whether there are no layers.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+/// An object on a render layer, made with [`RenderLayers::assign()`]. Its
+/// hits report the layer's index, and it can't be hit while the layer is
+/// disabled. When layers are nested, the innermost is kept.
+#[derive(Debug)]
+pub struct OnLayer<H: Hittable> {
+    index: usize,
+    enabled: Arc<AtomicBool>,
+    child: H,
+}
+
+impl<H: Hittable> OnLayer<H> {
+    /// The index of the layer the object is on.
+    #[must_use]
+    pub fn layer(&self) -> usize {
+        self.index
+    }
+
+    /// Whether the layer the object is on is enabled.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+}
+
+impl<H: Hittable> Hittable for OnLayer<H> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        if !self.is_enabled() {
+            return None;
+        }
+        self.child.hit(r, ray_t).map(|rec| HitRecord {
+            layer: rec.layer.or(Some(self.index)),
+            ..rec
+        })
+    }
+
+    /// The bounds of the object, even while the layer is disabled, so it can
+    /// be enabled again without rebuilding any [`BVHTree`](crate::bvh::BVHTree).
+    fn bound(&self) -> Aabb {
+        self.child.bound()
+    }
+
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        if self.is_enabled() {
+            self.child.closest_point(point)
+        } else {
+            None
+        }
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.child.translate(offset)
+    }
+
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.child.set_tolerances(tolerances);
+    }
+}
+
+/// The sums and counts of the samples of each pixel on each layer.
+#[derive(Debug, Default)]
+struct LayerSums {
+    width: u32,
+    height: u32,
+    /// The sum of each slot's samples in each pixel, slot by slot.
+    sums: Vec<Vec3>,
+    /// The number of samples in each pixel, over every slot.
+    counts: Vec<u32>,
+}
+
+/// A render split into an image per layer of some [`RenderLayers`], filled
+/// by a render it's [set on](crate::camera::RenderOptions::set_layers()).
+/// Each image has the samples that first saw something on its layer, over
+/// every sample of the pixel, so the images and the
+/// [rest](LayerFilm::rest()) add up to the render. Firefly filtering isn't
+/// applied, and with [`DepthOfField::Post`](crate::camera::DepthOfField::Post)
+/// the layers aren't blurred.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder, RenderOptions},
+///     hit_list::HittableList,
+///     layers::{LayerFilm, RenderLayers},
+///     material::Lambertian,
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let mut layers = RenderLayers::new();
+/// let mut world = HittableList::new();
+/// world.add(Box::new(layers.assign(
+///     "foreground",
+///     Sphere {
+///         centre: vec3![0.0, 0.0, -1.0],
+///         radius: 0.5,
+///         mat: Box::new(Lambertian::new(vec3![0.8, 0.2, 0.2])),
+///     },
+/// )));
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, -100.5, -1.0],
+///     radius: 100.0,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// }));
+///
+/// let camera = CameraBuilder::default()
+///     .set_image_width(16)
+///     .set_anti_aliasing(AntiAliasing::Random(8))
+///     .build().unwrap();
+/// let split = LayerFilm::new(&layers);
+/// let film = camera.render_film_with(&world, RenderOptions::default().set_layers(&split));
+///
+/// let foreground = split.film("foreground").unwrap();
+/// let rest = split.rest();
+/// // the sphere is in the middle, the corners see the ground or sky
+/// assert!(foreground.get(8, 4).length() > 0.0);
+/// assert_eq!(foreground.get(0, 0), vec3![0.0, 0.0, 0.0]);
+/// // and the layers add back up to the render
+/// let sum = foreground.get(8, 4) + rest.get(8, 4);
+/// assert!((sum - film.get(8, 4)).length() < 1e-9);
+/// ```
+#[derive(Debug, Default)]
+pub struct LayerFilm {
+    names: Vec<String>,
+    sums: Mutex<LayerSums>,
+}
+
+impl LayerFilm {
+    /// Create an empty [`LayerFilm`] for the current layers of `layers`,
+    /// sized by the render it's set on.
+    #[must_use]
+    pub fn new(layers: &RenderLayers) -> Self {
+        Self {
+            names: layers.names().map(str::to_string).collect(),
+            sums: Mutex::new(LayerSums::default()),
+        }
+    }
+
+    /// The number of slots per pixel, one per layer and one for the rest.
+    pub(crate) fn slots(&self) -> usize {
+        self.names.len() + 1
+    }
+
+    /// The slot samples first seeing something on `layer` go to.
+    pub(crate) fn slot(&self, layer: Option<usize>) -> usize {
+        layer
+            .filter(|&index| index < self.names.len())
+            .unwrap_or(self.names.len())
+    }
+
+    /// Clear the film for a `width` by `height` render.
+    pub(crate) fn reset(&self, width: u32, height: u32) {
+        let pixels = width as usize * height as usize;
+        *self.sums.lock().expect("layer film lock poisoned") = LayerSums {
+            width,
+            height,
+            sums: vec![Vec3::default(); pixels * self.slots()],
+            counts: vec![0; pixels],
+        };
+    }
+
+    /// Add the samples of pixels in row `j`, each given as `(i, count, sums)`
+    /// with a sum per slot.
+    pub(crate) fn add_row(&self, j: u32, pixels: &[(u32, u32, Vec<Vec3>)]) {
+        let slots = self.slots();
+        let mut film = self.sums.lock().expect("layer film lock poisoned");
+        let width = film.width as usize;
+        for (i, count, sums) in pixels {
+            let idx = j as usize * width + *i as usize;
+            film.counts[idx] += count;
+            for (acc, sum) in film.sums[idx * slots..(idx + 1) * slots]
+                .iter_mut()
+                .zip(sums)
+            {
+                *acc += *sum;
+            }
+        }
+    }
+
+    /// The image of one slot, each pixel's sum over its samples.
+    fn slot_film(&self, slot: usize) -> FilmBuffer {
+        let slots = self.slots();
+        let film = self.sums.lock().expect("layer film lock poisoned");
+        let mut out = FilmBuffer::new(film.width, film.height);
+        for (idx, &count) in film.counts.iter().enumerate() {
+            if count > 0 {
+                #[expect(clippy::cast_possible_truncation)]
+                let (i, j) = (
+                    (idx % film.width as usize) as u32,
+                    (idx / film.width as usize) as u32,
+                );
+                out.set(i, j, film.sums[idx * slots + slot] / f64::from(count));
+            }
+        }
+        out
+    }
+
+    /// The image of the layer called `name`, or [`None`] if the
+    /// [`RenderLayers`] the film was made for had no such layer.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while adding to the film.
+    #[must_use]
+    pub fn film(&self, name: &str) -> Option<FilmBuffer> {
+        let slot = self.names.iter().position(|layer| layer == name)?;
+        Some(self.slot_film(slot))
+    }
+
+    /// The image of everything on no layer, or on a layer added after the
+    /// film was made, and the background.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while adding to the film.
+    #[must_use]
+    pub fn rest(&self) -> FilmBuffer {
+        self.slot_film(self.names.len())
+    }
+
+    /// The names of the layers with images, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+}
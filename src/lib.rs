@@ -1,13 +1,56 @@
 pub mod aabb;
+pub mod bake;
+pub mod bench;
 pub mod bvh;
 pub mod camera;
+pub mod camera_path;
+pub mod compare;
+pub mod cpu;
+pub mod csg;
+pub mod disc;
+pub mod displace;
+pub mod dof;
+pub mod environment;
+pub mod film;
+pub mod fractal;
+pub mod furnace;
+pub mod gltf;
+pub mod ground;
+pub mod guide;
 pub mod hit;
 pub mod hit_list;
 pub mod interval;
+pub mod irradiance;
+pub mod layers;
+pub mod lut;
+pub mod map;
 pub mod material;
 pub mod mesh;
+pub mod noise;
+pub mod plane;
+pub mod point_cloud;
+pub mod preview;
+pub mod principled;
+pub mod probes;
+pub mod pyramid;
+pub mod quad;
 pub mod ray;
+pub mod scene;
+pub mod sdf;
+pub mod server;
+pub mod shader;
+pub mod sheet;
 pub mod sphere;
+pub mod stats;
+pub mod texture;
+pub mod thin_film;
+pub mod tolerance;
+pub mod toon;
+pub mod torus;
+pub mod transform;
 pub mod triangle;
+pub mod usd;
 pub mod utils;
 pub mod vec3;
+pub mod vegetation;
+pub mod volume;
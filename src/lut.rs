@@ -0,0 +1,259 @@
+//! This module contains the [`Lut`] struct, a 1D or 3D colour lookup table
+//! applied to display-encoded colours on output, so renders can match a show
+//! LUT or film emulation curve without a separate grading step. LUTs are read
+//! from the Adobe/Resolve `.cube` format or built from a response curve.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{vec3, vec3::Vec3};
+
+/// Whether a [`Lut`] maps each channel separately or the whole colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LutKind {
+    /// A curve per channel, `size` entries long.
+    OneD,
+    /// A `size` by `size` by `size` cube of colours.
+    ThreeD,
+}
+
+/// A colour lookup table, see the [module docs](crate::lut).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    kind: LutKind,
+    size: usize,
+    domain_min: Vec3,
+    domain_max: Vec3,
+    table: Vec<Vec3>,
+}
+
+impl Lut {
+    /// Build a 1D [`Lut`] with `size` entries over `0.0..=1.0` from a response
+    /// curve `f`, applied to each channel.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is less than `2`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{lut::Lut, vec3, vec3::Vec3};
+    ///
+    /// // a film-like S-curve
+    /// let curve = Lut::from_curve(256, |x| x * x * (3.0 - 2.0 * x));
+    ///
+    /// assert_eq!(curve.apply(vec3![0.0, 0.5, 1.0]), vec3![0.0, 0.5, 1.0]);
+    /// assert!(curve.apply(vec3![0.25, 0.25, 0.25])[0] < 0.25);
+    /// ```
+    #[must_use]
+    pub fn from_curve<F: Fn(f64) -> f64>(size: usize, f: F) -> Self {
+        assert!(size >= 2, "A LUT needs at least 2 entries");
+
+        #[expect(clippy::cast_precision_loss)]
+        let table = (0..size)
+            .map(|i| {
+                let y = f(i as f64 / (size - 1) as f64);
+                vec3![y, y, y]
+            })
+            .collect();
+
+        Self {
+            kind: LutKind::OneD,
+            size,
+            domain_min: vec3![0.0, 0.0, 0.0],
+            domain_max: vec3![1.0, 1.0, 1.0],
+            table,
+        }
+    }
+
+    /// Load a [`Lut`] from a `.cube` file, see [`Lut::parse_cube()`].
+    ///
+    /// # Errors
+    ///
+    /// If reading the file fails or it isn't a valid `.cube` file.
+    pub fn from_cube<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Self::parse_cube(&text).with_context(|| format!("Invalid LUT {}", path.display()))
+    }
+
+    /// Parse the text of a `.cube` file, with either `LUT_1D_SIZE` or
+    /// `LUT_3D_SIZE` and optional `DOMAIN_MIN`/`DOMAIN_MAX`. `TITLE` and
+    /// unknown keywords are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the size is missing or invalid, a line can't be parsed, or the
+    /// number of entries doesn't match the size.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{lut::{Lut, LutKind}, vec3, vec3::Vec3};
+    ///
+    /// // a 3D LUT that inverts colours
+    /// let cube = "
+    /// TITLE \"invert\"
+    /// LUT_3D_SIZE 2
+    /// 1 1 1
+    /// 0 1 1
+    /// 1 0 1
+    /// 0 0 1
+    /// 1 1 0
+    /// 0 1 0
+    /// 1 0 0
+    /// 0 0 0
+    /// ";
+    ///
+    /// let lut = Lut::parse_cube(cube).unwrap();
+    ///
+    /// assert_eq!(lut.kind(), LutKind::ThreeD);
+    /// assert_eq!(lut.apply(vec3![1.0, 0.0, 0.0]), vec3![0.0, 1.0, 1.0]);
+    /// assert_eq!(lut.apply(vec3![0.25, 0.5, 0.75]), vec3![0.75, 0.5, 0.25]);
+    /// ```
+    pub fn parse_cube(text: &str) -> Result<Self> {
+        fn parse_triple<'a, I: Iterator<Item = &'a str>>(parts: I) -> Result<Vec3> {
+            let values = parts
+                .map(str::parse::<f64>)
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.len() != 3 {
+                bail!("Expected 3 values, found {}", values.len());
+            }
+            Ok(vec3![values[0], values[1], values[2]])
+        }
+
+        let mut kind = None;
+        let mut domain_min = vec3![0.0, 0.0, 0.0];
+        let mut domain_max = vec3![1.0, 1.0, 1.0];
+        let mut table = vec![];
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            // comments and empties
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let keyword = parts.next().unwrap_or_default();
+            let result = match keyword {
+                "LUT_1D_SIZE" | "LUT_3D_SIZE" => parts
+                    .next()
+                    .unwrap_or_default()
+                    .parse::<usize>()
+                    .map(|size| {
+                        let lut_kind = if keyword == "LUT_1D_SIZE" {
+                            LutKind::OneD
+                        } else {
+                            LutKind::ThreeD
+                        };
+                        kind = Some((lut_kind, size));
+                    })
+                    .map_err(anyhow::Error::from),
+                "DOMAIN_MIN" => parse_triple(parts).map(|v| domain_min = v),
+                "DOMAIN_MAX" => parse_triple(parts).map(|v| domain_max = v),
+                k if k.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => {
+                    parse_triple(line.split_whitespace()).map(|v| table.push(v))
+                }
+                // TITLE and anything else we don't use
+                _ => Ok(()),
+            };
+            result.with_context(|| format!("Line {}: {line}", line_no + 1))?;
+        }
+
+        let Some((kind, size)) = kind else {
+            bail!("Missing LUT_1D_SIZE or LUT_3D_SIZE");
+        };
+        if size < 2 {
+            bail!("LUT size must be at least 2, found {size}");
+        }
+
+        let expected = match kind {
+            LutKind::OneD => size,
+            LutKind::ThreeD => size * size * size,
+        };
+        if table.len() != expected {
+            bail!("Expected {expected} LUT entries, found {}", table.len());
+        }
+
+        Ok(Self {
+            kind,
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    /// Whether the [`Lut`] is 1D or 3D.
+    #[must_use]
+    pub fn kind(&self) -> LutKind {
+        self.kind
+    }
+
+    /// The number of entries along each axis.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Look up `colour`, interpolating linearly (1D) or trilinearly (3D)
+    /// between entries. Values outside the domain are clamped to it.
+    #[must_use]
+    pub fn apply(&self, colour: Vec3) -> Vec3 {
+        // position of each channel in table entries, and the entries around it
+        let mut lo = [0; 3];
+        let mut hi = [0; 3];
+        let mut frac = [0.0; 3];
+        #[expect(clippy::cast_precision_loss)]
+        let max_idx = (self.size - 1) as f64;
+        for axis in 0..3 {
+            let range = self.domain_max[axis] - self.domain_min[axis];
+            let x = ((colour[axis] - self.domain_min[axis]) / range).clamp(0.0, 1.0) * max_idx;
+
+            #[expect(clippy::cast_possible_truncation)]
+            #[expect(clippy::cast_sign_loss)]
+            let i = (x.floor() as usize).min(self.size - 2);
+            lo[axis] = i;
+            hi[axis] = i + 1;
+            #[expect(clippy::cast_precision_loss)]
+            let f = x - i as f64;
+            frac[axis] = f;
+        }
+
+        match self.kind {
+            LutKind::OneD => {
+                let mut out = Vec3::default();
+                for axis in 0..3 {
+                    let a = self.table[lo[axis]][axis];
+                    let b = self.table[hi[axis]][axis];
+                    out[axis] = a + (b - a) * frac[axis];
+                }
+                out
+            }
+            LutKind::ThreeD => {
+                let mut out = Vec3::default();
+                for corner in 0..8 {
+                    let mut idx = [0; 3];
+                    let mut weight = 1.0;
+                    for axis in 0..3 {
+                        if corner >> axis & 1 == 1 {
+                            idx[axis] = hi[axis];
+                            weight *= frac[axis];
+                        } else {
+                            idx[axis] = lo[axis];
+                            weight *= 1.0 - frac[axis];
+                        }
+                    }
+                    // red changes fastest in .cube files
+                    let i = idx[0] + self.size * (idx[1] + self.size * idx[2]);
+                    out += self.table[i] * weight;
+                }
+                out
+            }
+        }
+    }
+}
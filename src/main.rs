@@ -1,17 +1,33 @@
-use std::path::PathBuf;
+use std::{
+    fs,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand};
 use mimalloc::MiMalloc;
 use rand::{
     rngs::{SmallRng, SysRng},
     RngExt as _, SeedableRng,
 };
 use rustrace::{
+    aabb::Aabb,
     bvh::BVHTree,
-    camera::{AntiAliasing, CameraBuilder},
+    camera::{AntiAliasing, CameraBuilder, DepthOfField},
+    compare::{Comparison, DEFAULT_PIXELS_PER_DEGREE},
+    film::{ColourSpace, FilmBuffer, Output},
+    furnace::{furnace_test, FURNACE_RESOLUTION, FURNACE_SAMPLES, MIN_FURNACE_RESOLUTION},
     hit_list::HittableList,
+    interval,
+    interval::Interval,
     material::{Dielectric, Lambertian, Material, Metal},
     mesh::Mesh,
+    point_cloud::PointCloud,
+    probes::ProbeGrid,
+    scene::{Reload, Scene},
     sphere::Sphere,
     vec3,
     vec3::Vec3,
@@ -20,7 +36,550 @@ use rustrace::{
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+#[derive(Debug, Parser)]
+#[command(version, about = "A CPU path tracer")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Render cameras from a RON scene file.
+    Render {
+        /// The scene file to render.
+        scene: PathBuf,
+
+        /// Which named camera to render. Optional if the scene only has one.
+        #[arg(short, long, conflicts_with = "all_cameras")]
+        camera: Option<String>,
+
+        /// Render every camera in the scene, each to `<name>.png`.
+        #[arg(short, long)]
+        all_cameras: bool,
+
+        /// The image to write, or the directory to write into with
+        /// `--all-cameras`. Defaults to `<camera>.png` in the current
+        /// directory. Can be given several times to save the one render to
+        /// each, and a width after an `@` scales that image down, as in
+        /// `-o final.exr -o preview.webp@320`.
+        #[arg(short, long)]
+        output: Vec<Output>,
+
+        /// Quantise 8-bit images exactly rather than dithering them, for
+        /// comparing against reference images.
+        #[arg(long)]
+        no_dither: bool,
+
+        /// Also save the world-space position of the first surface seen
+        /// through each pixel, best as `.exr` to keep the coordinates.
+        #[arg(long, conflicts_with = "all_cameras")]
+        position: Option<PathBuf>,
+
+        /// Also save the first surface seen through each pixel as a PLY
+        /// point cloud, coloured by the render.
+        #[arg(long, conflicts_with = "all_cameras")]
+        points: Option<PathBuf>,
+
+        /// Also save how curved the first surface seen through each pixel
+        /// is, one over its radius, best as `.exr` to keep negative values.
+        #[arg(long, conflicts_with = "all_cameras")]
+        curvature: Option<PathBuf>,
+
+        /// Render in passes, printing a histogram of the image's brightness
+        /// and how much of it clips after each, to catch a bad exposure
+        /// early.
+        #[arg(long, conflicts_with = "all_cameras")]
+        histogram: bool,
+
+        /// Also save the whole image rendered, with the camera's overscan
+        /// that the other images are cropped to the frame from, for
+        /// compositing.
+        #[arg(long, conflicts_with = "all_cameras")]
+        uncropped: Option<PathBuf>,
+    },
+    /// Re-render a camera from a RON scene file whenever the file or its
+    /// meshes change, swapping in edited materials without rebuilding the
+    /// geometry where possible.
+    Watch {
+        /// The scene file to watch.
+        scene: PathBuf,
+
+        /// Which named camera to render. Optional if the scene only has one.
+        #[arg(short, long)]
+        camera: Option<String>,
+
+        /// The preview image, rewritten after every pass.
+        #[arg(short, long, default_value = "preview.png")]
+        output: PathBuf,
+
+        /// How many passes to split the samples into, each a chance to update
+        /// the preview or restart after an edit.
+        #[arg(short, long, default_value_t = 8)]
+        passes: u32,
+
+        /// Approximate depth of field with a post process blur instead of
+        /// sampling the lens, so defocused previews clean up much sooner.
+        #[arg(long)]
+        post_dof: bool,
+    },
+    /// Serve an HTTP/JSON API to submit RON scenes, query their progress and
+    /// fetch the images, rendering them one at a time.
+    Serve {
+        /// The address to listen on.
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// The directory paths in submitted scenes are relative to, and
+        /// can't leave.
+        #[arg(short, long, default_value = ".")]
+        assets: PathBuf,
+    },
+    /// Bake a grid of spherical harmonic irradiance probes filling a RON
+    /// scene's bounds, for game engine global illumination.
+    Probes {
+        /// The scene file to bake.
+        scene: PathBuf,
+
+        /// Which named camera's samples, depth, shutter and background to
+        /// bake with. Optional if the scene only has one.
+        #[arg(short, long)]
+        camera: Option<String>,
+
+        /// How many probes along the x, y and z axes.
+        #[arg(long, num_args = 3, default_values_t = [4, 4, 4], value_parser = clap::value_parser!(u32).range(1..))]
+        counts: Vec<u32>,
+
+        /// The minimum corner of the box to fill, instead of the scene's
+        /// bounds, which are infinite if it has a ground plane.
+        #[arg(long, num_args = 3, allow_negative_numbers = true, requires = "max")]
+        min: Option<Vec<f64>>,
+
+        /// The maximum corner of the box to fill.
+        #[arg(long, num_args = 3, allow_negative_numbers = true, requires = "min")]
+        max: Option<Vec<f64>>,
+
+        /// The file to write, JSON if it ends in `.json` or binary otherwise.
+        #[arg(short, long, default_value = "probes.json")]
+        output: PathBuf,
+    },
+    /// Compare a render against a reference image, printing the RMSE, SSIM
+    /// and FLIP errors between them.
+    Compare {
+        /// The reference image.
+        reference: PathBuf,
+
+        /// The image to compare against it.
+        test: PathBuf,
+
+        /// Save where the images' structure differs, one minus the SSIM.
+        #[arg(long)]
+        ssim_map: Option<PathBuf>,
+
+        /// Save how visible the differences are, the FLIP error.
+        #[arg(long)]
+        flip_map: Option<PathBuf>,
+
+        /// The pixels per degree of vision the images are viewed at, by
+        /// default from 0.7m away on a 24" 4K monitor.
+        #[arg(long, default_value_t = DEFAULT_PIXELS_PER_DEGREE)]
+        pixels_per_degree: f64,
+
+        /// Fail if the mean FLIP error is over this, for golden image tests.
+        #[arg(long)]
+        max_flip: Option<f64>,
+    },
+    /// Render the material of every object in a RON scene file under a
+    /// uniform white background, a white furnace test, and print how much
+    /// energy each gains or loses.
+    Furnace {
+        /// The scene file to read the materials of.
+        scene: PathBuf,
+
+        /// The size of the square image each material is rendered to.
+        #[arg(long, default_value_t = FURNACE_RESOLUTION, value_parser = clap::value_parser!(u32).range(i64::from(MIN_FURNACE_RESOLUTION)..))]
+        resolution: u32,
+
+        /// The samples per pixel of each render.
+        #[arg(long, default_value_t = FURNACE_SAMPLES, value_parser = clap::value_parser!(u16).range(1..))]
+        samples: u16,
+
+        /// Fail if any material gains more than this fraction of the light
+        /// in any channel, to catch materials creating energy.
+        #[arg(long)]
+        max_gain: Option<f64>,
+    },
+    /// List the named cameras in a RON scene file.
+    Cameras {
+        /// The scene file to read.
+        scene: PathBuf,
+    },
+}
+
 fn main() -> Result<()> {
+    match Cli::parse().command {
+        None => render_demo(),
+        Some(Command::Render {
+            scene,
+            camera,
+            all_cameras,
+            output,
+            no_dither,
+            position,
+            points,
+            curvature,
+            histogram,
+            uncropped,
+        }) => {
+            let mut scene = Scene::load(&scene)?;
+            if no_dither {
+                let cameras: Vec<_> = scene
+                    .cameras()
+                    .map(|(name, camera)| (name.to_owned(), camera.clone()))
+                    .collect();
+                for (name, mut camera) in cameras {
+                    camera.dither = false;
+                    scene.add_camera(name, camera);
+                }
+            }
+
+            if all_cameras {
+                let dir = match output.as_slice() {
+                    [] => PathBuf::from("."),
+                    [Output { path, width: None }] => path.clone(),
+                    _ => bail!("--all-cameras takes one directory to write into"),
+                };
+                std::fs::create_dir_all(&dir)?;
+                for path in scene.render_all_cameras(&dir, "png")? {
+                    println!("{}", path.display());
+                }
+                return Ok(());
+            }
+
+            render_camera(
+                &scene,
+                camera,
+                output,
+                &ExtraOutputs {
+                    position: position.as_deref(),
+                    points: points.as_deref(),
+                    curvature: curvature.as_deref(),
+                    uncropped: uncropped.as_deref(),
+                },
+                histogram,
+            )
+        }
+        Some(Command::Watch {
+            scene,
+            camera,
+            output,
+            passes,
+            post_dof,
+        }) => watch(&scene, camera.as_deref(), &output, passes, post_dof),
+        Some(Command::Serve { listen, assets }) => rustrace::server::serve(&listen, assets),
+        Some(Command::Probes {
+            scene,
+            camera,
+            counts,
+            min,
+            max,
+            output,
+        }) => {
+            let scene = Scene::load(&scene)?;
+            let name = match camera {
+                Some(name) => name,
+                None => scene.only_camera()?.to_owned(),
+            };
+            let camera = scene
+                .camera(&name)
+                .ok_or_else(|| anyhow!("No camera named {name:?}"))?;
+
+            let counts = [counts[0], counts[1], counts[2]];
+            let mut grid = match min.zip(max) {
+                Some((min, max)) => {
+                    if min.iter().chain(&max).any(|x| !x.is_finite()) {
+                        bail!("The probe box's corners must be finite");
+                    }
+                    if min.iter().zip(&max).any(|(lo, hi)| lo > hi) {
+                        bail!("The probe box's minimum corner must be below its maximum");
+                    }
+                    let bounds = Aabb {
+                        x: interval![min[0], max[0]],
+                        y: interval![min[1], max[1]],
+                        z: interval![min[2], max[2]],
+                    };
+                    ProbeGrid::new(bounds, counts)
+                }
+                None => ProbeGrid::fitted(scene.world(), counts)?,
+            };
+            camera.bake_probe_grid(&mut grid, scene.world());
+            grid.save(&output)?;
+            println!("{}", output.display());
+            Ok(())
+        }
+        Some(Command::Compare {
+            reference,
+            test,
+            ssim_map,
+            flip_map,
+            pixels_per_degree,
+            max_flip,
+        }) => {
+            let load = |path: &Path| FilmBuffer::load(path, ColourSpace::Srgb);
+            let comparison = Comparison::with_pixels_per_degree(
+                &load(&reference)?,
+                &load(&test)?,
+                pixels_per_degree,
+            )?;
+            println!("RMSE: {:.6}", comparison.rmse);
+            println!("SSIM: {:.6}", comparison.ssim);
+            println!("FLIP: {:.6}", comparison.flip);
+            if let Some(path) = ssim_map {
+                comparison.ssim_map.save_linear(path)?;
+            }
+            if let Some(path) = flip_map {
+                comparison.flip_map.save_linear(path)?;
+            }
+            if let Some(max_flip) = max_flip {
+                if comparison.flip > max_flip {
+                    bail!("The FLIP error {:.6} is over {max_flip}", comparison.flip);
+                }
+            }
+            Ok(())
+        }
+        Some(Command::Furnace {
+            scene,
+            resolution,
+            samples,
+            max_gain,
+        }) => {
+            let mut gaining = 0;
+            for (name, material) in Scene::load_materials(&scene)? {
+                let result = furnace_test(&*material, resolution, samples);
+                println!("{name}: {result}");
+                if max_gain.is_some_and(|max_gain| !result.conserves(max_gain)) {
+                    gaining += 1;
+                }
+            }
+            if gaining > 0 {
+                bail!("{gaining} materials gain more energy than allowed");
+            }
+            Ok(())
+        }
+        Some(Command::Cameras { scene }) => {
+            for (name, camera) in Scene::load(&scene)?.cameras() {
+                println!(
+                    "{name}: {} wide, {:?}",
+                    camera.image_width, camera.anti_aliasing
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The modification times of the scene file and its assets, to poll for
+/// changes.
+fn modified_times(path: &Path, scene: &Scene) -> Vec<Option<SystemTime>> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    std::iter::once(modified(path))
+        .chain(scene.asset_paths().iter().map(|asset| modified(asset)))
+        .collect()
+}
+
+/// How many coarse previews `rustrace watch` shows before the first pass.
+const PREVIEW_LEVELS: u32 = 4;
+
+/// How many passes `rustrace render --histogram` prints a histogram after.
+const HISTOGRAM_PASSES: u32 = 8;
+
+/// How many bins the histograms of `rustrace render --histogram` have.
+const HISTOGRAM_BINS: usize = 16;
+
+/// The files `rustrace render` can save alongside the render.
+struct ExtraOutputs<'a> {
+    /// The world-space positions of the first surfaces seen.
+    position: Option<&'a Path>,
+    /// A point cloud of the positions, coloured by the render.
+    points: Option<&'a Path>,
+    /// The curvature of the first surfaces seen.
+    curvature: Option<&'a Path>,
+    /// The render before any overscan is cropped away.
+    uncropped: Option<&'a Path>,
+}
+
+impl ExtraOutputs<'_> {
+    fn is_empty(&self) -> bool {
+        self.position.is_none()
+            && self.points.is_none()
+            && self.curvature.is_none()
+            && self.uncropped.is_none()
+    }
+}
+
+/// Render `camera`, or the scene's only camera, to `output`, or
+/// `<camera>.png`, along with any of the `extra` outputs, printing a
+/// histogram after each pass if `histogram` is set.
+fn render_camera(
+    scene: &Scene,
+    camera: Option<String>,
+    output: Vec<Output>,
+    extra: &ExtraOutputs<'_>,
+    histogram: bool,
+) -> Result<()> {
+    let name = match camera {
+        Some(name) => name,
+        None => scene.only_camera()?.to_owned(),
+    };
+    let outputs = if output.is_empty() {
+        vec![Output::new(format!("{name}.png"))]
+    } else {
+        output
+    };
+    if extra.is_empty() && !histogram {
+        return scene.render_camera_outputs(&name, &outputs);
+    }
+
+    let camera = scene
+        .camera(&name)
+        .ok_or_else(|| anyhow!("No camera named {name:?}"))?;
+    if outputs
+        .iter()
+        .any(|output| output.path.extension().is_some_and(|ext| ext == "dzi"))
+    {
+        bail!("A Deep Zoom pyramid can't be rendered with --position, --points, --curvature, --histogram or --uncropped");
+    }
+    let mut options = scene.render_options();
+    if histogram {
+        options = options.set_passes(HISTOGRAM_PASSES).after_pass(|film, _| {
+            eprintln!("{}", film.histogram(HISTOGRAM_BINS));
+            ControlFlow::Continue(())
+        });
+    }
+    let uncropped_film = camera.render_film_with(scene.world(), options);
+    if let Some(path) = extra.uncropped {
+        uncropped_film.save(path)?;
+    }
+    let film = camera.crop_overscan(&uncropped_film);
+    film.save_outputs(&outputs)?;
+    if extra.position.is_some() || extra.points.is_some() {
+        let positions = camera.crop_overscan(&camera.render_position(scene.world()));
+        if let Some(path) = extra.position {
+            positions.save_linear(path)?;
+        }
+        if let Some(path) = extra.points {
+            PointCloud::from_films(&positions, &film).save_ply(path)?;
+        }
+    }
+    if let Some(path) = extra.curvature {
+        camera
+            .crop_overscan(&camera.render_curvature(scene.world()))
+            .save_linear(path)?;
+    }
+    Ok(())
+}
+
+/// Render `camera` progressively to `output`, restarting whenever the scene
+/// changes. Runs until interrupted.
+fn watch(
+    path: &Path,
+    camera: Option<&str>,
+    output: &Path,
+    passes: u32,
+    post_dof: bool,
+) -> Result<()> {
+    let mut scene = Scene::load_watched(path)?;
+    let mut seen = modified_times(path, &scene);
+
+    loop {
+        let name = match camera {
+            Some(name) => name,
+            None => scene.only_camera()?,
+        };
+        match scene.camera(name) {
+            Some(camera) => {
+                let mut camera = camera.clone();
+                if post_dof {
+                    camera.depth_of_field = DepthOfField::Post;
+                }
+                // save after every preview and pass, stopping if the scene
+                // changed
+                let save = |film: &FilmBuffer, stage: &str| {
+                    if let Err(e) = camera.crop_overscan(film).save(output) {
+                        eprintln!("Couldn't save preview: {e:#}");
+                    }
+                    eprintln!("{stage} saved to {}", output.display());
+                    if modified_times(path, &scene) == seen {
+                        ControlFlow::Continue(())
+                    } else {
+                        ControlFlow::Break(())
+                    }
+                };
+                let options = scene
+                    .render_options()
+                    .set_preview_levels(PREVIEW_LEVELS)
+                    .after_preview(|film, level| {
+                        save(film, &format!("Preview {}/{PREVIEW_LEVELS}", level + 1))
+                    })
+                    .set_passes(passes)
+                    .after_pass(|film, pass| save(film, &format!("Pass {}/{passes}", pass + 1)));
+                let film = camera.render_film_with(scene.world(), options);
+                // the hook only saw the passes before the blur
+                if post_dof {
+                    if let Err(e) = camera.crop_overscan(&film).save(output) {
+                        eprintln!("Couldn't save preview: {e:#}");
+                    }
+                }
+            }
+            None => eprintln!("No camera named {name:?}, waiting for it to be added"),
+        }
+
+        // wait for an edit that changes something before restarting
+        loop {
+            while modified_times(path, &scene) == seen {
+                thread::sleep(Duration::from_millis(250));
+            }
+
+            // meshes can't be swapped in place, so any change to them means a
+            // full reload
+            let assets_changed = modified_times(path, &scene)[1..] != seen[1..];
+            let reloaded = if assets_changed {
+                Scene::load_watched(path).map(|reloaded| {
+                    scene = reloaded;
+                    Reload {
+                        geometry: true,
+                        ..Reload::default()
+                    }
+                })
+            } else {
+                scene.reload(path)
+            };
+            seen = modified_times(path, &scene);
+
+            match reloaded {
+                Ok(reload) if reload.geometry => eprintln!("Reloaded everything"),
+                Ok(reload) if reload.changed() => eprintln!(
+                    "Reloaded {} material(s){}",
+                    reload.materials,
+                    if reload.cameras {
+                        " and the cameras"
+                    } else {
+                        ""
+                    }
+                ),
+                Ok(_) => continue,
+                Err(e) => {
+                    eprintln!("Keeping the previous scene: {e:#}");
+                    continue;
+                }
+            }
+            break;
+        }
+    }
+}
+
+/// The built-in demo scene, rendered to `output.png` when no command is given.
+fn render_demo() -> Result<()> {
     // Camera setup
     let camera = CameraBuilder::default()
         .set_image_width(300)
@@ -32,7 +591,7 @@ fn main() -> Result<()> {
         .set_look_at(vec3![0.0, 0.0, 0.0])
         .set_v_up(vec3![0.0, 1.0, 0.0])
         .set_defocus_angle(0.6)
-        .build();
+        .build()?;
 
     // Scene
     let mut hit_list = HittableList::default();
@@ -0,0 +1,232 @@
+//! This module contains the [`TopDownMap`] struct, an orthographic view of a
+//! scene straight down the `-Y` axis recording the elevation of the first
+//! surface under each pixel and whether it's in shadow from a sun direction.
+//! It's meant as data for minimaps and terrain analysis rather than as a final
+//! image, so each pixel takes a single ray through its centre.
+
+use anyhow::{anyhow, Result};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    aabb::Aabb,
+    camera::RayBias,
+    film::FilmBuffer,
+    hit::Hittable,
+    interval,
+    interval::Interval,
+    ray,
+    ray::Ray,
+    vec3,
+    vec3::{dot, Vec3},
+};
+
+/// What's under one pixel of a [`TopDownMap`].
+#[derive(Debug, Clone, Copy)]
+struct MapTexel {
+    elevation: f64,
+    shadowed: bool,
+}
+
+/// A top-down elevation and shadow map of a scene, see the
+/// [module docs](crate::map).
+///
+/// Pixel columns run along `+X` and rows along `+Z`, so the top of the map is
+/// the `-Z` edge of the scene.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit_list::HittableList, map::TopDownMap, material::Lambertian, sphere::Sphere,
+///     triangle::Triangle, vec3, vec3::Vec3,
+/// };
+///
+/// let grey = || Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5]));
+/// let corners = [
+///     vec3![-2.0, 0.0, -2.0],
+///     vec3![2.0, 0.0, -2.0],
+///     vec3![2.0, 0.0, 2.0],
+///     vec3![-2.0, 0.0, 2.0],
+/// ];
+///
+/// let mut world = HittableList::new();
+/// world.add(Box::new(Triangle::new([corners[0], corners[1], corners[2]], None, grey())));
+/// world.add(Box::new(Triangle::new([corners[0], corners[2], corners[3]], None, grey())));
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, 0.5, 0.0],
+///     radius: 0.5,
+///     mat: grey(),
+/// }));
+///
+/// // low sun in the +X direction
+/// let map = TopDownMap::render(&world, 8, vec3![1.0, 1.0, 0.0]).unwrap();
+///
+/// assert_eq!((map.width(), map.height()), (8, 8));
+/// assert!(map.elevation_at(4, 4).unwrap() > 0.8);
+/// assert!(map.elevation_at(0, 0).unwrap().abs() < 1e-9);
+///
+/// // the sphere's shadow falls towards -X
+/// assert!(map.in_shadow(2, 4));
+/// assert!(!map.in_shadow(7, 4));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TopDownMap {
+    width: u32,
+    height: u32,
+    area: Aabb,
+    texels: Vec<Option<MapTexel>>,
+}
+
+impl TopDownMap {
+    /// Render a [`TopDownMap`] covering the bounds of `world`, with the longer
+    /// horizontal side `resolution` pixels long and square pixels. `sun` is the
+    /// direction towards the sun, and surfaces facing away from it count as
+    /// shadowed.
+    ///
+    /// # Errors
+    ///
+    /// If `world` is empty or unbounded.
+    ///
+    /// # Panics
+    ///
+    /// If `resolution` is zero or `sun` is zero.
+    pub fn render(world: &dyn Hittable, resolution: u32, sun: Vec3) -> Result<Self> {
+        assert!(resolution > 0, "Map resolution must be at least 1");
+        assert!(!sun.near_zero(), "Sun direction must be non-zero");
+
+        let area = world.bound();
+        let extent = area.x.size().max(area.z.size());
+        if !extent.is_finite() || extent < 0.0 || area.y.size() < 0.0 {
+            return Err(anyhow!("Scene must be bounded to render a map."));
+        }
+
+        // square pixels, with at least one along the shorter side
+        let pixel_size = extent / f64::from(resolution);
+        let pixels_along = |size: f64| {
+            #[expect(clippy::cast_possible_truncation)]
+            #[expect(clippy::cast_sign_loss)]
+            let n = (size / pixel_size).round() as u32;
+            n.clamp(1, resolution)
+        };
+        let (width, height) = if pixel_size > 0.0 {
+            (pixels_along(area.x.size()), pixels_along(area.z.size()))
+        } else {
+            (resolution, resolution)
+        };
+
+        let sun = sun.unit();
+        let min_t = RayBias::Auto.resolve(&area);
+        let top = area.y.max + 1.0;
+        let down = vec3![0.0, -1.0, 0.0];
+
+        let texels = (0..height)
+            .into_par_iter()
+            .flat_map_iter(|j| {
+                (0..width).map(move |i| {
+                    let x = area.x.min + (f64::from(i) + 0.5) / f64::from(width) * area.x.size();
+                    let z = area.z.min + (f64::from(j) + 0.5) / f64::from(height) * area.z.size();
+                    let rec = world.nearest_hit_along(&ray!(vec3![x, top, z], down))?;
+
+                    let shadowed = dot(&rec.norm, &sun) <= 0.0
+                        || world
+                            .hit(&ray!(rec.p, sun), interval![min_t, f64::INFINITY])
+                            .is_some();
+
+                    Some(MapTexel {
+                        elevation: rec.p[1],
+                        shadowed,
+                    })
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            area,
+            texels,
+        })
+    }
+
+    /// The width of the map in pixels, along `X`.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the map in pixels, along `Z`.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The bounds of the scene the map covers.
+    #[must_use]
+    pub fn bounds(&self) -> Aabb {
+        self.area
+    }
+
+    fn texel(&self, x: u32, y: u32) -> Option<MapTexel> {
+        assert!(x < self.width && y < self.height, "Pixel out of bounds");
+        self.texels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// The world-space `Y` of the surface under the centre of pixel `(x, y)`,
+    /// or [`None`] if nothing is there.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    #[must_use]
+    pub fn elevation_at(&self, x: u32, y: u32) -> Option<f64> {
+        self.texel(x, y).map(|t| t.elevation)
+    }
+
+    /// Whether the surface under pixel `(x, y)` is in shadow. Empty pixels
+    /// aren't.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    #[must_use]
+    pub fn in_shadow(&self, x: u32, y: u32) -> bool {
+        self.texel(x, y).is_some_and(|t| t.shadowed)
+    }
+
+    /// Convert to a [`FilmBuffer`] with each map pixel passed through `f`, or
+    /// black where nothing is there.
+    fn to_film<F: Fn(MapTexel) -> f64>(&self, f: F) -> FilmBuffer {
+        let mut film = FilmBuffer::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(texel) = self.texel(x, y) {
+                    let v = f(texel);
+                    film.set(x, y, vec3![v, v, v]);
+                }
+            }
+        }
+        film
+    }
+
+    /// The elevations as a greyscale [`FilmBuffer`], from black at the bottom
+    /// of the scene bounds to white at the top. Save it with
+    /// [`FilmBuffer::save_linear()`] to keep the values unchanged.
+    #[must_use]
+    pub fn height_map(&self) -> FilmBuffer {
+        let range = self.area.y;
+        self.to_film(|t| {
+            if range.size() > 0.0 {
+                (t.elevation - range.min) / range.size()
+            } else {
+                1.0
+            }
+        })
+    }
+
+    /// The shadows as a [`FilmBuffer`], white where lit and black where in
+    /// shadow or empty.
+    #[must_use]
+    pub fn shadow_map(&self) -> FilmBuffer {
+        self.to_film(|t| if t.shadowed { 0.0 } else { 1.0 })
+    }
+}
@@ -1,16 +1,28 @@
 //! This module contains the [`Material`] trait and a few materials such as
-//! [`Metal`], [`Lambertian`], and [`Dielectric`].
+//! [`Metal`], [`Lambertian`], [`Dielectric`], [`Sheen`], [`PbrMaterial`] and
+//! [`DiffuseLight`],
+//! the [`TwoSided`] wrapper for giving each side of a surface its own material,
+//! as well as the
+//! [`MaterialArena`] for sharing them between many primitives and the
+//! [`SharedMaterial`] for swapping them out after they're in use.
 
-use std::fmt::Debug;
+use std::{
+    f64::consts::PI,
+    fmt::Debug,
+    sync::{Arc, RwLock, RwLockReadGuard},
+};
 
 use crate::{
     hit::HitRecord,
+    noise::Noise,
+    principled::Principled,
     ray,
     ray::Ray,
     vec3,
-    vec3::{dot, Vec3},
+    vec3::{cross, dot, Vec3},
 };
 use rand::{rngs::SmallRng, RngExt as _};
+use serde::Deserialize;
 
 /// This trait indicates a struct is a material and allows the material to
 /// [scatter](method@Material::scatter()) incident light.
@@ -18,13 +30,118 @@ pub trait Material: Debug + Sync + Send {
     /// Scatter incident light/[ray](Ray)s according to the materials properties.
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3);
 
+    /// Light emitted towards the incident [ray](Ray), added on top of whatever
+    /// is scattered. Most materials don't emit, which is the default.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Vec3 {
+        Vec3::default()
+    }
+
+    /// Which [ray](Ray)s can see the material, see [`Visibility`]. Visible to
+    /// everything by default.
+    fn visibility(&self) -> Visibility {
+        Visibility::default()
+    }
+
+    /// Whether the material scatters light diffusely rather than specularly,
+    /// used to tell which paths are caustics. Defaults to `true`.
+    fn is_diffuse(&self) -> bool {
+        true
+    }
+
+    /// The fraction of each colour of light passing straight through the
+    /// surface along `r_in`, without bending, for shadow and occlusion
+    /// queries such as [`Scene::transmittance()`](crate::scene::Scene::transmittance()).
+    /// Opaque by default.
+    fn transmittance(&self, _r_in: &Ray, _rec: &HitRecord) -> Vec3 {
+        Vec3::default()
+    }
+
+    /// If the material fakes its caustics, the fraction of each colour of
+    /// light carried straight through the surface along `r_in` by paths that
+    /// have already scattered off a diffuse surface, in place of scattering
+    /// them. It can exceed one where the surface would focus light. [`None`]
+    /// by default.
+    fn fake_caustic(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<Vec3> {
+        None
+    }
+
+    /// What the material does as a light, see [`LightEffect`].
+    /// [`LightEffect::Illuminate`] by default.
+    fn light_effect(&self) -> LightEffect {
+        LightEffect::Illuminate
+    }
+
+    /// The albedo of the hit if the material reflects like a [`Lambertian`],
+    /// so [rays](Ray) can be scattered in directions of the renderer's
+    /// choosing, such as by a [`PathGuide`](crate::guide::PathGuide). [`None`]
+    /// by default.
+    fn lambertian_albedo(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<Vec3> {
+        None
+    }
+
     /// Clones a [boxed](Box) material.
     fn clone_box(&self) -> Box<dyn Material>;
 }
 
+/// Which kinds of [ray](Ray) can see a [`Material`], to trade correctness for
+/// faster convergence. Rays that can't see a surface pass straight through it.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::material::{Dielectric, Material, Visibility};
+///
+/// // glass that doesn't focus light onto diffuse surfaces, so converges
+/// // without caustic fireflies (but also casts no shadow)
+/// let glass = Dielectric::new(1.5).with_visibility(Visibility {
+///     caustics: false,
+///     ..Visibility::default()
+/// });
+///
+/// assert!(!glass.visibility().caustics);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Visibility {
+    /// Visible to rays after the first bounce, such as in reflections or
+    /// lighting other surfaces.
+    pub secondary: bool,
+    /// Visible to rays that have already scattered off a diffuse surface, the
+    /// paths that make caustics.
+    pub caustics: bool,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            secondary: true,
+            caustics: true,
+        }
+    }
+}
+
+/// What a light such as a [`DiffuseLight`] does to a scene. Anything but
+/// [`LightEffect::Illuminate`] is nonphysical, a control for lighting
+/// artists that the renderer has to handle specially.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum LightEffect {
+    /// Light the scene as usual.
+    #[default]
+    Illuminate,
+    /// Only cast shadows: surfaces are darkened by the light they'd get from
+    /// it where something blocks it, and are otherwise untouched. The light
+    /// itself can't be seen or hit. Such lights are kept out of the world
+    /// and rendered with
+    /// [`RenderOptions::set_shadow_lights()`](crate::camera::RenderOptions::set_shadow_lights()).
+    ShadowOnly,
+    /// Take light away from whatever it would light, by the same paths. It
+    /// can't be seen by the camera directly, and pixels it would take below
+    /// black are clamped to black.
+    Negative,
+}
+
 /// A [`Lambertian`] diffuse [material](Material) with true Lambertian reflection.
 /// See [wikipedia](https://en.wikipedia.org/wiki/Lambertian_reflectance).
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Lambertian {
     albedo: Vec3,
 }
@@ -40,14 +157,18 @@ impl Lambertian {
 
 impl Material for Lambertian {
     /// Scatter light [ray](Ray)s with true Lambertian reflectance.
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
         let mut scatter_dir = rec.norm + Vec3::random_unit(rng.unwrap());
 
         if scatter_dir.near_zero() {
             scatter_dir = rec.norm;
         }
 
-        (ray![rec.p, scatter_dir], self.albedo)
+        (ray![rec.p, scatter_dir, r_in.time], self.albedo)
+    }
+
+    fn lambertian_albedo(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<Vec3> {
+        Some(self.albedo)
     }
 
     fn clone_box(&self) -> Box<dyn Material> {
@@ -56,11 +177,14 @@ impl Material for Lambertian {
 }
 
 /// The [`Metal`] [material](Material) with perfect reflectance when made with
-/// a `fuzz` of `0`.
-#[derive(Copy, Clone, Debug, Default)]
+/// a `fuzz` of `0`. An optional [`Glints`] layer can be added with
+/// [`Metal::with_glints()`] for sparkling flakes.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Metal {
     albedo: Vec3,
     fuzz: f64,
+    glints: Option<Glints>,
+    visibility: Visibility,
 }
 
 impl Metal {
@@ -75,17 +199,58 @@ impl Metal {
             (0.0..=1.0).contains(&fuzz),
             "Fuzz may not exceed 0.0 to 1.0"
         );
-        Self { albedo, fuzz }
+        Self {
+            albedo,
+            fuzz,
+            glints: None,
+            visibility: Visibility::default(),
+        }
+    }
+
+    /// Add a [`Glints`] layer on top of the [`Metal`].
+    #[must_use]
+    pub fn with_glints(self, glints: Glints) -> Self {
+        Self {
+            glints: Some(glints),
+            ..self
+        }
+    }
+
+    /// Set which [ray](Ray)s can see the [`Metal`].
+    #[must_use]
+    pub fn with_visibility(self, visibility: Visibility) -> Self {
+        Self { visibility, ..self }
     }
 }
 
 impl Material for Metal {
     /// Scatter light [ray](Ray)s with metal reflectance. Perfect reflectance if `fuzz`
-    /// is `0`.
+    /// is `0`. If the hit lands on a [glint](Glints) flake, the ray is mirrored
+    /// off the flake instead.
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        if let Some(glints) = self.glints {
+            if let Some(flake_norm) = glints.flake_at(rec.p, rec.norm) {
+                let reflected = r_in.direction.unit().reflect(&flake_norm);
+                if dot(&reflected, &rec.norm) > 0.0 {
+                    return (
+                        ray![rec.p, reflected, r_in.time],
+                        self.albedo * glints.intensity,
+                    );
+                }
+            }
+        }
+
         let mut reflected = r_in.direction.reflect(&rec.norm);
         reflected = reflected.unit() + (Vec3::random_unit(rng.unwrap()) * self.fuzz);
-        (ray![rec.p, reflected], self.albedo)
+        (ray![rec.p, reflected, r_in.time], self.albedo)
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn is_diffuse(&self) -> bool {
+        false
     }
 
     fn clone_box(&self) -> Box<dyn Material> {
@@ -93,24 +258,210 @@ impl Material for Metal {
     }
 }
 
+/// A discrete microfacet glint layer for [`Metal`]. Space is divided into
+/// cells of [`flake_size`](field@Glints::flake_size) and each cell
+/// deterministically either holds a single mirror flake with a randomly tilted
+/// normal or nothing. As flakes are hashed from world-space position, the
+/// sparkles stay put under camera motion and between frames.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Glints {
+    /// The world-space size of a flake cell.
+    pub flake_size: f64,
+    /// The probability (`0.0..=1.0`) that a cell contains a flake.
+    pub density: f64,
+    /// The maximum tilt of a flake normal away from the surface normal in
+    /// radians.
+    pub spread: f64,
+    /// Brightness multiplier for light reflected off a flake.
+    pub intensity: f64,
+    /// The seed for the flake pattern, see [`Noise`]. Scene files set it from
+    /// the scene's seed.
+    #[serde(skip)]
+    pub seed: u64,
+}
+
+impl Default for Glints {
+    fn default() -> Self {
+        Self {
+            flake_size: 0.01,
+            density: 0.2,
+            spread: 0.3,
+            intensity: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl Glints {
+    /// Get the normal of the flake covering world-space point `p` on a surface
+    /// with normal `norm`, or [`None`] if the cell containing `p` is empty.
+    #[must_use]
+    pub fn flake_at(&self, p: Vec3, norm: Vec3) -> Option<Vec3> {
+        #[expect(clippy::cast_possible_truncation)]
+        let cell = [
+            (p[0] / self.flake_size).floor() as i64,
+            (p[1] / self.flake_size).floor() as i64,
+            (p[2] / self.flake_size).floor() as i64,
+        ];
+
+        let mut rng = Noise::new(self.seed).rng(cell);
+        if rng.next_unit() >= self.density {
+            return None;
+        }
+
+        // uniformly sample a direction in the cone of half-angle `spread`
+        let cos_max = self.spread.cos();
+        let cos_theta = 1.0 - rng.next_unit() * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * rng.next_unit();
+
+        let (t, b) = norm.orthonormal_basis();
+        Some((t * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin()) + norm * cos_theta).unit())
+    }
+}
+
 /// Dielectric [material](Material) that uses Snell's law for refraction
 /// and the Schlick approximation for reflectance.
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Dielectric {
     refraction_index: f64,
+    visibility: Visibility,
+    tint: Option<Vec3>,
+    /// How tightly faked caustics are focused, if they're faked.
+    focus: Option<f64>,
 }
 
 impl Dielectric {
     /// Create a new [`Dielectric`] with the given refraction index.
     #[must_use]
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            visibility: Visibility::default(),
+            tint: None,
+            focus: None,
+        }
+    }
+
+    /// Set which [ray](Ray)s can see the [`Dielectric`].
+    #[must_use]
+    pub fn with_visibility(self, visibility: Visibility) -> Self {
+        Self { visibility, ..self }
+    }
+
+    /// Tint the light passing through each surface by `tint`, like thin
+    /// stained glass, so shadows through it are coloured too. Reflections
+    /// off it stay untinted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::HitRecord,
+    ///     material::{Dielectric, Material},
+    ///     ray,
+    ///     ray::Ray,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let red = Dielectric::new(1.5).with_tint(vec3![1.0, 0.2, 0.2]);
+    /// let r = ray!(vec3![0.0, 0.0, 1.0], vec3![0.0, 0.0, -1.0]);
+    /// let rec = HitRecord {
+    ///     p: vec3![0.0, 0.0, 0.0],
+    ///     norm: vec3![0.0, 0.0, 1.0],
+    ///     mat: &red,
+    ///     t: 1.0,
+    ///     front_face: true,
+    ///     u: 0.0,
+    ///     v: 0.0,
+    ///     object: None,
+    ///     layer: None,
+    ///     tangent: None,
+    /// };
+    ///
+    /// // head on, 4% is reflected and the rest tinted
+    /// let through = red.transmittance(&r, &rec);
+    /// assert!((through[0] - 0.96).abs() < 1e-9);
+    /// assert!((through[1] - 0.96 * 0.2).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn with_tint(self, tint: Vec3) -> Self {
+        Self {
+            tint: Some(tint),
+            ..self
+        }
+    }
+
+    /// Fake the caustics of the glass, so light reaching diffuse surfaces
+    /// through it goes straight through and is concentrated rather than
+    /// refracted, giving plausible bright spots under glass objects in fast
+    /// renders that would otherwise take many samples to resolve them.
+    ///
+    /// Where light enters the glass it's passed in proportion to
+    /// `cos^focus` of its angle to the surface, scaled by `(focus + 2) / 2`
+    /// so a convex object passes as much light in all as it would with
+    /// `focus` at `0`, as plain shadow-ray attenuation. Higher `focus` gives
+    /// a tighter, brighter spot in the middle of its shadow. It suits solid,
+    /// rounded objects like balls and lenses rather than flat panes.
+    ///
+    /// # Panics
+    ///
+    /// If `focus` is negative or not finite.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::HitRecord,
+    ///     material::{Dielectric, Material},
+    ///     ray,
+    ///     ray::Ray,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let ball = Dielectric::new(1.5).with_fake_caustics(4.0);
+    /// let rec = |norm| HitRecord {
+    ///     p: vec3![0.0, 0.0, 0.0],
+    ///     norm,
+    ///     mat: &ball,
+    ///     t: 1.0,
+    ///     front_face: true,
+    ///     u: 0.0,
+    ///     v: 0.0,
+    ///     object: None,
+    ///     layer: None,
+    ///     tangent: None,
+    /// };
+    /// let r = ray!(vec3![0.0, 1.0, 0.0], vec3![0.0, -1.0, 0.0]);
+    ///
+    /// // focused in the middle, where light meets the glass head on
+    /// let middle = ball.fake_caustic(&r, &rec(vec3![0.0, 1.0, 0.0])).unwrap();
+    /// assert!(middle[0] > 2.5);
+    /// // and dimmer towards the edges
+    /// let edge = ball.fake_caustic(&r, &rec(vec3![0.8, 0.6, 0.0])).unwrap();
+    /// assert!(edge[0] < 0.5);
+    ///
+    /// assert!(Dielectric::new(1.5).fake_caustic(&r, &rec(vec3![0.0, 1.0, 0.0])).is_none());
+    /// ```
+    #[must_use]
+    pub fn with_fake_caustics(self, focus: f64) -> Self {
+        assert!(
+            focus >= 0.0 && focus.is_finite(),
+            "Caustic focus must be positive and finite"
+        );
+        Self {
+            focus: Some(focus),
+            ..self
+        }
     }
 }
 
 impl Dielectric {
     /// The Schlick approximation to model reflectance at near-grazing incidence.
-    fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
+    pub(crate) fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
         let mut r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
         r0 *= r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
@@ -132,18 +483,1017 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = ri * sin_theta > 1.0;
-        let direction = if cannot_refract
+        let white = vec3![1.0, 1.0, 1.0];
+        let (direction, attenuation) = if cannot_refract
             || Self::reflectance(cos_theta, ri) > rng.unwrap().random_range(0.0..=1.0)
         {
-            unit_dir.reflect(&rec.norm)
+            (unit_dir.reflect(&rec.norm), white)
+        } else {
+            (unit_dir.refract(&rec.norm, ri), self.tint.unwrap_or(white))
+        };
+
+        (ray![rec.p, direction, r_in.time], attenuation)
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn is_diffuse(&self) -> bool {
+        false
+    }
+
+    /// Everything that isn't reflected, by the Schlick approximation, which
+    /// is the same from either side, times the tint.
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        let cos_theta = dot(&r_in.direction.unit(), &rec.norm).abs().min(1.0);
+        let through = 1.0 - Self::reflectance(cos_theta, self.refraction_index);
+        self.tint.unwrap_or(vec3![1.0, 1.0, 1.0]) * through
+    }
+
+    /// The [transmittance](Material::transmittance()), focused where light
+    /// enters the glass, see [`Dielectric::with_fake_caustics()`].
+    fn fake_caustic(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        let focus = self.focus?;
+        let through = self.transmittance(r_in, rec);
+        if !rec.front_face {
+            return Some(through);
+        }
+        let cos_theta = dot(&r_in.direction.unit(), &rec.norm).abs().min(1.0);
+        Some(through * (0.5 * (focus + 2.0) * cos_theta.powf(focus)))
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
+
+/// A cloth-like [material](Material) combining a Lambertian base with a
+/// retro-reflective sheen lobe using the "Charlie" microfibre distribution
+/// (Estevez & Kulla) and the Neubelt & Pettineo visibility term. Good for
+/// velvet, satin and other fabrics that glow at grazing angles.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Sheen {
+    albedo: Vec3,
+    tint: Vec3,
+    roughness: f64,
+}
+
+impl Sheen {
+    /// Create a new [`Sheen`] with a diffuse `albedo`, a sheen `tint` for the
+    /// microfibre lobe, and a `roughness` (must be inclusively between 0 and 1).
+    /// Lower roughness concentrates the sheen closer to grazing angles.
+    ///
+    /// # Panics
+    ///
+    /// If `roughness` exceeds the range `0.0..=1.0`.
+    #[must_use]
+    pub fn new(albedo: Vec3, tint: Vec3, roughness: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&roughness),
+            "Roughness may not exceed 0.0 to 1.0"
+        );
+        Self {
+            albedo,
+            tint,
+            roughness,
+        }
+    }
+
+    /// The Charlie sheen distribution for a half vector at `cos_h` to the normal.
+    fn distribution(&self, cos_h: f64) -> f64 {
+        // very low roughness makes the exponent blow up
+        let alpha = (self.roughness * self.roughness).max(0.007);
+        let inv_alpha = 1.0 / alpha;
+        let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+        (2.0 + inv_alpha) * sin_h.powf(inv_alpha) / (2.0 * PI)
+    }
+
+    /// The Neubelt & Pettineo visibility term for the sheen lobe.
+    fn visibility(cos_l: f64, cos_v: f64) -> f64 {
+        1.0 / (4.0 * (cos_l + cos_v - cos_l * cos_v))
+    }
+}
+
+impl Material for Sheen {
+    /// Scatter light [ray](Ray)s with a cosine-weighted distribution, weighting
+    /// the attenuation by the diffuse base plus the sheen lobe.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let mut scatter_dir = rec.norm + Vec3::random_unit(rng.unwrap());
+
+        if scatter_dir.near_zero() {
+            scatter_dir = rec.norm;
+        }
+
+        let view = -r_in.direction.unit();
+        let light = scatter_dir.unit();
+        let cos_v = dot(&rec.norm, &view).max(1e-4);
+        let cos_l = dot(&rec.norm, &light).max(1e-4);
+        let cos_h = dot(&rec.norm, &(view + light).unit()).clamp(0.0, 1.0);
+
+        // cosine-weighted sampling has pdf `cos_l / PI`, cancelling with the
+        // cosine term and leaving `f * PI`
+        let sheen = self.tint * (PI * self.distribution(cos_h) * Self::visibility(cos_l, cos_v));
+
+        (ray![rec.p, scatter_dir, r_in.time], self.albedo + sheen)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
+
+/// A metallic-roughness [material](Material) as used by standard PBR
+/// pipelines (glTF, Unreal, Substance and so on), so assets authored for them
+/// render as intended. Specular reflection uses a Cook-Torrance microfacet
+/// BRDF with the GGX distribution, height-correlated Smith masking and Schlick
+/// Fresnel, importance sampled from the distribution of visible normals
+/// (Heitz 2018). Dielectrics (`metallic` of `0`) add a Lambertian base under a
+/// 4% reflective coat; metals (`metallic` of `1`) reflect tinted by the base
+/// colour and have no diffuse part. The roughness can be
+/// [anisotropic](PbrMaterial::with_anisotropy()), for brushed metal.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::HitRecord,
+///     material::{Material, PbrMaterial},
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::{dot, Vec3},
+/// };
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let gold = PbrMaterial::new(vec3![1.0, 0.78, 0.34], 1.0, 0.0);
+/// let r = ray!(vec3![-1.0, 1.0, 0.0], vec3![1.0, -1.0, 0.0]);
+/// let rec = HitRecord {
+///     p: vec3![0.0, 0.0, 0.0],
+///     norm: vec3![0.0, 1.0, 0.0],
+///     mat: &gold,
+///     t: 1.0,
+///     front_face: true,
+///     u: 0.0,
+///     v: 0.0,
+///     object: None,
+///     layer: None,
+///     tangent: None,
+/// };
+///
+/// // polished metal is a mirror, tinted towards white at grazing angles
+/// let mut rng = SmallRng::seed_from_u64(0);
+/// let (scattered, attenuation) = gold.scatter(&r, &rec, Some(&mut rng));
+/// assert!(dot(&scattered.direction.unit(), &vec3![1.0, 1.0, 0.0].unit()) > 0.999);
+/// assert!(attenuation[2] > 0.34 && attenuation[2] < 1.0);
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PbrMaterial {
+    base_colour: Vec3,
+    metallic: f64,
+    /// The roughness along the tangent, and in every direction unless
+    /// anisotropic.
+    roughness: f64,
+    bitangent_roughness: f64,
+    /// The angle the tangent is turned by about the normal, in radians.
+    rotation: f64,
+    visibility: Visibility,
+}
+
+impl PbrMaterial {
+    /// The reflectance at normal incidence of dielectrics, which is about 4%
+    /// for most common ones.
+    pub const DIELECTRIC_F0: f64 = 0.04;
+
+    /// Create a new [`PbrMaterial`] with a `base_colour` (the albedo of
+    /// dielectrics, the reflectance of metals), how `metallic` it is and its
+    /// perceptual `roughness` (both must be inclusively between 0 and 1).
+    ///
+    /// # Panics
+    ///
+    /// If `metallic` or `roughness` exceed the range `0.0..=1.0`.
+    #[must_use]
+    pub fn new(base_colour: Vec3, metallic: f64, roughness: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&metallic),
+            "Metallic may not exceed 0.0 to 1.0"
+        );
+        assert!(
+            (0.0..=1.0).contains(&roughness),
+            "Roughness may not exceed 0.0 to 1.0"
+        );
+        Self {
+            base_colour,
+            metallic,
+            roughness,
+            bitangent_roughness: roughness,
+            rotation: 0.0,
+            visibility: Visibility::default(),
+        }
+    }
+
+    /// Set which [ray](Ray)s can see the [`PbrMaterial`].
+    #[must_use]
+    pub fn with_visibility(self, visibility: Visibility) -> Self {
+        Self { visibility, ..self }
+    }
+
+    /// Make the roughness anisotropic, with `tangent_roughness` along the
+    /// surface's tangent (the direction of increasing texture coordinate
+    /// `u`) turned by `rotation` radians about the normal, and
+    /// `bitangent_roughness` across it. Highlights stretch across the
+    /// rougher direction, so metal brushed along the tangent has a lower
+    /// tangent roughness. Surfaces without a tangent use an arbitrary one.
+    ///
+    /// # Panics
+    ///
+    /// If `tangent_roughness` or `bitangent_roughness` exceed the range
+    /// `0.0..=1.0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::HitRecord,
+    ///     material::{Material, PbrMaterial},
+    ///     ray,
+    ///     ray::Ray,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    /// use rand::{rngs::SmallRng, SeedableRng};
+    ///
+    /// // brushed along x, so light spreads more across it, along z
+    /// let brushed = PbrMaterial::new(vec3![0.9, 0.9, 0.9], 1.0, 0.5)
+    ///     .with_anisotropy(0.05, 0.5, 0.0);
+    /// let r = ray!(vec3![0.0, 1.0, 0.0], vec3![0.0, -1.0, 0.0]);
+    /// let rec = HitRecord {
+    ///     p: vec3![0.0, 0.0, 0.0],
+    ///     norm: vec3![0.0, 1.0, 0.0],
+    ///     mat: &brushed,
+    ///     t: 1.0,
+    ///     front_face: true,
+    ///     u: 0.0,
+    ///     v: 0.0,
+    ///     object: None,
+    ///     layer: None,
+    ///     tangent: Some(vec3![1.0, 0.0, 0.0]),
+    /// };
+    ///
+    /// let mut rng = SmallRng::seed_from_u64(0);
+    /// let (mut along, mut across) = (0.0, 0.0);
+    /// for _ in 0..1000 {
+    ///     let (scattered, _) = brushed.scatter(&r, &rec, Some(&mut rng));
+    ///     let d = scattered.direction.unit();
+    ///     along += d[0].abs();
+    ///     across += d[2].abs();
+    /// }
+    /// assert!(across > 4.0 * along);
+    /// ```
+    #[must_use]
+    pub fn with_anisotropy(
+        self,
+        tangent_roughness: f64,
+        bitangent_roughness: f64,
+        rotation: f64,
+    ) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&tangent_roughness) && (0.0..=1.0).contains(&bitangent_roughness),
+            "Roughness may not exceed 0.0 to 1.0"
+        );
+        Self {
+            roughness: tangent_roughness,
+            bitangent_roughness,
+            rotation,
+            ..self
+        }
+    }
+
+    /// The reflectance at normal incidence, see [`metallic_f0()`].
+    fn f0(&self) -> Vec3 {
+        metallic_f0(Self::DIELECTRIC_F0, self.base_colour, self.metallic)
+    }
+}
+
+impl Material for PbrMaterial {
+    /// Scatter light [ray](Ray)s off either the specular or the diffuse lobe,
+    /// picked in proportion to the light each reflects, with the attenuation
+    /// weighted by the chance of the pick. Specular rays leaving below the
+    /// surface are absorbed, which is the energy lost to masking.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let rng = rng.unwrap();
+        let view = -r_in.direction.unit();
+        let cos_v = dot(&rec.norm, &view).max(1e-4);
+
+        let fresnel_v = schlick_fresnel(self.f0(), cos_v);
+        let diffuse = self.base_colour * (vec3![1.0, 1.0, 1.0] - fresnel_v) * (1.0 - self.metallic);
+        // a black base that reflects nothing either way takes the diffuse
+        // lobe, which absorbs the ray, rather than dividing by zero
+        let specular_chance = {
+            let specular = fresnel_v.luminance();
+            let total = specular + diffuse.luminance();
+            if total > 0.0 {
+                specular / total
+            } else {
+                0.0
+            }
+        };
+
+        if rng.random_range(0.0..1.0) >= specular_chance {
+            let mut scatter_dir = rec.norm + Vec3::random_unit(rng);
+            if scatter_dir.near_zero() {
+                scatter_dir = rec.norm;
+            }
+            return (
+                ray![rec.p, scatter_dir, r_in.time],
+                diffuse / (1.0 - specular_chance),
+            );
+        }
+
+        let ggx = Ggx::anisotropic(
+            rec,
+            Ggx::alpha(self.roughness),
+            Ggx::alpha(self.bitangent_roughness),
+            self.rotation,
+        );
+        let half = ggx.sample_visible_normal(view, rng);
+        let light = (-view).reflect(&half);
+        if dot(&rec.norm, &light) <= 0.0 {
+            return (ray![rec.p, light, r_in.time], Vec3::default());
+        }
+
+        // sampling visible normals leaves `F * G2 / G1` of the BRDF
+        let masking = ggx.masking(view, light);
+        let fresnel = schlick_fresnel(self.f0(), dot(&view, &half));
+
+        (
+            ray![rec.p, light, r_in.time],
+            fresnel * (masking / specular_chance),
+        )
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    /// Only pure metals count as specular, anything with a diffuse base is
+    /// diffuse.
+    fn is_diffuse(&self) -> bool {
+        self.metallic < 1.0
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
+
+/// Schlick's approximation of the Fresnel reflectance at `cos_theta`, for a
+/// reflectance of `f0` at normal incidence.
+pub(crate) fn schlick_fresnel(f0: Vec3, cos_theta: f64) -> Vec3 {
+    let weight = (1.0 - cos_theta).clamp(0.0, 1.0).powi(5);
+    f0 + (vec3![1.0, 1.0, 1.0] - f0) * weight
+}
+
+/// The reflectance at normal incidence of a metallic-roughness material,
+/// blending from `dielectric`, that of its non-metallic parts, to the
+/// `base_colour` as it gets more `metallic`.
+pub(crate) fn metallic_f0(dielectric: f64, base_colour: Vec3, metallic: f64) -> Vec3 {
+    vec3![dielectric, dielectric, dielectric] * (1.0 - metallic) + base_colour * metallic
+}
+
+/// A GGX microfacet distribution over a surface, in the frame of its
+/// tangent, bitangent and normal, with its own roughness along each of the
+/// tangent and bitangent for anisotropic materials.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ggx {
+    tangent: Vec3,
+    bitangent: Vec3,
+    norm: Vec3,
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl Ggx {
+    /// The GGX `alpha` for a perceptual `roughness`, its square, kept just
+    /// above zero so smooth surfaces stay finite.
+    pub(crate) fn alpha(roughness: f64) -> f64 {
+        (roughness * roughness).max(1e-4)
+    }
+
+    /// The same `alpha` in every direction over `norm`.
+    pub(crate) fn isotropic(norm: Vec3, alpha: f64) -> Self {
+        let (tangent, bitangent) = norm.orthonormal_basis();
+        Self {
+            tangent,
+            bitangent,
+            norm,
+            alpha_x: alpha,
+            alpha_y: alpha,
+        }
+    }
+
+    /// `alpha_x` along the tangent of `rec` turned by `rotation` radians
+    /// about its normal, and `alpha_y` across it. Hits without a tangent get
+    /// an arbitrary one.
+    pub(crate) fn anisotropic(rec: &HitRecord, alpha_x: f64, alpha_y: f64, rotation: f64) -> Self {
+        let norm = rec.norm;
+        let tangent = rec
+            .tangent
+            .map(|tangent| tangent - norm * dot(&norm, &tangent))
+            .filter(|tangent| !tangent.near_zero())
+            .map_or_else(|| norm.orthonormal_basis().0, |tangent| tangent.unit());
+        let bitangent = cross(&norm, &tangent);
+        let (sin, cos) = rotation.sin_cos();
+        let tangent = tangent * cos + bitangent * sin;
+        Self {
+            tangent,
+            bitangent: cross(&norm, &tangent),
+            norm,
+            alpha_x,
+            alpha_y,
+        }
+    }
+
+    /// `d` in the frame, with the normal along `+z`.
+    fn local(&self, d: Vec3) -> Vec3 {
+        vec3![
+            dot(&d, &self.tangent),
+            dot(&d, &self.bitangent),
+            dot(&d, &self.norm)
+        ]
+    }
+
+    /// The Smith `Λ` for the direction `local`, in the frame.
+    fn lambda(&self, local: Vec3) -> f64 {
+        let z2 = (local[2] * local[2]).max(1e-12);
+        let projected = (self.alpha_x * local[0]).powi(2) + (self.alpha_y * local[1]).powi(2);
+        0.5 * ((1.0 + projected / z2).sqrt() - 1.0)
+    }
+
+    /// The height-correlated Smith masking-shadowing over the masking of
+    /// `view` alone, `G2 / G1`, which is what's left of a microfacet BSDF
+    /// sampled by [`Ggx::sample_visible_normal()`]. `light` may be on either
+    /// side of the surface.
+    pub(crate) fn masking(&self, view: Vec3, light: Vec3) -> f64 {
+        let lambda_v = self.lambda(self.local(view));
+        let lambda_l = self.lambda(self.local(light));
+        (1.0 + lambda_v) / (1.0 + lambda_v + lambda_l)
+    }
+
+    /// Sample a microfacet normal visible from `view` (Heitz 2018).
+    pub(crate) fn sample_visible_normal(&self, view: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let local = self.local(view);
+
+        // stretch the view to the hemisphere configuration
+        let stretched = vec3![self.alpha_x * local[0], self.alpha_y * local[1], local[2]].unit();
+        let len_sq = stretched[0] * stretched[0] + stretched[1] * stretched[1];
+        let t1 = if len_sq > 0.0 {
+            vec3![-stretched[1], stretched[0], 0.0] / len_sq.sqrt()
         } else {
-            unit_dir.refract(&rec.norm, ri)
+            vec3![1.0, 0.0, 0.0]
         };
+        let t2 = cross(&stretched, &t1);
+
+        // sample the projected area of the visible hemisphere
+        let r = rng.random_range(0.0..1.0_f64).sqrt();
+        let phi = 2.0 * PI * rng.random_range(0.0..1.0);
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + stretched[2]);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let hemi = t1 * p1 + t2 * p2 + stretched * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        // and unstretch back to the ellipsoid
+        let h = vec3![
+            self.alpha_x * hemi[0],
+            self.alpha_y * hemi[1],
+            hemi[2].max(0.0)
+        ]
+        .unit();
+        self.tangent * h[0] + self.bitangent * h[1] + self.norm * h[2]
+    }
+}
 
-        (ray![rec.p, direction], vec3![1.0, 1.0, 1.0])
+/// A light-emitting [material](Material) that glows with the same radiance in
+/// every direction from its front face, like a lamp or a lit window. By
+/// default it absorbs everything that hits it, ending the path, but it can
+/// also reflect diffusely with [`DiffuseLight::with_albedo()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::HitRecord,
+///     material::{DiffuseLight, Material},
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let light = DiffuseLight::new(vec3![4.0, 4.0, 4.0]);
+/// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+/// let mut rec = HitRecord {
+///     p: vec3![0.0, 0.0, 1.0],
+///     norm: vec3![0.0, 0.0, 1.0],
+///     mat: &light,
+///     t: 1.0,
+///     front_face: true,
+///     u: 0.0,
+///     v: 0.0,
+///     object: None,
+///     layer: None,
+///     tangent: None,
+/// };
+///
+/// assert_eq!(light.emitted(&r, &rec), vec3![4.0, 4.0, 4.0]);
+/// assert!(light.scatter(&r, &rec, None).1.near_zero());
+///
+/// // dark from behind
+/// rec.front_face = false;
+/// assert!(light.emitted(&r, &rec).near_zero());
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DiffuseLight {
+    emit: Vec3,
+    albedo: Vec3,
+    effect: LightEffect,
+}
+
+impl DiffuseLight {
+    /// Create a new [`DiffuseLight`] emitting `emit`, which can be brighter
+    /// than `1.0` in each channel.
+    #[must_use]
+    pub fn new(emit: Vec3) -> Self {
+        Self {
+            emit,
+            albedo: Vec3::default(),
+            effect: LightEffect::default(),
+        }
+    }
+
+    /// Also reflect light diffusely with `albedo`, like a [`Lambertian`].
+    #[must_use]
+    pub fn with_albedo(self, albedo: Vec3) -> Self {
+        Self { albedo, ..self }
+    }
+
+    /// Set what the light does to the scene, to only cast shadows or to take
+    /// light away, see [`LightEffect`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::HitRecord,
+    ///     material::{DiffuseLight, LightEffect, Material},
+    ///     ray,
+    ///     ray::Ray,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let dimmer = DiffuseLight::new(vec3![0.5, 0.5, 0.5]).with_effect(LightEffect::Negative);
+    /// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+    /// let rec = HitRecord {
+    ///     p: vec3![0.0, 0.0, 1.0],
+    ///     norm: vec3![0.0, 0.0, 1.0],
+    ///     mat: &dimmer,
+    ///     t: 1.0,
+    ///     front_face: true,
+    ///     u: 0.0,
+    ///     v: 0.0,
+    ///     object: None,
+    ///     layer: None,
+    ///     tangent: None,
+    /// };
+    ///
+    /// assert_eq!(dimmer.light_effect(), LightEffect::Negative);
+    /// assert_eq!(dimmer.emitted(&r, &rec), vec3![-0.5, -0.5, -0.5]);
+    /// ```
+    #[must_use]
+    pub fn with_effect(self, effect: LightEffect) -> Self {
+        Self { effect, ..self }
+    }
+}
+
+impl Material for DiffuseLight {
+    /// Scatter light [ray](Ray)s like a [`Lambertian`] with the albedo, or
+    /// absorb them if it's black.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        if self.albedo.near_zero() {
+            return (ray![rec.p, rec.norm, r_in.time], Vec3::default());
+        }
+        Lambertian::new(self.albedo).scatter(r_in, rec, rng)
+    }
+
+    /// The emitted radiance from the front face, negated for a
+    /// [negative](LightEffect::Negative) light, and nothing from the back.
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        match (rec.front_face, self.effect) {
+            (false, _) => Vec3::default(),
+            (true, LightEffect::Negative) => -self.emit,
+            (true, _) => self.emit,
+        }
+    }
+
+    fn light_effect(&self) -> LightEffect {
+        self.effect
+    }
+
+    fn lambertian_albedo(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<Vec3> {
+        (!self.albedo.near_zero()).then_some(self.albedo)
     }
 
     fn clone_box(&self) -> Box<dyn Material> {
         Box::new(*self)
     }
 }
+
+/// A [material](Material) with one material on the front face of a surface
+/// and another on the back, going by [`HitRecord::front_face`], for leaves,
+/// paper and the insides of open meshes.
+///
+/// Whether it's [visible](Material::visibility()), [diffuse](Material::is_diffuse())
+/// or a [light](Material::light_effect()) doesn't depend on the hit, so is
+/// taken from the front material.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::HitRecord,
+///     material::{Lambertian, Material, TwoSided},
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // a leaf, dark green on top and pale underneath
+/// let leaf = TwoSided::new(
+///     Box::new(Lambertian::new(vec3![0.1, 0.4, 0.1])),
+///     Box::new(Lambertian::new(vec3![0.4, 0.6, 0.3])),
+/// );
+/// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+/// let mut rec = HitRecord {
+///     p: vec3![0.0, 0.0, 1.0],
+///     norm: vec3![0.0, 0.0, 1.0],
+///     mat: &leaf,
+///     t: 1.0,
+///     front_face: true,
+///     u: 0.0,
+///     v: 0.0,
+///     object: None,
+///     layer: None,
+///     tangent: None,
+/// };
+/// assert_eq!(leaf.lambertian_albedo(&r, &rec), Some(vec3![0.1, 0.4, 0.1]));
+///
+/// rec.front_face = false;
+/// assert_eq!(leaf.lambertian_albedo(&r, &rec), Some(vec3![0.4, 0.6, 0.3]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TwoSided {
+    front: Arc<dyn Material>,
+    back: Arc<dyn Material>,
+}
+
+impl TwoSided {
+    /// Create a new [`TwoSided`] material showing `front` on the front faces
+    /// of a surface and `back` on the back faces.
+    #[must_use]
+    pub fn new(front: Box<dyn Material>, back: Box<dyn Material>) -> Self {
+        Self {
+            front: Arc::from(front),
+            back: Arc::from(back),
+        }
+    }
+
+    /// The material on the side of the surface that was hit.
+    fn side(&self, rec: &HitRecord) -> &dyn Material {
+        if rec.front_face {
+            self.front.as_ref()
+        } else {
+            self.back.as_ref()
+        }
+    }
+}
+
+impl Material for TwoSided {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        self.side(rec).scatter(r_in, rec, rng)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.side(rec).emitted(r_in, rec)
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.front.visibility()
+    }
+
+    fn is_diffuse(&self) -> bool {
+        self.front.is_diffuse()
+    }
+
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.side(rec).transmittance(r_in, rec)
+    }
+
+    fn fake_caustic(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.side(rec).fake_caustic(r_in, rec)
+    }
+
+    fn light_effect(&self) -> LightEffect {
+        self.front.light_effect()
+    }
+
+    fn lambertian_albedo(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.side(rec).lambertian_albedo(r_in, rec)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(self.clone())
+    }
+}
+
+/// Any of the built-in [material](Material)s with plain parameters as a
+/// value, with no allocation of its own. Dispatches with a `match` rather than
+/// through a vtable, and can be compared so identical materials are stored
+/// once by a [`MaterialArena`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PackedMaterial {
+    /// A [`Lambertian`] material.
+    Lambertian(Lambertian),
+    /// A [`Metal`] material.
+    Metal(Metal),
+    /// A [`Dielectric`] material.
+    Dielectric(Dielectric),
+    /// A [`Sheen`] material.
+    Sheen(Sheen),
+    /// A [`PbrMaterial`].
+    Pbr(PbrMaterial),
+    /// A [`Principled`] material.
+    Principled(Principled),
+    /// A [`DiffuseLight`] material.
+    DiffuseLight(DiffuseLight),
+}
+
+impl PackedMaterial {
+    /// The packed material as a [`Material`] trait object.
+    fn as_material(&self) -> &dyn Material {
+        match self {
+            PackedMaterial::Lambertian(m) => m,
+            PackedMaterial::Metal(m) => m,
+            PackedMaterial::Dielectric(m) => m,
+            PackedMaterial::Sheen(m) => m,
+            PackedMaterial::Pbr(m) => m,
+            PackedMaterial::Principled(m) => m,
+            PackedMaterial::DiffuseLight(m) => m,
+        }
+    }
+}
+
+impl From<Lambertian> for PackedMaterial {
+    fn from(m: Lambertian) -> Self {
+        PackedMaterial::Lambertian(m)
+    }
+}
+
+impl From<Metal> for PackedMaterial {
+    fn from(m: Metal) -> Self {
+        PackedMaterial::Metal(m)
+    }
+}
+
+impl From<Dielectric> for PackedMaterial {
+    fn from(m: Dielectric) -> Self {
+        PackedMaterial::Dielectric(m)
+    }
+}
+
+impl From<Sheen> for PackedMaterial {
+    fn from(m: Sheen) -> Self {
+        PackedMaterial::Sheen(m)
+    }
+}
+
+impl From<PbrMaterial> for PackedMaterial {
+    fn from(m: PbrMaterial) -> Self {
+        PackedMaterial::Pbr(m)
+    }
+}
+
+impl From<Principled> for PackedMaterial {
+    fn from(m: Principled) -> Self {
+        PackedMaterial::Principled(m)
+    }
+}
+
+impl From<DiffuseLight> for PackedMaterial {
+    fn from(m: DiffuseLight) -> Self {
+        PackedMaterial::DiffuseLight(m)
+    }
+}
+
+impl Material for PackedMaterial {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        self.as_material().scatter(r_in, rec, rng)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.as_material().emitted(r_in, rec)
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.as_material().visibility()
+    }
+
+    fn is_diffuse(&self) -> bool {
+        self.as_material().is_diffuse()
+    }
+
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.as_material().transmittance(r_in, rec)
+    }
+
+    fn fake_caustic(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.as_material().fake_caustic(r_in, rec)
+    }
+
+    fn light_effect(&self) -> LightEffect {
+        self.as_material().light_effect()
+    }
+
+    fn lambertian_albedo(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.as_material().lambertian_albedo(r_in, rec)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
+
+/// The index of a [`PackedMaterial`] in a [`MaterialArena`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialId(u32);
+
+/// One block holding the parameters of every distinct [`PackedMaterial`] of
+/// a scene. Scenes with millions of primitives usually only use a few dozen
+/// materials, so primitives built over an arena, such as the faces of a
+/// [`Mesh::from_packed_data()`](crate::mesh::Mesh::from_packed_data()), each
+/// keep a four byte [`MaterialId`] rather than a boxed material of their own.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{material::{Lambertian, MaterialArena, Metal, PackedMaterial}, vec3, vec3::Vec3};
+///
+/// let mut arena = MaterialArena::new();
+///
+/// let red = arena.intern(Lambertian::new(vec3![0.8, 0.1, 0.1]));
+/// let also_red = arena.intern(Lambertian::new(vec3![0.8, 0.1, 0.1]));
+/// let chrome = arena.intern(Metal::new(vec3![0.9, 0.9, 0.9], 0.0));
+///
+/// assert_eq!(red, also_red);
+/// assert_ne!(red, chrome);
+/// assert_eq!(arena.len(), 2);
+/// let expected = PackedMaterial::Metal(Metal::new(vec3![0.9, 0.9, 0.9], 0.0));
+/// assert_eq!(arena.get(chrome), Some(&expected));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MaterialArena {
+    materials: Vec<PackedMaterial>,
+}
+
+impl MaterialArena {
+    /// Create a new, empty [`MaterialArena`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The [`MaterialId`] of `mat`, adding it to the arena if an equal
+    /// material isn't already there.
+    ///
+    /// # Panics
+    ///
+    /// If the arena already holds [`u32::MAX`] materials.
+    pub fn intern<M: Into<PackedMaterial>>(&mut self, mat: M) -> MaterialId {
+        let mat = mat.into();
+        let index = match self.materials.iter().position(|m| *m == mat) {
+            Some(index) => index,
+            None => {
+                self.materials.push(mat);
+                self.materials.len() - 1
+            }
+        };
+        MaterialId(u32::try_from(index).expect("too many materials"))
+    }
+
+    /// The material with the given `id`, or [`None`] if it isn't from this
+    /// arena.
+    #[must_use]
+    pub fn get(&self, id: MaterialId) -> Option<&PackedMaterial> {
+        self.materials.get(id.0 as usize)
+    }
+
+    /// The number of distinct materials in the arena.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    /// Whether the arena has no materials.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+}
+
+/// A [`Material`] that can be replaced after the objects using it are built.
+/// Clones share the same slot, so replacing the material changes it on every
+/// object holding a clone without rebuilding any geometry, which is what
+/// material hot reloading uses.
+///
+/// Every call goes through a lock, so only use it where materials need to
+/// change.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::material::{Lambertian, Material, Metal, SharedMaterial};
+/// use rustrace::{vec3, vec3::Vec3};
+///
+/// let shared = SharedMaterial::new(Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])));
+/// let on_object = shared.clone_box();
+/// assert!(on_object.is_diffuse());
+///
+/// shared.replace(Box::new(Metal::new(vec3![0.9, 0.9, 0.9], 0.0)));
+/// assert!(!on_object.is_diffuse());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedMaterial {
+    slot: Arc<RwLock<Box<dyn Material>>>,
+}
+
+impl SharedMaterial {
+    /// Create a new [`SharedMaterial`] holding `mat`.
+    #[must_use]
+    pub fn new(mat: Box<dyn Material>) -> Self {
+        Self {
+            slot: Arc::new(RwLock::new(mat)),
+        }
+    }
+
+    /// Replace the material for everything sharing it.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while using the material.
+    pub fn replace(&self, mat: Box<dyn Material>) {
+        *self.slot.write().expect("shared material lock poisoned") = mat;
+    }
+
+    fn get(&self) -> RwLockReadGuard<'_, Box<dyn Material>> {
+        self.slot.read().expect("shared material lock poisoned")
+    }
+}
+
+impl Material for SharedMaterial {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        self.get().scatter(r_in, rec, rng)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.get().emitted(r_in, rec)
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.get().visibility()
+    }
+
+    fn is_diffuse(&self) -> bool {
+        self.get().is_diffuse()
+    }
+
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.get().transmittance(r_in, rec)
+    }
+
+    fn fake_caustic(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.get().fake_caustic(r_in, rec)
+    }
+
+    fn light_effect(&self) -> LightEffect {
+        self.get().light_effect()
+    }
+
+    fn lambertian_albedo(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.get().lambertian_albedo(r_in, rec)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(self.clone())
+    }
+}
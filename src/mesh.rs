@@ -1,39 +1,891 @@
-//! This module contains the [`Mesh`] struct which mostly implements OBJ parsing
-//! to construct [`BVHTree`] of [`Triangle`]s.
+//! This module contains the [`Mesh`] struct which mostly implements OBJ, PLY
+//! and STL parsing to construct a [`BVHTree`] of packets of faces sharing
+//! one copy of the mesh's vertex arrays, or of [`MovingTriangle`]s for meshes
+//! deforming over the shutter.
 
 use std::{
+    collections::HashMap,
+    f64::consts::PI,
     fs::File,
     io::{BufRead, BufReader},
     path::Path,
+    sync::Arc,
 };
 
 use crate::{
     aabb::Aabb,
     bvh::BVHTree,
+    film::ColourSpace,
     hit::{HitRecord, Hittable},
     hit_list::HittableList,
     interval::Interval,
-    material::Material,
+    material::{Material, MaterialArena, MaterialId},
     ray::Ray,
-    triangle::Triangle,
-    vec3::Vec3,
+    scene::SceneSettings,
+    texture::{Textured, VertexColours},
+    tolerance::Tolerances,
+    transform::{Keyframe, Transform},
+    triangle::{
+        closest_point_on, face_normal, group_by_centroid, interpolate_normal, interpolate_uv,
+        intersect_lanes, nearest_lane, triangle_bound, uv_tangent, LaneVertices, MovingTriangle,
+        BARYCENTRIC_UVS, PACKET_LANES,
+    },
+    utils::deg_to_rad,
+    vec3::{cross, dot, Vec3},
 };
 
 use crate::vec3;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// A corner of a [`MeshData`] face, made of indices into the mesh's vertex
+/// attribute arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceVertex {
+    /// Index into [`MeshData::positions`].
+    pub position: usize,
+    /// Index into [`MeshData::normals`], if given.
+    pub normal: Option<usize>,
+    /// Index into [`MeshData::uvs`], if given.
+    pub uv: Option<usize>,
+}
+
+/// Changes baked into the vertices of a mesh as it's loaded, for models that
+/// come in all sorts of units and origins. In order, the mesh is recentred
+/// on the origin, scaled so its longest side is one unit long, then scaled,
+/// rotated (Euler angles in degrees) and translated like a
+/// [`Keyframe`]. See
+/// [`Mesh::from_obj_with_options()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     mesh::{FaceVertex, MeshData, MeshLoadOptions},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let corner = |position| FaceVertex { position, normal: None, uv: None };
+/// let tile = MeshData {
+///     positions: vec![vec3![100.0, 0.0, 0.0], vec3![140.0, 0.0, 0.0], vec3![100.0, 20.0, 0.0]],
+///     faces: vec![[corner(0), corner(1), corner(2)]],
+///     ..MeshData::default()
+/// };
+///
+/// // a unit wide tile standing on the origin
+/// let options = MeshLoadOptions::default()
+///     .recentred()
+///     .normalised()
+///     .with_translation(vec3![0.0, 0.25, 0.0]);
+/// let placed = options.apply(tile);
+/// assert_eq!(placed.positions[0], vec3![-0.5, 0.0, 0.0]);
+/// assert_eq!(placed.positions[1], vec3![0.5, 0.0, 0.0]);
+/// assert_eq!(placed.positions[2], vec3![-0.5, 0.5, 0.0]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshLoadOptions {
+    /// Whether to move the centre of the mesh's bounds to the origin.
+    pub recentre: bool,
+    /// Whether to scale the mesh evenly so the longest side of its bounds is
+    /// one unit long.
+    pub normalise: bool,
+    /// The per-axis scale, after recentring and normalising.
+    pub scale: Vec3,
+    /// The rotation as Euler angles in degrees, see
+    /// [`Transform::rotate_euler()`].
+    pub rotation: Vec3,
+    /// The translation, applied last.
+    pub translation: Vec3,
+}
+
+impl Default for MeshLoadOptions {
+    fn default() -> Self {
+        Self {
+            recentre: false,
+            normalise: false,
+            scale: vec3![1.0, 1.0, 1.0],
+            rotation: vec3![0.0, 0.0, 0.0],
+            translation: vec3![0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl MeshLoadOptions {
+    /// Move the centre of the mesh's bounds to the origin.
+    #[must_use]
+    pub fn recentred(self) -> Self {
+        Self {
+            recentre: true,
+            ..self
+        }
+    }
+
+    /// Scale the mesh so the longest side of its bounds is one unit long.
+    #[must_use]
+    pub fn normalised(self) -> Self {
+        Self {
+            normalise: true,
+            ..self
+        }
+    }
+
+    /// Set the scale.
+    #[must_use]
+    pub fn with_scale(self, scale: Vec3) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// Set the rotation.
+    #[must_use]
+    pub fn with_rotation(self, rotation: Vec3) -> Self {
+        Self { rotation, ..self }
+    }
+
+    /// Set the translation.
+    #[must_use]
+    pub fn with_translation(self, translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..self
+        }
+    }
+
+    /// The [`Transform`] the options make of `data`, which depends on its
+    /// bounds when recentring or normalising. Meshes made to line up with
+    /// `data`, such as the end of a [deforming](Mesh::deforming()) mesh,
+    /// can be moved by the same transform to keep them lined up.
+    ///
+    /// # Panics
+    ///
+    /// If any axis of the [`scale`](field@MeshLoadOptions::scale) is zero.
+    #[must_use]
+    pub fn transform(&self, data: &MeshData) -> Transform {
+        let mut transform = Transform::identity();
+        if let Some((min, max)) = data.bounds() {
+            if self.recentre {
+                transform = Transform::translate(-(min + max) / 2.0);
+            }
+            let longest = (0..3).map(|axis| max[axis] - min[axis]).fold(0.0, f64::max);
+            if self.normalise && longest > 0.0 {
+                let factor = 1.0 / longest;
+                transform = transform.then(&Transform::scale(vec3![factor, factor, factor]));
+            }
+        }
+        transform.then(
+            &Keyframe::at(0.0, self.translation)
+                .with_rotation(self.rotation)
+                .with_scale(self.scale)
+                .transform(),
+        )
+    }
+
+    /// Bake the options into `data`, see [`MeshData::transformed()`].
+    ///
+    /// # Panics
+    ///
+    /// If any axis of the [`scale`](field@MeshLoadOptions::scale) is zero.
+    #[must_use]
+    pub fn apply(&self, data: MeshData) -> MeshData {
+        let transform = self.transform(&data);
+        data.transformed(&transform)
+    }
+}
+
+/// How [`MeshData::with_projected_uvs()`] makes up texture coordinates for
+/// faces that weren't given any, fitted to the bounds of the mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum UvProjection {
+    /// Each face is projected flat along the axis it faces most, so boxy
+    /// shapes get an undistorted texture on every side, with the texture
+    /// spanning the mesh's longest side.
+    Box,
+    /// Around the centre of the mesh by longitude and latitude, like a
+    /// [`Sphere`](crate::sphere::Sphere), with `v = 0.0` at the bottom.
+    Spherical,
+    /// Around the vertical axis through the centre of the mesh by longitude,
+    /// and up it from the bottom of the mesh to the top.
+    Cylindrical,
+}
+
+/// The indexed geometry of a [`Mesh`] as loaded from a file, with every face
+/// triangulated. Kept alongside the [`BVHTree`] for tools that need the
+/// surface itself, such as [baking](crate::bake).
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    /// Vertex positions.
+    pub positions: Vec<Vec3>,
+    /// Vertex normals.
+    pub normals: Vec<Vec3>,
+    /// Texture coordinates as `[u, v]`, with `v = 0.0` at the bottom of the
+    /// texture.
+    pub uvs: Vec<[f64; 2]>,
+    /// Linear vertex colours, indexed like [`MeshData::positions`], or empty
+    /// if the mesh has none.
+    pub colours: Vec<Vec3>,
+    /// Triangles made of three [`FaceVertex`]s each.
+    pub faces: Vec<[FaceVertex; 3]>,
+}
+
+impl MeshData {
+    /// Whether every face has texture coordinates.
+    #[must_use]
+    pub fn has_uvs(&self) -> bool {
+        !self.uvs.is_empty() && self.faces.iter().flatten().all(|fv| fv.uv.is_some())
+    }
+
+    /// Get the positions of the corners of `face`.
+    #[must_use]
+    pub fn face_positions(&self, face: &[FaceVertex; 3]) -> [Vec3; 3] {
+        face.map(|fv| self.positions[fv.position])
+    }
+
+    /// Get the normals of the corners of `face`, or [`None`] if the mesh has no
+    /// normals. Corners without a normal index use the normal with the same
+    /// index as their position.
+    #[must_use]
+    pub fn face_normals(&self, face: &[FaceVertex; 3]) -> Option<[Vec3; 3]> {
+        if self.normals.is_empty() {
+            None
+        } else {
+            Some(face.map(|fv| self.normals[fv.normal.unwrap_or(fv.position)]))
+        }
+    }
+
+    /// Get the texture coordinates of the corners of `face`, or [`None`] if any
+    /// corner lacks them.
+    #[must_use]
+    pub fn face_uvs(&self, face: &[FaceVertex; 3]) -> Option<[[f64; 2]; 3]> {
+        let [a, b, c] = face.map(|fv| fv.uv.map(|idx| self.uvs[idx]));
+        Some([a?, b?, c?])
+    }
+
+    /// Get the vertex colours of the corners of `face`, or [`None`] if the mesh
+    /// has no vertex colours, or none for one of the face's positions.
+    #[must_use]
+    pub fn face_colours(&self, face: &[FaceVertex; 3]) -> Option<[Vec3; 3]> {
+        let [a, b, c] = face.map(|fv| self.colours.get(fv.position).copied());
+        Some([a?, b?, c?])
+    }
+
+    /// Read the mesh file at `path` by its extension, a PLY file with
+    /// [`MeshData::from_ply()`] for `.ply`, an STL file with
+    /// [`MeshData::from_stl()`] for `.stl` and an OBJ file with
+    /// [`MeshData::from_obj()`] otherwise.
+    ///
+    /// # Panics
+    ///
+    /// If a prefix or required part of an OBJ line isn't found.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or parsed.
+    pub fn load<P: AsRef<Path>>(path: &P, settings: &SceneSettings) -> Result<Self> {
+        let is = |extension: &str| {
+            path.as_ref()
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+        };
+        if is("ply") {
+            Self::from_ply(path, settings)
+        } else if is("stl") {
+            Self::from_stl(path, settings)
+        } else {
+            Self::from_obj(path, settings)
+        }
+    }
+
+    /// Read the OBJ file at `path`, converting positions and normals with the
+    /// unit scale and up axis of `settings`, to change before building a
+    /// [`Mesh`] with [`Mesh::from_data()`]. Corners without a normal are
+    /// given a smooth one up to the crease angle of `settings`, see
+    /// [`MeshData::with_smooth_normals()`].
+    ///
+    /// # Panics
+    ///
+    /// If a prefix or required part of a line isn't found.
+    ///
+    /// # Errors
+    ///
+    /// If opening the file or parsing things that are supposed to be numbers
+    /// fails, or a face uses a vertex that doesn't exist.
+    pub fn from_obj<P: AsRef<Path>>(path: &P, settings: &SceneSettings) -> Result<Self> {
+        read_obj(path, settings)
+    }
+
+    /// Read the PLY file at `path`, ASCII or binary of either byte order, such
+    /// as a 3D scan or one of the Stanford models, converting positions and
+    /// normals with the unit scale and up axis of `settings`. Vertices keep
+    /// their normals (`nx`, `ny`, `nz`), texture coordinates (`s`, `t` or
+    /// `u`, `v`) and colours (`red`, `green`, `blue`), which are decoded from
+    /// sRGB, bytes out of `255` and shorts out of `65535`. Faces are read from
+    /// the `vertex_indices` list and triangulated by
+    /// [`MeshData::add_polygon()`]. Any other elements are skipped.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, the header is malformed, the body ends
+    /// early, or a face uses a vertex that doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{mesh::MeshData, scene::SceneSettings, vec3, vec3::Vec3};
+    ///
+    /// // a square of coloured corners facing +z, written both ways
+    /// let header = |format| {
+    ///     format!(
+    ///         "ply\nformat {format} 1.0\ncomment made by hand\nelement vertex 4\n\
+    ///          property float x\nproperty float y\nproperty float z\n\
+    ///          property float nx\nproperty float ny\nproperty float nz\n\
+    ///          property uchar red\nproperty uchar green\nproperty uchar blue\n\
+    ///          element face 1\nproperty list uchar int vertex_indices\nend_header\n"
+    ///     )
+    /// };
+    /// let corners = [
+    ///     ([0.0_f32, 0.0], [255_u8, 0, 0]),
+    ///     ([1.0, 0.0], [0, 255, 0]),
+    ///     ([1.0, 1.0], [0, 0, 255]),
+    ///     ([0.0, 1.0], [255, 255, 255]),
+    /// ];
+    ///
+    /// let mut ascii = header("ascii");
+    /// let mut binary = header("binary_big_endian").into_bytes();
+    /// for ([x, y], [r, g, b]) in corners {
+    ///     ascii += &format!("{x} {y} 0 0 0 1 {r} {g} {b}\n");
+    ///     for value in [x, y, 0.0, 0.0, 0.0, 1.0] {
+    ///         binary.extend(value.to_be_bytes());
+    ///     }
+    ///     binary.extend([r, g, b]);
+    /// }
+    /// ascii += "4 0 1 2 3\n";
+    /// binary.push(4);
+    /// for index in 0_i32..4 {
+    ///     binary.extend(index.to_be_bytes());
+    /// }
+    ///
+    /// let dir = std::env::temp_dir();
+    /// std::fs::write(dir.join("rustrace_square_ascii.ply"), ascii).unwrap();
+    /// std::fs::write(dir.join("rustrace_square_binary.ply"), binary).unwrap();
+    /// let settings = SceneSettings::default();
+    /// let square = MeshData::from_ply(&dir.join("rustrace_square_ascii.ply"), &settings).unwrap();
+    /// let binary = MeshData::from_ply(&dir.join("rustrace_square_binary.ply"), &settings).unwrap();
+    ///
+    /// assert_eq!(square.faces, binary.faces);
+    /// assert_eq!(square.positions, binary.positions);
+    /// assert_eq!(square.colours, binary.colours);
+    /// assert_eq!(square.faces.len(), 2);
+    /// assert_eq!(square.positions[2], vec3![1.0, 1.0, 0.0]);
+    /// assert_eq!(square.normals[3], vec3![0.0, 0.0, 1.0]);
+    /// assert_eq!(square.colours[1], vec3![0.0, 1.0, 0.0]);
+    /// assert!(square.faces.iter().flatten().all(|fv| fv.normal == Some(fv.position)));
+    /// ```
+    pub fn from_ply<P: AsRef<Path>>(path: &P, settings: &SceneSettings) -> Result<Self> {
+        read_ply(path, settings)
+    }
+
+    /// Read the STL file at `path`, binary or ASCII, as exported by CAD tools
+    /// and slicers, converting positions with the unit scale and up axis of
+    /// `settings`. STL repeats each corner for every facet it's in, so
+    /// identical corners are welded into one vertex. The facet normals are
+    /// ignored, as triangles without vertex normals are shaded flat anyway.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, a binary file is cut short or an ASCII one
+    /// has a number that doesn't parse.
+    pub fn from_stl<P: AsRef<Path>>(path: &P, settings: &SceneSettings) -> Result<Self> {
+        read_stl(path, settings)
+    }
+
+    /// Give every face without texture coordinates some made up by
+    /// `projection`, so image textures can still be applied to meshes
+    /// exported without them. Faces that have them keep them. Each face gets
+    /// its own texture coordinates, and faces straddling the seam of a
+    /// [spherical](UvProjection::Spherical) or
+    /// [cylindrical](UvProjection::Cylindrical) projection run on past
+    /// `u = 1.0`, which textures wrap, rather than across the whole texture.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     mesh::{FaceVertex, MeshData, UvProjection},
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let corner = |position| FaceVertex { position, normal: None, uv: None };
+    /// // a wall 4 wide and 2 high, facing +z
+    /// let wall = MeshData {
+    ///     positions: vec![
+    ///         vec3![0.0, 0.0, 0.0],
+    ///         vec3![4.0, 0.0, 0.0],
+    ///         vec3![4.0, 2.0, 0.0],
+    ///         vec3![0.0, 2.0, 0.0],
+    ///     ],
+    ///     faces: vec![[corner(0), corner(1), corner(2)], [corner(0), corner(2), corner(3)]],
+    ///     ..MeshData::default()
+    /// };
+    /// assert!(!wall.has_uvs());
+    ///
+    /// let wall = wall.with_projected_uvs(UvProjection::Box);
+    /// assert!(wall.has_uvs());
+    /// // the texture spans the width, and keeps its aspect up the wall
+    /// assert_eq!(wall.face_uvs(&wall.faces[0]), Some([[0.0, 0.0], [1.0, 0.0], [1.0, 0.5]]));
+    ///
+    /// // around a cylinder, the face across the seam at -x doesn't span the
+    /// // whole texture
+    /// let cylinder = MeshData {
+    ///     positions: vec![
+    ///         vec3![-1.0, 0.0, 0.1],
+    ///         vec3![-1.0, 0.0, -0.1],
+    ///         vec3![-1.0, 1.0, 0.0],
+    ///         vec3![1.0, 0.0, 0.0],
+    ///     ],
+    ///     faces: vec![[corner(0), corner(1), corner(2)]],
+    ///     ..MeshData::default()
+    /// }
+    /// .with_projected_uvs(UvProjection::Cylindrical);
+    /// let [a, b, c] = cylinder.face_uvs(&cylinder.faces[0]).unwrap();
+    /// assert!((a[0] - b[0]).abs() < 0.1 && (a[0] - c[0]).abs() < 0.1);
+    /// assert_eq!([a[1], b[1], c[1]], [0.0, 0.0, 1.0]);
+    /// ```
+    #[must_use]
+    pub fn with_projected_uvs(mut self, projection: UvProjection) -> Self {
+        let (mut min, mut max) = (
+            vec3![f64::INFINITY, f64::INFINITY, f64::INFINITY],
+            vec3![f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY],
+        );
+        for p in &self.positions {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+        let centre = (min + max) / 2.0;
+        let size = max - min;
+        let longest = size[0].max(size[1]).max(size[2]);
+        // fractions across the bounds, kept finite for flat meshes
+        let across = |value: f64, axis: usize, span: f64| {
+            if span > 0.0 {
+                (value - min[axis]) / span
+            } else {
+                0.5
+            }
+        };
+        let longitude = |p: Vec3| (-(p[2] - centre[2])).atan2(p[0] - centre[0]) / (2.0 * PI) + 0.5;
+
+        for k in 0..self.faces.len() {
+            let face = self.faces[k];
+            if face.iter().all(|fv| fv.uv.is_some()) {
+                continue;
+            }
+            let corners = self.face_positions(&face);
+            let mut uvs = match projection {
+                UvProjection::Box => {
+                    let facing = cross(&(corners[1] - corners[0]), &(corners[2] - corners[0]));
+                    let [x, y, z] = [0, 1, 2].map(|axis| facing[axis].abs());
+                    let (u_axis, v_axis) = if x >= y && x >= z {
+                        (2, 1)
+                    } else if y >= z {
+                        (0, 2)
+                    } else {
+                        (0, 1)
+                    };
+                    corners.map(|p| {
+                        [
+                            across(p[u_axis], u_axis, longest),
+                            across(p[v_axis], v_axis, longest),
+                        ]
+                    })
+                }
+                UvProjection::Spherical => corners.map(|p| {
+                    let dir = p - centre;
+                    let v = if dir.near_zero() {
+                        0.5
+                    } else {
+                        (-dir[1] / dir.length()).clamp(-1.0, 1.0).acos() / PI
+                    };
+                    [longitude(p), v]
+                }),
+                UvProjection::Cylindrical => {
+                    corners.map(|p| [longitude(p), across(p[1], 1, size[1])])
+                }
+            };
+            if projection != UvProjection::Box {
+                // carry on round past the seam rather than back across
+                let low = uvs.iter().map(|uv| uv[0]).fold(f64::INFINITY, f64::min);
+                let high = uvs.iter().map(|uv| uv[0]).fold(f64::NEG_INFINITY, f64::max);
+                if high - low > 0.5 {
+                    for uv in &mut uvs {
+                        if uv[0] < 0.5 {
+                            uv[0] += 1.0;
+                        }
+                    }
+                }
+            }
+            for (fv, uv) in self.faces[k].iter_mut().zip(uvs) {
+                fv.uv = Some(self.uvs.len());
+                self.uvs.push(uv);
+            }
+        }
+        self
+    }
+
+    /// The lowest and highest corners of the bounds of the vertices, or
+    /// [`None`] if there are none.
+    #[must_use]
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let first = *self.positions.first()?;
+        Some(self.positions.iter().fold((first, first), |(min, max), p| {
+            (
+                vec3![min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+                vec3![max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+            )
+        }))
+    }
+
+    /// Move the vertices and normals by `transform`. A transform that mirrors
+    /// the mesh also reverses the winding of its faces, so they keep facing
+    /// the same way as their normals.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     mesh::{FaceVertex, MeshData},
+    ///     transform::Transform,
+    ///     vec3,
+    ///     vec3::{cross, Vec3},
+    /// };
+    ///
+    /// let corner = |position| FaceVertex { position, normal: Some(0), uv: None };
+    /// let facing = MeshData {
+    ///     positions: vec![vec3![0.0, 0.0, 0.0], vec3![1.0, 0.0, 0.0], vec3![0.0, 1.0, 0.0]],
+    ///     normals: vec![vec3![0.0, 0.0, 1.0]],
+    ///     faces: vec![[corner(0), corner(1), corner(2)]],
+    ///     ..MeshData::default()
+    /// };
+    ///
+    /// let mirrored = facing.transformed(&Transform::scale(vec3![1.0, 1.0, -2.0]));
+    /// assert_eq!(mirrored.normals[0], vec3![0.0, 0.0, -1.0]);
+    /// // the face is wound to face the same way as its normal
+    /// let [a, b, c] = mirrored.face_positions(&mirrored.faces[0]);
+    /// assert!(cross(&(b - a), &(c - a))[2] < 0.0);
+    /// ```
+    #[must_use]
+    pub fn transformed(mut self, transform: &Transform) -> Self {
+        for p in &mut self.positions {
+            *p = transform.point(*p);
+        }
+        for n in &mut self.normals {
+            *n = transform.normal(*n).unit();
+        }
+        let [x, y, z] = [
+            vec3![1.0, 0.0, 0.0],
+            vec3![0.0, 1.0, 0.0],
+            vec3![0.0, 0.0, 1.0],
+        ]
+        .map(|axis| transform.vector(axis));
+        if dot(&x, &cross(&y, &z)) < 0.0 {
+            for face in &mut self.faces {
+                face.swap(1, 2);
+            }
+        }
+        self
+    }
+
+    /// Give every corner without a normal one averaged from the faces around
+    /// its vertex, weighted by the angle each makes there, so curved surfaces
+    /// exported without normals shade smoothly. Faces meeting at more than
+    /// `crease_angle` degrees aren't averaged together, keeping hard edges
+    /// such as the corners of a box, and `0.0` shades every face flat.
+    /// Corners with the same vertex and normal share it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{mesh::{FaceVertex, MeshData}, vec3, vec3::Vec3};
+    ///
+    /// // a low pyramid, and a wall standing on its edge at right angles
+    /// let corner = |position| FaceVertex { position, normal: None, uv: None };
+    /// let pyramid = MeshData {
+    ///     positions: vec![
+    ///         vec3![0.0, 0.2, 0.0],
+    ///         vec3![-1.0, 0.0, 1.0],
+    ///         vec3![1.0, 0.0, 1.0],
+    ///         vec3![1.0, 0.0, -1.0],
+    ///         vec3![-1.0, 0.0, -1.0],
+    ///         vec3![1.0, -2.0, 1.0],
+    ///     ],
+    ///     faces: vec![
+    ///         [corner(0), corner(1), corner(2)],
+    ///         [corner(0), corner(2), corner(3)],
+    ///         [corner(0), corner(3), corner(4)],
+    ///         [corner(0), corner(4), corner(1)],
+    ///         [corner(1), corner(5), corner(2)],
+    ///     ],
+    ///     ..MeshData::default()
+    /// };
+    ///
+    /// let smooth = pyramid.clone().with_smooth_normals(60.0);
+    /// // the tip points straight up
+    /// let [tip, _, side] = smooth.face_normals(&smooth.faces[0]).unwrap();
+    /// assert!((tip - vec3![0.0, 1.0, 0.0]).length() < 1e-12);
+    /// // but the wall doesn't round the edge it meets the pyramid at
+    /// let [_, _, wall] = smooth.face_normals(&smooth.faces[4]).unwrap();
+    /// assert!((wall - vec3![0.0, 0.0, 1.0]).length() < 1e-12);
+    /// assert!(side[2] > 0.0 && side[1] > 0.9);
+    ///
+    /// // with no crease angle the faces are flat
+    /// let flat = pyramid.with_smooth_normals(0.0);
+    /// let [a, b, c] = flat.face_normals(&flat.faces[0]).unwrap();
+    /// assert!((a - b).length() < 1e-12 && (b - c).length() < 1e-12 && a[2] > 0.0);
+    /// ```
+    #[must_use]
+    pub fn with_smooth_normals(mut self, crease_angle: f64) -> Self {
+        let unit_normals: Vec<Option<Vec3>> = self
+            .faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] = self.face_positions(face);
+                let normal = cross(&(b - a), &(c - a));
+                (normal.length_squared() > 0.0).then(|| normal.unit())
+            })
+            .collect();
+        // the angle of each corner, between the edges leaving it
+        let corner_angles: Vec<[f64; 3]> = self
+            .faces
+            .iter()
+            .map(|face| {
+                let p = self.face_positions(face);
+                [0, 1, 2].map(|i| {
+                    let e1 = p[(i + 1) % 3] - p[i];
+                    let e2 = p[(i + 2) % 3] - p[i];
+                    cross(&e1, &e2).length().atan2(dot(&e1, &e2))
+                })
+            })
+            .collect();
+        let mut around = vec![Vec::new(); self.positions.len()];
+        for (k, face) in self.faces.iter().enumerate() {
+            for (corner, fv) in face.iter().enumerate() {
+                around[fv.position].push((k, corner));
+            }
+        }
+
+        let min_cos = deg_to_rad(crease_angle).cos();
+        let mut shared = HashMap::new();
+        for k in 0..self.faces.len() {
+            for corner in 0..3 {
+                let fv = self.faces[k][corner];
+                if fv.normal.is_some() {
+                    continue;
+                }
+                let own = unit_normals[k];
+                let mut sum = Vec3::default();
+                for &(other, other_corner) in &around[fv.position] {
+                    let Some(normal) = unit_normals[other] else {
+                        continue;
+                    };
+                    // degenerate faces take the average of all around them
+                    if own.is_none_or(|own| other == k || dot(&own, &normal) >= min_cos) {
+                        sum += normal * corner_angles[other][other_corner];
+                    }
+                }
+                let normal = if sum.length_squared() == 0.0 {
+                    own.unwrap_or(vec3![0.0, 1.0, 0.0])
+                } else {
+                    sum.unit()
+                };
+                let index = *shared
+                    .entry((fv.position, normal.e.map(f64::to_bits)))
+                    .or_insert_with(|| {
+                        self.normals.push(normal);
+                        self.normals.len() - 1
+                    });
+                self.faces[k][corner].normal = Some(index);
+            }
+        }
+        self
+    }
+
+    /// Triangulate the planar polygon with the corners `polygon`, in order,
+    /// and add its triangles to the faces. Polygons are split by ear
+    /// clipping in their plane, so concave ones are covered exactly, with
+    /// the triangles wound the same way as the polygon and each corner
+    /// keeping its own normal and texture coordinates. Self-intersecting
+    /// polygons, which have no such split, are fanned once no ears are left.
+    /// Polygons with fewer than three corners are skipped.
+    ///
+    /// # Panics
+    ///
+    /// If a corner's position is outside of [`MeshData::positions`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     mesh::{FaceVertex, MeshData},
+    ///     vec3,
+    ///     vec3::{cross, dot, Vec3},
+    /// };
+    /// use rand::{rngs::SmallRng, RngExt as _, SeedableRng};
+    ///
+    /// let corner = |position| FaceVertex { position, normal: None, uv: None };
+    /// // twice the area of each face, along its normal
+    /// let areas = |data: &MeshData| -> Vec<Vec3> {
+    ///     data.faces
+    ///         .iter()
+    ///         .map(|face| {
+    ///             let [a, b, c] = data.face_positions(face);
+    ///             cross(&(b - a), &(c - a))
+    ///         })
+    ///         .collect()
+    /// };
+    ///
+    /// // an arrowhead, which a fan from its first corner would spill out of
+    /// let mut arrow = MeshData {
+    ///     positions: vec![
+    ///         vec3![1.0, -1.0, 0.0],
+    ///         vec3![0.0, 2.0, 0.0],
+    ///         vec3![-1.0, -1.0, 0.0],
+    ///         vec3![0.0, 0.0, 0.0],
+    ///     ],
+    ///     ..MeshData::default()
+    /// };
+    /// arrow.add_polygon(&[corner(0), corner(1), corner(2), corner(3)]);
+    /// assert_eq!(arrow.faces.len(), 2);
+    /// // both facing +z like the polygon, covering its area of 2 exactly
+    /// assert!(areas(&arrow).iter().all(|twice| twice[2] > 0.0));
+    /// assert!((areas(&arrow).iter().map(|twice| twice[2]).sum::<f64>() - 4.0).abs() < 1e-12);
+    ///
+    /// // random concave star polygons in random planes
+    /// let mut rng = SmallRng::seed_from_u64(780);
+    /// for _ in 0..200 {
+    ///     let corners = rng.random_range(3..24);
+    ///     let norm = Vec3::random_unit(&mut rng);
+    ///     let (tangent, bitangent) = norm.orthonormal_basis();
+    ///     let mut star = MeshData::default();
+    ///     let mut twice_area = 0.0;
+    ///     for k in 0..corners {
+    ///         let angle = std::f64::consts::TAU * (f64::from(k) + 0.5 * rng.random_range(0.0..1.0))
+    ///             / f64::from(corners);
+    ///         let radius = rng.random_range(0.1..1.0);
+    ///         let (sin, cos) = angle.sin_cos();
+    ///         star.positions.push(tangent * (radius * cos) + bitangent * (radius * sin));
+    ///     }
+    ///     for (k, &p) in star.positions.iter().enumerate() {
+    ///         let next = star.positions[(k + 1) % star.positions.len()];
+    ///         twice_area += dot(&cross(&p, &next), &norm);
+    ///     }
+    ///     let polygon: Vec<_> = (0..star.positions.len()).map(corner).collect();
+    ///     star.add_polygon(&polygon);
+    ///
+    ///     assert_eq!(star.faces.len(), polygon.len() - 2);
+    ///     assert!(areas(&star).iter().all(|twice| dot(twice, &norm) > -1e-12));
+    ///     let covered: f64 = areas(&star).iter().map(|twice| dot(twice, &norm)).sum();
+    ///     assert!((covered - twice_area).abs() < 1e-9);
+    /// }
+    /// ```
+    pub fn add_polygon(&mut self, polygon: &[FaceVertex]) {
+        if polygon.len() < 3 {
+            return;
+        }
+        if let [a, b, c] = *polygon {
+            self.faces.push([a, b, c]);
+            return;
+        }
+
+        // Newell's normal, which is stable for concave polygons
+        let positions: Vec<Vec3> = polygon
+            .iter()
+            .map(|fv| self.positions[fv.position])
+            .collect();
+        let mut norm = Vec3::default();
+        for (k, p) in positions.iter().enumerate() {
+            norm += cross(p, &positions[(k + 1) % positions.len()]);
+        }
+        if norm.near_zero() {
+            self.fan(polygon, &(0..polygon.len()).collect::<Vec<_>>());
+            return;
+        }
+
+        // flatten onto the plane, wound anticlockwise
+        let (tangent, bitangent) = norm.unit().orthonormal_basis();
+        let flat: Vec<[f64; 2]> = positions
+            .iter()
+            .map(|p| [dot(p, &tangent), dot(p, &bitangent)])
+            .collect();
+        let winding = dot(&cross(&tangent, &bitangent), &norm).signum();
+        let turn = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| {
+            winding * ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]))
+        };
+
+        let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+        while remaining.len() > 3 {
+            let len = remaining.len();
+            let ear = (0..len).find(|&k| {
+                let (a, b, c) = (
+                    remaining[(k + len - 1) % len],
+                    remaining[k],
+                    remaining[(k + 1) % len],
+                );
+                let (pa, pb, pc) = (flat[a], flat[b], flat[c]);
+                // convex, with no other vertex inside or on its edges
+                turn(pa, pb, pc) > 0.0
+                    && remaining.iter().all(|&other| {
+                        let p = flat[other];
+                        [a, b, c]
+                            .iter()
+                            .any(|&own| polygon[own].position == polygon[other].position)
+                            || turn(pa, pb, p) < 0.0
+                            || turn(pb, pc, p) < 0.0
+                            || turn(pc, pa, p) < 0.0
+                    })
+            });
+            let Some(k) = ear else {
+                self.fan(polygon, &remaining);
+                return;
+            };
+            let (a, c) = (remaining[(k + len - 1) % len], remaining[(k + 1) % len]);
+            self.faces
+                .push([polygon[a], polygon[remaining[k]], polygon[c]]);
+            remaining.remove(k);
+        }
+        let [a, b, c] = [remaining[0], remaining[1], remaining[2]];
+        self.faces.push([polygon[a], polygon[b], polygon[c]]);
+    }
+
+    /// Add a fan of triangles from the first of the `corners` of `polygon`.
+    fn fan(&mut self, polygon: &[FaceVertex], corners: &[usize]) {
+        for pair in corners[1..].windows(2) {
+            self.faces
+                .push([polygon[corners[0]], polygon[pair[0]], polygon[pair[1]]]);
+        }
+    }
+}
 
 /// The [`Mesh`] struct itself, contains a [`BVHTree`] that it defers
-/// [`Hittable::hit()`] and [`Hittable::bound()`] to.
+/// [`Hittable::hit()`] and [`Hittable::bound()`] to. Its leaves hold packets
+/// of up to [`PACKET_LANES`] faces, each keeping only the vertex positions
+/// of its faces, laid out to be tested at once, and their indices into the
+/// one copy of the [`MeshData`] that normals, texture coordinates and
+/// materials are read from for the nearest hit.
 #[derive(Debug)]
 pub struct Mesh {
     bvh: BVHTree,
+    buffers: Arc<MeshBuffers>,
+    /// The geometry at the end of the shutter, for deforming meshes.
+    end: Option<MeshData>,
 }
 
 impl Mesh {
     // TODO: investigate moving elsewhere
-    /// Basic OBJ parser, builds up a [`HittableList`] of [`Triangle`]s which
-    /// it then constructs a [`BVHTree`] out of.
+    /// Basic OBJ parser, builds up a [`MeshData`] which it then constructs a
+    /// [`BVHTree`] over with [`Mesh::from_data()`]. Faces with more than three
+    /// corners are triangulated by [`MeshData::add_polygon()`].
     ///
     /// # Panics
     ///
@@ -41,107 +893,455 @@ impl Mesh {
     ///
     /// # Errors
     ///
-    /// If opening the file or parsing things that are supposed to be numbers fails.
+    /// If opening the file or parsing things that are supposed to be numbers
+    /// fails, or a face uses a vertex that doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{material::Lambertian, mesh::Mesh, vec3, vec3::Vec3};
+    ///
+    /// // an L-shaped hexagon, each corner with its own normal
+    /// let path = std::env::temp_dir().join("rustrace_l_shape.obj");
+    /// std::fs::write(
+    ///     &path,
+    ///     "v 0 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\n\
+    ///      vn 0 0 1\nvn 0.1 0 1\nvn 0.2 0 1\nvn 0.3 0 1\nvn 0.4 0 1\nvn 0.5 0 1\n\
+    ///      f 1//1 2//2 3//3 4//4 5//5 6//6\n",
+    /// )
+    /// .unwrap();
+    ///
+    /// let mesh = Mesh::from_obj(&path, &Lambertian::new(vec3![0.5, 0.5, 0.5])).unwrap();
+    /// let data = mesh.data();
+    /// assert_eq!(data.faces.len(), 4);
+    /// // the normals follow the corners they were given for
+    /// for face in &data.faces {
+    ///     assert!(face.iter().all(|fv| fv.normal == Some(fv.position)));
+    /// }
+    /// ```
     pub fn from_obj<P: AsRef<Path>>(path: &P, mat: &dyn Material) -> Result<Self> {
-        fn parse_face_vertex(s: &str) -> Result<(usize, usize)> {
-            let parts: Vec<&str> = s.split('/').collect();
-            let v_idx: usize = parts[0].parse::<usize>()? - 1;
-            let n_idx = if parts.len() > 2 && !parts[2].is_empty() {
-                parts[2].parse::<usize>()? - 1
-            } else {
-                v_idx
-            };
+        Self::from_obj_with_settings(path, mat, &SceneSettings::default())
+    }
 
-            Ok((v_idx, n_idx))
+    /// Like [`Mesh::from_obj()`], but converts positions and normals with the
+    /// unit scale and up axis of `settings` as they are read.
+    ///
+    /// # Panics
+    ///
+    /// If a prefix or required part of a line isn't found.
+    ///
+    /// # Errors
+    ///
+    /// If opening the file or parsing things that are supposed to be numbers fails.
+    pub fn from_obj_with_settings<P: AsRef<Path>>(
+        path: &P,
+        mat: &dyn Material,
+        settings: &SceneSettings,
+    ) -> Result<Self> {
+        Ok(Self::from_data(MeshData::from_obj(path, settings)?, mat))
+    }
+
+    /// Like [`Mesh::from_obj()`], but changes the mesh by `options` as it's
+    /// loaded, such as to recentre it and scale it to a unit size whatever
+    /// units it was modelled in.
+    ///
+    /// # Panics
+    ///
+    /// If a prefix or required part of a line isn't found.
+    ///
+    /// # Errors
+    ///
+    /// If opening the file or parsing things that are supposed to be numbers
+    /// fails, a face uses a vertex that doesn't exist, or the options'
+    /// [`scale`](field@MeshLoadOptions::scale) is zero or not finite on any
+    /// axis.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::Hittable,
+    ///     material::Lambertian,
+    ///     mesh::{Mesh, MeshLoadOptions},
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let options = MeshLoadOptions::default()
+    ///     .recentred()
+    ///     .normalised()
+    ///     .with_translation(vec3![0.0, 0.0, -3.0]);
+    /// let mat = Lambertian::new(vec3![0.5, 0.5, 0.5]);
+    /// let teapot = Mesh::from_obj_with_options(&"objs/teapot.obj", &mat, &options).unwrap();
+    ///
+    /// let bound = teapot.bound();
+    /// let longest = bound.x.size().max(bound.y.size()).max(bound.z.size());
+    /// assert!((longest - 1.0).abs() < 0.01);
+    /// assert!((bound.centroid() - vec3![0.0, 0.0, -3.0]).length() < 0.01);
+    ///
+    /// // flattening the mesh entirely is an error rather than a panic
+    /// let flat = options.with_scale(vec3![1.0, 0.0, 1.0]);
+    /// assert!(Mesh::from_obj_with_options(&"objs/teapot.obj", &mat, &flat).is_err());
+    /// ```
+    pub fn from_obj_with_options<P: AsRef<Path>>(
+        path: &P,
+        mat: &dyn Material,
+        options: &MeshLoadOptions,
+    ) -> Result<Self> {
+        if options.scale.e.iter().any(|&s| s == 0.0 || !s.is_finite()) {
+            bail!("Mesh scale must be finite and non-zero on every axis");
         }
+        let data = MeshData::from_obj(path, &SceneSettings::default())?;
+        Ok(Self::from_data(options.apply(data), mat))
+    }
 
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+    /// Load the STL file at `path` as a [`Mesh`], see
+    /// [`MeshData::from_stl()`] for how it's read.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, a binary file is cut short or an ASCII one
+    /// has a number that doesn't parse.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{material::Lambertian, mesh::Mesh, vec3, vec3::Vec3};
+    ///
+    /// // a tetrahedron, written both ways
+    /// let corners = [
+    ///     [0.0_f32, 0.0, 0.0],
+    ///     [1.0, 0.0, 0.0],
+    ///     [0.0, 1.0, 0.0],
+    ///     [0.0, 0.0, 1.0],
+    /// ];
+    /// let facets = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+    ///
+    /// let mut ascii = String::from("solid tetrahedron\n");
+    /// let mut binary = vec![0; 80];
+    /// binary.extend(4_u32.to_le_bytes());
+    /// for facet in facets {
+    ///     ascii += "facet normal 0 0 0\nouter loop\n";
+    ///     binary.extend([0.0_f32; 3].map(f32::to_le_bytes).concat());
+    ///     for corner in facet.map(|i| corners[i]) {
+    ///         ascii += &format!("vertex {} {} {}\n", corner[0], corner[1], corner[2]);
+    ///         binary.extend(corner.map(f32::to_le_bytes).concat());
+    ///     }
+    ///     ascii += "endloop\nendfacet\n";
+    ///     binary.extend([0, 0]);
+    /// }
+    /// ascii += "endsolid tetrahedron\n";
+    ///
+    /// let dir = std::env::temp_dir();
+    /// std::fs::write(dir.join("rustrace_tetrahedron_ascii.stl"), ascii).unwrap();
+    /// std::fs::write(dir.join("rustrace_tetrahedron_binary.stl"), binary).unwrap();
+    /// let grey = Lambertian::new(vec3![0.5, 0.5, 0.5]);
+    /// let ascii = Mesh::from_stl(&dir.join("rustrace_tetrahedron_ascii.stl"), &grey).unwrap();
+    /// let binary = Mesh::from_stl(&dir.join("rustrace_tetrahedron_binary.stl"), &grey).unwrap();
+    ///
+    /// // the twelve corners are welded into four vertices
+    /// for mesh in [&ascii, &binary] {
+    ///     assert_eq!(mesh.data().positions.len(), 4);
+    ///     assert_eq!(mesh.data().faces.len(), 4);
+    /// }
+    /// assert_eq!(ascii.data().faces, binary.data().faces);
+    /// ```
+    pub fn from_stl<P: AsRef<Path>>(path: &P, mat: &dyn Material) -> Result<Self> {
+        Self::from_stl_with_settings(path, mat, &SceneSettings::default())
+    }
 
-        let mut vertices = Vec::new();
-        let mut normals = Vec::new();
-        let mut triangles = HittableList::new();
+    /// Like [`Mesh::from_stl()`], but converts positions with the unit scale
+    /// and up axis of `settings` as they are read, e.g. for the millimetres
+    /// and `+Z` up of most CAD packages.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, a binary file is cut short or an ASCII one
+    /// has a number that doesn't parse.
+    pub fn from_stl_with_settings<P: AsRef<Path>>(
+        path: &P,
+        mat: &dyn Material,
+        settings: &SceneSettings,
+    ) -> Result<Self> {
+        Ok(Self::from_data(MeshData::from_stl(path, settings)?, mat))
+    }
 
-        // read OBJ file
-        for line in reader.lines() {
-            let line = line?;
-            let line = line.trim();
+    /// Build a [`Mesh`] from indexed geometry made in code rather than loaded
+    /// from a file, such as [generated vegetation](crate::vegetation). The
+    /// geometry is kept as it is and shared by every packet of the tree, and
+    /// packed triangles are hit exactly as a
+    /// [`Triangle`](crate::triangle::Triangle) made from the same corners
+    /// would be.
+    ///
+    /// # Panics
+    ///
+    /// If a face indexes outside of the vertex attribute arrays, or there are
+    /// more than [`u32::MAX`] faces.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::Hittable, interval, interval::Interval, material::Lambertian,
+    ///     mesh::{FaceVertex, Mesh, MeshData}, ray, ray::Ray, triangle::Triangle, vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// // a strip of squares sharing their edges
+    /// let mut data = MeshData::default();
+    /// for i in 0..=100 {
+    ///     let x = f64::from(i);
+    ///     data.positions.extend([vec3![x, 0.0, 0.0], vec3![x, 1.0, 0.0]]);
+    /// }
+    /// let corner = |position| FaceVertex { position, normal: None, uv: None };
+    /// for i in 0..100 {
+    ///     let [a, b, c, d] = [2 * i, 2 * i + 2, 2 * i + 3, 2 * i + 1].map(corner);
+    ///     data.faces.extend([[a, b, c], [a, c, d]]);
+    /// }
+    ///
+    /// let grey = Lambertian::new(vec3![0.5, 0.5, 0.5]);
+    /// let mesh = Mesh::from_data(data.clone(), &grey);
+    /// assert_eq!(mesh.data().positions.len(), 202);
+    ///
+    /// let r = ray!(vec3![42.3, 0.6, 2.0], vec3![0.1, -0.05, -1.0]);
+    /// let rec = mesh.hit(&r, interval![0.0, f64::INFINITY]).unwrap();
+    /// let face = data.faces[85];
+    /// let tri = Triangle::new(data.face_positions(&face), None, Box::new(grey));
+    /// let expected = tri.hit(&r, interval![0.0, f64::INFINITY]).unwrap();
+    /// assert_eq!((rec.p, rec.norm, rec.u, rec.v), (expected.p, expected.norm, expected.u, expected.v));
+    /// ```
+    #[must_use]
+    pub fn from_data(data: MeshData, mat: &dyn Material) -> Self {
+        let buffers = Arc::new(MeshBuffers::new(data, mat));
+        Self {
+            bvh: MeshPacket::tree(&buffers),
+            buffers,
+            end: None,
+        }
+    }
 
-            // comments and empties
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+    /// Like [`Mesh::from_data()`], but with each face's material picked out of
+    /// `arena` by the [`MaterialId`] at its index in `face_materials`. The
+    /// leaves of the tree only hold the ids, so a mesh of millions of faces
+    /// using a few dozen materials keeps one copy of each.
+    ///
+    /// # Panics
+    ///
+    /// If a face indexes outside of the vertex attribute arrays, or there are
+    /// more than [`u32::MAX`] faces.
+    ///
+    /// # Errors
+    ///
+    /// If there isn't one material for every face, a material isn't from
+    /// `arena`, or the mesh has vertex colours, which packed materials can't
+    /// be tinted by.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::Hittable,
+    ///     material::{Lambertian, MaterialArena},
+    ///     mesh::{FaceVertex, Mesh, MeshData},
+    ///     ray,
+    ///     ray::Ray,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// // a strip of squares alternating between red and blue
+    /// let mut data = MeshData::default();
+    /// for i in 0..=1000 {
+    ///     let x = f64::from(i);
+    ///     data.positions.extend([vec3![x, 0.0, 0.0], vec3![x, 1.0, 0.0]]);
+    /// }
+    /// let corner = |position| FaceVertex { position, normal: None, uv: None };
+    /// for i in 0..1000 {
+    ///     let [a, b, c, d] = [2 * i, 2 * i + 2, 2 * i + 3, 2 * i + 1].map(corner);
+    ///     data.faces.extend([[a, b, c], [a, c, d]]);
+    /// }
+    ///
+    /// let mut arena = MaterialArena::new();
+    /// let faces = (0..2000)
+    ///     .map(|k| match k / 2 % 2 {
+    ///         0 => arena.intern(Lambertian::new(vec3![0.8, 0.1, 0.1])),
+    ///         _ => arena.intern(Lambertian::new(vec3![0.1, 0.1, 0.8])),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(arena.len(), 2);
+    /// let mesh = Mesh::from_packed_data(data.clone(), arena.clone(), faces).unwrap();
+    ///
+    /// let albedo_at = |x| {
+    ///     let r = ray!(vec3![x, 0.5, 2.0], vec3![0.0, 0.0, -1.0]);
+    ///     let rec = mesh.nearest_hit_along(&r).unwrap();
+    ///     rec.mat.lambertian_albedo(&r, &rec).unwrap()
+    /// };
+    /// assert_eq!(albedo_at(10.5), vec3![0.8, 0.1, 0.1]);
+    /// assert_eq!(albedo_at(11.5), vec3![0.1, 0.1, 0.8]);
+    ///
+    /// // every face needs a material from the arena
+    /// let one = vec![arena.intern(Lambertian::default())];
+    /// assert!(Mesh::from_packed_data(data.clone(), arena.clone(), one).is_err());
+    /// let foreign = MaterialArena::new().intern(Lambertian::default());
+    /// assert!(Mesh::from_packed_data(data, MaterialArena::new(), vec![foreign; 2000]).is_err());
+    /// ```
+    pub fn from_packed_data(
+        data: MeshData,
+        arena: MaterialArena,
+        face_materials: Vec<MaterialId>,
+    ) -> Result<Self> {
+        if face_materials.len() != data.faces.len() {
+            bail!(
+                "Need a material for each of the {} faces, found {}",
+                data.faces.len(),
+                face_materials.len()
+            );
+        }
+        if face_materials.iter().any(|&id| arena.get(id).is_none()) {
+            bail!("A face's material isn't from the arena");
+        }
+        if !data.colours.is_empty() {
+            bail!("Meshes with packed materials can't have vertex colours");
+        }
+        let buffers = Arc::new(MeshBuffers {
+            data,
+            materials: FaceMaterials::Packed {
+                arena,
+                faces: face_materials,
+            },
+            tolerances: Tolerances::DEFAULT,
+        });
+        Ok(Self {
+            bvh: MeshPacket::tree(&buffers),
+            buffers,
+            end: None,
+        })
+    }
 
-            let mut parts = line.split_whitespace();
-            let prefix = parts.next().unwrap();
-
-            // parse lines accordingly
-            match prefix {
-                "v" => {
-                    let x: f64 = parts.next().unwrap().parse()?;
-                    let y: f64 = parts.next().unwrap().parse()?;
-                    let z: f64 = parts.next().unwrap().parse()?;
-                    vertices.push(vec3![x, y, z]);
-                }
-                "vn" => {
-                    let x: f64 = parts.next().unwrap().parse()?;
-                    let y: f64 = parts.next().unwrap().parse()?;
-                    let z: f64 = parts.next().unwrap().parse()?;
-                    normals.push(vec3![x, y, z]);
-                }
-                "f" => {
-                    let face_verts: Vec<_> = parts.collect();
-                    if face_verts.len() < 3 {
-                        continue;
-                    }
+    /// Like [`Mesh::from_obj_with_settings()`], but for a mesh deforming
+    /// between the OBJ file at `start` at time `0.0` and the one at `end` at
+    /// time `1.0`, such as two frames of a vertex cache, see
+    /// [`Mesh::deforming()`].
+    ///
+    /// # Panics
+    ///
+    /// If a prefix or required part of a line isn't found.
+    ///
+    /// # Errors
+    ///
+    /// If opening either file or parsing things that are supposed to be
+    /// numbers fails, or the meshes aren't the same topology.
+    pub fn from_obj_deforming<P: AsRef<Path>>(
+        start: &P,
+        end: &P,
+        mat: &dyn Material,
+        settings: &SceneSettings,
+    ) -> Result<Self> {
+        Self::deforming(read_obj(start, settings)?, read_obj(end, settings)?, mat)
+    }
 
-                    let (v0, n0) = parse_face_vertex(face_verts[0])?;
-                    let (v1, n1) = parse_face_vertex(face_verts[1])?;
-                    let (mut v2, mut n2) = parse_face_vertex(face_verts[2])?;
+    /// Build a [`Mesh`] deforming from `start` at time `0.0` to `end` at time
+    /// `1.0`, each vertex moving in a straight line, so it's motion blurred
+    /// by the deformation (see [`MovingTriangle`]). The meshes must be the
+    /// same topology: the same number of vertices, with faces joining the
+    /// same ones. [`Mesh::data()`] is the `start` shape.
+    ///
+    /// # Errors
+    ///
+    /// If the meshes aren't the same topology.
+    ///
+    /// # Panics
+    ///
+    /// If a face indexes outside of the vertex attribute arrays.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::Hittable, interval, interval::Interval, material::Lambertian,
+    ///     mesh::{FaceVertex, Mesh, MeshData}, ray, ray::Ray, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let corner = |position| FaceVertex { position, normal: None, uv: None };
+    /// let start = MeshData {
+    ///     positions: vec![vec3![-1.0, 0.0, 0.0], vec3![1.0, 0.0, 0.0], vec3![0.0, 1.0, 0.0]],
+    ///     faces: vec![[corner(0), corner(1), corner(2)]],
+    ///     ..MeshData::default()
+    /// };
+    ///
+    /// // the tip swings out to the side
+    /// let mut end = start.clone();
+    /// end.positions[2] = vec3![3.0, 1.0, 0.0];
+    ///
+    /// let mesh = Mesh::deforming(start, end, &Lambertian::new(vec3![0.5, 0.5, 0.5])).unwrap();
+    /// assert_eq!(mesh.bound().x, interval![-1.0, 3.0]);
+    ///
+    /// let down_z = |time| ray!(vec3![1.5, 0.5, 5.0], vec3![0.0, 0.0, -1.0], time);
+    /// assert!(mesh.hit(&down_z(0.0), interval![0.0, f64::INFINITY]).is_none());
+    /// assert!(mesh.hit(&down_z(1.0), interval![0.0, f64::INFINITY]).is_some());
+    /// ```
+    pub fn deforming(start: MeshData, end: MeshData, mat: &dyn Material) -> Result<Self> {
+        let same_corners = |a: &[FaceVertex; 3], b: &[FaceVertex; 3]| {
+            (0..3).all(|i| a[i].position == b[i].position)
+        };
+        if start.positions.len() != end.positions.len()
+            || start.faces.len() != end.faces.len()
+            || !start
+                .faces
+                .iter()
+                .zip(&end.faces)
+                .all(|(a, b)| same_corners(a, b))
+        {
+            bail!("Deforming meshes must have the same vertices and faces at both ends");
+        }
 
-                    let tri_verts = [vertices[v0], vertices[v1], vertices[v2]];
-                    let tri_normals = if normals.is_empty() {
-                        None
-                    } else {
-                        Some([normals[n0], normals[n1], normals[n2]])
-                    };
+        let buffers = MeshBuffers::new(start, mat);
+        let start = &buffers.data;
+        let triangles = start
+            .faces
+            .iter()
+            .zip(&end.faces)
+            .enumerate()
+            .map(|(k, (start_face, end_face))| {
+                let normals = start
+                    .face_normals(start_face)
+                    .zip(end.face_normals(end_face))
+                    .map(|(a, b)| [a, b]);
+                MovingTriangle::new(
+                    start.face_positions(start_face),
+                    end.face_positions(end_face),
+                    normals,
+                    buffers.shared_material(k),
+                )
+                .set_uvs(buffers.uvs(k))
+            })
+            .collect();
 
-                    triangles.add(Box::new(Triangle::new(
-                        tri_verts,
-                        tri_normals,
-                        mat.clone_box(),
-                    )));
-
-                    // TODO: test
-                    // poly -> tris
-                    for face_vert in face_verts.iter().skip(3) {
-                        let (v_new, n_new) = parse_face_vertex(face_vert)?;
-
-                        let tri_verts = [vertices[v0], vertices[v2], vertices[v_new]];
-                        let tri_normals = if normals.is_empty() {
-                            None
-                        } else {
-                            Some([normals[n0], normals[n1], normals[n2]])
-                        };
-
-                        triangles.add(Box::new(Triangle::new(
-                            tri_verts,
-                            tri_normals,
-                            mat.clone_box(),
-                        )));
-
-                        v2 = v_new;
-                        n2 = n_new;
-                    }
+        let bvh = BVHTree::from_items(
+            triangles,
+            buffers.tolerances.bound_padding,
+            &MovingTriangle::bound,
+            &|node_triangles| {
+                let mut objects = HittableList::new();
+                for triangle in node_triangles {
+                    objects.add(Box::new(triangle));
                 }
-                _ => {}
-            }
-        }
+                objects
+            },
+        );
 
         Ok(Self {
-            bvh: BVHTree::from_hit_list(triangles),
+            bvh,
+            buffers: Arc::new(buffers),
+            end: Some(end),
         })
     }
+
+    /// Get the indexed geometry the [`Mesh`] was built from.
+    #[must_use]
+    pub fn data(&self) -> &MeshData {
+        &self.buffers.data
+    }
 }
 
 impl Hittable for Mesh {
@@ -156,4 +1356,703 @@ impl Hittable for Mesh {
         // root bvh bounding box should encapsulate the mesh
         self.bvh.aabb
     }
+
+    /// Moves the [`MeshData`] positions. The packets of the tree share its
+    /// buffers, so the tree is let go of first and rebuilt around the moved
+    /// vertices, while a deforming mesh's tree is moved in place.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        if self.end.is_none() {
+            self.bvh = BVHTree::from_hit_list(HittableList::new());
+        }
+        let buffers = Arc::get_mut(&mut self.buffers).expect("only the tree shares the buffers");
+        vec3::offset_all(&mut buffers.data.positions, offset);
+        match &mut self.end {
+            Some(end) => {
+                vec3::offset_all(&mut end.positions, offset);
+                self.bvh.translate(offset)
+            }
+            None => {
+                self.bvh = MeshPacket::tree(&self.buffers);
+                true
+            }
+        }
+    }
+
+    /// Keeps the tolerances with the shared buffers, rebuilding the tree
+    /// around them like [`Mesh::translate()`] when they change, while a
+    /// deforming mesh's tree passes them on to its triangles.
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        if self.buffers.tolerances == *tolerances {
+            return;
+        }
+        if self.end.is_none() {
+            self.bvh = BVHTree::from_hit_list(HittableList::new());
+        }
+        let buffers = Arc::get_mut(&mut self.buffers).expect("only the tree shares the buffers");
+        buffers.tolerances = *tolerances;
+        match self.end {
+            Some(_) => self.bvh.set_tolerances(tolerances),
+            None => self.bvh = MeshPacket::tree(&self.buffers),
+        }
+    }
+
+    /// Defers to [`BVHTree::closest_point()`] on its internal [`BVHTree`].
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        self.bvh.closest_point(point)
+    }
+}
+
+/// The materials of the faces of a [`Mesh`].
+#[derive(Debug)]
+enum FaceMaterials {
+    /// One material for every face, or one for each face of a mesh with
+    /// vertex colours, tinting the mesh's material by a [`VertexColours`]
+    /// texture which takes the place of the face's texture coordinates.
+    Shared(Vec<Arc<dyn Material>>),
+    /// The distinct materials of the mesh, with each face's index into them.
+    Packed {
+        arena: MaterialArena,
+        faces: Vec<MaterialId>,
+    },
+}
+
+/// The geometry of a [`Mesh`] with its materials, shared by every
+/// [`MeshPacket`] of its tree.
+#[derive(Debug)]
+struct MeshBuffers {
+    data: MeshData,
+    materials: FaceMaterials,
+    /// The tolerances the faces are intersected and bounded with.
+    tolerances: Tolerances,
+}
+
+impl MeshBuffers {
+    /// Share one copy of `mat` between the faces of `data`.
+    fn new(data: MeshData, mat: &dyn Material) -> Self {
+        let mat: Arc<dyn Material> = Arc::from(mat.clone_box());
+        let materials = if data.colours.is_empty() {
+            vec![mat]
+        } else {
+            data.faces
+                .iter()
+                .map(|face| -> Arc<dyn Material> {
+                    match data.face_colours(face) {
+                        Some(colours) => Arc::new(Textured::with_shared_material(
+                            Box::new(VertexColours::new(colours)),
+                            Arc::clone(&mat),
+                        )),
+                        None => Arc::clone(&mat),
+                    }
+                })
+                .collect()
+        };
+        Self {
+            data,
+            materials: FaceMaterials::Shared(materials),
+            tolerances: Tolerances::DEFAULT,
+        }
+    }
+
+    /// The material of face `k`.
+    fn material(&self, k: usize) -> &dyn Material {
+        match &self.materials {
+            FaceMaterials::Shared(materials) if self.data.colours.is_empty() => &*materials[0],
+            FaceMaterials::Shared(materials) => &*materials[k],
+            FaceMaterials::Packed { arena, faces } => arena
+                .get(faces[k])
+                .expect("Packed faces are checked when built"),
+        }
+    }
+
+    /// The material of face `k` for a primitive of its own to hold.
+    fn shared_material(&self, k: usize) -> Arc<dyn Material> {
+        match &self.materials {
+            FaceMaterials::Shared(materials) if self.data.colours.is_empty() => {
+                Arc::clone(&materials[0])
+            }
+            FaceMaterials::Shared(materials) => Arc::clone(&materials[k]),
+            FaceMaterials::Packed { .. } => Arc::from(self.material(k).clone_box()),
+        }
+    }
+
+    /// The texture coordinates of face `k`, [`BARYCENTRIC_UVS`] if it has
+    /// none or is coloured by its vertices.
+    fn uvs(&self, k: usize) -> [[f64; 2]; 3] {
+        if self.data.colours.is_empty() {
+            self.data
+                .face_uvs(&self.data.faces[k])
+                .unwrap_or(BARYCENTRIC_UVS)
+        } else {
+            BARYCENTRIC_UVS
+        }
+    }
+
+    /// The corners of face `k`.
+    fn vertices(&self, k: usize) -> [Vec3; 3] {
+        self.data.face_positions(&self.data.faces[k])
+    }
+}
+
+/// Up to [`PACKET_LANES`] faces of a [`Mesh`], tested all at once by
+/// [`intersect_lanes()`]. Only the vertex positions are copied into the
+/// packet, the rest of each face is looked up in the shared [`MeshBuffers`]
+/// once the nearest is found.
+#[derive(Debug)]
+struct MeshPacket {
+    lanes: LaneVertices,
+    faces: [u32; PACKET_LANES],
+    buffers: Arc<MeshBuffers>,
+}
+
+impl MeshPacket {
+    /// Build a [`BVHTree`] over the faces of `buffers`, split over single
+    /// faces but storing each node's as packets of nearby faces.
+    fn tree(buffers: &Arc<MeshBuffers>) -> BVHTree {
+        let faces: Vec<u32> = (0..buffers.data.faces.len())
+            .map(|k| u32::try_from(k).expect("too many faces"))
+            .collect();
+        let vertices = |&k: &u32| buffers.vertices(k as usize);
+        let centroid = |k: &u32| {
+            let [a, b, c] = vertices(k);
+            (a + b + c) / 3.0
+        };
+        let padding = buffers.tolerances.bound_padding;
+        let bound = |k: &u32| triangle_bound(vertices(k), padding);
+        BVHTree::from_items(faces, padding, &bound, &|node_faces| {
+            let mut packets = HittableList::new();
+            group_by_centroid(node_faces, &centroid, &mut |group| {
+                let mut lanes = LaneVertices {
+                    len: group.len(),
+                    ..LaneVertices::default()
+                };
+                let mut faces = [0; PACKET_LANES];
+                for (lane, k) in group.iter().enumerate() {
+                    lanes.set(lane, vertices(k));
+                    faces[lane] = *k;
+                }
+                packets.add(Box::new(Self {
+                    lanes,
+                    faces,
+                    buffers: Arc::clone(buffers),
+                }));
+            });
+            packets
+        })
+    }
+}
+
+impl Hittable for MeshPacket {
+    /// Tests every lane at once like a [`Triangle`](crate::triangle::Triangle),
+    /// shading only the nearest hit.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let tolerances = &self.buffers.tolerances;
+        let (lane, t, u, v) = nearest_lane(
+            intersect_lanes(&self.lanes, ray, ray_t, tolerances),
+            self.lanes.len,
+        )?;
+
+        let k = self.faces[lane] as usize;
+        let vertices = self.lanes.get(lane);
+        let normals = self
+            .buffers
+            .data
+            .face_normals(&self.buffers.data.faces[k])
+            .unwrap_or([face_normal(vertices); 3]);
+        let norm = interpolate_normal(normals, u, v);
+        let uvs = self.buffers.uvs(k);
+        let [tex_u, tex_v] = interpolate_uv(uvs, u, v);
+
+        let mut rec = HitRecord {
+            p: ray.at(t - tolerances.hit_offset),
+            norm,
+            mat: self.buffers.material(k),
+            t,
+            front_face: true,
+            u: tex_u,
+            v: tex_v,
+            object: None,
+            layer: None,
+            tangent: Some(uv_tangent(vertices, uvs)),
+        };
+        rec.set_face_norm(ray, &norm);
+
+        Some(rec)
+    }
+
+    fn bound(&self) -> Aabb {
+        let mut aabb = Aabb::new();
+        for lane in 0..self.lanes.len {
+            let padding = self.buffers.tolerances.bound_padding;
+            aabb.union(&triangle_bound(self.lanes.get(lane), padding));
+        }
+        aabb
+    }
+
+    /// The closest point over every lane.
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        (0..self.lanes.len)
+            .map(|lane| closest_point_on(self.lanes.get(lane), point))
+            .min_by(|a, b| {
+                (*a - point)
+                    .length_squared()
+                    .total_cmp(&(*b - point).length_squared())
+            })
+    }
+}
+
+/// Read an OBJ file into a [`MeshData`], converting positions and normals
+/// with the unit scale and up axis of `settings`, and smoothing the normals
+/// of faces without them up to its crease angle.
+fn read_obj<P: AsRef<Path>>(path: &P, settings: &SceneSettings) -> Result<MeshData> {
+    fn parse_face_vertex(s: &str) -> Result<FaceVertex> {
+        let parts: Vec<&str> = s.split('/').collect();
+        let position: usize = parts[0].parse::<usize>()? - 1;
+        let uv = if parts.len() > 1 && !parts[1].is_empty() {
+            Some(parts[1].parse::<usize>()? - 1)
+        } else {
+            None
+        };
+        let normal = if parts.len() > 2 && !parts[2].is_empty() {
+            Some(parts[2].parse::<usize>()? - 1)
+        } else {
+            None
+        };
+
+        Ok(FaceVertex {
+            position,
+            normal,
+            uv,
+        })
+    }
+
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    let mut data = MeshData::default();
+    // triangulated once every position is known
+    let mut polygons = Vec::new();
+
+    // read OBJ file
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        // comments and empties
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let prefix = parts.next().unwrap();
+
+        // parse lines accordingly
+        match prefix {
+            "v" => {
+                let x: f64 = parts.next().unwrap().parse()?;
+                let y: f64 = parts.next().unwrap().parse()?;
+                let z: f64 = parts.next().unwrap().parse()?;
+                data.positions.push(settings.point(vec3![x, y, z]));
+            }
+            "vn" => {
+                let x: f64 = parts.next().unwrap().parse()?;
+                let y: f64 = parts.next().unwrap().parse()?;
+                let z: f64 = parts.next().unwrap().parse()?;
+                data.normals.push(settings.normal(vec3![x, y, z]));
+            }
+            "vt" => {
+                let u: f64 = parts.next().unwrap().parse()?;
+                let v: f64 = parts.next().map_or(Ok(0.0), str::parse)?;
+                data.uvs.push([u, v]);
+            }
+            "f" => {
+                let polygon = parts.map(parse_face_vertex).collect::<Result<Vec<_>>>()?;
+                polygons.push(polygon);
+            }
+            _ => {}
+        }
+    }
+
+    for polygon in &polygons {
+        if let Some(fv) = polygon
+            .iter()
+            .find(|fv| fv.position >= data.positions.len())
+        {
+            bail!("Face uses vertex {}, which doesn't exist", fv.position + 1);
+        }
+        data.add_polygon(polygon);
+    }
+
+    Ok(data.with_smooth_normals(settings.crease_angle))
+}
+
+/// The type of a value in a PLY file.
+#[derive(Debug, Clone, Copy)]
+enum PlyScalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyScalar {
+    /// Parse the name of a type, in either of the spellings in use.
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => bail!("Unknown PLY type {name}"),
+        })
+    }
+
+    /// The size of a binary value in bytes.
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// The value of full brightness for a colour of this type.
+    fn colour_scale(self) -> f64 {
+        match self {
+            Self::U8 => 255.0,
+            Self::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A property of each instance of a [`PlyElement`], a list of values if it
+/// has a `count` type.
+#[derive(Debug)]
+struct PlyProperty {
+    name: String,
+    count: Option<PlyScalar>,
+    scalar: PlyScalar,
+}
+
+/// An element declared in a PLY header, such as `vertex` or `face`.
+#[derive(Debug)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    /// The index of the first single-valued property with one of `names`.
+    fn find(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|p| p.count.is_none() && names.contains(&p.name.as_str()))
+    }
+
+    /// The indices of three single-valued properties, if all are present.
+    fn find_all(&self, names: [&[&str]; 3]) -> Option<[usize; 3]> {
+        let [a, b, c] = names.map(|names| self.find(names));
+        Some([a?, b?, c?])
+    }
+}
+
+/// The body of a PLY file, read one value at a time.
+enum PlyBody<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl PlyBody<'_> {
+    /// Read the next value, of type `scalar` if binary.
+    fn read(&mut self, scalar: PlyScalar) -> Result<f64> {
+        match self {
+            Self::Ascii(tokens) => {
+                let token = tokens.next().context("PLY file ends early")?;
+                token
+                    .parse()
+                    .with_context(|| format!("Couldn't parse PLY value {token}"))
+            }
+            Self::Binary { bytes, big_endian } => {
+                let size = scalar.size();
+                if bytes.len() < size {
+                    bail!("PLY file ends early");
+                }
+                let (value, rest) = bytes.split_at(size);
+                *bytes = rest;
+                let mut buf = [0; 8];
+                buf[..size].copy_from_slice(value);
+                if *big_endian {
+                    buf[..size].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buf;
+                Ok(match scalar {
+                    PlyScalar::I8 => f64::from(i8::from_le_bytes([b0])),
+                    PlyScalar::U8 => f64::from(b0),
+                    PlyScalar::I16 => f64::from(i16::from_le_bytes([b0, b1])),
+                    PlyScalar::U16 => f64::from(u16::from_le_bytes([b0, b1])),
+                    PlyScalar::I32 => f64::from(i32::from_le_bytes([b0, b1, b2, b3])),
+                    PlyScalar::U32 => f64::from(u32::from_le_bytes([b0, b1, b2, b3])),
+                    PlyScalar::F32 => f64::from(f32::from_le_bytes([b0, b1, b2, b3])),
+                    PlyScalar::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+
+    /// Read the next value as a count or index.
+    fn read_index(&mut self, scalar: PlyScalar) -> Result<usize> {
+        match self.read(scalar)? {
+            #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            i if i >= 0.0 && i.fract() == 0.0 => Ok(i as usize),
+            i => bail!("PLY index {i} isn't a whole number"),
+        }
+    }
+}
+
+/// Read a PLY file into a [`MeshData`], converting positions and normals
+/// with the unit scale and up axis of `settings`.
+fn read_ply<P: AsRef<Path>>(path: &P, settings: &SceneSettings) -> Result<MeshData> {
+    let bytes = std::fs::read(path)?;
+
+    // header, a line at a time up to end_header
+    let mut rest = &bytes[..];
+    let mut next_line = || -> Result<&str> {
+        let end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .context("PLY header never ends")?;
+        let line = std::str::from_utf8(&rest[..end])?;
+        rest = &rest[end + 1..];
+        Ok(line.trim())
+    };
+    if next_line()? != "ply" {
+        bail!("Not a PLY file");
+    }
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    loop {
+        let line = next_line()?;
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts[..] {
+            ["end_header"] => break,
+            ["format", name, _] => format = Some(name.to_string()),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse()?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, scalar, name] => elements
+                .last_mut()
+                .context("PLY property before any element")?
+                .properties
+                .push(PlyProperty {
+                    name: name.to_string(),
+                    count: Some(PlyScalar::parse(count)?),
+                    scalar: PlyScalar::parse(scalar)?,
+                }),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .context("PLY property before any element")?
+                .properties
+                .push(PlyProperty {
+                    name: name.to_string(),
+                    count: None,
+                    scalar: PlyScalar::parse(scalar)?,
+                }),
+            ["comment" | "obj_info", ..] | [] => {}
+            _ => bail!("Unexpected PLY header line {line}"),
+        }
+    }
+    let mut body = match format.as_deref() {
+        Some("ascii") => PlyBody::Ascii(std::str::from_utf8(rest)?.split_ascii_whitespace()),
+        Some("binary_little_endian") => PlyBody::Binary {
+            bytes: rest,
+            big_endian: false,
+        },
+        Some("binary_big_endian") => PlyBody::Binary {
+            bytes: rest,
+            big_endian: true,
+        },
+        _ => bail!("Unknown PLY format {}", format.unwrap_or_default()),
+    };
+
+    let mut data = MeshData::default();
+    let mut polygons = Vec::new();
+    let mut has_normals = false;
+    let mut has_uvs = false;
+    // each instance's single values, with a placeholder for lists
+    let mut row = Vec::new();
+    for element in &elements {
+        let position = element.find_all([&["x"], &["y"], &["z"]]);
+        let normal = element.find_all([&["nx"], &["ny"], &["nz"]]);
+        let colour = element.find_all([
+            &["red", "diffuse_red"],
+            &["green", "diffuse_green"],
+            &["blue", "diffuse_blue"],
+        ]);
+        let uv = element
+            .find(&["s", "u", "texture_u", "texture_s"])
+            .zip(element.find(&["t", "v", "texture_v", "texture_t"]));
+        let indices = element.properties.iter().position(|p| {
+            p.count.is_some() && matches!(&*p.name, "vertex_indices" | "vertex_index")
+        });
+        if element.name == "vertex" {
+            if position.is_none() {
+                bail!("PLY vertices have no position");
+            }
+            has_normals = normal.is_some();
+            has_uvs = uv.is_some();
+        }
+
+        for _ in 0..element.count {
+            row.clear();
+            let mut polygon = Vec::new();
+            for (k, property) in element.properties.iter().enumerate() {
+                if let Some(count) = property.count {
+                    let len = body.read_index(count)?;
+                    for _ in 0..len {
+                        let position = body.read_index(property.scalar)?;
+                        if Some(k) == indices {
+                            polygon.push(position);
+                        }
+                    }
+                    row.push(f64::NAN);
+                } else {
+                    row.push(body.read(property.scalar)?);
+                }
+            }
+
+            match &*element.name {
+                "vertex" => {
+                    if let Some([x, y, z]) = position {
+                        data.positions
+                            .push(settings.point(vec3![row[x], row[y], row[z]]));
+                    }
+                    if let Some([x, y, z]) = normal {
+                        data.normals
+                            .push(settings.normal(vec3![row[x], row[y], row[z]]));
+                    }
+                    if let Some((u, v)) = uv {
+                        data.uvs.push([row[u], row[v]]);
+                    }
+                    if let Some(channels) = colour {
+                        let [r, g, b] = channels.map(|k| {
+                            let scale = element.properties[k].scalar.colour_scale();
+                            ColourSpace::Srgb.decode(row[k] / scale)
+                        });
+                        data.colours.push(vec3![r, g, b]);
+                    }
+                }
+                "face" => polygons.push(polygon),
+                _ => {}
+            }
+        }
+    }
+
+    for polygon in &polygons {
+        if let Some(&position) = polygon.iter().find(|&&i| i >= data.positions.len()) {
+            bail!("Face uses vertex {position}, which doesn't exist");
+        }
+        let polygon: Vec<FaceVertex> = polygon
+            .iter()
+            .map(|&i| FaceVertex {
+                position: i,
+                normal: has_normals.then_some(i),
+                uv: has_uvs.then_some(i),
+            })
+            .collect();
+        data.add_polygon(&polygon);
+    }
+
+    Ok(data)
+}
+
+/// Read an STL file into a [`MeshData`], converting positions with the unit
+/// scale and up axis of `settings`.
+fn read_stl<P: AsRef<Path>>(path: &P, settings: &SceneSettings) -> Result<MeshData> {
+    let bytes = std::fs::read(path)?;
+    let mut data = MeshData::default();
+    // welds corners by their exact coordinates
+    let mut indices: HashMap<[u64; 3], usize> = HashMap::new();
+    let mut corner = |data: &mut MeshData, [x, y, z]: [f64; 3]| {
+        let position = *indices
+            .entry([x.to_bits(), y.to_bits(), z.to_bits()])
+            .or_insert_with(|| {
+                data.positions.push(settings.point(vec3![x, y, z]));
+                data.positions.len() - 1
+            });
+        FaceVertex {
+            position,
+            normal: None,
+            uv: None,
+        }
+    };
+
+    // binary files can start with "solid" too, but always have the size
+    // their triangle count gives
+    let count = bytes
+        .get(80..84)
+        .map(|count| u32::from_le_bytes([count[0], count[1], count[2], count[3]]));
+    let is_binary = count.is_some_and(|count| {
+        u64::try_from(bytes.len()).is_ok_and(|len| len == 84 + 50 * u64::from(count))
+    }) || !bytes.starts_with(b"solid");
+
+    if is_binary {
+        let Some(count) = count else {
+            bail!("STL file is too short");
+        };
+        let count = usize::try_from(count)?;
+        if bytes.len() < 84 + 50 * count {
+            bail!("STL file ends early");
+        }
+        for facet in bytes[84..].chunks_exact(50).take(count) {
+            // skip the normal, then three corners
+            let polygon: Vec<FaceVertex> = facet[12..48]
+                .chunks_exact(12)
+                .map(|xyz| {
+                    let component = |k: usize| {
+                        let b = &xyz[4 * k..4 * k + 4];
+                        f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    };
+                    corner(&mut data, [component(0), component(1), component(2)])
+                })
+                .collect();
+            data.add_polygon(&polygon);
+        }
+    } else {
+        let text = std::str::from_utf8(&bytes)?;
+        let mut polygon = Vec::new();
+        for line in text.lines() {
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("vertex") => {
+                    let mut xyz = [0.0; 3];
+                    for value in &mut xyz {
+                        let part = parts.next().context("STL vertex has too few coordinates")?;
+                        *value = part
+                            .parse()
+                            .with_context(|| format!("Couldn't parse STL coordinate {part}"))?;
+                    }
+                    polygon.push(corner(&mut data, xyz));
+                }
+                Some("endloop") => {
+                    data.add_polygon(&polygon);
+                    polygon.clear();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(data)
 }
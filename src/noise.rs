@@ -0,0 +1,149 @@
+//! This module contains [`Noise`], the stable hashed randomness used by
+//! procedural textures and scatter systems. Values are pure functions of a
+//! seed and a position, never of a thread's RNG or the order pixels are
+//! rendered in, so the same scene seed reproduces the same patterns on any
+//! machine or thread count.
+
+use crate::{vec3, vec3::Vec3};
+
+/// The starting state of every hash, so seed `0` isn't a degenerate state.
+const HASH_START: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A seed for procedural randomness, usually from
+/// [`SceneSettings::seed`](field@crate::scene::SceneSettings::seed).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{noise::Noise, vec3, vec3::Vec3};
+///
+/// let noise = Noise::new(42);
+///
+/// // the same seed and position always give the same value
+/// let p = vec3![1.5, 2.25, -3.0];
+/// assert_eq!(noise.value(p), Noise::new(42).value(p));
+/// assert_ne!(noise.value(p), Noise::new(43).value(p));
+///
+/// // separate systems get independent streams from one scene seed
+/// assert_ne!(noise.stream("scatter").seed(), noise.stream("glints").seed());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Noise {
+    seed: u64,
+}
+
+impl Noise {
+    /// Create a [`Noise`] from `seed`.
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// The seed.
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// An independent [`Noise`] for the system called `name`, so e.g. a
+    /// scatter and a texture seeded from the same scene don't correlate. The
+    /// name is hashed with FNV-1a, which is stable across platforms and Rust
+    /// versions unlike [`std::hash`].
+    #[must_use]
+    pub fn stream(&self, name: &str) -> Self {
+        let fnv = name.bytes().fold(0xCBF2_9CE4_8422_2325, |h: u64, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01B3)
+        });
+        Self::new(splitmix64(self.seed ^ fnv))
+    }
+
+    /// Hash an integer lattice cell.
+    #[must_use]
+    pub fn hash(&self, cell: [i64; 3]) -> u64 {
+        cell.iter().fold(HASH_START ^ self.seed, |h, &c| {
+            #[expect(clippy::cast_sign_loss)]
+            splitmix64(h ^ (c as u64))
+        })
+    }
+
+    /// A [`HashRng`] for drawing any number of values for a lattice cell.
+    #[must_use]
+    pub fn rng(&self, cell: [i64; 3]) -> HashRng {
+        HashRng::new(self.hash(cell))
+    }
+
+    /// Smooth value noise in `0.0..1.0` at `p`, interpolating random values
+    /// at the integer lattice points around it. Scale `p` to change the size
+    /// of the features.
+    #[must_use]
+    pub fn value(&self, p: Vec3) -> f64 {
+        let base = vec3![p[0].floor(), p[1].floor(), p[2].floor()];
+        let f = p - base;
+        // smoothstep, so the noise has no creases at the lattice
+        let w = |t: f64| t * t * (3.0 - 2.0 * t);
+        let (wx, wy, wz) = (w(f[0]), w(f[1]), w(f[2]));
+
+        #[expect(clippy::cast_possible_truncation)]
+        let cell = [base[0] as i64, base[1] as i64, base[2] as i64];
+        let corner = |dx: i64, dy: i64, dz: i64| {
+            HashRng::new(self.hash([cell[0] + dx, cell[1] + dy, cell[2] + dz])).next_unit()
+        };
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), wx);
+        let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), wx);
+        let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), wx);
+        let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), wx);
+        lerp(lerp(x00, x10, wy), lerp(x01, x11, wy), wz)
+    }
+}
+
+/// A small deterministic random stream from a hashed state, see
+/// [`Noise::rng()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::noise::Noise;
+///
+/// let mut a = Noise::new(7).rng([1, 2, 3]);
+/// let mut b = Noise::new(7).rng([1, 2, 3]);
+///
+/// let x = a.next_unit();
+/// assert!((0.0..1.0).contains(&x));
+/// assert_eq!(x, b.next_unit());
+/// assert_ne!(a.next_u64(), Noise::new(7).rng([1, 2, 4]).next_u64());
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashRng {
+    state: u64,
+}
+
+impl HashRng {
+    /// Create a [`HashRng`] starting from `state`.
+    #[must_use]
+    pub fn new(state: u64) -> Self {
+        Self { state }
+    }
+
+    /// The next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = splitmix64(self.state);
+        self.state
+    }
+
+    /// The next value in `0.0..1.0`.
+    pub fn next_unit(&mut self) -> f64 {
+        #[expect(clippy::cast_precision_loss)]
+        let unit = (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+        unit
+    }
+}
+
+/// A single round of the splitmix64 mixing function.
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
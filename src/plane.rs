@@ -0,0 +1,146 @@
+//! Contains the [`Plane`] struct which models an infinite plane in any
+//! orientation. See [`GroundPlane`](crate::ground::GroundPlane) for a
+//! horizontal floor that stays exactly flat far from the origin.
+//!
+//! Its bounds are infinite, so [`BVHTree`](crate::bvh::BVHTree)s keep it
+//! aside and test it for every [ray](Ray).
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    tolerance::Tolerances,
+    vec3::{dot, Vec3},
+};
+
+/// An infinite plane through [`point`](field@Plane::point), facing along
+/// [`normal`](field@Plane::normal).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable,
+///     material::Lambertian,
+///     plane::Plane,
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // a wall at x = 2 facing back towards the origin
+/// let wall = Plane::new(
+///     vec3![2.0, 0.0, 0.0],
+///     vec3![-3.0, 0.0, 0.0],
+///     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// );
+/// assert_eq!(wall.normal, vec3![-1.0, 0.0, 0.0]);
+///
+/// let rec = wall
+///     .nearest_hit_along(&ray!(vec3![0.0, 0.0, 0.0], vec3![1.0, 100.0, 0.0]))
+///     .unwrap();
+/// assert_eq!(rec.p, vec3![2.0, 200.0, 0.0]);
+/// assert!(rec.front_face);
+///
+/// assert_eq!(wall.closest_point(vec3![5.0, 1.0, 2.0]), Some(vec3![2.0, 1.0, 2.0]));
+///
+/// // flat along X, infinite along Y and Z
+/// let bound = wall.bound();
+/// assert!(bound.x.size() < 1e-3);
+/// assert!(bound.is_unbounded());
+/// ```
+#[derive(Debug)]
+pub struct Plane {
+    /// Any point on the plane.
+    pub point: Vec3,
+    /// The unit normal of the front face.
+    pub normal: Vec3,
+    /// The [`Material`] of the plane.
+    pub mat: Box<dyn Material>,
+    /// The tolerances the plane is bounded with, see
+    /// [`Hittable::set_tolerances()`].
+    pub tolerances: Tolerances,
+}
+
+impl Plane {
+    /// Create a new [`Plane`] through `point` facing along `normal`, which is
+    /// normalised.
+    ///
+    /// # Panics
+    ///
+    /// If `normal` is zero.
+    #[must_use]
+    pub fn new(point: Vec3, normal: Vec3, mat: Box<dyn Material>) -> Self {
+        assert!(!normal.near_zero(), "Plane normal can't be zero");
+        Self {
+            point,
+            normal: normal.unit(),
+            mat,
+            tolerances: Tolerances::DEFAULT,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let denom = dot(&self.normal, &ray.direction);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let t = dot(&self.normal, &(self.point - ray.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            t,
+            p: ray.at(t),
+            norm: self.normal,
+            mat: &*self.mat,
+            front_face: false,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+            layer: None,
+            tangent: None,
+        };
+        rec.set_face_norm(ray, &self.normal);
+        Some(rec)
+    }
+
+    /// Infinite along every axis, except along the normal of a plane facing
+    /// straight down an axis, where it's [padded](Aabb::padded()) by the
+    /// [bound padding](Tolerances::bound_padding) of its tolerances.
+    fn bound(&self) -> Aabb {
+        let axis = |i: usize| {
+            if (0..3).all(|j| j == i || self.normal[j] == 0.0) {
+                Interval::new(self.point[i], self.point[i])
+            } else {
+                Interval::universe()
+            }
+        };
+        Aabb {
+            x: axis(0),
+            y: axis(1),
+            z: axis(2),
+        }
+        .padded(self.tolerances.bound_padding)
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.point += offset;
+        true
+    }
+
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.tolerances = *tolerances;
+    }
+
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        Some(point - self.normal * dot(&self.normal, &(point - self.point)))
+    }
+}
@@ -0,0 +1,168 @@
+//! This module contains [`PointCloud`], the first surface seen through each
+//! pixel as a coloured point, made from a
+//! [position render](crate::camera::Camera::render_position()) and the
+//! render itself, for checking where geometry ended up in a 3D viewer or
+//! comparing against a reconstruction of the scene from its images.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use image::Rgb;
+
+use crate::{film::FilmBuffer, vec3::Vec3};
+
+/// A point on a surface and the colour it was seen as.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Point {
+    /// The world-space position of the point.
+    pub position: Vec3,
+    /// The gamma-encoded 8-bit colour the point was seen as.
+    pub colour: Rgb<u8>,
+}
+
+/// A list of coloured [`Point`]s, saved as a PLY file.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder},
+///     hit_list::HittableList,
+///     material::Lambertian,
+///     point_cloud::PointCloud,
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let mut world = HittableList::new();
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, 0.0, -5.0],
+///     radius: 1.0,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// }));
+///
+/// let camera = CameraBuilder::default()
+///     .set_image_width(16)
+///     .set_anti_aliasing(AntiAliasing::Random(4))
+///     .build().unwrap();
+/// let cloud = PointCloud::from_films(&camera.render_position(&world), &camera.render_film(&world));
+///
+/// // only the pixels that hit the sphere have a point
+/// assert!(!cloud.is_empty() && cloud.len() < 16 * 16);
+/// assert!(cloud
+///     .points()
+///     .iter()
+///     .all(|point| ((point.position - vec3![0.0, 0.0, -5.0]).length() - 1.0).abs() < 1e-6));
+///
+/// let ply = cloud.to_ply();
+/// let header = format!(
+///     "ply\nformat binary_little_endian 1.0\nelement vertex {}\n",
+///     cloud.len()
+/// );
+/// assert!(ply.starts_with(header.as_bytes()));
+/// // three floats and three bytes a point after the header
+/// let end = b"end_header\n";
+/// let body = ply.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+/// assert_eq!(ply.len() - body, cloud.len() * 15);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointCloud {
+    points: Vec<Point>,
+}
+
+impl PointCloud {
+    /// Create a new, empty [`PointCloud`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make a [`PointCloud`] with a point for each pixel of `position`, a
+    /// [position render](crate::camera::Camera::render_position()), that
+    /// hit something, coloured by the same pixel of `colour`.
+    ///
+    /// # Panics
+    ///
+    /// If the two films aren't the same size.
+    #[must_use]
+    pub fn from_films(position: &FilmBuffer, colour: &FilmBuffer) -> Self {
+        assert!(
+            position.width() == colour.width() && position.height() == colour.height(),
+            "Position and colour films must be the same size"
+        );
+        let mut cloud = Self::new();
+        for y in 0..position.height() {
+            for x in 0..position.width() {
+                let p = position.get(x, y);
+                if p.e.iter().all(|c| c.is_finite()) {
+                    cloud.push(p, colour.get(x, y).to_rgb());
+                }
+            }
+        }
+        cloud
+    }
+
+    /// Add a point at `position` coloured `colour`.
+    pub fn push(&mut self, position: Vec3, colour: Rgb<u8>) {
+        self.points.push(Point { position, colour });
+    }
+
+    /// The points in the order they were added.
+    #[must_use]
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// The number of points.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether there are no points.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The points as a binary PLY file, each a `float` `x`, `y` and `z` and
+    /// a `uchar` `red`, `green` and `blue`, which most 3D tools read.
+    #[must_use]
+    pub fn to_ply(&self) -> Vec<u8> {
+        let header = format!(
+            "ply\n\
+             format binary_little_endian 1.0\n\
+             element vertex {}\n\
+             property float x\n\
+             property float y\n\
+             property float z\n\
+             property uchar red\n\
+             property uchar green\n\
+             property uchar blue\n\
+             end_header\n",
+            self.len()
+        );
+        let mut bytes = header.into_bytes();
+        bytes.reserve(self.len() * 15);
+        for point in &self.points {
+            for c in point.position.e {
+                #[expect(clippy::cast_possible_truncation)]
+                bytes.extend_from_slice(&(c as f32).to_le_bytes());
+            }
+            bytes.extend_from_slice(&point.colour.0);
+        }
+        bytes
+    }
+
+    /// Save the points as a binary [PLY file](PointCloud::to_ply()).
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be written.
+    pub fn save_ply<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_ply())
+            .with_context(|| format!("Couldn't write point cloud {}", path.display()))
+    }
+}
@@ -0,0 +1,156 @@
+//! This module contains [`render_material_preview()`], which renders a
+//! material on a ball sitting on a checkered floor, lit by a procedural studio
+//! environment, for thumbnails of material libraries and scene files.
+
+use std::sync::Arc;
+
+use crate::{
+    camera::{AntiAliasing, CameraBuilder},
+    environment::EnvironmentMap,
+    film::FilmBuffer,
+    material::{Lambertian, Material},
+    quad::Quad,
+    scene::{Scene, SceneSettings},
+    sphere::Sphere,
+    texture::{CheckerTexture, Textured},
+    utils::{deg_to_rad, equirect_to_direction},
+    vec3,
+    vec3::{dot, Vec3},
+};
+
+/// Samples per pixel of a preview.
+pub const PREVIEW_SAMPLES: u16 = 64;
+
+/// The size of the studio environment image.
+const STUDIO_WIDTH: u32 = 128;
+const STUDIO_HEIGHT: u32 = 64;
+
+/// The softboxes of the studio: a direction, an angular radius in degrees and
+/// a radiance. A bright key light to the upper left, a dim fill to the right
+/// and a rim light behind.
+const SOFTBOXES: [(Vec3, f64, f64); 3] = [
+    (vec3![-1.0, 1.0, 1.0], 20.0, 6.0),
+    (vec3![1.5, 0.3, 1.0], 25.0, 1.5),
+    (vec3![0.0, 0.8, -1.0], 15.0, 4.0),
+];
+
+/// A studio environment of soft grey walls and three round softboxes, the
+/// lighting of [`preview_scene()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{preview::studio_environment, vec3, vec3::Vec3};
+///
+/// let studio = studio_environment();
+/// // the key light is far brighter than the walls and the floor below
+/// let key = studio.radiance(vec3![-1.0, 1.0, 1.0].unit());
+/// assert!(key.luminance() > 10.0 * studio.radiance(vec3![0.0, 0.1, -1.0]).luminance());
+/// assert!(studio.radiance(vec3![0.0, -1.0, 0.0]).luminance() < 0.1);
+/// ```
+#[must_use]
+pub fn studio_environment() -> EnvironmentMap {
+    let rows = (0..STUDIO_HEIGHT)
+        .map(|j| {
+            (0..STUDIO_WIDTH)
+                .map(|i| {
+                    let dir = equirect_to_direction(
+                        (f64::from(i) + 0.5) / f64::from(STUDIO_WIDTH),
+                        (f64::from(j) + 0.5) / f64::from(STUDIO_HEIGHT),
+                    );
+                    // lighter towards the ceiling, dark below the horizon
+                    let wall = if dir[1] > 0.0 {
+                        0.15 + 0.1 * dir[1]
+                    } else {
+                        0.05
+                    };
+                    let light = SOFTBOXES
+                        .iter()
+                        .filter(|(centre, radius, _)| {
+                            dot(&dir, &centre.unit()) > deg_to_rad(*radius).cos()
+                        })
+                        .map(|(_, _, radiance)| radiance)
+                        .sum::<f64>();
+                    let value = wall + light;
+                    vec3![value, value, value]
+                })
+                .collect()
+        })
+        .collect();
+    EnvironmentMap::new(FilmBuffer::from_rows(rows))
+}
+
+/// Build the preview scene: a unit ball of `material` at the origin, sitting
+/// on an 8 by 8 checkered floor, lit by [`studio_environment()`] and seen by
+/// a single square camera `resolution` pixels across taking
+/// [`PREVIEW_SAMPLES`] samples per pixel. Change the scene before rendering it
+/// for anything [`render_material_preview()`] doesn't do.
+///
+/// # Panics
+///
+/// If `resolution` is zero.
+#[must_use]
+pub fn preview_scene(material: &dyn Material, resolution: u32) -> Scene {
+    assert!(resolution > 0, "Preview resolution must be positive");
+    let mut scene = Scene::new(SceneSettings::default());
+    scene.add(Box::new(Sphere {
+        centre: vec3![0.0, 0.0, 0.0],
+        radius: 1.0,
+        mat: material.clone_box(),
+    }));
+
+    let checker = CheckerTexture::uv(vec3![0.8, 0.8, 0.8], vec3![0.2, 0.2, 0.2], 0.125);
+    scene.add(Box::new(Quad::new(
+        vec3![-4.0, -1.0, -4.0],
+        vec3![0.0, 0.0, 8.0],
+        vec3![8.0, 0.0, 0.0],
+        Box::new(Textured::new(
+            Box::new(checker),
+            Box::new(Lambertian::new(vec3![1.0, 1.0, 1.0])),
+        )),
+    )));
+
+    scene.add_camera(
+        "preview",
+        CameraBuilder::default()
+            .set_aspect_ratio(1.0)
+            .set_image_width(resolution)
+            .set_anti_aliasing(AntiAliasing::Random(PREVIEW_SAMPLES))
+            .set_max_depth(16)
+            .set_vfov_degrees(30.0)
+            .set_look_from(vec3![0.0, 1.2, 6.0])
+            .set_look_at(vec3![0.0, -0.2, 0.0])
+            .set_environment(Arc::new(studio_environment()))
+            .build()
+            .expect("a square camera with pixels is valid"),
+    );
+    scene
+}
+
+/// Render a square `resolution` pixel thumbnail of `material` on the ball of
+/// [`preview_scene()`].
+///
+/// # Panics
+///
+/// If `resolution` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{material::Lambertian, preview::render_material_preview, vec3, vec3::Vec3};
+///
+/// let film = render_material_preview(&Lambertian::new(vec3![0.8, 0.1, 0.1]), 16);
+/// assert_eq!((film.width(), film.height()), (16, 16));
+///
+/// // the ball in the middle is red
+/// let centre = film.get(8, 8);
+/// assert!(centre[0] > 2.0 * centre[1] && centre[0] > 2.0 * centre[2]);
+/// ```
+#[must_use]
+pub fn render_material_preview(material: &dyn Material, resolution: u32) -> FilmBuffer {
+    let scene = preview_scene(material, resolution);
+    let camera = scene
+        .camera("preview")
+        .expect("the preview scene has a camera");
+    camera.render_film(scene.world())
+}
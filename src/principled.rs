@@ -0,0 +1,388 @@
+//! This module contains the [`Principled`] [material](Material), the Disney
+//! "principled" BSDF (Burley 2012, 2015) as found in Blender and most other
+//! DCC tools, so looks made there can be ported over parameter for parameter.
+
+use std::f64::consts::PI;
+
+use rand::{rngs::SmallRng, RngExt as _};
+
+use crate::{
+    hit::HitRecord,
+    material::{metallic_f0, schlick_fresnel, Dielectric, Ggx, Material},
+    ray,
+    ray::Ray,
+    utils::check_unit,
+    vec3,
+    vec3::{dot, Vec3},
+};
+
+/// The Disney principled BSDF, one [material](Material) layering every lobe
+/// an artist usually needs behind a handful of `0.0..=1.0` sliders:
+///
+/// - a Burley diffuse base with retro-reflection at grazing angles,
+/// - a GGX specular lobe, tinted by the base colour as it gets more metallic,
+/// - a sheen lobe for cloth,
+/// - a clear coat, a second, usually glossier specular lobe on top,
+/// - and rough transmission through the surface, for glass and liquids.
+///
+/// The defaults match Blender's Principled BSDF node, and each lobe is
+/// enabled with one of the `with_*` methods. Every scatter picks one lobe in
+/// proportion to how much light it reflects.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::HitRecord,
+///     material::Material,
+///     principled::Principled,
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let car_paint = Principled::new(vec3![0.6, 0.02, 0.02])
+///     .with_metallic(0.3)
+///     .with_roughness(0.4)
+///     .with_clearcoat(1.0, 0.05);
+/// let frosted_glass = Principled::new(vec3![1.0, 1.0, 1.0])
+///     .with_roughness(0.3)
+///     .with_transmission(1.0, 1.5);
+///
+/// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+/// let rec = HitRecord {
+///     p: vec3![0.0, 0.0, 1.0],
+///     norm: vec3![0.0, 0.0, 1.0],
+///     mat: &frosted_glass,
+///     t: 1.0,
+///     front_face: true,
+///     u: 0.0,
+///     v: 0.0,
+///     object: None,
+///     layer: None,
+///     tangent: None,
+/// };
+///
+/// // light gets through the glass, but not the paint
+/// assert!(frosted_glass.transmittance(&r, &rec)[0] > 0.9);
+/// assert!(car_paint.transmittance(&r, &rec).near_zero());
+/// assert!(car_paint.is_diffuse() && !frosted_glass.is_diffuse());
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Principled {
+    base_colour: Vec3,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    sheen: f64,
+    sheen_tint: f64,
+    clearcoat: f64,
+    clearcoat_roughness: f64,
+    transmission: f64,
+    ior: f64,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self::new(vec3![0.8, 0.8, 0.8])
+    }
+}
+
+impl Principled {
+    /// Create a new [`Principled`] with a `base_colour`, the albedo of its
+    /// diffuse and transmissive parts and the reflectance of its metallic
+    /// part, and otherwise Blender's defaults: a dielectric with a roughness
+    /// of `0.5` and a specular of `0.5`, and no sheen, clear coat or
+    /// transmission.
+    #[must_use]
+    pub fn new(base_colour: Vec3) -> Self {
+        Self {
+            base_colour,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            transmission: 0.0,
+            ior: 1.45,
+        }
+    }
+
+    /// Set how metallic the [`Principled`] is, blending from a dielectric to
+    /// a metal reflecting in its base colour.
+    ///
+    /// # Panics
+    ///
+    /// If `metallic` exceeds the range `0.0..=1.0`.
+    #[must_use]
+    pub fn with_metallic(self, metallic: f64) -> Self {
+        check_unit("Metallic", metallic).unwrap();
+        Self { metallic, ..self }
+    }
+
+    /// Set the perceptual roughness of the specular and transmission lobes,
+    /// and how much the diffuse lobe retro-reflects.
+    ///
+    /// # Panics
+    ///
+    /// If `roughness` exceeds the range `0.0..=1.0`.
+    #[must_use]
+    pub fn with_roughness(self, roughness: f64) -> Self {
+        check_unit("Roughness", roughness).unwrap();
+        Self { roughness, ..self }
+    }
+
+    /// Set the strength of the dielectric specular lobe, where `0.5` is the
+    /// 4% reflectance at normal incidence of most dielectrics, and `1.0` is
+    /// 8%.
+    ///
+    /// # Panics
+    ///
+    /// If `specular` exceeds the range `0.0..=1.0`.
+    #[must_use]
+    pub fn with_specular(self, specular: f64) -> Self {
+        check_unit("Specular", specular).unwrap();
+        Self { specular, ..self }
+    }
+
+    /// Add a `sheen` lobe, glowing at grazing angles like cloth, in a colour
+    /// blended from white to the base colour's hue by `tint`.
+    ///
+    /// # Panics
+    ///
+    /// If `sheen` or `tint` exceed the range `0.0..=1.0`.
+    #[must_use]
+    pub fn with_sheen(self, sheen: f64, tint: f64) -> Self {
+        check_unit("Sheen", sheen).unwrap();
+        check_unit("Sheen tint", tint).unwrap();
+        Self {
+            sheen,
+            sheen_tint: tint,
+            ..self
+        }
+    }
+
+    /// Add a colourless `clearcoat` lobe with its own `roughness`, like
+    /// lacquer or car paint.
+    ///
+    /// # Panics
+    ///
+    /// If `clearcoat` or `roughness` exceed the range `0.0..=1.0`.
+    #[must_use]
+    pub fn with_clearcoat(self, clearcoat: f64, roughness: f64) -> Self {
+        check_unit("Clearcoat", clearcoat).unwrap();
+        check_unit("Clearcoat roughness", roughness).unwrap();
+        Self {
+            clearcoat,
+            clearcoat_roughness: roughness,
+            ..self
+        }
+    }
+
+    /// Let light through the dielectric part of the [`Principled`], bending
+    /// by the refractive index `ior` and tinted by the base colour.
+    ///
+    /// # Panics
+    ///
+    /// If `transmission` exceeds the range `0.0..=1.0`, or `ior` isn't
+    /// positive and finite.
+    #[must_use]
+    pub fn with_transmission(self, transmission: f64, ior: f64) -> Self {
+        check_unit("Transmission", transmission).unwrap();
+        assert!(
+            ior > 0.0 && ior.is_finite(),
+            "Index of refraction must be positive and finite"
+        );
+        Self {
+            transmission,
+            ior,
+            ..self
+        }
+    }
+
+    /// The reflectance at normal incidence of the specular lobe, with the
+    /// specular level scaling that of its non-metallic parts.
+    fn f0(&self) -> Vec3 {
+        metallic_f0(0.08 * self.specular, self.base_colour, self.metallic)
+    }
+
+    /// The colour of the sheen lobe, blending from white to the hue of the
+    /// base colour with the sheen tint.
+    fn sheen_colour(&self) -> Vec3 {
+        let white = vec3![1.0, 1.0, 1.0];
+        let luminance = self.base_colour.luminance();
+        let hue = if luminance > 0.0 {
+            self.base_colour / luminance
+        } else {
+            white
+        };
+        white * (1.0 - self.sheen_tint) + hue * self.sheen_tint
+    }
+
+    /// Scatter off the diffuse lobe and the sheen over it, cosine weighted.
+    /// Returns the direction and the BSDF over the sampling pdf.
+    fn scatter_diffuse(&self, norm: Vec3, view: Vec3, rng: &mut SmallRng) -> (Vec3, Vec3) {
+        let mut light = norm + Vec3::random_unit(rng);
+        if light.near_zero() {
+            light = norm;
+        }
+        let light = light.unit();
+
+        let cos_v = dot(&norm, &view).max(1e-4);
+        let cos_l = dot(&norm, &light).max(1e-4);
+        let cos_d = dot(&light, &(view + light).unit()).clamp(0.0, 1.0);
+
+        // Burley's retro-reflection, brightening the rims of rough surfaces
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let grazing = |cos: f64| 1.0 + (fd90 - 1.0) * (1.0 - cos).powi(5);
+        let diffuse = self.base_colour * (grazing(cos_l) * grazing(cos_v));
+
+        // cosine-weighted sampling has pdf `cos_l / PI`, leaving `f * PI`,
+        // and the sheen isn't divided by `PI` to start with
+        let sheen = self.sheen_colour() * (self.sheen * (1.0 - cos_d).powi(5) * PI);
+
+        (light, diffuse + sheen)
+    }
+
+    /// Scatter off a GGX specular lobe with reflectance `f0` at normal
+    /// incidence. Returns the direction and the BSDF over the sampling pdf,
+    /// which is zero if the reflection leaves below the surface.
+    fn scatter_specular(
+        norm: Vec3,
+        view: Vec3,
+        alpha: f64,
+        f0: Vec3,
+        rng: &mut SmallRng,
+    ) -> (Vec3, Vec3) {
+        let ggx = Ggx::isotropic(norm, alpha);
+        let half = ggx.sample_visible_normal(view, rng);
+        let light = (-view).reflect(&half);
+        if dot(&norm, &light) <= 0.0 {
+            return (light, Vec3::default());
+        }
+
+        let fresnel = schlick_fresnel(f0, dot(&view, &half));
+        (light, fresnel * ggx.masking(view, light))
+    }
+
+    /// Scatter through a rough dielectric interface, reflecting or
+    /// refracting off a GGX microfacet by its Fresnel reflectance. Returns
+    /// the direction and the BSDF over the sampling pdf.
+    fn scatter_transmission(
+        &self,
+        rec: &HitRecord,
+        view: Vec3,
+        rng: &mut SmallRng,
+    ) -> (Vec3, Vec3) {
+        let ri = if rec.front_face {
+            1.0 / self.ior
+        } else {
+            self.ior
+        };
+        let ggx = Ggx::isotropic(rec.norm, Ggx::alpha(self.roughness));
+        let half = ggx.sample_visible_normal(view, rng);
+
+        let cos_h = dot(&view, &half).clamp(0.0, 1.0);
+        let sin_h = (1.0 - cos_h * cos_h).sqrt();
+        let cannot_refract = ri * sin_h > 1.0;
+
+        if cannot_refract || Dielectric::reflectance(cos_h, ri) > rng.random_range(0.0..=1.0) {
+            let light = (-view).reflect(&half);
+            if dot(&rec.norm, &light) <= 0.0 {
+                return (light, Vec3::default());
+            }
+            (light, vec3![1.0, 1.0, 1.0] * ggx.masking(view, light))
+        } else {
+            let light = (-view).refract(&half, ri);
+            if dot(&rec.norm, &light) >= 0.0 {
+                return (light, Vec3::default());
+            }
+            (light, self.base_colour * ggx.masking(view, light))
+        }
+    }
+}
+
+impl Material for Principled {
+    /// Scatter light [ray](Ray)s off one of the lobes, picked in proportion
+    /// to a rough estimate of the light each reflects from this view, with
+    /// the attenuation weighted by the chance of the pick.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let rng = rng.unwrap();
+        let view = -r_in.direction.unit();
+        let cos_v = dot(&rec.norm, &view).max(1e-4);
+
+        let dielectric = 1.0 - self.metallic;
+        let diffuse_weight = dielectric * (1.0 - self.transmission);
+        let transmission_weight = dielectric * self.transmission;
+        let specular_weight = 1.0 - transmission_weight;
+        let clearcoat_weight = 0.25 * self.clearcoat;
+
+        let coat_f0 = vec3![0.04, 0.04, 0.04];
+        let chances = [
+            diffuse_weight * (self.base_colour.luminance() + self.sheen),
+            specular_weight * schlick_fresnel(self.f0(), cos_v).luminance(),
+            transmission_weight,
+            clearcoat_weight * schlick_fresnel(coat_f0, cos_v).luminance(),
+        ];
+        let total: f64 = chances.iter().sum();
+        let mut pick = rng.random_range(0.0..1.0) * total;
+        let lobe = chances
+            .iter()
+            .position(|&chance| {
+                pick -= chance;
+                pick < 0.0
+            })
+            .unwrap_or(1);
+        let chance = chances[lobe] / total;
+
+        let (direction, weight) = match lobe {
+            0 => {
+                let (light, f) = self.scatter_diffuse(rec.norm, view, rng);
+                (light, f * diffuse_weight)
+            }
+            1 => {
+                let alpha = Ggx::alpha(self.roughness);
+                let (light, f) = Self::scatter_specular(rec.norm, view, alpha, self.f0(), rng);
+                (light, f * specular_weight)
+            }
+            2 => {
+                let (light, f) = self.scatter_transmission(rec, view, rng);
+                (light, f * transmission_weight)
+            }
+            _ => {
+                let alpha = Ggx::alpha(self.clearcoat_roughness);
+                let (light, f) = Self::scatter_specular(rec.norm, view, alpha, coat_f0, rng);
+                (light, f * clearcoat_weight)
+            }
+        };
+
+        // nothing is reflected at all if every lobe is black
+        let weight = if chance > 0.0 {
+            weight / chance
+        } else {
+            Vec3::default()
+        };
+        (ray![rec.p, direction, r_in.time], weight)
+    }
+
+    /// Only pure metals and fully transmissive dielectrics count as
+    /// specular, anything with a diffuse base is diffuse.
+    fn is_diffuse(&self) -> bool {
+        (1.0 - self.metallic) * (1.0 - self.transmission) > 0.0
+    }
+
+    /// The transmissive part's share of what isn't reflected, by the Schlick
+    /// approximation, tinted by the base colour.
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        let cos_theta = dot(&r_in.direction.unit(), &rec.norm).abs().min(1.0);
+        let through = 1.0 - Dielectric::reflectance(cos_theta, self.ior);
+        self.base_colour * (through * (1.0 - self.metallic) * self.transmission)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
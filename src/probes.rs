@@ -0,0 +1,388 @@
+//! This module contains light probe baking for game engine global
+//! illumination: a [`ProbeGrid`] of points filling a box, each storing the
+//! irradiance arriving from every direction as [`ShIrradiance`], order 2
+//! (nine coefficient) spherical harmonics. Bake one with
+//! [`Camera::bake_probe_grid()`], then [save](ProbeGrid::save()) it as JSON or
+//! a compact binary file for the engine to load and interpolate at runtime.
+//!
+//! The coefficients are for the real spherical harmonics in the usual order
+//! (`Y00`, `Y1-1`, `Y10`, `Y11`, `Y2-2`, `Y2-1`, `Y20`, `Y21`, `Y22`), already
+//! convolved with the cosine lobe (Ramamoorthi and Hanrahan, "An Efficient
+//! Representation for Irradiance Environment Maps", 2001), so the irradiance
+//! facing a normal is just the sum of each coefficient times its basis
+//! function, see [`ShIrradiance::irradiance()`].
+
+use std::{f64::consts::PI, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use rand::{
+    rngs::{SmallRng, SysRng},
+    SeedableRng,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
+
+use crate::{
+    aabb::Aabb,
+    camera::{Camera, PathState, Trace},
+    hit::Hittable,
+    interval::Interval,
+    ray,
+    ray::Ray,
+    vec3,
+    vec3::Vec3,
+};
+
+/// The number of spherical harmonic coefficients up to order 2.
+pub const SH_COEFFICIENTS: usize = 9;
+
+/// The first four bytes of a binary probe file.
+const BINARY_MAGIC: &[u8; 4] = b"RSHP";
+
+/// The cosine lobe convolution for each order, `A0`, `A1` and `A2`.
+const COSINE_LOBE: [f64; 3] = [PI, 2.0 * PI / 3.0, PI / 4.0];
+
+/// The order of each coefficient.
+const ORDERS: [usize; SH_COEFFICIENTS] = [0, 1, 1, 1, 2, 2, 2, 2, 2];
+
+/// The real spherical harmonic basis functions up to order 2 at the unit
+/// direction `dir`.
+fn sh_basis(dir: Vec3) -> [f64; SH_COEFFICIENTS] {
+    let [x, y, z] = dir.e;
+    [
+        0.282_095,
+        0.488_603 * y,
+        0.488_603 * z,
+        0.488_603 * x,
+        1.092_548 * x * y,
+        1.092_548 * y * z,
+        0.315_392 * (3.0 * z * z - 1.0),
+        1.092_548 * x * z,
+        0.546_274 * (x * x - y * y),
+    ]
+}
+
+/// The irradiance arriving at a point from every direction, as order 2
+/// spherical harmonics already convolved with the cosine lobe. See the
+/// [module docs](crate::probes) for the basis.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{probes::ShIrradiance, vec3, vec3::Vec3};
+///
+/// // white light from straight up
+/// let mut sh = ShIrradiance::default();
+/// sh.add_radiance(vec3![0.0, 1.0, 0.0], vec3![1.0, 1.0, 1.0], 1.0);
+///
+/// let up = sh.irradiance(vec3![0.0, 1.0, 0.0]);
+/// let side = sh.irradiance(vec3![1.0, 0.0, 0.0]);
+/// assert!(up[0] > side[0]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShIrradiance {
+    /// The coefficient of each basis function, for red, green and blue.
+    pub coefficients: [Vec3; SH_COEFFICIENTS],
+}
+
+impl ShIrradiance {
+    /// Add `radiance` arriving from the unit direction `dir`, over a solid
+    /// angle of `weight` steradians.
+    pub fn add_radiance(&mut self, dir: Vec3, radiance: Vec3, weight: f64) {
+        for ((coefficient, basis), order) in
+            self.coefficients.iter_mut().zip(sh_basis(dir)).zip(ORDERS)
+        {
+            *coefficient += radiance * (basis * COSINE_LOBE[order] * weight);
+        }
+    }
+
+    /// The irradiance on a surface facing the unit `normal`.
+    #[must_use]
+    pub fn irradiance(&self, normal: Vec3) -> Vec3 {
+        self.coefficients
+            .iter()
+            .zip(sh_basis(normal))
+            .fold(vec3![0.0, 0.0, 0.0], |sum, (&coefficient, basis)| {
+                sum + coefficient * basis
+            })
+    }
+}
+
+/// A grid of [`ShIrradiance`] probes at the centres of the cells of a box
+/// split `counts` times along each axis, so none sit on the box's faces. See
+/// the [module docs](crate::probes).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     aabb::Aabb,
+///     camera::{AntiAliasing, Background, CameraBuilder},
+///     hit_list::HittableList,
+///     interval,
+///     interval::Interval,
+///     probes::ProbeGrid,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let room = Aabb {
+///     x: interval![0.0, 4.0],
+///     y: interval![0.0, 2.0],
+///     z: interval![0.0, 2.0],
+/// };
+/// let mut grid = ProbeGrid::new(room, [2, 1, 1]);
+/// assert_eq!(grid.position(1, 0, 0), vec3![3.0, 1.0, 1.0]);
+///
+/// // lit evenly by a white sky, every surface gets `PI` irradiance
+/// let camera = CameraBuilder::default()
+///     .set_anti_aliasing(AntiAliasing::Random(4096))
+///     .set_background(Background::Solid(vec3![1.0, 1.0, 1.0]))
+///     .build().unwrap();
+/// camera.bake_probe_grid(&mut grid, &HittableList::new());
+///
+/// let irradiance = grid.probe(0, 0, 0).irradiance(vec3![0.0, 1.0, 0.0]);
+/// assert!((irradiance[0] - std::f64::consts::PI).abs() < 0.3);
+///
+/// let path = std::env::temp_dir().join("rustrace_probes.json");
+/// grid.save(&path).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProbeGrid {
+    bounds: Aabb,
+    counts: [u32; 3],
+    probes: Vec<ShIrradiance>,
+}
+
+impl ProbeGrid {
+    /// Create a [`ProbeGrid`] of `counts` probes along each axis filling
+    /// `bounds`, all dark until baked.
+    ///
+    /// # Panics
+    ///
+    /// If any of `counts` is `0`, or `bounds` is empty or unbounded.
+    #[must_use]
+    pub fn new(bounds: Aabb, counts: [u32; 3]) -> Self {
+        assert!(
+            counts.iter().all(|&count| count > 0),
+            "Probe grids need at least one probe along each axis"
+        );
+        assert!(
+            !bounds.is_unbounded()
+                && [bounds.x, bounds.y, bounds.z]
+                    .iter()
+                    .all(|axis| axis.size() >= 0.0),
+            "Probe grids must fill a finite box"
+        );
+        let total = counts.iter().map(|&count| count as usize).product();
+        Self {
+            bounds,
+            counts,
+            probes: vec![ShIrradiance::default(); total],
+        }
+    }
+
+    /// Create a [`ProbeGrid`] like [`ProbeGrid::new()`], filling the bounds
+    /// of `world`.
+    ///
+    /// # Errors
+    ///
+    /// If `world` is empty or unbounded, such as when it has an infinite
+    /// ground plane.
+    ///
+    /// # Panics
+    ///
+    /// If any of `counts` is `0`.
+    pub fn fitted(world: &dyn Hittable, counts: [u32; 3]) -> Result<Self> {
+        let bounds = world.bound();
+        if bounds.is_unbounded() || bounds.x.size() < 0.0 {
+            bail!("Can't fit probes to an empty or unbounded world");
+        }
+        Ok(Self::new(bounds, counts))
+    }
+
+    /// The number of probes along each axis.
+    #[must_use]
+    pub fn counts(&self) -> [u32; 3] {
+        self.counts
+    }
+
+    /// The box the probes fill.
+    #[must_use]
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn index(&self, x: u32, y: u32, z: u32) -> usize {
+        let [nx, ny, nz] = self.counts;
+        assert!(x < nx && y < ny && z < nz, "Probe out of bounds");
+        (z as usize * ny as usize + y as usize) * nx as usize + x as usize
+    }
+
+    /// The position of probe `(x, y, z)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y, z)` is out of bounds.
+    #[must_use]
+    pub fn position(&self, x: u32, y: u32, z: u32) -> Vec3 {
+        let [nx, ny, nz] = self.counts.map(f64::from);
+        let along =
+            |axis: Interval, i: u32, n: f64| axis.min + axis.size() * (f64::from(i) + 0.5) / n;
+        // checks the bounds
+        self.index(x, y, z);
+        vec3![
+            along(self.bounds.x, x, nx),
+            along(self.bounds.y, y, ny),
+            along(self.bounds.z, z, nz)
+        ]
+    }
+
+    /// The probe `(x, y, z)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y, z)` is out of bounds.
+    #[must_use]
+    pub fn probe(&self, x: u32, y: u32, z: u32) -> &ShIrradiance {
+        &self.probes[self.index(x, y, z)]
+    }
+
+    /// Every probe's grid coordinates, with `x` changing fastest, then `y`,
+    /// then `z`, the order they're saved in.
+    fn coordinates(&self) -> Vec<(u32, u32, u32)> {
+        let [nx, ny, nz] = self.counts;
+        (0..nz)
+            .flat_map(|z| (0..ny).flat_map(move |y| (0..nx).map(move |x| (x, y, z))))
+            .collect()
+    }
+
+    /// Save the grid as JSON if `path` ends in `.json`, with the bounds,
+    /// counts, and every probe's position and coefficients, or as binary
+    /// otherwise.
+    ///
+    /// The binary layout is all little-endian: the magic bytes `RSHP`, the
+    /// counts as three `u32`s, the bounds' minimum and maximum corners as
+    /// three `f32`s each, then for each probe its nine coefficients as red,
+    /// green and blue `f32`s. Probes are ordered with `x` changing fastest,
+    /// then `y`, then `z`.
+    ///
+    /// # Errors
+    ///
+    /// If writing the file fails.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let bytes = if is_json {
+            self.to_json().into_bytes()
+        } else {
+            self.to_binary()
+        };
+        fs::write(path, bytes).with_context(|| format!("Couldn't write probes {}", path.display()))
+    }
+
+    fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct ProbeFile {
+            min: [f64; 3],
+            max: [f64; 3],
+            counts: [u32; 3],
+            probes: Vec<ProbeEntry>,
+        }
+
+        #[derive(Serialize)]
+        struct ProbeEntry {
+            position: [f64; 3],
+            sh: [[f64; 3]; SH_COEFFICIENTS],
+        }
+
+        let (min, max) = self.corners();
+        serde_json::to_string_pretty(&ProbeFile {
+            min: min.e,
+            max: max.e,
+            counts: self.counts,
+            probes: self
+                .coordinates()
+                .into_iter()
+                .map(|(x, y, z)| ProbeEntry {
+                    position: self.position(x, y, z).e,
+                    sh: self.probe(x, y, z).coefficients.map(|c| c.e),
+                })
+                .collect(),
+        })
+        .expect("Probe grids are always valid JSON")
+    }
+
+    fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = BINARY_MAGIC.to_vec();
+        for count in self.counts {
+            bytes.extend(count.to_le_bytes());
+        }
+        #[expect(clippy::cast_possible_truncation)]
+        let mut push = |value: f64| bytes.extend((value as f32).to_le_bytes());
+
+        let (min, max) = self.corners();
+        min.e.into_iter().chain(max.e).for_each(&mut push);
+        for probe in &self.probes {
+            for coefficient in probe.coefficients {
+                coefficient.e.into_iter().for_each(&mut push);
+            }
+        }
+        bytes
+    }
+
+    fn corners(&self) -> (Vec3, Vec3) {
+        let Aabb { x, y, z } = self.bounds;
+        (vec3![x.min, y.min, z.min], vec3![x.max, y.max, z.max])
+    }
+}
+
+impl Camera {
+    /// Bake every probe of `grid` by tracing the [`Camera`]'s samples per
+    /// pixel worth of [ray](Ray)s from it in uniformly random directions
+    /// into `world`, with the [`Camera`]'s max depth, shutter, background and
+    /// [`RayBias`](crate::camera::RayBias). Probes see lights and the
+    /// background directly, as the camera would.
+    ///
+    /// Probes inside solid objects only see their insides, so leave gaps
+    /// around walls and props or keep the engine from using those probes.
+    ///
+    /// # Panics
+    ///
+    /// May panic if unable to get or use the system random.
+    pub fn bake_probe_grid(&self, grid: &mut ProbeGrid, world: &dyn Hittable) {
+        let min_t = self.ray_bias.resolve(&world.bound());
+        let samples = self.samples_per_pixel();
+        let weight = 4.0 * PI / f64::from(samples);
+
+        let origin = self.render_origin();
+        let positions: Vec<Vec3> = grid
+            .coordinates()
+            .into_iter()
+            .map(|(x, y, z)| grid.position(x, y, z) - origin)
+            .collect();
+
+        grid.probes = positions
+            .into_par_iter()
+            .map(|position| {
+                let mut rng = SmallRng::try_from_rng(&mut SysRng).unwrap();
+                let mut sh = ShIrradiance::default();
+                for _ in 0..samples {
+                    let dir = Vec3::random_unit(&mut rng);
+                    let r = ray!(position, dir, self.sample_time(&mut rng));
+                    let radiance = self.ray_colour(
+                        &r,
+                        PathState::CAMERA,
+                        self.max_depth,
+                        &mut Trace::new(world, min_t),
+                        &mut rng,
+                    );
+                    sh.add_radiance(dir, radiance, weight);
+                }
+                sh
+            })
+            .collect();
+    }
+}
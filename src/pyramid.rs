@@ -0,0 +1,241 @@
+//! Contains the [`DeepZoomWriter`], which writes an image as a tiled,
+//! mipmapped Deep Zoom pyramid one tile at a time, so gigapixel renders never
+//! need the whole image in memory. Deep Zoom viewers open the `.dzi` file and
+//! stream in just the tiles they need. See
+//! [`Camera::render_deep_zoom()`](crate::camera::Camera::render_deep_zoom()).
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use rayon::iter::{IntoParallelIterator as _, ParallelIterator as _};
+
+use crate::{
+    camera::Tile,
+    film::{is_hdr_path, ColourSpace, FilmBuffer},
+    vec3::Vec3,
+};
+
+/// Writes a Deep Zoom image pyramid: a `.dzi` description next to a
+/// `<name>_files` directory with a subdirectory of tiles per level. The most
+/// detailed level is the image itself, and each level below is half the size
+/// of the one above, down to a single pixel.
+///
+/// Tiles of the full image are written as they're given to
+/// [`DeepZoomWriter::write_tile()`]. [`DeepZoomWriter::finish()`] then builds
+/// each smaller level from the four tiles above each of its tiles, read back
+/// from disk, so only a handful of tiles are ever held at once.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{camera::Tile, film::FilmBuffer, pyramid::DeepZoomWriter, vec3, vec3::Vec3};
+///
+/// let dir = std::env::temp_dir().join("rustrace-deep-zoom-doctest");
+/// let writer = DeepZoomWriter::new(dir.join("image.dzi"), 5, 3, 4);
+/// assert_eq!(writer.levels(), 4);
+///
+/// let mut tile = FilmBuffer::new(4, 3);
+/// tile.pixels_mut().fill(vec3![0.25, 0.25, 0.25]);
+/// writer.write_tile(Tile::new(0, 0, 4, 3), &tile).unwrap();
+/// writer.write_tile(Tile::new(4, 0, 1, 3), &FilmBuffer::new(1, 3)).unwrap();
+/// writer.finish().unwrap();
+///
+/// assert!(dir.join("image_files/3/1_0.png").exists());
+/// // down to a single pixel at the bottom of the pyramid
+/// let top = FilmBuffer::load(dir.join("image_files/0/0_0.png"), Default::default()).unwrap();
+/// assert_eq!((top.width(), top.height()), (1, 1));
+/// # std::fs::remove_dir_all(dir).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeepZoomWriter {
+    path: PathBuf,
+    tiles_dir: PathBuf,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    format: String,
+}
+
+impl DeepZoomWriter {
+    /// Create a new [`DeepZoomWriter`] for an image of `width` by `height`
+    /// pixels, described by the `.dzi` file at `path`, in tiles of
+    /// `tile_size` pixels. Tiles are PNGs unless set otherwise with
+    /// [`DeepZoomWriter::set_format()`]. Nothing is written until the first
+    /// tile.
+    ///
+    /// # Panics
+    ///
+    /// If any of `width`, `height` or `tile_size` are `0`.
+    #[must_use]
+    pub fn new<P: AsRef<Path>>(path: P, width: u32, height: u32, tile_size: u32) -> Self {
+        assert!(width > 0 && height > 0, "The image must have pixels");
+        assert!(tile_size > 0, "Tiles need at least one pixel");
+
+        let path = path.as_ref().to_path_buf();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let tiles_dir = path.with_file_name(format!("{stem}_files"));
+        Self {
+            path,
+            tiles_dir,
+            width,
+            height,
+            tile_size,
+            format: "png".to_string(),
+        }
+    }
+
+    /// Set the image format of the tiles by its extension, such as `"jpg"`,
+    /// or `"exr"` for linear HDR tiles.
+    #[must_use]
+    pub fn set_format(mut self, extension: &str) -> Self {
+        extension.clone_into(&mut self.format);
+        self
+    }
+
+    /// The number of levels in the pyramid, from a single pixel up to the
+    /// full image.
+    #[must_use]
+    pub fn levels(&self) -> u32 {
+        self.width.max(self.height).next_power_of_two().ilog2() + 1
+    }
+
+    /// The size of the image at `level`, halving (rounding up) for each level
+    /// below the top.
+    fn level_size(&self, level: u32) -> (u32, u32) {
+        let shift = self.levels() - 1 - level;
+        let halve = |length: u32| length.div_ceil(1 << shift);
+        (halve(self.width), halve(self.height))
+    }
+
+    fn tile_path(&self, level: u32, column: u32, row: u32) -> PathBuf {
+        self.tiles_dir
+            .join(level.to_string())
+            .join(format!("{column}_{row}.{}", self.format))
+    }
+
+    fn save(&self, level: u32, column: u32, row: u32, film: &FilmBuffer) -> Result<()> {
+        let path = self.tile_path(level, column, row);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Couldn't create tile directory {}", dir.display()))?;
+        }
+        film.save(&path)
+            .with_context(|| format!("Couldn't save tile {}", path.display()))
+    }
+
+    /// Read a tile back as linear colour, undoing the gamma of 8-bit tiles.
+    fn load(&self, level: u32, column: u32, row: u32) -> Result<FilmBuffer> {
+        let path = self.tile_path(level, column, row);
+        let mut film = FilmBuffer::load(&path, ColourSpace::Linear)?;
+        if !is_hdr_path(&path) {
+            for pixel in film.pixels_mut() {
+                *pixel = *pixel * *pixel;
+            }
+        }
+        Ok(film)
+    }
+
+    /// Write `film` as the full resolution `tile` of the image.
+    ///
+    /// # Errors
+    ///
+    /// If `tile` isn't one of the image's tiles, `film` isn't its size, or
+    /// saving it fails.
+    pub fn write_tile(&self, tile: Tile, film: &FilmBuffer) -> Result<()> {
+        let size = self.tile_size;
+        let expected = Tile::new(
+            tile.x,
+            tile.y,
+            size.min(self.width.saturating_sub(tile.x)),
+            size.min(self.height.saturating_sub(tile.y)),
+        );
+        if !tile.x.is_multiple_of(size) || !tile.y.is_multiple_of(size) || tile != expected {
+            bail!("{tile:?} isn't one of the image's {size} pixel tiles");
+        }
+        if (film.width(), film.height()) != (tile.width, tile.height) {
+            bail!(
+                "A {}x{} image can't fill {tile:?}",
+                film.width(),
+                film.height()
+            );
+        }
+        self.save(self.levels() - 1, tile.x / size, tile.y / size, film)
+    }
+
+    /// Build the smaller levels from the full resolution tiles, which must
+    /// all have been written, then write the `.dzi` file.
+    ///
+    /// # Errors
+    ///
+    /// If reading or writing any tile, or writing the `.dzi` file, fails.
+    pub fn finish(&self) -> Result<()> {
+        let size = self.tile_size;
+        for level in (0..self.levels() - 1).rev() {
+            let (width, height) = self.level_size(level);
+            let above = self.level_size(level + 1);
+            let tiles: Vec<(u32, u32)> = (0..height.div_ceil(size))
+                .flat_map(|row| (0..width.div_ceil(size)).map(move |column| (column, row)))
+                .collect();
+
+            tiles.into_par_iter().try_for_each(|(column, row)| {
+                // the up to four tiles above this one
+                let mut quarters = vec![];
+                for dy in 0..2 {
+                    for dx in 0..2 {
+                        let (c, r) = (2 * column + dx, 2 * row + dy);
+                        quarters.push(if c * size < above.0 && r * size < above.1 {
+                            Some(self.load(level + 1, c, r)?)
+                        } else {
+                            None
+                        });
+                    }
+                }
+                let pixel_above = |x: u32, y: u32| -> Option<Vec3> {
+                    let (dx, dy) = (x / size - 2 * column, y / size - 2 * row);
+                    let quarter = quarters[(2 * dy + dx) as usize].as_ref()?;
+                    Some(quarter.get(x % size, y % size))
+                };
+
+                let tile_width = size.min(width - column * size);
+                let tile_height = size.min(height - row * size);
+                let mut film = FilmBuffer::new(tile_width, tile_height);
+                for y in 0..tile_height {
+                    for x in 0..tile_width {
+                        let (gx, gy) = (column * size + x, row * size + y);
+                        let mut total = Vec3::default();
+                        let mut count = 0.0;
+                        for (ax, ay) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                            let (px, py) = (2 * gx + ax, 2 * gy + ay);
+                            if px < above.0 && py < above.1 {
+                                if let Some(colour) = pixel_above(px, py) {
+                                    total += colour;
+                                    count += 1.0;
+                                }
+                            }
+                        }
+                        if count > 0.0 {
+                            film.set(x, y, total / count);
+                        }
+                    }
+                }
+                self.save(level, column, row, &film)
+            })?;
+        }
+
+        let description = format!(
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" ",
+                "Format=\"{}\" Overlap=\"0\" TileSize=\"{}\">\n",
+                "  <Size Width=\"{}\" Height=\"{}\"/>\n",
+                "</Image>\n"
+            ),
+            self.format, size, self.width, self.height
+        );
+        fs::write(&self.path, description)
+            .with_context(|| format!("Couldn't write {}", self.path.display()))
+    }
+}
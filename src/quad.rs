@@ -0,0 +1,251 @@
+//! Contains the [`Quad`] struct which models a flat parallelogram, such as the
+//! walls and lights of a Cornell box, and [`cuboid()`] which closes six of them
+//! into a box.
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    hit_list::HittableList,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    tolerance::Tolerances,
+    vec3,
+    vec3::{cross, dot, Vec3},
+};
+
+/// A parallelogram with a corner at [`origin`](field@Quad::origin) and edges
+/// [`u`](field@Quad::u) and [`v`](field@Quad::v) from it. Its front faces
+/// along `u x v`, so a wall facing into a room is wound anticlockwise as seen
+/// from inside.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable,
+///     material::Lambertian,
+///     quad::Quad,
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // a unit square floor facing +Y
+/// let floor = Quad::new(
+///     vec3![0.0, 0.0, 0.0],
+///     vec3![0.0, 0.0, 1.0],
+///     vec3![1.0, 0.0, 0.0],
+///     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// );
+/// assert_eq!(floor.normal(), vec3![0.0, 1.0, 0.0]);
+///
+/// let rec = floor
+///     .nearest_hit_along(&ray!(vec3![0.25, 2.0, 0.75], vec3![0.0, -1.0, 0.0]))
+///     .unwrap();
+/// assert_eq!(rec.p, vec3![0.25, 0.0, 0.75]);
+/// assert!(rec.front_face);
+///
+/// // just off the edge
+/// assert!(floor
+///     .nearest_hit_along(&ray!(vec3![1.25, 2.0, 0.75], vec3![0.0, -1.0, 0.0]))
+///     .is_none());
+///
+/// // still has some thickness to its bounds
+/// assert!(floor.bound().y.size() > 0.0);
+/// ```
+#[derive(Debug)]
+pub struct Quad {
+    /// One corner of the quad.
+    pub origin: Vec3,
+    /// The first edge from the [`origin`](field@Quad::origin).
+    pub u: Vec3,
+    /// The second edge from the [`origin`](field@Quad::origin).
+    pub v: Vec3,
+    /// The [`Material`] of the quad.
+    pub mat: Box<dyn Material>,
+    /// The tolerances the quad is bounded with, see
+    /// [`Hittable::set_tolerances()`].
+    pub tolerances: Tolerances,
+}
+
+impl Quad {
+    /// Create a new [`Quad`] with a corner at `origin` and edges `u` and `v`.
+    #[must_use]
+    pub fn new(origin: Vec3, u: Vec3, v: Vec3, mat: Box<dyn Material>) -> Self {
+        Self {
+            origin,
+            u,
+            v,
+            mat,
+            tolerances: Tolerances::DEFAULT,
+        }
+    }
+
+    /// The unit normal of the front face, along `u x v`.
+    #[must_use]
+    pub fn normal(&self) -> Vec3 {
+        cross(&self.u, &self.v).unit()
+    }
+
+    /// The coordinates of `p` (on the quad's plane) along
+    /// [`u`](field@Quad::u) and [`v`](field@Quad::v), each from `0.0` to `1.0`
+    /// across the quad.
+    fn coordinates(&self, p: Vec3) -> (f64, f64) {
+        let n = cross(&self.u, &self.v);
+        let w = n / n.length_squared();
+        let offset = p - self.origin;
+        (
+            dot(&w, &cross(&offset, &self.v)),
+            dot(&w, &cross(&self.u, &offset)),
+        )
+    }
+}
+
+impl Hittable for Quad {
+    /// Intersect the quad's plane, then check the hit is within the edges.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let norm = self.normal();
+        let denom = dot(&norm, &ray.direction);
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = dot(&norm, &(self.origin - ray.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let p = ray.at(t);
+        let (alpha, beta) = self.coordinates(p);
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            t,
+            p,
+            norm,
+            mat: &*self.mat,
+            front_face: false,
+            u: alpha,
+            v: beta,
+            object: None,
+            layer: None,
+            tangent: Some(self.u),
+        };
+        rec.set_face_norm(ray, &norm);
+        Some(rec)
+    }
+
+    /// The bounds of the four corners, [padded](Aabb::padded()) by the
+    /// [bound padding](Tolerances::bound_padding) of its tolerances along any
+    /// axis the quad lies flat in.
+    fn bound(&self) -> Aabb {
+        let mut bound = Aabb::new();
+        for corner in [
+            self.origin,
+            self.origin + self.u,
+            self.origin + self.v,
+            self.origin + self.u + self.v,
+        ] {
+            bound.union(&Aabb {
+                x: Interval::new(corner[0], corner[0]),
+                y: Interval::new(corner[1], corner[1]),
+                z: Interval::new(corner[2], corner[2]),
+            });
+        }
+        bound.padded(self.tolerances.bound_padding)
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.origin += offset;
+        true
+    }
+
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.tolerances = *tolerances;
+    }
+
+    /// The projection of `point` onto the quad's plane if it lands inside,
+    /// otherwise the closest point on the nearest edge.
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        let norm = self.normal();
+        let on_plane = point - norm * dot(&norm, &(point - self.origin));
+        let (alpha, beta) = self.coordinates(on_plane);
+        if (0.0..=1.0).contains(&alpha) && (0.0..=1.0).contains(&beta) {
+            return Some(on_plane);
+        }
+
+        let on_segment = |start: Vec3, edge: Vec3| {
+            let t = (dot(&(point - start), &edge) / edge.length_squared()).clamp(0.0, 1.0);
+            start + edge * t
+        };
+        let far = self.origin + self.u + self.v;
+        [
+            on_segment(self.origin, self.u),
+            on_segment(self.origin, self.v),
+            on_segment(far, -self.u),
+            on_segment(far, -self.v),
+        ]
+        .into_iter()
+        .min_by(|a, b| {
+            (*a - point)
+                .length_squared()
+                .total_cmp(&(*b - point).length_squared())
+        })
+    }
+}
+
+/// An axis-aligned box between opposite corners `a` and `b`, as six [`Quad`]s
+/// facing outwards, so it can be used as a closed solid, e.g. in a
+/// [`Csg`](crate::csg::Csg).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable,
+///     material::Lambertian,
+///     quad::cuboid,
+///     ray,
+///     ray::Ray,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let crate_box = cuboid(
+///     vec3![1.0, 0.0, 1.0],
+///     vec3![-1.0, 2.0, -1.0],
+///     &Lambertian::new(vec3![0.5, 0.5, 0.5]),
+/// );
+/// assert_eq!(crate_box.objects.len(), 6);
+///
+/// let rec = crate_box
+///     .nearest_hit_along(&ray!(vec3![0.0, 1.0, 5.0], vec3![0.0, 0.0, -1.0]))
+///     .unwrap();
+/// assert_eq!(rec.p, vec3![0.0, 1.0, 1.0]);
+/// assert!(rec.front_face);
+/// ```
+#[must_use]
+pub fn cuboid(a: Vec3, b: Vec3, mat: &dyn Material) -> HittableList {
+    let min = vec3![a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])];
+    let max = vec3![a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])];
+    let dx = vec3![max[0] - min[0], 0.0, 0.0];
+    let dy = vec3![0.0, max[1] - min[1], 0.0];
+    let dz = vec3![0.0, 0.0, max[2] - min[2]];
+
+    let mut sides = HittableList::new();
+    for (origin, u, v) in [
+        (vec3![min[0], min[1], max[2]], dx, dy),
+        (vec3![max[0], min[1], min[2]], -dx, dy),
+        (vec3![max[0], min[1], max[2]], -dz, dy),
+        (min, dz, dy),
+        (vec3![min[0], max[1], max[2]], dx, -dz),
+        (min, dx, dz),
+    ] {
+        sides.add(Box::new(Quad::new(origin, u, v, mat.clone_box())));
+    }
+    sides
+}
@@ -1,7 +1,8 @@
 //! Contains the [`Ray`] struct which is a simple abstraction over two [`Vec3`]s for
-//! the rays starting position and direction of travel.
+//! the rays starting position and direction of travel, plus the time the ray
+//! was sent at (for motion blur).
 
-use crate::vec3::Vec3;
+use crate::{vec3, vec3::Vec3};
 
 /// The [`Ray`] struct itself.
 #[derive(Copy, Clone, Debug)]
@@ -13,6 +14,9 @@ pub struct Ray {
     // runtime overhead.
     /// The unit direction vector of the ray.
     pub direction: Vec3,
+
+    /// The time within the camera shutter interval that the ray exists at.
+    pub time: f64,
 }
 
 #[macro_export]
@@ -21,6 +25,14 @@ macro_rules! ray {
         Ray {
             origin: $o,
             direction: $d,
+            time: 0.0,
+        }
+    };
+    ($o:expr, $d:expr, $t:expr $(,)?) => {
+        Ray {
+            origin: $o,
+            direction: $d,
+            time: $t,
         }
     };
 }
@@ -46,3 +58,94 @@ impl Ray {
         self.origin + self.direction * t
     }
 }
+
+/// Many [ray](Ray)s stored field by field (a structure of arrays), the layout
+/// packet tracers and GPU uploads want, along with the pixel and sample each
+/// was made for. See [`Camera::generate_rays()`](crate::camera::Camera::generate_rays()).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{ray::{Ray, RayBatch}, ray, vec3::Vec3, vec3};
+///
+/// let mut batch = RayBatch::default();
+/// batch.push(&ray!(vec3![0.0, 0.0, 0.0], vec3![0.0, 0.0, -1.0], 0.5), (3, 4), 0);
+///
+/// assert_eq!(batch.len(), 1);
+/// assert_eq!(batch.directions[2], [-1.0]);
+/// assert_eq!(batch.ray(0).time, 0.5);
+/// assert_eq!(batch.pixels[0], (3, 4));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RayBatch {
+    /// The `x`, `y` and `z` of each origin.
+    pub origins: [Vec<f64>; 3],
+    /// The `x`, `y` and `z` of each direction, which aren't necessarily unit
+    /// length.
+    pub directions: [Vec<f64>; 3],
+    /// The time of each ray.
+    pub times: Vec<f64>,
+    /// The pixel `(i, j)` each ray was made for.
+    pub pixels: Vec<(u32, u32)>,
+    /// Which of its pixel's samples each ray is.
+    pub samples: Vec<u32>,
+}
+
+impl RayBatch {
+    /// Create an empty [`RayBatch`] with room for `capacity` rays.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let column = || Vec::with_capacity(capacity);
+        Self {
+            origins: [column(), column(), column()],
+            directions: [column(), column(), column()],
+            times: column(),
+            pixels: Vec::with_capacity(capacity),
+            samples: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Add `ray`, made for `sample` of `pixel`.
+    pub fn push(&mut self, ray: &Ray, pixel: (u32, u32), sample: u32) {
+        for axis in 0..3 {
+            self.origins[axis].push(ray.origin[axis]);
+            self.directions[axis].push(ray.direction[axis]);
+        }
+        self.times.push(ray.time);
+        self.pixels.push(pixel);
+        self.samples.push(sample);
+    }
+
+    /// The number of rays in the batch.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Whether the batch has no rays.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// The ray at `index` as a [`Ray`].
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    #[must_use]
+    pub fn ray(&self, index: usize) -> Ray {
+        let [ox, oy, oz] = &self.origins;
+        let [dx, dy, dz] = &self.directions;
+        Ray {
+            origin: vec3![ox[index], oy[index], oz[index]],
+            direction: vec3![dx[index], dy[index], dz[index]],
+            time: self.times[index],
+        }
+    }
+
+    /// Every ray in the batch in order, as [`Ray`]s.
+    pub fn rays(&self) -> impl Iterator<Item = Ray> + '_ {
+        (0..self.len()).map(|index| self.ray(index))
+    }
+}
@@ -0,0 +1,2227 @@
+//! This module contains [`Scene`], a world with named cameras that can be
+//! loaded from a scene file, and [`SceneSettings`], the global conventions
+//! applied to assets as they are imported, so that models authored with
+//! different units or up axes line up in one scene.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ron::extensions::Extensions;
+use serde::Deserialize;
+
+use crate::{
+    camera::{
+        AntiAliasing, Background, Camera, CameraBuilder, DepthOfField, RayBias, RenderOptions,
+        MAX_OVERSCAN,
+    },
+    disc::Disc,
+    film::Output,
+    ground::{Ground, GroundPattern, GroundPlane, HorizonFade},
+    hit::Hittable,
+    hit_list::HittableList,
+    interval,
+    interval::Interval,
+    material::{
+        Dielectric, DiffuseLight, Glints, Lambertian, LightEffect, Material, Metal, PbrMaterial,
+        SharedMaterial, Sheen, TwoSided,
+    },
+    mesh::{Mesh, MeshData, MeshLoadOptions, UvProjection},
+    noise::Noise,
+    principled::Principled,
+    quad::Quad,
+    ray,
+    ray::Ray,
+    shader::FacingRatio,
+    sphere::Sphere,
+    texture::{
+        CheckerMapping, CheckerTexture, ImageTexture, TextureChannel, TextureFilter, Textured,
+    },
+    thin_film::{FilmBase, ThinFilm},
+    tolerance::Tolerances,
+    torus::Torus,
+    transform::{Keyframe, Transform, Transformed},
+    utils::check_unit,
+    vec3,
+    vec3::Vec3,
+};
+
+/// The most transmissive surfaces a [`Scene::visibility()`] query passes
+/// through before giving up and treating the line as blocked.
+const MAX_OCCLUDERS: usize = 256;
+
+/// The default [`SceneSettings::crease_angle`], smoothing curved surfaces
+/// while keeping the corners of boxes hard.
+pub const DEFAULT_CREASE_ANGLE: f64 = 60.0;
+
+/// Which axis an asset treats as up. Scenes are rendered `+Y` up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum UpAxis {
+    /// `+Y` is up, as in OBJ files exported from most tools and glTF.
+    #[default]
+    Y,
+    /// `+Z` is up, as in Blender, 3ds Max and most CAD packages. Converted to
+    /// `+Y` up by rotating `-90` degrees about `X`, so `-Y` (into the screen in
+    /// a front view) becomes `-Z` and the handedness is kept.
+    Z,
+}
+
+/// Settings applied to geometry at import time. The unit scale and up axis
+/// convert the positions and normals of every imported mesh, whether OBJ,
+/// PLY, STL, glTF or USD, and the cameras of glTF and USD assets. Primitives
+/// written directly in a scene file are already in scene units and aren't
+/// converted.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{scene::{SceneSettings, UpAxis}, vec3, vec3::Vec3};
+///
+/// let settings = SceneSettings::default()
+///     .set_unit_scale(0.01) // centimetres to metres
+///     .set_up_axis(UpAxis::Z);
+///
+/// assert_eq!(settings.point(vec3![100.0, 200.0, 300.0]), vec3![1.0, 3.0, -2.0]);
+/// assert_eq!(settings.normal(vec3![0.0, 0.0, 1.0]), vec3![0.0, 1.0, 0.0]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneSettings {
+    /// Multiplier from the asset's units to scene units.
+    pub unit_scale: f64,
+    /// The up axis the asset was authored with.
+    pub up_axis: UpAxis,
+    /// The seed all procedural randomness in the scene derives from, see
+    /// [`SceneSettings::noise()`].
+    pub seed: u64,
+    /// The numeric tolerances of intersection, given to every object added to
+    /// a [`Scene`] with these settings, see [`Tolerances`].
+    pub tolerances: Tolerances,
+    /// The angle in degrees beyond which faces of an OBJ without normals
+    /// are shaded with a hard edge between them rather than smoothly, see
+    /// [`MeshData::with_smooth_normals()`].
+    pub crease_angle: f64,
+}
+
+impl Default for SceneSettings {
+    fn default() -> Self {
+        Self {
+            unit_scale: 1.0,
+            up_axis: UpAxis::default(),
+            seed: 0,
+            tolerances: Tolerances::default(),
+            crease_angle: DEFAULT_CREASE_ANGLE,
+        }
+    }
+}
+
+impl SceneSettings {
+    /// Set the unit scale.
+    ///
+    /// # Panics
+    ///
+    /// If `unit_scale` isn't positive and finite.
+    #[must_use]
+    pub fn set_unit_scale(mut self, unit_scale: f64) -> Self {
+        assert!(
+            unit_scale > 0.0 && unit_scale.is_finite(),
+            "Unit scale must be positive and finite"
+        );
+        self.unit_scale = unit_scale;
+        self
+    }
+
+    /// Set the up axis.
+    #[must_use]
+    pub fn set_up_axis(mut self, up_axis: UpAxis) -> Self {
+        self.up_axis = up_axis;
+        self
+    }
+
+    /// Set the seed.
+    #[must_use]
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the tolerances.
+    #[must_use]
+    pub fn set_tolerances(mut self, tolerances: Tolerances) -> Self {
+        self.tolerances = tolerances;
+        self
+    }
+
+    /// Set the crease angle in degrees, `0.0` to shade every face flat.
+    ///
+    /// # Panics
+    ///
+    /// If `crease_angle` isn't between `0.0` and `180.0`.
+    #[must_use]
+    pub fn set_crease_angle(mut self, crease_angle: f64) -> Self {
+        assert!(
+            (0.0..=180.0).contains(&crease_angle),
+            "Crease angle must be between 0 and 180 degrees"
+        );
+        self.crease_angle = crease_angle;
+        self
+    }
+
+    /// The [`Noise`] for the procedural system called `stream`, derived from
+    /// the seed so that each system gets independent but reproducible
+    /// randomness.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{scene::SceneSettings, vec3, vec3::Vec3};
+    ///
+    /// let settings = SceneSettings::default().set_seed(1234);
+    /// let p = vec3![0.3, 0.6, 0.9];
+    ///
+    /// assert_eq!(
+    ///     settings.noise("clouds").value(p),
+    ///     SceneSettings::default().set_seed(1234).noise("clouds").value(p)
+    /// );
+    /// assert_ne!(settings.noise("clouds").value(p), settings.noise("rocks").value(p));
+    /// ```
+    #[must_use]
+    pub fn noise(&self, stream: &str) -> Noise {
+        Noise::new(self.seed).stream(stream)
+    }
+
+    /// Rotate a vector from the asset's up axis into the scene's, without
+    /// scaling.
+    fn orient(&self, v: Vec3) -> Vec3 {
+        match self.up_axis {
+            UpAxis::Y => v,
+            UpAxis::Z => vec3![v[0], v[2], -v[1]],
+        }
+    }
+
+    /// Convert an imported position into scene space.
+    #[must_use]
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        self.orient(p) * self.unit_scale
+    }
+
+    /// Convert an imported normal into scene space. The scale is uniform, so
+    /// only the axis change applies.
+    #[must_use]
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.orient(n)
+    }
+}
+
+/// A named camera in a scene file. Anything not given keeps the
+/// [`CameraBuilder`] default.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CameraDescription {
+    image_width: Option<u32>,
+    aspect_ratio: Option<f64>,
+    anti_aliasing: Option<AntiAliasing>,
+    max_depth: Option<u32>,
+    vfov: Option<u16>,
+    look_from: Option<[f64; 3]>,
+    look_at: Option<[f64; 3]>,
+    v_up: Option<[f64; 3]>,
+    defocus_angle: Option<f64>,
+    focus_dist: Option<f64>,
+    depth_of_field: Option<DepthOfField>,
+    background: Option<BackgroundDescription>,
+    dither: Option<bool>,
+    overscan: Option<f64>,
+}
+
+/// A [`Background`] in a scene file. Environment maps and callbacks are only
+/// available from code.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+enum BackgroundDescription {
+    Solid([f64; 3]),
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+}
+
+impl CameraDescription {
+    fn build(&self, ray_bias: RayBias) -> Result<Camera> {
+        let v = |[x, y, z]: [f64; 3]| vec3![x, y, z];
+        let mut builder = CameraBuilder::default().set_ray_bias(ray_bias);
+        if let Some(image_width) = self.image_width {
+            builder = builder.set_image_width(image_width);
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            builder = builder.set_aspect_ratio(aspect_ratio);
+        }
+        if let Some(anti_aliasing) = self.anti_aliasing {
+            builder = builder.set_anti_aliasing(anti_aliasing);
+        }
+        if let Some(max_depth) = self.max_depth {
+            builder = builder.set_max_depth(max_depth);
+        }
+        if let Some(vfov) = self.vfov {
+            builder = builder.set_vfov(vfov);
+        }
+        if let Some(look_from) = self.look_from {
+            builder = builder.set_look_from(v(look_from));
+        }
+        if let Some(look_at) = self.look_at {
+            builder = builder.set_look_at(v(look_at));
+        }
+        if let Some(v_up) = self.v_up {
+            builder = builder.set_v_up(v(v_up));
+        }
+        if let Some(defocus_angle) = self.defocus_angle {
+            builder = builder.set_defocus_angle(defocus_angle);
+        }
+        if let Some(focus_dist) = self.focus_dist {
+            builder = builder.set_focus_dist(focus_dist);
+        }
+        if let Some(depth_of_field) = self.depth_of_field {
+            builder = builder.set_depth_of_field(depth_of_field);
+        }
+        if let Some(dither) = self.dither {
+            builder = builder.set_dither(dither);
+        }
+        if let Some(overscan) = self.overscan {
+            builder = builder.set_overscan(overscan);
+        }
+        match self.background {
+            Some(BackgroundDescription::Solid(colour)) => {
+                builder = builder.set_background(Background::Solid(v(colour)));
+            }
+            Some(BackgroundDescription::Gradient { bottom, top }) => {
+                builder = builder.set_background(Background::Gradient {
+                    bottom: v(bottom),
+                    top: v(top),
+                });
+            }
+            None => {}
+        }
+        builder.build()
+    }
+}
+
+/// A material in a scene file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian {
+        albedo: [f64; 3],
+    },
+    Metal {
+        albedo: [f64; 3],
+        #[serde(default)]
+        fuzz: f64,
+        #[serde(default)]
+        glints: Option<Glints>,
+    },
+    /// A [`Dielectric`], with [faked caustics](Dielectric::with_fake_caustics())
+    /// focused by `fake_caustics` if given.
+    Dielectric {
+        refraction_index: f64,
+        #[serde(default)]
+        tint: Option<[f64; 3]>,
+        #[serde(default)]
+        fake_caustics: Option<f64>,
+    },
+    Sheen {
+        albedo: [f64; 3],
+        tint: [f64; 3],
+        roughness: f64,
+    },
+    /// A [`PbrMaterial`], anisotropic if given a `bitangent_roughness` or a
+    /// `rotation` of the tangent in degrees.
+    Pbr {
+        base_colour: [f64; 3],
+        #[serde(default)]
+        metallic: f64,
+        roughness: f64,
+        #[serde(default)]
+        bitangent_roughness: Option<f64>,
+        #[serde(default)]
+        rotation: f64,
+    },
+    Principled(PrincipledDescription),
+    ThinFilm(ThinFilmDescription),
+    FacingRatio(FacingRatioDescription),
+    /// A [`DiffuseLight`]. Objects with a shadow-only `effect` are kept out
+    /// of the world, see [`Scene::shadow_lights()`].
+    DiffuseLight {
+        emit: [f64; 3],
+        #[serde(default)]
+        albedo: [f64; 3],
+        #[serde(default)]
+        effect: LightEffect,
+    },
+    Ground {
+        colour: [f64; 3],
+        alternate: [f64; 3],
+        tile_size: f64,
+        #[serde(default)]
+        pattern: GroundPattern,
+        #[serde(default)]
+        fade: Option<FadeDescription>,
+    },
+    /// Another material tinted by a [`CheckerTexture`], see [`Textured`].
+    Checker {
+        material: Box<MaterialDescription>,
+        even: [f64; 3],
+        odd: [f64; 3],
+        size: f64,
+        #[serde(default)]
+        mapping: CheckerMapping,
+        #[serde(default)]
+        channel: TextureChannel,
+    },
+    /// Another material tinted by an [`ImageTexture`] loaded from `path`.
+    Image {
+        material: Box<MaterialDescription>,
+        path: PathBuf,
+        #[serde(default)]
+        filter: TextureFilter,
+        #[serde(default)]
+        channel: TextureChannel,
+    },
+    /// A [`TwoSided`] material, `front` on the front faces and `back` on the
+    /// back faces.
+    TwoSided {
+        front: Box<MaterialDescription>,
+        back: Box<MaterialDescription>,
+    },
+}
+
+/// Load the OBJ, PLY or STL file at `path` as a [`Mesh`], deforming into the
+/// one at `end_path` if given, both changed by `options`, with any faces
+/// lacking texture coordinates given some by `uv_projection`.
+fn load_mesh(
+    path: &Path,
+    end_path: Option<&Path>,
+    options: &MeshLoadOptions,
+    uv_projection: Option<UvProjection>,
+    material: &dyn Material,
+    settings: &SceneSettings,
+) -> Result<Mesh> {
+    let mut start = MeshData::load(&path, settings)?;
+    // the end mesh is moved with the start's transform so they stay lined up
+    let transform = options.transform(&start);
+    start = start.transformed(&transform);
+    if let Some(projection) = uv_projection {
+        start = start.with_projected_uvs(projection);
+    }
+    match end_path {
+        Some(end_path) => {
+            let end = MeshData::load(&end_path, settings)?.transformed(&transform);
+            Mesh::deforming(start, end, material)
+        }
+        None => Ok(Mesh::from_data(start, material)),
+    }
+}
+
+/// Build a [`Metal`] material, checking the fuzz, with any glints seeded from
+/// `settings`.
+fn build_metal(
+    albedo: Vec3,
+    fuzz: f64,
+    glints: Option<Glints>,
+    settings: &SceneSettings,
+) -> Result<Metal> {
+    check_unit("Metal fuzz", fuzz)?;
+    let metal = Metal::new(albedo, fuzz);
+    Ok(match glints {
+        Some(glints) => metal.with_glints(Glints {
+            seed: settings.noise("glints").seed(),
+            ..glints
+        }),
+        None => metal,
+    })
+}
+
+/// Build a [`Dielectric`] material, checking the caustic focus.
+fn build_dielectric(
+    refraction_index: f64,
+    tint: Option<Vec3>,
+    fake_caustics: Option<f64>,
+) -> Result<Dielectric> {
+    let mut glass = Dielectric::new(refraction_index);
+    if let Some(tint) = tint {
+        glass = glass.with_tint(tint);
+    }
+    if let Some(focus) = fake_caustics {
+        if !(focus >= 0.0 && focus.is_finite()) {
+            bail!("Caustic focus must be positive and finite, found {focus}");
+        }
+        glass = glass.with_fake_caustics(focus);
+    }
+    Ok(glass)
+}
+
+/// Build a [`PbrMaterial`], checking the metalness and roughnesses, with the
+/// rotation of its anisotropy in degrees.
+fn build_pbr(
+    base_colour: Vec3,
+    metallic: f64,
+    roughness: f64,
+    bitangent_roughness: Option<f64>,
+    rotation: f64,
+) -> Result<PbrMaterial> {
+    check_unit("PBR metallic", metallic)?;
+    check_unit("PBR roughness", roughness)?;
+    let across = bitangent_roughness.unwrap_or(roughness);
+    check_unit("PBR bitangent roughness", across)?;
+    let pbr = PbrMaterial::new(base_colour, metallic, roughness);
+    Ok(pbr.with_anisotropy(roughness, across, rotation.to_radians()))
+}
+
+/// Build a [`CheckerTexture`], checking the size of its squares.
+fn build_checker(
+    even: Vec3,
+    odd: Vec3,
+    size: f64,
+    mapping: CheckerMapping,
+) -> Result<CheckerTexture> {
+    if !(size > 0.0 && size.is_finite()) {
+        bail!("Checker size must be positive and finite, found {size}");
+    }
+    Ok(CheckerTexture::new(even, odd, size, mapping))
+}
+
+/// Load the [`ImageTexture`] at `path`, read with `filter`.
+fn load_image(path: &Path, filter: TextureFilter) -> Result<ImageTexture> {
+    let image = ImageTexture::load(path)
+        .with_context(|| format!("Couldn't load texture {}", path.display()))?;
+    Ok(image.set_filter(filter))
+}
+
+/// Build a [`Ground`] material, checking the tile size and grid lines.
+fn build_ground(
+    pattern: GroundPattern,
+    colour: Vec3,
+    alternate: Vec3,
+    tile_size: f64,
+    fade: Option<FadeDescription>,
+) -> Result<Ground> {
+    if !(tile_size > 0.0 && tile_size.is_finite()) {
+        bail!("Ground tile size must be positive and finite, found {tile_size}");
+    }
+    if let GroundPattern::Grid { line_width } = pattern {
+        if !(0.0..=tile_size).contains(&line_width) {
+            bail!("Ground grid lines must be between zero and a tile wide, found {line_width}");
+        }
+    }
+    let ground = Ground::new(pattern, colour, alternate, tile_size);
+    Ok(match fade {
+        Some(fade) => ground.with_fade(fade.build()?),
+        None => ground,
+    })
+}
+
+/// A [`Principled`] material in a scene file, with Blender's defaults for
+/// anything not given.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PrincipledDescription {
+    base_colour: [f64; 3],
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    sheen: f64,
+    sheen_tint: f64,
+    clearcoat: f64,
+    clearcoat_roughness: f64,
+    transmission: f64,
+    ior: f64,
+}
+
+impl Default for PrincipledDescription {
+    fn default() -> Self {
+        Self {
+            base_colour: [0.8, 0.8, 0.8],
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            transmission: 0.0,
+            ior: 1.45,
+        }
+    }
+}
+
+impl PrincipledDescription {
+    /// Build the material, checking the parameters the builder methods would
+    /// otherwise panic on.
+    fn build(&self) -> Result<Principled> {
+        let [r, g, b] = self.base_colour;
+        check_unit("Principled metallic", self.metallic)?;
+        check_unit("Principled roughness", self.roughness)?;
+        check_unit("Principled specular", self.specular)?;
+        check_unit("Principled sheen", self.sheen)?;
+        check_unit("Principled sheen tint", self.sheen_tint)?;
+        check_unit("Principled clearcoat", self.clearcoat)?;
+        check_unit("Principled clearcoat roughness", self.clearcoat_roughness)?;
+        check_unit("Principled transmission", self.transmission)?;
+        if !(self.ior > 0.0 && self.ior.is_finite()) {
+            bail!(
+                "Principled IOR must be positive and finite, found {}",
+                self.ior
+            );
+        }
+        Ok(Principled::new(vec3![r, g, b])
+            .with_metallic(self.metallic)
+            .with_roughness(self.roughness)
+            .with_specular(self.specular)
+            .with_sheen(self.sheen, self.sheen_tint)
+            .with_clearcoat(self.clearcoat, self.clearcoat_roughness)
+            .with_transmission(self.transmission, self.ior))
+    }
+}
+
+/// What's under a [`ThinFilm`] in a scene file, see [`FilmBase`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+enum FilmBaseDescription {
+    Dielectric(f64),
+    Conductor([f64; 3]),
+}
+
+/// A [`ThinFilm`] material in a scene file, a soap bubble's film unless
+/// given otherwise, with lengths in nanometres and any variation seeded from
+/// the scene's seed.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThinFilmDescription {
+    thickness: f64,
+    film_index: f64,
+    base: FilmBaseDescription,
+    variation: f64,
+    scale: f64,
+}
+
+impl Default for ThinFilmDescription {
+    fn default() -> Self {
+        Self {
+            thickness: 400.0,
+            film_index: 1.33,
+            base: FilmBaseDescription::Dielectric(1.0),
+            variation: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+impl ThinFilmDescription {
+    /// Build the material, checking the parameters the constructors would
+    /// otherwise panic on.
+    fn build(&self, settings: &SceneSettings) -> Result<ThinFilm> {
+        let check = |what: &str, value: f64, min_ok: bool| {
+            if !(min_ok && value.is_finite()) {
+                bail!("Thin film {what} must be positive and finite, found {value}");
+            }
+            Ok(())
+        };
+        check("thickness", self.thickness, self.thickness >= 0.0)?;
+        check("refractive index", self.film_index, self.film_index > 0.0)?;
+        check("variation", self.variation, self.variation >= 0.0)?;
+        check("variation scale", self.scale, self.scale > 0.0)?;
+        let base = match self.base {
+            FilmBaseDescription::Dielectric(index) => {
+                check("base refractive index", index, index > 0.0)?;
+                FilmBase::Dielectric(index)
+            }
+            FilmBaseDescription::Conductor(reflectance) => {
+                for channel in reflectance {
+                    check_unit("Thin film base reflectance", channel)?;
+                }
+                let [r, g, b] = reflectance;
+                FilmBase::Conductor(vec3![r, g, b])
+            }
+        };
+        Ok(
+            ThinFilm::new(self.thickness, self.film_index, base).with_variation(
+                self.variation,
+                self.scale,
+                settings.noise("thin film").seed(),
+            ),
+        )
+    }
+}
+
+/// A [`FacingRatio`] shader in a scene file, ramping linearly unless given a
+/// `power`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FacingRatioDescription {
+    facing: [f64; 3],
+    edge: [f64; 3],
+    #[serde(default = "default_power")]
+    power: f64,
+}
+
+fn default_power() -> f64 {
+    1.0
+}
+
+impl FacingRatioDescription {
+    /// Build the shader, checking the power of its ramp.
+    fn build(&self) -> Result<FacingRatio> {
+        let &Self {
+            facing: [fr, fg, fb],
+            edge: [er, eg, eb],
+            power,
+        } = self;
+        if !(power > 0.0 && power.is_finite()) {
+            bail!("Facing ratio power must be positive and finite, found {power}");
+        }
+        Ok(FacingRatio::new(vec3![fr, fg, fb], vec3![er, eg, eb]).with_power(power))
+    }
+}
+
+/// A [`HorizonFade`] in a scene file, fading to the default sky if no colour
+/// is given.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FadeDescription {
+    start: f64,
+    end: f64,
+    #[serde(default)]
+    colour: Option<[f64; 3]>,
+}
+
+impl FadeDescription {
+    /// Build the fade, checking its distances are in order.
+    fn build(&self) -> Result<HorizonFade> {
+        let &Self { start, end, colour } = self;
+        if !(0.0 <= start && start <= end) {
+            bail!("Ground fade must start at a positive distance and end after it starts");
+        }
+        let fade = HorizonFade::new(start, end);
+        Ok(colour.map_or(fade, |[r, g, b]| fade.with_colour(vec3![r, g, b])))
+    }
+}
+
+impl MaterialDescription {
+    /// Build the material, checking the parameters the constructors would
+    /// otherwise panic on. Procedural parts are seeded from `settings`, and
+    /// image paths are relative to `base_dir`.
+    fn build(&self, settings: &SceneSettings, base_dir: &Path) -> Result<Box<dyn Material>> {
+        let v = |[x, y, z]: [f64; 3]| vec3![x, y, z];
+        Ok(match *self {
+            MaterialDescription::Lambertian { albedo } => Box::new(Lambertian::new(v(albedo))),
+            MaterialDescription::Metal {
+                albedo,
+                fuzz,
+                glints,
+            } => Box::new(build_metal(v(albedo), fuzz, glints, settings)?),
+            MaterialDescription::Dielectric {
+                refraction_index,
+                tint,
+                fake_caustics,
+            } => Box::new(build_dielectric(
+                refraction_index,
+                tint.map(v),
+                fake_caustics,
+            )?),
+            MaterialDescription::Sheen {
+                albedo,
+                tint,
+                roughness,
+            } => {
+                check_unit("Sheen roughness", roughness)?;
+                Box::new(Sheen::new(v(albedo), v(tint), roughness))
+            }
+            MaterialDescription::Pbr {
+                base_colour,
+                metallic,
+                roughness,
+                bitangent_roughness,
+                rotation,
+            } => Box::new(build_pbr(
+                v(base_colour),
+                metallic,
+                roughness,
+                bitangent_roughness,
+                rotation,
+            )?),
+            MaterialDescription::Principled(principled) => Box::new(principled.build()?),
+            MaterialDescription::ThinFilm(film) => Box::new(film.build(settings)?),
+            MaterialDescription::FacingRatio(ratio) => Box::new(ratio.build()?),
+            MaterialDescription::DiffuseLight {
+                emit,
+                albedo,
+                effect,
+            } => Box::new(
+                DiffuseLight::new(v(emit))
+                    .with_albedo(v(albedo))
+                    .with_effect(effect),
+            ),
+            MaterialDescription::Ground {
+                colour,
+                alternate,
+                tile_size,
+                pattern,
+                fade,
+            } => Box::new(build_ground(
+                pattern,
+                v(colour),
+                v(alternate),
+                tile_size,
+                fade,
+            )?),
+            MaterialDescription::Checker {
+                ref material,
+                even,
+                odd,
+                size,
+                mapping,
+                channel,
+            } => Box::new(
+                Textured::new(
+                    Box::new(build_checker(v(even), v(odd), size, mapping)?),
+                    material.build(settings, base_dir)?,
+                )
+                .with_channel(channel),
+            ),
+            MaterialDescription::Image {
+                ref material,
+                ref path,
+                filter,
+                channel,
+            } => Box::new(
+                Textured::new(
+                    Box::new(load_image(&base_dir.join(path), filter)?),
+                    material.build(settings, base_dir)?,
+                )
+                .with_channel(channel),
+            ),
+            MaterialDescription::TwoSided {
+                ref front,
+                ref back,
+            } => Box::new(TwoSided::new(
+                front.build(settings, base_dir)?,
+                back.build(settings, base_dir)?,
+            )),
+        })
+    }
+
+    /// The image files the material loads, relative to the scene file.
+    fn image_paths(&self) -> Vec<&Path> {
+        match self {
+            MaterialDescription::Image { material, path, .. } => {
+                let mut paths = material.image_paths();
+                paths.push(path);
+                paths
+            }
+            MaterialDescription::Checker { material, .. } => material.image_paths(),
+            MaterialDescription::TwoSided { front, back } => {
+                let mut paths = front.image_paths();
+                paths.extend(back.image_paths());
+                paths
+            }
+            MaterialDescription::Lambertian { .. }
+            | MaterialDescription::Metal { .. }
+            | MaterialDescription::Dielectric { .. }
+            | MaterialDescription::Sheen { .. }
+            | MaterialDescription::Pbr { .. }
+            | MaterialDescription::Principled(_)
+            | MaterialDescription::ThinFilm(_)
+            | MaterialDescription::FacingRatio(_)
+            | MaterialDescription::DiffuseLight { .. }
+            | MaterialDescription::Ground { .. } => vec![],
+        }
+    }
+}
+
+/// Where to put an object in a scene file: a scale, then a rotation (Euler
+/// angles in degrees), then a translation, like a [`Keyframe`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PlacementDescription {
+    translate: [f64; 3],
+    rotate: [f64; 3],
+    scale: [f64; 3],
+}
+
+impl Default for PlacementDescription {
+    fn default() -> Self {
+        Self {
+            translate: [0.0; 3],
+            rotate: [0.0; 3],
+            scale: [1.0; 3],
+        }
+    }
+}
+
+impl PlacementDescription {
+    /// The transform of the placement, checking it can be inverted.
+    fn transform(&self) -> Result<Transform> {
+        let parts = [self.translate, self.rotate, self.scale];
+        if !parts.iter().flatten().all(|x| x.is_finite()) {
+            bail!("Transforms must be finite");
+        }
+        if self.scale.contains(&0.0) {
+            bail!("Transform scales can't be zero, found {:?}", self.scale);
+        }
+        let v3 = |[x, y, z]: [f64; 3]| vec3![x, y, z];
+        Ok(Keyframe::at(0.0, v3(self.translate))
+            .with_rotation(v3(self.rotate))
+            .with_scale(v3(self.scale))
+            .transform())
+    }
+}
+
+/// An object in a scene file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+enum ObjectDescription {
+    Sphere {
+        centre: [f64; 3],
+        radius: f64,
+        material: MaterialDescription,
+    },
+    /// An OBJ, PLY or STL file, relative to the scene file, optionally placed
+    /// by a [`Transformed`]. With an `end_path`, the mesh deforms into that
+    /// one over the shutter, see [`Mesh::deforming()`]. Faces without texture
+    /// coordinates are given some by a `uv_projection` if there is one, see
+    /// [`MeshData::with_projected_uvs()`]. Models in other units or about
+    /// other origins can be `recentre`d on the origin and `normalise`d to a
+    /// unit size as they're loaded, before any `transform`, see
+    /// [`MeshLoadOptions`].
+    Mesh {
+        path: PathBuf,
+        material: MaterialDescription,
+        #[serde(default)]
+        transform: Option<PlacementDescription>,
+        #[serde(default)]
+        end_path: Option<PathBuf>,
+        #[serde(default)]
+        uv_projection: Option<UvProjection>,
+        #[serde(default)]
+        recentre: bool,
+        #[serde(default)]
+        normalise: bool,
+    },
+    /// An infinite [`GroundPlane`].
+    Plane {
+        height: f64,
+        material: MaterialDescription,
+    },
+    /// A [`Quad`] with a corner at `origin` and edges `u` and `v`.
+    Quad {
+        origin: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: MaterialDescription,
+    },
+    /// A [`Disc`] of `radius` about `centre` facing along `normal`.
+    Disc {
+        centre: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        material: MaterialDescription,
+    },
+    /// A [`Torus`] about `centre` with its hole along `axis`.
+    Torus {
+        centre: [f64; 3],
+        axis: [f64; 3],
+        major: f64,
+        minor: f64,
+        material: MaterialDescription,
+    },
+}
+
+impl ObjectDescription {
+    fn material(&self) -> &MaterialDescription {
+        match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::Mesh { material, .. }
+            | ObjectDescription::Plane { material, .. }
+            | ObjectDescription::Quad { material, .. }
+            | ObjectDescription::Disc { material, .. }
+            | ObjectDescription::Torus { material, .. } => material,
+        }
+    }
+
+    /// Build the object with `material`, loading any mesh relative to
+    /// `base_dir` with `settings`.
+    fn build(
+        &self,
+        material: Box<dyn Material>,
+        base_dir: &Path,
+        settings: &SceneSettings,
+    ) -> Result<Box<dyn Hittable>> {
+        Ok(match self {
+            &ObjectDescription::Sphere {
+                centre: [x, y, z],
+                radius,
+                ..
+            } => Box::new(Sphere {
+                centre: vec3![x, y, z],
+                radius,
+                mat: material,
+            }),
+            ObjectDescription::Mesh {
+                path,
+                transform,
+                end_path,
+                uv_projection,
+                recentre,
+                normalise,
+                ..
+            } => {
+                let path = base_dir.join(path);
+                let end_path = end_path.as_ref().map(|end_path| base_dir.join(end_path));
+                let options = MeshLoadOptions {
+                    recentre: *recentre,
+                    normalise: *normalise,
+                    ..MeshLoadOptions::default()
+                };
+                let mesh = load_mesh(
+                    &path,
+                    end_path.as_deref(),
+                    &options,
+                    *uv_projection,
+                    material.as_ref(),
+                    settings,
+                )
+                .with_context(|| format!("Couldn't load mesh {}", path.display()))?;
+                match transform {
+                    Some(placement) => Box::new(Transformed::new(mesh, placement.transform()?)),
+                    None => Box::new(mesh),
+                }
+            }
+            &ObjectDescription::Plane { height, .. } => Box::new(GroundPlane {
+                height,
+                mat: material,
+            }),
+            &ObjectDescription::Quad { origin, u, v, .. } => {
+                let v3 = |[x, y, z]: [f64; 3]| vec3![x, y, z];
+                Box::new(Quad::new(v3(origin), v3(u), v3(v), material))
+            }
+            &ObjectDescription::Disc {
+                centre,
+                normal,
+                radius,
+                ..
+            } => {
+                let v3 = |[x, y, z]: [f64; 3]| vec3![x, y, z];
+                if v3(normal).near_zero() {
+                    bail!("Disc normal can't be zero");
+                }
+                Box::new(Disc::new(v3(centre), v3(normal), radius, material))
+            }
+            &ObjectDescription::Torus {
+                centre,
+                axis,
+                major,
+                minor,
+                ..
+            } => {
+                let v3 = |[x, y, z]: [f64; 3]| vec3![x, y, z];
+                if v3(axis).near_zero() {
+                    bail!("Torus axis can't be zero");
+                }
+                Box::new(Torus::new(v3(centre), v3(axis), major, minor, material))
+            }
+        })
+    }
+
+    /// Whether `other` is the same shape in the same place, whatever its
+    /// material. Values are compared exactly, as they're what was written in
+    /// the file.
+    #[expect(clippy::float_cmp)]
+    fn same_geometry(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                ObjectDescription::Sphere {
+                    centre: a_centre,
+                    radius: a_radius,
+                    ..
+                },
+                ObjectDescription::Sphere {
+                    centre: b_centre,
+                    radius: b_radius,
+                    ..
+                },
+            ) => a_centre == b_centre && a_radius == b_radius,
+            (
+                ObjectDescription::Mesh {
+                    path: a_path,
+                    transform: a_transform,
+                    end_path: a_end_path,
+                    recentre: a_recentre,
+                    normalise: a_normalise,
+                    ..
+                },
+                ObjectDescription::Mesh {
+                    path: b_path,
+                    transform: b_transform,
+                    end_path: b_end_path,
+                    recentre: b_recentre,
+                    normalise: b_normalise,
+                    ..
+                },
+            ) => {
+                a_path == b_path
+                    && a_transform == b_transform
+                    && a_end_path == b_end_path
+                    && a_recentre == b_recentre
+                    && a_normalise == b_normalise
+            }
+            (
+                ObjectDescription::Plane { height: a, .. },
+                ObjectDescription::Plane { height: b, .. },
+            ) => a == b,
+            (
+                ObjectDescription::Quad {
+                    origin: a_origin,
+                    u: a_u,
+                    v: a_v,
+                    ..
+                },
+                ObjectDescription::Quad {
+                    origin: b_origin,
+                    u: b_u,
+                    v: b_v,
+                    ..
+                },
+            ) => a_origin == b_origin && a_u == b_u && a_v == b_v,
+            (
+                ObjectDescription::Disc {
+                    centre: a_centre,
+                    normal: a_normal,
+                    radius: a_radius,
+                    ..
+                },
+                ObjectDescription::Disc {
+                    centre: b_centre,
+                    normal: b_normal,
+                    radius: b_radius,
+                    ..
+                },
+            ) => a_centre == b_centre && a_normal == b_normal && a_radius == b_radius,
+            (
+                ObjectDescription::Torus {
+                    centre: a_centre,
+                    axis: a_axis,
+                    major: a_major,
+                    minor: a_minor,
+                    ..
+                },
+                ObjectDescription::Torus {
+                    centre: b_centre,
+                    axis: b_axis,
+                    major: b_major,
+                    minor: b_minor,
+                    ..
+                },
+            ) => {
+                a_centre == b_centre && a_axis == b_axis && a_major == b_major && a_minor == b_minor
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The top level of a scene file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    #[serde(default)]
+    settings: SceneSettings,
+    #[serde(default)]
+    cameras: BTreeMap<String, CameraDescription>,
+    #[serde(default)]
+    objects: Vec<ObjectDescription>,
+}
+
+impl SceneDescription {
+    fn parse(text: &str) -> Result<Self> {
+        let description: Self = ron::Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str(text)?;
+        let unit_scale = description.settings.unit_scale;
+        if !(unit_scale > 0.0 && unit_scale.is_finite()) {
+            bail!("Unit scale must be positive and finite");
+        }
+        let crease_angle = description.settings.crease_angle;
+        if !(0.0..=180.0).contains(&crease_angle) {
+            bail!("Crease angle must be between 0 and 180 degrees, found {crease_angle}");
+        }
+        for (name, camera) in &description.cameras {
+            if camera.image_width == Some(0) {
+                bail!("The image width of camera {name:?} must be at least one pixel");
+            }
+            if camera
+                .aspect_ratio
+                .is_some_and(|aspect_ratio| !(aspect_ratio > 0.0 && aspect_ratio.is_finite()))
+            {
+                bail!("The aspect ratio of camera {name:?} must be positive and finite");
+            }
+            if camera
+                .overscan
+                .is_some_and(|overscan| !(0.0..=MAX_OVERSCAN).contains(&overscan))
+            {
+                bail!(
+                    "The overscan of camera {name:?} must be a percentage between 0 and \
+                     {MAX_OVERSCAN}"
+                );
+            }
+        }
+        Ok(description)
+    }
+
+    /// The paths of the meshes and images the scene loads, relative to the
+    /// scene file.
+    fn asset_paths(&self) -> Vec<&Path> {
+        self.objects
+            .iter()
+            .flat_map(|object| {
+                let mut paths = match object {
+                    ObjectDescription::Mesh { path, end_path, .. } => std::iter::once(path)
+                        .chain(end_path)
+                        .map(PathBuf::as_path)
+                        .collect(),
+                    ObjectDescription::Sphere { .. }
+                    | ObjectDescription::Plane { .. }
+                    | ObjectDescription::Quad { .. }
+                    | ObjectDescription::Disc { .. }
+                    | ObjectDescription::Torus { .. } => vec![],
+                };
+                paths.extend(object.material().image_paths());
+                paths
+            })
+            .collect()
+    }
+
+    /// Whether `other` only differs in its cameras and materials.
+    fn same_geometry(&self, other: &Self) -> bool {
+        self.settings == other.settings
+            && self.objects.len() == other.objects.len()
+            && self
+                .objects
+                .iter()
+                .zip(&other.objects)
+                .all(|(a, b)| a.same_geometry(b))
+    }
+}
+
+/// A RON scene file that has been parsed and checked, but with none of its
+/// meshes or images loaded yet, so it can be vetted cheaply and then
+/// [loaded](SceneFile::load()) elsewhere, such as on a render server's worker
+/// thread. See [`Scene::parse_ron()`] for the format.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::scene::SceneFile;
+///
+/// let teapot = |path: &str| {
+///     format!(
+///         r#"(
+///             cameras: {{ "main": () }},
+///             objects: [Mesh(path: {path:?}, material: Lambertian(albedo: (0.5, 0.5, 0.5)))],
+///         )"#
+///     )
+/// };
+///
+/// let file = SceneFile::parse_ron(&teapot("objs/teapot.obj"), ".").unwrap();
+/// assert_eq!(file.only_camera().unwrap(), "main");
+/// assert!(file.confined().is_ok());
+///
+/// // paths that leave the base directory are caught before anything is read
+/// for outside in ["../teapot.obj", "objs/../../teapot.obj", "/etc/teapot.obj"] {
+///     let file = SceneFile::parse_ron(&teapot(outside), ".").unwrap();
+///     assert!(file.confined().is_err());
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneFile {
+    description: SceneDescription,
+    base_dir: PathBuf,
+}
+
+impl SceneFile {
+    /// Parse the text of a RON scene file, with paths relative to
+    /// `base_dir`, without loading anything.
+    ///
+    /// # Errors
+    ///
+    /// If the text isn't a valid scene.
+    pub fn parse_ron<P: Into<PathBuf>>(text: &str, base_dir: P) -> Result<Self> {
+        Ok(Self {
+            description: SceneDescription::parse(text)?,
+            base_dir: base_dir.into(),
+        })
+    }
+
+    /// Check that every mesh and image path stays inside the base directory,
+    /// being relative and never going up a directory, for scenes that come
+    /// from untrusted clients. Symbolic links inside the base directory are
+    /// still followed.
+    ///
+    /// # Errors
+    ///
+    /// If a path is absolute or has a `..` component.
+    pub fn confined(self) -> Result<Self> {
+        for path in self.description.asset_paths() {
+            if !path
+                .components()
+                .all(|part| matches!(part, Component::Normal(_) | Component::CurDir))
+            {
+                bail!("{} isn't inside the scene's directory", path.display());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Whether the scene has a camera called `name`.
+    #[must_use]
+    pub fn has_camera(&self, name: &str) -> bool {
+        self.description.cameras.contains_key(name)
+    }
+
+    /// The name of the scene's only camera.
+    ///
+    /// # Errors
+    ///
+    /// If the scene has no cameras or several.
+    pub fn only_camera(&self) -> Result<&str> {
+        only_camera(self.description.cameras.keys())
+    }
+
+    /// Build the camera called `name` without loading anything, e.g. to check
+    /// the size of its image before rendering it.
+    ///
+    /// # Errors
+    ///
+    /// If there's no camera called `name`, or it can't be built, see
+    /// [`CameraBuilder::build()`].
+    pub fn camera(&self, name: &str) -> Result<Camera> {
+        self.description
+            .cameras
+            .get(name)
+            .with_context(|| format!("No camera named {name:?}"))?
+            .build(self.description.settings.tolerances.ray_bias)
+    }
+
+    /// Load the scene's meshes and images and build it.
+    ///
+    /// # Errors
+    ///
+    /// If a mesh or image fails to load, or a material's parameters are out
+    /// of range.
+    pub fn load(self) -> Result<Scene> {
+        Scene::from_description(self.description, &self.base_dir, false)
+    }
+}
+
+/// The name of the only camera in `names`.
+fn only_camera<'a>(mut names: impl Iterator<Item = &'a String>) -> Result<&'a str> {
+    match (names.next(), names.next()) {
+        (Some(name), None) => Ok(name),
+        (None, _) => bail!("The scene has no cameras"),
+        (Some(_), Some(_)) => bail!("The scene has several cameras, pick one by name"),
+    }
+}
+
+/// Where a [`Scene`] was loaded from, kept to [reload](Scene::reload()) it.
+#[derive(Debug)]
+struct SceneSource {
+    description: SceneDescription,
+    base_dir: PathBuf,
+    /// A slot for each object's material, if they can be hot reloaded.
+    materials: Option<Vec<SharedMaterial>>,
+}
+
+/// What changed when a [`Scene`] was [reloaded](Scene::reload()).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reload {
+    /// Whether the settings or objects changed, so the whole scene was rebuilt.
+    pub geometry: bool,
+    /// How many objects had their material rebuilt in place.
+    pub materials: usize,
+    /// Whether any of the cameras changed.
+    pub cameras: bool,
+}
+
+impl Reload {
+    /// Whether anything changed, meaning renders of the scene are out of date.
+    #[must_use]
+    pub fn changed(&self) -> bool {
+        self.geometry || self.materials > 0 || self.cameras
+    }
+}
+
+/// A world to render along with any number of named cameras looking at it,
+/// built in code or loaded from a [RON](https://github.com/ron-rs/ron) scene
+/// file with [`Scene::load()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::scene::Scene;
+///
+/// let scene = Scene::parse_ron(
+///     r#"(
+///         settings: (unit_scale: 1.0, up_axis: Y, seed: 7),
+///         cameras: {
+///             "wide": (image_width: 400, vfov: 60, look_from: (0.0, 2.0, 10.0)),
+///             "hero_closeup": (
+///                 image_width: 800,
+///                 aspect_ratio: 1.5,
+///                 anti_aliasing: Random(64),
+///                 look_from: (1.0, 1.0, 3.0),
+///                 look_at: (0.0, 0.5, 0.0),
+///                 background: Gradient(bottom: (0.9, 0.6, 0.4), top: (0.2, 0.3, 0.6)),
+///             ),
+///         },
+///         objects: [
+///             Sphere(
+///                 centre: (0.0, -1000.0, 0.0),
+///                 radius: 1000.0,
+///                 material: Checker(
+///                     material: Lambertian(albedo: (1.0, 1.0, 1.0)),
+///                     even: (0.8, 0.8, 0.8),
+///                     odd: (0.2, 0.2, 0.2),
+///                     size: 0.5,
+///                 ),
+///             ),
+///             Sphere(
+///                 centre: (0.0, 1.0, 0.0),
+///                 radius: 1.0,
+///                 material: Metal(albedo: (0.9, 0.9, 0.9), fuzz: 0.1, glints: (density: 0.5)),
+///             ),
+///         ],
+///     )"#,
+///     ".",
+/// )
+/// .unwrap();
+///
+/// let names: Vec<_> = scene.cameras().map(|(name, _)| name).collect();
+/// assert_eq!(names, ["hero_closeup", "wide"]);
+/// assert_eq!(scene.camera("hero_closeup").unwrap().image_width, 800);
+/// assert_eq!(scene.world().objects.len(), 2);
+///
+/// // a camera without any pixels is an error rather than an empty render
+/// let empty = r#"(cameras: { "main": (image_width: 0) })"#;
+/// assert!(Scene::parse_ron(empty, ".").is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct Scene {
+    /// The settings assets are imported with.
+    pub settings: SceneSettings,
+    world: HittableList,
+    shadow_lights: HittableList,
+    cameras: BTreeMap<String, Camera>,
+    source: Option<SceneSource>,
+}
+
+impl Scene {
+    /// Create an empty [`Scene`] that imports assets with `settings`, and
+    /// gives its tolerances to the objects added.
+    #[must_use]
+    pub fn new(settings: SceneSettings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    /// Load a [`Scene`] from a RON file, see [`Scene::parse_ron()`]. Mesh and
+    /// texture paths are relative to the file.
+    ///
+    /// # Errors
+    ///
+    /// If reading or parsing the file fails, or loading anything in it does.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_from(path.as_ref(), false)
+    }
+
+    /// Load a [`Scene`] like [`Scene::load()`], but with every material in a
+    /// [`SharedMaterial`] so that [`Scene::reload()`] can swap in edited
+    /// materials without rebuilding any geometry. Meant for interactive
+    /// previews, as the shared materials are a little slower to render.
+    ///
+    /// # Errors
+    ///
+    /// See [`Scene::load()`].
+    pub fn load_watched<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_from(path.as_ref(), true)
+    }
+
+    fn load_from(path: &Path, hot_reload: bool) -> Result<Self> {
+        let (description, base_dir) = Self::read_description(path)?;
+        Self::from_description(description, base_dir, hot_reload)
+            .with_context(|| format!("Invalid scene {}", path.display()))
+    }
+
+    /// Build the material of every object in the RON scene file at `path`,
+    /// without loading any geometry, for testing the materials on their own
+    /// such as in a [white furnace](crate::furnace). Each is named by its
+    /// place in the file and its kind, as in `objects[2] Metal`.
+    ///
+    /// # Errors
+    ///
+    /// If reading or parsing the file fails, or building a material does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::scene::Scene;
+    ///
+    /// let path = std::env::temp_dir().join("rustrace_materials.ron");
+    /// std::fs::write(
+    ///     &path,
+    ///     r#"(
+    ///         objects: [
+    ///             Sphere(centre: (0, 0, 0), radius: 1, material: Lambertian(albedo: (1, 1, 1))),
+    ///             Mesh(path: "missing.obj", material: Metal(albedo: (1, 1, 1), fuzz: 0.2)),
+    ///         ],
+    ///     )"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let names: Vec<_> = Scene::load_materials(&path)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|(name, _)| name)
+    ///     .collect();
+    /// assert_eq!(names, ["objects[0] Lambertian", "objects[1] Metal"]);
+    /// ```
+    pub fn load_materials<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Box<dyn Material>)>> {
+        let path = path.as_ref();
+        let (description, base_dir) = Self::read_description(path)?;
+        description
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let material = object.material();
+                // the derived Debug starts with the variant's name
+                let kind: String = format!("{material:?}")
+                    .chars()
+                    .take_while(char::is_ascii_alphanumeric)
+                    .collect();
+                let built = material
+                    .build(&description.settings, base_dir)
+                    .with_context(|| format!("Invalid scene {}", path.display()))?;
+                Ok((format!("objects[{index}] {kind}"), built))
+            })
+            .collect()
+    }
+
+    fn read_description(path: &Path) -> Result<(SceneDescription, &Path)> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read scene {}", path.display()))?;
+        let description = SceneDescription::parse(&text)
+            .with_context(|| format!("Invalid scene {}", path.display()))?;
+        Ok((description, path.parent().unwrap_or_else(|| Path::new("."))))
+    }
+
+    /// Parse a [`Scene`] from the text of a RON scene file with optional
+    /// `settings` (see [`SceneSettings`]), a map of named `cameras`, and a list
+    /// of `objects`. Mesh paths are relative to `base_dir`, and are imported
+    /// with the settings; everything else is in scene units. Meshes can be
+    /// placed with an optional `transform` of a `scale`, then a `rotate` (Euler
+    /// angles in degrees), then a `translate`, and deform over the shutter
+    /// into an optional `end_path` OBJ of the same topology. `Image`
+    /// materials tint another material by a colour map, whose `path` is also
+    /// relative to `base_dir`.
+    ///
+    /// # Errors
+    ///
+    /// If the text isn't a valid scene, a mesh fails to load or is placed by
+    /// a transform with a zero scale, or a material's parameters are out of
+    /// range.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{hit::Hittable, scene::Scene};
+    ///
+    /// let teapot = |transform: &str| {
+    ///     let text = format!(
+    ///         r#"(
+    ///             cameras: {{}},
+    ///             objects: [
+    ///                 Mesh(
+    ///                     path: "objs/teapot.obj",
+    ///                     material: Lambertian(albedo: (0.5, 0.5, 0.5)),
+    ///                     {transform}
+    ///                 ),
+    ///             ],
+    ///         )"#
+    ///     );
+    ///     Scene::parse_ron(&text, ".").unwrap().world().bound()
+    /// };
+    ///
+    /// let original = teapot("");
+    /// let placed = teapot("transform: (scale: (2.0, 2.0, 2.0), translate: (10.0, 0.0, 0.0)),");
+    ///
+    /// assert!((placed.x.size() - 2.0 * original.x.size()).abs() < 1e-9);
+    /// assert!((placed.x.min - (2.0 * original.x.min + 10.0)).abs() < 1e-9);
+    ///
+    /// // a scale flattening the mesh can't be undone, so is an error
+    /// let flat = r#"(objects: [Mesh(
+    ///     path: "objs/teapot.obj",
+    ///     material: Lambertian(albedo: (0.5, 0.5, 0.5)),
+    ///     transform: (scale: (1.0, 0.0, 1.0)),
+    /// )])"#;
+    /// assert!(Scene::parse_ron(flat, ".").is_err());
+    /// ```
+    pub fn parse_ron<P: AsRef<Path>>(text: &str, base_dir: P) -> Result<Self> {
+        SceneFile::parse_ron(text, base_dir.as_ref())?.load()
+    }
+
+    fn from_description(
+        description: SceneDescription,
+        base_dir: &Path,
+        hot_reload: bool,
+    ) -> Result<Self> {
+        let settings = description.settings;
+        let mut scene = Self::new(settings);
+        scene.cameras = scene.build_cameras(&description.cameras)?;
+
+        let mut slots = vec![];
+        for object in &description.objects {
+            let mut material = object.material().build(&settings, base_dir)?;
+            let shadow_only = material.light_effect() == LightEffect::ShadowOnly;
+            if hot_reload {
+                let slot = SharedMaterial::new(material);
+                material = Box::new(slot.clone());
+                slots.push(slot);
+            }
+
+            let object = object.build(material, base_dir, &settings)?;
+            if shadow_only {
+                scene.add_shadow_light(object);
+            } else {
+                scene.add(object);
+            }
+        }
+
+        scene.source = Some(SceneSource {
+            description,
+            base_dir: base_dir.to_path_buf(),
+            materials: hot_reload.then_some(slots),
+        });
+        Ok(scene)
+    }
+
+    fn build_cameras(
+        &self,
+        cameras: &BTreeMap<String, CameraDescription>,
+    ) -> Result<BTreeMap<String, Camera>> {
+        cameras
+            .iter()
+            .map(|(name, camera)| {
+                let camera = camera
+                    .build(self.settings.tolerances.ray_bias)
+                    .with_context(|| format!("Invalid camera {name:?}"))?;
+                Ok((name.clone(), camera))
+            })
+            .collect()
+    }
+
+    /// Re-read the scene file at `path` that the [`Scene`] was loaded from,
+    /// and update it to match. If only cameras or materials changed and the
+    /// scene was [loaded for watching](Scene::load_watched()), just the
+    /// changed materials are rebuilt and swapped in, keeping the geometry and
+    /// its BVHs. Otherwise the whole scene is rebuilt. If anything fails the
+    /// scene is left as it was.
+    ///
+    /// Mesh files aren't checked for changes, see [`Scene::asset_paths()`].
+    ///
+    /// # Errors
+    ///
+    /// If reading or parsing the file fails, or loading anything in it does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{hit::Hittable, scene::Scene};
+    ///
+    /// let path = std::env::temp_dir().join("reload_doc.ron");
+    /// let write = |fuzz: f64| {
+    ///     let text = format!(
+    ///         "(
+    ///             cameras: {{ \"main\": (image_width: 16) }},
+    ///             objects: [
+    ///                 Sphere(
+    ///                     centre: (0.0, 0.0, -1.0),
+    ///                     radius: 0.5,
+    ///                     material: Metal(albedo: (0.9, 0.9, 0.9), fuzz: {fuzz:?}),
+    ///                 ),
+    ///             ],
+    ///         )"
+    ///     );
+    ///     std::fs::write(&path, text).unwrap();
+    /// };
+    ///
+    /// write(0.0);
+    /// let mut scene = Scene::load_watched(&path).unwrap();
+    /// assert!(!scene.reload(&path).unwrap().changed());
+    ///
+    /// // tweaking the material swaps it in place
+    /// write(0.5);
+    /// let reload = scene.reload(&path).unwrap();
+    /// assert!(!reload.geometry);
+    /// assert_eq!(reload.materials, 1);
+    /// ```
+    pub fn reload<P: AsRef<Path>>(&mut self, path: P) -> Result<Reload> {
+        let path = path.as_ref();
+        let (description, base_dir) = Self::read_description(path)?;
+
+        let in_place = self.source.as_ref().and_then(|source| {
+            let slots = source.materials.as_ref()?;
+            (source.base_dir == base_dir && source.description.same_geometry(&description))
+                .then_some((source, slots))
+        });
+        let Some((source, slots)) = in_place else {
+            let hot_reload = self.source.as_ref().is_some_and(|s| s.materials.is_some());
+            *self = Self::from_description(description, base_dir, hot_reload)
+                .with_context(|| format!("Invalid scene {}", path.display()))?;
+            return Ok(Reload {
+                geometry: true,
+                materials: 0,
+                cameras: true,
+            });
+        };
+
+        // build everything first so a bad material leaves the scene untouched
+        let mut changed = vec![];
+        for ((old, new), slot) in source
+            .description
+            .objects
+            .iter()
+            .zip(&description.objects)
+            .zip(slots)
+        {
+            if old.material() != new.material() {
+                changed.push((
+                    slot.clone(),
+                    new.material().build(&description.settings, base_dir)?,
+                ));
+            }
+        }
+
+        let reload = Reload {
+            geometry: false,
+            materials: changed.len(),
+            cameras: source.description.cameras != description.cameras,
+        };
+        let cameras = if reload.cameras {
+            Some(self.build_cameras(&description.cameras)?)
+        } else {
+            None
+        };
+        for (slot, material) in changed {
+            slot.replace(material);
+        }
+        if let Some(cameras) = cameras {
+            self.cameras = cameras;
+        }
+        if let Some(source) = &mut self.source {
+            source.description = description;
+        }
+
+        Ok(reload)
+    }
+
+    /// The files other than the scene file itself that the [`Scene`] was
+    /// loaded from, such as meshes and textures. Changes to them need a full
+    /// [`Scene::load()`].
+    #[must_use]
+    pub fn asset_paths(&self) -> Vec<PathBuf> {
+        let Some(source) = &self.source else {
+            return vec![];
+        };
+        source
+            .description
+            .asset_paths()
+            .into_iter()
+            .map(|path| source.base_dir.join(path))
+            .collect()
+    }
+
+    /// Add an object to the world, giving it the
+    /// [tolerances](SceneSettings::tolerances) of the scene's settings.
+    pub fn add(&mut self, mut object: Box<dyn Hittable>) {
+        object.set_tolerances(&self.settings.tolerances);
+        self.world.add(object);
+    }
+
+    /// The objects in the [`Scene`].
+    #[must_use]
+    pub fn world(&self) -> &HittableList {
+        &self.world
+    }
+
+    /// Add a light that only casts shadows, kept out of the world, see
+    /// [`LightEffect::ShadowOnly`].
+    pub fn add_shadow_light(&mut self, mut light: Box<dyn Hittable>) {
+        light.set_tolerances(&self.settings.tolerances);
+        self.shadow_lights.add(light);
+    }
+
+    /// The lights that only cast shadows, which scene files put here rather
+    /// than in the world.
+    #[must_use]
+    pub fn shadow_lights(&self) -> &HittableList {
+        &self.shadow_lights
+    }
+
+    /// The [`RenderOptions`] the [`Scene`] needs rendering with, casting the
+    /// shadows of its [shadow-only lights](Scene::shadow_lights()), to build
+    /// on for progressive renders.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::scene::Scene;
+    ///
+    /// let scene = Scene::parse_ron(
+    ///     r#"(
+    ///         objects: [
+    ///             Sphere(centre: (0.0, 0.0, -1.0), radius: 0.5, material: Lambertian(albedo: (0.5, 0.5, 0.5))),
+    ///             Sphere(
+    ///                 centre: (0.0, 5.0, -1.0),
+    ///                 radius: 1.0,
+    ///                 material: DiffuseLight(emit: (4.0, 4.0, 4.0), effect: ShadowOnly),
+    ///             ),
+    ///         ],
+    ///     )"#,
+    ///     ".",
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(scene.world().objects.len(), 1);
+    /// assert_eq!(scene.shadow_lights().objects.len(), 1);
+    /// let options = scene.render_options().set_passes(4);
+    /// ```
+    #[must_use]
+    pub fn render_options(&self) -> RenderOptions<'_> {
+        RenderOptions::default().set_shadow_lights(&self.shadow_lights)
+    }
+
+    /// Render `camera` to each of `outputs`, with the scene's
+    /// [`RenderOptions`] unless it's a single Deep Zoom pyramid, which is
+    /// rendered tile by tile without them.
+    fn render_to(&self, camera: &Camera, outputs: &[Output]) -> Result<()> {
+        let is_dzi = |output: &Output| {
+            output
+                .path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("dzi"))
+        };
+        match outputs {
+            [output] if is_dzi(output) && output.width.is_none() => {
+                camera.render(&output.path.to_string_lossy(), &self.world)
+            }
+            _ if outputs.iter().any(is_dzi) => {
+                bail!("A Deep Zoom pyramid can only be rendered on its own, at full size")
+            }
+            _ => camera.render_outputs(outputs, &self.world, self.render_options()),
+        }
+    }
+
+    /// How much can be seen along the straight line from `p0` to `p1`, from
+    /// `1.0` with nothing in the way down to `0.0` when something opaque is,
+    /// the luminance of [`Scene::transmittance()`].
+    /// Transmissive surfaces such as glass let part through, see
+    /// [`Material::transmittance()`], and surfaces hidden from secondary rays
+    /// (see [`Visibility`](crate::material::Visibility)) are ignored. Useful
+    /// for gameplay line of sight checks or occluding sound, using the same
+    /// geometry as the render.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     material::{Dielectric, Lambertian},
+    ///     scene::{Scene, SceneSettings},
+    ///     sphere::Sphere,
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// let mut scene = Scene::new(SceneSettings::default());
+    /// scene.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 0.0, 0.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// }));
+    /// scene.add(Box::new(Sphere {
+    ///     centre: vec3![0.0, 5.0, 0.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Dielectric::new(1.5)),
+    /// }));
+    ///
+    /// // through the stone
+    /// assert_eq!(scene.visibility(vec3![-3.0, 0.0, 0.0], vec3![3.0, 0.0, 0.0]), 0.0);
+    /// // stopping short of it
+    /// assert_eq!(scene.visibility(vec3![-3.0, 0.0, 0.0], vec3![-1.5, 0.0, 0.0]), 1.0);
+    /// // through the glass, losing a little at each surface
+    /// let glass = scene.visibility(vec3![-3.0, 5.0, 0.0], vec3![3.0, 5.0, 0.0]);
+    /// assert!((glass - 0.96 * 0.96).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn visibility(&self, p0: Vec3, p1: Vec3) -> f64 {
+        self.transmittance(p0, p1).luminance()
+    }
+
+    /// The fraction of each colour of light getting along the straight line
+    /// from `p0` to `p1`, as a shadow ray, like [`Scene::visibility()`] but
+    /// coloured by any tinted glass in the way (see
+    /// [`Dielectric::with_tint()`]). A line through more than 256 surfaces is
+    /// treated as blocked, rather than guessing what's left of the light.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     material::Dielectric,
+    ///     quad::Quad,
+    ///     scene::{Scene, SceneSettings},
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// // a pane of blue stained glass between a light and the floor
+    /// let mut scene = Scene::new(SceneSettings::default());
+    /// scene.add(Box::new(Quad::new(
+    ///     vec3![-1.0, 2.0, -1.0],
+    ///     vec3![2.0, 0.0, 0.0],
+    ///     vec3![0.0, 0.0, 2.0],
+    ///     Box::new(Dielectric::new(1.5).with_tint(vec3![0.1, 0.3, 0.9])),
+    /// )));
+    ///
+    /// let shadow = scene.transmittance(vec3![0.0, 0.0, 0.0], vec3![0.0, 4.0, 0.0]);
+    /// assert!(shadow[2] > 0.8 && shadow[0] < 0.1);
+    ///
+    /// // a stack of panes that each let all of the light through is still too
+    /// // many to follow
+    /// let mut stack = Scene::new(SceneSettings::default());
+    /// for i in 0..300 {
+    ///     stack.add(Box::new(Quad::new(
+    ///         vec3![-1.0, 0.01 * f64::from(i), -1.0],
+    ///         vec3![2.0, 0.0, 0.0],
+    ///         vec3![0.0, 0.0, 2.0],
+    ///         Box::new(Dielectric::new(1.0)),
+    ///     )));
+    /// }
+    /// let to_light = |to| stack.transmittance(vec3![0.0, -1.0, 0.0], vec3![0.0, to, 0.0]);
+    /// assert_eq!(to_light(1.0), vec3![1.0, 1.0, 1.0]);
+    /// assert_eq!(to_light(4.0), vec3![0.0, 0.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn transmittance(&self, p0: Vec3, p1: Vec3) -> Vec3 {
+        let clear = vec3![1.0, 1.0, 1.0];
+        let offset = p1 - p0;
+        let distance = offset.length();
+        let bias = self
+            .settings
+            .tolerances
+            .ray_bias
+            .resolve(&self.world.bound());
+        if distance <= 2.0 * bias {
+            return clear;
+        }
+
+        let r = ray!(p0, offset / distance);
+        let mut through = clear;
+        let mut from = bias;
+        for _ in 0..MAX_OCCLUDERS {
+            let Some(rec) = self.world.hit(&r, interval![from, distance - bias]) else {
+                return through;
+            };
+            if rec.mat.visibility().secondary {
+                through = through * rec.mat.transmittance(&r, &rec);
+                if through.near_zero() {
+                    return Vec3::default();
+                }
+            }
+            from = rec.t + bias;
+        }
+        Vec3::default()
+    }
+
+    /// Add a `camera` called `name`, replacing any camera already called that.
+    pub fn add_camera<S: Into<String>>(&mut self, name: S, camera: Camera) {
+        self.cameras.insert(name.into(), camera);
+    }
+
+    /// The camera called `name`, if there is one.
+    #[must_use]
+    pub fn camera(&self, name: &str) -> Option<&Camera> {
+        self.cameras.get(name)
+    }
+
+    /// All of the cameras with their names, in name order.
+    pub fn cameras(&self) -> impl Iterator<Item = (&str, &Camera)> {
+        self.cameras
+            .iter()
+            .map(|(name, camera)| (name.as_str(), camera))
+    }
+
+    /// The name of the [`Scene`]'s camera, when it only has one.
+    ///
+    /// # Errors
+    ///
+    /// If the scene has no cameras or more than one.
+    pub fn only_camera(&self) -> Result<&str> {
+        only_camera(self.cameras.keys())
+    }
+
+    /// Render the camera called `name` to the file at `output`.
+    ///
+    /// # Errors
+    ///
+    /// If there's no camera called `name`, or saving the image fails.
+    pub fn render_camera(&self, name: &str, output: &str) -> Result<()> {
+        self.render_camera_outputs(name, &[Output::new(output)])
+    }
+
+    /// Render the camera called `name` once, saving it to every one of
+    /// `outputs`, see [`FilmBuffer::save_outputs()`](crate::film::FilmBuffer::save_outputs()).
+    /// A Deep Zoom (`.dzi`) pyramid can only be rendered on its own.
+    ///
+    /// # Errors
+    ///
+    /// If there's no camera called `name`, a Deep Zoom pyramid is among
+    /// other outputs, or saving any image fails.
+    pub fn render_camera_outputs(&self, name: &str, outputs: &[Output]) -> Result<()> {
+        let camera = self.camera(name).ok_or_else(|| {
+            let names: Vec<_> = self.cameras.keys().map(String::as_str).collect();
+            anyhow!(
+                "No camera named {name:?}, the scene has: {}",
+                names.join(", ")
+            )
+        })?;
+        self.render_to(camera, outputs)
+    }
+
+    /// Render every camera, each to `<name>.<extension>` in `dir`. Returns
+    /// the paths written.
+    ///
+    /// # Errors
+    ///
+    /// If saving any of the images fails.
+    pub fn render_all_cameras<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        extension: &str,
+    ) -> Result<Vec<PathBuf>> {
+        let mut written = vec![];
+        for (name, camera) in &self.cameras {
+            let output = dir.as_ref().join(format!("{name}.{extension}"));
+            eprintln!("Rendering camera {name}...");
+            self.render_to(camera, &[Output::new(&output)])?;
+            written.push(output);
+        }
+        Ok(written)
+    }
+}
+
+/// An animation rendered one [`Scene`] per frame, with the scenes built by a
+/// callback so objects, materials and cameras can be changed procedurally
+/// between frames, e.g. from a physics step or a parameter sweep.
+///
+/// Each frame is written to the output pattern with its number filled in, see
+/// [`Sequence::frame_path()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder},
+///     material::Lambertian,
+///     scene::{Scene, SceneSettings, Sequence},
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let dir = std::env::temp_dir().join("rustrace_sequence_doc");
+/// let sequence = Sequence::new(0..3, dir.join("bounce_##.png").to_str().unwrap());
+///
+/// let mut height = 2.0;
+/// let mut velocity = 0.0;
+/// let frames = sequence
+///     .render_sequence_with(&mut |_frame| {
+///         // a crude physics step per frame
+///         velocity -= 9.8 / 24.0;
+///         height += velocity / 24.0;
+///
+///         let mut scene = Scene::new(SceneSettings::default());
+///         scene.add(Box::new(Sphere {
+///             centre: vec3![0.0, height, -3.0],
+///             radius: 0.5,
+///             mat: Box::new(Lambertian::new(vec3![0.8, 0.3, 0.3])),
+///         }));
+///         scene.add_camera(
+///             "main",
+///             CameraBuilder::default()
+///                 .set_image_width(8)
+///                 .set_anti_aliasing(AntiAliasing::Random(1))
+///                 .build().unwrap(),
+///         );
+///         scene
+///     })
+///     .unwrap();
+///
+/// assert_eq!(frames, [0, 1, 2].map(|i| dir.join(format!("bounce_0{i}.png"))));
+/// assert!(frames.iter().all(|frame| frame.exists()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    frames: Range<u32>,
+    output: String,
+    camera: Option<String>,
+    resume: bool,
+    sample_seed: Option<u64>,
+}
+
+impl Sequence {
+    /// Create a [`Sequence`] rendering `frames` to files named after the
+    /// `output` pattern.
+    ///
+    /// # Panics
+    ///
+    /// If `frames` is empty.
+    #[must_use]
+    pub fn new<S: Into<String>>(frames: Range<u32>, output: S) -> Self {
+        assert!(!frames.is_empty(), "Sequence needs at least one frame");
+        Self {
+            frames,
+            output: output.into(),
+            camera: None,
+            resume: false,
+            sample_seed: None,
+        }
+    }
+
+    /// Set which named camera to render each frame from. By default each
+    /// frame's scene must have exactly one camera.
+    #[must_use]
+    pub fn set_camera<S: Into<String>>(self, camera: S) -> Self {
+        Self {
+            camera: Some(camera.into()),
+            ..self
+        }
+    }
+
+    /// Set whether to skip rendering frames whose output already exists, to
+    /// pick up an interrupted sequence where it left off.
+    #[must_use]
+    pub fn set_resume(self, resume: bool) -> Self {
+        Self { resume, ..self }
+    }
+
+    /// Render every frame with the same sample seed, so the noise stays put
+    /// from frame to frame rather than sparkling, see
+    /// [`CameraBuilder::set_sample_seed()`].
+    #[must_use]
+    pub fn set_sample_seed(self, seed: u64) -> Self {
+        Self {
+            sample_seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// The frames of the [`Sequence`].
+    #[must_use]
+    pub fn frames(&self) -> Range<u32> {
+        self.frames.clone()
+    }
+
+    /// The file `frame` is written to. The first run of `#` in the output
+    /// pattern is replaced with the zero-padded frame number, so
+    /// `shot_####.png` becomes `shot_0012.png`. Without a `#` the number is
+    /// added to the end of the file stem instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    ///
+    /// use rustrace::scene::Sequence;
+    ///
+    /// let padded = Sequence::new(0..100, "frames/shot_####.png");
+    /// assert_eq!(padded.frame_path(12), PathBuf::from("frames/shot_0012.png"));
+    ///
+    /// let plain = Sequence::new(0..100, "frames/shot.png");
+    /// assert_eq!(plain.frame_path(12), PathBuf::from("frames/shot_0012.png"));
+    /// ```
+    #[must_use]
+    pub fn frame_path(&self, frame: u32) -> PathBuf {
+        if let Some(start) = self.output.find('#') {
+            let width = self.output[start..]
+                .bytes()
+                .take_while(|&c| c == b'#')
+                .count();
+            let (before, after) = (&self.output[..start], &self.output[start + width..]);
+            return PathBuf::from(format!("{before}{frame:0width$}{after}"));
+        }
+
+        let path = Path::new(&self.output);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        match path.extension() {
+            Some(ext) => {
+                path.with_file_name(format!("{stem}_{frame:04}.{}", ext.to_string_lossy()))
+            }
+            None => path.with_file_name(format!("{stem}_{frame:04}")),
+        }
+    }
+
+    /// Render every frame, calling `scene_for` with each frame number in order
+    /// to build the [`Scene`] to render. Returns the paths of all of the
+    /// frames, including any skipped when resuming.
+    ///
+    /// `scene_for` is called for every frame even when its render is skipped,
+    /// so stateful simulations stay in step.
+    ///
+    /// # Errors
+    ///
+    /// If a frame's scene doesn't have the camera to render, or creating the
+    /// output directory or saving a frame fails.
+    pub fn render_sequence_with<F: FnMut(u32) -> Scene>(
+        &self,
+        scene_for: &mut F,
+    ) -> Result<Vec<PathBuf>> {
+        let mut written = vec![];
+        for frame in self.frames() {
+            let mut scene = scene_for(frame);
+            if let Some(seed) = self.sample_seed {
+                for camera in scene.cameras.values_mut() {
+                    camera.sample_seed = Some(seed);
+                }
+            }
+            let output = self.frame_path(frame);
+
+            if !(self.resume && output.exists()) {
+                if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    fs::create_dir_all(dir)?;
+                }
+
+                let camera = match &self.camera {
+                    Some(name) => name.as_str(),
+                    None => scene.only_camera()?,
+                };
+                eprintln!("Rendering frame {frame}...");
+                scene
+                    .render_camera(camera, &output.to_string_lossy())
+                    .with_context(|| format!("Couldn't render frame {frame}"))?;
+            }
+
+            written.push(output);
+        }
+        Ok(written)
+    }
+}
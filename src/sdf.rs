@@ -0,0 +1,713 @@
+//! This module contains [`SdfObject`], a [`Hittable`] whose surface is the zero
+//! of a signed distance function built from [`SdfShape`]s, for procedural
+//! geometry that would take far too many triangles. Along with the built-in
+//! shapes, blends and rounding, any distance function can be supplied as a
+//! closure with [`SdfFunction`], such as a fractal's distance estimator.
+//!
+//! By default [ray](Ray)s are sphere traced, stepping by the distance to the
+//! surface each time. That relies on the function never overstating the
+//! distance, which shapes like [`SdfShape::Ripple`] break, so steps jump
+//! through thin features and leave holes and banding. The
+//! [`SdfIntersection::Interval`] mode instead bounds the function over
+//! pieces of the ray with interval arithmetic and only throws away pieces that
+//! provably can't cross the surface, so it never misses, at some cost.
+
+use std::{
+    f64::consts::{FRAC_PI_2, TAU},
+    fmt,
+    sync::Arc,
+};
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    vec3,
+    vec3::Vec3,
+};
+
+/// The distance from the surface within which a sphere traced [ray](Ray)
+/// counts as hitting it.
+const HIT_DISTANCE: f64 = 1e-6;
+
+/// The most steps a sphere traced [ray](Ray) takes before giving up.
+const MAX_STEPS: u32 = 512;
+
+/// The length of ray an interval search narrows a crossing down to.
+const RESOLUTION: f64 = 1e-7;
+
+/// The offset used for the central differences giving the normal.
+const NORMAL_OFFSET: f64 = 1e-6;
+
+/// A signed distance function supplied as a closure, for an
+/// [`SdfShape::Function`].
+///
+/// The function should be negative inside and never overstate the distance to
+/// the surface. Estimators that can, like most for fractals, should say by
+/// how much with [`SdfFunction::with_lipschitz()`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{aabb::Aabb, interval, interval::Interval, sdf::SdfFunction, vec3, vec3::Vec3};
+///
+/// // an octahedron, whose distance is overstated by up to sqrt(3)
+/// let octahedron = SdfFunction::new(
+///     Aabb {
+///         x: interval![-1.0, 1.0],
+///         y: interval![-1.0, 1.0],
+///         z: interval![-1.0, 1.0],
+///     },
+///     |p| p[0].abs() + p[1].abs() + p[2].abs() - 1.0,
+/// )
+/// .with_lipschitz(3.0_f64.sqrt());
+///
+/// let distance = octahedron.distance(vec3![2.0, 0.0, 0.0]);
+/// assert!((distance - 1.0 / 3.0_f64.sqrt()).abs() < 1e-12);
+/// ```
+#[derive(Clone)]
+pub struct SdfFunction {
+    function: Arc<dyn Fn(Vec3) -> f64 + Send + Sync>,
+    bound: Aabb,
+    lipschitz: f64,
+}
+
+impl SdfFunction {
+    /// Wrap `function`, whose surface lies entirely within `bound`.
+    #[must_use]
+    pub fn new<F>(bound: Aabb, function: F) -> Self
+    where
+        F: Fn(Vec3) -> f64 + Send + Sync + 'static,
+    {
+        Self {
+            function: Arc::new(function),
+            bound,
+            lipschitz: 1.0,
+        }
+    }
+
+    /// Set the most the function changes over a unit distance, which the
+    /// distance is divided by to keep steps from overshooting. Exact distance
+    /// functions have `1.0`, the default.
+    ///
+    /// # Panics
+    ///
+    /// If `lipschitz` isn't positive and finite.
+    #[must_use]
+    pub fn with_lipschitz(self, lipschitz: f64) -> Self {
+        assert!(
+            lipschitz > 0.0 && lipschitz.is_finite(),
+            "Lipschitz constant must be positive and finite"
+        );
+        Self { lipschitz, ..self }
+    }
+
+    /// The function at `p`, scaled down to never overstate the distance.
+    #[must_use]
+    pub fn distance(&self, p: Vec3) -> f64 {
+        (self.function)(p) / self.lipschitz
+    }
+}
+
+impl fmt::Debug for SdfFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SdfFunction")
+            .field("function", &"..")
+            .field("bound", &self.bound)
+            .field("lipschitz", &self.lipschitz)
+            .finish()
+    }
+}
+
+/// Functions are only equal if they're the same closure.
+impl PartialEq for SdfFunction {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.function, &other.function)
+            && self.bound == other.bound
+            && self.lipschitz == other.lipschitz
+    }
+}
+
+/// A signed distance function, negative inside the shape.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{aabb::Aabb, interval, interval::Interval, sdf::SdfShape, vec3, vec3::Vec3};
+///
+/// let ball = SdfShape::Sphere {
+///     centre: vec3![0.0, 0.0, 0.0],
+///     radius: 1.0,
+/// };
+/// assert_eq!(ball.distance(vec3![3.0, 0.0, 0.0]), 2.0);
+///
+/// // a guaranteed range of distances over a whole box
+/// let range = ball.distance_range(&Aabb {
+///     x: interval![2.0, 3.0],
+///     y: interval![0.0, 0.0],
+///     z: interval![0.0, 0.0],
+/// });
+/// assert_eq!((range.min, range.max), (1.0, 2.0));
+///
+/// let hollow = SdfShape::Difference(
+///     Box::new(ball),
+///     Box::new(SdfShape::Sphere {
+///         centre: vec3![0.0, 0.0, 0.0],
+///         radius: 0.5,
+///     }),
+/// );
+/// assert!(hollow.distance(vec3![0.0, 0.0, 0.0]) > 0.0);
+/// assert!(hollow.distance(vec3![0.75, 0.0, 0.0]) < 0.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdfShape {
+    /// A sphere of `radius` about `centre`.
+    Sphere {
+        /// The centre of the sphere.
+        centre: Vec3,
+        /// The radius of the sphere.
+        radius: f64,
+    },
+    /// An axis-aligned box reaching `half_size` either side of `centre`.
+    Cuboid {
+        /// The centre of the box.
+        centre: Vec3,
+        /// Half the size of the box along each axis.
+        half_size: Vec3,
+    },
+    /// A torus about `centre` lying flat in the `XZ` plane.
+    Torus {
+        /// The centre of the torus.
+        centre: Vec3,
+        /// The radius of the ring through the middle of the tube.
+        major: f64,
+        /// The radius of the tube.
+        minor: f64,
+    },
+    /// Everything inside either shape.
+    Union(Box<SdfShape>, Box<SdfShape>),
+    /// Everything inside both shapes.
+    Intersection(Box<SdfShape>, Box<SdfShape>),
+    /// Everything inside the first shape but not the second.
+    Difference(Box<SdfShape>, Box<SdfShape>),
+    /// Everything inside either shape, with the seams filled in smoothly
+    /// where they come within `radius` of each other.
+    SmoothUnion {
+        /// The first shape.
+        a: Box<SdfShape>,
+        /// The second shape.
+        b: Box<SdfShape>,
+        /// How far apart the surfaces start to blend.
+        radius: f64,
+    },
+    /// A shape grown by `radius` all round, rounding its edges and corners.
+    Round {
+        /// The shape to round.
+        shape: Box<SdfShape>,
+        /// How far to grow the shape.
+        radius: f64,
+    },
+    /// A shape with `amplitude * sin(fx) sin(fy) sin(fz)` added to its
+    /// distance, for frequency `f`. Once `amplitude * frequency` nears `1`
+    /// the result isn't a true distance, and sphere tracing starts to miss.
+    Ripple {
+        /// The shape to ripple.
+        shape: Box<SdfShape>,
+        /// The height of the ripples.
+        amplitude: f64,
+        /// The angular frequency of the ripples.
+        frequency: f64,
+    },
+    /// A user supplied distance function.
+    Function(SdfFunction),
+}
+
+impl SdfShape {
+    /// The signed distance from `p` to the surface.
+    #[must_use]
+    pub fn distance(&self, p: Vec3) -> f64 {
+        match self {
+            SdfShape::Sphere { centre, radius } => (p - *centre).length() - radius,
+            SdfShape::Cuboid { centre, half_size } => {
+                let q = (0..3).map(|i| (p[i] - centre[i]).abs() - half_size[i]);
+                let (outside, inside) = q.fold((0.0, f64::NEG_INFINITY), |(out, inn), q| {
+                    (out + q.max(0.0).powi(2), f64::max(inn, q))
+                });
+                outside.sqrt() + inside.min(0.0)
+            }
+            SdfShape::Torus {
+                centre,
+                major,
+                minor,
+            } => {
+                let offset = p - *centre;
+                let ring = offset[0].hypot(offset[2]) - major;
+                ring.hypot(offset[1]) - minor
+            }
+            SdfShape::Union(a, b) => a.distance(p).min(b.distance(p)),
+            SdfShape::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            SdfShape::Difference(a, b) => a.distance(p).max(-b.distance(p)),
+            SdfShape::SmoothUnion { a, b, radius } => {
+                let (a, b) = (a.distance(p), b.distance(p));
+                a.min(b) - smooth_min_dip(a, b, *radius)
+            }
+            SdfShape::Round { shape, radius } => shape.distance(p) - radius,
+            SdfShape::Ripple {
+                shape,
+                amplitude,
+                frequency,
+            } => {
+                let wave = (0..3).map(|i| (p[i] * frequency).sin()).product::<f64>();
+                shape.distance(p) + amplitude * wave
+            }
+            SdfShape::Function(function) => function.distance(p),
+        }
+    }
+
+    /// A range certain to contain [`SdfShape::distance()`] at every point in
+    /// `region`, by interval arithmetic. It may be wider than the true range,
+    /// but never narrower.
+    #[must_use]
+    pub fn distance_range(&self, region: &Aabb) -> Interval {
+        let axes = [region.x, region.y, region.z];
+        match self {
+            SdfShape::Sphere { centre, radius } => {
+                let offset = axes_offset(&axes, *centre);
+                shift(length(&offset), -radius)
+            }
+            SdfShape::Cuboid { centre, half_size } => {
+                let offset = axes_offset(&axes, *centre);
+                let q = [0, 1, 2].map(|i| shift(abs(offset[i]), -half_size[i]));
+                let outside = length(&q.map(|q| max(q, Interval::new(0.0, 0.0))));
+                let inside = max(max(q[0], q[1]), q[2]);
+                add(outside, min(inside, Interval::new(0.0, 0.0)))
+            }
+            SdfShape::Torus {
+                centre,
+                major,
+                minor,
+            } => {
+                let offset = axes_offset(&axes, *centre);
+                let ring = shift(length(&[offset[0], offset[2]]), -major);
+                shift(length(&[ring, offset[1]]), -minor)
+            }
+            SdfShape::Union(a, b) => min(a.distance_range(region), b.distance_range(region)),
+            SdfShape::Intersection(a, b) => max(a.distance_range(region), b.distance_range(region)),
+            SdfShape::Difference(a, b) => {
+                max(a.distance_range(region), neg(b.distance_range(region)))
+            }
+            SdfShape::SmoothUnion { a, b, radius } => {
+                // the blend only ever dips below the union, by up to a
+                // quarter of the radius
+                let union = min(a.distance_range(region), b.distance_range(region));
+                Interval::new(union.min - radius.abs() * 0.25, union.max)
+            }
+            SdfShape::Round { shape, radius } => shift(shape.distance_range(region), -radius),
+            SdfShape::Ripple {
+                shape,
+                amplitude,
+                frequency,
+            } => {
+                let wave = axes
+                    .map(|axis| sin(scale(axis, *frequency)))
+                    .into_iter()
+                    .fold(Interval::new(1.0, 1.0), mul);
+                add(shape.distance_range(region), scale(wave, *amplitude))
+            }
+            // the function can't change by more than the distance from the
+            // middle of the region to its corners
+            SdfShape::Function(function) => {
+                let middle = function.distance(region.centroid());
+                let reach = 0.5 * length(&axes.map(|axis| Interval::new(0.0, axis.size()))).max;
+                Interval::new(middle - reach, middle + reach)
+            }
+        }
+    }
+
+    /// An [`Aabb`] containing the whole shape.
+    #[must_use]
+    pub fn bound(&self) -> Aabb {
+        let around = |centre: Vec3, half: Vec3| Aabb {
+            x: Interval::new(centre[0] - half[0], centre[0] + half[0]),
+            y: Interval::new(centre[1] - half[1], centre[1] + half[1]),
+            z: Interval::new(centre[2] - half[2], centre[2] + half[2]),
+        };
+        match self {
+            SdfShape::Sphere { centre, radius } => {
+                around(*centre, vec3![*radius, *radius, *radius])
+            }
+            SdfShape::Cuboid { centre, half_size } => around(*centre, *half_size),
+            SdfShape::Torus {
+                centre,
+                major,
+                minor,
+            } => {
+                let outer = major + minor;
+                around(*centre, vec3![outer, *minor, outer])
+            }
+            SdfShape::Union(a, b) => {
+                let mut bound = a.bound();
+                bound.union(&b.bound());
+                bound
+            }
+            SdfShape::Intersection(a, b) => {
+                let (a, b) = (a.bound(), b.bound());
+                let overlap =
+                    |a: Interval, b: Interval| Interval::new(a.min.max(b.min), a.max.min(b.max));
+                Aabb {
+                    x: overlap(a.x, b.x),
+                    y: overlap(a.y, b.y),
+                    z: overlap(a.z, b.z),
+                }
+            }
+            SdfShape::Difference(a, _) => a.bound(),
+            SdfShape::SmoothUnion { a, b, radius } => {
+                let mut bound = a.bound();
+                bound.union(&b.bound());
+                grown(&bound, radius.abs() * 0.25)
+            }
+            SdfShape::Round { shape, radius } => grown(&shape.bound(), radius.max(0.0)),
+            SdfShape::Ripple {
+                shape, amplitude, ..
+            } => grown(&shape.bound(), amplitude.abs()),
+            SdfShape::Function(function) => function.bound,
+        }
+    }
+
+    /// The outward unit normal at `p`, by central differences.
+    fn normal(&self, p: Vec3) -> Vec3 {
+        let axis = |i: usize| {
+            let mut offset = vec3![0.0, 0.0, 0.0];
+            offset[i] = NORMAL_OFFSET;
+            self.distance(p + offset) - self.distance(p - offset)
+        };
+        vec3![axis(0), axis(1), axis(2)].unit()
+    }
+}
+
+/// How [ray](Ray)s find the surface of an [`SdfObject`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SdfIntersection {
+    /// Step along the ray by the distance to the surface. Fast, but can jump
+    /// through the surface of shapes whose distance isn't exact.
+    #[default]
+    SphereTrace,
+    /// Split the ray into pieces, skipping those the surface provably isn't
+    /// in by interval arithmetic, until the first crossing is narrowed down.
+    /// Robust on any [`SdfShape`], but several times slower, so best kept to
+    /// objects that show the artefacts up close.
+    Interval,
+}
+
+/// A [`Hittable`] surface where an [`SdfShape`] is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable,
+///     material::Lambertian,
+///     ray,
+///     ray::Ray,
+///     sdf::{SdfIntersection, SdfObject, SdfShape},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // a sphere rippled so much its distance is far from exact
+/// let shape = SdfShape::Ripple {
+///     shape: Box::new(SdfShape::Sphere {
+///         centre: vec3![0.0, 0.0, 0.0],
+///         radius: 1.0,
+///     }),
+///     amplitude: 0.2,
+///     frequency: 40.0,
+/// };
+/// let mat = || Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5]));
+/// let traced = SdfObject::new(shape.clone(), mat());
+/// let robust =
+///     SdfObject::new(shape.clone(), mat()).with_intersection(SdfIntersection::Interval);
+///
+/// // sphere tracing steps straight through the first ripples it meets, but
+/// // interval arithmetic finds where the ray really crosses first
+/// let r = ray!(vec3![-0.6, -0.6, 5.0], vec3![0.0, 0.0, -1.0]);
+/// let first = robust.nearest_hit_along(&r).unwrap();
+/// assert!(shape.distance(first.p).abs() < 1e-5);
+/// assert!(traced.nearest_hit_along(&r).map_or(true, |rec| rec.t > first.t));
+/// ```
+///
+/// Blending a rounded box into a shape from a closure:
+///
+/// ```rust
+/// use rustrace::{
+///     aabb::Aabb,
+///     hit::Hittable,
+///     interval,
+///     interval::Interval,
+///     material::Lambertian,
+///     ray,
+///     ray::Ray,
+///     sdf::{SdfFunction, SdfObject, SdfShape},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let rounded_box = SdfShape::Round {
+///     shape: Box::new(SdfShape::Cuboid {
+///         centre: vec3![0.0, 0.0, 0.0],
+///         half_size: vec3![0.4, 0.4, 0.4],
+///     }),
+///     radius: 0.1,
+/// };
+/// let ball = SdfShape::Function(SdfFunction::new(
+///     Aabb {
+///         x: interval![0.9, 1.7],
+///         y: interval![-0.4, 0.4],
+///         z: interval![-0.4, 0.4],
+///     },
+///     |p| (p - vec3![1.3, 0.0, 0.0]).length() - 0.4,
+/// ));
+/// let blob = SdfShape::SmoothUnion {
+///     a: Box::new(rounded_box.clone()),
+///     b: Box::new(ball.clone()),
+///     radius: 0.3,
+/// };
+///
+/// // the corners are rounded off
+/// assert!(rounded_box.distance(vec3![0.55, 0.55, 0.55]) > 0.0);
+/// // and the gap between the shapes starts to fill in
+/// let gap = vec3![0.75, 0.3, 0.0];
+/// assert!(blob.distance(gap) < rounded_box.distance(gap).min(ball.distance(gap)));
+///
+/// let object = SdfObject::new(blob, Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])));
+/// let rec = object
+///     .nearest_hit_along(&ray!(vec3![5.0, 0.0, 0.0], vec3![-1.0, 0.0, 0.0]))
+///     .unwrap();
+/// assert!((rec.p - vec3![1.7, 0.0, 0.0]).length() < 1e-5);
+/// assert!((rec.norm - vec3![1.0, 0.0, 0.0]).length() < 1e-5);
+/// ```
+#[derive(Debug)]
+pub struct SdfObject {
+    /// The signed distance function.
+    pub shape: SdfShape,
+    /// The [`Material`] of the surface.
+    pub mat: Box<dyn Material>,
+    /// How rays find the surface.
+    pub intersection: SdfIntersection,
+    bound: Aabb,
+}
+
+impl SdfObject {
+    /// Create a new sphere traced [`SdfObject`] with the surface of `shape`.
+    #[must_use]
+    pub fn new(shape: SdfShape, mat: Box<dyn Material>) -> Self {
+        let bound = shape.bound();
+        Self {
+            shape,
+            mat,
+            intersection: SdfIntersection::default(),
+            bound,
+        }
+    }
+
+    /// Set how rays find the surface.
+    #[must_use]
+    pub fn with_intersection(self, intersection: SdfIntersection) -> Self {
+        Self {
+            intersection,
+            ..self
+        }
+    }
+
+    /// Sphere trace `ray` over `span`, first stepping off the surface if it
+    /// starts on it.
+    fn sphere_trace(&self, ray: &Ray, span: Interval) -> Option<f64> {
+        let speed = ray.direction.length();
+        let mut t = span.min;
+        let mut leaving = true;
+        for _ in 0..MAX_STEPS {
+            if t > span.max {
+                return None;
+            }
+            let distance = self.shape.distance(ray.at(t)).abs();
+            if distance < HIT_DISTANCE {
+                if !leaving {
+                    return Some(t);
+                }
+            } else {
+                leaving = false;
+            }
+            t += distance.max(HIT_DISTANCE) / speed;
+        }
+        None
+    }
+
+    /// Find the first piece of `ray` in `span` that the surface can cross,
+    /// splitting it in half until it's shorter than [`RESOLUTION`].
+    fn interval_search(&self, ray: &Ray, span: Interval) -> Option<f64> {
+        let speed = ray.direction.length();
+        let mut pieces = vec![span];
+        while let Some(piece) = pieces.pop() {
+            let (start, end) = (ray.at(piece.min), ray.at(piece.max));
+            let region = Aabb {
+                x: Interval::new(start[0].min(end[0]), start[0].max(end[0])),
+                y: Interval::new(start[1].min(end[1]), start[1].max(end[1])),
+                z: Interval::new(start[2].min(end[2]), start[2].max(end[2])),
+            };
+            let range = self.shape.distance_range(&region);
+            if range.min > 0.0 || range.max < 0.0 {
+                continue;
+            }
+
+            if piece.size() * speed < RESOLUTION {
+                return Some(piece.mid());
+            }
+            // the nearer half is searched first
+            let mid = piece.mid();
+            pieces.push(Interval::new(mid, piece.max));
+            pieces.push(Interval::new(piece.min, mid));
+        }
+        None
+    }
+}
+
+impl Hittable for SdfObject {
+    /// Clip the [ray](Ray) to the bounds of the shape, then find the surface
+    /// by the [`SdfIntersection`] mode.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let inside = self.bound.ray_interval(ray)?;
+        let span = Interval::new(inside.min.max(ray_t.min), inside.max.min(ray_t.max));
+        if span.min > span.max {
+            return None;
+        }
+
+        let t = match self.intersection {
+            SdfIntersection::SphereTrace => self.sphere_trace(ray, span)?,
+            SdfIntersection::Interval => self.interval_search(ray, span)?,
+        };
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let p = ray.at(t);
+        let norm = self.shape.normal(p);
+        let mut rec = HitRecord {
+            t,
+            p,
+            norm,
+            mat: &*self.mat,
+            front_face: false,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+            layer: None,
+            tangent: None,
+        };
+        rec.set_face_norm(ray, &norm);
+        Some(rec)
+    }
+
+    fn bound(&self) -> Aabb {
+        self.bound
+    }
+}
+
+/// How far the polynomial smooth minimum of `a` and `b` dips below their
+/// minimum, blending over `radius`.
+fn smooth_min_dip(a: f64, b: f64, radius: f64) -> f64 {
+    if radius <= 0.0 {
+        return 0.0;
+    }
+    let h = (radius - (a - b).abs()).max(0.0) / radius;
+    h * h * radius * 0.25
+}
+
+/// `bound` grown by `by` along every axis.
+fn grown(bound: &Aabb, by: f64) -> Aabb {
+    let grow = |axis: Interval| Interval::new(axis.min - by, axis.max + by);
+    Aabb {
+        x: grow(bound.x),
+        y: grow(bound.y),
+        z: grow(bound.z),
+    }
+}
+
+/// The ranges of each axis of `axes` less `centre`.
+fn axes_offset(axes: &[Interval; 3], centre: Vec3) -> [Interval; 3] {
+    [0, 1, 2].map(|i| shift(axes[i], -centre[i]))
+}
+
+fn shift(a: Interval, by: f64) -> Interval {
+    Interval::new(a.min + by, a.max + by)
+}
+
+fn scale(a: Interval, by: f64) -> Interval {
+    let (lo, hi) = (a.min * by, a.max * by);
+    Interval::new(lo.min(hi), lo.max(hi))
+}
+
+fn add(a: Interval, b: Interval) -> Interval {
+    Interval::new(a.min + b.min, a.max + b.max)
+}
+
+fn neg(a: Interval) -> Interval {
+    Interval::new(-a.max, -a.min)
+}
+
+fn mul(a: Interval, b: Interval) -> Interval {
+    let products = [a.min * b.min, a.min * b.max, a.max * b.min, a.max * b.max];
+    Interval::new(
+        products.into_iter().fold(f64::INFINITY, f64::min),
+        products.into_iter().fold(f64::NEG_INFINITY, f64::max),
+    )
+}
+
+fn min(a: Interval, b: Interval) -> Interval {
+    Interval::new(a.min.min(b.min), a.max.min(b.max))
+}
+
+fn max(a: Interval, b: Interval) -> Interval {
+    Interval::new(a.min.max(b.min), a.max.max(b.max))
+}
+
+fn abs(a: Interval) -> Interval {
+    if a.min >= 0.0 {
+        a
+    } else if a.max <= 0.0 {
+        neg(a)
+    } else {
+        Interval::new(0.0, a.max.max(-a.min))
+    }
+}
+
+/// The Euclidean length of a vector with components in `parts`.
+fn length(parts: &[Interval]) -> Interval {
+    let (lo, hi) = parts.iter().fold((0.0, 0.0), |(lo, hi), &part| {
+        let part = abs(part);
+        (lo + part.min * part.min, hi + part.max * part.max)
+    });
+    Interval::new(f64::sqrt(lo), f64::sqrt(hi))
+}
+
+fn sin(a: Interval) -> Interval {
+    if a.size() >= TAU {
+        return Interval::new(-1.0, 1.0);
+    }
+    let (start, end) = (a.min.sin(), a.max.sin());
+    // whether a peak at `offset` plus a whole number of turns is inside
+    let reaches = |offset: f64| offset + ((a.min - offset) / TAU).ceil() * TAU <= a.max;
+    Interval::new(
+        if reaches(-FRAC_PI_2) {
+            -1.0
+        } else {
+            start.min(end)
+        },
+        if reaches(FRAC_PI_2) {
+            1.0
+        } else {
+            start.max(end)
+        },
+    )
+}
@@ -0,0 +1,516 @@
+//! This module contains a headless render server: a [`JobQueue`] of scenes
+//! rendered one after another in the background, and [`serve()`] to drive it
+//! over a small HTTP/JSON API, so the renderer can sit behind a web service or
+//! be scripted from other languages. Started from the command line with
+//! `rustrace serve --listen 0.0.0.0:8080`.
+//!
+//! | Request | |
+//! |---|---|
+//! | `POST /jobs?camera=<name>&passes=<n>` | Queue the RON scene in the body, replying `{"id": <id>}`. The camera can be left out if the scene has only one, and `passes` defaults to `8`. |
+//! | `GET /jobs` | The [`JobInfo`] of every job. |
+//! | `GET /jobs/<id>` | The [`JobInfo`] of one job. |
+//! | `GET /jobs/<id>/image` | The image so far as a PNG, updated after every coarse preview and pass. |
+//! | `DELETE /jobs/<id>` | Stop a job after its current pass, or forget a job that isn't rendering. |
+//!
+//! Errors reply with `{"error": <message>}`. Submitted scenes can only load
+//! meshes and images from inside the server's asset directory, and the size
+//! of scenes and their images is limited, but there is no authentication, so
+//! only listen on addresses trusted clients can reach.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{Cursor, Read},
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use image::ImageFormat;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{film::FilmBuffer, scene::SceneFile, utils::percent_decode};
+
+/// Where a job is up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for the jobs before it.
+    Queued,
+    /// Being rendered.
+    Rendering,
+    /// Rendered, with the final image available.
+    Done,
+    /// Stopped early by a request, keeping the image so far.
+    Cancelled,
+    /// Couldn't be rendered, see [`JobInfo::error`].
+    Failed,
+}
+
+/// The progress of a job, as reported by the server.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobInfo {
+    /// The job's id.
+    pub id: u64,
+    /// The camera being rendered.
+    pub camera: String,
+    /// Where the job is up to.
+    pub status: JobStatus,
+    /// How many passes have been rendered.
+    pub passes_done: u32,
+    /// How many passes the samples are split into, which drops to the
+    /// camera's samples per pixel once rendering starts if there are fewer.
+    pub passes: u32,
+    /// Why the job failed, if it did.
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Job {
+    info: JobInfo,
+    scene: Option<SceneFile>,
+    film: Option<FilmBuffer>,
+    cancel: bool,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+    pending: VecDeque<u64>,
+}
+
+/// Scenes waiting to be rendered, rendered one at a time in the order they
+/// were submitted. Each render is already spread over every core, so running
+/// them side by side wouldn't finish any sooner. Scenes are checked when
+/// they're submitted, but their meshes and images are only loaded when
+/// they're rendered, so a scene whose assets fail to load fails its job.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::server::{JobQueue, JobStatus};
+///
+/// let queue = JobQueue::new(".");
+/// let scene = r#"(
+///     cameras: { "tiny": (image_width: 8, anti_aliasing: Random(4)) },
+///     objects: [],
+/// )"#;
+///
+/// let id = queue.submit(scene, None, 2).unwrap();
+/// assert_eq!(queue.info(id).unwrap().status, JobStatus::Queued);
+/// assert!(queue.image(id).is_none());
+///
+/// assert!(queue.run_next());
+/// let info = queue.info(id).unwrap();
+/// assert_eq!(info.status, JobStatus::Done);
+/// assert_eq!(info.passes_done, 2);
+/// assert_eq!(queue.image(id).unwrap().width(), 8);
+///
+/// // nothing left to do
+/// assert!(!queue.run_next());
+///
+/// // bad scenes are rejected up front
+/// assert!(queue.submit("(", None, 2).is_err());
+/// assert!(queue.submit(scene, Some("missing"), 2).is_err());
+/// let outside = r#"(
+///     cameras: { "tiny": () },
+///     objects: [Mesh(path: "../secret.obj", material: Lambertian(albedo: (1.0, 1.0, 1.0)))],
+/// )"#;
+/// assert!(queue.submit(outside, None, 2).is_err());
+/// let huge = r#"(cameras: { "huge": (image_width: 100000) })"#;
+/// assert!(queue.submit(huge, None, 2).is_err());
+/// let flat = r#"(cameras: { "flat": (aspect_ratio: 0.0) })"#;
+/// assert!(queue.submit(flat, None, 2).is_err());
+///
+/// // while missing assets fail the job
+/// let missing = outside.replace("../secret.obj", "missing.obj");
+/// let id = queue.submit(&missing, None, 2).unwrap();
+/// assert!(queue.run_next());
+/// let info = queue.info(id).unwrap();
+/// assert_eq!(info.status, JobStatus::Failed);
+/// assert!(info.error.unwrap().contains("missing.obj"));
+/// ```
+#[derive(Debug)]
+pub struct JobQueue {
+    base_dir: PathBuf,
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl JobQueue {
+    /// How many coarse previews each job starts with, see
+    /// [`crate::camera::RenderOptions::set_preview_levels()`].
+    pub const PREVIEW_LEVELS: u32 = 4;
+
+    /// The most pixels a job's image can have, including any overscan, so
+    /// one scene can't take all of the server's memory.
+    pub const MAX_PIXELS: u64 = 4096 * 4096;
+
+    /// Create an empty [`JobQueue`], resolving paths in submitted scenes
+    /// relative to `base_dir`, which they can't leave.
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(base_dir: P) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            state: Mutex::default(),
+            ready: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Queue the RON scene `text` to render `camera`, or its only camera if
+    /// [`None`], split into `passes`, or fewer if the camera takes fewer
+    /// samples. Returns the new job's id.
+    ///
+    /// # Panics
+    ///
+    /// If `passes` is `0`.
+    ///
+    /// # Errors
+    ///
+    /// If the scene can't be parsed, uses a path outside the base directory
+    /// (see [`SceneFile::confined()`]), doesn't have the camera, or the
+    /// camera's image has more than [`JobQueue::MAX_PIXELS`].
+    pub fn submit(&self, text: &str, camera: Option<&str>, passes: u32) -> Result<u64> {
+        assert!(passes > 0, "Need at least one pass");
+        let scene = SceneFile::parse_ron(text, self.base_dir.clone())?.confined()?;
+        let camera = match camera {
+            Some(name) if scene.has_camera(name) => name,
+            Some(name) => bail!("No camera named {name:?}"),
+            None => scene.only_camera()?,
+        };
+        let size = scene.camera(camera)?;
+        let (width, height) = (size.image_width, size.image_height());
+        if u64::from(width) * u64::from(height) > Self::MAX_PIXELS {
+            bail!(
+                "A {width} by {height} image is over the limit of {} pixels",
+                Self::MAX_PIXELS
+            );
+        }
+
+        let mut state = self.lock();
+        state.next_id += 1;
+        let id = state.next_id;
+        let info = JobInfo {
+            id,
+            camera: camera.to_owned(),
+            status: JobStatus::Queued,
+            passes_done: 0,
+            passes,
+            error: None,
+        };
+        state.jobs.insert(
+            id,
+            Job {
+                info,
+                scene: Some(scene),
+                film: None,
+                cancel: false,
+            },
+        );
+        state.pending.push_back(id);
+        drop(state);
+
+        self.ready.notify_one();
+        Ok(id)
+    }
+
+    /// The progress of job `id`, or [`None`] if there's no such job.
+    #[must_use]
+    pub fn info(&self, id: u64) -> Option<JobInfo> {
+        self.lock().jobs.get(&id).map(|job| job.info.clone())
+    }
+
+    /// The progress of every job, oldest first.
+    #[must_use]
+    pub fn infos(&self) -> Vec<JobInfo> {
+        self.lock()
+            .jobs
+            .values()
+            .map(|job| job.info.clone())
+            .collect()
+    }
+
+    /// The image of job `id` so far, or [`None`] if there's no such job or
+    /// it hasn't finished a pass.
+    #[must_use]
+    pub fn image(&self, id: u64) -> Option<FilmBuffer> {
+        self.lock().jobs.get(&id)?.film.clone()
+    }
+
+    /// Stop job `id` after its current pass if it's rendering, or otherwise
+    /// forget it. Returns whether there was such a job.
+    pub fn cancel(&self, id: u64) -> bool {
+        let mut state = self.lock();
+        match state.jobs.get_mut(&id) {
+            Some(job) if job.info.status == JobStatus::Rendering => {
+                job.cancel = true;
+                true
+            }
+            Some(_) => {
+                state.jobs.remove(&id);
+                state.pending.retain(|&pending| pending != id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Load and render the oldest queued job, if there is one, returning
+    /// whether there was. Failing to load the scene or a panic while
+    /// rendering fails the job rather than unwinding.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while holding the queue's lock.
+    pub fn run_next(&self) -> bool {
+        let mut state = self.lock();
+        let Some(id) = state.pending.pop_front() else {
+            return false;
+        };
+        let job = state.jobs.get_mut(&id).expect("Pending jobs are kept");
+        job.info.status = JobStatus::Rendering;
+        let scene = job.scene.take().expect("Queued jobs have a scene");
+        let (name, passes) = (job.info.camera.clone(), job.info.passes);
+        drop(state);
+
+        let scene = match scene.load() {
+            Ok(scene) => scene,
+            Err(e) => {
+                let mut state = self.lock();
+                let job = state.jobs.get_mut(&id).expect("Rendering jobs are kept");
+                job.info.status = JobStatus::Failed;
+                job.info.error = Some(format!("{e:#}"));
+                return true;
+            }
+        };
+        let camera = scene.camera(&name).expect("Checked on submit");
+        let passes = passes.min(camera.samples_per_pixel()).max(1);
+        self.lock()
+            .jobs
+            .get_mut(&id)
+            .expect("Rendering jobs are kept")
+            .info
+            .passes = passes;
+        let update = |film: &FilmBuffer, passes_done: u32| {
+            let mut state = self.lock();
+            let job = state.jobs.get_mut(&id).expect("Rendering jobs are kept");
+            job.info.passes_done = passes_done;
+            job.film = Some(film.clone());
+            if job.cancel {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let options = scene
+            .render_options()
+            .set_preview_levels(Self::PREVIEW_LEVELS)
+            .after_preview(|film, _| update(film, 0))
+            .set_passes(passes)
+            .after_pass(|film, pass| update(film, pass + 1));
+        // keep the worker going if a scene panics
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            camera.render_film_with(scene.world(), options)
+        }));
+
+        let mut state = self.lock();
+        let job = state.jobs.get_mut(&id).expect("Rendering jobs are kept");
+        match result {
+            Ok(film) => {
+                job.info.status = if job.cancel {
+                    JobStatus::Cancelled
+                } else {
+                    JobStatus::Done
+                };
+                // after any post process
+                job.film = Some(film);
+            }
+            Err(payload) => {
+                job.info.status = JobStatus::Failed;
+                job.info.error = Some(
+                    payload
+                        .downcast_ref::<&str>()
+                        .map(ToString::to_string)
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "Render panicked".to_owned()),
+                );
+            }
+        }
+        true
+    }
+
+    /// Render jobs as they're submitted, forever.
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while holding the queue's lock.
+    pub fn work(&self) -> ! {
+        loop {
+            if !self.run_next() {
+                let state = self.lock();
+                drop(
+                    self.ready
+                        .wait_while(state, |state| state.pending.is_empty())
+                        .unwrap(),
+                );
+            }
+        }
+    }
+}
+
+/// Serve a [`JobQueue`] over HTTP on `addr`, such as `0.0.0.0:8080`, see
+/// the [module](self) docs. Paths in submitted scenes are relative to
+/// `base_dir`. Runs until the process is stopped.
+///
+/// # Errors
+///
+/// If `addr` can't be listened on.
+///
+/// # Example
+///
+/// ```no_run
+/// rustrace::server::serve("127.0.0.1:8080", ".").unwrap();
+/// ```
+pub fn serve<P: Into<PathBuf>>(addr: &str, base_dir: P) -> Result<()> {
+    let server = Server::http(addr).map_err(|e| anyhow!("Couldn't listen on {addr}: {e}"))?;
+    let queue = Arc::new(JobQueue::new(base_dir));
+
+    let worker = Arc::clone(&queue);
+    thread::spawn(move || worker.work());
+
+    let content_type = |value: &str| {
+        Header::from_bytes("Content-Type", value).map_err(|()| anyhow!("Bad header {value}"))
+    };
+    let (json, png) = (
+        content_type("application/json")?,
+        content_type("image/png")?,
+    );
+
+    eprintln!("Listening on {addr}");
+    for mut request in server.incoming_requests() {
+        let response = handle(&queue, &mut request).unwrap_or_else(|e| Reply::error(400, &e));
+        let (status, header, body) = match response {
+            Reply::Json(status, body) => (status, json.clone(), body),
+            Reply::Png(body) => (200, png.clone(), body),
+        };
+        let response = Response::from_data(body)
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("Couldn't respond: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// A response body and its status code.
+enum Reply {
+    Json(u16, Vec<u8>),
+    Png(Vec<u8>),
+}
+
+impl Reply {
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        Self::Json(
+            status,
+            serde_json::to_vec(value).expect("Replies are always valid JSON"),
+        )
+    }
+
+    fn error(status: u16, error: &anyhow::Error) -> Self {
+        Self::json(
+            status,
+            &serde_json::json!({ "error": format!("{error:#}") }),
+        )
+    }
+
+    fn not_found(id: u64) -> Self {
+        Self::error(404, &anyhow!("No job {id}"))
+    }
+}
+
+/// The largest scene, in bytes, that can be posted to the server.
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// Route `request` to the [`JobQueue`]. Errors are bad requests.
+fn handle(queue: &JobQueue, request: &mut Request) -> Result<Reply> {
+    let url = request.url().to_owned();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    // form encoded, with spaces as `+`
+    let decode = |text: &str| percent_decode(&text.replace('+', " "));
+    let param = |key: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|&(k, _)| decode(k) == key)
+            .map(|(_, value)| decode(value))
+    };
+    let id = |segment: &str| {
+        segment
+            .parse::<u64>()
+            .with_context(|| format!("Bad job id {segment:?}"))
+    };
+
+    Ok(match (request.method(), segments.as_slice()) {
+        (Method::Post, ["jobs"]) => {
+            let passes = match param("passes") {
+                Some(passes) => passes.parse::<u32>().context("Bad number of passes")?,
+                None => 8,
+            };
+            if passes == 0 {
+                bail!("Need at least one pass");
+            }
+            let mut text = String::new();
+            request
+                .as_reader()
+                .take(MAX_BODY + 1)
+                .read_to_string(&mut text)
+                .context("Couldn't read the scene")?;
+            if text.len() as u64 > MAX_BODY {
+                bail!("The scene is over the limit of {MAX_BODY} bytes");
+            }
+            let id = queue.submit(&text, param("camera").as_deref(), passes)?;
+            Reply::json(201, &serde_json::json!({ "id": id }))
+        }
+        (Method::Get, ["jobs"]) => Reply::json(200, &queue.infos()),
+        (Method::Get, ["jobs", job]) => {
+            let id = id(job)?;
+            match queue.info(id) {
+                Some(info) => Reply::json(200, &info),
+                None => Reply::not_found(id),
+            }
+        }
+        (Method::Get, ["jobs", job, "image"]) => {
+            let id = id(job)?;
+            match queue.image(id) {
+                Some(film) => {
+                    let mut png = Cursor::new(vec![]);
+                    film.to_rgb_image().write_to(&mut png, ImageFormat::Png)?;
+                    Reply::Png(png.into_inner())
+                }
+                None if queue.info(id).is_some() => {
+                    Reply::error(409, &anyhow!("Job {id} hasn't finished a pass yet"))
+                }
+                None => Reply::not_found(id),
+            }
+        }
+        (Method::Delete, ["jobs", job]) => {
+            let id = id(job)?;
+            if queue.cancel(id) {
+                Reply::json(200, &serde_json::json!({ "id": id }))
+            } else {
+                Reply::not_found(id)
+            }
+        }
+        _ => Reply::error(404, &anyhow!("No route for {} {path}", request.method())),
+    })
+}
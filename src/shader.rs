@@ -0,0 +1,127 @@
+//! This module contains utility "shader" [material](Material)s, which don't
+//! react to light at all but show a property of the surface as a flat
+//! colour, for stylised looks and for rendering masks to composite with.
+//! So far that's the [`FacingRatio`]. Curvature needs the neighbouring
+//! pixels rather than just the hit, so is a render of its own, see
+//! [`Camera::render_curvature()`](crate::camera::Camera::render_curvature()).
+
+use rand::rngs::SmallRng;
+
+use crate::{
+    hit::HitRecord,
+    material::Material,
+    ray,
+    ray::Ray,
+    vec3::{dot, Vec3},
+};
+
+/// A flat colour ramping from `facing`, where the surface faces the
+/// [ray](Ray) head on, to `edge`, where it's seen edge on, like the
+/// brightening of Fresnel reflections. Good for rim lighting in toon looks,
+/// x-ray effects, or with black and white, a mask of a mesh's silhouette.
+///
+/// It shows the same from either side of the surface, and absorbs
+/// everything, so it doesn't light anything else.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::HitRecord,
+///     material::Material,
+///     ray,
+///     ray::Ray,
+///     shader::FacingRatio,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let black = vec3![0.0, 0.0, 0.0];
+/// let white = vec3![1.0, 1.0, 1.0];
+/// let rim = FacingRatio::new(black, white);
+///
+/// // head on shows the facing colour, edge on the edge colour
+/// assert_eq!(rim.colour(1.0), black);
+/// assert_eq!(rim.colour(0.0), white);
+/// assert_eq!(rim.colour(0.5), vec3![0.5, 0.5, 0.5]);
+///
+/// // and a power spreads the edge colour further in
+/// let wide = rim.with_power(4.0);
+/// assert!(wide.colour(0.5)[0] > 0.9);
+///
+/// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+/// let rec = HitRecord {
+///     p: vec3![0.0, 0.0, 1.0],
+///     norm: vec3![0.0, 0.0, 1.0],
+///     mat: &rim,
+///     t: 1.0,
+///     front_face: true,
+///     u: 0.0,
+///     v: 0.0,
+///     object: None,
+///     layer: None,
+///     tangent: None,
+/// };
+/// assert_eq!(rim.emitted(&r, &rec), black);
+/// assert!(rim.scatter(&r, &rec, None).1.near_zero());
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FacingRatio {
+    facing: Vec3,
+    edge: Vec3,
+    power: f64,
+}
+
+impl FacingRatio {
+    /// Create a new [`FacingRatio`] ramping linearly in the cosine of the
+    /// viewing angle from `facing` to `edge`.
+    #[must_use]
+    pub fn new(facing: Vec3, edge: Vec3) -> Self {
+        Self {
+            facing,
+            edge,
+            power: 1.0,
+        }
+    }
+
+    /// Raise the cosine of the viewing angle to `power` before ramping, so
+    /// above `1.0` the edge colour creeps further in from the edges, and
+    /// below it the facing colour does.
+    ///
+    /// # Panics
+    ///
+    /// If `power` isn't positive and finite.
+    #[must_use]
+    pub fn with_power(self, power: f64) -> Self {
+        assert!(
+            power > 0.0 && power.is_finite(),
+            "Facing ratio power must be positive and finite"
+        );
+        Self { power, ..self }
+    }
+
+    /// The colour of the ramp where the surface is seen at an angle with
+    /// cosine `cos_theta` to its normal.
+    #[must_use]
+    pub fn colour(&self, cos_theta: f64) -> Vec3 {
+        let facing = cos_theta.abs().min(1.0).powf(self.power);
+        self.edge + (self.facing - self.edge) * facing
+    }
+}
+
+impl Material for FacingRatio {
+    /// Absorb everything.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        (ray![rec.p, rec.norm, r_in.time], Vec3::default())
+    }
+
+    /// The [colour](FacingRatio::colour()) of the ramp for the angle the
+    /// [ray](Ray) arrives at.
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.colour(dot(&r_in.direction.unit(), &rec.norm))
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
@@ -0,0 +1,327 @@
+//! This module contains [`ContactSheet`], which renders a grid of small
+//! images varying one or two parameters, e.g. roughness against IOR, and lays
+//! them out in a single labelled image for comparing materials side by side.
+
+use anyhow::{bail, Result};
+
+use crate::{film::FilmBuffer, scene::Scene, vec3, vec3::Vec3};
+
+/// Space around and between cells, in pixels.
+const GAP: u32 = 4;
+/// The size of a glyph in the label font, before scaling.
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// The horizontal distance from one glyph to the next, before scaling.
+const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+const BACKGROUND: Vec3 = vec3![0.05, 0.05, 0.05];
+const TEXT: Vec3 = vec3![0.9, 0.9, 0.9];
+
+/// A parameter varied along one side of a [`ContactSheet`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepAxis {
+    name: String,
+    values: Vec<f64>,
+}
+
+impl SweepAxis {
+    /// Create a [`SweepAxis`] called `name` taking each of `values` in turn.
+    ///
+    /// # Panics
+    ///
+    /// If `values` is empty.
+    #[must_use]
+    pub fn new<S: Into<String>>(name: S, values: Vec<f64>) -> Self {
+        assert!(!values.is_empty(), "Sweep axis needs at least one value");
+        Self {
+            name: name.into(),
+            values,
+        }
+    }
+
+    /// Create a [`SweepAxis`] called `name` with `steps` evenly spaced values
+    /// from `start` to `end` inclusive.
+    ///
+    /// # Panics
+    ///
+    /// If `steps` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::sheet::SweepAxis;
+    ///
+    /// let fuzz = SweepAxis::linspace("fuzz", 0.0, 1.0, 5);
+    /// assert_eq!(fuzz.values(), [0.0, 0.25, 0.5, 0.75, 1.0]);
+    /// ```
+    #[must_use]
+    pub fn linspace<S: Into<String>>(name: S, start: f64, end: f64, steps: u32) -> Self {
+        assert!(steps > 0, "Sweep axis needs at least one value");
+        let values = (0..steps)
+            .map(|i| {
+                if steps == 1 {
+                    start
+                } else {
+                    start + (end - start) * f64::from(i) / f64::from(steps - 1)
+                }
+            })
+            .collect();
+        Self::new(name, values)
+    }
+
+    /// The name of the parameter.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The values the parameter takes.
+    #[must_use]
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// The label for one value, e.g. `FUZZ 0.25`.
+    fn label(&self, value: f64) -> String {
+        let formatted = format!("{value:.3}");
+        let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+        if self.name.is_empty() {
+            trimmed.to_owned()
+        } else {
+            format!("{} {trimmed}", self.name)
+        }
+    }
+}
+
+/// A grid of renders varying one parameter across the columns and optionally
+/// another down the rows, with each cell captioned with its values.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder},
+///     material::Metal,
+///     scene::{Scene, SceneSettings},
+///     sheet::{ContactSheet, SweepAxis},
+///     sphere::Sphere,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let sheet = ContactSheet::new(SweepAxis::linspace("fuzz", 0.0, 1.0, 3))
+///     .set_rows(SweepAxis::new("grey", vec![0.2, 0.8]));
+///
+/// let film = sheet
+///     .render_with(&mut |fuzz, grey| {
+///         let mut scene = Scene::new(SceneSettings::default());
+///         scene.add(Box::new(Sphere {
+///             centre: vec3![0.0, 0.0, -2.0],
+///             radius: 0.5,
+///             mat: Box::new(Metal::new(vec3![grey, grey, grey], fuzz)),
+///         }));
+///         scene.add_camera(
+///             "lookdev",
+///             CameraBuilder::default()
+///                 .set_image_width(48)
+///                 .set_anti_aliasing(AntiAliasing::Random(1))
+///                 .build().unwrap(),
+///         );
+///         scene
+///     })
+///     .unwrap();
+///
+/// // three columns and two rows of 48 pixel wide cells
+/// assert!(film.width() > 3 * 48);
+/// assert!(film.height() > 2 * 48);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactSheet {
+    columns: SweepAxis,
+    rows: SweepAxis,
+}
+
+impl ContactSheet {
+    /// Create a [`ContactSheet`] with one row, sweeping `columns`.
+    #[must_use]
+    pub fn new(columns: SweepAxis) -> Self {
+        Self {
+            columns,
+            rows: SweepAxis::new("", vec![0.0]),
+        }
+    }
+
+    /// Set a second parameter to sweep down the rows.
+    #[must_use]
+    pub fn set_rows(self, rows: SweepAxis) -> Self {
+        Self { rows, ..self }
+    }
+
+    /// Whether there's a second parameter down the rows.
+    fn has_rows(&self) -> bool {
+        !self.rows.name.is_empty() || self.rows.values.len() > 1
+    }
+
+    /// Render every cell, calling `scene_for` with the column and row values
+    /// to build the [`Scene`] to render, then lay them out in one
+    /// [`FilmBuffer`]. Without rows, the row value is always `0`. Each scene
+    /// must have exactly one camera, and all of them must be the same size.
+    ///
+    /// # Errors
+    ///
+    /// If a scene doesn't have exactly one camera, or its image size differs
+    /// from the first.
+    ///
+    /// # Panics
+    ///
+    /// If there are more columns or rows than fit in a [`u32`].
+    pub fn render_with<F: FnMut(f64, f64) -> Scene>(
+        &self,
+        scene_for: &mut F,
+    ) -> Result<FilmBuffer> {
+        let mut cells = vec![];
+        for &row in self.rows.values() {
+            for &column in self.columns.values() {
+                let scene = scene_for(column, row);
+                let name = scene.only_camera()?;
+                let camera = scene.camera(name).expect("only_camera names a camera");
+                eprintln!("Rendering {}...", self.caption(column, row).join(", "));
+                cells.push(camera.crop_overscan(&camera.render_film(scene.world())));
+            }
+        }
+
+        let (cell_width, cell_height) = (cells[0].width(), cells[0].height());
+        if cells
+            .iter()
+            .any(|cell| (cell.width(), cell.height()) != (cell_width, cell_height))
+        {
+            bail!("All of the cells of a contact sheet must be the same size");
+        }
+
+        // scale labels up on larger cells so they stay readable
+        let scale = (cell_width / 160).max(1);
+        let lines = if self.has_rows() { 2 } else { 1 };
+        let caption_height = lines * (GLYPH_HEIGHT + 2) * scale + GAP;
+
+        let columns = u32::try_from(self.columns.values.len()).expect("too many columns");
+        let rows = u32::try_from(self.rows.values.len()).expect("too many rows");
+        let mut sheet = FilmBuffer::new(
+            columns * (cell_width + GAP) + GAP,
+            rows * (cell_height + caption_height + GAP) + GAP,
+        );
+        sheet.pixels_mut().fill(BACKGROUND);
+
+        let mut cells = cells.into_iter();
+        for (j, &row) in (0..rows).zip(self.rows.values()) {
+            for (i, &column) in (0..columns).zip(self.columns.values()) {
+                let cell = cells.next().expect("one cell per grid position");
+                let x0 = GAP + i * (cell_width + GAP);
+                let y0 = GAP + j * (cell_height + caption_height + GAP);
+
+                for y in 0..cell_height {
+                    for x in 0..cell_width {
+                        sheet.set(x0 + x, y0 + y, cell.get(x, y));
+                    }
+                }
+
+                let mut y = y0 + cell_height + GAP;
+                for line in self.caption(column, row) {
+                    draw_text(&mut sheet, x0, y, scale, &line, cell_width);
+                    y += (GLYPH_HEIGHT + 2) * scale;
+                }
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    /// The caption lines for the cell at `column`, `row`.
+    fn caption(&self, column: f64, row: f64) -> Vec<String> {
+        let mut lines = vec![self.columns.label(column)];
+        if self.has_rows() {
+            lines.push(self.rows.label(row));
+        }
+        lines
+    }
+}
+
+/// Draw `text` with its top left corner at `x`, `y`, stopping at any glyph
+/// that would go past `max_width`. Letters are drawn in upper case, and
+/// characters without a glyph are left as spaces.
+fn draw_text(film: &mut FilmBuffer, x: u32, y: u32, scale: u32, text: &str, max_width: u32) {
+    let mut glyph_x = x;
+    for c in text.chars() {
+        if glyph_x + GLYPH_WIDTH * scale > x + max_width {
+            break;
+        }
+
+        for (row, bits) in (0..GLYPH_HEIGHT).zip(glyph(c)) {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (px, py) = (glyph_x + col * scale + dx, y + row * scale + dy);
+                        if px < film.width() && py < film.height() {
+                            film.set(px, py, TEXT);
+                        }
+                    }
+                }
+            }
+        }
+
+        glyph_x += GLYPH_ADVANCE * scale;
+    }
+}
+
+/// The rows of a 5x7 glyph, with the leftmost pixel in bit 4.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        _ => [0x00; 7],
+    }
+}
@@ -1,4 +1,7 @@
-//! Contains the [`Sphere`] struct that models a perfect 3D sphere.
+//! Contains the [`Sphere`] struct that models a perfect 3D sphere, and the
+//! [`MovingSphere`] that moves in a straight line for motion blur.
+
+use std::f64::consts::PI;
 
 use crate::{
     aabb::Aabb,
@@ -7,7 +10,8 @@ use crate::{
     interval::Interval,
     material::Material,
     ray::Ray,
-    vec3::{dot, Vec3},
+    vec3,
+    vec3::{cross, dot, Vec3},
 };
 
 /// The [`Sphere`] struct itself. The [`centre`](field@Sphere::centre),
@@ -40,62 +44,198 @@ impl Hittable for Sphere {
     /// Check if a given [`Ray`] hit the sphere. Returns a [`Some(HitRecord)`](Option<HitRecord>)
     /// with the closest intersection if a [ray](Ray) intersects it, otherwise [`None`].
     fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
-        let origin_to_centre = self.centre - ray.origin;
-        let a = ray.direction.length_squared();
-        let h = dot(&ray.direction, &origin_to_centre);
-        let c = origin_to_centre.length_squared() - self.radius * self.radius;
-        let discriminant = h * h - a * c;
+        hit_sphere(self.centre, self.radius, &*self.mat, ray, ray_t)
+    }
 
-        if discriminant < 0.0 {
-            return None;
-        }
+    fn bound(&self) -> Aabb {
+        sphere_bound(self.centre, self.radius)
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.centre += offset;
+        true
+    }
+
+    /// Projects `point` out from the centre onto the surface. The centre
+    /// itself is equally close to every point, so picks the top.
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        let offset = point - self.centre;
+        let dir = if offset.length_squared() > 0.0 {
+            offset.unit()
+        } else {
+            vec3![0.0, 1.0, 0.0]
+        };
+        Some(self.centre + dir * self.radius)
+    }
+}
+
+/// Intersect `ray` with a sphere of `radius` about `centre`, shared by
+/// [`Sphere`] and [`MovingSphere`].
+fn hit_sphere<'a>(
+    centre: Vec3,
+    radius: f64,
+    mat: &'a dyn Material,
+    ray: &Ray,
+    ray_t: Interval,
+) -> Option<HitRecord<'a>> {
+    let origin_to_centre = centre - ray.origin;
+    let a = ray.direction.length_squared();
+    let h = dot(&ray.direction, &origin_to_centre);
+    let c = origin_to_centre.length_squared() - radius * radius;
+    let discriminant = h * h - a * c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
 
-        let sqrtd = discriminant.sqrt();
+    let sqrtd = discriminant.sqrt();
 
-        let mut root = (h - sqrtd) / a;
+    let mut root = (h - sqrtd) / a;
+    if !ray_t.surrounds(root) {
+        root = (h + sqrtd) / a;
         if !ray_t.surrounds(root) {
-            root = (h + sqrtd) / a;
-            if !ray_t.surrounds(root) {
-                return None;
-            }
+            return None;
         }
+    }
 
-        let t_value = root;
-        let hit_point = ray.at(root);
-        let outward_norm = (hit_point - self.centre) / self.radius;
-        let norm = (hit_point - self.centre) / self.radius;
-        let mat = &(*self.mat);
+    let t_value = root;
+    let hit_point = ray.at(root);
+    let outward_norm = (hit_point - centre) / radius;
+    let norm = (hit_point - centre) / radius;
 
-        let mut rec = HitRecord {
-            t: t_value,
-            p: hit_point,
-            norm,
-            mat,
-            front_face: false,
-        };
+    let mut rec = HitRecord {
+        t: t_value,
+        p: hit_point,
+        norm,
+        mat,
+        front_face: false,
+        u: 0.0,
+        v: 0.0,
+        object: None,
+        layer: None,
+        tangent: None,
+    };
 
-        // DEBUG: Check that ray intersects bound
-        // assert!(self.bound().ray_hit(r).is_some());
+    (rec.u, rec.v) = sphere_uv(outward_norm);
+    // around the poles, which have no direction of their own
+    let around = cross(&vec3![0.0, 1.0, 0.0], &outward_norm);
+    rec.tangent = (!around.near_zero()).then_some(around);
+    rec.set_face_norm(ray, &outward_norm);
 
-        rec.set_face_norm(ray, &outward_norm);
+    Some(rec)
+}
 
-        Some(rec)
+/// The texture coordinates of the point on a sphere with `outward_norm`: the
+/// longitude around from `-X`, and the latitude up from `-Y`.
+pub(crate) fn sphere_uv(outward_norm: Vec3) -> (f64, f64) {
+    let u = (-outward_norm[2]).atan2(outward_norm[0]) / (2.0 * PI) + 0.5;
+    let v = (-outward_norm[1]).clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+/// The bound of a sphere of `radius` about `centre`.
+fn sphere_bound(centre: Vec3, radius: f64) -> Aabb {
+    Aabb {
+        x: interval![centre.e[0] - radius, centre.e[0] + radius],
+        y: interval![centre.e[1] - radius, centre.e[1] + radius],
+        z: interval![centre.e[2] - radius, centre.e[2] + radius],
     }
+}
+
+/// A [`Sphere`] moving in a straight line, from [`start`](field@MovingSphere::start)
+/// at time `0.0` to [`end`](field@MovingSphere::end) at time `1.0`, carrying
+/// on at the same speed outside those times. Each [ray](Ray) sees it where it
+/// is at the ray's time, so with a camera shutter (see
+/// [`CameraBuilder::set_shutter()`](crate::camera::CameraBuilder::set_shutter()))
+/// it's motion blurred. Far cheaper than an
+/// [`Animated`](crate::transform::Animated) sphere when the motion is this
+/// simple.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable, interval, interval::Interval, material::Lambertian, ray, ray::Ray,
+///     sphere::MovingSphere, vec3, vec3::Vec3,
+/// };
+///
+/// let ball = MovingSphere {
+///     start: vec3![0.0, 0.0, 0.0],
+///     end: vec3![0.0, 2.0, 0.0],
+///     radius: 0.5,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// };
+///
+/// let down_z = |time| ray!(vec3![0.0, 1.0, 5.0], vec3![0.0, 0.0, -1.0], time);
+/// assert!(ball.hit(&down_z(0.0), interval![0.0, f64::INFINITY]).is_none());
+/// assert!(ball.hit(&down_z(0.5), interval![0.0, f64::INFINITY]).is_some());
+/// assert_eq!(ball.centre_at(0.25), vec3![0.0, 0.5, 0.0]);
+///
+/// // the bound covers the whole motion
+/// assert_eq!(ball.bound().y, interval![-0.5, 2.5]);
+/// ```
+#[derive(Debug)]
+pub struct MovingSphere {
+    /// The centre at time `0.0`.
+    pub start: Vec3,
+    /// The centre at time `1.0`.
+    pub end: Vec3,
+    /// The radius of the sphere.
+    pub radius: f64,
+    /// The [`Material`] of the sphere.
+    pub mat: Box<dyn Material>,
+}
 
+impl MovingSphere {
+    /// The centre of the sphere at `time`, held at [`start`](field@MovingSphere::start)
+    /// or [`end`](field@MovingSphere::end) outside `0.0..=1.0` so it stays
+    /// within its bounds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{material::Lambertian, sphere::MovingSphere, vec3, vec3::Vec3};
+    ///
+    /// let ball = MovingSphere {
+    ///     start: vec3![0.0, 0.0, 0.0],
+    ///     end: vec3![2.0, 0.0, 0.0],
+    ///     radius: 0.5,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// };
+    ///
+    /// assert_eq!(ball.centre_at(0.5), vec3![1.0, 0.0, 0.0]);
+    /// assert_eq!(ball.centre_at(3.0), vec3![2.0, 0.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn centre_at(&self, time: f64) -> Vec3 {
+        self.start + (self.end - self.start) * time.clamp(0.0, 1.0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    /// Check if a given [`Ray`] hits the sphere where it is at the ray's
+    /// time, like [`Sphere::hit()`].
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        hit_sphere(
+            self.centre_at(ray.time),
+            self.radius,
+            &*self.mat,
+            ray,
+            ray_t,
+        )
+    }
+
+    /// The bound of the motion between times `0.0` and `1.0`, which is all a
+    /// shutter within them can see.
     fn bound(&self) -> Aabb {
-        Aabb {
-            x: interval![
-                self.centre.e[0] - self.radius,
-                self.centre.e[0] + self.radius
-            ],
-            y: interval![
-                self.centre.e[1] - self.radius,
-                self.centre.e[1] + self.radius
-            ],
-            z: interval![
-                self.centre.e[2] - self.radius,
-                self.centre.e[2] + self.radius
-            ],
-        }
+        let mut aabb = sphere_bound(self.start, self.radius);
+        aabb.union(&sphere_bound(self.end, self.radius));
+        aabb
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.start += offset;
+        self.end += offset;
+        true
     }
 }
@@ -0,0 +1,433 @@
+//! This module contains [`RenderStats`], which records where a render spends
+//! its time by object and by material, to find the few things that cost the
+//! most without reaching for a profiler. Give objects a name with [`Named`] to
+//! tell them apart, and collect the stats with
+//! [`RenderOptions::set_stats()`](crate::camera::RenderOptions::set_stats()).
+//!
+//! Each bounce of a path is charged to what it hit: the time to find the hit
+//! and scatter from it, but not the bounces after. Materials are told apart by
+//! their settings, so copies of the same material on different objects add up
+//! together.
+
+use std::{collections::HashMap, fmt, sync::Mutex, time::Duration};
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    tolerance::Tolerances,
+    vec3::Vec3,
+};
+
+/// An object with a name, which the hits on it are reported under by
+/// [`RenderStats`]. When names are nested, the innermost is kept.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable, material::Dielectric, ray, ray::Ray, sphere::Sphere, stats::Named, vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let cup = Named::new(
+///     "glass cup",
+///     Sphere {
+///         centre: vec3![0.0, 0.0, -2.0],
+///         radius: 0.5,
+///         mat: Box::new(Dielectric::new(1.5)),
+///     },
+/// );
+///
+/// let rec = cup
+///     .nearest_hit_along(&ray!(vec3![0.0, 0.0, 0.0], vec3![0.0, 0.0, -1.0]))
+///     .unwrap();
+/// assert_eq!(rec.object, Some("glass cup"));
+/// ```
+#[derive(Debug)]
+pub struct Named<H: Hittable> {
+    name: String,
+    child: H,
+}
+
+impl<H: Hittable> Named<H> {
+    /// Give `child` a `name`.
+    #[must_use]
+    pub fn new<S: Into<String>>(name: S, child: H) -> Self {
+        Self {
+            name: name.into(),
+            child,
+        }
+    }
+
+    /// The name of the object.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<H: Hittable> Hittable for Named<H> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.child.hit(r, ray_t).map(|rec| HitRecord {
+            object: rec.object.or(Some(&self.name)),
+            ..rec
+        })
+    }
+
+    fn bound(&self) -> Aabb {
+        self.child.bound()
+    }
+
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        self.child.closest_point(point)
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.child.translate(offset)
+    }
+
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.child.set_tolerances(tolerances);
+    }
+}
+
+/// What a [`StatsReport`] is sorted by, largest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsOrder {
+    /// The number of [rays](Ray) that hit it.
+    Rays,
+    /// The time spent on it.
+    #[default]
+    Time,
+    /// The number of samples whose paths touched it.
+    Samples,
+}
+
+/// The totals for one object or material in a [`StatsReport`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatsEntry {
+    /// The name of the object, or the settings of the material.
+    pub name: String,
+    /// The number of [rays](Ray) that hit it.
+    pub rays: u64,
+    /// The time spent finding the hits on it and scattering from them.
+    pub time: Duration,
+    /// The number of samples whose paths touched it at least once.
+    pub samples: u64,
+}
+
+impl StatsEntry {
+    fn add(&mut self, other: &Self) {
+        self.rays += other.rays;
+        self.time += other.time;
+        self.samples += other.samples;
+    }
+}
+
+/// The stats of a render by object and by material, see
+/// [`RenderStats::report()`]. Objects without a [`Named`] wrapper are
+/// reported together as `(unnamed)`, and [rays](Ray) that hit nothing under
+/// [`StatsReport::background`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatsReport {
+    /// The totals for each named object.
+    pub objects: Vec<StatsEntry>,
+    /// The totals for each distinct material.
+    pub materials: Vec<StatsEntry>,
+    /// The totals for [rays](Ray) that hit nothing.
+    pub background: StatsEntry,
+    /// The number of samples taken.
+    pub samples: u64,
+}
+
+impl StatsReport {
+    /// Sort the objects and materials by `order`, largest first, then by
+    /// name.
+    pub fn sort_by(&mut self, order: StatsOrder) {
+        let key = |entry: &StatsEntry| match order {
+            StatsOrder::Rays => u128::from(entry.rays),
+            StatsOrder::Time => entry.time.as_nanos(),
+            StatsOrder::Samples => u128::from(entry.samples),
+        };
+        for entries in [&mut self.objects, &mut self.materials] {
+            entries.sort_by(|a, b| key(b).cmp(&key(a)).then_with(|| a.name.cmp(&b.name)));
+        }
+    }
+
+    /// The total time charged, including the background.
+    #[must_use]
+    pub fn total_time(&self) -> Duration {
+        self.objects
+            .iter()
+            .map(|entry| entry.time)
+            .sum::<Duration>()
+            + self.background.time
+    }
+
+    /// The fraction of [`StatsReport::total_time()`] spent on `entry`, or `0.0`
+    /// if no time was charged at all.
+    #[must_use]
+    pub fn time_share(&self, entry: &StatsEntry) -> f64 {
+        let total = self.total_time().as_secs_f64();
+        if total > 0.0 {
+            entry.time.as_secs_f64() / total
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[expect(clippy::cast_precision_loss)]
+        let table = |f: &mut fmt::Formatter<'_>, title: &str, entries: &[StatsEntry]| {
+            writeln!(
+                f,
+                "{title:<40} {:>12} {:>10} {:>7} {:>10}",
+                "rays", "time", "share", "samples"
+            )?;
+            for entry in entries {
+                let name: String = entry.name.chars().take(40).collect();
+                writeln!(
+                    f,
+                    "{name:<40} {:>12} {:>9.3}s {:>6.1}% {:>9.1}%",
+                    entry.rays,
+                    entry.time.as_secs_f64(),
+                    100.0 * self.time_share(entry),
+                    100.0 * entry.samples as f64 / self.samples.max(1) as f64,
+                )?;
+            }
+            Ok(())
+        };
+
+        table(f, "Object", &self.objects)?;
+        writeln!(f)?;
+        table(f, "Material", &self.materials)?;
+        writeln!(f)?;
+        table(f, "", std::slice::from_ref(&self.background))
+    }
+}
+
+/// What a bounce was charged to, while working out which a sample touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Charge {
+    Object(usize),
+    Material(usize),
+    Background,
+}
+
+/// The stats from one thread, merged into [`RenderStats`] as it goes.
+#[derive(Debug, Default)]
+pub(crate) struct StatsTally {
+    objects: Vec<StatsEntry>,
+    object_index: HashMap<String, usize>,
+    materials: Vec<StatsEntry>,
+    material_index: HashMap<String, usize>,
+    /// Material addresses to their index, so each is only described once.
+    material_addresses: HashMap<usize, usize>,
+    background: StatsEntry,
+    samples: u64,
+    touched: Vec<Charge>,
+}
+
+impl StatsTally {
+    /// The name unnamed objects are reported under.
+    const UNNAMED: &'static str = "(unnamed)";
+
+    /// Charge `time` and a [ray](Ray) to the object and material of `rec`.
+    pub(crate) fn record_hit(&mut self, rec: &HitRecord, time: Duration) {
+        let object = self.object(rec.object.unwrap_or(Self::UNNAMED));
+        let material = self.material(rec.mat);
+        for charge in [Charge::Object(object), Charge::Material(material)] {
+            let entry = self.entry(charge);
+            entry.rays += 1;
+            entry.time += time;
+            self.touched.push(charge);
+        }
+    }
+
+    /// Charge `time` and a [ray](Ray) to the background.
+    pub(crate) fn record_miss(&mut self, time: Duration) {
+        self.background.rays += 1;
+        self.background.time += time;
+        self.touched.push(Charge::Background);
+    }
+
+    /// Finish a sample, counting it once for everything its path touched.
+    pub(crate) fn end_sample(&mut self) {
+        self.samples += 1;
+        let mut touched = std::mem::take(&mut self.touched);
+        touched.sort_unstable();
+        touched.dedup();
+        for &charge in &touched {
+            self.entry(charge).samples += 1;
+        }
+        touched.clear();
+        self.touched = touched;
+    }
+
+    fn entry(&mut self, charge: Charge) -> &mut StatsEntry {
+        match charge {
+            Charge::Object(index) => &mut self.objects[index],
+            Charge::Material(index) => &mut self.materials[index],
+            Charge::Background => &mut self.background,
+        }
+    }
+
+    fn object(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.object_index.get(name) {
+            return index;
+        }
+        Self::insert(&mut self.objects, &mut self.object_index, name.to_string())
+    }
+
+    fn material(&mut self, mat: &dyn Material) -> usize {
+        let address = std::ptr::from_ref(mat).cast::<()>().addr();
+        if let Some(&index) = self.material_addresses.get(&address) {
+            return index;
+        }
+
+        let name = format!("{mat:?}");
+        let index = match self.material_index.get(&name) {
+            Some(&index) => index,
+            None => Self::insert(&mut self.materials, &mut self.material_index, name),
+        };
+        self.material_addresses.insert(address, index);
+        index
+    }
+
+    fn insert(
+        entries: &mut Vec<StatsEntry>,
+        index: &mut HashMap<String, usize>,
+        name: String,
+    ) -> usize {
+        entries.push(StatsEntry {
+            name: name.clone(),
+            ..StatsEntry::default()
+        });
+        index.insert(name, entries.len() - 1);
+        entries.len() - 1
+    }
+
+    /// Add everything from `other`.
+    fn merge(&mut self, other: &Self) {
+        for entry in &other.objects {
+            let index = self.object(&entry.name);
+            self.objects[index].add(entry);
+        }
+        for entry in &other.materials {
+            let index = match self.material_index.get(&entry.name) {
+                Some(&index) => index,
+                None => Self::insert(
+                    &mut self.materials,
+                    &mut self.material_index,
+                    entry.name.clone(),
+                ),
+            };
+            self.materials[index].add(entry);
+        }
+        self.background.add(&other.background);
+        self.samples += other.samples;
+    }
+}
+
+/// Collects the stats of renders, by object and by material, see the
+/// [module docs](crate::stats). Timing every bounce slows the render down a
+/// little, so only collect stats when they're wanted.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, CameraBuilder, RenderOptions},
+///     hit_list::HittableList,
+///     material::{Dielectric, Lambertian},
+///     sphere::Sphere,
+///     stats::{Named, RenderStats, StatsOrder},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let mut world = HittableList::new();
+/// world.add(Box::new(Named::new(
+///     "glass cup",
+///     Sphere {
+///         centre: vec3![0.0, 0.0, -2.0],
+///         radius: 1.0,
+///         mat: Box::new(Dielectric::new(1.5)),
+///     },
+/// )));
+/// world.add(Box::new(Named::new(
+///     "floor",
+///     Sphere {
+///         centre: vec3![0.0, -101.0, -2.0],
+///         radius: 100.0,
+///         mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+///     },
+/// )));
+///
+/// let camera = CameraBuilder::default()
+///     .set_image_width(16)
+///     .set_anti_aliasing(AntiAliasing::Random(4))
+///     .build().unwrap();
+/// let stats = RenderStats::new();
+/// let _film = camera.render_film_with(&world, RenderOptions::default().set_stats(&stats));
+///
+/// let mut report = stats.report();
+/// assert_eq!(report.samples, 16 * 16 * 4);
+///
+/// assert_eq!(report.objects.len(), 2);
+/// assert_eq!(report.materials.len(), 2);
+///
+/// report.sort_by(StatsOrder::Samples);
+/// let cup = report.objects.iter().find(|entry| entry.name == "glass cup").unwrap();
+/// assert!(cup.samples > 0 && cup.samples <= report.samples);
+/// assert!(report.time_share(cup) > 0.0);
+///
+/// assert!(report.materials.iter().any(|entry| entry.name.starts_with("Dielectric")));
+/// println!("{report}");
+/// ```
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    tally: Mutex<StatsTally>,
+}
+
+impl RenderStats {
+    /// Create a new, empty [`RenderStats`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the stats from one thread.
+    pub(crate) fn merge(&self, tally: &StatsTally) {
+        self.tally
+            .lock()
+            .expect("render stats lock poisoned")
+            .merge(tally);
+    }
+
+    /// Everything collected so far, sorted by [`StatsOrder::Time`].
+    ///
+    /// # Panics
+    ///
+    /// If a thread panicked while adding its stats.
+    #[must_use]
+    pub fn report(&self) -> StatsReport {
+        let tally = self.tally.lock().expect("render stats lock poisoned");
+        let mut report = StatsReport {
+            objects: tally.objects.clone(),
+            materials: tally.materials.clone(),
+            background: StatsEntry {
+                name: "(background)".to_string(),
+                ..tally.background.clone()
+            },
+            samples: tally.samples,
+        };
+        report.sort_by(StatsOrder::Time);
+        report
+    }
+}
@@ -0,0 +1,415 @@
+//! This module contains the [`Texture`] trait for colours that vary over a
+//! surface, the [`CheckerTexture`], [`ImageTexture`] and [`VertexColours`],
+//! and the [`Textured`]
+//! material wrapper which uses a texture as the albedo, or the emission, of
+//! any other [`Material`].
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::Result;
+use rand::rngs::SmallRng;
+use serde::Deserialize;
+
+use crate::{
+    film::{ColourSpace, FilmBuffer},
+    hit::HitRecord,
+    material::{LightEffect, Material, Visibility},
+    ray::Ray,
+    vec3,
+    vec3::Vec3,
+};
+
+/// This trait indicates a struct is a texture, a colour looked up from where
+/// on a surface a [ray](Ray) hit it.
+pub trait Texture: std::fmt::Debug + Sync + Send {
+    /// The colour at the hit with texture coordinates `u` and `v`, at the
+    /// point `p` in world space.
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+}
+
+/// Where a [`CheckerTexture`] lays out its squares.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum CheckerMapping {
+    /// Cubes filling world space, so the pattern is the same on every
+    /// surface whatever its texture coordinates, like a solid carved out of a
+    /// block of checkers.
+    #[default]
+    Solid,
+    /// Squares over the surface's texture coordinates, so the pattern
+    /// follows the surface. Surfaces without texture coordinates are one
+    /// colour.
+    Uv,
+}
+
+/// A [`Texture`] of alternating squares (or cubes) of two colours, the
+/// standard test pattern for judging perspective and focus.
+///
+/// Surfaces lying exactly on the faces of [solid](CheckerMapping::Solid)
+/// cubes, such as a floor at `y = 0.0`, speckle between the colours either
+/// side. Offset the surface slightly, or use a [`Ground`](crate::ground::Ground)
+/// for checkered floors.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{texture::{CheckerTexture, Texture}, vec3, vec3::Vec3};
+///
+/// let white = vec3![0.9, 0.9, 0.9];
+/// let black = vec3![0.1, 0.1, 0.1];
+///
+/// let solid = CheckerTexture::solid(white, black, 1.0);
+/// assert_eq!(solid.value(0.0, 0.0, vec3![0.5, 0.5, 0.5]), white);
+/// assert_eq!(solid.value(0.0, 0.0, vec3![1.5, 0.5, 0.5]), black);
+/// assert_eq!(solid.value(0.0, 0.0, vec3![-0.5, 0.5, 0.5]), black);
+///
+/// // ten squares across the texture
+/// let uv = CheckerTexture::uv(white, black, 0.1);
+/// assert_eq!(uv.value(0.05, 0.05, vec3![7.0, 0.0, 0.0]), white);
+/// assert_eq!(uv.value(0.15, 0.05, vec3![7.0, 0.0, 0.0]), black);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CheckerTexture {
+    even: Vec3,
+    odd: Vec3,
+    size: f64,
+    mapping: CheckerMapping,
+}
+
+impl CheckerTexture {
+    /// Create a [`CheckerTexture`] alternating between `even` and `odd`
+    /// squares `size` across, in world units for
+    /// [`CheckerMapping::Solid`] or texture coordinates for
+    /// [`CheckerMapping::Uv`]. The square at the origin is `even`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` isn't positive.
+    #[must_use]
+    pub fn new(even: Vec3, odd: Vec3, size: f64, mapping: CheckerMapping) -> Self {
+        assert!(size > 0.0, "Checker size must be positive");
+        Self {
+            even,
+            odd,
+            size,
+            mapping,
+        }
+    }
+
+    /// Create a [`CheckerTexture`] of cubes `size` across in world space,
+    /// see [`CheckerTexture::new()`].
+    ///
+    /// # Panics
+    ///
+    /// If `size` isn't positive.
+    #[must_use]
+    pub fn solid(even: Vec3, odd: Vec3, size: f64) -> Self {
+        Self::new(even, odd, size, CheckerMapping::Solid)
+    }
+
+    /// Create a [`CheckerTexture`] of squares `size` across in texture
+    /// coordinates, see [`CheckerTexture::new()`].
+    ///
+    /// # Panics
+    ///
+    /// If `size` isn't positive.
+    #[must_use]
+    pub fn uv(even: Vec3, odd: Vec3, size: f64) -> Self {
+        Self::new(even, odd, size, CheckerMapping::Uv)
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let cell = |x: f64| (x / self.size).floor();
+        let sum = match self.mapping {
+            CheckerMapping::Solid => cell(p[0]) + cell(p[1]) + cell(p[2]),
+            CheckerMapping::Uv => cell(u) + cell(v),
+        };
+        if sum.rem_euclid(2.0) >= 1.0 {
+            self.odd
+        } else {
+            self.even
+        }
+    }
+}
+
+/// How an [`ImageTexture`] reads colours between its pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum TextureFilter {
+    /// Blend the four nearest pixels, for smooth magnified textures.
+    #[default]
+    Bilinear,
+    /// Take the pixel the texture coordinates fall in, for crisp pixel art.
+    Nearest,
+}
+
+/// A [`Texture`] looked up from an image by the hit's texture coordinates,
+/// such as a model's colour map. The image tiles outside `0.0..1.0`, and
+/// `v = 0.0` is its bottom row, as in OBJ files.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     film::FilmBuffer,
+///     texture::{ImageTexture, Texture, TextureFilter},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let red = vec3![1.0, 0.0, 0.0];
+/// let blue = vec3![0.0, 0.0, 1.0];
+/// // red on top, blue underneath
+/// let film = FilmBuffer::from_rows(vec![vec![red], vec![blue]]);
+///
+/// let texture = ImageTexture::new(film).set_filter(TextureFilter::Nearest);
+/// let p = vec3![0.0, 0.0, 0.0];
+/// assert_eq!(texture.value(0.5, 0.75, p), red);
+/// assert_eq!(texture.value(0.5, 0.25, p), blue);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    film: FilmBuffer,
+    filter: TextureFilter,
+}
+
+impl ImageTexture {
+    /// Create an [`ImageTexture`] of the linear colours in `film`, filtered
+    /// bilinearly.
+    ///
+    /// # Panics
+    ///
+    /// If `film` is empty.
+    #[must_use]
+    pub fn new(film: FilmBuffer) -> Self {
+        assert!(
+            film.width() > 0 && film.height() > 0,
+            "Image texture can't be empty"
+        );
+        Self {
+            film,
+            filter: TextureFilter::default(),
+        }
+    }
+
+    /// Load an [`ImageTexture`] from an image file, decoding 8 and 16-bit
+    /// images from sRGB as colour maps are stored. See [`FilmBuffer::load()`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or decoded.
+    ///
+    /// # Panics
+    ///
+    /// If the image is empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(FilmBuffer::load(path, ColourSpace::Srgb)?))
+    }
+
+    /// Set how colours are read between pixels.
+    #[must_use]
+    pub fn set_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        match self.filter {
+            TextureFilter::Bilinear => self.film.sample_uv(u, v),
+            TextureFilter::Nearest => self.film.texel_uv(u, v),
+        }
+    }
+}
+
+/// A [`Texture`] blending the colours of a triangle's three corners, such as
+/// the per-vertex colours of a scan. Reads the hit's texture coordinates as
+/// the weights of the second and third corners, so the triangle must have
+/// [`BARYCENTRIC_UVS`](crate::triangle::BARYCENTRIC_UVS).
+#[derive(Debug, Clone)]
+pub struct VertexColours {
+    colours: [Vec3; 3],
+}
+
+impl VertexColours {
+    /// Create a [`VertexColours`] texture with the linear `colours` of each
+    /// corner.
+    #[must_use]
+    pub fn new(colours: [Vec3; 3]) -> Self {
+        Self { colours }
+    }
+}
+
+impl Texture for VertexColours {
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        let [a, b, c] = self.colours;
+        a * (1.0 - u - v) + b * u + c * v
+    }
+}
+
+/// Which property of a [`Textured`] material its [`Texture`] sets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum TextureChannel {
+    /// Tint whatever the material scatters or lets through, as its albedo.
+    #[default]
+    Albedo,
+    /// Tint the light the material emits, leaving what it scatters alone, for
+    /// textured lights such as screens and stained glass lamps.
+    Emission,
+}
+
+/// Uses a [`Texture`] as the albedo of any other [`Material`], by tinting
+/// whatever the material scatters or lets through by the texture's colour at
+/// the hit, or as its emission, see [`Textured::with_channel()`]. Wrap a
+/// white material to get exactly the texture's colours.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     material::{Lambertian, Metal},
+///     texture::{CheckerTexture, Textured},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let checker = CheckerTexture::solid(vec3![0.9, 0.9, 0.9], vec3![0.2, 0.3, 0.8], 0.5);
+///
+/// let floor = Textured::new(Box::new(checker), Box::new(Lambertian::new(vec3![1.0, 1.0, 1.0])));
+/// let tiles = Textured::new(Box::new(checker), Box::new(Metal::new(vec3![1.0, 1.0, 1.0], 0.1)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Textured {
+    texture: Arc<dyn Texture>,
+    mat: Arc<dyn Material>,
+    channel: TextureChannel,
+}
+
+impl Textured {
+    /// Create a [`Textured`] material tinting the albedo of `mat` by
+    /// `texture`.
+    #[must_use]
+    pub fn new(texture: Box<dyn Texture>, mat: Box<dyn Material>) -> Self {
+        Self {
+            texture: Arc::from(texture),
+            mat: Arc::from(mat),
+            channel: TextureChannel::default(),
+        }
+    }
+
+    /// Like [`Textured::new()`], but sharing `mat` with other materials, so
+    /// many textures over one material don't each copy it.
+    pub(crate) fn with_shared_material(texture: Box<dyn Texture>, mat: Arc<dyn Material>) -> Self {
+        Self {
+            texture: Arc::from(texture),
+            mat,
+            channel: TextureChannel::default(),
+        }
+    }
+
+    /// Set which property of the material the texture tints. With
+    /// [`TextureChannel::Emission`], a light such as a
+    /// [`DiffuseLight`](crate::material::DiffuseLight) emits its radiance
+    /// times the texture's colour, so an image on a white light is a screen
+    /// showing it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::HitRecord,
+    ///     material::{DiffuseLight, Material},
+    ///     ray,
+    ///     ray::Ray,
+    ///     texture::{CheckerTexture, TextureChannel, Textured},
+    ///     vec3,
+    ///     vec3::Vec3,
+    /// };
+    ///
+    /// // a lamp of red and blue panes, twice as bright as the texture
+    /// let panes = CheckerTexture::uv(vec3![1.0, 0.1, 0.1], vec3![0.1, 0.1, 1.0], 0.5);
+    /// let lamp = Textured::new(
+    ///     Box::new(panes),
+    ///     Box::new(DiffuseLight::new(vec3![2.0, 2.0, 2.0])),
+    /// )
+    /// .with_channel(TextureChannel::Emission);
+    ///
+    /// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+    /// let rec = |u| HitRecord {
+    ///     p: vec3![0.0, 0.0, 1.0],
+    ///     norm: vec3![0.0, 0.0, 1.0],
+    ///     mat: &lamp,
+    ///     t: 1.0,
+    ///     front_face: true,
+    ///     u,
+    ///     v: 0.25,
+    ///     object: None,
+    ///     layer: None,
+    ///     tangent: None,
+    /// };
+    ///
+    /// assert_eq!(lamp.emitted(&r, &rec(0.25)), vec3![2.0, 0.2, 0.2]);
+    /// assert_eq!(lamp.emitted(&r, &rec(0.75)), vec3![0.2, 0.2, 2.0]);
+    /// ```
+    #[must_use]
+    pub fn with_channel(self, channel: TextureChannel) -> Self {
+        Self { channel, ..self }
+    }
+
+    /// The tint of what the material scatters or lets through at the hit.
+    fn tint(&self, rec: &HitRecord) -> Vec3 {
+        match self.channel {
+            TextureChannel::Albedo => self.texture.value(rec.u, rec.v, rec.p),
+            TextureChannel::Emission => vec3![1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl Material for Textured {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let (scattered, attenuation) = self.mat.scatter(r_in, rec, rng);
+        (scattered, attenuation * self.tint(rec))
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        match self.channel {
+            TextureChannel::Albedo => self.mat.emitted(r_in, rec),
+            TextureChannel::Emission => {
+                self.mat.emitted(r_in, rec) * self.texture.value(rec.u, rec.v, rec.p)
+            }
+        }
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.mat.visibility()
+    }
+
+    fn is_diffuse(&self) -> bool {
+        self.mat.is_diffuse()
+    }
+
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.mat.transmittance(r_in, rec) * self.tint(rec)
+    }
+
+    fn fake_caustic(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.mat
+            .fake_caustic(r_in, rec)
+            .map(|through| through * self.tint(rec))
+    }
+
+    fn light_effect(&self) -> LightEffect {
+        self.mat.light_effect()
+    }
+
+    fn lambertian_albedo(&self, r_in: &Ray, rec: &HitRecord) -> Option<Vec3> {
+        self.mat
+            .lambertian_albedo(r_in, rec)
+            .map(|albedo| albedo * self.tint(rec))
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,301 @@
+//! This module contains the [`ThinFilm`] [material](Material), a coating a
+//! few hundred nanometres thick over a [base](FilmBase), whose reflections
+//! interfere into the iridescent colours of soap bubbles, oil slicks and
+//! tempered steel.
+
+use std::f64::consts::PI;
+
+use rand::{rngs::SmallRng, RngExt as _};
+
+use crate::{
+    hit::HitRecord,
+    material::Material,
+    noise::Noise,
+    ray,
+    ray::Ray,
+    vec3,
+    vec3::{dot, Vec3},
+};
+
+/// The wavelengths in nanometres each colour channel spans, red, green and
+/// blue, which are averaged over rather than taking one wavelength per
+/// channel, so films a few microns thick fade to white as real ones do
+/// instead of aliasing into stripes.
+const BANDS: [[f64; 2]; 3] = [[580.0, 700.0], [490.0, 580.0], [400.0, 490.0]];
+
+/// The wavelengths sampled in each band.
+const SAMPLES_PER_BAND: u32 = 16;
+
+/// What's under a [`ThinFilm`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FilmBase {
+    /// A dielectric with the given refractive index, which light that isn't
+    /// reflected passes into, bending as through a [`Dielectric`](crate::material::Dielectric).
+    /// An index of `1.0` is air on both sides, as for a soap bubble.
+    Dielectric(f64),
+    /// An opaque conductor, a metal, with the given reflectance at normal
+    /// incidence. Its reflectance at other angles is Schlick's fit, with the
+    /// half-wave phase shift of reflecting off a metal.
+    Conductor(Vec3),
+}
+
+/// A film of a dielectric coating a [`FilmBase`], reflecting light off its
+/// top and bottom. The two reflections interfere, strengthening or cancelling
+/// each wavelength depending on the film's thickness and the angle it's seen
+/// at, so a film much thinner than a micron shows bands of colour.
+///
+/// The interference is evaluated exactly for each polarisation, with the
+/// Airy formula for a film between two media, at several wavelengths across
+/// each colour channel, which are averaged. The film doesn't absorb, so what
+/// isn't reflected is transmitted into a dielectric base. Thickness can
+/// vary over the surface with [`ThinFilm::with_variation()`], for the swirls
+/// of a bubble.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     thin_film::{FilmBase, ThinFilm},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let p = vec3![0.0, 0.0, 0.0];
+///
+/// // without a film, glass reflects its usual 4% head on
+/// let bare = ThinFilm::new(0.0, 1.33, FilmBase::Dielectric(1.5));
+/// let plain = bare.reflectance(1.0, p);
+/// assert!((plain[0] - 0.04).abs() < 1e-9 && (plain[2] - 0.04).abs() < 1e-9);
+///
+/// // a soap bubble 400nm thick cancels out green, so looks magenta
+/// let bubble = ThinFilm::new(400.0, 1.33, FilmBase::Dielectric(1.0));
+/// let colour = bubble.reflectance(1.0, p);
+/// assert!(colour[0] > 5.0 * colour[1] && colour[2] > 5.0 * colour[1]);
+/// // and the colour shifts with the angle it's seen at
+/// let grazing = bubble.reflectance(0.5, p);
+/// assert!((grazing[1] - colour[1]).abs() > 0.05);
+///
+/// // whereas a thick film averages out to an even white
+/// let thick = ThinFilm::new(5000.0, 1.33, FilmBase::Dielectric(1.0)).reflectance(1.0, p);
+/// assert!((thick[0] - thick[1]).abs() < 0.005 && (thick[0] - thick[2]).abs() < 0.005);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ThinFilm {
+    /// In nanometres.
+    thickness: f64,
+    film_index: f64,
+    base: FilmBase,
+    /// How far the thickness strays either way, in nanometres.
+    variation: f64,
+    /// The size of the swirls of the variation.
+    scale: f64,
+    noise: Noise,
+}
+
+/// Assert a refractive index of a [`ThinFilm`] is positive and finite.
+fn assert_index(name: &str, index: f64) {
+    assert!(
+        index > 0.0 && index.is_finite(),
+        "{name} must be positive and finite"
+    );
+}
+
+/// The Fresnel amplitude reflection coefficients, s then p polarised, of
+/// light going from a medium with index `n_i` to one with index `n_t`, at
+/// angles with cosines `cos_i` and `cos_t` to the normal.
+fn fresnel(n_i: f64, cos_i: f64, n_t: f64, cos_t: f64) -> [f64; 2] {
+    [
+        (n_i * cos_i - n_t * cos_t) / (n_i * cos_i + n_t * cos_t),
+        (n_t * cos_i - n_i * cos_t) / (n_t * cos_i + n_i * cos_t),
+    ]
+}
+
+/// The cosine of the angle light at `cos_i` going from index `n_i` to `n_t`
+/// is bent to, by Snell's law, or [`None`] if it's totally reflected.
+fn refracted_cos(n_i: f64, cos_i: f64, n_t: f64) -> Option<f64> {
+    let sin2 = (n_i / n_t).powi(2) * (1.0 - cos_i * cos_i);
+    (sin2 <= 1.0).then(|| (1.0 - sin2).sqrt())
+}
+
+/// The reflectance of a non-absorbing film with amplitude coefficients
+/// `r12` on top and `r23` underneath, where the bottom reflection lags the
+/// top by a phase of `delta`.
+fn airy(r12: f64, r23: f64, delta: f64) -> f64 {
+    let cross = 2.0 * r12 * r23 * delta.cos();
+    (r12 * r12 + r23 * r23 + cross) / (1.0 + r12 * r12 * r23 * r23 + cross)
+}
+
+impl ThinFilm {
+    /// Create a new [`ThinFilm`] `thickness` nanometres thick, with the
+    /// refractive index `film_index`, over `base`. Soap is about `1.33` and
+    /// oil about `1.5`, and the bright colours are under a micron thick.
+    ///
+    /// # Panics
+    ///
+    /// If `thickness` is negative or not finite, or a refractive index isn't
+    /// positive and finite.
+    #[must_use]
+    pub fn new(thickness: f64, film_index: f64, base: FilmBase) -> Self {
+        assert!(
+            thickness >= 0.0 && thickness.is_finite(),
+            "Film thickness must be positive and finite"
+        );
+        assert_index("Film refractive index", film_index);
+        if let FilmBase::Dielectric(index) = base {
+            assert_index("Base refractive index", index);
+        }
+        Self {
+            thickness,
+            film_index,
+            base,
+            variation: 0.0,
+            scale: 1.0,
+            noise: Noise::default(),
+        }
+    }
+
+    /// Vary the thickness by up to `variation` nanometres either way over the
+    /// surface, in smooth swirls about `scale` across from the [`Noise`]
+    /// seeded with `seed`, so a bubble shows many bands of colour at once.
+    /// The film is never thinner than nothing.
+    ///
+    /// # Panics
+    ///
+    /// If `variation` is negative or not finite, or `scale` isn't positive and
+    /// finite.
+    #[must_use]
+    pub fn with_variation(self, variation: f64, scale: f64, seed: u64) -> Self {
+        assert!(
+            variation >= 0.0 && variation.is_finite(),
+            "Film variation must be positive and finite"
+        );
+        assert!(
+            scale > 0.0 && scale.is_finite(),
+            "Film variation scale must be positive and finite"
+        );
+        Self {
+            variation,
+            scale,
+            noise: Noise::new(seed),
+            ..self
+        }
+    }
+
+    /// The thickness of the film at `p`, in nanometres.
+    fn thickness_at(&self, p: Vec3) -> f64 {
+        if self.variation == 0.0 {
+            return self.thickness;
+        }
+        let swirl = 2.0 * self.noise.value(p / self.scale) - 1.0;
+        (self.thickness + self.variation * swirl).max(0.0)
+    }
+
+    /// The fraction of each colour of light reflected by the film at `p`,
+    /// arriving from outside at an angle with cosine `cos_theta` to the
+    /// normal.
+    #[must_use]
+    pub fn reflectance(&self, cos_theta: f64, p: Vec3) -> Vec3 {
+        self.stack_reflectance(1.0, self.base, cos_theta, self.thickness_at(p))
+    }
+
+    /// The reflectance of the film `thickness` nanometres thick between a
+    /// medium with index `n1`, the light arrives in at `cos1`, and `base`.
+    fn stack_reflectance(&self, n1: f64, base: FilmBase, cos1: f64, thickness: f64) -> Vec3 {
+        let white = vec3![1.0, 1.0, 1.0];
+        let n2 = self.film_index;
+        let Some(cos2) = refracted_cos(n1, cos1, n2) else {
+            return white;
+        };
+        let r12 = fresnel(n1, cos1, n2, cos2);
+        let r23 = match base {
+            FilmBase::Dielectric(n3) => {
+                let Some(cos3) = refracted_cos(n2, cos2, n3) else {
+                    return white;
+                };
+                [fresnel(n2, cos2, n3, cos3); 3]
+            }
+            FilmBase::Conductor(f0) => [0, 1, 2].map(|c| {
+                let r = -(f0[c] + (1.0 - f0[c]) * (1.0 - cos2).powi(5)).sqrt();
+                [r, r]
+            }),
+        };
+
+        let path = 4.0 * PI * n2 * thickness * cos2;
+        let mut out = Vec3::default();
+        for (c, [lo, hi]) in BANDS.into_iter().enumerate() {
+            let mut sum = 0.0;
+            for k in 0..SAMPLES_PER_BAND {
+                let wavelength =
+                    lo + (hi - lo) * (f64::from(k) + 0.5) / f64::from(SAMPLES_PER_BAND);
+                let delta = path / wavelength;
+                sum += airy(r12[0], r23[c][0], delta) + airy(r12[1], r23[c][1], delta);
+            }
+            out[c] = sum / f64::from(2 * SAMPLES_PER_BAND);
+        }
+        out
+    }
+
+    /// The reflectance of the hit, from whichever side of a dielectric base
+    /// the [ray](Ray) is on, and the ratio of refractive indices it's bent by
+    /// if it goes through.
+    fn reflectance_for(&self, r_in: &Ray, rec: &HitRecord) -> (Vec3, f64) {
+        let cos_theta = dot(&r_in.direction.unit(), &rec.norm).abs().min(1.0);
+        let thickness = self.thickness_at(rec.p);
+        match self.base {
+            FilmBase::Dielectric(index) if !rec.front_face => (
+                self.stack_reflectance(index, FilmBase::Dielectric(1.0), cos_theta, thickness),
+                index,
+            ),
+            FilmBase::Dielectric(index) => (
+                self.stack_reflectance(1.0, self.base, cos_theta, thickness),
+                1.0 / index,
+            ),
+            FilmBase::Conductor(_) => (
+                self.stack_reflectance(1.0, self.base, cos_theta, thickness),
+                0.0,
+            ),
+        }
+    }
+}
+
+impl Material for ThinFilm {
+    /// Scatter light [ray](Ray)s off the film, or through it into a
+    /// dielectric base, picked in proportion to the average reflectance,
+    /// with the attenuation weighted by the chance of the pick.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let unit_dir = r_in.direction.unit();
+        let (reflected, ri) = self.reflectance_for(r_in, rec);
+        let reflect = ray![rec.p, unit_dir.reflect(&rec.norm), r_in.time];
+        if let FilmBase::Conductor(_) = self.base {
+            return (reflect, reflected);
+        }
+
+        let chance = ((reflected[0] + reflected[1] + reflected[2]) / 3.0).clamp(0.0, 1.0);
+        if chance >= 1.0 || chance > rng.unwrap().random_range(0.0..1.0) {
+            (reflect, reflected / chance)
+        } else {
+            let through = vec3![1.0, 1.0, 1.0] - reflected;
+            (
+                ray![rec.p, unit_dir.refract(&rec.norm, ri), r_in.time],
+                through / (1.0 - chance),
+            )
+        }
+    }
+
+    fn is_diffuse(&self) -> bool {
+        false
+    }
+
+    /// Everything a dielectric base doesn't reflect, which is nothing for a
+    /// conductor.
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        match self.base {
+            FilmBase::Dielectric(_) => vec3![1.0, 1.0, 1.0] - self.reflectance_for(r_in, rec).0,
+            FilmBase::Conductor(_) => Vec3::default(),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
@@ -0,0 +1,119 @@
+//! This module contains [`Tolerances`], every numeric tolerance used to keep
+//! [ray](crate::ray::Ray) intersection robust in floating point, in one place.
+//!
+//! The defaults suit scenes around a unit to a few thousand units across. For
+//! much smaller or larger scenes, [scale](Tolerances::scaled()) them by the
+//! size of the scene relative to that, or give them in a scene file's
+//! settings:
+//!
+//! ```rust
+//! use rustrace::{
+//!     camera::RayBias, hit::Hittable, material::Lambertian, quad::Quad, scene::Scene, vec3,
+//!     vec3::Vec3,
+//! };
+//!
+//! let mut scene = Scene::parse_ron(
+//!     "(settings: (tolerances: (ray_bias: Fixed(0.1), bound_padding: 0.01)))",
+//!     ".",
+//! )
+//! .unwrap();
+//! assert_eq!(scene.settings.tolerances.ray_bias, RayBias::Fixed(0.1));
+//!
+//! // a flat floor added to the scene is padded by the scene's padding
+//! scene.add(Box::new(Quad::new(
+//!     vec3![0.0, 0.0, 0.0],
+//!     vec3![1.0, 0.0, 0.0],
+//!     vec3![0.0, 0.0, 1.0],
+//!     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+//! )));
+//! assert_eq!(scene.world().bound().y.size(), 0.01);
+//! ```
+//!
+//! Primitives keep the tolerances they intersect with, starting with the
+//! defaults. A [`Scene`](crate::scene::Scene) gives those of its settings to
+//! every object [added](crate::scene::Scene::add()) to it, see
+//! [`Hittable::set_tolerances()`](crate::hit::Hittable::set_tolerances()), so
+//! scenes with different tolerances can be built and rendered side by side.
+
+use serde::Deserialize;
+
+use crate::camera::RayBias;
+
+/// The numeric tolerances of intersection, see the [module docs](crate::tolerance).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{camera::RayBias, tolerance::Tolerances};
+///
+/// // a scene modelled in millimetres, a thousand times the usual size
+/// let tolerances = Tolerances::default().scaled(1000.0);
+///
+/// assert_eq!(tolerances.ray_bias, RayBias::Fixed(1.0));
+/// assert_eq!(tolerances.bound_padding, 0.1);
+/// assert_eq!(tolerances.triangle_det, Tolerances::default().triangle_det * 1e6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tolerances {
+    /// Below this size, the determinant of the Möller-Trumbore test counts a
+    /// [ray](crate::ray::Ray) as parallel to a triangle, so it misses rather
+    /// than hitting at a wildly imprecise point. The determinant is the
+    /// product of two edges and the ray direction, so it scales with the
+    /// square of the scene size.
+    pub triangle_det: f64,
+    /// How far before the exact intersection triangle hit points are placed,
+    /// along the ray, so they're on the side the ray came from. Scales with
+    /// the scene size.
+    pub hit_offset: f64,
+    /// The thickness given to the bounds of flat primitives (quads, discs
+    /// and axis-aligned planes) along the axes they lie flat in, so bounding
+    /// boxes never have zero volume (see
+    /// [`Aabb::padded()`](crate::aabb::Aabb::padded())). Scales with the
+    /// scene size.
+    pub bound_padding: f64,
+    /// The minimum `t` accepted for a hit, to stop rays leaving a surface
+    /// hitting it again. Scene cameras without their own use it, as do
+    /// queries on the scene. A [fixed](RayBias::Fixed) bias scales with the
+    /// scene size, an [automatic](RayBias::Auto) one scales itself.
+    pub ray_bias: RayBias,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Tolerances {
+    /// The default tolerances.
+    pub const DEFAULT: Self = Self {
+        triangle_det: f64::EPSILON,
+        hit_offset: f64::EPSILON,
+        bound_padding: 1e-4,
+        ray_bias: RayBias::DEFAULT,
+    };
+
+    /// Scale the tolerances for a scene `scale` times the usual size, each by
+    /// how it grows with the scene (see the fields).
+    ///
+    /// # Panics
+    ///
+    /// If `scale` isn't positive and finite.
+    #[must_use]
+    pub fn scaled(self, scale: f64) -> Self {
+        assert!(
+            scale > 0.0 && scale.is_finite(),
+            "Tolerance scale must be positive and finite"
+        );
+        Self {
+            triangle_det: self.triangle_det * scale * scale,
+            hit_offset: self.hit_offset * scale,
+            bound_padding: self.bound_padding * scale,
+            ray_bias: match self.ray_bias {
+                RayBias::Fixed(bias) => RayBias::Fixed(bias * scale),
+                RayBias::Auto => RayBias::Auto,
+            },
+        }
+    }
+}
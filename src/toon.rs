@@ -0,0 +1,344 @@
+//! This module contains the [`Toon`] settings for non-photorealistic renders
+//! with [`Camera::render_toon()`]: flat colours lit in a few hard-edged
+//! bands by a single light, an optional rim light, and ink outlines around
+//! objects, creases and depth jumps, as in cel-shaded animation.
+//!
+//! Surfaces are shaded by the colour of their [material](crate::material::Material)
+//! alone, without any bounces, so a toon render takes a fraction of the time
+//! of a path traced one.
+
+use rand::rngs::SmallRng;
+
+use crate::{
+    camera::Camera,
+    film::FilmBuffer,
+    hit::Hittable,
+    interval,
+    interval::Interval,
+    ray,
+    ray::Ray,
+    utils::deg_to_rad,
+    vec3,
+    vec3::{dot, Vec3},
+};
+
+/// The ink lines of a [`Toon`] render. Lines are drawn where neighbouring
+/// pixels see different objects or materials, or nothing, and within a
+/// surface where it creases or jumps in depth.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Outline {
+    /// The colour of the lines.
+    pub colour: Vec3,
+    /// The angle in degrees between the surface normals of neighbouring
+    /// pixels over which they're either side of a crease.
+    pub crease_angle: f64,
+    /// How far the surfaces of neighbouring pixels are off each other's
+    /// planes, relative to the depth of the nearer, over which they're
+    /// either side of a jump in depth. Measuring off the planes rather than
+    /// along the view keeps lines from smearing over surfaces seen edge on,
+    /// such as a ground towards the horizon.
+    pub depth_ratio: f64,
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Self {
+            colour: Vec3::default(),
+            crease_angle: 30.0,
+            depth_ratio: 0.05,
+        }
+    }
+}
+
+/// What the centre of a pixel saw, for finding [`Outline`]s.
+#[derive(Copy, Clone, Debug)]
+struct OutlineSurface {
+    /// The distance from the camera.
+    depth: f64,
+    p: Vec3,
+    norm: Vec3,
+    /// The addresses of the material and the name of the object, if it has
+    /// one, which tell objects apart.
+    material: usize,
+    object: Option<usize>,
+}
+
+impl Outline {
+    /// Whether two pixels seeing `a` and `b` are either side of a line.
+    fn between(&self, a: Option<OutlineSurface>, b: Option<OutlineSurface>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let gap = b.p - a.p;
+                let off_plane = dot(&gap, &a.norm).abs().max(dot(&gap, &b.norm).abs());
+                a.material != b.material
+                    || a.object != b.object
+                    || dot(&a.norm, &b.norm) < deg_to_rad(self.crease_angle).cos()
+                    || off_plane > self.depth_ratio * a.depth.min(b.depth)
+            }
+            (None, None) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Settings for a non-photorealistic [`Camera::render_toon()`].
+///
+/// Each surface takes the colour of its material, lit by a single distant
+/// light from `light`: the cosine of the light's angle to the surface is
+/// rounded up to one of a few [bands](Toon::with_bands()), over an
+/// [ambient](Toon::with_ambient()) floor, with hard shadows. Lights and
+/// other emitters show their emission on top.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::{AntiAliasing, Background, CameraBuilder},
+///     hit_list::HittableList,
+///     material::Lambertian,
+///     sphere::Sphere,
+///     toon::Toon,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let mut world = HittableList::new();
+/// world.add(Box::new(Sphere {
+///     centre: vec3![0.0, 0.0, -3.0],
+///     radius: 1.0,
+///     mat: Box::new(Lambertian::new(vec3![1.0, 0.2, 0.2])),
+/// }));
+///
+/// let camera = CameraBuilder::default()
+///     .set_image_width(64)
+///     .set_aspect_ratio(1.0)
+///     .set_anti_aliasing(AntiAliasing::Random(4))
+///     .set_background(Background::Solid(vec3![1.0, 1.0, 1.0]))
+///     .build().unwrap();
+///
+/// // lit from the upper right in two bands, with a white rim
+/// let toon = Toon::new(vec3![1.0, 1.0, 1.0])
+///     .with_bands(2)
+///     .with_ambient(0.2)
+///     .with_rim(vec3![1.0, 1.0, 1.0], 0.1);
+/// let film = camera.render_toon(&world, &toon);
+///
+/// // the sphere is flat red in a handful of shades
+/// let mut reds: Vec<u64> = film
+///     .pixels()
+///     .iter()
+///     .filter(|c| c[1] < 0.5)
+///     .map(|c| (c[0] * 1000.0).round() as u64)
+///     .collect();
+/// reds.sort_unstable();
+/// reds.dedup();
+/// assert!(reds.contains(&200) && reds.contains(&600) && reds.contains(&1000));
+///
+/// // and outlined in black against the background
+/// assert_eq!(film.get(32, 0), vec3![1.0, 1.0, 1.0]);
+/// let outlined = (0..32).any(|x| film.get(x, 32) == Vec3::default());
+/// assert!(outlined);
+///
+/// // which can be turned off
+/// let plain = camera.render_toon(&world, &toon.with_outline(None));
+/// assert!((0..64).all(|x| plain.get(x, 32) != Vec3::default()));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Toon {
+    /// The unit direction towards the light.
+    light: Vec3,
+    light_colour: Vec3,
+    bands: u32,
+    ambient: f64,
+    shadows: bool,
+    /// The rim's colour and width.
+    rim: Option<(Vec3, f64)>,
+    outline: Option<Outline>,
+}
+
+impl Toon {
+    /// Create new [`Toon`] settings lit by a white light from the direction
+    /// `light`, in three bands over an ambient `0.2`, with shadows and black
+    /// [`Outline`]s but no rim.
+    ///
+    /// # Panics
+    ///
+    /// If `light` is zero.
+    #[must_use]
+    pub fn new(light: Vec3) -> Self {
+        assert!(!light.near_zero(), "Toon light direction must not be zero");
+        Self {
+            light: light.unit(),
+            light_colour: vec3![1.0, 1.0, 1.0],
+            bands: 3,
+            ambient: 0.2,
+            shadows: true,
+            rim: None,
+            outline: Some(Outline::default()),
+        }
+    }
+
+    /// Set the colour of the light, which the surfaces' colours are
+    /// multiplied by.
+    #[must_use]
+    pub fn with_light_colour(self, light_colour: Vec3) -> Self {
+        Self {
+            light_colour,
+            ..self
+        }
+    }
+
+    /// Set how many bands of light there are between the ambient and fully
+    /// lit. One band is simply lit or not.
+    ///
+    /// # Panics
+    ///
+    /// If `bands` is `0`.
+    #[must_use]
+    pub fn with_bands(self, bands: u32) -> Self {
+        assert!(bands > 0, "Need at least one band");
+        Self { bands, ..self }
+    }
+
+    /// Set how bright surfaces facing away from the light or in shadow are,
+    /// as a fraction of fully lit.
+    ///
+    /// # Panics
+    ///
+    /// If `ambient` isn't between `0.0` and `1.0`.
+    #[must_use]
+    pub fn with_ambient(self, ambient: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&ambient),
+            "Ambient must be between 0 and 1"
+        );
+        Self { ambient, ..self }
+    }
+
+    /// Set whether surfaces shadow each other from the light.
+    #[must_use]
+    pub fn with_shadows(self, shadows: bool) -> Self {
+        Self { shadows, ..self }
+    }
+
+    /// Add `colour` around the silhouettes of surfaces, where the cosine of
+    /// the angle they're seen at is below `width`, for a hard-edged rim
+    /// light.
+    ///
+    /// # Panics
+    ///
+    /// If `width` isn't between `0.0` and `1.0`.
+    #[must_use]
+    pub fn with_rim(self, colour: Vec3, width: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&width),
+            "Rim width must be between 0 and 1"
+        );
+        Self {
+            rim: Some((colour, width)),
+            ..self
+        }
+    }
+
+    /// Set the ink lines, or [`None`] for none.
+    #[must_use]
+    pub fn with_outline(self, outline: Option<Outline>) -> Self {
+        Self { outline, ..self }
+    }
+
+    /// How lit a surface is with the cosine `cos_light` of the angle to the
+    /// light, rounded up to a band.
+    fn brightness(&self, cos_light: f64) -> f64 {
+        let bands = f64::from(self.bands);
+        let band = (cos_light.clamp(0.0, 1.0) * bands).ceil() / bands;
+        self.ambient + (1.0 - self.ambient) * band
+    }
+}
+
+impl Camera {
+    /// Render `world` in a flat, cel-shaded style by `toon`, see [`Toon`].
+    /// Takes the [`Camera`]'s samples per pixel for anti-aliasing, but
+    /// doesn't bounce, so the max depth, and any depth of field blur other
+    /// than from the lens, are ignored. The outlines are a pixel either side
+    /// of each edge.
+    ///
+    /// # Panics
+    ///
+    /// If the camera takes no samples per pixel. May panic if unable to get or
+    /// use the system random.
+    #[must_use]
+    pub fn render_toon(&self, world: &dyn Hittable, toon: &Toon) -> FilmBuffer {
+        let min_t = self.ray_bias.resolve(&world.bound());
+
+        let shade = |r: &Ray, rng: &mut SmallRng| {
+            let Some(rec) = world.hit(r, interval![min_t, f64::INFINITY]) else {
+                return self.background.radiance(r.direction);
+            };
+            let base = rec
+                .mat
+                .lambertian_albedo(r, &rec)
+                .unwrap_or_else(|| rec.mat.scatter(r, &rec, Some(rng)).1);
+            let shadowed = toon.shadows
+                && world
+                    .hit(
+                        &ray!(rec.p, toon.light, r.time),
+                        interval![min_t, f64::INFINITY],
+                    )
+                    .is_some();
+            let cos_light = if shadowed {
+                0.0
+            } else {
+                dot(&rec.norm, &toon.light)
+            };
+
+            let mut colour =
+                base * toon.light_colour * toon.brightness(cos_light) + rec.mat.emitted(r, &rec);
+            if let Some((rim, width)) = toon.rim {
+                if dot(&rec.norm, &r.direction.unit()).abs() < width {
+                    colour += rim;
+                }
+            }
+            colour
+        };
+
+        let mut rows = self.shade_samples(shade);
+
+        if let Some(outline) = toon.outline {
+            self.draw_outlines(world, &outline, &mut rows);
+        }
+        FilmBuffer::from_rows(rows).with_dither(self.dither)
+    }
+
+    /// Colour the pixels of `rows` either side of each [`Outline`] edge.
+    fn draw_outlines(&self, world: &dyn Hittable, outline: &Outline, rows: &mut [Vec<Vec3>]) {
+        let surfaces = self.first_hits(world, |r, rec| OutlineSurface {
+            depth: rec.t * r.direction.length(),
+            p: rec.p,
+            norm: rec.norm,
+            material: std::ptr::from_ref(rec.mat).cast::<()>().addr(),
+            object: rec.object.map(|name| name.as_ptr().addr()),
+        });
+
+        let mut edges = vec![vec![false; self.image_width as usize]; surfaces.len()];
+        for (j, row) in surfaces.iter().enumerate() {
+            for (i, &surface) in row.iter().enumerate() {
+                let right = row.get(i + 1).map(|&other| (j, i + 1, other));
+                let below = surfaces.get(j + 1).map(|next| (j + 1, i, next[i]));
+                for (y, x, other) in right.into_iter().chain(below) {
+                    if outline.between(surface, other) {
+                        edges[j][i] = true;
+                        edges[y][x] = true;
+                    }
+                }
+            }
+        }
+
+        for (row, edge_row) in rows.iter_mut().zip(edges) {
+            for (pixel, edge) in row.iter_mut().zip(edge_row) {
+                if edge {
+                    *pixel = outline.colour;
+                }
+            }
+        }
+    }
+}
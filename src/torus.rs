@@ -0,0 +1,197 @@
+//! Contains the [`Torus`] struct which models a ring doughnut, intersected
+//! exactly by solving a quartic.
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    utils::polynomial_roots,
+    vec3,
+    vec3::{cross, dot, Vec3},
+};
+
+/// A torus about [`centre`](field@Torus::centre), made by sweeping a circle of
+/// radius [`minor`](field@Torus::minor) around a ring of radius
+/// [`major`](field@Torus::major) at right angles to
+/// [`axis`](field@Torus::axis).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable,
+///     material::Lambertian,
+///     ray,
+///     ray::Ray,
+///     torus::Torus,
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// // lying flat, with the hole looking up
+/// let ring = Torus::new(
+///     vec3![0.0, 0.0, 0.0],
+///     vec3![0.0, 1.0, 0.0],
+///     2.0,
+///     0.5,
+///     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// );
+///
+/// // across the ring, hitting the outside of the tube
+/// let rec = ring
+///     .nearest_hit_along(&ray!(vec3![-5.0, 0.0, 0.0], vec3![1.0, 0.0, 0.0]))
+///     .unwrap();
+/// assert!((rec.p - vec3![-2.5, 0.0, 0.0]).length() < 1e-9);
+/// assert!((rec.norm - vec3![-1.0, 0.0, 0.0]).length() < 1e-9);
+///
+/// // straight down through the hole
+/// assert!(ring
+///     .nearest_hit_along(&ray!(vec3![0.0, 5.0, 0.0], vec3![0.0, -1.0, 0.0]))
+///     .is_none());
+///
+/// assert_eq!(ring.bound().y.size(), 1.0);
+/// ```
+#[derive(Debug)]
+pub struct Torus {
+    /// The centre of the hole.
+    pub centre: Vec3,
+    /// The unit axis through the hole.
+    pub axis: Vec3,
+    /// The radius of the ring through the middle of the tube.
+    pub major: f64,
+    /// The radius of the tube.
+    pub minor: f64,
+    /// The [`Material`] of the torus.
+    pub mat: Box<dyn Material>,
+}
+
+impl Torus {
+    /// Create a new [`Torus`] about `centre` with its hole along `axis`, which
+    /// is normalised.
+    ///
+    /// # Panics
+    ///
+    /// If `axis` is zero.
+    #[must_use]
+    pub fn new(centre: Vec3, axis: Vec3, major: f64, minor: f64, mat: Box<dyn Material>) -> Self {
+        assert!(!axis.near_zero(), "Torus axis can't be zero");
+        Self {
+            centre,
+            axis: axis.unit(),
+            major,
+            minor,
+            mat,
+        }
+    }
+
+    /// `point` relative to the centre, in a frame with the axis along `+Y`.
+    fn to_local(&self, point: Vec3) -> Vec3 {
+        let (tangent, bitangent) = self.axis.orthonormal_basis();
+        vec3![
+            dot(&point, &tangent),
+            dot(&point, &self.axis),
+            dot(&point, &bitangent)
+        ]
+    }
+
+    /// The closest point on the ring through the middle of the tube to `point`,
+    /// or any point on it if `point` is on the axis.
+    fn nearest_on_ring(&self, point: Vec3) -> Vec3 {
+        let offset = point - self.centre;
+        let flat = offset - self.axis * dot(&offset, &self.axis);
+        let out = if flat.near_zero() {
+            self.axis.orthonormal_basis().0
+        } else {
+            flat.unit()
+        };
+        self.centre + out * self.major
+    }
+}
+
+impl Hittable for Torus {
+    /// Solve `(|p|² + R² - r²)² = 4R²(x² + z²)` along the [ray](Ray) in the
+    /// torus's frame, starting from where the ray enters the bounds to keep
+    /// the coefficients well conditioned.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let inside = self.bound().ray_interval(ray)?;
+        let (start, end) = (inside.min.max(ray_t.min), inside.max.min(ray_t.max));
+        if start > end {
+            return None;
+        }
+
+        // a unit direction from the entry point, so the roots are distances
+        let speed = ray.direction.length();
+        let origin = self.to_local(ray.at(start) - self.centre);
+        let dir = self.to_local(ray.direction) / speed;
+
+        let major_sq = self.major * self.major;
+        let along = dot(&origin, &dir);
+        let k = origin.length_squared() + major_sq - self.minor * self.minor;
+        let flat_dir = dir[0] * dir[0] + dir[2] * dir[2];
+        let flat_along = origin[0] * dir[0] + origin[2] * dir[2];
+        let flat_origin = origin[0] * origin[0] + origin[2] * origin[2];
+        let coeffs = [
+            k * k - 4.0 * major_sq * flat_origin,
+            4.0 * along * k - 8.0 * major_sq * flat_along,
+            4.0 * along * along + 2.0 * k - 4.0 * major_sq * flat_dir,
+            4.0 * along,
+            1.0,
+        ];
+
+        let distance = polynomial_roots(&coeffs, 0.0, (end - start) * speed)
+            .into_iter()
+            .find(|&distance| ray_t.surrounds(start + distance / speed))?;
+        let t = start + distance / speed;
+
+        let p = ray.at(t);
+        let ring = self.nearest_on_ring(p);
+        let outward_norm = (p - ring).unit();
+        let mut rec = HitRecord {
+            t,
+            p,
+            norm: outward_norm,
+            mat: &*self.mat,
+            front_face: false,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+            layer: None,
+            // around the ring
+            tangent: Some(cross(&self.axis, &(ring - self.centre))),
+        };
+        rec.set_face_norm(ray, &outward_norm);
+        Some(rec)
+    }
+
+    /// Along each axis the ring reaches `major * sqrt(1 - a²)`, where `a` is
+    /// that component of the axis, and the tube adds `minor` all round.
+    fn bound(&self) -> Aabb {
+        let extent = |i: usize| {
+            let reach =
+                self.major * (1.0 - self.axis[i] * self.axis[i]).max(0.0).sqrt() + self.minor;
+            Interval::new(self.centre[i] - reach, self.centre[i] + reach)
+        };
+        Aabb {
+            x: extent(0),
+            y: extent(1),
+            z: extent(2),
+        }
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.centre += offset;
+        true
+    }
+
+    /// The closest point on the ring, pushed out to the surface of the tube.
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        let ring = self.nearest_on_ring(point);
+        let out = point - ring;
+        if out.near_zero() {
+            return Some(ring + self.axis * self.minor);
+        }
+        Some(ring + out.unit() * self.minor)
+    }
+}
@@ -0,0 +1,689 @@
+//! This module contains the affine [`Transform`] struct, the [`Transformed`]
+//! hittable, which places a [`Hittable`] with a fixed [`Transform`], and the
+//! [`Animated`] hittable, which moves a [`Hittable`] through a set of
+//! [`Keyframe`]s sampled at each [ray](Ray)'s time for transform motion blur.
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval,
+    interval::Interval,
+    ray,
+    ray::Ray,
+    tolerance::Tolerances,
+    utils::deg_to_rad,
+    vec3,
+    vec3::Vec3,
+};
+
+/// Number of intermediate samples taken between two [`Keyframe`]s when bounding
+/// an [`Animated`] object, so that rotations are covered by the [`Aabb`].
+const BOUND_STEPS: u32 = 8;
+
+/// A row-major 3x3 matrix.
+type Mat3 = [[f64; 3]; 3];
+
+const IDENTITY: Mat3 = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// An affine transform made of a linear part and a translation. The inverse is
+/// kept alongside so [ray](Ray)s can be taken into object space cheaply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: Mat3,
+    inv: Mat3,
+    offset: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    /// The identity [`Transform`], which leaves everything where it is.
+    #[must_use]
+    pub fn identity() -> Self {
+        Self {
+            m: IDENTITY,
+            inv: IDENTITY,
+            offset: vec3![0.0, 0.0, 0.0],
+        }
+    }
+
+    /// A [`Transform`] that translates by `offset`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{transform::Transform, vec3, vec3::Vec3};
+    ///
+    /// let t = Transform::translate(vec3![1.0, 2.0, 3.0]);
+    ///
+    /// assert_eq!(t.point(vec3![0.0, 0.0, 0.0]), vec3![1.0, 2.0, 3.0]);
+    /// // Directions are unaffected by translation.
+    /// assert_eq!(t.vector(vec3![1.0, 0.0, 0.0]), vec3![1.0, 0.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn translate(offset: Vec3) -> Self {
+        Self {
+            offset,
+            ..Self::identity()
+        }
+    }
+
+    /// A [`Transform`] that scales each axis by the matching element of
+    /// `factors`.
+    ///
+    /// # Panics
+    ///
+    /// If any of the `factors` is `0.0`, as the transform wouldn't be invertible.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{transform::Transform, vec3, vec3::Vec3};
+    ///
+    /// let t = Transform::scale(vec3![2.0, 3.0, 4.0]);
+    ///
+    /// assert_eq!(t.point(vec3![1.0, 1.0, 1.0]), vec3![2.0, 3.0, 4.0]);
+    /// assert_eq!(t.inverse().point(vec3![2.0, 3.0, 4.0]), vec3![1.0, 1.0, 1.0]);
+    /// ```
+    #[must_use]
+    pub fn scale(factors: Vec3) -> Self {
+        assert!(
+            factors.e.iter().all(|&f| f != 0.0),
+            "Scale factors may not be zero"
+        );
+        Self {
+            m: [
+                [factors[0], 0.0, 0.0],
+                [0.0, factors[1], 0.0],
+                [0.0, 0.0, factors[2]],
+            ],
+            inv: [
+                [1.0 / factors[0], 0.0, 0.0],
+                [0.0, 1.0 / factors[1], 0.0],
+                [0.0, 0.0, 1.0 / factors[2]],
+            ],
+            offset: vec3![0.0, 0.0, 0.0],
+        }
+    }
+
+    /// A [`Transform`] that rotates by `degrees` counter-clockwise around the
+    /// (not necessarily unit) `axis`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{transform::Transform, vec3, vec3::Vec3};
+    ///
+    /// let t = Transform::rotate(vec3![0.0, 1.0, 0.0], 90.0);
+    /// let p = t.point(vec3![1.0, 0.0, 0.0]);
+    ///
+    /// assert!((p - vec3![0.0, 0.0, -1.0]).length() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn rotate(axis: Vec3, degrees: f64) -> Self {
+        let a = axis.unit();
+        let (sin, cos) = deg_to_rad(degrees).sin_cos();
+        let k = 1.0 - cos;
+
+        // Rodrigues' rotation formula
+        let m = [
+            [
+                cos + a[0] * a[0] * k,
+                a[0] * a[1] * k - a[2] * sin,
+                a[0] * a[2] * k + a[1] * sin,
+            ],
+            [
+                a[1] * a[0] * k + a[2] * sin,
+                cos + a[1] * a[1] * k,
+                a[1] * a[2] * k - a[0] * sin,
+            ],
+            [
+                a[2] * a[0] * k - a[1] * sin,
+                a[2] * a[1] * k + a[0] * sin,
+                cos + a[2] * a[2] * k,
+            ],
+        ];
+
+        Self {
+            m,
+            // rotations are orthogonal
+            inv: transpose(&m),
+            offset: vec3![0.0, 0.0, 0.0],
+        }
+    }
+
+    /// A [`Transform`] that rotates by the Euler angles (in degrees) `degrees`,
+    /// applied around the X, then Y, then Z axes.
+    #[must_use]
+    pub fn rotate_euler(degrees: Vec3) -> Self {
+        Self::rotate(vec3![1.0, 0.0, 0.0], degrees[0])
+            .then(&Self::rotate(vec3![0.0, 1.0, 0.0], degrees[1]))
+            .then(&Self::rotate(vec3![0.0, 0.0, 1.0], degrees[2]))
+    }
+
+    /// A [`Transform`] from a general row-major linear part `m`, then a
+    /// translation by `offset`. Returns [`None`] if `m` can't be inverted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{transform::Transform, vec3, vec3::Vec3};
+    ///
+    /// // a shear along X
+    /// let shear = [[1.0, 1.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    /// let t = Transform::from_matrix(shear, vec3![0.0, 0.0, 1.0]).unwrap();
+    ///
+    /// assert_eq!(t.point(vec3![0.0, 2.0, 0.0]), vec3![2.0, 2.0, 1.0]);
+    /// assert_eq!(t.inverse().point(vec3![2.0, 2.0, 1.0]), vec3![0.0, 2.0, 0.0]);
+    ///
+    /// let flat = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]];
+    /// assert!(Transform::from_matrix(flat, vec3![0.0, 0.0, 0.0]).is_none());
+    /// ```
+    #[must_use]
+    pub fn from_matrix(m: [[f64; 3]; 3], offset: Vec3) -> Option<Self> {
+        // the inverse is the transposed cofactors over the determinant
+        let cofactor = |i: usize, j: usize| {
+            let (r0, r1) = ((i + 1) % 3, (i + 2) % 3);
+            let (c0, c1) = ((j + 1) % 3, (j + 2) % 3);
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        let det: f64 = (0..3).map(|j| m[0][j] * cofactor(0, j)).sum();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let mut inv = [[0.0; 3]; 3];
+        for (i, row) in inv.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = cofactor(j, i) / det;
+            }
+        }
+        Some(Self { m, inv, offset })
+    }
+
+    /// Compose two [`Transform`]s, giving one that applies `self` and then
+    /// `next`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{transform::Transform, vec3, vec3::Vec3};
+    ///
+    /// let t = Transform::scale(vec3![2.0, 2.0, 2.0])
+    ///     .then(&Transform::translate(vec3![1.0, 0.0, 0.0]));
+    ///
+    /// assert_eq!(t.point(vec3![1.0, 1.0, 1.0]), vec3![3.0, 2.0, 2.0]);
+    /// ```
+    #[must_use]
+    pub fn then(&self, next: &Transform) -> Self {
+        Self {
+            m: mat_mul(&next.m, &self.m),
+            inv: mat_mul(&self.inv, &next.inv),
+            offset: mat_vec(&next.m, self.offset) + next.offset,
+        }
+    }
+
+    /// Get the inverse of `self`.
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.inv,
+            inv: self.m,
+            offset: -mat_vec(&self.inv, self.offset),
+        }
+    }
+
+    /// Transform a point.
+    #[must_use]
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        mat_vec(&self.m, p) + self.offset
+    }
+
+    /// Transform a direction, ignoring the translation.
+    #[must_use]
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        mat_vec(&self.m, v)
+    }
+
+    /// Transform a surface normal using the inverse transpose, so it stays
+    /// perpendicular to the transformed surface. The result isn't normalised.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{transform::Transform, vec3, vec3::{Vec3, dot}};
+    ///
+    /// // Squash a 45 degree slope so it becomes steeper.
+    /// let t = Transform::scale(vec3![1.0, 2.0, 1.0]);
+    /// let tangent = t.vector(vec3![1.0, 1.0, 0.0]);
+    /// let norm = t.normal(vec3![1.0, -1.0, 0.0]);
+    ///
+    /// assert_eq!(dot(&tangent, &norm), 0.0);
+    /// ```
+    #[must_use]
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        mat_vec(&transpose(&self.inv), n)
+    }
+
+    /// Transform a [ray](Ray), keeping its time. The direction isn't
+    /// normalised so `t` values are the same for both [ray](Ray)s.
+    #[must_use]
+    pub fn ray(&self, r: &Ray) -> Ray {
+        ray!(self.point(r.origin), self.vector(r.direction), r.time)
+    }
+
+    /// Get the [`Aabb`] bounding `aabb` once transformed, by transforming all
+    /// eight of its corners.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, interval, interval::Interval, transform::Transform, vec3, vec3::Vec3};
+    ///
+    /// let aabb = Aabb {
+    ///     x: interval![-1.0, 1.0],
+    ///     y: interval![-1.0, 1.0],
+    ///     z: interval![-1.0, 1.0],
+    /// };
+    ///
+    /// let moved = Transform::translate(vec3![5.0, 0.0, 0.0]).bound(&aabb);
+    ///
+    /// assert_eq!(moved.x, interval![4.0, 6.0]);
+    /// assert_eq!(moved.y, aabb.y);
+    /// ```
+    #[must_use]
+    pub fn bound(&self, aabb: &Aabb) -> Aabb {
+        let mut out = Aabb::new();
+        for x in [aabb.x.min, aabb.x.max] {
+            for y in [aabb.y.min, aabb.y.max] {
+                for z in [aabb.z.min, aabb.z.max] {
+                    let p = self.point(vec3![x, y, z]);
+                    out.union(&Aabb {
+                        x: interval![p[0], p[0]],
+                        y: interval![p[1], p[1]],
+                        z: interval![p[2], p[2]],
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+fn transpose(m: &Mat3) -> Mat3 {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = m[j][i];
+        }
+    }
+    out
+}
+
+fn mat_mul(a: &Mat3, b: &Mat3) -> Mat3 {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+fn mat_vec(m: &Mat3, v: Vec3) -> Vec3 {
+    vec3![
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2]
+    ]
+}
+
+/// A pose at a point in time, made of a scale, then a rotation (Euler angles in
+/// degrees, see [`Transform::rotate_euler()`]), then a translation.
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    /// The time of the pose.
+    pub time: f64,
+    /// The translation of the pose.
+    pub translation: Vec3,
+    /// The rotation of the pose as Euler angles in degrees.
+    pub rotation: Vec3,
+    /// The per-axis scale of the pose.
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    /// Create a [`Keyframe`] at `time` with no scaling or rotation.
+    #[must_use]
+    pub fn at(time: f64, translation: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation: vec3![0.0, 0.0, 0.0],
+            scale: vec3![1.0, 1.0, 1.0],
+        }
+    }
+
+    /// Set the rotation of the [`Keyframe`].
+    #[must_use]
+    pub fn with_rotation(self, rotation: Vec3) -> Self {
+        Self { rotation, ..self }
+    }
+
+    /// Set the scale of the [`Keyframe`].
+    #[must_use]
+    pub fn with_scale(self, scale: Vec3) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// Linearly interpolate each component of two [`Keyframe`]s.
+    #[must_use]
+    pub fn lerp(&self, other: &Keyframe, a: f64) -> Keyframe {
+        Keyframe {
+            time: self.time + (other.time - self.time) * a,
+            translation: self.translation + (other.translation - self.translation) * a,
+            rotation: self.rotation + (other.rotation - self.rotation) * a,
+            scale: self.scale + (other.scale - self.scale) * a,
+        }
+    }
+
+    /// The pose at `time` along `keyframes`, which are sorted by time,
+    /// interpolated between the two either side. Times before the first or
+    /// after the last [`Keyframe`] hold the first or last pose.
+    ///
+    /// # Panics
+    ///
+    /// If `keyframes` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{transform::Keyframe, vec3, vec3::Vec3};
+    ///
+    /// let keyframes = [
+    ///     Keyframe::at(0.0, vec3![0.0, 0.0, 0.0]),
+    ///     Keyframe::at(1.0, vec3![4.0, 0.0, 0.0]),
+    /// ];
+    ///
+    /// assert_eq!(Keyframe::interpolate(&keyframes, 0.25).translation, vec3![1.0, 0.0, 0.0]);
+    /// assert_eq!(Keyframe::interpolate(&keyframes, 2.0).translation, vec3![4.0, 0.0, 0.0]);
+    /// ```
+    #[must_use]
+    pub fn interpolate(keyframes: &[Keyframe], time: f64) -> Keyframe {
+        let first = keyframes[0];
+        let last = keyframes[keyframes.len() - 1];
+
+        if time <= first.time {
+            return first;
+        }
+        if time >= last.time {
+            return last;
+        }
+
+        let next = keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = (keyframes[next - 1], keyframes[next]);
+        a.lerp(&b, (time - a.time) / (b.time - a.time))
+    }
+
+    /// Get the [`Transform`] for this pose.
+    #[must_use]
+    pub fn transform(&self) -> Transform {
+        Transform::scale(self.scale)
+            .then(&Transform::rotate_euler(self.rotation))
+            .then(&Transform::translate(self.translation))
+    }
+}
+
+/// A [`Hittable`] that moves its child through a list of [`Keyframe`]s. The pose
+/// is interpolated at each [ray](Ray)'s time, so combined with a camera shutter
+/// (see [`CameraBuilder::set_shutter()`](crate::camera::CameraBuilder::set_shutter()))
+/// moving objects are motion blurred. Times before the first or after the last
+/// [`Keyframe`] hold the first or last pose.
+#[derive(Debug)]
+pub struct Animated<H: Hittable> {
+    child: H,
+    keyframes: Vec<Keyframe>,
+    aabb: Aabb,
+    /// The transform and its inverse when there's only one pose, so static
+    /// instances don't rebuild them for every ray.
+    fixed: Option<(Transform, Transform)>,
+}
+
+impl<H: Hittable> Animated<H> {
+    /// Create a new [`Animated`] moving `child` through `keyframes`, which are
+    /// sorted by time.
+    ///
+    /// # Panics
+    ///
+    /// If `keyframes` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::Hittable, interval, interval::Interval, material::Lambertian, ray, ray::Ray,
+    ///     sphere::Sphere, transform::{Animated, Keyframe}, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let sphere = Sphere {
+    ///     centre: vec3![0.0, 0.0, 0.0],
+    ///     radius: 1.0,
+    ///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// };
+    ///
+    /// // Moves from x = 0 to x = 10 over the shutter.
+    /// let moving = Animated::new(
+    ///     sphere,
+    ///     vec![
+    ///         Keyframe::at(0.0, vec3![0.0, 0.0, 0.0]),
+    ///         Keyframe::at(1.0, vec3![10.0, 0.0, 0.0]),
+    ///     ],
+    /// );
+    ///
+    /// let early = ray!(vec3![0.0, 0.0, 5.0], vec3![0.0, 0.0, -1.0], 0.0);
+    /// let late = ray!(vec3![0.0, 0.0, 5.0], vec3![0.0, 0.0, -1.0], 1.0);
+    ///
+    /// assert!(moving.hit(&early, interval![0.0, f64::INFINITY]).is_some());
+    /// assert!(moving.hit(&late, interval![0.0, f64::INFINITY]).is_none());
+    ///
+    /// // The bounds cover the whole motion.
+    /// assert_eq!(moving.bound().x, interval![-1.0, 11.0]);
+    /// ```
+    #[must_use]
+    pub fn new(child: H, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "Need at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let aabb = Self::motion_bound(&child, &keyframes);
+        let mut animated = Self {
+            child,
+            keyframes,
+            aabb,
+            fixed: None,
+        };
+        animated.update_fixed();
+        animated
+    }
+
+    /// The bound of `child` over all of the poses between `keyframes`.
+    fn motion_bound(child: &H, keyframes: &[Keyframe]) -> Aabb {
+        let child_aabb = child.bound();
+        let mut aabb = keyframes[0].transform().bound(&child_aabb);
+        for pair in keyframes.windows(2) {
+            for step in 1..=BOUND_STEPS {
+                let pose = pair[0].lerp(&pair[1], f64::from(step) / f64::from(BOUND_STEPS));
+                aabb.union(&pose.transform().bound(&child_aabb));
+            }
+        }
+        aabb
+    }
+
+    fn update_fixed(&mut self) {
+        self.fixed = match self.keyframes[..] {
+            [pose] => {
+                let transform = pose.transform();
+                Some((transform, transform.inverse()))
+            }
+            _ => None,
+        };
+    }
+
+    /// Get the interpolated [`Keyframe`] at `time`.
+    #[must_use]
+    pub fn pose_at(&self, time: f64) -> Keyframe {
+        Keyframe::interpolate(&self.keyframes, time)
+    }
+}
+
+impl<H: Hittable> Hittable for Animated<H> {
+    /// Move the [ray](Ray) into the child's space at the [ray](Ray)'s time, hit
+    /// the child, and move the resulting [`HitRecord`] back out.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let (transform, inverse) = self.fixed.unwrap_or_else(|| {
+            let transform = self.pose_at(r.time).transform();
+            (transform, transform.inverse())
+        });
+        let local = inverse.ray(r);
+
+        let mut rec = self.child.hit(&local, ray_t)?;
+        rec.p = transform.point(rec.p);
+        rec.norm = transform.normal(rec.norm).unit();
+        rec.tangent = rec.tangent.map(|tangent| transform.vector(tangent));
+        Some(rec)
+    }
+
+    /// The [`Aabb`] covering the child over all of the [`Keyframe`]s.
+    fn bound(&self) -> Aabb {
+        self.aabb
+    }
+
+    /// Moves every [`Keyframe`], leaving the child where it is in its own
+    /// space.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        for keyframe in &mut self.keyframes {
+            keyframe.translation += offset;
+        }
+        self.aabb = self.aabb.translated(offset);
+        self.update_fixed();
+        true
+    }
+
+    /// Sets the child's tolerances and bounds its motion again.
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.child.set_tolerances(tolerances);
+        self.aabb = Self::motion_bound(&self.child, &self.keyframes);
+    }
+}
+
+/// A [`Hittable`] placed by a fixed [`Transform`], such as a mesh moved,
+/// turned or resized without editing its file. [`Ray`]s are taken into the
+/// child's space by the inverse, so any affine transform works, including
+/// shears and non-uniform scales. For moving objects, see [`Animated`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable,
+///     interval,
+///     interval::Interval,
+///     material::Lambertian,
+///     ray,
+///     ray::Ray,
+///     sphere::Sphere,
+///     transform::{Transform, Transformed},
+///     vec3,
+///     vec3::Vec3,
+/// };
+///
+/// let ball = Sphere {
+///     centre: vec3![0.0, 0.0, 0.0],
+///     radius: 1.0,
+///     mat: Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+/// };
+///
+/// // squashed into a flat ellipsoid, then lifted up
+/// let placed = Transformed::new(
+///     ball,
+///     Transform::scale(vec3![2.0, 0.5, 2.0]).then(&Transform::translate(vec3![0.0, 3.0, 0.0])),
+/// );
+///
+/// let rec = placed
+///     .nearest_hit_along(&ray!(vec3![0.0, 10.0, 0.0], vec3![0.0, -1.0, 0.0]))
+///     .unwrap();
+/// assert_eq!(rec.p, vec3![0.0, 3.5, 0.0]);
+/// assert_eq!(rec.norm, vec3![0.0, 1.0, 0.0]);
+///
+/// assert_eq!(placed.bound().x, interval![-2.0, 2.0]);
+/// assert_eq!(placed.bound().y, interval![2.5, 3.5]);
+/// ```
+#[derive(Debug)]
+pub struct Transformed<H: Hittable> {
+    child: H,
+    transform: Transform,
+    inverse: Transform,
+    aabb: Aabb,
+}
+
+impl<H: Hittable> Transformed<H> {
+    /// Place `child` by `transform`.
+    #[must_use]
+    pub fn new(child: H, transform: Transform) -> Self {
+        let aabb = transform.bound(&child.bound());
+        Self {
+            child,
+            transform,
+            inverse: transform.inverse(),
+            aabb,
+        }
+    }
+
+    /// The [`Transform`] placing the child.
+    #[must_use]
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// The child in its own space.
+    #[must_use]
+    pub fn child(&self) -> &H {
+        &self.child
+    }
+}
+
+impl<H: Hittable> Hittable for Transformed<H> {
+    /// Move the [ray](Ray) into the child's space, hit the child, and move the
+    /// resulting [`HitRecord`] back out. The direction isn't normalised, so
+    /// `t` is the same in both spaces.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let mut rec = self.child.hit(&self.inverse.ray(r), ray_t)?;
+        rec.p = self.transform.point(rec.p);
+        rec.norm = self.transform.normal(rec.norm).unit();
+        rec.tangent = rec.tangent.map(|tangent| self.transform.vector(tangent));
+        Some(rec)
+    }
+
+    /// The corners of the child's [`Aabb`], transformed and bounded again.
+    fn bound(&self) -> Aabb {
+        self.aabb
+    }
+
+    /// Adds the offset to the [`Transform`], leaving the child where it is in
+    /// its own space.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.transform = self.transform.then(&Transform::translate(offset));
+        self.inverse = self.transform.inverse();
+        self.aabb = self.aabb.translated(offset);
+        true
+    }
+
+    /// Sets the child's tolerances and bounds it again.
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.child.set_tolerances(tolerances);
+        self.aabb = self.transform.bound(&self.child.bound());
+    }
+}
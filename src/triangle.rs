@@ -1,4 +1,12 @@
-//! Contains the [`Triangle`] struct which models a 2D triangle.
+//! Contains the [`Triangle`] struct which models a 2D triangle, and the
+//! [`MovingTriangle`] for deformation motion blur, along with the
+//! intersection of several triangles at once used by the packets of a
+//! [`Mesh`](crate::mesh::Mesh), written lane by lane for the compiler to
+//! auto-vectorise.
+
+use std::sync::Arc;
+
+use multiversion::multiversion;
 
 use crate::{
     aabb::Aabb,
@@ -7,6 +15,8 @@ use crate::{
     interval::Interval,
     material::Material,
     ray::Ray,
+    tolerance::Tolerances,
+    vec3,
     vec3::{cross, dot, Vec3},
 };
 
@@ -17,37 +27,77 @@ pub struct Triangle {
     pub vertices: [Vec3; 3],
     /// The vertex normals.
     pub normals: [Vec3; 3],
-    /// The [`Material`] of the triangle.
-    pub mat: Box<dyn Material>,
+    /// The texture coordinates of each vertex as `[u, v]`, interpolated across
+    /// the face for [`HitRecord::u`] and [`HitRecord::v`]. Defaults to
+    /// [`BARYCENTRIC_UVS`].
+    pub uvs: [[f64; 2]; 3],
+    /// The [`Material`] of the triangle, which may be shared with others.
+    pub mat: Arc<dyn Material>,
+    /// The tolerances the triangle is intersected and bounded with, see
+    /// [`Hittable::set_tolerances()`].
+    pub tolerances: Tolerances,
 }
 
+/// The texture coordinates of triangles without any, `(0, 0)`, `(1, 0)` and
+/// `(0, 1)`, so [`HitRecord::u`] and [`HitRecord::v`] are the barycentric
+/// coordinates of the hit.
+pub const BARYCENTRIC_UVS: [[f64; 2]; 3] = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+
 impl Triangle {
     /// Create a new [`Triangle`] with the given `vertices`, `normals`, and `mat`.
     /// If no `normals` are supplied, they are calculated by taking the
     /// [cross product](crate::vec3::cross) of the edges `v0 -> v1` and `v1 -> v2`.
     #[must_use]
     pub fn new(vertices: [Vec3; 3], normals: Option<[Vec3; 3]>, mat: Box<dyn Material>) -> Self {
-        let normals = if let Some(n) = normals {
-            n
-        } else {
-            let e1 = vertices[1] - vertices[0];
-            let e2 = vertices[2] - vertices[1];
-            let n = cross(&e1, &e2);
-            [n, n, n]
-        };
+        Self::with_shared_material(vertices, normals, Arc::from(mat))
+    }
+
+    /// Like [`Triangle::new()`], but with a [`Material`] shared between many
+    /// primitives, such as the faces of a [`Mesh`](crate::mesh::Mesh).
+    #[must_use]
+    pub fn with_shared_material(
+        vertices: [Vec3; 3],
+        normals: Option<[Vec3; 3]>,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let normals = normals.unwrap_or([face_normal(vertices); 3]);
 
         Self {
             vertices,
             normals,
+            uvs: BARYCENTRIC_UVS,
             mat,
+            tolerances: Tolerances::DEFAULT,
         }
     }
 
-    /// Get the interpolated normal at a point on the [`Triangle`] using
-    /// Barycentric coordinates `(u, v)` (`w` is calculated).
-    fn get_norm(&self, u: f64, v: f64) -> Vec3 {
-        let w = 1.0 - u - v;
-        (self.normals[0] * w + self.normals[1] * u + self.normals[2] * v).unit()
+    /// Set the texture coordinates of each vertex as `[u, v]`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     hit::Hittable, interval, interval::Interval, material::Lambertian, ray, ray::Ray,
+    ///     triangle::Triangle, vec3, vec3::Vec3,
+    /// };
+    ///
+    /// let tri = Triangle::new(
+    ///     [vec3![0.0, 0.0, 0.0], vec3![1.0, 0.0, 0.0], vec3![0.0, 1.0, 0.0]],
+    ///     None,
+    ///     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// )
+    /// .set_uvs([[0.5, 0.0], [1.0, 0.0], [0.5, 1.0]]);
+    ///
+    /// let r = ray!(vec3![0.5, 0.25, 1.0], vec3![0.0, 0.0, -1.0]);
+    /// let rec = tri.hit(&r, interval![0.0, f64::INFINITY]).unwrap();
+    ///
+    /// assert!((rec.u - 0.75).abs() < 1e-9);
+    /// assert!((rec.v - 0.25).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn set_uvs(mut self, uvs: [[f64; 2]; 3]) -> Self {
+        self.uvs = uvs;
+        self
     }
 }
 
@@ -56,64 +106,501 @@ impl Hittable for Triangle {
     /// intersects the [`Triangle`], and where. See [wikipedia](https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm).
     /// Returns a [`Some(HitRecord)`](Option<HitRecord>) if a hit occurred,
     /// otherwise [`None`].
-    fn hit(&self, ray: &Ray, _ray_t: Interval) -> Option<HitRecord<'_>> {
-        let e1 = self.vertices[1] - self.vertices[0];
-        let e2 = self.vertices[2] - self.vertices[0];
-        let ray_cross_e2 = cross(&ray.direction, &e2);
-        let det = dot(&e1, &ray_cross_e2);
-
-        if det > -f64::EPSILON && det < f64::EPSILON {
-            return None;
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        hit_triangle(
+            self.vertices,
+            self.normals,
+            self.uvs,
+            &*self.mat,
+            ray,
+            ray_t,
+            &self.tolerances,
+        )
+    }
+
+    /// The bounds of the vertices, [padded](Aabb::padded()) by the
+    /// [bound padding](Tolerances::bound_padding) of its tolerances along any
+    /// axis the triangle lies flat in.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{hit::Hittable, material::Lambertian, triangle::Triangle, vec3, vec3::Vec3};
+    ///
+    /// let floor = Triangle::new(
+    ///     [vec3![0.0, 0.0, 0.0], vec3![1.0, 0.0, 0.0], vec3![0.0, 0.0, 1.0]],
+    ///     None,
+    ///     Box::new(Lambertian::new(vec3![0.5, 0.5, 0.5])),
+    /// );
+    ///
+    /// let bound = floor.bound();
+    /// assert!(bound.y.size() > 0.0);
+    /// assert!(bound.surface_area() > 1.0);
+    /// ```
+    fn bound(&self) -> Aabb {
+        triangle_bound(self.vertices, self.tolerances.bound_padding)
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        for v in &mut self.vertices {
+            *v += offset;
         }
+        true
+    }
 
-        let inv_det = 1. / det;
-        let s = ray.origin - self.vertices[0];
-        let u = dot(&s, &ray_cross_e2) * inv_det;
-        if !(0. ..=1.).contains(&u) {
-            return None;
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.tolerances = *tolerances;
+    }
+
+    /// The closest point on the triangle, found from which vertex, edge or face
+    /// region `point` projects into.
+    fn closest_point(&self, point: Vec3) -> Option<Vec3> {
+        Some(closest_point_on(self.vertices, point))
+    }
+}
+
+/// A [`Triangle`] whose vertices each move in a straight line, from
+/// [`start`](field@MovingTriangle::start) at time `0.0` to
+/// [`end`](field@MovingTriangle::end) at time `1.0`, so a deforming mesh
+/// (such as a character or cloth vertex cache) is motion blurred by its
+/// deformation. Each [ray](Ray) sees the triangle where it is at the ray's
+/// time, like a [`MovingSphere`](crate::sphere::MovingSphere).
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use rustrace::{
+///     hit::Hittable, interval, interval::Interval, material::Lambertian, ray, ray::Ray,
+///     triangle::MovingTriangle, vec3, vec3::Vec3,
+/// };
+///
+/// // a triangle sliding up by 2
+/// let start = [vec3![-1.0, 0.0, 0.0], vec3![1.0, 0.0, 0.0], vec3![0.0, 1.0, 0.0]];
+/// let end = start.map(|v| v + vec3![0.0, 2.0, 0.0]);
+/// let tri = MovingTriangle::new(start, end, None, Arc::new(Lambertian::new(vec3![0.5, 0.5, 0.5])));
+///
+/// let down_z = |time| ray!(vec3![0.0, 0.5, 5.0], vec3![0.0, 0.0, -1.0], time);
+/// assert!(tri.hit(&down_z(0.0), interval![0.0, f64::INFINITY]).is_some());
+/// assert!(tri.hit(&down_z(1.0), interval![0.0, f64::INFINITY]).is_none());
+///
+/// // held at its end after the shutter closes
+/// let late = ray!(vec3![0.0, 2.5, 5.0], vec3![0.0, 0.0, -1.0], 3.0);
+/// assert!(tri.hit(&late, interval![0.0, f64::INFINITY]).is_some());
+///
+/// // the bound covers the whole motion
+/// assert_eq!(tri.bound().y, interval![0.0, 3.0]);
+/// ```
+#[derive(Debug)]
+pub struct MovingTriangle {
+    /// The vertices at time `0.0`.
+    pub start: [Vec3; 3],
+    /// The vertices at time `1.0`.
+    pub end: [Vec3; 3],
+    /// The vertex normals at time `0.0` and time `1.0`.
+    pub normals: [[Vec3; 3]; 2],
+    /// The texture coordinates of each vertex, which don't move, see
+    /// [`Triangle::uvs`].
+    pub uvs: [[f64; 2]; 3],
+    /// The [`Material`] of the triangle, which may be shared with others.
+    pub mat: Arc<dyn Material>,
+    /// The tolerances the triangle is intersected and bounded with, see
+    /// [`Triangle::tolerances`].
+    pub tolerances: Tolerances,
+}
+
+impl MovingTriangle {
+    /// Create a new [`MovingTriangle`] moving from `start` to `end`, with the
+    /// vertex `normals` at each end. Without `normals`, each end's face
+    /// normal is used like in [`Triangle::new()`].
+    #[must_use]
+    pub fn new(
+        start: [Vec3; 3],
+        end: [Vec3; 3],
+        normals: Option<[[Vec3; 3]; 2]>,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let normals = normals.unwrap_or([[face_normal(start); 3], [face_normal(end); 3]]);
+        Self {
+            start,
+            end,
+            normals,
+            uvs: BARYCENTRIC_UVS,
+            mat,
+            tolerances: Tolerances::DEFAULT,
         }
+    }
 
-        let s_cross_e1 = cross(&s, &e1);
-        let v = inv_det * dot(&ray.direction, &s_cross_e1);
-        if v < 0. || u + v > 1. {
-            return None;
+    /// Set the texture coordinates of each vertex as `[u, v]`, like
+    /// [`Triangle::set_uvs()`].
+    #[must_use]
+    pub fn set_uvs(mut self, uvs: [[f64; 2]; 3]) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    /// The vertices and vertex normals at `time`, held at the
+    /// [`start`](field@MovingTriangle::start) or
+    /// [`end`](field@MovingTriangle::end) outside `0.0..=1.0` so they stay
+    /// within its bounds.
+    fn at(&self, time: f64) -> ([Vec3; 3], [Vec3; 3]) {
+        let time = time.clamp(0.0, 1.0);
+        let lerp = |a: [Vec3; 3], b: [Vec3; 3]| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * time);
+        (
+            lerp(self.start, self.end),
+            lerp(self.normals[0], self.normals[1]),
+        )
+    }
+}
+
+impl Hittable for MovingTriangle {
+    /// Intersects the triangle where it is at the [ray](Ray)'s time, like
+    /// [`Triangle::hit()`].
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let (vertices, normals) = self.at(ray.time);
+        hit_triangle(
+            vertices,
+            normals,
+            self.uvs,
+            &*self.mat,
+            ray,
+            ray_t,
+            &self.tolerances,
+        )
+    }
+
+    /// The bound of the motion between times `0.0` and `1.0`, which is all a
+    /// shutter within them can see.
+    fn bound(&self) -> Aabb {
+        let padding = self.tolerances.bound_padding;
+        let mut aabb = triangle_bound(self.start, padding);
+        aabb.union(&triangle_bound(self.end, padding));
+        aabb
+    }
+
+    fn translate(&mut self, offset: Vec3) -> bool {
+        for v in self.start.iter_mut().chain(&mut self.end) {
+            *v += offset;
         }
+        true
+    }
 
-        let t_value = inv_det * dot(&e2, &s_cross_e1);
-        if t_value > f64::EPSILON {
-            let p = ray.at(t_value - f64::EPSILON);
-            let mat = &(*self.mat);
+    fn set_tolerances(&mut self, tolerances: &Tolerances) {
+        self.tolerances = *tolerances;
+    }
+}
+
+/// The bound of the triangle `vertices`, padded to at least `padding` where
+/// it would be flat.
+pub(crate) fn triangle_bound(vertices: [Vec3; 3], padding: f64) -> Aabb {
+    let min_max_axis = |axis: usize| {
+        let mut iter = vertices.iter().map(|v| v[axis]);
+        let first = iter.next().expect("No vertices.");
+        let (min, max) = iter.fold((first, first), |(min, max), val| {
+            (min.min(val), max.max(val))
+        });
+        interval![min, max]
+    };
+
+    Aabb {
+        x: min_max_axis(0),
+        y: min_max_axis(1),
+        z: min_max_axis(2),
+    }
+    .padded(padding)
+}
+
+/// Intersect `ray` with the triangle `vertices`, interpolating `normals` and
+/// `uvs`, shared by [`Triangle`] and [`MovingTriangle`].
+fn hit_triangle<'a>(
+    vertices: [Vec3; 3],
+    normals: [Vec3; 3],
+    uvs: [[f64; 2]; 3],
+    mat: &'a dyn Material,
+    ray: &Ray,
+    ray_t: Interval,
+    tolerances: &Tolerances,
+) -> Option<HitRecord<'a>> {
+    let e1 = vertices[1] - vertices[0];
+    let e2 = vertices[2] - vertices[0];
+    let ray_cross_e2 = cross(&ray.direction, &e2);
+    let det = dot(&e1, &ray_cross_e2);
 
-            let mut rec = HitRecord {
-                p,
-                norm: self.get_norm(u, v),
-                mat,
-                t: t_value,
-                front_face: true,
-            };
+    let det_tolerance = tolerances.triangle_det;
+    if det > -det_tolerance && det < det_tolerance {
+        return None;
+    }
+
+    let inv_det = 1. / det;
+    let s = ray.origin - vertices[0];
+    let u = dot(&s, &ray_cross_e2) * inv_det;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
 
-            rec.set_face_norm(ray, &self.get_norm(u, v));
+    let s_cross_e1 = cross(&s, &e1);
+    let v = inv_det * dot(&ray.direction, &s_cross_e1);
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let t_value = inv_det * dot(&e2, &s_cross_e1);
+    let offset = tolerances.hit_offset;
+    if t_value > offset && ray_t.surrounds(t_value) {
+        let p = ray.at(t_value - offset);
+        let [tex_u, tex_v] = interpolate_uv(uvs, u, v);
+        let mut rec = HitRecord {
+            p,
+            norm: interpolate_normal(normals, u, v),
+            mat,
+            t: t_value,
+            front_face: true,
+            u: tex_u,
+            v: tex_v,
+            object: None,
+            layer: None,
+            tangent: Some(uv_tangent(vertices, uvs)),
+        };
 
-            Some(rec)
-        } else {
-            None
+        rec.set_face_norm(ray, &interpolate_normal(normals, u, v));
+
+        Some(rec)
+    } else {
+        None
+    }
+}
+
+/// Get the interpolated normal at a point on a triangle using Barycentric
+/// coordinates `(u, v)` (`w` is calculated).
+pub(crate) fn interpolate_normal(normals: [Vec3; 3], u: f64, v: f64) -> Vec3 {
+    let w = 1.0 - u - v;
+    (normals[0] * w + normals[1] * u + normals[2] * v).unit()
+}
+
+/// Get the interpolated texture coordinates at a point on a triangle using
+/// Barycentric coordinates `(u, v)`, like [`interpolate_normal()`].
+pub(crate) fn interpolate_uv(uvs: [[f64; 2]; 3], u: f64, v: f64) -> [f64; 2] {
+    let w = 1.0 - u - v;
+    [0, 1].map(|i| uvs[0][i] * w + uvs[1][i] * u + uvs[2][i] * v)
+}
+
+/// The directions of increasing texture coordinates `u` and `v` over the
+/// triangle `vertices` with texture coordinates `uvs`, unnormalised, or
+/// [`None`] if the texture coordinates are degenerate.
+pub(crate) fn uv_derivatives(vertices: [Vec3; 3], uvs: [[f64; 2]; 3]) -> Option<(Vec3, Vec3)> {
+    let e1 = vertices[1] - vertices[0];
+    let e2 = vertices[2] - vertices[0];
+    let [du1, dv1] = [0, 1].map(|i| uvs[1][i] - uvs[0][i]);
+    let [du2, dv2] = [0, 1].map(|i| uvs[2][i] - uvs[0][i]);
+    let det = du1 * dv2 - du2 * dv1;
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    Some(((e1 * dv2 - e2 * dv1) / det, (e2 * du1 - e1 * du2) / det))
+}
+
+/// The direction of increasing texture coordinate `u` over the triangle
+/// `vertices` with texture coordinates `uvs`, unnormalised. Falls back to the
+/// edge `v0 -> v1` if the texture coordinates are degenerate.
+pub(crate) fn uv_tangent(vertices: [Vec3; 3], uvs: [[f64; 2]; 3]) -> Vec3 {
+    uv_derivatives(vertices, uvs).map_or(vertices[1] - vertices[0], |(tangent, _)| tangent)
+}
+
+/// The unnormalised normal of the triangle `vertices`, the
+/// [cross product](crate::vec3::cross) of the edges `v0 -> v1` and
+/// `v1 -> v2`.
+pub(crate) fn face_normal(vertices: [Vec3; 3]) -> Vec3 {
+    cross(&(vertices[1] - vertices[0]), &(vertices[2] - vertices[1]))
+}
+
+/// Finds the closest point on the triangle `vertices` to `point` by checking
+/// which vertex, edge or face region `point` projects into, see Ericson's
+/// *Real-Time Collision Detection* section 5.1.5.
+pub(crate) fn closest_point_on(vertices: [Vec3; 3], point: Vec3) -> Vec3 {
+    let [a, b, c] = vertices;
+    let ab = b - a;
+    let ac = c - a;
+
+    let ap = point - a;
+    let d1 = dot(&ab, &ap);
+    let d2 = dot(&ac, &ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = point - b;
+    let d3 = dot(&ab, &bp);
+    let d4 = dot(&ac, &bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = point - c;
+    let d5 = dot(&ab, &cp);
+    let d6 = dot(&ac, &cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// The number of triangles in each packet of a [`Mesh`](crate::mesh::Mesh),
+/// tested against a [ray](Ray) at once.
+pub const PACKET_LANES: usize = 4;
+
+/// Split `items` into groups of up to [`PACKET_LANES`] at the median
+/// `centroid` along the longest axis, passing each to `group`, so the items
+/// in a group are close together, as the packets of a
+/// [`Mesh`](crate::mesh::Mesh) are built.
+pub(crate) fn group_by_centroid<T>(
+    mut items: Vec<T>,
+    centroid: &impl Fn(&T) -> Vec3,
+    group: &mut impl FnMut(Vec<T>),
+) {
+    if items.len() <= PACKET_LANES {
+        if !items.is_empty() {
+            group(items);
         }
+        return;
     }
 
-    fn bound(&self) -> Aabb {
-        fn min_max_axis(vertices: [Vec3; 3], axis: usize) -> Interval {
-            let mut iter = vertices.iter().map(|v| v[axis]);
-            let first = iter.next().expect("No vertices.");
-            let (min, max) = iter.fold((first, first), |(min, max), val| {
-                (min.min(val), max.max(val))
-            });
-            interval![min, max]
+    let mut lo = [f64::INFINITY; 3];
+    let mut hi = [f64::NEG_INFINITY; 3];
+    for item in &items {
+        let c = centroid(item);
+        for axis in 0..3 {
+            lo[axis] = lo[axis].min(c[axis]);
+            hi[axis] = hi[axis].max(c[axis]);
         }
+    }
+    let axis = (0..3)
+        .max_by(|&a, &b| (hi[a] - lo[a]).total_cmp(&(hi[b] - lo[b])))
+        .unwrap_or_default();
+
+    // round the split up to whole packets so only the last one is partial
+    let mid = (items.len() / 2).next_multiple_of(PACKET_LANES);
+    items.select_nth_unstable_by(mid, |a, b| centroid(a)[axis].total_cmp(&centroid(b)[axis]));
+    let right = items.split_off(mid);
+
+    group_by_centroid(items, centroid, group);
+    group_by_centroid(right, centroid, group);
+}
+
+/// The first vertex and the edges `v0 -> v1` and `v0 -> v2` of up to
+/// [`PACKET_LANES`] triangles, indexed `[axis][lane]`, as tested by
+/// [`intersect_lanes()`].
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LaneVertices {
+    pub(crate) len: usize,
+    pub(crate) v0: [[f64; PACKET_LANES]; 3],
+    pub(crate) e1: [[f64; PACKET_LANES]; 3],
+    pub(crate) e2: [[f64; PACKET_LANES]; 3],
+}
 
-        Aabb {
-            x: min_max_axis(self.vertices, 0),
-            y: min_max_axis(self.vertices, 1),
-            z: min_max_axis(self.vertices, 2),
+impl LaneVertices {
+    /// The vertices of the triangle in `lane`.
+    pub(crate) fn get(&self, lane: usize) -> [Vec3; 3] {
+        let get = |arr: &[[f64; PACKET_LANES]; 3]| vec3![arr[0][lane], arr[1][lane], arr[2][lane]];
+        let a = get(&self.v0);
+        [a, a + get(&self.e1), a + get(&self.e2)]
+    }
+
+    /// Put the triangle `vertices` in `lane`.
+    pub(crate) fn set(&mut self, lane: usize, [a, b, c]: [Vec3; 3]) {
+        for axis in 0..3 {
+            self.v0[axis][lane] = a[axis];
+            self.e1[axis][lane] = b[axis] - a[axis];
+            self.e2[axis][lane] = c[axis] - a[axis];
         }
     }
 }
+
+/// The nearest of the first `len` lanes hit by [`intersect_lanes()`], with
+/// the `t`, `u` and `v` of the hit.
+pub(crate) fn nearest_lane(
+    [lane_t, lane_u, lane_v]: [[f64; PACKET_LANES]; 3],
+    len: usize,
+) -> Option<(usize, f64, f64, f64)> {
+    let mut nearest: Option<usize> = None;
+    for lane in 0..len {
+        if lane_t[lane] < nearest.map_or(f64::INFINITY, |n| lane_t[n]) {
+            nearest = Some(lane);
+        }
+    }
+    nearest.map(|lane| (lane, lane_t[lane], lane_u[lane], lane_v[lane]))
+}
+
+/// The `t`, `u` and `v` of `ray` on every lane of `lanes`, with `t` infinite
+/// where it misses. Does the same arithmetic as [`Triangle::hit()`] with
+/// `tolerances`, in the same order, so packed triangles are hit identically.
+#[multiversion(targets("x86_64+avx512f+avx512vl", "x86_64+avx2+fma", "aarch64+neon"))]
+pub(crate) fn intersect_lanes(
+    lanes: &LaneVertices,
+    ray: &Ray,
+    ray_t: Interval,
+    tolerances: &Tolerances,
+) -> [[f64; PACKET_LANES]; 3] {
+    let [ox, oy, oz] = ray.origin.e;
+    let [dx, dy, dz] = ray.direction.e;
+    let mut t = [f64::INFINITY; PACKET_LANES];
+    let mut u = [0.0; PACKET_LANES];
+    let mut v = [0.0; PACKET_LANES];
+    let det_tolerance = tolerances.triangle_det;
+    let offset = tolerances.hit_offset;
+
+    for lane in 0..PACKET_LANES {
+        let (e1x, e1y, e1z) = (lanes.e1[0][lane], lanes.e1[1][lane], lanes.e1[2][lane]);
+        let (e2x, e2y, e2z) = (lanes.e2[0][lane], lanes.e2[1][lane], lanes.e2[2][lane]);
+
+        // ray x e2
+        let px = dy * e2z - dz * e2y;
+        let py = dz * e2x - dx * e2z;
+        let pz = dx * e2y - dy * e2x;
+        let det = e1x * px + e1y * py + e1z * pz;
+        let inv_det = 1. / det;
+
+        let sx = ox - lanes.v0[0][lane];
+        let sy = oy - lanes.v0[1][lane];
+        let sz = oz - lanes.v0[2][lane];
+        let lane_u = (sx * px + sy * py + sz * pz) * inv_det;
+
+        // s x e1
+        let qx = sy * e1z - sz * e1y;
+        let qy = sz * e1x - sx * e1z;
+        let qz = sx * e1y - sy * e1x;
+        let lane_v = inv_det * (dx * qx + dy * qy + dz * qz);
+        let lane_t = inv_det * (e2x * qx + e2y * qy + e2z * qz);
+
+        let hit = lane < lanes.len
+            && !(det > -det_tolerance && det < det_tolerance)
+            && (0. ..=1.).contains(&lane_u)
+            && lane_v >= 0.
+            && lane_u + lane_v <= 1.
+            && lane_t > offset
+            && ray_t.surrounds(lane_t);
+
+        t[lane] = if hit { lane_t } else { f64::INFINITY };
+        u[lane] = lane_u;
+        v[lane] = lane_v;
+    }
+
+    [t, u, v]
+}
@@ -0,0 +1,1165 @@
+//! Contains the [`UsdStage`], an importer for a pragmatic subset of USD scenes
+//! written in the `.usda` text format, so animated shots from standard
+//! pipelines can be rendered frame by frame without a custom exporter.
+//!
+//! Supported are `Xform`, `Mesh` and `Camera` prims nested to any depth, the
+//! common transform ops, and time samples on any of their attributes. Layer
+//! composition (sublayers, references, payloads and variants) isn't, so
+//! flatten stages first, e.g. with `usdcat --flatten`, which also converts
+//! binary `.usdc` files to text.
+
+use std::{
+    borrow::Cow, collections::HashMap, fs, iter::Peekable, ops::Range, path::Path, str::Chars,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    camera::{Camera, CameraBuilder},
+    material::{Lambertian, Material},
+    mesh::{FaceVertex, Mesh, MeshData},
+    scene::{Scene, SceneSettings, UpAxis},
+    transform::Transform,
+    vec3,
+    vec3::Vec3,
+};
+
+/// The focal length, in tenths of a scene unit, of cameras that don't give
+/// one.
+const DEFAULT_FOCAL_LENGTH: f64 = 50.0;
+
+/// The film back, in tenths of a scene unit, of cameras that don't give one:
+/// 35mm academy.
+const DEFAULT_APERTURE: (f64, f64) = (20.955, 15.2908);
+
+/// A USD stage read from a `.usda` file, from which a [`Scene`] can be built
+/// at any frame with [`UsdStage::scene_at()`].
+///
+/// The layer's `upAxis` and `metersPerUnit` give the stage's
+/// [`SceneSettings`], taking scene units to be metres, and its
+/// `startTimeCode` and `endTimeCode` give the [frames](UsdStage::frames()).
+///
+/// Meshes are read from their `points`, `faceVertexCounts` and
+/// `faceVertexIndices`, with optional `normals` and `primvars:st` texture
+/// coordinates, and polygons are triangulated as fans. Subdivision surfaces
+/// are rendered as their cage. Cameras are read from their `focalLength`,
+/// apertures, `fStop` and `focusDistance`. Prims that are inactive,
+/// invisible, or `guide` or `proxy` purpose aren't rendered.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     camera::CameraBuilder, hit::Hittable, interval, interval::Interval,
+///     material::Lambertian, usd::UsdStage, vec3, vec3::Vec3,
+/// };
+///
+/// let stage = UsdStage::parse(
+///     r#"#usda 1.0
+///     (
+///         startTimeCode = 1
+///         endTimeCode = 24
+///     )
+///
+///     def Xform "World"
+///     {
+///         def Mesh "Card"
+///         {
+///             int[] faceVertexCounts = [4]
+///             int[] faceVertexIndices = [0, 1, 2, 3]
+///             point3f[] points = [(-1, -1, 0), (1, -1, 0), (1, 1, 0), (-1, 1, 0)]
+///             double3 xformOp:translate.timeSamples = {
+///                 1: (0, 0, 0),
+///                 24: (23, 0, 0),
+///             }
+///             uniform token[] xformOpOrder = ["xformOp:translate"]
+///         }
+///
+///         def Camera "Shot"
+///         {
+///             float focalLength = 35
+///             double3 xformOp:translate = (0, 0, 10)
+///             uniform token[] xformOpOrder = ["xformOp:translate"]
+///         }
+///     }
+///     "#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(stage.frames(), 1..25);
+///
+/// let grey = Lambertian::new(vec3![0.5, 0.5, 0.5]);
+/// let scene = stage.scene_at(3.0, &grey, &CameraBuilder::default()).unwrap();
+/// assert!(scene.camera("/World/Shot").is_some());
+///
+/// // the card moves a unit each frame, which the shutter covers from this
+/// // frame to the next
+/// assert_eq!(scene.world().bound().x, interval![1.0, 4.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct UsdStage {
+    prims: Vec<Prim>,
+    settings: SceneSettings,
+    start: Option<f64>,
+    end: Option<f64>,
+}
+
+impl UsdStage {
+    /// Load a [`UsdStage`] from a `.usda` file, see [`UsdStage::parse()`].
+    ///
+    /// # Errors
+    ///
+    /// If reading the file fails, it's a binary `.usdc` file, or parsing it
+    /// fails.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            fs::read(path).with_context(|| format!("Couldn't read stage {}", path.display()))?;
+        if bytes.starts_with(b"PXR-USDC") {
+            bail!(
+                "{} is a binary USD file, convert it to text with `usdcat` first",
+                path.display()
+            );
+        }
+        let text = String::from_utf8(bytes)
+            .with_context(|| format!("Stage {} isn't UTF-8 text", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid stage {}", path.display()))
+    }
+
+    /// Parse a [`UsdStage`] from the text of a `.usda` file.
+    ///
+    /// # Errors
+    ///
+    /// If the text isn't valid USD, or a layer setting is out of range.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+
+        let metadata = if parser.eat('(') {
+            parser.metadata()?
+        } else {
+            HashMap::new()
+        };
+        let mut prims = vec![];
+        while parser.peek().is_some() {
+            prims.push(parser.prim()?);
+        }
+
+        let mut settings = SceneSettings::default();
+        if let Some(axis) = metadata.get("upAxis").and_then(Value::string) {
+            settings = settings.set_up_axis(match axis {
+                "Y" => UpAxis::Y,
+                "Z" => UpAxis::Z,
+                _ => bail!("Unsupported up axis {axis:?}"),
+            });
+        }
+        if let Some(scale) = metadata.get("metersPerUnit").and_then(Value::number) {
+            if !(scale > 0.0 && scale.is_finite()) {
+                bail!("metersPerUnit must be positive and finite");
+            }
+            settings = settings.set_unit_scale(scale);
+        }
+
+        Ok(Self {
+            prims,
+            settings,
+            start: metadata.get("startTimeCode").and_then(Value::number),
+            end: metadata.get("endTimeCode").and_then(Value::number),
+        })
+    }
+
+    /// The [`SceneSettings`] the stage is imported with.
+    #[must_use]
+    pub fn settings(&self) -> SceneSettings {
+        self.settings
+    }
+
+    /// Set the [`SceneSettings`] to import the stage with, instead of those
+    /// from its layer settings.
+    #[must_use]
+    pub fn set_settings(mut self, settings: SceneSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// The frames of the shot, from its start time code to its end time code
+    /// inclusive, for a [`Sequence`](crate::scene::Sequence). Stages without
+    /// them span their time samples, or just frame `0`. Time codes beyond
+    /// the frames a [`u32`] can count are clamped to them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::usd::UsdStage;
+    ///
+    /// let stage = UsdStage::parse("#usda 1.0\n(\n    endTimeCode = 1e12\n)\n").unwrap();
+    /// assert_eq!(stage.frames(), 0..u32::MAX);
+    /// ```
+    #[must_use]
+    pub fn frames(&self) -> Range<u32> {
+        let mut times = vec![];
+        for prim in &self.prims {
+            prim.sample_times(&mut times);
+        }
+        let first = times.iter().copied().reduce(f64::min);
+        let last = times.iter().copied().reduce(f64::max);
+
+        let start = self.start.or(first).unwrap_or(0.0);
+        let end = self.end.or(last).unwrap_or(start).max(start);
+        #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let frame = |time: f64| time.max(0.0) as u32;
+        // end codes past the last frame `u32` can count stop there
+        frame(start.floor())..frame(end.floor()).saturating_add(1)
+    }
+
+    /// Build the [`Scene`] at `frame`, with every mesh made of `mat` unless
+    /// it has a `primvars:displayColor`, which it's given as a [`Lambertian`].
+    ///
+    /// Cameras are added named by their prim paths, starting from `camera`
+    /// with the view, field of view, aspect ratio and depth of field set from
+    /// the stage. Anything animated is sampled at `frame` and `frame + 1.0`,
+    /// so a shutter from `0.0` to `1.0` (see
+    /// [`CameraBuilder::set_shutter()`]) spans one frame of motion blur.
+    ///
+    /// # Errors
+    ///
+    /// If a prim's attributes are malformed, such as a mesh indexing past its
+    /// points, or a transform op isn't supported or can't be inverted.
+    pub fn scene_at(
+        &self,
+        frame: f64,
+        mat: &dyn Material,
+        camera: &CameraBuilder,
+    ) -> Result<Scene> {
+        let mut builder = SceneBuilder {
+            settings: self.settings,
+            frame,
+            mat,
+            camera,
+            scene: Scene::new(self.settings),
+        };
+        let root = [Transform::identity(); 2];
+        for prim in &self.prims {
+            builder.add(prim, "", &root)?;
+        }
+        Ok(builder.scene)
+    }
+}
+
+/// Builds a [`Scene`] from a [`UsdStage`]'s prims at a frame.
+struct SceneBuilder<'a> {
+    settings: SceneSettings,
+    frame: f64,
+    mat: &'a dyn Material,
+    camera: &'a CameraBuilder,
+    scene: Scene,
+}
+
+impl SceneBuilder<'_> {
+    /// Add `prim` and its descendants, under parents with world transforms
+    /// `parent` at the frame and the next.
+    fn add(&mut self, prim: &Prim, parent_path: &str, parent: &[Transform; 2]) -> Result<()> {
+        let path = format!("{parent_path}/{}", prim.name);
+        if !prim.rendered(self.frame) {
+            return Ok(());
+        }
+
+        let times = [self.frame, self.frame + 1.0];
+        let mut world = *parent;
+        for (world, time) in world.iter_mut().zip(times) {
+            let (local, reset) = prim
+                .local_transform(time)
+                .with_context(|| format!("Invalid transform on {path}"))?;
+            *world = if reset { local } else { local.then(world) };
+        }
+
+        match prim.kind.as_str() {
+            "Mesh" => self
+                .add_mesh(prim, &world)
+                .with_context(|| format!("Invalid mesh {path}"))?,
+            "Camera" => {
+                let camera = self
+                    .camera(prim, &world[0])
+                    .with_context(|| format!("Invalid camera {path}"))?;
+                self.scene.add_camera(path.clone(), camera);
+            }
+            _ => {}
+        }
+
+        for child in &prim.children {
+            self.add(child, &path, &world)?;
+        }
+        Ok(())
+    }
+
+    fn add_mesh(&mut self, prim: &Prim, world: &[Transform; 2]) -> Result<()> {
+        let start = self.mesh_data(prim, &world[0], self.frame)?;
+        let end = self.mesh_data(prim, &world[1], self.frame + 1.0)?;
+
+        let display_colour = prim
+            .value("primvars:displayColor", self.frame)
+            .and_then(|colours| colours.list()?.first()?.vec3());
+        let mat: Box<dyn Material> = match display_colour {
+            Some(colour) => Box::new(Lambertian::new(colour)),
+            None => self.mat.clone_box(),
+        };
+
+        let mesh = if start.positions == end.positions {
+            Mesh::from_data(start, &*mat)
+        } else {
+            Mesh::deforming(start, end, &*mat)?
+        };
+        self.scene.add(Box::new(mesh));
+        Ok(())
+    }
+
+    /// The triangulated geometry of a mesh prim at `time`, placed in the
+    /// scene by `world`.
+    fn mesh_data(&self, prim: &Prim, world: &Transform, time: f64) -> Result<MeshData> {
+        let required = |name: &str| {
+            prim.value(name, time)
+                .with_context(|| format!("Missing {name}"))
+        };
+        let points = vec3s(&*required("points")?).context("Invalid points")?;
+        let counts = integers(&*required("faceVertexCounts")?).context("Invalid face counts")?;
+        let indices = integers(&*required("faceVertexIndices")?).context("Invalid face indices")?;
+        if counts.iter().sum::<usize>() != indices.len() {
+            bail!("The face counts don't add up to the number of face indices");
+        }
+        if let Some(index) = indices.iter().find(|&&index| index >= points.len()) {
+            bail!(
+                "Face index {index} is past the mesh's {} points",
+                points.len()
+            );
+        }
+
+        let normals_name = if prim.attributes.contains_key("primvars:normals") {
+            "primvars:normals"
+        } else {
+            "normals"
+        };
+        let normals = Primvar::read(prim, normals_name, time, "vertex", vec3s)?;
+        let uvs = Primvar::read(prim, "primvars:st", time, "constant", |value| {
+            value
+                .list()
+                .context("Expected a list")?
+                .iter()
+                .map(|uv| match uv.list() {
+                    Some([u, v]) => Some([u.number()?, v.number()?]),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+                .context("Expected texture coordinates")
+        })?;
+
+        let left_handed = prim
+            .value("orientation", time)
+            .is_some_and(|orientation| orientation.string() == Some("leftHanded"));
+
+        let mut faces = vec![];
+        let mut first = 0;
+        for (face, &count) in counts.iter().enumerate() {
+            let mut corners = (first..first + count)
+                .map(|corner| {
+                    let point = indices[corner];
+                    Ok(FaceVertex {
+                        position: point,
+                        normal: normals
+                            .as_ref()
+                            .map(|normals| normals.index(face, point, corner))
+                            .transpose()?,
+                        uv: uvs
+                            .as_ref()
+                            .map(|uvs| uvs.index(face, point, corner))
+                            .transpose()?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if left_handed {
+                corners.reverse();
+            }
+            for i in 1..count.saturating_sub(1) {
+                faces.push([corners[0], corners[i], corners[i + 1]]);
+            }
+            first += count;
+        }
+
+        let settings = self.settings;
+        Ok(MeshData {
+            positions: points
+                .iter()
+                .map(|&p| settings.point(world.point(p)))
+                .collect(),
+            normals: normals.map_or_else(Vec::new, |normals| {
+                normals
+                    .values
+                    .iter()
+                    .map(|&n| settings.normal(world.normal(n).unit()))
+                    .collect()
+            }),
+            uvs: uvs.map_or_else(Vec::new, |uvs| uvs.values),
+            colours: Vec::new(),
+            faces,
+        })
+    }
+
+    /// The camera for a camera prim, placed in the scene by `world`.
+    fn camera(&self, prim: &Prim, world: &Transform) -> Result<Camera> {
+        let number = |name: &str, default: f64| {
+            prim.value(name, self.frame)
+                .and_then(|value| value.number())
+                .unwrap_or(default)
+        };
+        let focal_length = number("focalLength", DEFAULT_FOCAL_LENGTH);
+        let horizontal = number("horizontalAperture", DEFAULT_APERTURE.0);
+        let vertical = number("verticalAperture", DEFAULT_APERTURE.1);
+
+        // USD cameras look down -Z with +Y up
+        let settings = self.settings;
+        let look_from = settings.point(world.point(vec3![0.0, 0.0, 0.0]));
+        let forward = settings.normal(world.vector(vec3![0.0, 0.0, -1.0]));
+        let up = settings.normal(world.vector(vec3![0.0, 1.0, 0.0]));
+
+        let mut camera = self
+            .camera
+            .clone()
+            .set_aspect_ratio(horizontal / vertical)
+            .set_vfov_degrees((2.0 * (vertical / (2.0 * focal_length)).atan()).to_degrees())
+            .set_look_from(look_from)
+            .set_look_at(look_from + forward.unit())
+            .set_v_up(up);
+
+        let f_stop = number("fStop", 0.0);
+        let focus_distance = number("focusDistance", 0.0) * settings.unit_scale;
+        if f_stop > 0.0 && focus_distance > 0.0 {
+            // lens lengths are in tenths of a scene unit
+            let radius = 0.1 * settings.unit_scale * focal_length / f_stop / 2.0;
+            camera = camera
+                .set_focus_dist(focus_distance)
+                .set_defocus_angle((2.0 * (radius / focus_distance).atan()).to_degrees());
+        }
+        camera.build()
+    }
+}
+
+/// A mesh attribute with values spread over the surface according to its
+/// `interpolation`, optionally through `:indices`.
+struct Primvar<T> {
+    values: Vec<T>,
+    interpolation: String,
+    indices: Option<Vec<usize>>,
+}
+
+impl<T> Primvar<T> {
+    /// Read the primvar `name` of `prim` at `time`, if it has one, with
+    /// `parse` reading the values.
+    fn read<F: Fn(&Value) -> Result<Vec<T>>>(
+        prim: &Prim,
+        name: &str,
+        time: f64,
+        default_interpolation: &str,
+        parse: F,
+    ) -> Result<Option<Self>> {
+        let Some(attribute) = prim.attributes.get(name) else {
+            return Ok(None);
+        };
+        let Some(value) = attribute.at(time) else {
+            return Ok(None);
+        };
+        let values = parse(&value).with_context(|| format!("Invalid {name}"))?;
+        let indices = prim
+            .value(&format!("{name}:indices"), time)
+            .map(|indices| integers(&indices))
+            .transpose()
+            .with_context(|| format!("Invalid {name}:indices"))?;
+        Ok(Some(Self {
+            values,
+            interpolation: attribute
+                .interpolation
+                .as_deref()
+                .unwrap_or(default_interpolation)
+                .to_owned(),
+            indices,
+        }))
+    }
+
+    /// The index into the values for a corner of `face`, at `point`, which
+    /// is the `corner`th face vertex of the mesh.
+    fn index(&self, face: usize, point: usize, corner: usize) -> Result<usize> {
+        let element = match self.interpolation.as_str() {
+            "constant" => 0,
+            "uniform" => face,
+            "vertex" | "varying" => point,
+            "faceVarying" => corner,
+            other => bail!("Unsupported interpolation {other:?}"),
+        };
+        let index = match &self.indices {
+            Some(indices) => *indices
+                .get(element)
+                .context("Too few indices for the interpolation")?,
+            None => element,
+        };
+        if index >= self.values.len() {
+            bail!("Too few values for the interpolation");
+        }
+        Ok(index)
+    }
+}
+
+/// A prim, a node of the stage's hierarchy.
+#[derive(Debug, Clone, Default)]
+struct Prim {
+    /// The type name, such as `Mesh`, or empty if untyped.
+    kind: String,
+    name: String,
+    /// Whether the prim is a `class`, which is never rendered itself.
+    class: bool,
+    active: bool,
+    attributes: HashMap<String, Attribute>,
+    children: Vec<Prim>,
+}
+
+impl Prim {
+    fn value(&self, name: &str, time: f64) -> Option<Cow<'_, Value>> {
+        self.attributes.get(name)?.at(time)
+    }
+
+    /// Whether the prim should be rendered at `time`, along with its
+    /// descendants.
+    fn rendered(&self, time: f64) -> bool {
+        let token = |name: &str| {
+            self.value(name, time)
+                .and_then(|v| v.string().map(str::to_owned))
+        };
+        self.active
+            && !self.class
+            && token("visibility").as_deref() != Some("invisible")
+            && !matches!(token("purpose").as_deref(), Some("guide" | "proxy"))
+    }
+
+    /// Add the times of every sample on the prim and its descendants to
+    /// `times`.
+    fn sample_times(&self, times: &mut Vec<f64>) {
+        for attribute in self.attributes.values() {
+            times.extend(attribute.samples.iter().map(|(time, _)| time));
+        }
+        for child in &self.children {
+            child.sample_times(times);
+        }
+    }
+
+    /// The prim's transform relative to its parent at `time`, and whether it
+    /// ignores its parent's transform.
+    fn local_transform(&self, time: f64) -> Result<(Transform, bool)> {
+        let Some(order) = self.value("xformOpOrder", time) else {
+            return Ok((Transform::identity(), false));
+        };
+        let order = order
+            .list()
+            .context("xformOpOrder must be a list")?
+            .iter()
+            .map(|op| op.string().context("xformOpOrder must be a list of names"))
+            .collect::<Result<Vec<_>>>()?;
+
+        // ops are listed outermost first
+        let mut transform = Transform::identity();
+        let mut reset = false;
+        for op in order.iter().rev() {
+            if *op == "!resetXformStack!" {
+                reset = true;
+                continue;
+            }
+            let (invert, name) = match op.strip_prefix("!invert!") {
+                Some(name) => (true, name),
+                None => (false, *op),
+            };
+            let value = self
+                .value(name, time)
+                .with_context(|| format!("Missing {name}"))?;
+            let mut op_transform =
+                xform_op(name, &value).with_context(|| format!("Invalid {name}"))?;
+            if invert {
+                op_transform = op_transform.inverse();
+            }
+            transform = transform.then(&op_transform);
+        }
+        Ok((transform, reset))
+    }
+}
+
+/// The [`Transform`] of the transform op `name` with `value`.
+fn xform_op(name: &str, value: &Value) -> Result<Transform> {
+    let op = name
+        .strip_prefix("xformOp:")
+        .context("Transform ops must start with xformOp:")?;
+    let op = op.split(':').next().unwrap_or_default();
+    let vector = || value.vec3().context("Expected three numbers");
+    let number = || value.number().context("Expected a number");
+
+    Ok(match op {
+        "translate" => Transform::translate(vector()?),
+        "scale" => {
+            let [x, y, z] = vector()?.e;
+            let m = [[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, z]];
+            Transform::from_matrix(m, vec3![0.0, 0.0, 0.0])
+                .context("Scale factors may not be zero")?
+        }
+        "rotateX" => Transform::rotate(vec3![1.0, 0.0, 0.0], number()?),
+        "rotateY" => Transform::rotate(vec3![0.0, 1.0, 0.0], number()?),
+        "rotateZ" => Transform::rotate(vec3![0.0, 0.0, 1.0], number()?),
+        "orient" => {
+            // quaternions are written real part first
+            let quaternion = value
+                .list()
+                .and_then(|parts| parts.iter().map(Value::number).collect::<Option<Vec<_>>>())
+                .filter(|parts| parts.len() == 4)
+                .context("Expected a quaternion")?;
+            let axis = vec3![quaternion[1], quaternion[2], quaternion[3]];
+            if axis.length_squared() == 0.0 {
+                Transform::identity()
+            } else {
+                let angle = 2.0 * axis.length().atan2(quaternion[0]);
+                Transform::rotate(axis, angle.to_degrees())
+            }
+        }
+        "transform" => {
+            let rows = value
+                .list()
+                .filter(|rows| rows.len() == 4)
+                .and_then(|rows| {
+                    rows.iter()
+                        .map(|row| {
+                            let row = row.list().filter(|row| row.len() == 4)?;
+                            row.iter().map(Value::number).collect::<Option<Vec<_>>>()
+                        })
+                        .collect::<Option<Vec<_>>>()
+                })
+                .context("Expected a 4x4 matrix")?;
+            // USD matrices multiply row vectors, so are the transpose of ours
+            let m = [0, 1, 2].map(|i| [0, 1, 2].map(|j| rows[j][i]));
+            Transform::from_matrix(m, vec3![rows[3][0], rows[3][1], rows[3][2]])
+                .context("The matrix can't be inverted")?
+        }
+        _ => match op.strip_prefix("rotate") {
+            // three axis rotations, applied in the order named
+            Some(axes) if axes.len() == 3 && axes.chars().all(|axis| "XYZ".contains(axis)) => {
+                let degrees = vector()?;
+                axes.bytes().fold(Transform::identity(), |transform, axis| {
+                    let i = usize::from(axis - b'X');
+                    let mut unit = vec3![0.0, 0.0, 0.0];
+                    unit[i] = 1.0;
+                    transform.then(&Transform::rotate(unit, degrees[i]))
+                })
+            }
+            _ => bail!("Unsupported transform op {op}"),
+        },
+    })
+}
+
+/// An attribute's value, optionally varying over time.
+#[derive(Debug, Clone, Default)]
+struct Attribute {
+    default: Option<Value>,
+    /// Samples sorted by time.
+    samples: Vec<(f64, Value)>,
+    interpolation: Option<String>,
+}
+
+impl Attribute {
+    /// The value at `time`, interpolating linearly between time samples and
+    /// holding the first and last, or [`None`] if there isn't one.
+    fn at(&self, time: f64) -> Option<Cow<'_, Value>> {
+        let value = if self.samples.is_empty() {
+            Cow::Borrowed(self.default.as_ref()?)
+        } else {
+            let next = self.samples.partition_point(|(t, _)| *t < time);
+            if next == 0 {
+                Cow::Borrowed(&self.samples[0].1)
+            } else if next == self.samples.len() {
+                Cow::Borrowed(&self.samples[next - 1].1)
+            } else {
+                let (t0, before) = &self.samples[next - 1];
+                let (t1, after) = &self.samples[next];
+                // `t1` isn't before `time`, so this is an exact match
+                if *t1 <= time {
+                    Cow::Borrowed(after)
+                } else {
+                    Cow::Owned(before.lerp(after, (time - t0) / (t1 - t0)))
+                }
+            }
+        };
+        // blocked values are unset
+        (*value != Value::Ident("None".to_owned())).then_some(value)
+    }
+}
+
+/// A parsed attribute or metadata value. Tuples and arrays are both lists.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    String(String),
+    Ident(String),
+    List(Vec<Value>),
+    TimeSamples(Vec<(f64, Value)>),
+    /// Anything not needed, such as paths and dictionaries.
+    Other,
+}
+
+impl Value {
+    fn number(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            Self::Ident(ident) if ident == "true" => Some(1.0),
+            Self::Ident(ident) if ident == "false" => Some(0.0),
+            _ => None,
+        }
+    }
+
+    fn string(&self) -> Option<&str> {
+        match self {
+            Self::String(string) | Self::Ident(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    fn list(&self) -> Option<&[Value]> {
+        match self {
+            Self::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    fn vec3(&self) -> Option<Vec3> {
+        match self.list()? {
+            [x, y, z] => Some(vec3![x.number()?, y.number()?, z.number()?]),
+            _ => None,
+        }
+    }
+
+    /// Interpolate numbers and lists of numbers of the same shape, holding
+    /// anything else at `self`.
+    fn lerp(&self, other: &Value, a: f64) -> Value {
+        match (self, other) {
+            (Self::Number(x), Self::Number(y)) => Self::Number(x + (y - x) * a),
+            (Self::List(xs), Self::List(ys)) if xs.len() == ys.len() => {
+                Self::List(xs.iter().zip(ys).map(|(x, y)| x.lerp(y, a)).collect())
+            }
+            _ => self.clone(),
+        }
+    }
+}
+
+fn vec3s(value: &Value) -> Result<Vec<Vec3>> {
+    value
+        .list()
+        .context("Expected a list")?
+        .iter()
+        .map(Value::vec3)
+        .collect::<Option<_>>()
+        .context("Expected a list of three numbers each")
+}
+
+fn integers(value: &Value) -> Result<Vec<usize>> {
+    value
+        .list()
+        .context("Expected a list")?
+        .iter()
+        .map(|number| {
+            let number = number
+                .number()
+                .filter(|number| *number >= 0.0 && number.fract() == 0.0)
+                .context("Expected non-negative integers")?;
+            #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Ok(number as usize)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    String(String),
+    Number(f64),
+    /// A prim or property path, `<...>`.
+    Path,
+    /// An asset path, `@...@`.
+    Asset,
+    Punct(char),
+}
+
+/// Split `text` into [`Token`]s, each with the line it's on.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '#' => {
+                take_until(&mut chars, '\n');
+                line += 1;
+                continue;
+            }
+            '"' | '\'' => {
+                let triple = chars.next_if_eq(&c).is_some();
+                let mut string = String::new();
+                if triple && chars.next_if_eq(&c).is_none() {
+                    // just an empty string
+                } else {
+                    loop {
+                        let Some(next) = chars.next() else {
+                            bail!("Line {line}: unterminated string");
+                        };
+                        match next {
+                            '\\' => string.extend(chars.next()),
+                            next if next == c => {
+                                if !triple {
+                                    break;
+                                }
+                                if chars.next_if_eq(&c).is_some() {
+                                    if chars.next_if_eq(&c).is_some() {
+                                        break;
+                                    }
+                                    string.push(c);
+                                }
+                                string.push(c);
+                            }
+                            next => {
+                                line += usize::from(next == '\n');
+                                string.push(next);
+                            }
+                        }
+                    }
+                }
+                Token::String(string)
+            }
+            '<' => {
+                take_until(&mut chars, '>')
+                    .with_context(|| format!("Line {line}: unterminated path"))?;
+                Token::Path
+            }
+            '@' => {
+                take_until(&mut chars, '@')
+                    .with_context(|| format!("Line {line}: unterminated asset path"))?;
+                Token::Asset
+            }
+            '(' | ')' | '[' | ']' | '{' | '}' | '=' | ',' | ';' | ':' => Token::Punct(c),
+            c if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let mut number = c.to_string();
+                while let Some(next) =
+                    chars.next_if(|&next| next.is_ascii_alphanumeric() || "+-.".contains(next))
+                {
+                    number.push(next);
+                }
+                Token::Number(match number.trim_start_matches(['-', '+']) {
+                    "inf" => f64::INFINITY.copysign(if c == '-' { -1.0 } else { 1.0 }),
+                    _ => number
+                        .parse()
+                        .with_context(|| format!("Line {line}: invalid number {number}"))?,
+                })
+            }
+            c if c.is_alphabetic() || c == '_' || c == '!' => {
+                let mut ident = c.to_string();
+                while let Some(next) =
+                    chars.next_if(|&next| next.is_alphanumeric() || "_:.!".contains(next))
+                {
+                    ident.push(next);
+                }
+                // array types
+                if chars.next_if_eq(&'[').is_some() {
+                    if chars.next_if_eq(&']').is_none() {
+                        bail!("Line {line}: expected ']' after {ident}[");
+                    }
+                    ident.push_str("[]");
+                }
+                match ident.as_str() {
+                    "inf" => Token::Number(f64::INFINITY),
+                    "nan" => Token::Number(f64::NAN),
+                    _ => Token::Ident(ident),
+                }
+            }
+            c => bail!("Line {line}: unexpected {c:?}"),
+        };
+        tokens.push((token, line));
+    }
+    Ok(tokens)
+}
+
+/// Take the characters up to `end`, dropping it, or [`None`] if there isn't
+/// one.
+fn take_until(chars: &mut Peekable<Chars>, end: char) -> Option<String> {
+    let mut taken = String::new();
+    for c in chars.by_ref() {
+        if c == end {
+            return Some(taken);
+        }
+        taken.push(c);
+    }
+    None
+}
+
+/// Specifiers that start a prim.
+const SPECIFIERS: [&str; 3] = ["def", "over", "class"];
+
+/// Parses prims and values from a list of [`Token`]s.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + ahead).map(|(token, _)| token)
+    }
+
+    /// The line of the next token, or the last if there are none left.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let Some((token, _)) = self.tokens.get(self.pos) else {
+            bail!("Unexpected end of file");
+        };
+        self.pos += 1;
+        Ok(token.clone())
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(punct));
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn expect(&mut self, punct: char) -> Result<()> {
+        if !self.eat(punct) {
+            bail!("Line {}: expected '{punct}'", self.line());
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        let line = self.line();
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            token => bail!("Line {line}: expected a name, found {token:?}"),
+        }
+    }
+
+    /// Skip a bracketed group, having just passed its opening `open`.
+    fn skip_group(&mut self, open: char) -> Result<()> {
+        let close = match open {
+            '(' => ')',
+            '[' => ']',
+            _ => '}',
+        };
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Punct(c) if c == open => depth += 1,
+                Token::Punct(c) if c == close => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a parenthesised list of metadata, having just passed the `(`.
+    fn metadata(&mut self) -> Result<HashMap<String, Value>> {
+        let mut metadata = HashMap::new();
+        while !self.eat(')') {
+            match self.next()? {
+                // documentation and separators
+                Token::String(_) | Token::Punct(';') => {}
+                Token::Ident(mut key) => {
+                    if matches!(
+                        key.as_str(),
+                        "prepend" | "append" | "add" | "delete" | "reorder"
+                    ) {
+                        key = self.ident()?;
+                    }
+                    self.expect('=')?;
+                    let value = self.value()?;
+                    metadata.insert(key, value);
+                }
+                token => bail!("Line {}: unexpected {token:?} in metadata", self.line()),
+            }
+        }
+        Ok(metadata)
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        let line = self.line();
+        Ok(match self.next()? {
+            Token::Number(number) => Value::Number(number),
+            Token::String(string) => Value::String(string),
+            Token::Ident(ident) => Value::Ident(ident),
+            Token::Path => Value::Other,
+            Token::Asset => {
+                // references can target a prim in the asset
+                if self.peek() == Some(&Token::Path) {
+                    self.pos += 1;
+                }
+                Value::Other
+            }
+            Token::Punct(open @ ('(' | '[')) => {
+                let close = if open == '(' { ')' } else { ']' };
+                let mut list = vec![];
+                while !self.eat(close) {
+                    list.push(self.value()?);
+                    if !self.eat(',') {
+                        self.expect(close)?;
+                        break;
+                    }
+                }
+                Value::List(list)
+            }
+            Token::Punct('{') => {
+                let time_samples = matches!(
+                    (self.peek(), self.peek_at(1)),
+                    (Some(Token::Number(_)), Some(Token::Punct(':')))
+                );
+                if !time_samples {
+                    self.skip_group('{')?;
+                    return Ok(Value::Other);
+                }
+
+                let mut samples = vec![];
+                while !self.eat('}') {
+                    let line = self.line();
+                    let Token::Number(time) = self.next()? else {
+                        bail!("Line {line}: expected a time code");
+                    };
+                    self.expect(':')?;
+                    samples.push((time, self.value()?));
+                    if !self.eat(',') {
+                        self.expect('}')?;
+                        break;
+                    }
+                }
+                samples.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+                Value::TimeSamples(samples)
+            }
+            token @ Token::Punct(_) => bail!("Line {line}: expected a value, found {token:?}"),
+        })
+    }
+
+    /// Parse a prim, starting from its specifier.
+    fn prim(&mut self) -> Result<Prim> {
+        let line = self.line();
+        let specifier = self.ident()?;
+        if !SPECIFIERS.contains(&specifier.as_str()) {
+            bail!("Line {line}: expected a prim, found {specifier}");
+        }
+        let kind = match self.peek() {
+            Some(Token::Ident(_)) => self.ident()?,
+            _ => String::new(),
+        };
+        let line = self.line();
+        let Token::String(name) = self.next()? else {
+            bail!("Line {line}: expected the prim's name");
+        };
+
+        let metadata = if self.eat('(') {
+            self.metadata()?
+        } else {
+            HashMap::new()
+        };
+        let mut prim = Prim {
+            kind,
+            name,
+            class: specifier == "class",
+            active: metadata.get("active").and_then(Value::number) != Some(0.0),
+            ..Prim::default()
+        };
+
+        self.expect('{')?;
+        while !self.eat('}') {
+            match self.peek() {
+                Some(Token::Ident(ident)) if SPECIFIERS.contains(&ident.as_str()) => {
+                    let child = self.prim()?;
+                    prim.children.push(child);
+                }
+                Some(Token::Ident(ident)) if ident == "variantSet" => {
+                    self.pos += 2;
+                    self.expect('=')?;
+                    self.expect('{')?;
+                    self.skip_group('{')?;
+                }
+                Some(Token::Punct(';')) => self.pos += 1,
+                _ => self.property(&mut prim)?,
+            }
+        }
+        Ok(prim)
+    }
+
+    /// Parse an attribute or relationship of `prim`, keeping attributes.
+    fn property(&mut self, prim: &mut Prim) -> Result<()> {
+        // qualifiers, type and name are all on the first line
+        let line = self.line();
+        let mut header = vec![];
+        while let Some((Token::Ident(ident), ident_line)) = self.tokens.get(self.pos) {
+            if *ident_line != line {
+                break;
+            }
+            header.push(ident.clone());
+            self.pos += 1;
+        }
+        let Some(name) = header.last() else {
+            bail!("Line {line}: expected a property, found {:?}", self.peek());
+        };
+
+        let value = if self.eat('=') {
+            Some(self.value()?)
+        } else {
+            None
+        };
+        let metadata = if self.eat('(') {
+            self.metadata()?
+        } else {
+            HashMap::new()
+        };
+
+        let relationship = header.iter().any(|word| word == "rel");
+        let list_edit = matches!(
+            header[0].as_str(),
+            "prepend" | "append" | "add" | "delete" | "reorder"
+        );
+        if relationship || list_edit || header.len() < 2 {
+            return Ok(());
+        }
+
+        let (name, timed) = match name.strip_suffix(".timeSamples") {
+            Some(name) => (name, true),
+            None => (name.as_str(), false),
+        };
+        if name.contains('.') {
+            // connections and splines
+            return Ok(());
+        }
+        let attribute = prim.attributes.entry(name.to_owned()).or_default();
+        match value {
+            Some(Value::TimeSamples(samples)) if timed => attribute.samples = samples,
+            Some(_) if timed => bail!("Line {line}: expected time samples for {name}"),
+            Some(value) => attribute.default = Some(value),
+            None => {}
+        }
+        if let Some(interpolation) = metadata.get("interpolation").and_then(Value::string) {
+            attribute.interpolation = Some(interpolation.to_owned());
+        }
+        Ok(())
+    }
+}
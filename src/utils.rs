@@ -3,6 +3,10 @@
 
 use std::f64::consts::PI;
 
+use anyhow::{bail, Result};
+
+use crate::{vec3, vec3::Vec3};
+
 /// Convert degrees to radians.
 ///
 /// # Example
@@ -16,3 +20,176 @@ use std::f64::consts::PI;
 pub fn deg_to_rad(deg: f64) -> f64 {
     (deg * PI) / 180.0
 }
+
+/// Convert equirectangular (latitude/longitude) image coordinates `(u, v)`, both
+/// in `0.0..=1.0` with `v = 0.0` at the top of the image, into a unit
+/// direction. `+Y` is up and the centre of the image looks down `-Z`.
+///
+/// # Example
+/// ```rust
+/// use rustrace::{utils::equirect_to_direction, vec3, vec3::Vec3};
+///
+/// let forward = equirect_to_direction(0.5, 0.5);
+/// assert!((forward - vec3![0.0, 0.0, -1.0]).length() < 1e-12);
+///
+/// let up = equirect_to_direction(0.5, 0.0);
+/// assert!((up - vec3![0.0, 1.0, 0.0]).length() < 1e-12);
+/// ```
+#[must_use]
+pub fn equirect_to_direction(u: f64, v: f64) -> Vec3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+    vec3![
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos()
+    ]
+}
+
+/// Convert a (not necessarily unit) direction into equirectangular image
+/// coordinates. The inverse of [`equirect_to_direction()`].
+///
+/// # Example
+/// ```rust
+/// use rustrace::{utils::{direction_to_equirect, equirect_to_direction}, vec3, vec3::Vec3};
+///
+/// let (u, v) = direction_to_equirect(vec3![1.0, 0.0, 0.0]);
+/// assert!((u - 0.75).abs() < 1e-12);
+/// assert!((v - 0.5).abs() < 1e-12);
+///
+/// let (u, v) = direction_to_equirect(equirect_to_direction(0.3, 0.8));
+/// assert!((u - 0.3).abs() < 1e-12);
+/// assert!((v - 0.8).abs() < 1e-12);
+/// ```
+#[must_use]
+pub fn direction_to_equirect(dir: Vec3) -> (f64, f64) {
+    let d = dir.unit();
+    let u = 0.5 + d[0].atan2(-d[2]) / (2.0 * PI);
+    let v = d[1].clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+/// The real roots within `lo..=hi` of the polynomial with coefficients
+/// `coeffs`, lowest power first, in ascending order. Roots are isolated
+/// between the turning points, found by recursing on the derivative, then
+/// narrowed down by bisection, which stays reliable where closed-form
+/// solutions for cubics and quartics lose precision. Repeated roots that
+/// only touch zero without crossing it can be missed.
+///
+/// # Example
+/// ```rust
+/// use rustrace::utils::polynomial_roots;
+///
+/// // (x - 1)(x - 2)(x - 3)(x - 4)
+/// let roots = polynomial_roots(&[24.0, -50.0, 35.0, -10.0, 1.0], 0.0, 10.0);
+/// assert_eq!(roots.len(), 4);
+/// for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+///     assert!((root - expected).abs() < 1e-9);
+/// }
+///
+/// assert_eq!(polynomial_roots(&[24.0, -50.0, 35.0, -10.0, 1.0], 2.5, 3.5).len(), 1);
+/// ```
+#[must_use]
+pub fn polynomial_roots(coeffs: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    // drop vanishing leading terms
+    let degree = coeffs.iter().rposition(|&c| c != 0.0).unwrap_or(0);
+    let coeffs = &coeffs[..=degree];
+    let eval = |x: f64| coeffs.iter().rev().fold(0.0, |acc, &c| acc * x + c);
+
+    match degree {
+        0 => return vec![],
+        1 => {
+            let root = -coeffs[0] / coeffs[1];
+            return if (lo..=hi).contains(&root) {
+                vec![root]
+            } else {
+                vec![]
+            };
+        }
+        _ => {}
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    let derivative: Vec<f64> = coeffs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(power, &c)| c * power as f64)
+        .collect();
+
+    let mut edges = vec![lo];
+    edges.extend(polynomial_roots(&derivative, lo, hi));
+    edges.push(hi);
+
+    let mut roots: Vec<f64> = vec![];
+    for pair in edges.windows(2) {
+        let (mut start, mut end) = (pair[0], pair[1]);
+        let (start_value, end_value) = (eval(start), eval(end));
+        if start_value == 0.0 {
+            if roots.last() != Some(&start) {
+                roots.push(start);
+            }
+            continue;
+        }
+        if start_value.signum() == end_value.signum() {
+            continue;
+        }
+
+        // monotonic between turning points, so there's exactly one crossing
+        let rising = end_value > start_value;
+        for _ in 0..100 {
+            let mid = 0.5 * (start + end);
+            if mid <= start || mid >= end {
+                break;
+            }
+            if (eval(mid) < 0.0) == rising {
+                start = mid;
+            } else {
+                end = mid;
+            }
+        }
+        roots.push(0.5 * (start + end));
+    }
+    if eval(hi) == 0.0 && roots.last() != Some(&hi) {
+        roots.push(hi);
+    }
+    roots
+}
+
+/// Decode `%XX` escapes in a URI, as file names with spaces and query
+/// parameters are written. Anything that isn't a valid escape is kept as is.
+pub(crate) fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Check the `value` of the parameter `what` is between 0 and 1, as most
+/// material parameters must be.
+///
+/// # Errors
+///
+/// If `value` is outside of `0.0..=1.0`.
+pub(crate) fn check_unit(what: &str, value: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&value) {
+        bail!("{what} must be between 0 and 1, found {value}");
+    }
+    Ok(())
+}
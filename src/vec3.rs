@@ -5,6 +5,7 @@ use std::ops::{
 };
 
 use image::Rgb;
+use multiversion::multiversion;
 
 use crate::interval;
 use interval::Interval;
@@ -33,6 +34,22 @@ impl Vec3 {
         Vec3 { e: [x, y, z] }
     }
 
+    /// Calculate the relative luminance of the [`Vec3`] as a linear Rec. 709
+    /// colour.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{vec3, vec3::Vec3};
+    ///
+    /// assert!((vec3![1.0, 1.0, 1.0].luminance() - 1.0).abs() < 1e-12);
+    /// assert!(vec3![0.0, 1.0, 0.0].luminance() > vec3![1.0, 0.0, 0.0].luminance());
+    /// ```
+    #[must_use]
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self[0] + 0.7152 * self[1] + 0.0722 * self[2]
+    }
+
     /// Calculate the length squared of the [`Vec3`].
     ///
     /// # Example
@@ -156,6 +173,38 @@ impl Vec3 {
         (self[0].abs() < eps) && (self[1].abs() < eps) && (self[2].abs() < eps)
     }
 
+    /// Build two unit [`Vec3`]s that together with `self` (assumed to be a unit
+    /// vector) form an orthonormal basis. Uses the branchless construction from
+    /// Duff et al. so it is stable for any input direction.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{vec3, vec3::{Vec3, dot}};
+    ///
+    /// let n = vec3![0.0, 0.0, -1.0];
+    /// let (t, b) = n.orthonormal_basis();
+    ///
+    /// assert!(dot(&n, &t).abs() < 1e-12);
+    /// assert!(dot(&n, &b).abs() < 1e-12);
+    /// assert!(dot(&t, &b).abs() < 1e-12);
+    /// assert!((t.length() - 1.0).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let sign = 1.0_f64.copysign(self[2]);
+        let a = -1.0 / (sign + self[2]);
+        let b = self[0] * self[1] * a;
+        (
+            vec3![
+                1.0 + sign * self[0] * self[0] * a,
+                sign * b,
+                -sign * self[0]
+            ],
+            vec3![b, sign + self[1] * self[1] * a, -self[1]],
+        )
+    }
+
     /// Generate a random [`Vec3`] with elements between `0.0` and `1.0` inclusive.
     pub fn random(rng: &mut SmallRng) -> Self {
         vec3![
@@ -281,9 +330,56 @@ pub fn cross(u: &Vec3, v: &Vec3) -> Vec3 {
     ]
 }
 
+/// Sum a slice of [`Vec3`]s, with the same runtime SIMD dispatch as
+/// [`ray_hit_batch()`](crate::aabb::ray_hit_batch).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{vec3, vec3::{sum, Vec3}};
+///
+/// let values = [vec3![1.0, 2.0, 3.0], vec3![4.0, 5.0, 6.0]];
+///
+/// assert_eq!(sum(&values), vec3![5.0, 7.0, 9.0]);
+/// assert_eq!(sum(&[]), vec3![0.0, 0.0, 0.0]);
+/// ```
+#[must_use]
+#[multiversion(targets("x86_64+avx512f+avx512vl", "x86_64+avx2+fma", "aarch64+neon"))]
+pub fn sum(values: &[Vec3]) -> Vec3 {
+    let mut total = [0.0; 3];
+    for v in values {
+        for (t, x) in total.iter_mut().zip(v.e) {
+            *t += x;
+        }
+    }
+    Vec3 { e: total }
+}
+
+/// Add `offset` to every [`Vec3`] in `points`, with the same runtime SIMD
+/// dispatch as [`ray_hit_batch()`](crate::aabb::ray_hit_batch).
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{vec3, vec3::{offset_all, Vec3}};
+///
+/// let mut points = [vec3![0.0, 0.0, 0.0], vec3![1.0, 1.0, 1.0]];
+/// offset_all(&mut points, vec3![1.0, 0.0, -1.0]);
+///
+/// assert_eq!(points, [vec3![1.0, 0.0, -1.0], vec3![2.0, 1.0, 0.0]]);
+/// ```
+#[multiversion(targets("x86_64+avx512f+avx512vl", "x86_64+avx2+fma", "aarch64+neon"))]
+pub fn offset_all(points: &mut [Vec3], offset: Vec3) {
+    for p in points {
+        for (x, o) in p.e.iter_mut().zip(offset.e) {
+            *x += o;
+        }
+    }
+}
+
 /// Convert linear values to gamma values. Essentially an [`f64::sqrt`] with
 /// a check that the operand is positive.
-fn linear_to_gamma(linear_component: f64) -> f64 {
+pub(crate) fn linear_to_gamma(linear_component: f64) -> f64 {
     if linear_component > 0.0 {
         linear_component.sqrt()
     } else {
@@ -0,0 +1,347 @@
+//! This module contains [`Vegetation`], which covers a ground mesh in clumps of
+//! grass swaying in the [`Wind`]. Each clump is an [`Animated`] instance of one
+//! of a few shared clump meshes, keyframed over the camera shutter, so large
+//! fields stay small in memory and are motion blurred as they move.
+//!
+//! Blades are thin tapered triangle strips, curving as they lean, and the wind
+//! tilts each clump about its root.
+
+use std::{f64::consts::TAU, sync::Arc};
+
+use crate::{
+    hit_list::HittableList,
+    interval::Interval,
+    material::Material,
+    mesh::{FaceVertex, Mesh, MeshData},
+    noise::{HashRng, Noise},
+    transform::{Animated, Keyframe},
+    vec3,
+    vec3::{cross, dot, Vec3},
+};
+
+/// The number of distinct clump meshes instanced over the ground.
+const VARIANTS: usize = 8;
+
+/// The number of segments along each blade.
+const SEGMENTS: usize = 4;
+
+/// The number of [`Keyframe`]s sampled over the shutter for each clump.
+const KEYS: u32 = 5;
+
+/// A steady wind with gusts rolling across the field, tilting vegetation
+/// downwind.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{vec3, vec3::Vec3, vegetation::Wind};
+///
+/// let wind = Wind {
+///     direction: vec3![1.0, 0.0, 0.0],
+///     strength: 20.0,
+///     period: 2.0,
+///     wavelength: 4.0,
+/// };
+///
+/// // always leaning downwind, by up to the strength
+/// let lean = wind.lean_at(vec3![0.0, 0.0, 0.0], 0.0);
+/// assert!((0.0..=20.0).contains(&lean));
+///
+/// // the same gust reaches a wavelength downwind a period later
+/// let later = wind.lean_at(vec3![4.0, 0.0, 0.0], 2.0);
+/// assert!((lean - later).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wind {
+    /// The direction the wind blows towards. Only its horizontal part is
+    /// used.
+    pub direction: Vec3,
+    /// The largest tilt in degrees, at the peak of a gust.
+    pub strength: f64,
+    /// The time between gusts.
+    pub period: f64,
+    /// The distance between gust fronts.
+    pub wavelength: f64,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: vec3![1.0, 0.0, 0.0],
+            strength: 15.0,
+            period: 2.0,
+            wavelength: 3.0,
+        }
+    }
+}
+
+impl Wind {
+    /// The tilt downwind in degrees of vegetation rooted at `position` at
+    /// `time`, easing between upright and [`Wind::strength`] as each gust
+    /// passes.
+    #[must_use]
+    pub fn lean_at(&self, position: Vec3, time: f64) -> f64 {
+        let along = dot(&self.heading(), &position);
+        let phase = TAU * (time / self.period - along / self.wavelength);
+        self.strength * 0.5 * (1.0 - phase.cos())
+    }
+
+    /// The horizontal unit direction of the wind, or `+X` if it has none.
+    fn heading(&self) -> Vec3 {
+        let flat = vec3![self.direction[0], 0.0, self.direction[2]];
+        if flat.near_zero() {
+            vec3![1.0, 0.0, 0.0]
+        } else {
+            flat.unit()
+        }
+    }
+}
+
+/// Clumps of grass scattered over a ground mesh, see the
+/// [module docs](crate::vegetation). The same seed always gives the same
+/// field.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{
+///     hit::Hittable,
+///     interval,
+///     interval::Interval,
+///     material::Lambertian,
+///     mesh::{FaceVertex, MeshData},
+///     vec3,
+///     vec3::Vec3,
+///     vegetation::Vegetation,
+/// };
+///
+/// // a 4 by 4 square of ground facing up
+/// let corner = |position| FaceVertex { position, normal: None, uv: None };
+/// let ground = MeshData {
+///     positions: vec![
+///         vec3![-2.0, 0.0, -2.0],
+///         vec3![-2.0, 0.0, 2.0],
+///         vec3![2.0, 0.0, 2.0],
+///         vec3![2.0, 0.0, -2.0],
+///     ],
+///     faces: vec![
+///         [corner(0), corner(1), corner(2)],
+///         [corner(0), corner(2), corner(3)],
+///     ],
+///     ..MeshData::default()
+/// };
+///
+/// let grass = Vegetation::new(50).set_height(0.5).set_seed(3);
+/// let field = grass.populate(
+///     &ground,
+///     &Lambertian::new(vec3![0.2, 0.5, 0.1]),
+///     interval![0.0, 0.04],
+/// );
+///
+/// assert_eq!(field.objects.len(), 50);
+/// // the bounds of tilted clumps are a little loose
+/// let bound = field.bound();
+/// assert!(bound.y.min > -0.1 && bound.y.max < 0.6);
+/// assert!(bound.x.min > -2.5 && bound.x.max < 2.5);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vegetation {
+    clumps: usize,
+    blades: u32,
+    height: f64,
+    blade_width: f64,
+    clump_radius: f64,
+    wind: Wind,
+    seed: u64,
+}
+
+impl Vegetation {
+    /// Create a [`Vegetation`] of `clumps` clumps, each of 12 blades up to
+    /// `0.3` high within `0.05` of its root, in the default [`Wind`].
+    #[must_use]
+    pub fn new(clumps: usize) -> Self {
+        Self {
+            clumps,
+            blades: 12,
+            height: 0.3,
+            blade_width: 0.01,
+            clump_radius: 0.05,
+            wind: Wind::default(),
+            seed: 0,
+        }
+    }
+
+    /// Set the number of blades in each clump.
+    #[must_use]
+    pub fn set_blades(self, blades: u32) -> Self {
+        Self { blades, ..self }
+    }
+
+    /// Set the height of the tallest blades. Blades range from half of it.
+    #[must_use]
+    pub fn set_height(self, height: f64) -> Self {
+        Self { height, ..self }
+    }
+
+    /// Set the width of each blade at its root, tapering to a point.
+    #[must_use]
+    pub fn set_blade_width(self, blade_width: f64) -> Self {
+        Self {
+            blade_width,
+            ..self
+        }
+    }
+
+    /// Set how far from the centre of a clump its blades grow.
+    #[must_use]
+    pub fn set_clump_radius(self, clump_radius: f64) -> Self {
+        Self {
+            clump_radius,
+            ..self
+        }
+    }
+
+    /// Set the [`Wind`].
+    #[must_use]
+    pub fn set_wind(self, wind: Wind) -> Self {
+        Self { wind, ..self }
+    }
+
+    /// Set the seed the clumps and their placement derive from.
+    #[must_use]
+    pub fn set_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// Scatter the clumps over the upward facing parts of `ground`, weighted
+    /// by area, with blades of material `mat`. Each clump is keyframed over
+    /// `shutter` to sway in the [`Wind`], so render each frame of an animation
+    /// with a field populated for that frame's shutter.
+    ///
+    /// Returns an empty list if `ground` has nothing facing up.
+    #[must_use]
+    pub fn populate(
+        &self,
+        ground: &MeshData,
+        mat: &dyn Material,
+        shutter: Interval,
+    ) -> HittableList {
+        let noise = Noise::new(self.seed).stream("vegetation");
+        let mut field = HittableList::new();
+
+        // faces by their upward facing area
+        let mut total = 0.0;
+        let cumulative: Vec<(f64, [Vec3; 3])> = ground
+            .faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] = ground.face_positions(face);
+                let doubled = cross(&(b - a), &(c - a));
+                total += doubled[1].max(0.0) * 0.5;
+                (total, [a, b, c])
+            })
+            .collect();
+        if total <= 0.0 {
+            return field;
+        }
+
+        let variants: Vec<Arc<Mesh>> = (0..VARIANTS)
+            .map(|variant| {
+                #[expect(clippy::cast_possible_wrap)]
+                let mut rng = noise.rng([variant as i64, 0, 0]);
+                Arc::new(Mesh::from_data(self.clump(&mut rng), mat))
+            })
+            .collect();
+
+        // keep the downwind direction along the clump's +Z
+        let heading = self.wind.heading();
+        let yaw = heading[0].atan2(heading[2]).to_degrees();
+        for clump in 0..self.clumps {
+            #[expect(clippy::cast_possible_wrap)]
+            let mut rng = noise.rng([clump as i64, 1, 0]);
+
+            let pick = rng.next_unit() * total;
+            let face = cumulative.partition_point(|&(area, _)| area < pick);
+            let [origin, b, c] = cumulative[face.min(cumulative.len() - 1)].1;
+            let (mut along_b, mut along_c) = (rng.next_unit(), rng.next_unit());
+            if along_b + along_c > 1.0 {
+                (along_b, along_c) = (1.0 - along_b, 1.0 - along_c);
+            }
+            let root = origin + (b - origin) * along_b + (c - origin) * along_c;
+
+            let keyframes = (0..KEYS)
+                .map(|key| {
+                    let time = shutter.min + shutter.size() * f64::from(key) / f64::from(KEYS - 1);
+                    Keyframe::at(time, root).with_rotation(vec3![
+                        self.wind.lean_at(root, time),
+                        yaw,
+                        0.0
+                    ])
+                })
+                .collect();
+
+            #[expect(clippy::cast_possible_truncation)]
+            let variant = rng.next_u64() as usize % VARIANTS;
+            field.add(Box::new(Animated::new(
+                Arc::clone(&variants[variant]),
+                keyframes,
+            )));
+        }
+
+        field
+    }
+
+    /// The geometry of one clump rooted at the origin, growing up `+Y`.
+    fn clump(&self, rng: &mut HashRng) -> MeshData {
+        let mut data = MeshData::default();
+        let corner = |position| FaceVertex {
+            position,
+            normal: None,
+            uv: None,
+        };
+
+        for _ in 0..self.blades {
+            let spot = TAU * rng.next_unit();
+            let distance = self.clump_radius * rng.next_unit().sqrt();
+            let root = vec3![spot.cos() * distance, 0.0, spot.sin() * distance];
+
+            // lean outwards from the middle, turning the flat side to face it
+            let height = self.height * (0.5 + 0.5 * rng.next_unit());
+            let curl = 0.2 + 0.4 * rng.next_unit();
+            let lean = vec3![spot.cos(), 0.0, spot.sin()];
+            let side = vec3![-spot.sin(), 0.0, spot.cos()] * (self.blade_width * 0.5);
+
+            // a single point at the tip, pairs below it
+            let start = data.positions.len();
+            #[expect(clippy::cast_precision_loss)]
+            for segment in 0..=SEGMENTS {
+                let along = segment as f64 / SEGMENTS as f64;
+                // keep the length about the same however far it curls
+                let rise = height * along * (1.0 - 0.5 * curl * curl * along * along);
+                let centre = root + vec3![0.0, rise, 0.0] + lean * (height * curl * along * along);
+                if segment == SEGMENTS {
+                    data.positions.push(centre);
+                } else {
+                    let taper = 1.0 - along;
+                    data.positions.push(centre - side * taper);
+                    data.positions.push(centre + side * taper);
+                }
+            }
+
+            for segment in 0..SEGMENTS {
+                let (left, right) = (start + 2 * segment, start + 2 * segment + 1);
+                if segment + 1 == SEGMENTS {
+                    data.faces
+                        .push([corner(left), corner(right), corner(left + 2)]);
+                } else {
+                    data.faces
+                        .push([corner(left), corner(right), corner(left + 3)]);
+                    data.faces
+                        .push([corner(left), corner(left + 3), corner(left + 2)]);
+                }
+            }
+        }
+
+        data
+    }
+}
@@ -0,0 +1,1210 @@
+//! This module contains the [`VoxelGrid`] struct, a dense grid of density and
+//! temperature values, and the [`FireVolume`] hittable which ray-marches a
+//! grid that both scatters and emits blackbody light, for rendering fire and
+//! explosion caches, and the [`DensityVolume`] hittable which delta tracks a
+//! grid that only scatters, for smoke and clouds. Mostly empty grids can be
+//! accelerated with a sparse voxel octree, see [`VolumeAcceleration`]. Light
+//! scatters off them by a phase function, [`Isotropic`] or
+//! [`HenyeyGreenstein`], which are also [material](Material)s of their own.
+
+use std::{f64::consts::PI, fs, path::Path, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use rand::{rngs::SmallRng, RngExt as _, SeedableRng};
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    noise::Noise,
+    ray,
+    ray::Ray,
+    vec3,
+    vec3::Vec3,
+};
+
+/// Temperature in kelvin at which [`blackbody()`] has a brightness of `1.0`.
+const BLACKBODY_REFERENCE: f64 = 1500.0;
+
+/// The most voxels along each side of a leaf of an [`OctreeNode`].
+const OCTREE_LEAF_VOXELS: usize = 4;
+
+/// A dense grid of voxels spanning `bounds`, each holding a density and a
+/// temperature in kelvin. Values are stored at voxel centres and sampled with
+/// trilinear interpolation.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{aabb::Aabb, interval, interval::Interval, vec3, vec3::Vec3, volume::VoxelGrid};
+///
+/// let bounds = Aabb {
+///     x: interval![0.0, 2.0],
+///     y: interval![0.0, 2.0],
+///     z: interval![0.0, 2.0],
+/// };
+///
+/// // hotter and denser towards +x
+/// let grid = VoxelGrid::from_fn([2, 2, 2], bounds, |p| (p[0], 1000.0 * p[0]));
+///
+/// assert_eq!(grid.sample(vec3![0.5, 1.0, 1.0]), (0.5, 500.0));
+/// assert_eq!(grid.sample(vec3![1.0, 1.0, 1.0]), (1.0, 1000.0));
+/// assert_eq!(grid.sample(vec3![3.0, 1.0, 1.0]), (0.0, 0.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    dims: [usize; 3],
+    bounds: Aabb,
+    density: Vec<f64>,
+    temperature: Vec<f64>,
+}
+
+impl VoxelGrid {
+    /// Create a new [`VoxelGrid`] of `dims` voxels spanning `bounds`, with
+    /// zero density and temperature everywhere.
+    ///
+    /// # Panics
+    ///
+    /// If any of the dimensions are `0`.
+    #[must_use]
+    pub fn new(dims: [usize; 3], bounds: Aabb) -> Self {
+        assert!(
+            dims.iter().all(|&d| d > 0),
+            "Grid dimensions must be non-zero"
+        );
+        let len = dims[0] * dims[1] * dims[2];
+        Self {
+            dims,
+            bounds,
+            density: vec![0.0; len],
+            temperature: vec![0.0; len],
+        }
+    }
+
+    /// Create a new [`VoxelGrid`] by evaluating `f` at the centre of each
+    /// voxel, which returns `(density, temperature)`.
+    ///
+    /// # Panics
+    ///
+    /// If any of the dimensions are `0`.
+    #[must_use]
+    pub fn from_fn<F: Fn(Vec3) -> (f64, f64)>(dims: [usize; 3], bounds: Aabb, f: F) -> Self {
+        let mut grid = Self::new(dims, bounds);
+        let size = grid.voxel_size();
+        let min = vec3![bounds.x.min, bounds.y.min, bounds.z.min];
+
+        for z in 0..dims[2] {
+            for y in 0..dims[1] {
+                for x in 0..dims[0] {
+                    #[expect(clippy::cast_precision_loss)]
+                    let centre = min
+                        + vec3![
+                            (x as f64 + 0.5) * size[0],
+                            (y as f64 + 0.5) * size[1],
+                            (z as f64 + 0.5) * size[2]
+                        ];
+                    let (density, temperature) = f(centre);
+                    grid.set([x, y, z], density, temperature);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Create a new [`VoxelGrid`] from a raw array of `densities`, with `x`
+    /// varying fastest then `y` then `z`, and zero temperature everywhere.
+    ///
+    /// # Panics
+    ///
+    /// If any of the dimensions are `0`, or there isn't one density per
+    /// voxel.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{aabb::Aabb, interval, interval::Interval, volume::VoxelGrid};
+    ///
+    /// let bounds = Aabb {
+    ///     x: interval![0.0, 2.0],
+    ///     y: interval![0.0, 1.0],
+    ///     z: interval![0.0, 1.0],
+    /// };
+    ///
+    /// let grid = VoxelGrid::from_densities([2, 1, 1], bounds, vec![0.25, 1.0]);
+    ///
+    /// assert_eq!(grid.get([1, 0, 0]), (1.0, 0.0));
+    /// ```
+    #[must_use]
+    pub fn from_densities(dims: [usize; 3], bounds: Aabb, densities: Vec<f64>) -> Self {
+        let grid = Self::new(dims, bounds);
+        assert_eq!(
+            densities.len(),
+            grid.density.len(),
+            "There must be one density per voxel"
+        );
+        Self {
+            density: densities,
+            ..grid
+        }
+    }
+
+    /// Load a [`VoxelGrid`] of `dims` voxels spanning `bounds` from a raw
+    /// file of little-endian 32-bit float densities, in the order of
+    /// [`VoxelGrid::from_densities()`], as exported by most simulation tools.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or isn't the right size for `dims`.
+    ///
+    /// # Panics
+    ///
+    /// If any of the dimensions are `0`.
+    pub fn load_raw<P: AsRef<Path>>(path: P, dims: [usize; 3], bounds: Aabb) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .with_context(|| format!("Couldn't read density grid {}", path.display()))?;
+        let expected = dims.iter().product::<usize>() * size_of::<f32>();
+        if bytes.len() != expected {
+            bail!(
+                "Density grid {} is {} bytes, but {dims:?} voxels need {expected}",
+                path.display(),
+                bytes.len()
+            );
+        }
+
+        let densities = bytes
+            .chunks_exact(size_of::<f32>())
+            .map(|b| f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+            .collect();
+        Ok(Self::from_densities(dims, bounds, densities))
+    }
+
+    /// The number of voxels along each axis.
+    #[must_use]
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    /// The [`Aabb`] the grid spans.
+    #[must_use]
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// The size of a single voxel along each axis.
+    #[must_use]
+    pub fn voxel_size(&self) -> Vec3 {
+        #[expect(clippy::cast_precision_loss)]
+        let size = vec3![
+            self.bounds.x.size() / self.dims[0] as f64,
+            self.bounds.y.size() / self.dims[1] as f64,
+            self.bounds.z.size() / self.dims[2] as f64
+        ];
+        size
+    }
+
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        assert!(
+            x < self.dims[0] && y < self.dims[1] && z < self.dims[2],
+            "Voxel out of bounds"
+        );
+        (z * self.dims[1] + y) * self.dims[0] + x
+    }
+
+    /// Set the density and temperature of the voxel at `idx`.
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is out of bounds.
+    pub fn set(&mut self, idx: [usize; 3], density: f64, temperature: f64) {
+        let i = self.index(idx);
+        self.density[i] = density;
+        self.temperature[i] = temperature;
+    }
+
+    /// Get the density and temperature of the voxel at `idx`.
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is out of bounds.
+    #[must_use]
+    pub fn get(&self, idx: [usize; 3]) -> (f64, f64) {
+        let i = self.index(idx);
+        (self.density[i], self.temperature[i])
+    }
+
+    /// Whether any voxel in the index range `lo..hi` has a non-zero density.
+    fn any_density(&self, lo: [usize; 3], hi: [usize; 3]) -> bool {
+        (lo[2]..hi[2]).any(|z| {
+            (lo[1]..hi[1]).any(|y| {
+                let row = self.index([lo[0], y, z]);
+                self.density[row..row + hi[0] - lo[0]]
+                    .iter()
+                    .any(|&d| d > 0.0)
+            })
+        })
+    }
+
+    /// The largest density in the grid.
+    #[must_use]
+    pub fn max_density(&self) -> f64 {
+        self.density.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// Sample `(density, temperature)` at `p` with trilinear interpolation
+    /// between voxel centres. Points outside the bounds are empty.
+    #[must_use]
+    pub fn sample(&self, p: Vec3) -> (f64, f64) {
+        if !self.bounds.contains_point(p) {
+            return (0.0, 0.0);
+        }
+
+        let size = self.voxel_size();
+        let mut lo = [0; 3];
+        let mut hi = [0; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            let min = [self.bounds.x, self.bounds.y, self.bounds.z][axis].min;
+            #[expect(clippy::cast_precision_loss)]
+            let max_idx = (self.dims[axis] - 1) as f64;
+            let g = ((p[axis] - min) / size[axis] - 0.5).clamp(0.0, max_idx);
+
+            #[expect(clippy::cast_possible_truncation)]
+            #[expect(clippy::cast_sign_loss)]
+            let i = g.floor() as usize;
+            lo[axis] = i;
+            hi[axis] = (i + 1).min(self.dims[axis] - 1);
+            #[expect(clippy::cast_precision_loss)]
+            let f = g - i as f64;
+            frac[axis] = f;
+        }
+
+        let mut density = 0.0;
+        let mut temperature = 0.0;
+        for corner in 0..8 {
+            let mut idx = [0; 3];
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                if corner >> axis & 1 == 1 {
+                    idx[axis] = hi[axis];
+                    weight *= frac[axis];
+                } else {
+                    idx[axis] = lo[axis];
+                    weight *= 1.0 - frac[axis];
+                }
+            }
+            if weight > 0.0 {
+                let i = self.index(idx);
+                density += self.density[i] * weight;
+                temperature += self.temperature[i] * weight;
+            }
+        }
+
+        (density, temperature)
+    }
+}
+
+/// The linear colour of a blackbody at `kelvin`, with a brightness following
+/// the Stefan-Boltzmann law relative to 1500K. Uses Tanner Helland's fit of
+/// the blackbody chromaticity, which is reasonable from 1000K to 40000K.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::volume::blackbody;
+///
+/// let ember = blackbody(1200.0);
+/// let flame = blackbody(1800.0);
+///
+/// // hotter is brighter, and embers are red
+/// assert!(flame.length() > ember.length());
+/// assert!(ember[0] > ember[1] && ember[1] > ember[2]);
+/// assert_eq!(blackbody(0.0).length(), 0.0);
+/// ```
+#[must_use]
+pub fn blackbody(kelvin: f64) -> Vec3 {
+    if kelvin <= 0.0 {
+        return Vec3::default();
+    }
+
+    let t = kelvin / 100.0;
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_727_446 * (t - 60.0).powf(-0.133_204_759_2)
+    };
+    let g = if t <= 66.0 {
+        99.470_802_586_1 * t.ln() - 161.119_568_166_1
+    } else {
+        288.122_169_528_3 * (t - 60.0).powf(-0.075_514_849_2)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_731_223_1 * (t - 10.0).ln() - 305.044_792_730_7
+    };
+
+    // the fit is of gamma-encoded sRGB, so square back to linear
+    let to_linear = |c: f64| (c.clamp(0.0, 255.0) / 255.0).powi(2);
+    let brightness = (kelvin / BLACKBODY_REFERENCE).powi(4);
+    vec3![to_linear(r), to_linear(g), to_linear(b)] * brightness
+}
+
+/// How a [`FireVolume`] finds the parts of a [ray](Ray) worth marching.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VolumeAcceleration {
+    /// March the whole way through the grid's bounds. Best for dense grids.
+    #[default]
+    None,
+    /// Build a sparse voxel octree of the non-empty voxels and only march
+    /// through its leaves, skipping empty space hierarchically. Best for large,
+    /// mostly empty grids such as explosions.
+    Octree,
+}
+
+/// A node of a sparse voxel octree, only stored where the [`VoxelGrid`] isn't
+/// empty. Leaves have no children.
+#[derive(Debug, Clone)]
+struct OctreeNode {
+    bounds: Aabb,
+    children: Vec<OctreeNode>,
+}
+
+impl OctreeNode {
+    /// Build the node covering the voxels `lo..hi` of `grid`, or [`None`] if
+    /// they are empty.
+    fn build(grid: &VoxelGrid, lo: [usize; 3], hi: [usize; 3]) -> Option<Self> {
+        // interpolation reaches half a voxel past non-empty voxels, so
+        // include the neighbours and pad the bounds to match
+        let padded_lo = lo.map(|i| i.saturating_sub(1));
+        let padded_hi = [0, 1, 2].map(|axis| (hi[axis] + 1).min(grid.dims[axis]));
+        if !grid.any_density(padded_lo, padded_hi) {
+            return None;
+        }
+
+        let size = grid.voxel_size();
+        let axes = [grid.bounds.x, grid.bounds.y, grid.bounds.z];
+        #[expect(clippy::cast_precision_loss)]
+        let [x, y, z] = [0, 1, 2].map(|axis| {
+            let min = axes[axis].min + (lo[axis] as f64 - 0.5) * size[axis];
+            let max = axes[axis].min + (hi[axis] as f64 + 0.5) * size[axis];
+            Interval::new(min, max)
+        });
+        let bounds = Aabb { x, y, z };
+
+        if (0..3).all(|axis| hi[axis] - lo[axis] <= OCTREE_LEAF_VOXELS) {
+            return Some(Self {
+                bounds,
+                children: vec![],
+            });
+        }
+
+        let mid = [0, 1, 2].map(|axis| (lo[axis] + hi[axis]).div_ceil(2));
+        let mut children = vec![];
+        for octant in 0..8 {
+            let mut child_lo = [0; 3];
+            let mut child_hi = [0; 3];
+            for axis in 0..3 {
+                (child_lo[axis], child_hi[axis]) = if octant >> axis & 1 == 1 {
+                    (mid[axis], hi[axis])
+                } else {
+                    (lo[axis], mid[axis])
+                };
+            }
+            if (0..3).all(|axis| child_lo[axis] < child_hi[axis]) {
+                children.extend(Self::build(grid, child_lo, child_hi));
+            }
+        }
+
+        Some(Self { bounds, children })
+    }
+
+    /// Move the node and its children by `offset`.
+    fn translate(&mut self, offset: Vec3) {
+        self.bounds = self.bounds.translated(offset);
+        for child in &mut self.children {
+            child.translate(offset);
+        }
+    }
+
+    /// Push the ranges of `t` in `start..end` where `r` passes through this
+    /// node's leaves.
+    fn spans(&self, r: &Ray, start: f64, end: f64, out: &mut Vec<(f64, f64)>) {
+        let Some(span) = self.bounds.ray_interval(r) else {
+            return;
+        };
+        let (a, b) = (span.min.max(start), span.max.min(end));
+        if a >= b {
+            return;
+        }
+
+        if self.children.is_empty() {
+            out.push((a, b));
+        } else {
+            for child in &self.children {
+                child.spans(r, a, b, out);
+            }
+        }
+    }
+}
+
+/// A [`SmallRng`] seeded from the origin, direction and time of `r`, for the
+/// random steps taken through a medium. Hits can't reach the path's own RNG,
+/// and this keeps them reproducible where a thread RNG wouldn't be.
+fn ray_rng(r: &Ray) -> SmallRng {
+    let bits = |v: Vec3| v.e.map(|x| x.to_bits().cast_signed());
+    let noise = Noise::new(r.time.to_bits()).stream("volume");
+    SmallRng::seed_from_u64(Noise::new(noise.hash(bits(r.origin))).hash(bits(r.direction)))
+}
+
+/// The roots of the octree `acceleration` asks for over `grid`: [`None`]
+/// without acceleration, and none at all for an empty grid, so
+/// [`octree_spans()`] skips it entirely.
+fn build_octree(grid: &VoxelGrid, acceleration: VolumeAcceleration) -> Option<Vec<OctreeNode>> {
+    match acceleration {
+        VolumeAcceleration::None => None,
+        VolumeAcceleration::Octree => Some(
+            OctreeNode::build(grid, [0; 3], grid.dims)
+                .into_iter()
+                .collect(),
+        ),
+    }
+}
+
+/// The ranges of `t` in `start..end`, in order, where `r` may pass through
+/// the leaves of `octree`, or the whole range without one.
+fn octree_spans(octree: Option<&[OctreeNode]>, r: &Ray, start: f64, end: f64) -> Vec<(f64, f64)> {
+    let Some(roots) = octree else {
+        return vec![(start, end)];
+    };
+
+    let mut spans = vec![];
+    for root in roots {
+        root.spans(r, start, end, &mut spans);
+    }
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // merge touching leaves so they're marched as one
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(spans.len());
+    for (a, b) in spans {
+        match merged.last_mut() {
+            Some(last) if a <= last.1 => last.1 = last.1.max(b),
+            _ => merged.push((a, b)),
+        }
+    }
+    merged
+}
+
+/// A phase function [material](Material) scattering light equally in every
+/// direction, whichever way it arrived, the simplest model of a medium.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{hit::HitRecord, material::Material, ray, ray::Ray, vec3, vec3::Vec3, volume::Isotropic};
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let haze = Isotropic::new(vec3![0.8, 0.8, 0.8]);
+/// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+/// let rec = HitRecord {
+///     p: vec3![0.0, 0.0, 0.0],
+///     norm: vec3![0.0, 0.0, 1.0],
+///     mat: &haze,
+///     t: 2.0,
+///     front_face: true,
+///     u: 0.0,
+///     v: 0.0,
+///     object: None,
+///     layer: None,
+///     tangent: None,
+/// };
+///
+/// // as much light goes backwards as forwards
+/// let mut rng = SmallRng::seed_from_u64(1);
+/// let runs = 10000;
+/// let forwards = (0..runs)
+///     .filter(|_| haze.scatter(&r, &rec, Some(&mut rng)).0.direction[2] < 0.0)
+///     .count();
+/// assert!((forwards as f64 / runs as f64 - 0.5).abs() < 0.02);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Isotropic {
+    albedo: Vec3,
+}
+
+impl Isotropic {
+    /// Create a new [`Isotropic`] phase function, scattering `albedo` of the
+    /// light at each collision.
+    #[must_use]
+    pub fn new(albedo: Vec3) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let dir = Vec3::random_unit(rng.unwrap());
+        (ray![rec.p, dir, r_in.time], self.albedo)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
+
+/// The Henyey-Greenstein phase function [material](Material), scattering
+/// light mostly onwards or mostly back the way it came by its asymmetry `g`.
+/// Water droplets in clouds and fog scatter strongly forwards, with `g`
+/// around `0.85`, which gives the bright silver linings of clouds lit from
+/// behind and their darker faces lit from the front, where isotropic
+/// scattering looks like uniform haze. Smoke is nearer `0.3`, and `0.0` is
+/// [`Isotropic`].
+///
+/// Directions are importance sampled from the phase function exactly, so
+/// each scatter carries the albedo unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{hit::HitRecord, material::Material, ray, ray::Ray, vec3, vec3::Vec3, volume::HenyeyGreenstein};
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let cloud = HenyeyGreenstein::new(vec3![0.9, 0.9, 0.9], 0.85);
+///
+/// // a normalised distribution over the sphere, peaked forwards
+/// assert!(cloud.phase(1.0) > 100.0 * cloud.phase(-1.0));
+/// let steps = 100000;
+/// let integral = (0..steps)
+///     .map(|i| {
+///         let cos_theta = -1.0 + 2.0 * (f64::from(i) + 0.5) / f64::from(steps);
+///         cloud.phase(cos_theta) * 2.0 * std::f64::consts::PI * 2.0 / f64::from(steps)
+///     })
+///     .sum::<f64>();
+/// assert!((integral - 1.0).abs() < 1e-3);
+///
+/// let r = ray!(vec3![0.0, 0.0, 2.0], vec3![0.0, 0.0, -1.0]);
+/// let rec = HitRecord {
+///     p: vec3![0.0, 0.0, 0.0],
+///     norm: vec3![0.0, 0.0, 1.0],
+///     mat: &cloud,
+///     t: 2.0,
+///     front_face: true,
+///     u: 0.0,
+///     v: 0.0,
+///     object: None,
+///     layer: None,
+///     tangent: None,
+/// };
+///
+/// // the mean cosine of the scattered directions is `g`
+/// let mut rng = SmallRng::seed_from_u64(1);
+/// let runs = 20000;
+/// let mean = (0..runs)
+///     .map(|_| -cloud.scatter(&r, &rec, Some(&mut rng)).0.direction.unit()[2])
+///     .sum::<f64>()
+///     / f64::from(runs);
+/// assert!((mean - 0.85).abs() < 0.01);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HenyeyGreenstein {
+    albedo: Vec3,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    /// Create a new [`HenyeyGreenstein`] phase function with asymmetry `g`,
+    /// the mean cosine of the angle light is scattered by, scattering
+    /// `albedo` of the light at each collision. Positive `g` scatters
+    /// forwards and negative `g` backwards.
+    ///
+    /// # Panics
+    ///
+    /// If `g` isn't strictly between `-1.0` and `1.0`.
+    #[must_use]
+    pub fn new(albedo: Vec3, g: f64) -> Self {
+        assert!(
+            g > -1.0 && g < 1.0,
+            "Phase function asymmetry must be between -1 and 1"
+        );
+        Self { albedo, g }
+    }
+
+    /// The density of light scattered at an angle with cosine `cos_theta` to
+    /// the direction it was travelling, per steradian.
+    #[must_use]
+    pub fn phase(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    /// A random direction light travelling along `dir` is scattered into,
+    /// distributed by the phase function.
+    fn sample(&self, dir: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let g = self.g;
+        let xi = rng.random::<f64>();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 + g - 2.0 * g * xi);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.random::<f64>();
+
+        let w = dir.unit();
+        let (u, v) = w.orthonormal_basis();
+        u * (sin_theta * phi.cos()) + v * (sin_theta * phi.sin()) + w * cos_theta
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        let dir = self.sample(r_in.direction, rng.unwrap());
+        (ray![rec.p, dir, r_in.time], self.albedo)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(*self)
+    }
+}
+
+/// The part of a [`FireVolume`] its [material](Material)s need to integrate
+/// emission along a [ray](Ray).
+#[derive(Debug, Clone)]
+struct FireMedium {
+    grid: VoxelGrid,
+    density_scale: f64,
+    emission_scale: f64,
+    step: f64,
+    octree: Option<Vec<OctreeNode>>,
+}
+
+impl FireMedium {
+    /// The ranges of `t` in `start..end`, in order, where `r` may pass through
+    /// non-empty voxels.
+    fn spans(&self, r: &Ray, start: f64, end: f64) -> Vec<(f64, f64)> {
+        octree_spans(self.octree.as_deref(), r, start, end)
+    }
+
+    /// The emitted radiance per unit length at `p`.
+    fn emission_at(&self, p: Vec3) -> Vec3 {
+        let (density, temperature) = self.grid.sample(p);
+        blackbody(temperature) * (density * self.density_scale * self.emission_scale)
+    }
+
+    /// Integrate emission along `r` from where it enters the grid up to `t`.
+    fn emission_until(&self, r: &Ray, t: f64) -> Vec3 {
+        let Some(span) = self.grid.bounds.ray_interval(r) else {
+            return Vec3::default();
+        };
+        let dir_len = r.direction.length();
+
+        let mut total = Vec3::default();
+        for (start, end) in self.spans(r, span.min.max(0.0), t.min(span.max)) {
+            let mut t0 = start;
+            while t0 < end {
+                let t1 = (t0 + self.step / dir_len).min(end);
+                let mid = r.at(0.5 * (t0 + t1));
+                total += self.emission_at(mid) * ((t1 - t0) * dir_len);
+                t0 = t1;
+            }
+        }
+        total
+    }
+}
+
+/// Scatters by the phase function where a [ray](Ray) collided with the
+/// medium.
+#[derive(Debug, Clone)]
+struct FireScatter {
+    medium: Arc<FireMedium>,
+    phase: HenyeyGreenstein,
+}
+
+impl Material for FireScatter {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        self.phase.scatter(r_in, rec, rng)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.medium.emission_until(r_in, rec.t)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(self.clone())
+    }
+}
+
+/// Lets a [ray](Ray) that made it through the medium carry on unchanged.
+#[derive(Debug, Clone)]
+struct FirePassThrough {
+    medium: Arc<FireMedium>,
+}
+
+impl Material for FirePassThrough {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _rng: Option<&mut SmallRng>) -> (Ray, Vec3) {
+        (ray![rec.p, r_in.direction, r_in.time], vec3![1.0, 1.0, 1.0])
+    }
+
+    fn is_diffuse(&self) -> bool {
+        false
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.medium.emission_until(r_in, rec.t)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material> {
+        Box::new(self.clone())
+    }
+}
+
+/// A heterogeneous volume made from a [`VoxelGrid`] whose voxels both scatter
+/// light and emit [blackbody] light by their temperature, such as fire or an
+/// explosion.
+///
+/// A [ray](Ray) entering the volume is marched in steps of half a voxel to
+/// pick a random collision distance by its optical depth. It either scatters
+/// there with the volume's albedo, isotropically unless given an
+/// [anisotropy](FireVolume::with_anisotropy()), or passes out of the far
+/// side unchanged.
+/// Either way, the emission along the way up to that point is added.
+///
+/// Solid objects inside the volume hide the emission in front of them, and
+/// the grid's bounds shouldn't lie exactly on a surface (such as a floor), as
+/// rays leaving the volume there start on the surface and miss it.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{aabb::Aabb, interval, interval::Interval, vec3, vec3::Vec3, volume::{FireVolume, VoxelGrid}};
+///
+/// let bounds = Aabb {
+///     x: interval![-1.0, 1.0],
+///     y: interval![0.0, 3.0],
+///     z: interval![-1.0, 1.0],
+/// };
+///
+/// // a column of flame that cools as it rises
+/// let grid = VoxelGrid::from_fn([16, 24, 16], bounds, |p| {
+///     let r = (p[0] * p[0] + p[2] * p[2]).sqrt();
+///     let density = (1.0 - r).max(0.0);
+///     (density, 2000.0 - 400.0 * p[1])
+/// });
+///
+/// let fire = FireVolume::new(grid, vec3![0.2, 0.2, 0.2], 2.0, 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FireVolume {
+    medium: Arc<FireMedium>,
+    scatter: FireScatter,
+    pass_through: FirePassThrough,
+}
+
+impl FireVolume {
+    /// Create a new [`FireVolume`] from `grid`. Densities are multiplied by
+    /// `density_scale` to get the extinction per unit length, and scattering
+    /// events reflect `albedo` of the light. The emission per unit length is
+    /// the extinction times `emission_scale` times the [`blackbody()`]
+    /// colour of the temperature.
+    ///
+    /// # Panics
+    ///
+    /// If `density_scale` or `emission_scale` is negative.
+    #[must_use]
+    pub fn new(grid: VoxelGrid, albedo: Vec3, density_scale: f64, emission_scale: f64) -> Self {
+        assert!(density_scale >= 0.0, "Density scale must not be negative");
+        assert!(emission_scale >= 0.0, "Emission scale must not be negative");
+
+        let size = grid.voxel_size();
+        let step = 0.5 * size[0].min(size[1]).min(size[2]);
+        Self::from_medium(
+            FireMedium {
+                grid,
+                density_scale,
+                emission_scale,
+                step,
+                octree: None,
+            },
+            HenyeyGreenstein::new(albedo, 0.0),
+        )
+    }
+
+    /// Scatter light by a [`HenyeyGreenstein`] phase function with asymmetry
+    /// `g` rather than isotropically, forwards for positive `g`, as smoke
+    /// does with `g` around `0.3`.
+    ///
+    /// # Panics
+    ///
+    /// If `g` isn't strictly between `-1.0` and `1.0`.
+    #[must_use]
+    pub fn with_anisotropy(self, g: f64) -> Self {
+        let phase = HenyeyGreenstein::new(self.scatter.phase.albedo, g);
+        Self {
+            scatter: FireScatter {
+                phase,
+                ..self.scatter
+            },
+            ..self
+        }
+    }
+
+    /// Set how the [`FireVolume`] skips empty space, see
+    /// [`VolumeAcceleration`]. Builds the octree if needed, which for an
+    /// empty grid has no nodes, so rays aren't marched through it at all.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     aabb::Aabb, interval, interval::Interval, vec3, vec3::Vec3,
+    ///     volume::{FireVolume, VolumeAcceleration, VoxelGrid},
+    /// };
+    ///
+    /// let bounds = Aabb {
+    ///     x: interval![-10.0, 10.0],
+    ///     y: interval![-10.0, 10.0],
+    ///     z: interval![-10.0, 10.0],
+    /// };
+    ///
+    /// // a small fireball in a big, mostly empty cache
+    /// let grid = VoxelGrid::from_fn([128, 128, 128], bounds, |p| {
+    ///     if p.length() < 1.0 { (1.0, 1800.0) } else { (0.0, 0.0) }
+    /// });
+    ///
+    /// let fire = FireVolume::new(grid, vec3![0.2, 0.2, 0.2], 2.0, 1.0)
+    ///     .with_acceleration(VolumeAcceleration::Octree);
+    /// ```
+    #[must_use]
+    pub fn with_acceleration(self, acceleration: VolumeAcceleration) -> Self {
+        let Self {
+            medium,
+            scatter,
+            pass_through,
+        } = self;
+        let phase = scatter.phase;
+        // let go of the materials' references so the medium isn't copied
+        drop((scatter, pass_through));
+        let mut medium = Arc::unwrap_or_clone(medium);
+
+        medium.octree = build_octree(&medium.grid, acceleration);
+
+        Self::from_medium(medium, phase)
+    }
+
+    /// Share `medium` between the [`FireVolume`] and its materials.
+    fn from_medium(medium: FireMedium, phase: HenyeyGreenstein) -> Self {
+        let medium = Arc::new(medium);
+        Self {
+            scatter: FireScatter {
+                medium: Arc::clone(&medium),
+                phase,
+            },
+            pass_through: FirePassThrough {
+                medium: Arc::clone(&medium),
+            },
+            medium,
+        }
+    }
+}
+
+impl Hittable for FireVolume {
+    /// Marches through the grid to find where the [ray](Ray) collides with
+    /// the medium, at a random optical depth seeded from the ray, returning a
+    /// hit there, or at the far side of the grid if the ray passed through.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let span = self.medium.grid.bounds.ray_interval(r)?;
+        let start = span.min.max(ray_t.min);
+        let end = span.max.min(ray_t.max);
+        if start >= end {
+            return None;
+        }
+
+        let dir_len = r.direction.length();
+        let target = -(1.0 - ray_rng(r).random::<f64>()).ln();
+        let mut depth = 0.0;
+        let mut collision = None;
+        'spans: for (span_start, span_end) in self.medium.spans(r, start, end) {
+            let mut t0 = span_start;
+            while t0 < span_end {
+                let t1 = (t0 + self.medium.step / dir_len).min(span_end);
+                let (density, _) = self.medium.grid.sample(r.at(0.5 * (t0 + t1)));
+                let sigma = density * self.medium.density_scale;
+                let segment = sigma * (t1 - t0) * dir_len;
+
+                if depth + segment >= target {
+                    // land partway through the step
+                    collision = Some(t0 + (target - depth) / (sigma * dir_len));
+                    break 'spans;
+                }
+                depth += segment;
+                t0 = t1;
+            }
+        }
+
+        let (t, mat): (f64, &dyn Material) = match collision {
+            Some(t) => (t, &self.scatter),
+            None if span.max <= ray_t.max => (span.max, &self.pass_through),
+            None => return None,
+        };
+
+        Some(HitRecord {
+            p: r.at(t),
+            norm: -r.direction.unit(),
+            mat,
+            t,
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+            object: None,
+            layer: None,
+            tangent: None,
+        })
+    }
+
+    /// Returns the bounds of the [`VoxelGrid`].
+    fn bound(&self) -> Aabb {
+        self.medium.grid.bounds
+    }
+
+    /// Moves the [`VoxelGrid`]. The grid is shared with the volume's
+    /// materials, so this makes a new copy of it.
+    fn translate(&mut self, offset: Vec3) -> bool {
+        let mut medium = (*self.medium).clone();
+        medium.grid.bounds = medium.grid.bounds.translated(offset);
+        for node in medium.octree.iter_mut().flatten() {
+            node.translate(offset);
+        }
+        *self = Self::from_medium(medium, self.scatter.phase);
+        true
+    }
+}
+
+/// A heterogeneous volume made from the densities of a [`VoxelGrid`] which
+/// scatters light but doesn't emit any, such as smoke or clouds.
+///
+/// Collisions are found by delta tracking: the [ray](Ray) takes exponentially
+/// distributed steps as though the whole grid were as dense as its densest
+/// voxel, and each tentative collision is kept with the probability of the
+/// real density there over that. Unlike marching, this is unbiased however
+/// fine the detail in the grid. A ray that doesn't collide passes through
+/// without a hit, so objects inside and behind the volume are seen as usual.
+/// Collisions scatter isotropically unless the volume is given an
+/// [anisotropy](DensityVolume::with_anisotropy()), which clouds need to look
+/// right.
+/// Shadow queries such as [`Scene::transmittance()`](crate::scene::Scene::transmittance())
+/// see collisions as opaque, which averages out to the right transmittance,
+/// while [`DensityVolume::transmittance()`] estimates it with less noise by
+/// ratio tracking.
+///
+/// Tracking takes more steps the denser the densest voxel is, so a few
+/// very dense voxels in a thin grid are best clamped first.
+///
+/// # Example
+///
+/// ```rust
+/// use rustrace::{aabb::Aabb, interval, interval::Interval, vec3, vec3::Vec3, volume::{DensityVolume, VoxelGrid}};
+///
+/// let bounds = Aabb {
+///     x: interval![-2.0, 2.0],
+///     y: interval![-1.0, 1.0],
+///     z: interval![-2.0, 2.0],
+/// };
+///
+/// // a puffy cloud that thins towards its edges
+/// let grid = VoxelGrid::from_fn([64, 32, 64], bounds, |p| {
+///     let r = (0.25 * p[0] * p[0] + p[1] * p[1] + 0.25 * p[2] * p[2]).sqrt();
+///     ((1.0 - r).max(0.0), 0.0)
+/// });
+///
+/// let cloud = DensityVolume::new(grid, vec3![0.9, 0.9, 0.9], 4.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DensityVolume {
+    grid: VoxelGrid,
+    density_scale: f64,
+    majorant: f64,
+    octree: Option<Vec<OctreeNode>>,
+    scatter: HenyeyGreenstein,
+}
+
+impl DensityVolume {
+    /// Create a new [`DensityVolume`] from the densities of `grid`, ignoring
+    /// its temperatures. Densities are multiplied by `density_scale` to get
+    /// the extinction per unit length, and scattering events reflect `albedo`
+    /// of the light.
+    ///
+    /// # Panics
+    ///
+    /// If `density_scale` is negative.
+    #[must_use]
+    pub fn new(grid: VoxelGrid, albedo: Vec3, density_scale: f64) -> Self {
+        assert!(density_scale >= 0.0, "Density scale must not be negative");
+
+        let majorant = grid.max_density() * density_scale;
+        Self {
+            grid,
+            density_scale,
+            majorant,
+            octree: None,
+            scatter: HenyeyGreenstein::new(albedo, 0.0),
+        }
+    }
+
+    /// Scatter light by a [`HenyeyGreenstein`] phase function with asymmetry
+    /// `g` rather than isotropically, forwards for positive `g`, as clouds do
+    /// with `g` around `0.85`.
+    ///
+    /// # Panics
+    ///
+    /// If `g` isn't strictly between `-1.0` and `1.0`.
+    #[must_use]
+    pub fn with_anisotropy(self, g: f64) -> Self {
+        Self {
+            scatter: HenyeyGreenstein::new(self.scatter.albedo, g),
+            ..self
+        }
+    }
+
+    /// Set how the [`DensityVolume`] skips empty space, see
+    /// [`VolumeAcceleration`]. Builds the octree if needed.
+    #[must_use]
+    pub fn with_acceleration(mut self, acceleration: VolumeAcceleration) -> Self {
+        self.octree = build_octree(&self.grid, acceleration);
+        self
+    }
+
+    /// The fraction of light passing through the volume along `r` within
+    /// `ray_t`, estimated by ratio tracking. Each is a single random estimate
+    /// seeded from `r`, so the same ray always gives the same one, which
+    /// averages out to the true transmittance over many rays.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustrace::{
+    ///     aabb::Aabb, interval, interval::Interval, ray, ray::Ray, vec3, vec3::Vec3,
+    ///     volume::{DensityVolume, VoxelGrid},
+    /// };
+    ///
+    /// let bounds = Aabb {
+    ///     x: interval![0.0, 2.0],
+    ///     y: interval![0.0, 2.0],
+    ///     z: interval![0.0, 2.0],
+    /// };
+    ///
+    /// let grid = VoxelGrid::from_fn([4, 4, 4], bounds, |_| (1.0, 0.0));
+    /// let fog = DensityVolume::new(grid, vec3![1.0, 1.0, 1.0], 0.5);
+    ///
+    /// // straight through 2 units of extinction 0.5
+    /// let r = ray!(vec3![-1.0, 1.0, 1.0], vec3![1.0, 0.0, 0.0]);
+    /// let once = fog.transmittance(&r, interval![0.0, f64::INFINITY]);
+    /// assert_eq!(once, fog.transmittance(&r, interval![0.0, f64::INFINITY]));
+    ///
+    /// let runs = 4000;
+    /// let mean = (0..runs)
+    ///     .map(|i| {
+    ///         let y = 0.1 + 1.8 * f64::from(i) / f64::from(runs);
+    ///         let r = ray!(vec3![-1.0, y, 1.0], vec3![1.0, 0.0, 0.0]);
+    ///         fog.transmittance(&r, interval![0.0, f64::INFINITY])
+    ///     })
+    ///     .sum::<f64>()
+    ///     / f64::from(runs);
+    ///
+    /// assert!((mean - (-1.0f64).exp()).abs() < 0.02);
+    /// ```
+    #[must_use]
+    pub fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let mut rng = ray_rng(r);
+        let dir_len = r.direction.length();
+
+        let mut through = 1.0;
+        for (start, end) in self.spans(r, ray_t) {
+            let mut t = start;
+            loop {
+                t -= (1.0 - rng.random::<f64>()).ln() / (self.majorant * dir_len);
+                if t >= end {
+                    break;
+                }
+                through *= 1.0 - self.sigma(r.at(t)) / self.majorant;
+            }
+        }
+        through
+    }
+
+    /// The extinction per unit length at `p`.
+    fn sigma(&self, p: Vec3) -> f64 {
+        self.grid.sample(p).0 * self.density_scale
+    }
+
+    /// The ranges of `t` within `ray_t`, in order, where `r` may pass through
+    /// non-empty voxels. Empty if the whole grid is.
+    fn spans(&self, r: &Ray, ray_t: Interval) -> Vec<(f64, f64)> {
+        if self.majorant <= 0.0 {
+            return vec![];
+        }
+        let Some(span) = self.grid.bounds.ray_interval(r) else {
+            return vec![];
+        };
+        let start = span.min.max(ray_t.min);
+        let end = span.max.min(ray_t.max);
+        if start >= end {
+            return vec![];
+        }
+        octree_spans(self.octree.as_deref(), r, start, end)
+    }
+}
+
+impl Hittable for DensityVolume {
+    /// Delta tracks through the grid to find where the [ray](Ray) collides
+    /// with the medium, returning a hit there, or [`None`] if it passes
+    /// through.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let mut rng = ray_rng(r);
+        let dir_len = r.direction.length();
+
+        for (start, end) in self.spans(r, ray_t) {
+            let mut t = start;
+            loop {
+                t -= (1.0 - rng.random::<f64>()).ln() / (self.majorant * dir_len);
+                if t >= end {
+                    break;
+                }
+                if rng.random::<f64>() * self.majorant < self.sigma(r.at(t)) {
+                    return Some(HitRecord {
+                        p: r.at(t),
+                        norm: -r.direction.unit(),
+                        mat: &self.scatter,
+                        t,
+                        front_face: true,
+                        u: 0.0,
+                        v: 0.0,
+                        object: None,
+                        layer: None,
+                        tangent: None,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the bounds of the [`VoxelGrid`].
+    fn bound(&self) -> Aabb {
+        self.grid.bounds
+    }
+
+    /// Moves the [`VoxelGrid`].
+    fn translate(&mut self, offset: Vec3) -> bool {
+        self.grid.bounds = self.grid.bounds.translated(offset);
+        for node in self.octree.iter_mut().flatten() {
+            node.translate(offset);
+        }
+        true
+    }
+}